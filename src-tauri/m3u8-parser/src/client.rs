@@ -0,0 +1,241 @@
+use std::sync::Arc;
+
+use crate::error::M3u8Error;
+use crate::fetcher::{check_content_type, check_playlist_start, FetchFuture, HttpFetcher, DEFAULT_MAX_PLAYLIST_BYTES};
+use crate::parser::parse_playlist;
+use crate::types::ParsedPlaylist;
+
+/// Redirects [`M3u8Parser::new`] follows before giving up with
+/// [`M3u8Error::TooManyRedirects`].
+pub const MAX_REDIRECTS: usize = 5;
+
+// A redirect loop gets its own error; everything else is a network error
+fn request_error(e: reqwest::Error) -> M3u8Error {
+    if e.is_redirect() {
+        M3u8Error::TooManyRedirects
+    } else {
+        M3u8Error::NetworkError(e.to_string())
+    }
+}
+
+/// [`HttpFetcher`] over a reqwest client.
+#[derive(Debug, Clone)]
+pub struct ReqwestFetcher {
+    client: reqwest::Client,
+}
+
+impl ReqwestFetcher {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl HttpFetcher for ReqwestFetcher {
+    fn get<'a>(&'a self, url: &'a str, range: Option<(u64, u64)>) -> FetchFuture<'a, Vec<u8>> {
+        Box::pin(async move {
+            let mut request = self.client.get(url);
+            if let Some((offset, length)) = range {
+                request = request.header(reqwest::header::RANGE, format!("bytes={}-{}", offset, offset + length.max(1) - 1));
+            }
+            let response = request
+                .send()
+                .await
+                .map_err(request_error)?;
+
+            if !response.status().is_success() {
+                return Err(M3u8Error::HttpStatus(response.status().as_u16()));
+            }
+
+            let bytes = response
+                .bytes()
+                .await
+                .map_err(|e| M3u8Error::NetworkError(e.to_string()))?;
+            Ok(bytes.to_vec())
+        })
+    }
+
+    fn content_length<'a>(&'a self, url: &'a str) -> FetchFuture<'a, Option<u64>> {
+        Box::pin(async move {
+            let response = self.client
+                .head(url)
+                .send()
+                .await
+                .map_err(request_error)?;
+
+            if !response.status().is_success() {
+                return Err(M3u8Error::HttpStatus(response.status().as_u16()));
+            }
+
+            // Read the header directly; the body of a HEAD response is always empty
+            Ok(response
+                .headers()
+                .get(reqwest::header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok()))
+        })
+    }
+
+    fn get_playlist<'a>(&'a self, url: &'a str, max_bytes: u64) -> FetchFuture<'a, Vec<u8>> {
+        Box::pin(async move {
+            let mut response = self.client
+                .get(url)
+                .send()
+                .await
+                .map_err(request_error)?;
+
+            if !response.status().is_success() {
+                return Err(M3u8Error::HttpStatus(response.status().as_u16()));
+            }
+            if let Some(content_type) = response.headers().get(reqwest::header::CONTENT_TYPE) {
+                check_content_type(content_type.to_str().unwrap_or_default())?;
+            }
+            if response.content_length().is_some_and(|length| length > max_bytes) {
+                return Err(M3u8Error::TooLarge(max_bytes));
+            }
+
+            // Read chunk by chunk, so a segment without a Content-Length is
+            // turned away after its first bytes rather than read whole
+            let mut body = Vec::new();
+            while let Some(chunk) = response.chunk().await.map_err(request_error)? {
+                body.extend_from_slice(&chunk);
+                if body.len() as u64 > max_bytes {
+                    return Err(M3u8Error::TooLarge(max_bytes));
+                }
+                check_playlist_start(&body)?;
+            }
+            Ok(body)
+        })
+    }
+}
+
+/// Fetches playlists and parses them. Fetches go through an
+/// [`HttpFetcher`], reqwest unless [`M3u8Parser::with_fetcher`] gives
+/// another one.
+#[derive(Clone)]
+pub struct M3u8Parser {
+    fetcher: Arc<dyn HttpFetcher>,
+    max_playlist_bytes: u64,
+}
+
+impl Default for M3u8Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl M3u8Parser {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .user_agent("m3u8-mcp/0.1.0")
+            .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+            .build()
+            .unwrap_or_default();
+        
+        Self::with_client(client)
+    }
+
+    /// Uses a preconfigured HTTP client, for example one that goes through
+    /// a proxy.
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self::with_fetcher(Arc::new(ReqwestFetcher::new(client)))
+    }
+
+    /// Fetches through `fetcher`, for example a
+    /// [`FixtureFetcher`](crate::FixtureFetcher) serving local files.
+    pub fn with_fetcher(fetcher: Arc<dyn HttpFetcher>) -> Self {
+        Self {
+            fetcher,
+            max_playlist_bytes: DEFAULT_MAX_PLAYLIST_BYTES,
+        }
+    }
+
+    /// Limits the size of fetched playlists, [`DEFAULT_MAX_PLAYLIST_BYTES`]
+    /// unless set.
+    pub fn with_max_playlist_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_playlist_bytes = max_bytes;
+        self
+    }
+
+    /// The fetcher this parser uses, to fetch segments and keys the same way.
+    pub fn fetcher(&self) -> Arc<dyn HttpFetcher> {
+        self.fetcher.clone()
+    }
+
+    pub async fn parse_url(&self, url: &str) -> Result<ParsedPlaylist, M3u8Error> {
+        self.fetch_and_parse(url).await.map(|(_, playlist)| playlist)
+    }
+
+    /// Like [`parse_url`](Self::parse_url), but also returns the fetched
+    /// text, so callers that need it too fetch the playlist only once.
+    pub async fn fetch_and_parse(&self, url: &str) -> Result<(String, ParsedPlaylist), M3u8Error> {
+        // Validate URL
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(M3u8Error::InvalidUrl("URL must start with http:// or https://".to_string()));
+        }
+
+        // Fetch the playlist content
+        let content = self.fetch_playlist(url).await?;
+        
+        // Parse the playlist
+        let playlist = self.parse_content(&content, url)?;
+        Ok((content, playlist))
+    }
+
+    /// Fetches the raw playlist text. Responses over the size limit, served
+    /// as media or images, or not starting like playlist text fail without
+    /// being read whole.
+    pub async fn fetch_playlist(&self, url: &str) -> Result<String, M3u8Error> {
+        let body = self.fetcher.get_playlist(url, self.max_playlist_bytes).await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// Returns the `Content-Length` of a resource from a HEAD request, if the
+    /// server reports one.
+    pub async fn head_content_length(&self, url: &str) -> Result<Option<u64>, M3u8Error> {
+        self.fetcher.content_length(url).await
+    }
+
+    /// Parses playlist text that has already been fetched. See
+    /// [`parse_playlist`].
+    pub fn parse_content(&self, content: &str, base_url: &str) -> Result<ParsedPlaylist, M3u8Error> {
+        parse_playlist(content, base_url)
+    }
+
+    pub async fn extract_segments(&self, url: &str, base_url: Option<&str>) -> Result<Vec<String>, M3u8Error> {
+        // Fetch the playlist content
+        let content = self.fetch_playlist(url).await?;
+        
+        // Use the provided base_url or the URL itself
+        let base = base_url.unwrap_or(url);
+        
+        // Parse the playlist
+        let playlist = self.parse_content(&content, base)?;
+        
+        match playlist {
+            ParsedPlaylist::Media { segments, .. } => {
+                // Extract segment URLs from media playlist
+                Ok(segments.into_iter().map(|s| s.uri).collect())
+            }
+            ParsedPlaylist::Master { variants, .. } => {
+                // For master playlist, we need to fetch one of the variant playlists
+                // Let's use the first variant for simplicity
+                if let Some(first_variant) = variants.first() {
+                    // Fetch and parse the variant playlist directly
+                    let variant_url = &first_variant.uri;
+                    let variant_content = self.fetch_playlist(variant_url).await?;
+                    let variant_playlist = self.parse_content(&variant_content, variant_url)?;
+                    
+                    match variant_playlist {
+                        ParsedPlaylist::Media { segments, .. } => {
+                            Ok(segments.into_iter().map(|s| s.uri).collect())
+                        }
+                        _ => Ok(Vec::new())
+                    }
+                } else {
+                    Ok(Vec::new())
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use serde::{Deserialize, Serialize};
+
+use crate::parser::{parse_attribute_list, resolve_uri};
+use crate::variables::substitute_variables;
+
+/// A timed event from an `#EXT-X-DATERANGE` tag, such as a programme
+/// boundary or an ad break.
+///
+/// Dates are kept as the ISO 8601 strings from the playlist; callers map
+/// them onto the media timeline with [`first_program_date_time`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DateRange {
+    pub id: String,
+    pub class: Option<String>,
+    pub start_date: String,
+    pub end_date: Option<String>,
+    /// `DURATION` in seconds.
+    pub duration: Option<f64>,
+    /// `PLANNED-DURATION` in seconds, for ranges whose end is not known yet.
+    pub planned_duration: Option<f64>,
+    /// Client-defined `X-` attributes, in playlist order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub client_attributes: Vec<(String, String)>,
+}
+
+impl DateRange {
+    /// Value of a client-defined attribute such as `X-TITLE`.
+    pub fn client_attribute(&self, name: &str) -> Option<&str> {
+        self.client_attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Collects the `#EXT-X-DATERANGE` tags of a media playlist. Tags without
+/// the required `ID` or `START-DATE` attributes are skipped.
+pub fn parse_date_ranges(content: &str) -> Vec<DateRange> {
+    let mut ranges = Vec::new();
+
+    for line in content.lines() {
+        let Some(list) = line.trim().strip_prefix("#EXT-X-DATERANGE:") else {
+            continue;
+        };
+
+        let mut id = None;
+        let mut start_date = None;
+        let mut range = DateRange {
+            id: String::new(),
+            class: None,
+            start_date: String::new(),
+            end_date: None,
+            duration: None,
+            planned_duration: None,
+            client_attributes: Vec::new(),
+        };
+
+        for (name, value) in parse_attribute_list(list) {
+            match name.as_str() {
+                "ID" => id = Some(value),
+                "CLASS" => range.class = Some(value),
+                "START-DATE" => start_date = Some(value),
+                "END-DATE" => range.end_date = Some(value),
+                "DURATION" => range.duration = value.parse().ok(),
+                "PLANNED-DURATION" => range.planned_duration = value.parse().ok(),
+                _ if name.starts_with("X-") => range.client_attributes.push((name, value)),
+                _ => {}
+            }
+        }
+
+        if let (Some(id), Some(start_date)) = (id, start_date) {
+            range.id = id;
+            range.start_date = start_date;
+            ranges.push(range);
+        }
+    }
+
+    ranges
+}
+
+/// `CLASS` of the date ranges that schedule HLS interstitials.
+pub const INTERSTITIAL_CLASS: &str = "com.apple.hls.interstitial";
+
+/// An HLS interstitial: separate content, such as an ad or a promo, that
+/// players switch to at a point of the primary timeline. Interstitials are
+/// `#EXT-X-DATERANGE` tags of class [`INTERSTITIAL_CLASS`]; their content is
+/// not among the segments of the playlist.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Interstitial {
+    pub id: String,
+    pub start_date: String,
+    /// `DURATION` in seconds.
+    pub duration: Option<f64>,
+    pub planned_duration: Option<f64>,
+    /// `X-ASSET-URI`, resolved: the playlist of a single asset.
+    pub asset_uri: Option<String>,
+    /// `X-ASSET-LIST`, resolved: a JSON document listing the assets.
+    pub asset_list: Option<String>,
+    /// `X-RESUME-OFFSET`: seconds of primary content after the start date at
+    /// which playback resumes. When absent, the duration of the assets.
+    pub resume_offset: Option<f64>,
+    /// `X-PLAYOUT-LIMIT`: the most seconds of the assets to play.
+    pub playout_limit: Option<f64>,
+    /// `X-CUE` triggers: `PRE`, `POST` and `ONCE`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cue: Vec<String>,
+    /// `X-RESTRICT` navigation limits: `SKIP` and `JUMP`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub restrict: Vec<String>,
+    /// `X-SNAP` alignment to segment boundaries: `IN` and `OUT`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub snap: Vec<String>,
+}
+
+impl Interstitial {
+    /// Played before the primary content starts.
+    pub fn is_pre_roll(&self) -> bool {
+        self.cue.iter().any(|cue| cue == "PRE")
+    }
+
+    /// Played after the primary content ends.
+    pub fn is_post_roll(&self) -> bool {
+        self.cue.iter().any(|cue| cue == "POST")
+    }
+}
+
+fn enumerated_list(value: Option<&str>) -> Vec<String> {
+    value
+        .map(|list| {
+            list.split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Collects the interstitials of a media playlist, with asset URIs resolved
+/// against `base_url`. Date ranges of an interstitial without an asset are
+/// skipped.
+pub fn parse_interstitials(content: &str, base_url: &str) -> Vec<Interstitial> {
+    parse_date_ranges(&substitute_variables(content, base_url))
+        .into_iter()
+        .filter(|range| range.class.as_deref() == Some(INTERSTITIAL_CLASS))
+        .filter_map(|range| {
+            let number = |name: &str| {
+                range
+                    .client_attribute(name)
+                    .and_then(|value| value.parse().ok())
+            };
+            let uri = |name: &str| {
+                range
+                    .client_attribute(name)
+                    .map(|uri| resolve_uri(uri, base_url))
+            };
+            let interstitial = Interstitial {
+                id: range.id.clone(),
+                start_date: range.start_date.clone(),
+                duration: range.duration,
+                planned_duration: range.planned_duration,
+                asset_uri: uri("X-ASSET-URI"),
+                asset_list: uri("X-ASSET-LIST"),
+                resume_offset: number("X-RESUME-OFFSET"),
+                playout_limit: number("X-PLAYOUT-LIMIT"),
+                cue: enumerated_list(range.client_attribute("X-CUE")),
+                restrict: enumerated_list(range.client_attribute("X-RESTRICT")),
+                snap: enumerated_list(range.client_attribute("X-SNAP")),
+            };
+            (interstitial.asset_uri.is_some() || interstitial.asset_list.is_some())
+                .then_some(interstitial)
+        })
+        .collect()
+}
+
+/// The first `#EXT-X-PROGRAM-DATE-TIME` of a media playlist, which anchors
+/// the first segment to wall-clock time.
+pub fn first_program_date_time(content: &str) -> Option<String> {
+    content
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("#EXT-X-PROGRAM-DATE-TIME:"))
+        .map(|date| date.trim().to_string())
+}
+
+/// The `#EXT-X-PROGRAM-DATE-TIME` written directly before each segment of a
+/// media playlist, in segment order. Segments without their own tag are
+/// `None`; their time follows from the previous segment and its duration.
+pub fn segment_program_date_times(content: &str) -> Vec<Option<String>> {
+    let mut dates = Vec::new();
+    let mut pending: Option<String> = None;
+    let mut lines = content.lines().map(str::trim).peekable();
+
+    while let Some(line) = lines.next() {
+        if let Some(date) = line.strip_prefix("#EXT-X-PROGRAM-DATE-TIME:") {
+            pending = Some(date.trim().to_string());
+        } else if line.starts_with("#EXTINF:") {
+            // Same rule as the segment parser: the URI is on the next line
+            if lines.peek().is_some_and(|next| !next.starts_with('#')) {
+                dates.push(pending.take());
+            }
+        }
+    }
+
+    dates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date_ranges() {
+        let content = "#EXTM3U\n\
+            #EXT-X-PROGRAM-DATE-TIME:2024-05-01T10:00:00.000Z\n\
+            #EXT-X-DATERANGE:ID=\"news\",CLASS=\"chapter\",START-DATE=\"2024-05-01T10:05:00Z\",DURATION=600,X-TITLE=\"News, weather\"\n\
+            #EXT-X-DATERANGE:ID=\"broken\",DURATION=10\n\
+            #EXTINF:6,\n\
+            seg0.ts\n";
+
+        let ranges = parse_date_ranges(content);
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].id, "news");
+        assert_eq!(ranges[0].class.as_deref(), Some("chapter"));
+        assert_eq!(ranges[0].duration, Some(600.0));
+        assert_eq!(ranges[0].client_attribute("X-TITLE"), Some("News, weather"));
+        assert_eq!(first_program_date_time(content).as_deref(), Some("2024-05-01T10:00:00.000Z"));
+    }
+
+    #[test]
+    fn test_parse_interstitials() {
+        let content = "#EXTM3U\n\
+            #EXT-X-PROGRAM-DATE-TIME:2024-05-01T10:00:00.000Z\n\
+            #EXT-X-DATERANGE:ID=\"ad1\",CLASS=\"com.apple.hls.interstitial\",START-DATE=\"2024-05-01T10:00:30Z\",DURATION=15,X-ASSET-URI=\"ads/ad1.m3u8\",X-RESUME-OFFSET=0,X-RESTRICT=\"SKIP,JUMP\"\n\
+            #EXT-X-DATERANGE:ID=\"pre\",CLASS=\"com.apple.hls.interstitial\",START-DATE=\"2024-05-01T10:00:00Z\",X-ASSET-LIST=\"/list.json\",X-CUE=\"PRE,ONCE\"\n\
+            #EXT-X-DATERANGE:ID=\"chapter\",START-DATE=\"2024-05-01T10:00:00Z\",X-ASSET-URI=\"ignored.m3u8\"\n\
+            #EXTINF:6,\n\
+            seg0.ts\n";
+
+        let interstitials = parse_interstitials(content, "https://example.com/live/index.m3u8");
+        assert_eq!(interstitials.len(), 2);
+        assert_eq!(
+            interstitials[0].asset_uri.as_deref(),
+            Some("https://example.com/live/ads/ad1.m3u8")
+        );
+        assert_eq!(interstitials[0].resume_offset, Some(0.0));
+        assert_eq!(interstitials[0].restrict, vec!["SKIP", "JUMP"]);
+        assert!(!interstitials[0].is_pre_roll());
+        assert_eq!(
+            interstitials[1].asset_list.as_deref(),
+            Some("https://example.com/list.json")
+        );
+        assert!(interstitials[1].is_pre_roll());
+    }
+
+    #[test]
+    fn test_segment_program_date_times() {
+        let content = "#EXTM3U\n\
+            #EXT-X-PROGRAM-DATE-TIME:2024-05-01T10:00:00.000Z\n\
+            #EXTINF:6,\n\
+            seg0.ts\n\
+            #EXTINF:6,\n\
+            seg1.ts\n\
+            #EXT-X-DISCONTINUITY\n\
+            #EXT-X-PROGRAM-DATE-TIME:2024-05-01T11:00:00.000Z\n\
+            #EXTINF:6,\n\
+            seg2.ts\n";
+
+        assert_eq!(segment_program_date_times(content), vec![
+            Some("2024-05-01T10:00:00.000Z".to_string()),
+            None,
+            Some("2024-05-01T11:00:00.000Z".to_string()),
+        ]);
+    }
+}
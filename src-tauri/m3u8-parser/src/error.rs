@@ -0,0 +1,35 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum M3u8Error {
+    NetworkError(String),
+    ParseError(String),
+    InvalidUrl(String),
+    /// The server answered with a non-success HTTP status code.
+    HttpStatus(u16),
+    /// The response was larger than the limit, in bytes, it was read with.
+    TooLarge(u64),
+    /// The server redirected more times than allowed.
+    TooManyRedirects,
+    /// The response is not playlist text, such as a media segment or an
+    /// HTML page; the message says why.
+    NotAPlaylist(String),
+}
+
+impl fmt::Display for M3u8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            M3u8Error::NetworkError(msg) => write!(f, "Network error: {}", msg),
+            M3u8Error::ParseError(msg) => write!(f, "Parse error: {}", msg),
+            M3u8Error::InvalidUrl(msg) => write!(f, "Invalid URL: {}", msg),
+            M3u8Error::HttpStatus(code) => write!(f, "HTTP error: {}", code),
+            M3u8Error::TooLarge(limit) => write!(f, "Response is larger than {} bytes", limit),
+            M3u8Error::TooManyRedirects => write!(f, "Too many redirects"),
+            M3u8Error::NotAPlaylist(msg) => write!(f, "Not a playlist: {}", msg),
+        }
+    }
+}
+
+impl Error for M3u8Error {}
@@ -0,0 +1,212 @@
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use crate::error::M3u8Error;
+
+/// Most bytes [`HttpFetcher::get_playlist`] reads by default. Real
+/// playlists stay far below it; a segment passed by mistake does not.
+pub const DEFAULT_MAX_PLAYLIST_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Future returned by [`HttpFetcher`] methods.
+pub type FetchFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, M3u8Error>> + Send + 'a>>;
+
+/// Where playlists, keys and segments come from. [`M3u8Parser`] fetches
+/// through one, so a test or an offline demo can hand it
+/// [`FixtureFetcher`] (or its own implementation) instead of the network.
+///
+/// Implementations report a non-success status as
+/// [`M3u8Error::HttpStatus`] and transport failures as
+/// [`M3u8Error::NetworkError`].
+///
+/// [`M3u8Parser`]: crate::M3u8Parser
+pub trait HttpFetcher: Send + Sync {
+    /// Body of `url`, or `length` bytes of it from `offset` when `range` is
+    /// `Some((offset, length))`.
+    fn get<'a>(&'a self, url: &'a str, range: Option<(u64, u64)>) -> FetchFuture<'a, Vec<u8>>;
+
+    /// Size of the resource at `url`, if known without fetching it.
+    fn content_length<'a>(&'a self, url: &'a str) -> FetchFuture<'a, Option<u64>>;
+
+    /// Body of the playlist at `url`, failing with [`M3u8Error::TooLarge`]
+    /// past `max_bytes` and with [`M3u8Error::NotAPlaylist`] when the body
+    /// does not start like playlist text. The default reads the whole body
+    /// first; implementations that can should stop reading early.
+    fn get_playlist<'a>(&'a self, url: &'a str, max_bytes: u64) -> FetchFuture<'a, Vec<u8>> {
+        Box::pin(async move {
+            let body = self.get(url, None).await?;
+            if body.len() as u64 > max_bytes {
+                return Err(M3u8Error::TooLarge(max_bytes));
+            }
+            check_playlist_start(&body)?;
+            Ok(body)
+        })
+    }
+}
+
+/// Rejects a `Content-Type` that no playlist is served with, such as
+/// `video/mp2t` or `image/png`. Playlist types like `audio/mpegurl` pass, as
+/// do generic ones like `application/octet-stream`.
+pub fn check_content_type(content_type: &str) -> Result<(), M3u8Error> {
+    let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    if essence.contains("mpegurl") || essence.contains("m3u") {
+        return Ok(());
+    }
+    if ["video/", "audio/", "image/", "font/"].iter().any(|prefix| essence.starts_with(prefix)) {
+        return Err(M3u8Error::NotAPlaylist(format!("served as {}", essence)));
+    }
+    Ok(())
+}
+
+/// Checks the first bytes of a body: after an optional byte order mark
+/// and whitespace, playlist text starts with `#`. Bytes that are all
+/// whitespace so far pass, since more may follow.
+pub fn check_playlist_start(body: &[u8]) -> Result<(), M3u8Error> {
+    let body = body.strip_prefix(b"\xef\xbb\xbf").unwrap_or(body);
+    match body.iter().find(|byte| !byte.is_ascii_whitespace()) {
+        None | Some(b'#') => Ok(()),
+        Some(b'<') => Err(M3u8Error::NotAPlaylist("the response is HTML or XML".to_string())),
+        Some(_) if body.iter().take(512).any(|&byte| byte == 0) => {
+            Err(M3u8Error::NotAPlaylist("the response is binary".to_string()))
+        }
+        Some(_) => Err(M3u8Error::NotAPlaylist("the response does not start with a tag".to_string())),
+    }
+}
+
+/// Serves URLs from files under a directory, without any network access.
+/// `https://cdn.example.com/live/index.m3u8?token=1` is read from
+/// `<root>/cdn.example.com/live/index.m3u8`; the scheme, port and query are
+/// ignored. Missing files answer 404.
+#[derive(Debug, Clone)]
+pub struct FixtureFetcher {
+    root: PathBuf,
+}
+
+impl FixtureFetcher {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// File serving `url`, or `None` for URLs that are not http(s) or would
+    /// leave the root.
+    pub fn path_for(&self, url: &str) -> Option<PathBuf> {
+        let parsed = url::Url::parse(url).ok()?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return None;
+        }
+        let mut path = self.root.join(parsed.host_str()?);
+        for part in parsed.path_segments()? {
+            let part = percent_decode(part);
+            if part.is_empty() || part == "." || part == ".." || part.contains(['/', '\\']) {
+                continue;
+            }
+            path.push(part);
+        }
+        Some(path)
+    }
+
+    fn read(&self, url: &str) -> Result<Vec<u8>, M3u8Error> {
+        let path = self.path_for(url)
+            .ok_or_else(|| M3u8Error::InvalidUrl(format!("No fixture can serve {}", url)))?;
+        read_file(&path)
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, M3u8Error> {
+    std::fs::read(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => M3u8Error::HttpStatus(404),
+        _ => M3u8Error::NetworkError(format!("Cannot read fixture {}: {}", path.display(), e)),
+    })
+}
+
+/// `length` bytes of `body` from `offset`, as a server answers a range
+/// request; 416 when the range starts past the end.
+fn slice_range(mut body: Vec<u8>, range: Option<(u64, u64)>) -> Result<Vec<u8>, M3u8Error> {
+    let Some((offset, length)) = range else {
+        return Ok(body);
+    };
+    let start = usize::try_from(offset).unwrap_or(usize::MAX);
+    if start >= body.len() {
+        return Err(M3u8Error::HttpStatus(416));
+    }
+    let end = start.saturating_add(usize::try_from(length).unwrap_or(usize::MAX)).min(body.len());
+    body.truncate(end);
+    Ok(body.split_off(start))
+}
+
+impl HttpFetcher for FixtureFetcher {
+    fn get<'a>(&'a self, url: &'a str, range: Option<(u64, u64)>) -> FetchFuture<'a, Vec<u8>> {
+        Box::pin(async move { slice_range(self.read(url)?, range) })
+    }
+
+    fn content_length<'a>(&'a self, url: &'a str) -> FetchFuture<'a, Option<u64>> {
+        Box::pin(async move { Ok(Some(self.read(url)?.len() as u64)) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_path() {
+        let fetcher = FixtureFetcher::new("/fixtures");
+        assert_eq!(
+            fetcher.path_for("https://cdn.example.com:8443/live/index.m3u8?token=1").unwrap(),
+            PathBuf::from("/fixtures/cdn.example.com/live/index.m3u8")
+        );
+        assert_eq!(
+            fetcher.path_for("http://cdn.example.com/a/..%2F..%2Fsecret").unwrap(),
+            PathBuf::from("/fixtures/cdn.example.com/a")
+        );
+        assert_eq!(
+            fetcher.path_for("https://cdn.example.com/my%20show/1.ts").unwrap(),
+            PathBuf::from("/fixtures/cdn.example.com/my show/1.ts")
+        );
+        assert!(fetcher.path_for("file:///etc/passwd").is_none());
+    }
+
+    #[test]
+    fn test_playlist_checks() {
+        assert!(check_content_type("application/vnd.apple.mpegurl").is_ok());
+        assert!(check_content_type("audio/x-mpegURL; charset=utf-8").is_ok());
+        assert!(check_content_type("application/octet-stream").is_ok());
+        assert!(matches!(check_content_type("video/MP2T"), Err(M3u8Error::NotAPlaylist(_))));
+
+        assert!(check_playlist_start(b"\xef\xbb\xbf  #EXTM3U").is_ok());
+        assert!(check_playlist_start(b"\n\n").is_ok());
+        assert!(check_playlist_start(b"<!DOCTYPE html>").is_err());
+        assert!(check_playlist_start(b"\x47\x40\x00\x10").is_err());
+    }
+
+    #[test]
+    fn test_slice_range() {
+        let body = b"0123456789".to_vec();
+        assert_eq!(slice_range(body.clone(), None).unwrap(), body);
+        assert_eq!(slice_range(body.clone(), Some((2, 3))).unwrap(), b"234");
+        assert_eq!(slice_range(body.clone(), Some((8, 10))).unwrap(), b"89");
+        assert!(matches!(slice_range(body, Some((10, 1))), Err(M3u8Error::HttpStatus(416))));
+    }
+}
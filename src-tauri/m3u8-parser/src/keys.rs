@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::parser::{parse_attribute_list, resolve_uri};
+use crate::variables::substitute_variables;
+
+/// Encryption in effect for a segment, from the last `#EXT-X-KEY` tag
+/// before it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SegmentKey {
+    /// `AES-128`, `SAMPLE-AES`, ... (`NONE` tags clear the key instead).
+    pub method: String,
+    /// Key URI, resolved against the playlist URL.
+    pub uri: Option<String>,
+    /// `IV` as written in the playlist (`0x` followed by 32 hex digits).
+    pub iv: Option<String>,
+    pub key_format: Option<String>,
+}
+
+/// Returns the key of each segment of a media playlist, in segment order.
+/// `None` entries are unencrypted segments.
+pub fn segment_keys(content: &str, base_url: &str) -> Vec<Option<SegmentKey>> {
+    let content = substitute_variables(content, base_url);
+    let mut keys = Vec::new();
+    let mut current: Option<SegmentKey> = None;
+    let mut lines = content.lines().map(str::trim).peekable();
+
+    while let Some(line) = lines.next() {
+        if let Some(list) = line.strip_prefix("#EXT-X-KEY:") {
+            let mut key = SegmentKey {
+                method: String::new(),
+                uri: None,
+                iv: None,
+                key_format: None,
+            };
+            for (name, value) in parse_attribute_list(list) {
+                match name.as_str() {
+                    "METHOD" => key.method = value,
+                    "URI" => key.uri = Some(resolve_uri(&value, base_url)),
+                    "IV" => key.iv = Some(value),
+                    "KEYFORMAT" => key.key_format = Some(value),
+                    _ => {}
+                }
+            }
+            current = if key.method == "NONE" { None } else { Some(key) };
+        } else if line.starts_with("#EXTINF:") {
+            // Same rule as the segment parser: the URI is on the next line
+            if lines.peek().is_some_and(|next| !next.starts_with('#')) {
+                keys.push(current.clone());
+            }
+        }
+    }
+
+    keys
+}
+
+/// The `#EXT-X-MEDIA-SEQUENCE` of a media playlist (0 when absent), which
+/// numbers the first segment.
+pub fn media_sequence(content: &str) -> u64 {
+    content
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("#EXT-X-MEDIA-SEQUENCE:"))
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_keys() {
+        let content = "#EXTM3U\n\
+            #EXT-X-MEDIA-SEQUENCE:7\n\
+            #EXTINF:6,\n\
+            clear.ts\n\
+            #EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\",IV=0x0000000000000000000000000000000A\n\
+            #EXTINF:6,\n\
+            enc.ts\n\
+            #EXT-X-KEY:METHOD=NONE\n\
+            #EXTINF:6,\n\
+            clear2.ts\n";
+
+        let keys = segment_keys(content, "https://example.com/live/index.m3u8");
+        assert_eq!(keys.len(), 3);
+        assert!(keys[0].is_none());
+        let key = keys[1].as_ref().unwrap();
+        assert_eq!(key.method, "AES-128");
+        assert_eq!(key.uri.as_deref(), Some("https://example.com/live/key.bin"));
+        assert!(keys[2].is_none());
+        assert_eq!(media_sequence(content), 7);
+    }
+}
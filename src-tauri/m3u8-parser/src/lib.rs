@@ -0,0 +1,69 @@
+//! HLS (m3u8) playlist parsing.
+//!
+//! This crate is the playlist layer of m3u8 MCP, split out so it can be used
+//! without Tauri. Parsing works on playlist text via [`parse_playlist`]; the
+//! `fetch` feature (enabled by default) adds [`M3u8Parser`], which downloads
+//! playlists (by default with reqwest, or through any [`HttpFetcher`] such
+//! as [`FixtureFetcher`]) before parsing them. [`write_master_playlist`]
+//! goes the other way and writes a master playlist.
+//!
+//! ```
+//! use m3u8_mcp_parser::{parse_playlist, ParsedPlaylist};
+//!
+//! let text = "#EXTM3U\n#EXT-X-TARGETDURATION:10\n#EXTINF:9.5,\nseg0.ts\n";
+//! let playlist = parse_playlist(text, "https://example.com/live/index.m3u8").unwrap();
+//! match playlist {
+//!     ParsedPlaylist::Media { segments, .. } => {
+//!         assert_eq!(segments[0].uri, "https://example.com/live/seg0.ts");
+//!     }
+//!     ParsedPlaylist::Master { .. } => unreachable!(),
+//! }
+//! ```
+//!
+//! # Stability
+//!
+//! Everything re-exported from the crate root is public API and follows
+//! semver: breaking changes to these types or functions only happen in a
+//! minor release while the crate is `0.x`, and in a major release after 1.0.
+//! [`M3u8Error`] is `#[non_exhaustive]`, so new error kinds may be added in
+//! patch releases. The serde representation of [`ParsedPlaylist`] is part of
+//! the stable surface since the MCP tools return it verbatim.
+
+mod daterange;
+mod error;
+mod fetcher;
+mod keys;
+mod metadata;
+mod parser;
+mod start;
+mod stats;
+mod summary;
+mod types;
+mod variables;
+mod writer;
+
+#[cfg(feature = "fetch")]
+mod client;
+
+pub use daterange::{
+    first_program_date_time, parse_date_ranges, parse_interstitials, segment_program_date_times,
+    DateRange, Interstitial, INTERSTITIAL_CLASS,
+};
+pub use error::M3u8Error;
+pub use fetcher::{
+    check_content_type, check_playlist_start, FetchFuture, FixtureFetcher, HttpFetcher, DEFAULT_MAX_PLAYLIST_BYTES,
+};
+pub use keys::{media_sequence, segment_keys, SegmentKey};
+pub use metadata::{id3_frames, parse_session_data, stream_title, Id3Frame, SessionData};
+pub use parser::{
+    parse_playlist, parse_playlist_tolerant, parse_renditions, resolve_uri, variant_attributes, ParseIssue,
+};
+pub use start::{parse_start, playback_start, PlaybackStart, StartTag, LIVE_EDGE_TARGET_DURATIONS};
+pub use stats::{byte_range_length, media_stats, BitratePoint, PlaylistStats};
+pub use summary::{summarize_playlist, PlaylistSummary, VariantSummary};
+pub use types::{ParsedPlaylist, Segment, Variant};
+pub use variables::{substitute_variables, substitute_variables_with};
+pub use writer::{write_master_playlist, MasterPlaylist, Rendition, VariantStream};
+
+#[cfg(feature = "fetch")]
+pub use client::{M3u8Parser, ReqwestFetcher, MAX_REDIRECTS};
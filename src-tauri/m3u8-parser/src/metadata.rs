@@ -0,0 +1,309 @@
+use serde::{Deserialize, Serialize};
+
+use crate::parser::parse_attribute_list;
+
+/// An `#EXT-X-SESSION-DATA` entry of a master playlist. Each entry carries
+/// either an inline `VALUE` or a `URI` of a JSON document.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SessionData {
+    pub data_id: String,
+    pub value: Option<String>,
+    pub uri: Option<String>,
+    pub language: Option<String>,
+}
+
+/// Collects the `#EXT-X-SESSION-DATA` tags of a master playlist. Tags
+/// without the required `DATA-ID` are skipped.
+pub fn parse_session_data(content: &str) -> Vec<SessionData> {
+    let mut entries = Vec::new();
+
+    for line in content.lines() {
+        let Some(list) = line.trim().strip_prefix("#EXT-X-SESSION-DATA:") else {
+            continue;
+        };
+
+        let mut entry = SessionData {
+            data_id: String::new(),
+            value: None,
+            uri: None,
+            language: None,
+        };
+        for (name, value) in parse_attribute_list(list) {
+            match name.as_str() {
+                "DATA-ID" => entry.data_id = value,
+                "VALUE" => entry.value = Some(value),
+                "URI" => entry.uri = Some(value),
+                "LANGUAGE" => entry.language = Some(value),
+                _ => {}
+            }
+        }
+
+        if !entry.data_id.is_empty() {
+            entries.push(entry);
+        }
+    }
+
+    entries
+}
+
+/// A text frame of an ID3v2 tag: a `T***` frame such as `TIT2` (title) or
+/// `TPE1` (artist), or a `TXXX` frame with its description.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Id3Frame {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub text: String,
+}
+
+const TS_PACKET_SIZE: usize = 188;
+const TS_SYNC_BYTE: u8 = 0x47;
+
+fn is_transport_stream(data: &[u8]) -> bool {
+    data.len() >= TS_PACKET_SIZE * 2 && data[0] == TS_SYNC_BYTE && data[TS_PACKET_SIZE] == TS_SYNC_BYTE
+}
+
+/// Payloads of an MPEG-TS buffer, concatenated per PID. Timed metadata
+/// travels in PES packets of its own stream and may span TS packets.
+fn ts_payloads(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut streams: Vec<(u16, Vec<u8>)> = Vec::new();
+
+    for packet in data.chunks_exact(TS_PACKET_SIZE) {
+        if packet[0] != TS_SYNC_BYTE {
+            break;
+        }
+        let pid = (((packet[1] & 0x1f) as u16) << 8) | packet[2] as u16;
+        let adaptation = (packet[3] >> 4) & 0x3;
+        let mut start = 4;
+        if adaptation & 0x2 != 0 {
+            start += 1 + packet[4] as usize;
+        }
+        if adaptation & 0x1 == 0 || start >= TS_PACKET_SIZE {
+            continue;
+        }
+
+        match streams.iter_mut().find(|(id, _)| *id == pid) {
+            Some((_, payload)) => payload.extend_from_slice(&packet[start..]),
+            None => streams.push((pid, packet[start..].to_vec())),
+        }
+    }
+
+    streams.into_iter().map(|(_, payload)| payload).collect()
+}
+
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |size, &b| (size << 7) | (b & 0x7f) as usize)
+}
+
+fn big_endian(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |size, &b| (size << 8) | b as usize)
+}
+
+/// Decodes ID3 text in one of its four encodings: ISO-8859-1, UTF-16 with
+/// a byte order mark, UTF-16BE or UTF-8.
+fn decode_text(encoding: u8, bytes: &[u8]) -> String {
+    let text = match encoding {
+        1 | 2 => {
+            let (big, body) = match bytes {
+                [0xfe, 0xff, rest @ ..] => (true, rest),
+                [0xff, 0xfe, rest @ ..] => (false, rest),
+                _ => (encoding == 2, bytes),
+            };
+            let units: Vec<u16> = body
+                .chunks_exact(2)
+                .map(|c| if big { u16::from_be_bytes([c[0], c[1]]) } else { u16::from_le_bytes([c[0], c[1]]) })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        3 => String::from_utf8_lossy(bytes).into_owned(),
+        _ => bytes.iter().map(|&b| b as char).collect(),
+    };
+    text.trim_end_matches('\0').trim().to_string()
+}
+
+/// Splits `bytes` at the first string terminator of `encoding`: one zero
+/// byte, or two on a UTF-16 code unit boundary.
+fn split_terminated(encoding: u8, bytes: &[u8]) -> (&[u8], &[u8]) {
+    let end = if encoding == 1 || encoding == 2 {
+        (0..bytes.len().saturating_sub(1)).step_by(2).find(|&i| bytes[i] == 0 && bytes[i + 1] == 0).map(|i| (i, i + 2))
+    } else {
+        bytes.iter().position(|&b| b == 0).map(|i| (i, i + 1))
+    };
+    match end {
+        Some((end, next)) => (&bytes[..end], &bytes[next..]),
+        None => (bytes, &[]),
+    }
+}
+
+/// Text frames of the ID3v2.3 or 2.4 tag at the start of `data`, or None
+/// when no valid tag header is there.
+fn parse_tag(data: &[u8]) -> Option<Vec<Id3Frame>> {
+    if data.len() < 10 || &data[..3] != b"ID3" || !matches!(data[3], 3 | 4) || data[6..10].iter().any(|&b| b >= 0x80) {
+        return None;
+    }
+    let version = data[3];
+    let flags = data[5];
+    let end = (10 + syncsafe(&data[6..10])).min(data.len());
+
+    let mut pos = 10;
+    if flags & 0x40 != 0 && pos + 4 <= end {
+        // Extended header; its size excludes itself in 2.3 only
+        pos += if version == 4 { syncsafe(&data[pos..pos + 4]) } else { 4 + big_endian(&data[pos..pos + 4]) };
+    }
+
+    let mut frames = Vec::new();
+    while pos + 10 <= end {
+        let header = &data[pos..pos + 10];
+        if !header[..4].iter().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit()) {
+            // Padding or garbage ends the frame list
+            break;
+        }
+        let size = if version == 4 { syncsafe(&header[4..8]) } else { big_endian(&header[4..8]) };
+        let body_start = pos + 10;
+        let body_end = body_start + size;
+        if body_end > end {
+            break;
+        }
+        let id = String::from_utf8_lossy(&header[..4]).into_owned();
+        let body = &data[body_start..body_end];
+
+        if id.starts_with('T') && !body.is_empty() {
+            let encoding = body[0];
+            let (description, text) = if id == "TXXX" {
+                let (description, value) = split_terminated(encoding, &body[1..]);
+                (Some(decode_text(encoding, description)), decode_text(encoding, value))
+            } else {
+                (None, decode_text(encoding, &body[1..]))
+            };
+            if !text.is_empty() {
+                frames.push(Id3Frame { id, description, text });
+            }
+        }
+        pos = body_end;
+    }
+
+    Some(frames)
+}
+
+/// Text frames of the ID3v2 tags in a media segment: at its start for
+/// packed audio (AAC, MP3), or in a timed metadata stream for MPEG-TS.
+/// Other frames, such as `PRIV` timestamps, are left out.
+pub fn id3_frames(data: &[u8]) -> Vec<Id3Frame> {
+    let streams = if is_transport_stream(data) { ts_payloads(data) } else { vec![data.to_vec()] };
+
+    let mut frames = Vec::new();
+    for stream in &streams {
+        let mut pos = 0;
+        while let Some(offset) = stream[pos..].windows(3).position(|w| w == b"ID3") {
+            let start = pos + offset;
+            match parse_tag(&stream[start..]) {
+                Some(tag_frames) => {
+                    // Tags are often repeated; keep each frame once
+                    for frame in tag_frames {
+                        if !frames.contains(&frame) {
+                            frames.push(frame);
+                        }
+                    }
+                    pos = start + 10 + syncsafe(&stream[start + 6..start + 10]);
+                }
+                None => pos = start + 3,
+            }
+            if pos >= stream.len() {
+                break;
+            }
+        }
+    }
+    frames
+}
+
+/// The most human-readable title available: session data whose `DATA-ID`
+/// ends in `title` (such as `com.example.title`), then the ID3 `TIT2`
+/// frame, then a `TXXX` frame described as a title.
+pub fn stream_title(session_data: &[SessionData], id3: &[Id3Frame]) -> Option<String> {
+    let is_title = |name: &str| {
+        let name = name.to_ascii_lowercase();
+        name == "title" || name.ends_with(".title") || name.ends_with("-title") || name.ends_with("_title")
+    };
+
+    session_data
+        .iter()
+        .filter(|entry| is_title(&entry.data_id))
+        .find_map(|entry| entry.value.clone())
+        .or_else(|| id3.iter().find(|frame| frame.id == "TIT2").map(|frame| frame.text.clone()))
+        .or_else(|| {
+            id3.iter()
+                .find(|frame| frame.id == "TXXX" && frame.description.as_deref().is_some_and(is_title))
+                .map(|frame| frame.text.clone())
+        })
+        .filter(|title| !title.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id3_tag(frames: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (id, content) in frames {
+            body.extend_from_slice(id.as_bytes());
+            body.extend_from_slice(&(content.len() as u32).to_be_bytes());
+            body.extend_from_slice(&[0, 0]);
+            body.extend_from_slice(content);
+        }
+        let size = body.len();
+        let mut tag = b"ID3\x03\x00\x00".to_vec();
+        tag.extend([(size >> 21) as u8 & 0x7f, (size >> 14) as u8 & 0x7f, (size >> 7) as u8 & 0x7f, size as u8 & 0x7f]);
+        tag.extend(body);
+        tag
+    }
+
+    #[test]
+    fn test_session_data_title() {
+        let master = "#EXTM3U\n\
+            #EXT-X-SESSION-DATA:DATA-ID=\"com.example.title\",VALUE=\"Evening News Live\",LANGUAGE=\"en\"\n\
+            #EXT-X-SESSION-DATA:DATA-ID=\"com.example.lyrics\",URI=\"lyrics.json\"\n\
+            #EXT-X-SESSION-DATA:VALUE=\"no id\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000\nlow.m3u8\n";
+        let session = parse_session_data(master);
+        assert_eq!(session.len(), 2);
+        assert_eq!(session[1].uri.as_deref(), Some("lyrics.json"));
+        assert_eq!(stream_title(&session, &[]), Some("Evening News Live".to_string()));
+    }
+
+    #[test]
+    fn test_id3_frames() {
+        let mut utf16 = vec![1, 0xff, 0xfe];
+        utf16.extend("Nachrichten".encode_utf16().flat_map(|u| u.to_le_bytes()));
+        let tag = id3_tag(&[
+            ("PRIV", b"com.apple.streaming.transportStreamTimestamp\0\0\0\0\0\0\0\0\0".to_vec()),
+            ("TIT2", b"\x03Evening News Live".to_vec()),
+            ("TXXX", b"\x00program\0Nightly".to_vec()),
+            ("TALB", utf16),
+        ]);
+
+        // Packed audio: the tag precedes the audio frames
+        let mut aac = tag.clone();
+        aac.extend_from_slice(&[0xff, 0xf1, 0x50, 0x80]);
+        let frames = id3_frames(&aac);
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[1], Id3Frame { id: "TXXX".into(), description: Some("program".into()), text: "Nightly".into() });
+        assert_eq!(frames[2].text, "Nachrichten");
+        assert_eq!(stream_title(&[], &frames), Some("Evening News Live".to_string()));
+
+        // MPEG-TS: the tag is split over two packets of a metadata PID,
+        // with a video packet in between
+        let mut ts = Vec::new();
+        let mut pes = b"\x00\x00\x01\xbd\x00\x00\x84\x80\x05\x21\x00\x01\x00\x01".to_vec();
+        pes.extend(&tag);
+        for (i, chunk) in pes.chunks(184).enumerate() {
+            let mut packet = vec![TS_SYNC_BYTE, if i == 0 { 0x41 } else { 0x01 }, 0x02, 0x10];
+            packet.extend(chunk);
+            packet.resize(TS_PACKET_SIZE, 0xff);
+            ts.extend(packet);
+            let mut video = vec![TS_SYNC_BYTE, 0x01, 0x00, 0x10];
+            video.resize(TS_PACKET_SIZE, 0x00);
+            ts.extend(video);
+        }
+        assert_eq!(id3_frames(&ts), frames);
+    }
+}
@@ -0,0 +1,546 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::M3u8Error;
+use crate::types::{ParsedPlaylist, Segment, Variant};
+use crate::variables::{substitute_variables, substitute_variables_with};
+use crate::writer::Rendition;
+
+/// A syntax problem [`parse_playlist_tolerant`] worked around.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ParseIssue {
+    /// 1-based line of the playlist, 0 for the playlist as a whole.
+    pub line: usize,
+    pub message: String,
+}
+
+/// Parses playlist text without touching the network. Relative URIs are
+/// resolved against `base_url`.
+///
+/// A UTF-8 byte order mark, CRLF (or CR) line endings, blank lines and
+/// comments between a tag and its URI, and whitespace around tags are
+/// accepted. `#EXT-X-DEFINE` variables are substituted before URIs are
+/// resolved. Fails when the text does not start with `#EXTM3U`; see
+/// [`parse_playlist_tolerant`] for playlists that get that wrong too.
+pub fn parse_playlist(content: &str, base_url: &str) -> Result<ParsedPlaylist, M3u8Error> {
+    let content = normalize(content);
+    if !content.starts_with("#EXTM3U") {
+        return Err(M3u8Error::ParseError("Not a valid m3u8 file".to_string()));
+    }
+    let content = substitute_variables(&content, base_url);
+    Ok(parse_lines(&content, base_url, &mut Vec::new()))
+}
+
+/// Like [`parse_playlist`], but also parses playlists without the
+/// `#EXTM3U` header, and returns the syntax problems it recovered from: a
+/// missing header, `#EXTINF` or `#EXT-X-STREAM-INF` tags without a URI,
+/// URIs without an `#EXTINF`, unreadable durations, bandwidths and bitrates,
+/// and references to variables no `#EXT-X-DEFINE` declares. Only text with
+/// no playlist tag at all, such as an HTML page, is an error.
+pub fn parse_playlist_tolerant(content: &str, base_url: &str) -> Result<(ParsedPlaylist, Vec<ParseIssue>), M3u8Error> {
+    let content = normalize(content);
+    let mut issues = Vec::new();
+    if !content.starts_with("#EXTM3U") {
+        let has_tags = content.lines().any(|line| {
+            let line = line.trim_start();
+            line.starts_with("#EXTINF") || line.starts_with("#EXT-X-")
+        });
+        if !has_tags {
+            return Err(M3u8Error::ParseError("Not a valid m3u8 file".to_string()));
+        }
+        issues.push(issue(1, "Missing #EXTM3U header"));
+    }
+    let (content, undefined) = substitute_variables_with(&content, base_url, &Default::default());
+    issues.extend(undefined.into_iter().map(|name| issue(0, format!("Undefined variable {{${}}}", name))));
+    let playlist = parse_lines(&content, base_url, &mut issues);
+    Ok((playlist, issues))
+}
+
+fn issue(line: usize, message: impl Into<String>) -> ParseIssue {
+    ParseIssue { line, message: message.into() }
+}
+
+// The text without a byte order mark or leading whitespace, with \n line endings
+fn normalize(content: &str) -> Cow<'_, str> {
+    let content = content.trim_start_matches('\u{feff}').trim_start();
+    if content.contains('\r') {
+        Cow::Owned(content.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(content)
+    }
+}
+
+// A trimmed line with the whitespace around the colon of a tag removed:
+// "#EXTINF : 6.0," reads as "#EXTINF:6.0,"
+fn clean_line(line: &str) -> Cow<'_, str> {
+    let line = line.trim();
+    if !line.starts_with("#EXT") {
+        return Cow::Borrowed(line);
+    }
+    match line.split_once(':') {
+        Some((name, value)) if name.ends_with(char::is_whitespace) || value.starts_with(char::is_whitespace) => {
+            Cow::Owned(format!("{}:{}", name.trim_end(), value.trim_start()))
+        }
+        _ => Cow::Borrowed(line),
+    }
+}
+
+// Lines of a normalized playlist as (1-based number, cleaned line), without blank ones
+fn numbered_lines(content: &str) -> Vec<(usize, Cow<'_, str>)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, clean_line(line)))
+        .filter(|(_, line)| !line.is_empty())
+        .collect()
+}
+
+fn parse_lines(content: &str, base_url: &str, issues: &mut Vec<ParseIssue>) -> ParsedPlaylist {
+    let lines = numbered_lines(content);
+    if lines.iter().any(|(_, line)| line.starts_with("#EXT-X-STREAM-INF:")) {
+        parse_master_playlist(&lines, base_url, issues)
+    } else {
+        parse_media_playlist(&lines, base_url, issues)
+    }
+}
+
+// Each #EXT-X-STREAM-INF as (line, attribute list, URI), in playlist order.
+// Comments and other tags may come between the tag and its URI; a tag
+// followed by another #EXT-X-STREAM-INF or the end has no URI.
+fn stream_infs<'a>(lines: &'a [(usize, Cow<'_, str>)]) -> Vec<(usize, &'a str, Option<&'a str>)> {
+    let mut stream_infs: Vec<(usize, &str, Option<&str>)> = Vec::new();
+    // Whether the last tag still waits for its URI
+    let mut pending = false;
+    for (number, line) in lines {
+        if let Some(list) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+            stream_infs.push((*number, list, None));
+            pending = true;
+        } else if pending && !line.starts_with('#') {
+            if let Some(last) = stream_infs.last_mut() {
+                last.2 = Some(line);
+            }
+            pending = false;
+        }
+    }
+    stream_infs
+}
+
+fn parse_master_playlist(lines: &[(usize, Cow<'_, str>)], base_url: &str, issues: &mut Vec<ParseIssue>) -> ParsedPlaylist {
+    let mut variants = Vec::new();
+    let version = lines
+        .iter()
+        .rev()
+        .find_map(|(_, line)| line.strip_prefix("#EXT-X-VERSION:"))
+        .and_then(|value| value.trim().parse::<u8>().ok());
+
+    for (number, list, uri) in stream_infs(lines) {
+        let mut variant = Variant {
+            uri: String::new(),
+            bandwidth: 0,
+            resolution: None,
+            codecs: None,
+            frame_rate: None,
+        };
+        let mut has_bandwidth = false;
+        for (name, value) in parse_attribute_list(list) {
+            match name.as_str() {
+                "BANDWIDTH" => {
+                    has_bandwidth = true;
+                    match value.parse() {
+                        Ok(bandwidth) => variant.bandwidth = bandwidth,
+                        Err(_) => issues.push(issue(number, format!("Invalid BANDWIDTH {:?}", value))),
+                    }
+                }
+                "RESOLUTION" => variant.resolution = Some(value),
+                "CODECS" => variant.codecs = Some(value),
+                "FRAME-RATE" => variant.frame_rate = value.parse().ok(),
+                _ => {}
+            }
+        }
+        if !has_bandwidth {
+            issues.push(issue(number, "#EXT-X-STREAM-INF without BANDWIDTH"));
+        }
+        match uri {
+            Some(uri) => {
+                variant.uri = resolve_uri(uri, base_url);
+                variants.push(variant);
+            }
+            None => issues.push(issue(number, "#EXT-X-STREAM-INF without a URI")),
+        }
+    }
+
+    ParsedPlaylist::Master { version, variants }
+}
+
+fn parse_media_playlist(lines: &[(usize, Cow<'_, str>)], base_url: &str, issues: &mut Vec<ParseIssue>) -> ParsedPlaylist {
+    let mut segments = Vec::new();
+    let mut version = None;
+    let mut target_duration = None;
+    let mut discontinuity = false;
+    let mut gap = false;
+    // Applies to every later segment until the next #EXT-X-BITRATE
+    let mut bitrate = None;
+    // The #EXTINF (line, duration, title) and #EXT-X-BYTERANGE of the next segment
+    let mut info: Option<(usize, f32, Option<String>)> = None;
+    let mut byte_range = None;
+
+    for (number, line) in lines {
+        let number = *number;
+        if let Some(value) = line.strip_prefix("#EXT-X-VERSION:") {
+            version = value.trim().parse::<u8>().ok();
+        } else if let Some(value) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+            target_duration = value.trim().parse::<u64>().ok();
+            if target_duration.is_none() {
+                issues.push(issue(number, format!("Invalid #EXT-X-TARGETDURATION {:?}", value.trim())));
+            }
+        } else if line == "#EXT-X-DISCONTINUITY" {
+            discontinuity = true;
+        } else if line == "#EXT-X-GAP" {
+            gap = true;
+        } else if let Some(value) = line.strip_prefix("#EXT-X-BITRATE:") {
+            bitrate = value.trim().parse::<u64>().ok();
+            if bitrate.is_none() {
+                issues.push(issue(number, format!("Invalid #EXT-X-BITRATE {:?}", value.trim())));
+            }
+        } else if let Some(value) = line.strip_prefix("#EXTINF:") {
+            if let Some((tag_line, _, _)) = info.take() {
+                issues.push(issue(tag_line, "#EXTINF without a URI"));
+            }
+            let (duration, title) = match value.split_once(',') {
+                Some((duration, title)) => (duration.trim(), Some(title.trim().to_string())),
+                None => (value.trim(), None),
+            };
+            let duration = duration.parse::<f32>().unwrap_or_else(|_| {
+                issues.push(issue(number, format!("Invalid #EXTINF duration {:?}", duration)));
+                0.0
+            });
+            info = Some((number, duration, title));
+        } else if let Some(value) = line.strip_prefix("#EXT-X-BYTERANGE:") {
+            byte_range = Some(value.trim().to_string());
+        } else if !line.starts_with('#') {
+            // Comments and tags such as #EXT-X-PROGRAM-DATE-TIME may come
+            // between #EXTINF and the URI
+            match info.take() {
+                Some((_, duration, title)) => {
+                    let byte_range = byte_range.take();
+                    segments.push(Segment {
+                        uri: resolve_uri(line, base_url),
+                        duration,
+                        title,
+                        // The bitrate of a playlist does not cover its byte-range segments
+                        bitrate: bitrate.filter(|_| byte_range.is_none()),
+                        byte_range,
+                        discontinuity,
+                        gap,
+                    });
+                    discontinuity = false;
+                    gap = false;
+                }
+                None => issues.push(issue(number, format!("URI {:?} without #EXTINF", line))),
+            }
+        }
+    }
+    if let Some((tag_line, _, _)) = info {
+        issues.push(issue(tag_line, "#EXTINF without a URI"));
+    }
+
+    ParsedPlaylist::Media {
+        version,
+        target_duration,
+        segments,
+    }
+}
+
+/// Returns the attributes of each `#EXT-X-STREAM-INF` tag of a master
+/// playlist, in variant order, including those [`Variant`] has no field for
+/// (`VIDEO-RANGE`, `HDCP-LEVEL`, ...). The text is read as [`parse_playlist`]
+/// reads it, so the list lines up with its variants; `base_url` serves
+/// `#EXT-X-DEFINE:QUERYPARAM` variables.
+pub fn variant_attributes(content: &str, base_url: &str) -> Vec<Vec<(String, String)>> {
+    let content = normalize(content);
+    let content = substitute_variables(&content, base_url);
+    let lines = numbered_lines(&content);
+    stream_infs(&lines)
+        .into_iter()
+        .filter(|(_, _, uri)| uri.is_some())
+        .map(|(_, list, _)| parse_attribute_list(list))
+        .collect()
+}
+
+/// Returns the `#EXT-X-MEDIA` renditions of a master playlist, in playlist
+/// order, with their URIs resolved against `base_url`.
+pub fn parse_renditions(content: &str, base_url: &str) -> Vec<Rendition> {
+    let content = normalize(content);
+    let content = substitute_variables(&content, base_url);
+    numbered_lines(&content)
+        .iter()
+        .filter_map(|(_, line)| line.strip_prefix("#EXT-X-MEDIA:"))
+        .map(|list| {
+            let mut rendition = Rendition::default();
+            for (name, value) in parse_attribute_list(list) {
+                match name.as_str() {
+                    "TYPE" => rendition.media_type = value,
+                    "GROUP-ID" => rendition.group_id = value,
+                    "NAME" => rendition.name = value,
+                    "LANGUAGE" => rendition.language = Some(value),
+                    "URI" => rendition.uri = Some(resolve_uri(&value, base_url)),
+                    "DEFAULT" => rendition.default = value == "YES",
+                    "AUTOSELECT" => rendition.autoselect = value == "YES",
+                    "CHANNELS" => rendition.channels = Some(value),
+                    "INSTREAM-ID" => rendition.instream_id = Some(value),
+                    _ => {}
+                }
+            }
+            rendition
+        })
+        .collect()
+}
+
+/// Splits an attribute list such as `ID="a",CLASS="b,c",DURATION=10` into
+/// name/value pairs. Quotes are removed from quoted values, and commas
+/// inside them do not separate attributes.
+pub(crate) fn parse_attribute_list(list: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = list.trim();
+
+    while !rest.is_empty() {
+        let Some(eq) = rest.find('=') else { break };
+        let name = rest[..eq].trim().to_string();
+        rest = &rest[eq + 1..];
+
+        let value;
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            value = quoted[..end].to_string();
+            rest = quoted.get(end + 1..).unwrap_or("");
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            value = rest[..end].trim().to_string();
+            rest = &rest[end..];
+        }
+
+        attributes.push((name, value));
+        rest = rest.trim_start().strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+
+    attributes
+}
+
+// Percent-encodes what may not appear in a URL as is: non-ASCII characters,
+// spaces and control characters. Existing escapes are left alone.
+fn encode_uri(uri: &str) -> Cow<'_, str> {
+    if uri.bytes().all(|b| b.is_ascii_graphic()) {
+        return Cow::Borrowed(uri);
+    }
+    let mut encoded = String::with_capacity(uri.len() * 3);
+    for byte in uri.bytes() {
+        if byte.is_ascii_graphic() {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    Cow::Owned(encoded)
+}
+
+/// Resolves a playlist URI against the URL of the playlist that referenced
+/// it. Non-ASCII characters and spaces in the URI are percent-encoded.
+pub fn resolve_uri(uri: &str, base_url: &str) -> String {
+    let uri = encode_uri(uri.trim());
+    let uri = uri.as_ref();
+    if uri.starts_with("http://") || uri.starts_with("https://") {
+        uri.to_string()
+    } else if uri.starts_with("/") {
+        // Absolute path
+        if let Ok(url) = url::Url::parse(base_url) {
+            format!("{}://{}{}", url.scheme(), url.host_str().unwrap_or(""), uri)
+        } else {
+            uri.to_string()
+        }
+    } else {
+        // Relative path
+        if let Some(pos) = base_url.rfind('/') {
+            format!("{}/{}", &base_url[..pos], uri)
+        } else {
+            format!("{}/{}", base_url, uri)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_uri() {
+        // Test absolute URL
+        assert_eq!(
+            resolve_uri("https://example.com/video.ts", "https://base.com/playlist.m3u8"),
+            "https://example.com/video.ts"
+        );
+        
+        // Test absolute path
+        assert_eq!(
+            resolve_uri("/videos/video.ts", "https://example.com/playlist.m3u8"),
+            "https://example.com/videos/video.ts"
+        );
+        
+        // Test relative path
+        assert_eq!(
+            resolve_uri("video.ts", "https://example.com/streams/playlist.m3u8"),
+            "https://example.com/streams/video.ts"
+        );
+    }
+
+    #[test]
+    fn test_resolve_non_ascii_uri() {
+        assert_eq!(
+            resolve_uri("番組 1.ts", "https://example.com/live/index.m3u8"),
+            "https://example.com/live/%E7%95%AA%E7%B5%84%201.ts"
+        );
+        assert_eq!(
+            resolve_uri("already%20encoded.ts", "https://example.com/index.m3u8"),
+            "https://example.com/already%20encoded.ts"
+        );
+    }
+
+    #[test]
+    fn test_parse_edge_cases() {
+        let content = "\u{feff}#EXTM3U\r\n\
+            #EXT-X-TARGETDURATION : 6\r\n\
+            \r\n\
+            #EXTINF: 6.0 , Opening\r\n\
+            # a comment\r\n\
+            #EXT-X-PROGRAM-DATE-TIME:2024-01-01T00:00:00Z\r\n\
+            seg0.ts\r\n\
+            #EXTINF:4.0,\r\n\
+            #EXT-X-BYTERANGE:1000@0\r\n\
+            seg1.ts\r\n";
+        let ParsedPlaylist::Media { target_duration, segments, .. } = parse_playlist(content, "https://example.com/index.m3u8").unwrap() else {
+            panic!("expected a media playlist");
+        };
+        assert_eq!(target_duration, Some(6));
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].uri, "https://example.com/seg0.ts");
+        assert_eq!(segments[0].duration, 6.0);
+        assert_eq!(segments[0].title.as_deref(), Some("Opening"));
+        assert_eq!(segments[1].byte_range.as_deref(), Some("1000@0"));
+
+        let master = "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=800000,CODECS=\"avc1.4d401e,mp4a.40.2\"\n\n# low\n360p.m3u8\n";
+        let ParsedPlaylist::Master { variants, .. } = parse_playlist(master, "https://example.com/master.m3u8").unwrap() else {
+            panic!("expected a master playlist");
+        };
+        assert_eq!(variants[0].uri, "https://example.com/360p.m3u8");
+        assert_eq!(variants[0].codecs.as_deref(), Some("avc1.4d401e,mp4a.40.2"));
+    }
+
+    #[test]
+    fn test_parse_playlist_tolerant() {
+        let content = "#EXT-X-TARGETDURATION:6\n#EXTINF:abc,\na.ts\nstray.ts\n#EXTINF:6,\n";
+        assert!(parse_playlist(content, "https://example.com/index.m3u8").is_err());
+        let (playlist, issues) = parse_playlist_tolerant(content, "https://example.com/index.m3u8").unwrap();
+        let ParsedPlaylist::Media { segments, .. } = playlist else {
+            panic!("expected a media playlist");
+        };
+        assert_eq!(segments.len(), 1);
+        let lines: Vec<usize> = issues.iter().map(|issue| issue.line).collect();
+        assert_eq!(lines, vec![1, 2, 4, 5]);
+
+        assert!(parse_playlist_tolerant("<!DOCTYPE html><html></html>", "https://example.com/").is_err());
+    }
+
+    #[test]
+    fn test_parse_gap_bitrate_and_variables() {
+        let content = "#EXTM3U\n\
+            #EXT-X-DEFINE:NAME=\"path\",VALUE=\"media/720p\"\n\
+            #EXT-X-BITRATE:2500\n\
+            #EXTINF:6,\n\
+            {$path}/a.ts\n\
+            #EXT-X-GAP\n\
+            #EXTINF:6,\n\
+            {$path}/b.ts\n\
+            #EXT-X-BYTERANGE:1000@0\n\
+            #EXTINF:6,\n\
+            {$other}/c.ts\n";
+        let (playlist, issues) = parse_playlist_tolerant(content, "https://example.com/index.m3u8").unwrap();
+        let ParsedPlaylist::Media { segments, .. } = playlist else {
+            panic!("expected a media playlist");
+        };
+        assert_eq!(segments[0].uri, "https://example.com/media/720p/a.ts");
+        assert_eq!(segments[0].bitrate, Some(2500));
+        assert!(!segments[0].gap);
+        assert!(segments[1].gap);
+        assert_eq!(segments[2].bitrate, None);
+        assert_eq!(issues, vec![issue(0, "Undefined variable {$other}")]);
+    }
+
+    #[test]
+    fn test_variant_attributes() {
+        let content = "#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=8000000,CODECS=\"hvc1.2.4.L150,mp4a.40.2\",VIDEO-RANGE=PQ\n\
+            hdr.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2000000\n\
+            sdr.m3u8\n";
+        let variants = variant_attributes(content, "https://example.com/master.m3u8");
+        assert_eq!(variants.len(), 2);
+        assert!(variants[0].contains(&("VIDEO-RANGE".to_string(), "PQ".to_string())));
+        assert!(variants[0].contains(&("CODECS".to_string(), "hvc1.2.4.L150,mp4a.40.2".to_string())));
+        assert_eq!(variants[1], vec![("BANDWIDTH".to_string(), "2000000".to_string())]);
+
+        // Playlists the parser tolerates line up with its variants
+        let fixtures = [
+            "\u{feff}#EXTM3U\r\n#EXT-X-STREAM-INF : BANDWIDTH=800000\r\n\r\n# low\r\n360p.m3u8\r\n",
+            "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=800000\n#EXT-X-STREAM-INF:BANDWIDTH=1600000\n720p.m3u8\n",
+            "#EXTM3U\n#EXT-X-DEFINE:NAME=\"rate\",VALUE=\"1600000\"\n#EXT-X-STREAM-INF:BANDWIDTH={$rate}\n720p.m3u8\n#EXT-X-STREAM-INF:BANDWIDTH=400000\n",
+            "#EXT-X-STREAM-INF:BANDWIDTH=400000\r240p.m3u8\r",
+        ];
+        for content in fixtures {
+            let (playlist, _) = parse_playlist_tolerant(content, "https://example.com/master.m3u8").unwrap();
+            let ParsedPlaylist::Master { variants, .. } = playlist else {
+                panic!("expected a master playlist");
+            };
+            let attributes = variant_attributes(content, "https://example.com/master.m3u8");
+            assert_eq!(attributes.len(), variants.len(), "{:?}", content);
+        }
+        let attributes = variant_attributes(fixtures[2], "https://example.com/master.m3u8");
+        assert_eq!(attributes[0], vec![("BANDWIDTH".to_string(), "1600000".to_string())]);
+    }
+
+    #[test]
+    fn test_parse_renditions() {
+        let content = "#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aac\",LANGUAGE=\"ja\",NAME=\"日本語\",DEFAULT=YES,URI=\"audio/ja.m3u8\"\n\
+            #EXT-X-MEDIA:TYPE=CLOSED-CAPTIONS,GROUP-ID=\"cc\",NAME=\"English\",INSTREAM-ID=\"CC1\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2000000,AUDIO=\"aac\",CLOSED-CAPTIONS=\"cc\"\n\
+            video.m3u8\n";
+        let renditions = parse_renditions(content, "https://example.com/live/master.m3u8");
+        assert_eq!(renditions.len(), 2);
+        assert_eq!(renditions[0].language.as_deref(), Some("ja"));
+        assert_eq!(renditions[0].uri.as_deref(), Some("https://example.com/live/audio/ja.m3u8"));
+        assert!(renditions[0].default && !renditions[0].autoselect);
+        assert_eq!(renditions[1].media_type, "CLOSED-CAPTIONS");
+        assert_eq!(renditions[1].uri, None);
+
+        // A byte order mark, bare CR line ends and space around the colon
+        // read the same
+        let content = format!("\u{feff}{}", content.replace('\n', "\r").replace("#EXT-X-MEDIA:", "#EXT-X-MEDIA : "));
+        let renditions = parse_renditions(&content, "https://example.com/live/master.m3u8");
+        assert_eq!(renditions.len(), 2);
+        assert_eq!(renditions[0].uri.as_deref(), Some("https://example.com/live/audio/ja.m3u8"));
+        assert_eq!(renditions[1].name, "English");
+    }
+
+    #[test]
+    fn test_parse_attribute_list() {
+        let attributes = parse_attribute_list(r#"ID="ad,1",DURATION=30.5, X-TITLE="Intro""#);
+        assert_eq!(
+            attributes,
+            vec![
+                ("ID".to_string(), "ad,1".to_string()),
+                ("DURATION".to_string(), "30.5".to_string()),
+                ("X-TITLE".to_string(), "Intro".to_string()),
+            ]
+        );
+    }
+}
\ No newline at end of file
@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+
+use crate::parser::parse_attribute_list;
+use crate::types::Segment;
+
+/// Target durations from the end of a live playlist a player starts at
+/// when the playlist has no `#EXT-X-START` (RFC 8216, section 6.3.3).
+pub const LIVE_EDGE_TARGET_DURATIONS: u64 = 3;
+
+/// An `#EXT-X-START` tag: where the author wants playback to begin.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StartTag {
+    /// Seconds from the beginning of the playlist, or from its end when
+    /// negative.
+    pub time_offset: f64,
+    /// `PRECISE=YES`: start exactly at the offset instead of at the
+    /// beginning of the segment containing it.
+    pub precise: bool,
+}
+
+/// Where a compliant player starts playing a media playlist.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PlaybackStart {
+    /// Seconds from the beginning of the playlist.
+    pub position: f64,
+    /// Index of the segment containing `position`.
+    pub segment: usize,
+    /// Seconds from the beginning of that segment.
+    pub segment_offset: f64,
+    /// Seconds of media between `position` and the end of the playlist,
+    /// which for a live playlist is how far behind the live edge
+    /// playback starts.
+    pub live_edge_offset: f64,
+    /// Whether the position comes from an `#EXT-X-START` tag.
+    pub from_start_tag: bool,
+}
+
+/// The `#EXT-X-START` tag of a playlist, if it has a readable one.
+pub fn parse_start(content: &str) -> Option<StartTag> {
+    let list = content
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("#EXT-X-START:"))?;
+    let attributes = parse_attribute_list(list);
+    let time_offset = attributes
+        .iter()
+        .find(|(name, _)| name == "TIME-OFFSET")
+        .and_then(|(_, value)| value.parse().ok())?;
+    let precise = attributes.iter().any(|(name, value)| name == "PRECISE" && value == "YES");
+    Some(StartTag { time_offset, precise })
+}
+
+/// Computes where playback of a media playlist starts. With a `start` tag
+/// (the media playlist's own, or else its master playlist's) its offset
+/// is clamped to the playlist; without one, a `live` playlist starts at
+/// the last segment beginning at least three target durations before its
+/// end and any other playlist at its beginning. Returns `None` for a
+/// playlist without segments.
+pub fn playback_start(
+    segments: &[Segment],
+    target_duration: Option<u64>,
+    start: Option<&StartTag>,
+    live: bool,
+) -> Option<PlaybackStart> {
+    if segments.is_empty() {
+        return None;
+    }
+    let total: f64 = segments.iter().map(|segment| segment.duration as f64).sum();
+    let (wanted, precise) = match start {
+        Some(start) if start.time_offset < 0.0 => (total + start.time_offset, start.precise),
+        Some(start) => (start.time_offset, start.precise),
+        None if live => {
+            let edge = target_duration.unwrap_or(0) * LIVE_EDGE_TARGET_DURATIONS;
+            (total - edge as f64, false)
+        }
+        None => (0.0, false),
+    };
+    let wanted = wanted.clamp(0.0, total);
+
+    // The segment containing `wanted`; a position at the very end belongs
+    // to the last segment
+    let mut segment_start = 0.0;
+    let mut segment = segments.len() - 1;
+    for (index, item) in segments.iter().enumerate() {
+        let end = segment_start + item.duration as f64;
+        if wanted < end || index + 1 == segments.len() {
+            segment = index;
+            break;
+        }
+        segment_start = end;
+    }
+    let position = if precise { wanted } else { segment_start };
+
+    Some(PlaybackStart {
+        position,
+        segment,
+        segment_offset: position - segment_start,
+        live_edge_offset: total - position,
+        from_start_tag: start.is_some(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_playlist;
+    use crate::types::ParsedPlaylist;
+
+    fn segments(content: &str) -> Vec<Segment> {
+        match parse_playlist(content, "https://example.com/index.m3u8").unwrap() {
+            ParsedPlaylist::Media { segments, .. } => segments,
+            ParsedPlaylist::Master { .. } => panic!("expected a media playlist"),
+        }
+    }
+
+    #[test]
+    fn test_playback_start() {
+        let content = "#EXTM3U\n\
+            #EXT-X-TARGETDURATION:6\n\
+            #EXT-X-START:TIME-OFFSET=-10.5,PRECISE=YES\n\
+            #EXTINF:6,\na.ts\n#EXTINF:6,\nb.ts\n#EXTINF:6,\nc.ts\n#EXTINF:6,\nd.ts\n#EXTINF:6,\ne.ts\n";
+        let segments = segments(content);
+        let tag = parse_start(content).unwrap();
+        assert_eq!(tag, StartTag { time_offset: -10.5, precise: true });
+
+        let start = playback_start(&segments, Some(6), Some(&tag), true).unwrap();
+        assert_eq!((start.position, start.segment, start.segment_offset), (19.5, 3, 1.5));
+        assert_eq!(start.live_edge_offset, 10.5);
+
+        // Without PRECISE, from the start of the segment
+        let imprecise = StartTag { precise: false, ..tag };
+        let start = playback_start(&segments, Some(6), Some(&imprecise), true).unwrap();
+        assert_eq!((start.position, start.segment), (18.0, 3));
+
+        // Without a tag, three target durations from the live edge
+        let start = playback_start(&segments, Some(6), None, true).unwrap();
+        assert_eq!((start.position, start.segment, start.live_edge_offset), (12.0, 2, 18.0));
+        assert!(!start.from_start_tag);
+        assert_eq!(playback_start(&segments, Some(6), None, false).unwrap().position, 0.0);
+
+        // Offsets past either end are clamped
+        let late = StartTag { time_offset: 100.0, precise: true };
+        assert_eq!(playback_start(&segments, Some(6), Some(&late), false).unwrap().segment, 4);
+        assert!(playback_start(&[], Some(6), None, true).is_none());
+    }
+}
@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::Segment;
+
+/// Number of time windows in [`PlaylistStats::bitrate_profile`].
+const PROFILE_WINDOWS: usize = 10;
+
+/// Maximum number of entries in [`PlaylistStats::anomalies`].
+const MAX_ANOMALIES: usize = 50;
+
+/// Average bitrate over one time window of the playlist.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BitratePoint {
+    /// Window start, in seconds from the beginning of the playlist.
+    pub start: f64,
+    pub end: f64,
+    pub kbps: f64,
+    /// Segments with a known size that contributed to this window.
+    pub samples: usize,
+}
+
+/// Derived analytics for a media playlist, for quality control of packaged content.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PlaylistStats {
+    pub segment_count: usize,
+    pub total_duration: f64,
+    pub target_duration: Option<u64>,
+    pub min_segment_duration: f64,
+    pub max_segment_duration: f64,
+    pub avg_segment_duration: f64,
+    pub duration_variance: f64,
+    /// Segments shorter than half the target duration, not counting the last one.
+    pub short_segments: usize,
+    /// Segments whose rounded duration exceeds the target duration.
+    pub long_segments: usize,
+    pub discontinuities: usize,
+    /// Segments marked with `#EXT-X-GAP`.
+    #[serde(default)]
+    pub gaps: usize,
+    /// Empty unless segment sizes or `#EXT-X-BITRATE` values are known.
+    pub bitrate_profile: Vec<BitratePoint>,
+    pub anomalies: Vec<String>,
+}
+
+/// Size in bytes of a `length[@offset]` byte range.
+pub fn byte_range_length(byte_range: &str) -> Option<u64> {
+    byte_range.split('@').next()?.trim().parse().ok()
+}
+
+/// Computes statistics for the segments of a media playlist.
+///
+/// `sizes` optionally holds the byte size of each segment by index (for
+/// example from HEAD requests); segments with an `EXT-X-BYTERANGE` use its
+/// length when no size is given, and those with an `#EXT-X-BITRATE` are
+/// estimated from it. It may be shorter than `segments`.
+pub fn media_stats(segments: &[Segment], target_duration: Option<u64>, sizes: &[Option<u64>]) -> PlaylistStats {
+    let durations: Vec<f64> = segments.iter().map(|s| s.duration as f64).collect();
+    let count = durations.len();
+    let total: f64 = durations.iter().sum();
+    let avg = if count > 0 { total / count as f64 } else { 0.0 };
+    let variance = if count > 0 {
+        durations.iter().map(|d| (d - avg).powi(2)).sum::<f64>() / count as f64
+    } else {
+        0.0
+    };
+    let min = durations.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = durations.iter().cloned().fold(0.0, f64::max);
+
+    // Without a target duration, judge segments against the average instead
+    let reference = target_duration.map(|t| t as f64).unwrap_or(avg);
+
+    let mut anomalies = Vec::new();
+    let mut short_segments = 0;
+    let mut long_segments = 0;
+    let mut discontinuities = 0;
+    let mut gaps = 0;
+
+    for (index, segment) in segments.iter().enumerate() {
+        let duration = segment.duration as f64;
+        if segment.discontinuity {
+            discontinuities += 1;
+            anomalies.push(format!("Discontinuity before segment {}", index));
+        }
+        if segment.gap {
+            gaps += 1;
+            anomalies.push(format!("Segment {} is a gap", index));
+        }
+        if duration <= 0.0 {
+            anomalies.push(format!("Segment {} has no duration", index));
+        } else if index + 1 < count && duration < reference * 0.5 {
+            short_segments += 1;
+            anomalies.push(format!("Segment {} is unusually short ({:.3}s)", index, duration));
+        }
+        if target_duration.is_some() && duration.round() > reference {
+            long_segments += 1;
+            anomalies.push(format!(
+                "Segment {} ({:.3}s) exceeds the target duration of {}s",
+                index, duration, reference
+            ));
+        } else if target_duration.is_none() && duration > reference * 1.5 {
+            long_segments += 1;
+            anomalies.push(format!("Segment {} is unusually long ({:.3}s)", index, duration));
+        }
+    }
+    let anomaly_count = anomalies.len();
+    if anomaly_count > MAX_ANOMALIES {
+        anomalies.truncate(MAX_ANOMALIES);
+        anomalies.push(format!("... and {} more", anomaly_count - MAX_ANOMALIES));
+    }
+
+    PlaylistStats {
+        segment_count: count,
+        total_duration: total,
+        target_duration,
+        min_segment_duration: if count > 0 { min } else { 0.0 },
+        max_segment_duration: max,
+        avg_segment_duration: avg,
+        duration_variance: variance,
+        short_segments,
+        long_segments,
+        discontinuities,
+        gaps,
+        bitrate_profile: bitrate_profile(segments, sizes, total),
+        anomalies,
+    }
+}
+
+fn bitrate_profile(segments: &[Segment], sizes: &[Option<u64>], total: f64) -> Vec<BitratePoint> {
+    if total <= 0.0 {
+        return Vec::new();
+    }
+
+    let window = total / PROFILE_WINDOWS as f64;
+    let mut bits = [0.0f64; PROFILE_WINDOWS];
+    let mut seconds = [0.0f64; PROFILE_WINDOWS];
+    let mut samples = [0usize; PROFILE_WINDOWS];
+
+    let mut start = 0.0;
+    for (index, segment) in segments.iter().enumerate() {
+        let size = sizes.get(index).copied().flatten()
+            .or_else(|| segment.byte_range.as_deref().and_then(byte_range_length))
+            .or_else(|| segment.bitrate.map(|kbps| (kbps as f64 * 1000.0 / 8.0 * segment.duration as f64) as u64));
+        // Gap segments have no media to measure
+        if let Some(size) = size.filter(|_| !segment.gap) {
+            if segment.duration > 0.0 {
+                let slot = ((start / window) as usize).min(PROFILE_WINDOWS - 1);
+                bits[slot] += size as f64 * 8.0;
+                seconds[slot] += segment.duration as f64;
+                samples[slot] += 1;
+            }
+        }
+        start += segment.duration as f64;
+    }
+
+    if samples.iter().all(|n| *n == 0) {
+        return Vec::new();
+    }
+
+    (0..PROFILE_WINDOWS)
+        .filter(|slot| samples[*slot] > 0)
+        .map(|slot| BitratePoint {
+            start: slot as f64 * window,
+            end: (slot + 1) as f64 * window,
+            kbps: bits[slot] / seconds[slot] / 1000.0,
+            samples: samples[slot],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(duration: f32, discontinuity: bool) -> Segment {
+        Segment {
+            uri: "https://example.com/seg.ts".to_string(),
+            duration,
+            title: None,
+            byte_range: None,
+            discontinuity,
+            gap: false,
+            bitrate: None,
+        }
+    }
+
+    #[test]
+    fn test_media_stats() {
+        let segments = vec![
+            segment(10.0, false),
+            segment(2.0, false),
+            segment(12.0, true),
+            segment(4.0, false),
+        ];
+        let stats = media_stats(&segments, Some(10), &[]);
+
+        assert_eq!(stats.segment_count, 4);
+        assert_eq!(stats.total_duration, 28.0);
+        assert_eq!(stats.min_segment_duration, 2.0);
+        assert_eq!(stats.max_segment_duration, 12.0);
+        assert_eq!(stats.avg_segment_duration, 7.0);
+        // The short last segment is not an anomaly
+        assert_eq!(stats.short_segments, 1);
+        assert_eq!(stats.long_segments, 1);
+        assert_eq!(stats.discontinuities, 1);
+        assert!(stats.bitrate_profile.is_empty());
+    }
+
+    #[test]
+    fn test_bitrate_profile() {
+        let segments = vec![segment(10.0, false), segment(10.0, false)];
+        let sizes = [Some(1_250_000), None];
+        let stats = media_stats(&segments, Some(10), &sizes);
+
+        assert_eq!(stats.bitrate_profile.len(), 1);
+        assert_eq!(stats.bitrate_profile[0].kbps, 1000.0);
+        assert_eq!(byte_range_length("1024@2048"), Some(1024));
+
+        // Estimated from #EXT-X-BITRATE, without the gap
+        let mut segments = vec![segment(10.0, false), segment(10.0, false)];
+        segments[0].bitrate = Some(800);
+        segments[1].bitrate = Some(800);
+        segments[1].gap = true;
+        let stats = media_stats(&segments, Some(10), &[]);
+        assert_eq!(stats.gaps, 1);
+        assert_eq!(stats.bitrate_profile.len(), 1);
+        assert_eq!(stats.bitrate_profile[0].kbps, 800.0);
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::M3u8Error;
+use crate::keys::segment_keys;
+use crate::parser::{parse_attribute_list, parse_playlist, parse_renditions};
+use crate::start::{parse_start, playback_start, PlaybackStart};
+use crate::types::ParsedPlaylist;
+
+/// Tags every summary already accounts for, left out of
+/// [`PlaylistSummary::notable_tags`].
+const SUMMARIZED_TAGS: &[&str] = &[
+    "EXTM3U",
+    "EXTINF",
+    "EXT-X-VERSION",
+    "EXT-X-TARGETDURATION",
+    "EXT-X-MEDIA-SEQUENCE",
+    "EXT-X-PLAYLIST-TYPE",
+    "EXT-X-ENDLIST",
+    "EXT-X-DISCONTINUITY",
+    "EXT-X-KEY",
+    "EXT-X-SESSION-KEY",
+    "EXT-X-STREAM-INF",
+    "EXT-X-MEDIA",
+    "EXT-X-START",
+];
+
+/// One row of the variant table of a master playlist.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct VariantSummary {
+    pub bandwidth: u64,
+    pub resolution: Option<String>,
+    pub codecs: Option<String>,
+    pub frame_rate: Option<f32>,
+    pub uri: String,
+}
+
+/// Compact digest of a playlist, for readers that do not need every
+/// segment.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PlaylistSummary {
+    /// `master` or `media`.
+    #[serde(rename = "type")]
+    pub playlist_type: String,
+    pub version: Option<u8>,
+    /// Master playlists only, highest bandwidth first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<VariantSummary>,
+    /// Master playlists only: `TYPE` to the `NAME (LANGUAGE)` of each
+    /// rendition, e.g. `"AUDIO": ["English (en)"]`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub renditions: BTreeMap<String, Vec<String>>,
+    /// `VOD`, `EVENT` or `LIVE`, for media playlists.
+    pub presentation: Option<String>,
+    pub target_duration: Option<u64>,
+    pub segment_count: Option<usize>,
+    /// Seconds, for media playlists.
+    pub total_duration: Option<f64>,
+    pub discontinuities: usize,
+    /// Media playlists only: where a player starts playing, see
+    /// [`playback_start`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<PlaybackStart>,
+    /// Distinct `METHOD`s of the `#EXT-X-KEY` (or, in a master playlist,
+    /// `#EXT-X-SESSION-KEY`) tags; empty when unencrypted.
+    pub encryption: Vec<String>,
+    /// How often each other tag occurs, e.g. `EXT-X-PROGRAM-DATE-TIME` or
+    /// `EXT-X-DATERANGE`.
+    pub notable_tags: BTreeMap<String, usize>,
+}
+
+fn tag_name(line: &str) -> Option<&str> {
+    let tag = line.strip_prefix('#')?;
+    if !tag.starts_with("EXT") {
+        return None;
+    }
+    Some(tag.split(':').next().unwrap_or(tag))
+}
+
+fn push_unique(values: &mut Vec<String>, value: String) {
+    if !value.is_empty() && !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+/// Summarizes playlist text without listing its segments or variant
+/// attributes one by one.
+pub fn summarize_playlist(content: &str, base_url: &str) -> Result<PlaylistSummary, M3u8Error> {
+    let playlist = parse_playlist(content, base_url)?;
+
+    let mut notable_tags = BTreeMap::new();
+    let mut playlist_type = None;
+    let mut has_end_list = false;
+    let mut session_keys = Vec::new();
+    for line in content.lines().map(str::trim) {
+        let Some(tag) = tag_name(line) else {
+            continue;
+        };
+        match tag {
+            "EXT-X-PLAYLIST-TYPE" => playlist_type = line.split_once(':').map(|(_, value)| value.trim().to_string()),
+            "EXT-X-ENDLIST" => has_end_list = true,
+            "EXT-X-SESSION-KEY" => {
+                let list = line.split_once(':').map_or("", |(_, list)| list);
+                for (name, value) in parse_attribute_list(list) {
+                    if name == "METHOD" && value != "NONE" {
+                        push_unique(&mut session_keys, value);
+                    }
+                }
+            }
+            _ => {}
+        }
+        if !SUMMARIZED_TAGS.contains(&tag) {
+            *notable_tags.entry(tag.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    let summary = match playlist {
+        ParsedPlaylist::Master { version, variants } => {
+            let mut variants: Vec<VariantSummary> = variants
+                .into_iter()
+                .map(|variant| VariantSummary {
+                    bandwidth: variant.bandwidth,
+                    resolution: variant.resolution,
+                    codecs: variant.codecs,
+                    frame_rate: variant.frame_rate,
+                    uri: variant.uri,
+                })
+                .collect();
+            variants.sort_by_key(|v| std::cmp::Reverse(v.bandwidth));
+
+            let mut renditions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+            for rendition in parse_renditions(content, base_url) {
+                let label = match &rendition.language {
+                    Some(language) => format!("{} ({})", rendition.name, language),
+                    None => rendition.name.clone(),
+                };
+                renditions.entry(rendition.media_type).or_default().push(label);
+            }
+
+            PlaylistSummary {
+                playlist_type: "master".to_string(),
+                version,
+                variants,
+                renditions,
+                presentation: None,
+                target_duration: None,
+                segment_count: None,
+                total_duration: None,
+                discontinuities: 0,
+                start: None,
+                encryption: session_keys,
+                notable_tags,
+            }
+        }
+        ParsedPlaylist::Media { version, target_duration, segments } => {
+            let mut encryption = Vec::new();
+            for key in segment_keys(content, base_url).into_iter().flatten() {
+                push_unique(&mut encryption, key.method);
+            }
+            let presentation = match playlist_type.as_deref() {
+                Some("VOD") => "VOD",
+                Some("EVENT") => "EVENT",
+                _ if has_end_list => "VOD",
+                _ => "LIVE",
+            };
+            let start = parse_start(content);
+            let start = playback_start(&segments, target_duration, start.as_ref(), !has_end_list);
+            PlaylistSummary {
+                playlist_type: "media".to_string(),
+                version,
+                variants: Vec::new(),
+                renditions: BTreeMap::new(),
+                presentation: Some(presentation.to_string()),
+                target_duration,
+                segment_count: Some(segments.len()),
+                total_duration: Some(segments.iter().map(|s| s.duration as f64).sum()),
+                discontinuities: segments.iter().filter(|s| s.discontinuity).count(),
+                start,
+                encryption,
+                notable_tags,
+            }
+        }
+    };
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_playlist() {
+        let media = "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:10\n\
+                     #EXT-X-PROGRAM-DATE-TIME:2024-01-01T00:00:00Z\n\
+                     #EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"\n\
+                     #EXTINF:10.0,\nseg0.ts\n#EXT-X-DISCONTINUITY\n#EXTINF:5.5,\nseg1.ts\n#EXT-X-ENDLIST\n";
+        let summary = summarize_playlist(media, "https://example.com/live/index.m3u8").unwrap();
+        assert_eq!(summary.playlist_type, "media");
+        assert_eq!(summary.presentation.as_deref(), Some("VOD"));
+        assert_eq!(summary.segment_count, Some(2));
+        assert_eq!(summary.total_duration, Some(15.5));
+        assert_eq!(summary.discontinuities, 1);
+        assert_eq!(summary.encryption, vec!["AES-128"]);
+        assert_eq!(summary.notable_tags.get("EXT-X-PROGRAM-DATE-TIME"), Some(&1));
+        assert_eq!(summary.notable_tags.len(), 1);
+
+        let master = "#EXTM3U\n\
+                      #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud\",NAME=\"English\",LANGUAGE=\"en\",URI=\"en.m3u8\"\n\
+                      #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,AUDIO=\"aud\"\nlow.m3u8\n\
+                      #EXT-X-STREAM-INF:BANDWIDTH=2400000,RESOLUTION=1280x720,AUDIO=\"aud\"\nhigh.m3u8\n";
+        let summary = summarize_playlist(master, "https://example.com/master.m3u8").unwrap();
+        assert_eq!(summary.playlist_type, "master");
+        assert_eq!(summary.variants[0].bandwidth, 2400000);
+        assert_eq!(summary.variants[1].uri, "https://example.com/low.m3u8");
+        assert_eq!(summary.renditions.get("AUDIO"), Some(&vec!["English (en)".to_string()]));
+        assert!(summary.encryption.is_empty());
+        assert!(summary.notable_tags.is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+/// A media segment from an `#EXTINF` entry.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Segment {
+    pub uri: String,
+    pub duration: f32,
+    pub title: Option<String>,
+    pub byte_range: Option<String>,
+    /// Set when an `#EXT-X-DISCONTINUITY` tag precedes this segment.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub discontinuity: bool,
+    /// Set when an `#EXT-X-GAP` tag marks this segment as missing; clients
+    /// must not fetch it.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub gap: bool,
+    /// Approximate bitrate in kbit/s from the last `#EXT-X-BITRATE` tag.
+    /// Not set for byte-range segments, which the tag does not apply to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<u64>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// A variant stream from an `#EXT-X-STREAM-INF` entry.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Variant {
+    pub uri: String,
+    pub bandwidth: u64,
+    pub resolution: Option<String>,
+    pub codecs: Option<String>,
+    pub frame_rate: Option<f32>,
+}
+
+/// A parsed playlist, either a master playlist listing variants or a
+/// media playlist listing segments.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum ParsedPlaylist {
+    #[serde(rename = "master")]
+    Master {
+        version: Option<u8>,
+        variants: Vec<Variant>,
+    },
+    #[serde(rename = "media")]
+    Media {
+        version: Option<u8>,
+        target_duration: Option<u64>,
+        segments: Vec<Segment>,
+    },
+}
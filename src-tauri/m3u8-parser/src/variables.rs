@@ -0,0 +1,119 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use crate::parser::parse_attribute_list;
+
+// Variables a playlist declares with `#EXT-X-DEFINE`, in the order given:
+// NAME/VALUE pairs, QUERYPARAM taken from the query of `base_url`, and
+// IMPORT taken from `imported`, the variables of the master playlist that
+// referenced this one. Declarations that cannot be satisfied are left out.
+fn define_variables(
+    content: &str,
+    base_url: &str,
+    imported: &BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let mut variables = BTreeMap::new();
+    for list in content.lines().filter_map(|line| line.trim().strip_prefix("#EXT-X-DEFINE:")) {
+        let attributes = parse_attribute_list(list);
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        };
+        if let (Some(name), Some(value)) = (attribute("NAME"), attribute("VALUE")) {
+            variables.insert(name, value);
+        } else if let Some(name) = attribute("QUERYPARAM") {
+            if let Some(value) = query_param(base_url, &name) {
+                variables.insert(name, value);
+            }
+        } else if let Some(name) = attribute("IMPORT") {
+            if let Some(value) = imported.get(&name) {
+                variables.insert(name, value.clone());
+            }
+        }
+    }
+    variables
+}
+
+fn query_param(url: &str, name: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+// Replaces {$name} references with the values of `variables`. References to
+// unknown variables are left as written and returned alongside.
+fn substitute<'a>(text: &'a str, variables: &BTreeMap<String, String>) -> (Cow<'a, str>, Vec<String>) {
+    if !text.contains("{$") {
+        return (Cow::Borrowed(text), Vec::new());
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut undefined = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{$") {
+        result.push_str(&rest[..start]);
+        let reference = &rest[start..];
+        let Some(end) = reference.find('}') else {
+            break;
+        };
+        let name = &reference[2..end];
+        match variables.get(name) {
+            Some(value) => result.push_str(value),
+            None => {
+                result.push_str(&reference[..=end]);
+                if !undefined.iter().any(|known| known == name) {
+                    undefined.push(name.to_string());
+                }
+            }
+        }
+        rest = &reference[end + 1..];
+    }
+    result.push_str(rest);
+    (Cow::Owned(result), undefined)
+}
+
+/// Playlist text with its `#EXT-X-DEFINE` variables substituted in every
+/// URI and attribute, as a client would read it, together with the names of
+/// variables referenced but never defined. `imported` provides the
+/// variables of the master playlist for `IMPORT` declarations.
+pub fn substitute_variables_with<'a>(
+    content: &'a str,
+    base_url: &str,
+    imported: &BTreeMap<String, String>,
+) -> (Cow<'a, str>, Vec<String>) {
+    if !content.contains("#EXT-X-DEFINE") {
+        return (Cow::Borrowed(content), Vec::new());
+    }
+    let variables = define_variables(content, base_url, imported);
+    substitute(content, &variables)
+}
+
+/// [`substitute_variables_with`] without imported variables, which is how
+/// the parsers in this crate read playlists.
+pub fn substitute_variables<'a>(content: &'a str, base_url: &str) -> Cow<'a, str> {
+    substitute_variables_with(content, base_url, &BTreeMap::new()).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitute_variables() {
+        let content = "#EXTM3U\n\
+            #EXT-X-DEFINE:NAME=\"cdn\",VALUE=\"https://cdn.example.com\"\n\
+            #EXT-X-DEFINE:QUERYPARAM=\"token\"\n\
+            #EXT-X-DEFINE:IMPORT=\"session\"\n\
+            #EXTINF:6,\n\
+            {$cdn}/seg0.ts?token={$token}&s={$session}&x={$missing}\n";
+        let imported = BTreeMap::from([("session".to_string(), "42".to_string())]);
+        let (text, undefined) =
+            substitute_variables_with(content, "https://example.com/index.m3u8?token=a%20b", &imported);
+        assert!(text.contains("https://cdn.example.com/seg0.ts?token=a b&s=42&x={$missing}\n"));
+        assert_eq!(undefined, vec!["missing".to_string()]);
+
+        assert!(matches!(substitute_variables("#EXTM3U\nseg.ts\n", ""), Cow::Borrowed(_)));
+    }
+}
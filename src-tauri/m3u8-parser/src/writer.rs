@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::M3u8Error;
+
+/// An alternative rendition, written as an `#EXT-X-MEDIA` tag.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Rendition {
+    /// `AUDIO`, `SUBTITLES` or `CLOSED-CAPTIONS`.
+    #[serde(rename = "type")]
+    pub media_type: String,
+    pub group_id: String,
+    pub name: String,
+    pub language: Option<String>,
+    /// Media playlist of the rendition. Omitted for closed captions and for
+    /// audio muxed into the variant streams.
+    pub uri: Option<String>,
+    pub default: bool,
+    pub autoselect: bool,
+    /// Audio channel count, e.g. `2` or `6`.
+    pub channels: Option<String>,
+    /// `CC1`..`CC4` or `SERVICE1`..`SERVICE63`, for closed captions.
+    pub instream_id: Option<String>,
+}
+
+/// A variant stream, written as an `#EXT-X-STREAM-INF` tag and its URI.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct VariantStream {
+    pub uri: String,
+    /// Peak bits per second.
+    pub bandwidth: u64,
+    pub average_bandwidth: Option<u64>,
+    /// `WIDTHxHEIGHT`.
+    pub resolution: Option<String>,
+    pub codecs: Option<String>,
+    pub frame_rate: Option<f32>,
+    /// `GROUP-ID` of the audio renditions to play with this variant.
+    pub audio: Option<String>,
+    pub subtitles: Option<String>,
+    /// `GROUP-ID` of the closed captions, or `NONE`.
+    pub closed_captions: Option<String>,
+}
+
+/// The contents of a master playlist for [`write_master_playlist`].
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct MasterPlaylist {
+    /// `#EXT-X-VERSION`; the lowest version the tags need when absent.
+    pub version: Option<u8>,
+    /// Adds `#EXT-X-INDEPENDENT-SEGMENTS`.
+    pub independent_segments: bool,
+    pub renditions: Vec<Rendition>,
+    pub variants: Vec<VariantStream>,
+}
+
+fn quoted(value: &str) -> Result<String, M3u8Error> {
+    if value.contains(['"', '\n', '\r']) {
+        return Err(M3u8Error::ParseError(format!("Attribute value cannot contain quotes or line breaks: {}", value)));
+    }
+    Ok(format!("\"{}\"", value))
+}
+
+fn check_uri(uri: &str) -> Result<(), M3u8Error> {
+    if uri.trim().is_empty() || uri.contains(['\n', '\r']) || uri.starts_with('#') {
+        return Err(M3u8Error::InvalidUrl(uri.to_string()));
+    }
+    Ok(())
+}
+
+/// Group a variant attribute refers to must exist with the matching type.
+fn check_group(
+    groups: &HashMap<(&str, &str), usize>,
+    media_type: &str,
+    group: Option<&str>,
+    variant: &str,
+) -> Result<(), M3u8Error> {
+    match group {
+        Some(group) if !(media_type == "CLOSED-CAPTIONS" && group == "NONE") => {
+            if !groups.contains_key(&(media_type, group)) {
+                return Err(M3u8Error::ParseError(format!(
+                    "Variant {} refers to {} group \"{}\", which has no renditions",
+                    variant, media_type, group
+                )));
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Writes a master playlist. Renditions are grouped by `GROUP-ID` and the
+/// variants' `AUDIO`, `SUBTITLES` and `CLOSED-CAPTIONS` attributes are checked
+/// against those groups, so the result is consistent; URIs are written as
+/// given.
+pub fn write_master_playlist(playlist: &MasterPlaylist) -> Result<String, M3u8Error> {
+    if playlist.variants.is_empty() {
+        return Err(M3u8Error::ParseError("A master playlist needs at least one variant".to_string()));
+    }
+
+    let mut groups: HashMap<(&str, &str), usize> = HashMap::new();
+    let mut names: HashMap<(&str, &str), Vec<&str>> = HashMap::new();
+    for rendition in &playlist.renditions {
+        let media_type = rendition.media_type.as_str();
+        if !matches!(media_type, "AUDIO" | "SUBTITLES" | "CLOSED-CAPTIONS") {
+            return Err(M3u8Error::ParseError(format!("Unknown rendition type: {}", media_type)));
+        }
+        if rendition.group_id.is_empty() || rendition.name.is_empty() {
+            return Err(M3u8Error::ParseError("Renditions need a group_id and a name".to_string()));
+        }
+        match (media_type, &rendition.uri, &rendition.instream_id) {
+            ("SUBTITLES", None, _) => {
+                return Err(M3u8Error::ParseError(format!("Subtitle rendition {} needs a URI", rendition.name)));
+            }
+            ("CLOSED-CAPTIONS", Some(_), _) => {
+                return Err(M3u8Error::ParseError(format!("Closed captions {} cannot have a URI", rendition.name)));
+            }
+            ("CLOSED-CAPTIONS", None, None) => {
+                return Err(M3u8Error::ParseError(format!("Closed captions {} need an instream_id", rendition.name)));
+            }
+            (_, Some(uri), _) => check_uri(uri)?,
+            _ => {}
+        }
+        let key = (media_type, rendition.group_id.as_str());
+        let group_names = names.entry(key).or_default();
+        if group_names.contains(&rendition.name.as_str()) {
+            return Err(M3u8Error::ParseError(format!(
+                "Rendition name \"{}\" is used twice in group \"{}\"",
+                rendition.name, rendition.group_id
+            )));
+        }
+        group_names.push(&rendition.name);
+        let defaults = groups.entry(key).or_insert(0);
+        if rendition.default {
+            *defaults += 1;
+            if *defaults > 1 {
+                return Err(M3u8Error::ParseError(format!(
+                    "Group \"{}\" has more than one DEFAULT rendition",
+                    rendition.group_id
+                )));
+            }
+        }
+    }
+
+    for variant in &playlist.variants {
+        check_uri(&variant.uri)?;
+        if variant.bandwidth == 0 {
+            return Err(M3u8Error::ParseError(format!("Variant {} needs a bandwidth", variant.uri)));
+        }
+        check_group(&groups, "AUDIO", variant.audio.as_deref(), &variant.uri)?;
+        check_group(&groups, "SUBTITLES", variant.subtitles.as_deref(), &variant.uri)?;
+        check_group(&groups, "CLOSED-CAPTIONS", variant.closed_captions.as_deref(), &variant.uri)?;
+    }
+
+    // INSTREAM-ID=SERVICEn needs version 7; everything else here works with 3
+    let needs_seven = playlist.renditions.iter()
+        .any(|r| r.instream_id.as_deref().is_some_and(|id| id.starts_with("SERVICE")));
+    let version = playlist.version.unwrap_or(if needs_seven { 7 } else { 3 });
+
+    let mut out = String::from("#EXTM3U\n");
+    let _ = writeln!(out, "#EXT-X-VERSION:{}", version);
+    if playlist.independent_segments {
+        out.push_str("#EXT-X-INDEPENDENT-SEGMENTS\n");
+    }
+
+    if !playlist.renditions.is_empty() {
+        out.push('\n');
+    }
+    for rendition in &playlist.renditions {
+        let mut attributes = vec![
+            format!("TYPE={}", rendition.media_type),
+            format!("GROUP-ID={}", quoted(&rendition.group_id)?),
+        ];
+        if let Some(language) = &rendition.language {
+            attributes.push(format!("LANGUAGE={}", quoted(language)?));
+        }
+        attributes.push(format!("NAME={}", quoted(&rendition.name)?));
+        attributes.push(format!("DEFAULT={}", if rendition.default { "YES" } else { "NO" }));
+        // DEFAULT=YES requires AUTOSELECT=YES
+        let autoselect = rendition.autoselect || rendition.default;
+        attributes.push(format!("AUTOSELECT={}", if autoselect { "YES" } else { "NO" }));
+        if let Some(instream_id) = &rendition.instream_id {
+            attributes.push(format!("INSTREAM-ID={}", quoted(instream_id)?));
+        }
+        if let Some(channels) = &rendition.channels {
+            attributes.push(format!("CHANNELS={}", quoted(channels)?));
+        }
+        if let Some(uri) = &rendition.uri {
+            attributes.push(format!("URI={}", quoted(uri)?));
+        }
+        let _ = writeln!(out, "#EXT-X-MEDIA:{}", attributes.join(","));
+    }
+
+    for variant in &playlist.variants {
+        let mut attributes = vec![format!("BANDWIDTH={}", variant.bandwidth)];
+        if let Some(average) = variant.average_bandwidth {
+            attributes.push(format!("AVERAGE-BANDWIDTH={}", average));
+        }
+        if let Some(codecs) = &variant.codecs {
+            attributes.push(format!("CODECS={}", quoted(codecs)?));
+        }
+        if let Some(resolution) = &variant.resolution {
+            attributes.push(format!("RESOLUTION={}", resolution));
+        }
+        if let Some(frame_rate) = variant.frame_rate {
+            attributes.push(format!("FRAME-RATE={:.3}", frame_rate));
+        }
+        if let Some(audio) = &variant.audio {
+            attributes.push(format!("AUDIO={}", quoted(audio)?));
+        }
+        if let Some(subtitles) = &variant.subtitles {
+            attributes.push(format!("SUBTITLES={}", quoted(subtitles)?));
+        }
+        match variant.closed_captions.as_deref() {
+            Some("NONE") => attributes.push("CLOSED-CAPTIONS=NONE".to_string()),
+            Some(group) => attributes.push(format!("CLOSED-CAPTIONS={}", quoted(group)?)),
+            None => {}
+        }
+        let _ = write!(out, "\n#EXT-X-STREAM-INF:{}\n{}\n", attributes.join(","), variant.uri);
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_playlist;
+    use crate::types::ParsedPlaylist;
+
+    #[test]
+    fn test_write_master_playlist() {
+        let mut playlist = MasterPlaylist {
+            independent_segments: true,
+            renditions: vec![
+                Rendition {
+                    media_type: "AUDIO".to_string(),
+                    group_id: "aac".to_string(),
+                    name: "English".to_string(),
+                    language: Some("en".to_string()),
+                    uri: Some("audio/en.m3u8".to_string()),
+                    default: true,
+                    channels: Some("2".to_string()),
+                    ..Rendition::default()
+                },
+                Rendition {
+                    media_type: "SUBTITLES".to_string(),
+                    group_id: "subs".to_string(),
+                    name: "English".to_string(),
+                    language: Some("en".to_string()),
+                    uri: Some("subs/en.m3u8".to_string()),
+                    ..Rendition::default()
+                },
+            ],
+            variants: vec![
+                VariantStream {
+                    uri: "1080p.m3u8".to_string(),
+                    bandwidth: 5_000_000,
+                    resolution: Some("1920x1080".to_string()),
+                    frame_rate: Some(30.0),
+                    audio: Some("aac".to_string()),
+                    subtitles: Some("subs".to_string()),
+                    closed_captions: Some("NONE".to_string()),
+                    ..VariantStream::default()
+                },
+                VariantStream {
+                    uri: "720p.m3u8".to_string(),
+                    bandwidth: 2_500_000,
+                    audio: Some("aac".to_string()),
+                    ..VariantStream::default()
+                },
+            ],
+            ..MasterPlaylist::default()
+        };
+
+        let text = write_master_playlist(&playlist).unwrap();
+        assert!(text.contains(
+            "#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aac\",LANGUAGE=\"en\",NAME=\"English\",DEFAULT=YES,AUTOSELECT=YES,CHANNELS=\"2\",URI=\"audio/en.m3u8\""
+        ));
+        assert!(text.contains("FRAME-RATE=30.000,AUDIO=\"aac\",SUBTITLES=\"subs\",CLOSED-CAPTIONS=NONE\n1080p.m3u8\n"));
+        match parse_playlist(&text, "https://example.com/live/master.m3u8").unwrap() {
+            ParsedPlaylist::Master { version, variants } => {
+                assert_eq!(version, Some(3));
+                assert_eq!(variants.len(), 2);
+                assert_eq!(variants[0].uri, "https://example.com/live/1080p.m3u8");
+                assert_eq!(variants[1].bandwidth, 2_500_000);
+            }
+            ParsedPlaylist::Media { .. } => panic!("expected a master playlist"),
+        }
+
+        // Variants may only refer to groups that exist
+        playlist.variants[1].subtitles = Some("missing".to_string());
+        assert!(write_master_playlist(&playlist).is_err());
+    }
+}
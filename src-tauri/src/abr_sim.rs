@@ -0,0 +1,297 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::m3u8_parser::{self, M3u8Parser, ParsedPlaylist};
+
+// Playback of a master playlist by a throughput-based ABR client, simulated
+// against a bandwidth profile instead of a real network. Segment sizes are
+// estimated from each variant's (average) bandwidth, so the result shows how
+// the ladder behaves, not how a particular encode would.
+
+// Seconds of content simulated unless the caller says otherwise
+pub const DEFAULT_DURATION: f64 = 300.0;
+
+// Bandwidth from `from` seconds on, until the next step
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BandwidthStep {
+    pub from: f64,
+    pub kbps: f64,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct SimOptions {
+    // Seconds of media to simulate
+    pub duration: f64,
+    // Buffer the client fills up to before it pauses fetching
+    pub max_buffer: f64,
+    // Share of the estimated bandwidth a variant may use
+    pub safety: f64,
+}
+
+impl Default for SimOptions {
+    fn default() -> Self {
+        Self { duration: DEFAULT_DURATION, max_buffer: 30.0, safety: 0.8 }
+    }
+}
+
+// A variant as the client sees it
+#[derive(Debug, Clone, Serialize)]
+pub struct Rung {
+    pub uri: String,
+    // BANDWIDTH, which the client picks by
+    pub bandwidth: u64,
+    // AVERAGE-BANDWIDTH when given, else BANDWIDTH; segment sizes follow it
+    pub average_bandwidth: u64,
+    pub resolution: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimSegment {
+    pub index: usize,
+    // Wall-clock seconds since playback was requested when the fetch began
+    pub requested_at: f64,
+    pub variant: usize,
+    pub bandwidth: u64,
+    // Seconds the fetch took
+    pub download_time: f64,
+    // Seconds playback stood still waiting for this segment
+    pub stall: f64,
+    // Buffered seconds once it arrived
+    pub buffer: f64,
+    // Client's bandwidth estimate afterwards, kbps
+    pub estimate_kbps: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VariantUsage {
+    pub variant: usize,
+    pub uri: String,
+    pub bandwidth: u64,
+    pub resolution: Option<String>,
+    pub segments: usize,
+    // Seconds of media played from it
+    pub seconds: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Simulation {
+    pub profile: Vec<BandwidthStep>,
+    // Seconds from the request to the first frame
+    pub startup_delay: f64,
+    // Times playback stalled after it started, and for how long in all
+    pub rebuffers: usize,
+    pub rebuffer_time: f64,
+    pub switches: usize,
+    // Mean bandwidth of the variants played, weighted by time
+    pub average_kbps: f64,
+    pub variants: Vec<VariantUsage>,
+    pub timeline: Vec<SimSegment>,
+}
+
+// Bandwidth in kbps at `time`
+fn kbps_at(profile: &[BandwidthStep], time: f64) -> f64 {
+    profile.iter().rev().find(|step| step.from <= time).or(profile.first()).map_or(0.0, |step| step.kbps)
+}
+
+// Seconds to move `bits` starting at `time` under the profile
+fn transfer_time(profile: &[BandwidthStep], time: f64, bits: f64) -> f64 {
+    let mut now = time;
+    let mut left = bits;
+    loop {
+        // Never quite 0, so a dead link only makes the fetch very slow
+        let rate = kbps_at(profile, now).max(1.0) * 1000.0;
+        let next = profile.iter().map(|step| step.from).find(|&from| from > now);
+        match next {
+            Some(next) if left > rate * (next - now) => {
+                left -= rate * (next - now);
+                now = next;
+            }
+            _ => return now + left / rate - time,
+        }
+    }
+}
+
+// Simulate playback of segments of `durations` (repeated when the media is
+// shorter than options.duration) from `rungs`, lowest bandwidth first
+pub fn simulate(rungs: &[Rung], durations: &[f64], profile: &[BandwidthStep], options: &SimOptions) -> Simulation {
+    let mut timeline = Vec::new();
+    let mut usage: Vec<VariantUsage> = rungs
+        .iter()
+        .enumerate()
+        .map(|(variant, rung)| VariantUsage {
+            variant,
+            uri: rung.uri.clone(),
+            bandwidth: rung.bandwidth,
+            resolution: rung.resolution.clone(),
+            segments: 0,
+            seconds: 0.0,
+        })
+        .collect();
+    let (mut now, mut buffer, mut media) = (0.0f64, 0.0f64, 0.0f64);
+    let (mut fast, mut slow): (Option<f64>, Option<f64>) = (None, None);
+    let (mut startup_delay, mut rebuffers, mut rebuffer_time, mut switches) = (0.0, 0, 0.0, 0);
+    let mut previous: Option<usize> = None;
+
+    let durations: Vec<f64> = durations.iter().copied().filter(|d| *d > 0.0).collect();
+    if rungs.is_empty() || durations.is_empty() {
+        return Simulation { profile: profile.to_vec(), startup_delay, rebuffers, rebuffer_time, switches, average_kbps: 0.0, variants: usage, timeline };
+    }
+
+    for (index, duration) in durations.iter().cycle().enumerate() {
+        if media >= options.duration {
+            break;
+        }
+        // Wait while the buffer is full
+        if buffer > options.max_buffer {
+            now += buffer - options.max_buffer;
+            buffer = options.max_buffer;
+        }
+        // The lowest variant until there is a measurement, then the highest
+        // the estimate affords
+        let estimate = match (fast, slow) {
+            (Some(fast), Some(slow)) => Some(fast.min(slow)),
+            _ => None,
+        };
+        let variant = match estimate {
+            Some(estimate) => rungs
+                .iter()
+                .rposition(|rung| rung.bandwidth as f64 <= estimate * options.safety)
+                .unwrap_or(0),
+            None => 0,
+        };
+        let bits = rungs[variant].average_bandwidth as f64 * duration;
+        let download_time = transfer_time(profile, now, bits);
+
+        let stall = if index == 0 {
+            startup_delay = download_time;
+            0.0
+        } else {
+            (download_time - buffer).max(0.0)
+        };
+        if stall > 0.0 {
+            rebuffers += 1;
+            rebuffer_time += stall;
+        }
+        buffer = (buffer - download_time).max(0.0) + duration;
+        let measured = bits / download_time.max(0.001);
+        fast = Some(fast.map_or(measured, |fast| 0.5 * measured + 0.5 * fast));
+        slow = Some(slow.map_or(measured, |slow| 0.15 * measured + 0.85 * slow));
+
+        if previous.is_some_and(|previous| previous != variant) {
+            switches += 1;
+        }
+        previous = Some(variant);
+        usage[variant].segments += 1;
+        usage[variant].seconds += duration;
+        timeline.push(SimSegment {
+            index,
+            requested_at: now,
+            variant,
+            bandwidth: rungs[variant].bandwidth,
+            download_time,
+            stall,
+            buffer,
+            estimate_kbps: fast.unwrap_or(0.0).min(slow.unwrap_or(0.0)) / 1000.0,
+        });
+        now += download_time;
+        media += duration;
+    }
+
+    let played: f64 = usage.iter().map(|u| u.seconds).sum();
+    let average_kbps = usage.iter().map(|u| u.bandwidth as f64 * u.seconds).sum::<f64>() / played.max(f64::EPSILON) / 1000.0;
+    Simulation { profile: profile.to_vec(), startup_delay, rebuffers, rebuffer_time, switches, average_kbps, variants: usage, timeline }
+}
+
+// Profile from the tool arguments: a list of steps, or one constant rate
+pub fn profile(steps: Option<Vec<BandwidthStep>>, kbps: Option<f64>) -> Result<Vec<BandwidthStep>, AppError> {
+    let mut steps = match (steps, kbps) {
+        (Some(steps), _) if !steps.is_empty() => steps,
+        (_, Some(kbps)) => vec![BandwidthStep { from: 0.0, kbps }],
+        _ => return Err(AppError::invalid_input("Give a bandwidth profile or a constant kbps")),
+    };
+    if steps.iter().any(|step| step.from < 0.0 || step.kbps < 0.0 || !step.from.is_finite() || !step.kbps.is_finite()) {
+        return Err(AppError::invalid_input("Profile steps need a non-negative from and kbps"));
+    }
+    steps.sort_by(|a, b| a.from.total_cmp(&b.from));
+    Ok(steps)
+}
+
+// Simulate playback of `url`, a master playlist, under `profile`. Segment
+// durations come from the lowest variant's media playlist.
+pub async fn run(parser: &M3u8Parser, url: &str, profile: &[BandwidthStep], options: &SimOptions) -> Result<Simulation, AppError> {
+    let content = parser.fetch_playlist(url).await?;
+    let ParsedPlaylist::Master { variants, .. } = parser.parse_content(&content, url)? else {
+        return Err(AppError::invalid_input("ABR simulation needs a master playlist"));
+    };
+    let attributes = m3u8_parser::variant_attributes(&content, url);
+    let mut rungs: Vec<Rung> = variants
+        .iter()
+        .enumerate()
+        .map(|(index, variant)| {
+            let average = attributes
+                .get(index)
+                .and_then(|attributes| attributes.iter().find(|(name, _)| name == "AVERAGE-BANDWIDTH"))
+                .and_then(|(_, value)| value.parse().ok());
+            Rung {
+                uri: variant.uri.clone(),
+                bandwidth: variant.bandwidth,
+                average_bandwidth: average.unwrap_or(variant.bandwidth),
+                resolution: variant.resolution.clone(),
+            }
+        })
+        .filter(|rung| rung.bandwidth > 0)
+        .collect();
+    if rungs.is_empty() {
+        return Err(AppError::invalid_input("Master playlist has no variants with a bandwidth"));
+    }
+    rungs.sort_by_key(|rung| rung.bandwidth);
+
+    let ParsedPlaylist::Media { segments, .. } = parser.parse_url(&rungs[0].uri).await? else {
+        return Err(AppError::invalid_input("Variant playlist is itself a master playlist"));
+    };
+    let durations: Vec<f64> = segments.iter().map(|segment| segment.duration as f64).collect();
+    if durations.iter().all(|duration| *duration <= 0.0) {
+        return Err(AppError::invalid_input("Variant playlist has no segments"));
+    }
+    Ok(simulate(&rungs, &durations, profile, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rung(kbps: u64) -> Rung {
+        Rung { uri: format!("{}k.m3u8", kbps), bandwidth: kbps * 1000, average_bandwidth: kbps * 1000, resolution: None }
+    }
+
+    #[test]
+    fn test_simulate() {
+        let rungs = [rung(500), rung(1500), rung(3000)];
+        let durations = [6.0; 10];
+        let options = SimOptions { duration: 120.0, ..SimOptions::default() };
+
+        // Plenty of bandwidth: climbs to the top and stays there
+        let steady = simulate(&rungs, &durations, &profile(None, Some(10_000.0)).unwrap(), &options);
+        assert_eq!(steady.timeline.len(), 20);
+        assert_eq!(steady.timeline.last().unwrap().variant, 2);
+        assert_eq!(steady.rebuffers, 0);
+        assert_eq!(steady.switches, 1);
+
+        // A deep dip in the middle of a top-variant fetch: stalls, steps down
+        // and climbs back
+        let dip = vec![
+            BandwidthStep { from: 0.0, kbps: 5000.0 },
+            BandwidthStep { from: 20.0, kbps: 300.0 },
+            BandwidthStep { from: 60.0, kbps: 5000.0 },
+        ];
+        let dipped = simulate(&rungs, &durations, &profile(Some(dip), None).unwrap(), &options);
+        assert_eq!(dipped.rebuffers, 1);
+        assert!(dipped.timeline.iter().any(|segment| segment.variant == 1 && segment.index > 1));
+        assert_eq!(dipped.timeline.last().unwrap().variant, 2);
+        assert_eq!(dipped.switches, 3);
+
+        assert_eq!(transfer_time(&[BandwidthStep { from: 0.0, kbps: 1000.0 }, BandwidthStep { from: 2.0, kbps: 2000.0 }], 1.0, 3_000_000.0), 2.0);
+        assert!(profile(None, None).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use futures::future::join_all;
+use serde_json::{json, Value};
+
+use crate::m3u8_parser::{self, M3u8Error, M3u8Parser, ParsedPlaylist, Segment};
+
+// Fetch a media playlist. A master playlist resolves to its first variant,
+// matching extract_segments. Returns the media playlist URL alongside.
+pub async fn fetch_media_playlist(
+    parser: &M3u8Parser,
+    url: &str,
+) -> Result<(String, Option<u64>, Vec<Segment>), M3u8Error> {
+    match parser.parse_url(url).await? {
+        ParsedPlaylist::Media { target_duration, segments, .. } => {
+            Ok((url.to_string(), target_duration, segments))
+        }
+        ParsedPlaylist::Master { variants, .. } => {
+            let variant = variants.first()
+                .ok_or_else(|| M3u8Error::ParseError("Master playlist has no variants".to_string()))?;
+            match parser.parse_url(&variant.uri).await? {
+                ParsedPlaylist::Media { target_duration, segments, .. } => {
+                    Ok((variant.uri.clone(), target_duration, segments))
+                }
+                ParsedPlaylist::Master { .. } => Err(M3u8Error::ParseError(
+                    "Variant playlist is itself a master playlist".to_string(),
+                )),
+            }
+        }
+    }
+}
+
+// Sizes of up to `max_samples` evenly spaced segments via HEAD requests
+async fn sample_segment_sizes(parser: &M3u8Parser, segments: &[Segment], max_samples: usize) -> Vec<Option<u64>> {
+    let count = segments.len();
+    let indices: Vec<usize> = if count <= max_samples {
+        (0..count).collect()
+    } else {
+        (0..max_samples).map(|k| k * count / max_samples).collect()
+    };
+
+    let lengths = join_all(
+        indices.iter().map(|&i| parser.head_content_length(&segments[i].uri))
+    ).await;
+
+    let mut sizes = vec![None; count];
+    for (i, length) in indices.into_iter().zip(lengths) {
+        sizes[i] = length.ok().flatten();
+    }
+    sizes
+}
+
+// Statistics and anomaly report for a media playlist (m3u8_stats)
+pub async fn playlist_stats(
+    parser: &M3u8Parser,
+    url: &str,
+    sample_sizes: bool,
+    max_samples: usize,
+) -> Result<Value, M3u8Error> {
+    let (media_url, target_duration, segments) = fetch_media_playlist(parser, url).await?;
+
+    let sizes = if sample_sizes {
+        sample_segment_sizes(parser, &segments, max_samples).await
+    } else {
+        Vec::new()
+    };
+
+    let stats = m3u8_parser::media_stats(&segments, target_duration, &sizes);
+    Ok(json!({
+        "url": media_url,
+        "stats": stats
+    }))
+}
@@ -0,0 +1,186 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::config::{AppConfig, DownloaderConfig};
+use crate::error::{AppError, ErrorKind};
+use crate::events::{self, JobEvent, JobUpdate};
+use crate::ffmpeg_wrapper::FFmpegService;
+use crate::i18n::{self, Msg};
+
+// A file for aria2c to fetch: the URLs serving it, tried in order, and the
+// name to save it under
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub sources: Vec<String>,
+    pub out: String,
+}
+
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    let name = if cfg!(windows) { format!("{}.exe", name) } else { name.to_string() };
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&name))
+        .find(|path| path.is_file())
+}
+
+// aria2c -i input: each entry's URLs on one line, separated by tabs, then
+// its options indented on the lines after
+pub fn input_file(entries: &[Entry], headers: &[(String, String)]) -> String {
+    let mut input = String::new();
+    for entry in entries {
+        input.push_str(&entry.sources.join("\t"));
+        input.push('\n');
+        input.push_str(&format!("  out={}\n", entry.out));
+        for (name, value) in headers {
+            input.push_str(&format!("  header={}: {}\n", name, value));
+        }
+    }
+    input
+}
+
+// File named by a "Download complete: <path>" notice
+pub fn completed_file(line: &str) -> Option<&str> {
+    line.split_once("Download complete: ").map(|(_, path)| path.trim())
+}
+
+// A configured aria2c, ready to run
+pub struct Aria2c {
+    binary: PathBuf,
+    args: Vec<String>,
+    headers: Vec<(String, String)>,
+    // Time aria2c gets to exit when stopped, see processes.kill_grace_secs
+    grace: Duration,
+}
+
+impl Aria2c {
+    // aria2c as configured, fetching `parallel` files at once. None when it
+    // is disabled or cannot be found.
+    pub fn from_config(config: &DownloaderConfig, app_config: &AppConfig, parallel: usize) -> Result<Option<Self>, AppError> {
+        if !config.aria2c.enabled {
+            return Ok(None);
+        }
+        let binary = match &config.aria2c.path {
+            Some(path) => Some(crate::paths::expand_path(path)),
+            None => find_on_path("aria2c"),
+        };
+        let Some(binary) = binary else {
+            eprintln!("aria2c is enabled but not on PATH; fetching segments natively");
+            return Ok(None);
+        };
+
+        let mut args = vec![
+            format!("--max-concurrent-downloads={}", parallel.max(1)),
+            // One connection per segment; extra URLs are fallbacks
+            "--split=1".to_string(),
+            format!("--max-tries={}", config.aria2c.max_tries.max(1)),
+            "--retry-wait=1".to_string(),
+            "--auto-file-renaming=false".to_string(),
+            "--allow-overwrite=true".to_string(),
+            "--file-allocation=none".to_string(),
+            "--console-log-level=notice".to_string(),
+            "--show-console-readout=false".to_string(),
+            "--summary-interval=0".to_string(),
+            format!("--user-agent={}", crate::profiles::USER_AGENT),
+        ];
+        if let Some(proxy) = crate::profiles::proxy_url(app_config)? {
+            args.push(format!("--all-proxy={}", proxy));
+        }
+        let headers = config.aria2c.headers.iter().map(|(name, value)| (name.clone(), value.clone())).collect();
+        let grace = crate::process::grace(&app_config.processes);
+        Ok(Some(Self { binary, args, headers, grace }))
+    }
+
+    // Fetch `entries` into `dir`, reporting progress as job events. Returns
+    // the file of each entry, None for those aria2c gave up on; only failing
+    // to run aria2c at all, or cancellation, is an error.
+    pub async fn fetch(
+        &self,
+        ffmpeg: &FFmpegService,
+        job_id: &str,
+        entries: &[Entry],
+        dir: &Path,
+    ) -> Result<Vec<Option<PathBuf>>, AppError> {
+        std::fs::create_dir_all(dir)?;
+        let input_path = dir.join("input.txt");
+        std::fs::write(&input_path, input_file(entries, &self.headers))?;
+
+        let mut child = crate::process::spawn(tokio::process::Command::new(&self.binary)
+            .args(&self.args)
+            .arg(format!("--input-file={}", input_path.display()))
+            .arg(format!("--dir={}", dir.display()))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null()))
+            .map_err(|e| AppError::internal(format!("Failed to run {}: {}", self.binary.display(), e)))?;
+        crate::job_log::note(job_id, &format!("aria2c fetching {} segments", entries.len()));
+
+        let stdout = child.stdout.take().ok_or_else(|| AppError::internal("aria2c has no output to read"))?;
+        let mut lines = BufReader::new(stdout).lines();
+        let (mut done, mut bytes) = (0usize, 0u64);
+        loop {
+            let line = match crate::cancellation::or_cancelled(lines.next_line()).await {
+                Some(Ok(Some(line))) => line,
+                Some(_) => break,
+                None => {
+                    let _ = child.stop(self.grace).await;
+                    return Err(AppError::new(ErrorKind::Ffmpeg, i18n::t(Msg::DownloadCancelled)));
+                }
+            };
+            if !ffmpeg.is_job_active(job_id).await {
+                let _ = child.stop(self.grace).await;
+                return Err(AppError::new(ErrorKind::Ffmpeg, i18n::t(Msg::DownloadCancelled)));
+            }
+            if line.contains("[ERROR]") {
+                crate::job_log::note(job_id, line.trim());
+            }
+            let Some(file) = completed_file(&line) else {
+                continue;
+            };
+            done += 1;
+            bytes += std::fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+            ffmpeg.emit(events::DOWNLOAD_PROGRESS, JobEvent::new(
+                job_id,
+                format!("aria2c: Segments: {}/{} | Size: {}kB", done, entries.len(), bytes / 1024),
+                JobUpdate::segments(done, entries.len(), bytes, Some("aria2c")),
+            ));
+        }
+
+        let status = child.wait().await?;
+        if !status.success() {
+            crate::job_log::note(job_id, &format!("aria2c exited with {}; fetching the rest natively", status));
+        }
+        // aria2c keeps a .aria2 control file next to files it did not finish
+        Ok(entries
+            .iter()
+            .map(|entry| {
+                let path = dir.join(&entry.out);
+                let mut control = path.clone().into_os_string();
+                control.push(".aria2");
+                (path.is_file() && !Path::new(&control).exists()).then_some(path)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_file() {
+        let entries = [Entry {
+            sources: vec!["https://a.example.com/seg0.ts".to_string(), "https://b.example.com/seg0.ts".to_string()],
+            out: "00000.ts".to_string(),
+        }];
+        let headers = [("Referer".to_string(), "https://example.com/".to_string())];
+        assert_eq!(
+            input_file(&entries, &headers),
+            "https://a.example.com/seg0.ts\thttps://b.example.com/seg0.ts\n  out=00000.ts\n  header=Referer: https://example.com/\n"
+        );
+        assert_eq!(
+            completed_file("05/01 10:00:00 [NOTICE] Download complete: /tmp/show.mp4.aria2c/00000.ts"),
+            Some("/tmp/show.mp4.aria2c/00000.ts")
+        );
+        assert_eq!(completed_file("[ERROR] CUID#7 - Download aborted."), None);
+    }
+}
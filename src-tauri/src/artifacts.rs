@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::error::AppError;
+
+// Token remote clients send to fetch artifacts, created on first use and
+// kept in the config file
+pub fn auth_token() -> Result<String, AppError> {
+    let mut app_config = config::load_config()?;
+    if let Some(token) = app_config.mcp.auth_token.clone().filter(|t| !t.is_empty()) {
+        return Ok(token);
+    }
+    let token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+    app_config.mcp.auth_token = Some(token.clone());
+    config::save_config(&app_config)?;
+    Ok(token)
+}
+
+// Compare in constant time so the token cannot be guessed byte by byte
+pub fn token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected.bytes().zip(given.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn server_url(host: &str, port: u16) -> String {
+    let host = if config::is_loopback_host(host) || host == "0.0.0.0" || host == "::" { "localhost" } else { host };
+    format!("http://{}:{}", host, port)
+}
+
+// Where a job's artifacts are listed on a server reachable at host:port
+pub fn artifacts_url(host: &str, port: u16, job_id: &str) -> String {
+    format!("{}/artifacts/{}", server_url(host, port), job_id)
+}
+
+// Where the AES-128 key of a conversion is served, see record_packaged
+pub fn key_url(host: &str, port: u16, job_id: &str) -> String {
+    format!("{}/keys/{}", server_url(host, port), job_id)
+}
+
+// Record a file produced by a job in the global database, if it is initialized
+pub async fn record(job_id: &str, kind: &str, path: &Path) {
+    let db = crate::database::GLOBAL_DB.read().await.clone();
+    if let Some(db) = db {
+        if let Err(e) = db.save_artifact(job_id, kind, &path.to_string_lossy()) {
+            eprintln!("Failed to record artifact {}: {}", path.display(), e);
+        }
+    }
+}
+
+// Record a finished download and, for native downloads, its manifest
+pub async fn record_download(job_id: &str, output: &Path) {
+    record(job_id, "download", output).await;
+    let manifest = crate::native_download::manifest_path(output);
+    if manifest.exists() {
+        record(job_id, "manifest", &manifest).await;
+    }
+}
+
+// Record the manifests of a conversion, and its key when it is to be
+// served; only recorded keys are served, from /keys/<job id> and the job's
+// artifacts
+pub async fn record_packaged(job_id: &str, output: &crate::ffmpeg_wrapper::PackagedOutput, serve_key: bool) {
+    if let Some(playlist) = &output.playlist {
+        record(job_id, "playlist", playlist).await;
+        if serve_key {
+            record(job_id, "key", &playlist.with_file_name("playlist.key")).await;
+        }
+    }
+    if let Some(mpd) = &output.mpd {
+        record(job_id, "mpd", mpd).await;
+    }
+}
+
+// Write generated text (a report) to ~/.m3u8-mcp/artifacts/<job_id>.<extension>
+pub fn save_text(job_id: &str, extension: &str, content: &str) -> Result<PathBuf, AppError> {
+    let dir = config::app_dir()?.join("artifacts");
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| AppError::io(format!("Failed to create artifact directory: {}", e)))?;
+    let path = dir.join(format!("{}.{}", job_id, extension));
+    std::fs::write(&path, content)
+        .map_err(|e| AppError::io(format!("Failed to write {}: {}", path.display(), e)))?;
+    Ok(path)
+}
+
+pub fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+        Some("mp4" | "m4v") => "video/mp4",
+        Some("mkv") => "video/x-matroska",
+        Some("mov") => "video/quicktime",
+        Some("ts") => "video/mp2t",
+        Some("m3u8") => "application/vnd.apple.mpegurl",
+        Some("json") => "application/json",
+        Some("csv") => "text/csv",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    }
+}
+
+// Inclusive byte range from a "bytes=start-end" header for a file of `len`
+// bytes. Multiple ranges are not supported; None serves the whole file.
+pub fn parse_range(header: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') || len == 0 {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        // Suffix range: the last N bytes
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (len.saturating_sub(suffix), len - 1)
+        }
+        (start, "") => (start.parse().ok()?, len - 1),
+        (start, end) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(len - 1)),
+    };
+    (start <= end && start < len).then_some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some((0, 99)));
+        assert_eq!(parse_range("bytes=900-", 1000), Some((900, 999)));
+        assert_eq!(parse_range("bytes=-100", 1000), Some((900, 999)));
+        assert_eq!(parse_range("bytes=500-5000", 1000), Some((500, 999)));
+        assert_eq!(parse_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), None);
+        assert!(token_matches("abc", "abc"));
+        assert!(!token_matches("abc", "abd"));
+        assert!(!token_matches("abc", "ab"));
+        assert_eq!(key_url("0.0.0.0", 37650, "job-1"), "http://localhost:37650/keys/job-1");
+        assert_eq!(artifacts_url("nas.local", 37650, "job-1"), "http://nas.local:37650/artifacts/job-1");
+    }
+}
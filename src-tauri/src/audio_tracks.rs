@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::ffmpeg_wrapper::{self, FFmpegService};
+
+// An audio stream of a finished download, as ffprobe reports it
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioTrack {
+    pub codec: Option<String>,
+    pub language: Option<String>,
+}
+
+// Audio streams in ffprobe's JSON (-show_streams), in order
+pub fn audio_tracks(probe: &serde_json::Value) -> Vec<AudioTrack> {
+    probe.get("streams")
+        .and_then(|streams| streams.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|stream| stream.get("codec_type").and_then(|t| t.as_str()) == Some("audio"))
+        .map(|stream| AudioTrack {
+            codec: stream.get("codec_name").and_then(|c| c.as_str()).map(String::from),
+            language: stream.pointer("/tags/language")
+                .and_then(|l| l.as_str())
+                .filter(|l| !l.is_empty() && *l != "und")
+                .map(String::from),
+        })
+        .collect()
+}
+
+// Container that takes a codec's stream as is
+fn extension_for(codec: Option<&str>) -> &'static str {
+    match codec {
+        Some("aac") | Some("alac") => "m4a",
+        Some("mp3") => "mp3",
+        _ => "mka",
+    }
+}
+
+// File of each track next to `output`: <name>.<language>.<ext>, with the
+// track number added where languages repeat or are missing
+pub fn track_paths(output: &Path, tracks: &[AudioTrack]) -> Vec<PathBuf> {
+    let stem = output.file_stem().and_then(|s| s.to_str()).unwrap_or("audio");
+    tracks
+        .iter()
+        .enumerate()
+        .map(|(index, track)| {
+            let label = match &track.language {
+                Some(language) if tracks.iter().filter(|t| t.language == track.language).count() == 1 => language.clone(),
+                Some(language) => format!("{}-{}", language, index + 1),
+                None => format!("audio{}", index + 1),
+            };
+            let name = format!("{}.{}.{}", stem, label, extension_for(track.codec.as_deref()));
+            output.with_file_name(crate::paths::sanitize_filename(&name))
+        })
+        .collect()
+}
+
+// Extract every audio track of `output` into its own file without
+// re-encoding; returns the files written
+pub async fn split(ffmpeg: &FFmpegService, output: &Path) -> Result<Vec<PathBuf>, AppError> {
+    let probe = ffmpeg.probe_stream(&output.to_string_lossy()).await?;
+    let probe: serde_json::Value = serde_json::from_str(&probe)
+        .map_err(|e| AppError::internal(format!("Failed to read ffprobe output: {}", e)))?;
+    let tracks = audio_tracks(&probe);
+    if tracks.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let paths = track_paths(output, &tracks);
+    let mut args = vec!["-y".to_string(), "-i".to_string(), output.to_string_lossy().to_string()];
+    for (index, path) in paths.iter().enumerate() {
+        args.extend([
+            "-map".to_string(),
+            format!("0:a:{}", index),
+            "-c".to_string(),
+            "copy".to_string(),
+        ]);
+        args.extend(ffmpeg_wrapper::output_args(path));
+    }
+    ffmpeg.run_ffmpeg(&args).await?;
+    for path in &paths {
+        ffmpeg_wrapper::finish_output(path)?;
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_paths() {
+        let probe = serde_json::json!({
+            "streams": [
+                { "codec_type": "video", "codec_name": "h264" },
+                { "codec_type": "audio", "codec_name": "aac", "tags": { "language": "eng" } },
+                { "codec_type": "audio", "codec_name": "ac3", "tags": { "language": "jpn" } },
+                { "codec_type": "audio", "codec_name": "aac", "tags": { "language": "eng" } },
+                { "codec_type": "audio", "codec_name": "aac", "tags": { "language": "und" } }
+            ]
+        });
+        let tracks = audio_tracks(&probe);
+        assert_eq!(tracks.len(), 4);
+        let names = track_paths(Path::new("/videos/show.mp4"), &tracks);
+        assert_eq!(names, vec![
+            PathBuf::from("/videos/show.eng-1.m4a"),
+            PathBuf::from("/videos/show.jpn.mka"),
+            PathBuf::from("/videos/show.eng-3.m4a"),
+            PathBuf::from("/videos/show.audio4.m4a"),
+        ]);
+    }
+}
@@ -0,0 +1,59 @@
+use std::future::Future;
+use std::time::Duration;
+
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+
+use crate::error::{AppError, ErrorKind};
+
+// Time cancelled work gets to stop its processes and remove partial files
+// before the caller stops waiting for it
+const CLEANUP_GRACE: Duration = Duration::from_secs(5);
+
+tokio::task_local! {
+    static TOKEN: CancellationToken;
+}
+
+// Token of the request the current task works for, if it has one
+pub fn current() -> Option<CancellationToken> {
+    TOKEN.try_with(|token| token.clone()).ok()
+}
+
+pub fn is_cancelled() -> bool {
+    current().is_some_and(|token| token.is_cancelled())
+}
+
+// Run `work` until it finishes or the current request is cancelled; None
+// when cancelled. Outside a request `work` just runs.
+pub async fn or_cancelled<F: Future>(work: F) -> Option<F::Output> {
+    match current() {
+        Some(token) => tokio::select! {
+            output = work => Some(output),
+            _ = token.cancelled() => None,
+        },
+        None => Some(work.await),
+    }
+}
+
+// Run `work` with its own cancellation token, cancelled once `limit` has
+// passed. Cancelled work gets a short grace period to clean up, then the
+// result is a Timeout error naming `what`. Without a limit `work` runs to
+// the end.
+pub async fn with_timeout<F: Future>(limit: Option<Duration>, what: &str, work: F) -> Result<F::Output, AppError> {
+    let token = CancellationToken::new();
+    let work = TOKEN.scope(token.clone(), work);
+    let Some(limit) = limit else {
+        return Ok(work.await);
+    };
+
+    tokio::pin!(work);
+    if let Ok(output) = tokio::time::timeout(limit, &mut work).await {
+        return Ok(output);
+    }
+    token.cancel();
+    if tokio::time::timeout(CLEANUP_GRACE, &mut work).await.is_err() {
+        eprintln!("{} did not stop within {}s of being cancelled", what, CLEANUP_GRACE.as_secs());
+    }
+    Err(AppError::new(ErrorKind::Timeout, format!("{} timed out after {}s", what, limit.as_secs()))
+        .with_details(json!({ "timeout_secs": limit.as_secs() })))
+}
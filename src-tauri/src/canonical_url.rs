@@ -0,0 +1,81 @@
+use crate::config::{self, CanonicalUrlConfig};
+
+fn is_stripped(name: &str, rules: &CanonicalUrlConfig) -> bool {
+    rules.strip_query_params.iter().any(|pattern| match pattern.strip_suffix('*') {
+        Some(prefix) => name.len() >= prefix.len()
+            && name.is_char_boundary(prefix.len())
+            && name[..prefix.len()].eq_ignore_ascii_case(prefix),
+        None => name.eq_ignore_ascii_case(pattern),
+    })
+}
+
+// `url` as a key: without the fragment and the stripped query parameters,
+// and with a lowercase host when the rules ask for it. The other
+// parameters keep their order and encoding. Local paths stay as they are.
+pub fn canonicalize(url: &str, rules: &CanonicalUrlConfig) -> String {
+    let Some(scheme_end) = url.find("://") else {
+        return url.to_string();
+    };
+    let url = url.split('#').next().unwrap_or(url);
+    let (base, query) = match url.split_once('?') {
+        Some((base, query)) => (base, Some(query)),
+        None => (url, None),
+    };
+
+    let mut key = if rules.lowercase_host {
+        let authority_start = scheme_end + 3;
+        let authority_end = base[authority_start..].find('/').map_or(base.len(), |end| authority_start + end);
+        // Credentials before the host keep their case
+        let host_start = base[authority_start..authority_end].rfind('@').map_or(authority_start, |at| authority_start + at + 1);
+        format!(
+            "{}{}{}",
+            &base[..host_start],
+            base[host_start..authority_end].to_ascii_lowercase(),
+            &base[authority_end..]
+        )
+    } else {
+        base.to_string()
+    };
+
+    let kept: Vec<&str> = query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .filter(|pair| !pair.is_empty())
+        .filter(|pair| !is_stripped(pair.split('=').next().unwrap_or(pair), rules))
+        .collect();
+    if !kept.is_empty() {
+        key.push('?');
+        key.push_str(&kept.join("&"));
+    }
+    key
+}
+
+// Key of `url` under the configured rules, for cache and history lookups
+pub fn key(url: &str) -> String {
+    canonicalize(url, &config::load_config().map(|c| c.canonical_urls).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        let rules = CanonicalUrlConfig::default();
+        assert_eq!(
+            canonicalize("https://User:Pw@CDN.Example.com/Live/index.m3u8?token=abc&quality=hd&X-Amz-Signature=f00#t=10", &rules),
+            "https://User:Pw@cdn.example.com/Live/index.m3u8?quality=hd"
+        );
+        assert_eq!(
+            canonicalize("https://cdn.example.com/index.m3u8?Expires=1&Signature=2&Key-Pair-Id=3", &rules),
+            "https://cdn.example.com/index.m3u8"
+        );
+        assert_eq!(canonicalize("/videos/Show.m3u8", &rules), "/videos/Show.m3u8");
+
+        let rules = CanonicalUrlConfig { strip_query_params: vec!["sid".to_string()], lowercase_host: false };
+        assert_eq!(
+            canonicalize("https://CDN.example.com/a.m3u8?sid=1&token=2", &rules),
+            "https://CDN.example.com/a.m3u8?token=2"
+        );
+    }
+}
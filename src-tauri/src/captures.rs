@@ -0,0 +1,122 @@
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::error::AppError;
+
+// Stream URLs sent by a companion browser extension to POST /api/captures,
+// waiting for the user or an agent to pick them up. They are only kept in
+// memory: the headers of a capture often carry the page's cookies.
+
+// Captures kept; the oldest are dropped as new ones come in
+const MAX_PENDING: usize = 100;
+
+lazy_static::lazy_static! {
+    static ref PENDING: Mutex<Vec<Capture>> = Mutex::new(Vec::new());
+}
+
+// What the extension posts
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureRequest {
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub page_title: Option<String>,
+    pub page_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Capture {
+    pub id: String,
+    pub url: String,
+    // Request headers the browser sent for the stream, such as Referer or Cookie
+    pub headers: BTreeMap<String, String>,
+    pub page_title: Option<String>,
+    pub page_url: Option<String>,
+    pub received_at: String,
+}
+
+// Queue a capture. The same URL captured again replaces the earlier
+// capture, whose headers may have expired since.
+pub fn add(request: CaptureRequest) -> Result<Capture, AppError> {
+    let url = request.url.trim();
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(AppError::invalid_input("URL must start with http:// or https://"));
+    }
+    let capture = Capture {
+        id: uuid::Uuid::new_v4().simple().to_string(),
+        url: url.to_string(),
+        headers: request.headers.into_iter()
+            .map(|(name, value)| (name.trim().to_string(), value))
+            .filter(|(name, _)| !name.is_empty())
+            .collect(),
+        page_title: request.page_title.map(|title| title.trim().to_string()).filter(|title| !title.is_empty()),
+        page_url: request.page_url.filter(|page_url| !page_url.trim().is_empty()),
+        received_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+    };
+
+    let mut pending = PENDING.lock().unwrap();
+    pending.retain(|existing| existing.url != capture.url);
+    pending.push(capture.clone());
+    if pending.len() > MAX_PENDING {
+        let excess = pending.len() - MAX_PENDING;
+        pending.drain(..excess);
+    }
+    Ok(capture)
+}
+
+// Pending captures, newest first
+pub fn list() -> Vec<Capture> {
+    PENDING.lock().unwrap().iter().rev().cloned().collect()
+}
+
+// Remove a capture, once it has been used or is not wanted
+pub fn dismiss(id: &str) -> Result<Capture, AppError> {
+    let mut pending = PENDING.lock().unwrap();
+    let index = pending.iter().position(|capture| capture.id == id)
+        .ok_or_else(|| AppError::not_found(format!("Capture {} not found", id)))?;
+    Ok(pending.remove(index))
+}
+
+// Remove every capture and return how many there were
+pub fn clear() -> usize {
+    let mut pending = PENDING.lock().unwrap();
+    let count = pending.len();
+    pending.clear();
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(url: &str, title: &str) -> CaptureRequest {
+        serde_json::from_value(serde_json::json!({
+            "url": url,
+            "headers": { "Referer": "https://example.com/watch", " ": "dropped" },
+            "pageTitle": title
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_captures() {
+        assert!(add(request("file:///etc/passwd", "")).is_err());
+
+        let first = add(request("https://cdn.example.com/a.m3u8", " Live ")).unwrap();
+        assert_eq!(first.page_title.as_deref(), Some("Live"));
+        assert_eq!(first.headers.len(), 1);
+        let second = add(request("https://cdn.example.com/b.m3u8", "")).unwrap();
+        assert_eq!(second.page_title, None);
+        let again = add(request("https://cdn.example.com/a.m3u8", "Live")).unwrap();
+
+        let ids: Vec<String> = list().into_iter().map(|capture| capture.id).collect();
+        assert_eq!(ids, vec![again.id.clone(), second.id.clone()]);
+        assert_eq!(dismiss(&second.id).unwrap().url, "https://cdn.example.com/b.m3u8");
+        assert!(dismiss(&second.id).is_err());
+        assert_eq!(clear(), 1);
+        assert!(list().is_empty());
+    }
+}
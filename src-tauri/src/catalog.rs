@@ -0,0 +1,274 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::Path;
+
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::mcp_server::{self, Tool};
+
+// The surface agents get, as files for generating typed clients or for
+// review: every MCP tool with its input schema, and an OpenAPI document for
+// the HTTP artifact routes. Tools answer with MCP content rather than
+// structured output, so each one points to a shared CallToolResult schema.
+
+pub const TOOLS_FILE: &str = "m3u8-mcp-tools.json";
+pub const OPENAPI_FILE: &str = "m3u8-mcp-openapi.json";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedCatalog {
+    pub tools: String,
+    pub openapi: String,
+    pub tool_count: usize,
+}
+
+// What a tools/call answers, whatever the tool
+fn call_tool_result_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "content": {
+                "type": "array",
+                "items": {
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "properties": {
+                                "type": { "const": "text" },
+                                "text": { "type": "string", "description": "Usually JSON, pretty-printed" }
+                            },
+                            "required": ["type", "text"]
+                        },
+                        {
+                            "type": "object",
+                            "properties": {
+                                "type": { "const": "image" },
+                                "data": { "type": "string", "contentEncoding": "base64" },
+                                "mimeType": { "type": "string" }
+                            },
+                            "required": ["type", "data", "mimeType"]
+                        }
+                    ]
+                }
+            },
+            "isError": { "type": "boolean" }
+        },
+        "required": ["content"]
+    })
+}
+
+// Every tool as tools/list would describe it, with whether `config`
+// enables it
+pub fn tool_bundle(tools: &[Tool], config: &AppConfig) -> Value {
+    let tools: Vec<Value> = tools
+        .iter()
+        .map(|tool| {
+            json!({
+                "name": tool.name,
+                "description": tool.description,
+                "inputSchema": tool.input_schema,
+                "resultSchema": { "$ref": "#/$defs/CallToolResult" },
+                "enabled": config.mcp.enabled_tools.contains(&tool.name)
+            })
+        })
+        .collect();
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "server": {
+            "name": "m3u8-mcp",
+            "version": env!("CARGO_PKG_VERSION"),
+            "protocolVersion": mcp_server::MCP_PROTOCOL_VERSION,
+            "endpoint": format!("http://localhost:{}/mcp", config.mcp.port)
+        },
+        "tools": tools,
+        "$defs": {
+            "CallToolResult": call_tool_result_schema()
+        }
+    })
+}
+
+fn error_response(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": {
+            "application/json": {
+                "schema": { "$ref": "#/components/schemas/Error" }
+            }
+        }
+    })
+}
+
+// OpenAPI 3.1 description of GET /artifacts/{job_id} and
+// GET /artifacts/{job_id}/{index}
+pub fn openapi(config: &AppConfig) -> Value {
+    let job_id = json!({
+        "name": "job_id",
+        "in": "path",
+        "required": true,
+        "schema": { "type": "string" },
+        "description": "Job id from the result of m3u8_download, m3u8_report or m3u8_transcribe"
+    });
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "m3u8-mcp artifacts",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Files produced by jobs, for clients that cannot open the server's local paths"
+        },
+        "servers": [{
+            "url": "http://{host}:{port}",
+            "variables": {
+                "host": { "default": "localhost" },
+                "port": { "default": config.mcp.port.to_string() }
+            }
+        }],
+        "security": [{ "bearer": [] }, { "token": [] }],
+        "paths": {
+            "/artifacts/{job_id}": {
+                "get": {
+                    "operationId": "listArtifacts",
+                    "summary": "List a job's files with their size, type and download URL",
+                    "parameters": [job_id],
+                    "responses": {
+                        "200": {
+                            "description": "The job's files, in the order they were produced",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/ArtifactList" }
+                                }
+                            }
+                        },
+                        "401": error_response("Missing or invalid token"),
+                        "404": error_response("The job has no files on disk"),
+                        "503": { "description": "The database is not initialized" }
+                    }
+                }
+            },
+            "/artifacts/{job_id}/{index}": {
+                "get": {
+                    "operationId": "getArtifact",
+                    "summary": "Stream a file; a single Range header is honoured",
+                    "parameters": [
+                        job_id,
+                        {
+                            "name": "index",
+                            "in": "path",
+                            "required": true,
+                            "schema": { "type": "integer", "minimum": 0 },
+                            "description": "Index of the file in the list"
+                        },
+                        {
+                            "name": "Range",
+                            "in": "header",
+                            "required": false,
+                            "schema": { "type": "string", "examples": ["bytes=0-1048575"] }
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "The whole file",
+                            "content": { "*/*": { "schema": { "type": "string", "format": "binary" } } }
+                        },
+                        "206": {
+                            "description": "The requested range",
+                            "content": { "*/*": { "schema": { "type": "string", "format": "binary" } } }
+                        },
+                        "401": error_response("Missing or invalid token"),
+                        "404": error_response("No such job or file"),
+                        "410": error_response("The file was recorded but is gone from disk")
+                    }
+                }
+            }
+        },
+        "components": {
+            "securitySchemes": {
+                "bearer": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "mcp.auth_token from ~/.m3u8-mcp/config.json"
+                },
+                "token": {
+                    "type": "apiKey",
+                    "in": "query",
+                    "name": "token",
+                    "description": "The same token, for players and browsers that cannot set headers"
+                }
+            },
+            "schemas": {
+                "ArtifactList": {
+                    "type": "object",
+                    "properties": {
+                        "job_id": { "type": "string" },
+                        "artifacts": {
+                            "type": "array",
+                            "items": { "$ref": "#/components/schemas/Artifact" }
+                        }
+                    },
+                    "required": ["job_id", "artifacts"]
+                },
+                "Artifact": {
+                    "type": "object",
+                    "properties": {
+                        "index": { "type": "integer" },
+                        "kind": { "type": "string" },
+                        "name": { "type": ["string", "null"] },
+                        "size": { "type": ["integer", "null"] },
+                        "content_type": { "type": "string" },
+                        "created_at": { "type": "string", "format": "date-time" },
+                        "url": { "type": "string", "format": "uri" }
+                    },
+                    "required": ["index", "kind", "content_type", "created_at", "url"]
+                },
+                "Error": {
+                    "type": "object",
+                    "properties": { "error": { "type": "string" } },
+                    "required": ["error"]
+                }
+            }
+        }
+    })
+}
+
+// Write both documents into `dir`, replacing earlier exports
+pub fn export(dir: &Path) -> Result<ExportedCatalog, AppError> {
+    let config = crate::config::load_config().unwrap_or_default().effective();
+    let tools = mcp_server::all_tools();
+    std::fs::create_dir_all(dir)?;
+
+    let tools_path = dir.join(TOOLS_FILE);
+    let openapi_path = dir.join(OPENAPI_FILE);
+    let pretty = |value: &Value| serde_json::to_string_pretty(value).unwrap_or_default();
+    std::fs::write(&tools_path, pretty(&tool_bundle(&tools, &config)))?;
+    std::fs::write(&openapi_path, pretty(&openapi(&config)))?;
+
+    Ok(ExportedCatalog {
+        tools: tools_path.to_string_lossy().to_string(),
+        openapi: openapi_path.to_string_lossy().to_string(),
+        tool_count: tools.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_bundle() {
+        let config = AppConfig::default();
+        let tools = mcp_server::all_tools();
+        let bundle = tool_bundle(&tools, &config);
+        let listed = bundle["tools"].as_array().unwrap();
+        assert_eq!(listed.len(), tools.len());
+        assert!(listed.iter().all(|tool| tool["inputSchema"]["type"] == "object"));
+        assert!(listed.iter().any(|tool| tool["name"] == "m3u8_download" && tool["enabled"] == true));
+        assert_eq!(bundle["$defs"]["CallToolResult"]["required"][0], "content");
+
+        // Every reference in the OpenAPI document resolves
+        let document = openapi(&config);
+        let text = document.to_string();
+        for reference in text.split("\"$ref\":\"#/").skip(1) {
+            let pointer = format!("/{}", &reference[..reference.find('"').unwrap()]);
+            assert!(document.pointer(&pointer).is_some(), "{}", pointer);
+        }
+        assert!(document["paths"]["/artifacts/{job_id}/{index}"]["get"]["responses"]["206"].is_object());
+    }
+}
@@ -0,0 +1,207 @@
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::ffmpeg_wrapper::FFmpegService;
+use crate::m3u8_parser::{self, DateRange, M3u8Parser, ParsedPlaylist};
+
+// A chapter on the output timeline, in seconds from the start of the file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub start: f64,
+    // Defaults to the next chapter's start, or the end of the file
+    #[serde(default)]
+    pub end: Option<f64>,
+    pub title: String,
+}
+
+// Seconds between two ISO 8601 dates
+pub fn seconds_between(from: &str, to: &str) -> Option<f64> {
+    let from = DateTime::parse_from_rfc3339(from).ok()?;
+    let to = DateTime::parse_from_rfc3339(to).ok()?;
+    Some((to - from).num_milliseconds() as f64 / 1000.0)
+}
+
+// Chapter title from X-TITLE, falling back to the class and the ID
+fn range_title(range: &DateRange) -> String {
+    range.client_attribute("X-TITLE")
+        .or(range.client_attribute("X-COM-TITLE"))
+        .map(|t| t.to_string())
+        .or_else(|| range.class.clone())
+        .unwrap_or_else(|| range.id.clone())
+}
+
+// Map EXT-X-DATERANGE events onto the timeline that starts at the playlist's
+// first EXT-X-PROGRAM-DATE-TIME. Events before the start are clamped to 0.
+pub fn chapters_from_date_ranges(ranges: &[DateRange], program_date_time: &str) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = ranges
+        .iter()
+        .filter_map(|range| {
+            let start = seconds_between(program_date_time, &range.start_date)?;
+            let end = range.end_date.as_deref()
+                .and_then(|end| seconds_between(program_date_time, end))
+                .or(range.duration.map(|d| start + d));
+            Some(Chapter {
+                start: start.max(0.0),
+                end,
+                title: range_title(range),
+            })
+        })
+        .collect();
+    chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+    chapters.dedup_by(|b, a| a.start == b.start);
+    chapters
+}
+
+// Fill in missing ends and drop chapters outside the file
+pub fn normalize_chapters(mut chapters: Vec<Chapter>, total_duration: f64) -> Vec<Chapter> {
+    chapters.retain(|c| c.start < total_duration);
+    chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    let starts: Vec<f64> = chapters.iter().map(|c| c.start).skip(1).collect();
+    for (i, chapter) in chapters.iter_mut().enumerate() {
+        let next = starts.get(i).copied().unwrap_or(total_duration);
+        let end = chapter.end.unwrap_or(next).min(next).min(total_duration);
+        chapter.end = Some(end.max(chapter.start));
+    }
+    chapters
+}
+
+fn escape_metadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// FFmpeg metadata file (FFMETADATA1) declaring the chapters in milliseconds
+pub fn ffmetadata(chapters: &[Chapter]) -> String {
+    let mut out = String::from(";FFMETADATA1\n");
+    for chapter in chapters {
+        let start = (chapter.start * 1000.0).round() as i64;
+        let end = (chapter.end.unwrap_or(chapter.start) * 1000.0).round() as i64;
+        out.push_str(&format!(
+            "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            start,
+            end,
+            escape_metadata(&chapter.title)
+        ));
+    }
+    out
+}
+
+// Read the date range chapters of a stream. For a master playlist the first
+// variant's media playlist is used, since date ranges live there.
+pub async fn fetch_date_range_chapters(parser: &M3u8Parser, url: &str) -> Result<Vec<Chapter>, AppError> {
+    let mut content = parser.fetch_playlist(url).await?;
+    if let ParsedPlaylist::Master { variants, .. } = parser.parse_content(&content, url)? {
+        let variant = variants.first()
+            .ok_or_else(|| AppError::invalid_input("Master playlist has no variants"))?;
+        content = parser.fetch_playlist(&variant.uri).await?;
+    }
+
+    let ranges = m3u8_parser::parse_date_ranges(&content);
+    if ranges.is_empty() {
+        return Ok(Vec::new());
+    }
+    let program_date_time = m3u8_parser::first_program_date_time(&content)
+        .ok_or_else(|| AppError::invalid_input("Playlist has EXT-X-DATERANGE tags but no EXT-X-PROGRAM-DATE-TIME to anchor them"))?;
+    Ok(chapters_from_date_ranges(&ranges, &program_date_time))
+}
+
+// Write chapters into an MP4/MKV file in place (stream copy, then replace)
+pub async fn embed_chapters(
+    ffmpeg: &FFmpegService,
+    media: &Path,
+    chapters: Vec<Chapter>,
+) -> Result<Vec<Chapter>, AppError> {
+    if !media.exists() {
+        return Err(AppError::not_found(format!("File not found: {}", media.display())));
+    }
+    let extension = media.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    if !matches!(extension.as_str(), "mp4" | "m4v" | "mov" | "mkv") {
+        return Err(AppError::invalid_input("Chapters can only be embedded in MP4, MOV or MKV files"));
+    }
+
+    let total_duration = ffmpeg.probe_duration(media).await?;
+    let chapters = normalize_chapters(chapters, total_duration);
+    if chapters.is_empty() {
+        return Err(AppError::invalid_input("No chapters fall within the file"));
+    }
+
+    let metadata_path = std::env::temp_dir().join(format!("m3u8-mcp-{}.ffmeta", uuid::Uuid::new_v4()));
+    std::fs::write(&metadata_path, ffmetadata(&chapters))?;
+    let temp_output: PathBuf = media.with_extension(format!("chapters.{}", extension));
+
+    let args: Vec<String> = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        media.to_string_lossy().to_string(),
+        "-i".to_string(),
+        metadata_path.to_string_lossy().to_string(),
+        "-map".to_string(),
+        "0".to_string(),
+        "-map_metadata".to_string(),
+        "0".to_string(),
+        "-map_chapters".to_string(),
+        "1".to_string(),
+        "-c".to_string(),
+        "copy".to_string(),
+        temp_output.to_string_lossy().to_string(),
+    ];
+    let result = ffmpeg.run_ffmpeg(&args).await;
+    let _ = std::fs::remove_file(&metadata_path);
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temp_output);
+        return Err(e.into());
+    }
+    std::fs::rename(&temp_output, media)?;
+    Ok(chapters)
+}
+
+// Embed the given chapters, or when none are given, the stream's date ranges
+pub async fn add_chapters(
+    parser: &M3u8Parser,
+    ffmpeg: &FFmpegService,
+    media: &Path,
+    stream_url: Option<&str>,
+    chapters: Option<Vec<Chapter>>,
+) -> Result<Vec<Chapter>, AppError> {
+    let chapters = match (chapters, stream_url) {
+        (Some(chapters), _) => chapters,
+        (None, Some(url)) => fetch_date_range_chapters(parser, url).await?,
+        (None, None) => return Err(AppError::invalid_input("Provide chapters or a stream URL with EXT-X-DATERANGE tags")),
+    };
+    if chapters.is_empty() {
+        return Err(AppError::not_found("The stream has no EXT-X-DATERANGE events to turn into chapters"));
+    }
+    embed_chapters(ffmpeg, media, chapters).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chapters() {
+        let content = "#EXTM3U\n\
+            #EXT-X-PROGRAM-DATE-TIME:2024-05-01T10:00:00Z\n\
+            #EXT-X-DATERANGE:ID=\"b\",START-DATE=\"2024-05-01T10:10:00Z\",X-TITLE=\"Q&A\"\n\
+            #EXT-X-DATERANGE:ID=\"a\",CLASS=\"talk\",START-DATE=\"2024-05-01T10:01:30Z\",DURATION=300\n";
+        let ranges = m3u8_parser::parse_date_ranges(content);
+        let chapters = chapters_from_date_ranges(&ranges, "2024-05-01T10:00:00Z");
+        assert_eq!(chapters[0], Chapter { start: 90.0, end: Some(390.0), title: "talk".to_string() });
+        assert_eq!(chapters[1].start, 600.0);
+
+        let chapters = normalize_chapters(chapters, 900.0);
+        assert_eq!(chapters[1].end, Some(900.0));
+        assert!(ffmetadata(&chapters).contains("START=90000\nEND=390000\ntitle=talk\n"));
+        assert_eq!(escape_metadata("a=b;c"), "a\\=b\\;c");
+    }
+}
@@ -0,0 +1,126 @@
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::database::{Database, DownloadedStream};
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CleanupReport {
+    // Nothing was deleted; `removed` lists what would have been
+    pub dry_run: bool,
+    pub removed: Vec<DownloadedStream>,
+    pub freed_bytes: u64,
+    // Files that could not be removed, with the reason
+    pub failed: Vec<String>,
+}
+
+// Remove a downloaded file: to the OS trash by default, or permanently
+pub fn remove_file(path: &Path, permanent: bool) -> Result<(), AppError> {
+    if !path.exists() {
+        return Ok(());
+    }
+    if permanent {
+        std::fs::remove_file(path)
+            .map_err(|e| AppError::io(format!("Failed to delete {}: {}", path.display(), e)))
+    } else {
+        trash::delete(path)
+            .map_err(|e| AppError::io(format!("Failed to move {} to trash: {}", path.display(), e)))
+    }
+}
+
+// Size of the file on disk, falling back to the recorded size
+fn file_size(download: &DownloadedStream) -> u64 {
+    std::fs::metadata(&download.output_path)
+        .map(|m| m.len())
+        .unwrap_or_else(|_| download.file_size.unwrap_or(0).max(0) as u64)
+}
+
+// Delete one download's file and its record
+pub fn delete_download(db: &Database, id: i32, permanent: bool) -> Result<DownloadedStream, AppError> {
+    let download = db.get_download(id)?
+        .ok_or_else(|| AppError::not_found(format!("Download {} not found", id)))?;
+
+    remove_file(Path::new(&download.output_path), permanent)?;
+    db.delete_download_record(id)?;
+    Ok(download)
+}
+
+// Remove downloads older than `older_than_days`, then the oldest remaining
+// ones until the total size fits in `max_total_bytes`.
+pub fn cleanup_downloads(
+    db: &Database,
+    older_than_days: Option<u32>,
+    max_total_bytes: Option<u64>,
+    permanent: bool,
+    dry_run: bool,
+) -> Result<CleanupReport, AppError> {
+    if older_than_days.is_none() && max_total_bytes.is_none() {
+        return Err(AppError::invalid_input("Specify older_than_days and/or max_total_bytes"));
+    }
+
+    // Oldest first, only files that still exist
+    let downloads: Vec<DownloadedStream> = db.list_all_downloads()?
+        .into_iter()
+        .filter(|d| Path::new(&d.output_path).exists())
+        .collect();
+
+    let mut selected = vec![false; downloads.len()];
+    if let Some(days) = older_than_days {
+        let cutoff = Utc::now() - Duration::days(days as i64);
+        for (i, download) in downloads.iter().enumerate() {
+            selected[i] = download.downloaded_at < cutoff;
+        }
+    }
+    if let Some(budget) = max_total_bytes {
+        let mut total: u64 = downloads.iter().zip(&selected)
+            .filter(|(_, s)| !**s)
+            .map(|(d, _)| file_size(d))
+            .sum();
+        for (i, download) in downloads.iter().enumerate() {
+            if total <= budget {
+                break;
+            }
+            if !selected[i] {
+                selected[i] = true;
+                total = total.saturating_sub(file_size(download));
+            }
+        }
+    }
+
+    let mut report = CleanupReport {
+        dry_run,
+        removed: Vec::new(),
+        freed_bytes: 0,
+        failed: Vec::new(),
+    };
+
+    for (download, _) in downloads.into_iter().zip(selected).filter(|(_, s)| *s) {
+        let size = file_size(&download);
+        if !dry_run {
+            if let Err(e) = remove_file(Path::new(&download.output_path), permanent) {
+                report.failed.push(e.message);
+                continue;
+            }
+            db.delete_download_record(download.id)?;
+        }
+        report.freed_bytes += size;
+        report.removed.push(download);
+    }
+
+    Ok(report)
+}
+
+// Drop records whose files were deleted outside the app. A file being
+// downloaded again is only a .part until it completes; its record stays.
+pub fn reconcile_downloads(db: &Database) -> Result<Vec<DownloadedStream>, AppError> {
+    let mut missing = Vec::new();
+    for download in db.list_all_downloads()? {
+        let path = Path::new(&download.output_path);
+        if !path.exists() && !crate::paths::part_path(path).exists() {
+            db.delete_download_record(download.id)?;
+            missing.push(download);
+        }
+    }
+    Ok(missing)
+}
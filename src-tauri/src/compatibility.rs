@@ -0,0 +1,401 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::error::AppError;
+use crate::ffmpeg_wrapper::FFmpegService;
+use crate::m3u8_parser::{M3u8Parser, ParsedPlaylist};
+
+// Codec families we know how to judge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodecFamily {
+    H264,
+    Hevc,
+    DolbyVision,
+    Av1,
+    Vp9,
+    Aac,
+    HeAac,
+    Mp3,
+    Ac3,
+    Eac3,
+    Ac4,
+    Opus,
+    Flac,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Codec {
+    // Original RFC 6381 string or ffprobe codec name
+    pub raw: String,
+    pub family: CodecFamily,
+}
+
+// Playback targets covered by the built-in table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    SafariMacos,
+    SafariIos14,
+    SafariIos17,
+    ChromeDesktop,
+    FirefoxDesktop,
+    AndroidLegacy,
+    Android,
+    Chromecast,
+    SmartTv,
+}
+
+impl Target {
+    pub const ALL: [Target; 9] = [
+        Target::SafariMacos,
+        Target::SafariIos14,
+        Target::SafariIos17,
+        Target::ChromeDesktop,
+        Target::FirefoxDesktop,
+        Target::AndroidLegacy,
+        Target::Android,
+        Target::Chromecast,
+        Target::SmartTv,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Target::SafariMacos => "Safari macOS",
+            Target::SafariIos14 => "Safari iOS 14",
+            Target::SafariIos17 => "Safari iOS 17+",
+            Target::ChromeDesktop => "Chrome desktop (hls.js)",
+            Target::FirefoxDesktop => "Firefox desktop (hls.js)",
+            Target::AndroidLegacy => "Android 6 and older (ExoPlayer)",
+            Target::Android => "Android 10+ (ExoPlayer)",
+            Target::Chromecast => "Chromecast",
+            Target::SmartTv => "Smart TV (Tizen/webOS)",
+        }
+    }
+
+    fn is_apple(&self) -> bool {
+        matches!(self, Target::SafariMacos | Target::SafariIos14 | Target::SafariIos17)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Support {
+    Yes,
+    // Depends on hardware, OS version or player configuration
+    Partial,
+    No,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Verdict {
+    pub support: Support,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<&'static str>,
+}
+
+// Classify an RFC 6381 codec string (from CODECS) or an ffprobe codec name
+pub fn classify_codec(raw: &str) -> Codec {
+    let lower = raw.trim().to_ascii_lowercase();
+    let tag = lower.split('.').next().unwrap_or("");
+    let family = match tag {
+        "avc1" | "avc3" | "h264" => CodecFamily::H264,
+        "hvc1" | "hev1" | "hevc" | "h265" => CodecFamily::Hevc,
+        "dvh1" | "dvhe" | "dvav" | "dva1" => CodecFamily::DolbyVision,
+        "av01" | "av1" => CodecFamily::Av1,
+        "vp09" | "vp9" => CodecFamily::Vp9,
+        "mp4a" => match lower.as_str() {
+            "mp4a.40.5" | "mp4a.40.29" => CodecFamily::HeAac,
+            "mp4a.40.34" | "mp4a.6b" | "mp4a.69" => CodecFamily::Mp3,
+            _ => CodecFamily::Aac,
+        },
+        "aac" => CodecFamily::Aac,
+        "mp3" => CodecFamily::Mp3,
+        "ac-3" | "ac3" => CodecFamily::Ac3,
+        "ec-3" | "eac3" => CodecFamily::Eac3,
+        "ac-4" | "ac4" => CodecFamily::Ac4,
+        "opus" => CodecFamily::Opus,
+        "flac" => CodecFamily::Flac,
+        _ => CodecFamily::Unknown,
+    };
+    Codec {
+        raw: raw.trim().to_string(),
+        family,
+    }
+}
+
+// Split a CODECS attribute value into classified codecs
+pub fn parse_codecs(codecs: &str) -> Vec<Codec> {
+    codecs
+        .split(',')
+        .filter(|c| !c.trim().is_empty())
+        .map(classify_codec)
+        .collect()
+}
+
+fn verdict(support: Support, note: Option<&'static str>) -> Verdict {
+    Verdict { support, note }
+}
+
+// Built-in compatibility table. Best-effort: it reflects common behaviour of
+// each platform's default HLS player, not every device.
+pub fn check(codec: &Codec, target: Target) -> Verdict {
+    use Support::*;
+    use Target::*;
+
+    let hev1 = codec.raw.to_ascii_lowercase().starts_with("hev1");
+
+    match codec.family {
+        CodecFamily::H264 | CodecFamily::Aac | CodecFamily::Mp3 => verdict(Yes, None),
+        CodecFamily::HeAac => match target {
+            AndroidLegacy => verdict(Partial, Some("HE-AACv2 decoding varies on old Android devices")),
+            _ => verdict(Yes, None),
+        },
+        CodecFamily::Hevc => match target {
+            _ if target.is_apple() && hev1 => verdict(No, Some("Apple players require the hvc1 sample entry, not hev1")),
+            SafariMacos | SafariIos14 | SafariIos17 => verdict(Yes, None),
+            ChromeDesktop => verdict(Partial, Some("Requires hardware HEVC decoding (Chrome 107+)")),
+            FirefoxDesktop => verdict(No, Some("Firefox does not decode HEVC")),
+            AndroidLegacy => verdict(No, Some("No HEVC decoder on most pre-Android 7 devices")),
+            Android | Chromecast => verdict(Partial, Some("Depends on the device's hardware decoder")),
+            SmartTv => verdict(Yes, None),
+        },
+        CodecFamily::DolbyVision => match target {
+            SafariMacos | SafariIos17 => verdict(Yes, Some("Falls back to the HDR10/SDR base layer on non-HDR displays")),
+            SafariIos14 => verdict(Partial, Some("Only on devices with Dolby Vision hardware")),
+            ChromeDesktop | FirefoxDesktop | AndroidLegacy => verdict(No, None),
+            Android | Chromecast | SmartTv => verdict(Partial, Some("Only on Dolby Vision certified devices")),
+        },
+        CodecFamily::Av1 => match target {
+            SafariIos14 | AndroidLegacy => verdict(No, None),
+            SafariMacos | SafariIos17 => verdict(Partial, Some("Only with hardware AV1 decoding (M3 / A17 Pro and later)")),
+            ChromeDesktop | FirefoxDesktop => verdict(Yes, None),
+            Android => verdict(Partial, Some("Software decoding on Android 10+, may stutter at high resolutions")),
+            Chromecast | SmartTv => verdict(Partial, Some("Only on recent models")),
+        },
+        CodecFamily::Vp9 => match target {
+            SafariMacos | SafariIos14 | SafariIos17 => verdict(No, Some("Apple HLS players do not support VP9")),
+            ChromeDesktop | FirefoxDesktop | Android | Chromecast => verdict(Yes, None),
+            AndroidLegacy | SmartTv => verdict(Partial, None),
+        },
+        CodecFamily::Ac3 | CodecFamily::Eac3 => match target {
+            SafariMacos | SafariIos14 | SafariIos17 | SmartTv => verdict(Yes, None),
+            ChromeDesktop | FirefoxDesktop => verdict(No, Some("Dolby audio is not available in Chrome/Firefox MSE")),
+            AndroidLegacy | Android | Chromecast => verdict(Partial, Some("Needs a device decoder or HDMI passthrough")),
+        },
+        CodecFamily::Ac4 => match target {
+            SmartTv => verdict(Partial, None),
+            ChromeDesktop | FirefoxDesktop | AndroidLegacy => verdict(No, None),
+            _ => verdict(Unknown, None),
+        },
+        CodecFamily::Opus => match target {
+            SafariIos14 | AndroidLegacy => verdict(No, None),
+            SafariMacos | SafariIos17 => verdict(Partial, Some("Opus in fMP4 requires Safari 17+")),
+            _ => verdict(Yes, None),
+        },
+        CodecFamily::Flac => match target {
+            SafariIos14 | AndroidLegacy | Chromecast => verdict(Partial, None),
+            _ => verdict(Yes, None),
+        },
+        CodecFamily::Unknown => verdict(Unknown, Some("Codec not in the compatibility table")),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetResult {
+    pub target: Target,
+    pub label: &'static str,
+    pub support: Support,
+    pub notes: Vec<String>,
+}
+
+// Combine per-codec verdicts: a stream plays only if every codec plays
+pub fn check_stream(codecs: &[Codec], target: Target) -> TargetResult {
+    let mut support = if codecs.is_empty() { Support::Unknown } else { Support::Yes };
+    let mut notes = Vec::new();
+
+    for codec in codecs {
+        let v = check(codec, target);
+        support = match (support, v.support) {
+            (Support::No, _) | (_, Support::No) => Support::No,
+            (Support::Unknown, _) | (_, Support::Unknown) => Support::Unknown,
+            (Support::Partial, _) | (_, Support::Partial) => Support::Partial,
+            _ => Support::Yes,
+        };
+        if let Some(note) = v.note {
+            notes.push(format!("{}: {}", codec.raw, note));
+        }
+    }
+
+    TargetResult {
+        target,
+        label: target.label(),
+        support,
+        notes,
+    }
+}
+
+// Codecs reported by ffprobe JSON output. The codec tag is preferred when
+// it is meaningful (e.g. hvc1 vs hev1); MPEG-TS tags like "[27][0][0][0]" are not.
+pub fn codecs_from_probe(probe_json: &str) -> Vec<Codec> {
+    let value: Value = match serde_json::from_str(probe_json) {
+        Ok(v) => v,
+        Err(_) => return Vec::new(),
+    };
+    let streams = match value.get("streams").and_then(|s| s.as_array()) {
+        Some(s) => s,
+        None => return Vec::new(),
+    };
+
+    streams
+        .iter()
+        .filter(|s| matches!(s.get("codec_type").and_then(|t| t.as_str()), Some("video") | Some("audio")))
+        .filter_map(|s| {
+            let tag = s.get("codec_tag_string").and_then(|t| t.as_str()).map(classify_codec);
+            match tag {
+                Some(codec) if codec.family != CodecFamily::Unknown => Some(codec),
+                _ => s.get("codec_name").and_then(|n| n.as_str()).map(classify_codec),
+            }
+        })
+        .collect()
+}
+
+// Streams to judge: every variant of a master playlist, or the media playlist itself
+struct Stream {
+    uri: String,
+    label: String,
+    bandwidth: Option<u64>,
+    codecs: Option<String>,
+}
+
+// Device compatibility report for a playlist (m3u8_compatibility).
+// CODECS attributes are used when present; with `probe`, streams without
+// them are probed with ffprobe instead.
+pub async fn compatibility_report(
+    parser: &M3u8Parser,
+    ffmpeg: &FFmpegService,
+    url: &str,
+    targets: &[Target],
+    probe: bool,
+) -> Result<Value, AppError> {
+    let streams: Vec<Stream> = match parser.parse_url(url).await? {
+        ParsedPlaylist::Master { variants, .. } => variants
+            .into_iter()
+            .map(|v| Stream {
+                label: v.resolution.clone().unwrap_or_else(|| format!("{} bps", v.bandwidth)),
+                uri: v.uri,
+                bandwidth: Some(v.bandwidth),
+                codecs: v.codecs,
+            })
+            .collect(),
+        ParsedPlaylist::Media { .. } => vec![Stream {
+            uri: url.to_string(),
+            label: "media playlist".to_string(),
+            bandwidth: None,
+            codecs: None,
+        }],
+    };
+
+    let mut warnings = Vec::new();
+    let mut playable = vec![0usize; targets.len()];
+    let mut judged = 0usize;
+    let mut variants = Vec::new();
+
+    for stream in &streams {
+        let (codecs, source) = match &stream.codecs {
+            Some(c) => (parse_codecs(c), "codecs"),
+            None if probe => match ffmpeg.probe_stream(&stream.uri).await {
+                Ok(output) => (codecs_from_probe(&output), "probe"),
+                Err(e) => {
+                    warnings.push(format!("Failed to probe {}: {}", stream.label, e));
+                    (Vec::new(), "none")
+                }
+            },
+            None => (Vec::new(), "none"),
+        };
+
+        if codecs.is_empty() {
+            warnings.push(format!(
+                "{} has no codec information; enable probe to inspect it",
+                stream.label
+            ));
+        } else {
+            judged += 1;
+        }
+
+        let results: Vec<TargetResult> = targets.iter().map(|t| check_stream(&codecs, *t)).collect();
+        for (i, result) in results.iter().enumerate() {
+            match result.support {
+                Support::Yes | Support::Partial => playable[i] += 1,
+                Support::No if result.notes.is_empty() => {
+                    warnings.push(format!("Variant {} won't play on {}", stream.label, result.label));
+                }
+                Support::No => warnings.push(format!(
+                    "Variant {} won't play on {}: {}",
+                    stream.label,
+                    result.label,
+                    result.notes.join("; ")
+                )),
+                Support::Unknown => {}
+            }
+        }
+
+        variants.push(json!({
+            "uri": stream.uri,
+            "label": stream.label,
+            "bandwidth": stream.bandwidth,
+            "codecs": codecs,
+            "codec_source": source,
+            "targets": results
+        }));
+    }
+
+    let summary: Vec<Value> = targets
+        .iter()
+        .zip(&playable)
+        .map(|(target, count)| {
+            if judged > 0 && *count == 0 {
+                warnings.push(format!("No variant plays on {}", target.label()));
+            }
+            json!({
+                "target": target,
+                "label": target.label(),
+                "playable_variants": count
+            })
+        })
+        .collect();
+
+    Ok(json!({
+        "url": url,
+        "variants": variants,
+        "summary": summary,
+        "warnings": warnings
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_codecs() {
+        let codecs = parse_codecs("avc1.640028,mp4a.40.2");
+        assert_eq!(codecs.len(), 2);
+        assert_eq!(codecs[0].family, CodecFamily::H264);
+        assert_eq!(codecs[1].family, CodecFamily::Aac);
+        assert_eq!(classify_codec("ec-3").family, CodecFamily::Eac3);
+    }
+
+    #[test]
+    fn test_check_stream() {
+        let hev1 = parse_codecs("hev1.1.6.L93.B0,mp4a.40.2");
+        assert_eq!(check_stream(&hev1, Target::SafariIos14).support, Support::No);
+
+        let hvc1 = parse_codecs("hvc1.1.6.L93.B0,mp4a.40.2");
+        assert_eq!(check_stream(&hvc1, Target::SafariIos14).support, Support::Yes);
+        assert_eq!(check_stream(&hvc1, Target::ChromeDesktop).support, Support::Partial);
+        assert_eq!(check_stream(&hvc1, Target::FirefoxDesktop).support, Support::No);
+    }
+}
@@ -0,0 +1,157 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+use crate::paths;
+
+// Suggestions for MCP's completion/complete. MCP completes the arguments of
+// prompts and resource templates; this server has neither, so it accepts
+// {"type": "ref/tool", "name": ...} references as well and completes tool
+// arguments by name: stream URLs from the history and recorded downloads,
+// paths under the output directory, and the variants of a master playlist.
+
+// Most values in one answer, as MCP allows
+const MAX_VALUES: usize = 100;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Completion {
+    pub values: Vec<String>,
+    pub total: usize,
+    pub has_more: bool,
+}
+
+impl Completion {
+    fn from_values(mut values: Vec<String>) -> Self {
+        let total = values.len();
+        values.truncate(MAX_VALUES);
+        Completion { values, total, has_more: total > MAX_VALUES }
+    }
+}
+
+enum ArgumentKind {
+    Url,
+    Path,
+    Variant,
+}
+
+fn argument_kind(name: &str, value: &str) -> Option<ArgumentKind> {
+    match name {
+        "url" => Some(ArgumentKind::Url),
+        "variant" => Some(ArgumentKind::Variant),
+        // Stream URL or file
+        "input" if value.starts_with("http") => Some(ArgumentKind::Url),
+        "output" => Some(ArgumentKind::Path),
+        name if paths::PATH_ARGUMENTS.contains(&name) => Some(ArgumentKind::Path),
+        _ => None,
+    }
+}
+
+// Candidates containing `value`, ignoring case: those starting with it first
+fn matching(candidates: Vec<String>, value: &str) -> Vec<String> {
+    let value = value.to_lowercase();
+    let (mut prefixed, contained): (Vec<String>, Vec<String>) = candidates
+        .into_iter()
+        .filter(|candidate| candidate.to_lowercase().contains(&value))
+        .partition(|candidate| candidate.to_lowercase().starts_with(&value));
+    prefixed.extend(contained);
+    prefixed
+}
+
+async fn saved_urls() -> Vec<String> {
+    match crate::database::global_db().await {
+        Ok(db) => crate::history::saved_urls(&db),
+        Err(_) => crate::history::urls(),
+    }
+    .unwrap_or_default()
+}
+
+// Entries of the directory `value` points into whose names continue it, as
+// `value` would read with them completed: relative paths stay relative to
+// `output_dir`, and directories end with a separator
+fn complete_path(value: &str, output_dir: &Path) -> Vec<String> {
+    let split = value.rfind(|c| c == '/' || (cfg!(windows) && c == '\\')).map_or(0, |i| i + 1);
+    let (typed_dir, prefix) = value.split_at(split);
+    let dir = paths::resolve_output_path(&paths::expand_path(typed_dir), output_dir);
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let mut values: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Hidden files only when asked for
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let separator = if entry.path().is_dir() { std::path::MAIN_SEPARATOR_STR } else { "" };
+            Some(format!("{}{}{}", typed_dir, name, separator))
+        })
+        .collect();
+    values.sort();
+    values
+}
+
+// "auto", the heights as "720p" and the indexes of the variants of `url`
+async fn variant_choices(url: Option<&str>) -> Vec<String> {
+    let mut values = vec!["auto".to_string()];
+    let variants = match url {
+        Some(url) => crate::variant_select::master_variants(&crate::profiles::parser(), url)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let mut heights: Vec<u64> = variants
+        .iter()
+        .filter_map(|variant| variant.resolution.as_deref()?.split_once('x')?.1.parse().ok())
+        .collect();
+    heights.sort_unstable_by(|a, b| b.cmp(a));
+    heights.dedup();
+    values.extend(heights.into_iter().map(|height| format!("{}p", height)));
+    values.extend((0..variants.len()).map(|index| index.to_string()));
+    values
+}
+
+// Suggestions for the argument `name` of a tool, typed so far as `value`.
+// `context` holds the other arguments already given, which is where the
+// URL of a variant choice comes from.
+pub async fn complete(name: &str, value: &str, context: &Map<String, Value>) -> Completion {
+    let values = match argument_kind(name, value) {
+        Some(ArgumentKind::Url) => matching(saved_urls().await, value),
+        Some(ArgumentKind::Path) => {
+            let output_dir = paths::expand_path(&crate::config::load_config().unwrap_or_default().output_dir);
+            complete_path(value, &output_dir)
+        }
+        Some(ArgumentKind::Variant) => {
+            let url = context.get("url").and_then(|v| v.as_str());
+            matching(variant_choices(url).await, value)
+        }
+        None => Vec::new(),
+    };
+    Completion::from_values(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_path() {
+        let dir = std::env::temp_dir().join(format!("m3u8-completions-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(dir.join("shows")).unwrap();
+        std::fs::write(dir.join("show.mp4"), b"").unwrap();
+        std::fs::write(dir.join(".hidden"), b"").unwrap();
+
+        let separator = std::path::MAIN_SEPARATOR_STR;
+        assert_eq!(complete_path("sho", &dir), vec!["show.mp4".to_string(), format!("shows{}", separator)]);
+        assert_eq!(complete_path("", &dir).len(), 2);
+        assert_eq!(complete_path("shows/", &dir), Vec::<String>::new());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let urls = vec!["https://b.example.com/live.m3u8".to_string(), "https://a.example.com/b.m3u8".to_string()];
+        assert_eq!(matching(urls, "https://b")[0], "https://b.example.com/live.m3u8");
+        assert_eq!(Completion::from_values(vec!["x".to_string(); 150]).values.len(), MAX_VALUES);
+    }
+}
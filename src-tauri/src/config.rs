@@ -0,0 +1,704 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::{AppError, ErrorKind};
+use crate::ffmpeg_wrapper::FFmpegConfig;
+use crate::i18n::Locale;
+
+pub const DEFAULT_MCP_PORT: u16 = 37650;
+pub const DEFAULT_BIND_HOST: &str = "127.0.0.1";
+
+// MCP server settings, restored at startup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct McpConfig {
+    // Interface to listen on; loopback unless LAN exposure is allowed
+    pub bind_host: String,
+    // Explicit opt-in for non-loopback bind hosts
+    pub allow_lan: bool,
+    // 0 means an OS-assigned free port
+    pub port: u16,
+    // Start the server on `port` when the app launches
+    pub auto_start: bool,
+    // Write ~/.m3u8-mcp/server.json while the server is running
+    pub publish_discovery: bool,
+    pub enabled_tools: Vec<String>,
+    // Bearer token for the /artifacts routes; generated when first needed
+    pub auth_token: Option<String>,
+    pub variant_selection: VariantSelection,
+    // Seconds a tools/call may run before it is cancelled; 0 for no limit
+    pub tool_timeout_secs: u64,
+    // Limits for single tools, overriding tool_timeout_secs
+    pub tool_timeouts: BTreeMap<String, u64>,
+    // Heavy tool calls (FFmpeg, media fetches) run at once; the rest wait
+    pub workers: usize,
+    // Calls that may wait for a worker before new ones are turned away
+    pub max_queued: usize,
+    // Characters of a parse, segment or probe result returned inline; the
+    // rest is readable as a result resource. 0 for no cap.
+    pub max_result_chars: usize,
+}
+
+impl McpConfig {
+    // Time limit of a tool call, None when unlimited
+    pub fn tool_timeout(&self, tool: &str) -> Option<std::time::Duration> {
+        let secs = self.tool_timeouts.get(tool).copied().unwrap_or(self.tool_timeout_secs);
+        (secs > 0).then(|| std::time::Duration::from_secs(secs))
+    }
+}
+
+// What m3u8_download does with a master playlist when no variant is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariantSelection {
+    // List the variants and wait for a call naming one
+    #[default]
+    Ask,
+    // Let the downloader pick the highest-bandwidth variant
+    Auto,
+}
+
+impl Default for McpConfig {
+    fn default() -> Self {
+        Self {
+            bind_host: DEFAULT_BIND_HOST.to_string(),
+            allow_lan: false,
+            port: DEFAULT_MCP_PORT,
+            auto_start: false,
+            publish_discovery: true,
+            enabled_tools: vec![
+                "m3u8_set_url".to_string(),
+                "m3u8_get_url".to_string(),
+                "m3u8_url_back".to_string(),
+                "m3u8_url_forward".to_string(),
+                "m3u8_pin_url".to_string(),
+                "m3u8_parse".to_string(),
+                "m3u8_download".to_string(),
+                "m3u8_convert".to_string(),
+                "m3u8_probe".to_string(),
+                "m3u8_extract_segments".to_string(),
+                "m3u8_stats".to_string(),
+                "m3u8_compatibility".to_string(),
+                "m3u8_loudness".to_string(),
+                "m3u8_scenes".to_string(),
+                "m3u8_grab_frame".to_string(),
+                "m3u8_fingerprint".to_string(),
+                "m3u8_report".to_string(),
+                "m3u8_transcribe".to_string(),
+                "m3u8_chapters".to_string(),
+                "m3u8_cache_stats".to_string(),
+                "m3u8_cache_list".to_string(),
+                "m3u8_cache_prune".to_string(),
+                "m3u8_trash_list".to_string(),
+                "m3u8_trash_restore".to_string(),
+                "m3u8_resume_jobs".to_string(),
+                "m3u8_generate_master".to_string(),
+                "m3u8_repair".to_string(),
+                "m3u8_ffmpeg_command".to_string(),
+                "m3u8_export_vod".to_string(),
+                "m3u8_interstitials".to_string(),
+                "m3u8_graph".to_string(),
+                "m3u8_abr_simulate".to_string(),
+                "m3u8_detect_stream".to_string(),
+                "m3u8_archive_list".to_string(),
+                "m3u8_archive_reconstruct".to_string(),
+            ],
+            auth_token: None,
+            variant_selection: VariantSelection::default(),
+            tool_timeout_secs: 300,
+            // Downloads, conversions and transcriptions take as long as the media
+            tool_timeouts: ["m3u8_download", "m3u8_convert", "m3u8_transcribe"]
+                .iter()
+                .map(|tool| (tool.to_string(), 0))
+                .collect(),
+            workers: 4,
+            max_queued: 32,
+            max_result_chars: 50_000,
+        }
+    }
+}
+
+// Whether a bind host only accepts connections from this machine
+pub fn is_loopback_host(host: &str) -> bool {
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<std::net::IpAddr>().map(|ip| ip.is_loopback()).unwrap_or(false)
+}
+
+// Speech-to-text post-processing of finished downloads. Either a local
+// whisper.cpp binary (with a model file) or an HTTP endpoint that accepts
+// OpenAI-style multipart transcription requests.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranscriptionConfig {
+    // Transcribe every download automatically when it completes
+    pub auto_transcribe: bool,
+    // whisper.cpp CLI, e.g. "whisper-cli"
+    pub whisper_path: Option<String>,
+    // ggml model file passed to the whisper binary with -m
+    pub model_path: Option<String>,
+    // e.g. http://127.0.0.1:8080/inference or .../v1/audio/transcriptions
+    pub endpoint: Option<String>,
+    // Model name sent to the HTTP endpoint
+    pub endpoint_model: Option<String>,
+    // Spoken language hint such as "en" or "ja"; auto-detected when unset
+    pub language: Option<String>,
+}
+
+// Periodic validation of saved URLs (history and recorded downloads)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkCheckConfig {
+    // Hours between checks; 0 disables the periodic check
+    pub interval_hours: u32,
+    // Keep the playlist text from the last successful check, so dead
+    // entries still show what the stream looked like
+    pub keep_snapshots: bool,
+    // Remove dead URLs from the history; their status and snapshot stay
+    // in the database
+    pub archive_dead: bool,
+}
+
+// Where playlist snapshots are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotStorage {
+    #[default]
+    Database,
+    Files,
+}
+
+// Archiving of raw playlist text whenever a URL is parsed or downloaded
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    pub enabled: bool,
+    pub storage: SnapshotStorage,
+    // Directory for file storage; defaults to ~/.m3u8-mcp/snapshots
+    pub dir: Option<String>,
+}
+
+// Protection of local data at rest
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    // Encrypt cache.db with SQLCipher; the key lives in the OS keychain.
+    // Takes effect the next time the database is opened.
+    pub encrypt_database: bool,
+}
+
+// How long each kind of personal data is kept. None keeps it forever;
+// download records are always kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    // URL history entries
+    pub history_days: Option<u32>,
+    // Cached playlists, probe results and stream fingerprints
+    pub cache_days: Option<u32>,
+    // Playlist snapshots and link check records
+    pub audit_days: Option<u32>,
+    // Tool results stored as m3u8://results/ resources
+    pub results_days: Option<u32>,
+    // Cleared cache and history kept for restoring
+    pub trash_days: Option<u32>,
+    // Hours between maintenance runs; 0 disables the periodic run
+    pub maintenance_interval_hours: u32,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            history_days: None,
+            cache_days: None,
+            audit_days: None,
+            results_days: Some(7),
+            trash_days: Some(7),
+            maintenance_interval_hours: 24,
+        }
+    }
+}
+
+// How stream URLs become the keys of cached results and history entries,
+// so the same stream with a fresh token is found again. Fetches always use
+// the URL as given.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CanonicalUrlConfig {
+    // Query parameters left out of keys, compared case-insensitively; a
+    // trailing * matches every parameter starting with the rest
+    pub strip_query_params: Vec<String>,
+    pub lowercase_host: bool,
+}
+
+impl Default for CanonicalUrlConfig {
+    fn default() -> Self {
+        Self {
+            // Signed URL parameters of common CDNs
+            strip_query_params: ["token", "expires", "signature", "policy", "key-pair-id", "hdnts", "hdnea", "x-amz-*"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            lowercase_host: true,
+        }
+    }
+}
+
+// Which engine fetches streams
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadEngine {
+    // FFmpeg reads the playlist and writes the output in one pass
+    #[default]
+    Ffmpeg,
+    // Segments are fetched, decrypted and hashed here, then remuxed by FFmpeg
+    Native,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloaderConfig {
+    pub engine: DownloadEngine,
+    // Segments fetched at once by the native downloader; with adaptive
+    // concurrency, the starting point for hosts not seen before
+    pub concurrency: usize,
+    // Write <output>.manifest.json with per-segment SHA-256 and key info
+    pub write_manifest: bool,
+    // Tune connections per host from its throughput and errors
+    pub adaptive_concurrency: bool,
+    pub max_concurrency: usize,
+    // Default for jobs started without their own retry policy
+    pub retry: RetryPolicy,
+    // Subfolders of the output directory for downloads without an output
+    // path, one level per rule in order
+    pub organize: Vec<OrganizeRule>,
+    // Write a VOD playlist next to native .ts recordings of live streams
+    pub export_vod: bool,
+    // Name downloads after the ID3 title of the first segment when the
+    // session data names none
+    pub id3_title: bool,
+    // Default for jobs started without their own audio track choice
+    pub audio: AudioTracks,
+    // Languages to pick EXT-X-MEDIA audio and subtitle renditions by,
+    // preferred first
+    pub preferred_languages: Vec<String>,
+    // Default for jobs started without their own interstitial handling
+    pub interstitials: InterstitialMode,
+    // Fetch the native downloader's segments with aria2c
+    pub aria2c: Aria2cConfig,
+    // Keep the native downloader's segments in a content-addressed archive
+    pub archive: ArchiveConfig,
+}
+
+impl Default for DownloaderConfig {
+    fn default() -> Self {
+        Self {
+            engine: DownloadEngine::default(),
+            concurrency: 4,
+            write_manifest: true,
+            adaptive_concurrency: true,
+            max_concurrency: 16,
+            retry: RetryPolicy::default(),
+            organize: Vec::new(),
+            export_vod: false,
+            id3_title: false,
+            audio: AudioTracks::default(),
+            preferred_languages: Vec::new(),
+            interstitials: InterstitialMode::default(),
+            aria2c: Aria2cConfig::default(),
+            archive: ArchiveConfig::default(),
+        }
+    }
+}
+
+// aria2c as the segment fetcher of the native downloader. Decryption,
+// hashing and variant fallback stay with the native downloader, which also
+// fetches whatever aria2c could not.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Aria2cConfig {
+    pub enabled: bool,
+    // aria2c binary; looked up on PATH when not set
+    pub path: Option<String>,
+    // Attempts per segment before aria2c gives up on it
+    pub max_tries: u32,
+    // Extra request headers, e.g. Referer or Cookie, sent with every segment
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Default for Aria2cConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            max_tries: 5,
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+// Segment archive of the native downloader, see segment_archive
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveConfig {
+    pub enabled: bool,
+    // Archive directory; `archive` under the output directory when not set
+    pub dir: Option<String>,
+}
+
+fn default_date_folder() -> String {
+    "%Y-%m".to_string()
+}
+
+// What a download's subfolder is named after
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "by", rename_all = "snake_case")]
+pub enum OrganizeRule {
+    // Download date as a chrono format; "/" in it nests folders
+    Date {
+        #[serde(default = "default_date_folder")]
+        format: String,
+    },
+    // Host of the stream URL, without "www."
+    Host,
+    // Name of the download's project; skipped for downloads without one
+    Project,
+}
+
+// Audio tracks a download keeps. Each kept track carries its language tag
+// and disposition into the output.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioTracks {
+    // Languages (as in EXT-X-MEDIA LANGUAGE) of the tracks to keep, preferred
+    // first; empty keeps every track
+    pub languages: Vec<String>,
+    // Also extract each kept track into its own file next to the output
+    pub split: bool,
+}
+
+// What a download does with HLS interstitials (EXT-X-DATERANGE of class
+// com.apple.hls.interstitial), whose content is not part of the playlist
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InterstitialMode {
+    // Download the primary content only
+    #[default]
+    Skip,
+    // Also download each interstitial's assets into files next to the output
+    Separate,
+    // Splice the assets into the output where players would show them
+    Include,
+}
+
+// When and how often a failed download is started again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    // Attempts in total, including the first; 1 disables retrying
+    pub max_attempts: u32,
+    pub initial_delay_secs: f64,
+    // Each wait is this much longer than the one before
+    pub backoff_multiplier: f64,
+    pub max_delay_secs: f64,
+    // Error kinds worth another attempt
+    pub retry_on: Vec<ErrorKind>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay_secs: 5.0,
+            backoff_multiplier: 2.0,
+            max_delay_secs: 300.0,
+            retry_on: vec![ErrorKind::Network],
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Wait before attempt `attempt + 1`, after `attempt` attempts have failed
+    pub fn delay(&self, attempt: u32) -> std::time::Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let secs = self.initial_delay_secs * self.backoff_multiplier.max(1.0).powi(exponent);
+        std::time::Duration::from_secs_f64(secs.clamp(0.0, self.max_delay_secs.max(0.0)))
+    }
+
+    // Network errors the server made final, such as 403 or 404, are not retried
+    pub fn should_retry(&self, attempt: u32, error: &AppError) -> bool {
+        let permanent = error.kind == ErrorKind::Network && !error.retryable;
+        attempt < self.max_attempts && self.retry_on.contains(&error.kind) && !permanent
+    }
+}
+
+// Thumbnail and preview generation for downloaded files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThumbnailConfig {
+    // FFmpeg processes run at once; read when the queue first starts
+    pub workers: usize,
+    // Let FFmpeg pick a hardware decoder (-hwaccel auto)
+    pub hardware_decode: bool,
+    // Maximum width in pixels; smaller videos keep their size
+    pub width: u32,
+    // Length of the preview clip written next to the thumbnail; 0 for none
+    pub preview_seconds: u32,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            workers: 2,
+            hardware_decode: false,
+            width: 320,
+            preview_seconds: 0,
+        }
+    }
+}
+
+// A named set of settings, e.g. "work VPN" or "home". Fields that are set
+// override the top-level configuration while the profile is active.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub output_dir: Option<String>,
+    pub proxy: Option<String>,
+    // Keychain entry holding "user:password" for the proxy
+    pub proxy_credentials: Option<String>,
+    // MCP tools exposed while the profile is active
+    pub enabled_tools: Option<Vec<String>>,
+}
+
+// Engine the UI talks to. When enabled, parsing and downloads go to the job
+// API of a headless instance (`m3u8-mcp --headless`) instead of this process.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    pub enabled: bool,
+    // e.g. http://nas.local:37650
+    pub base_url: String,
+    // mcp.auth_token of the remote instance
+    pub token: Option<String>,
+}
+
+// Which releases count as updates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    // Pre-releases as well
+    Beta,
+}
+
+// Where check_for_updates looks for new versions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    // GitHub releases API of the project, or a feed answering in its format
+    pub feed_url: String,
+    pub channel: UpdateChannel,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            feed_url: "https://api.github.com/repos/yonaka15/m3u8-mcp/releases".to_string(),
+            channel: UpdateChannel::default(),
+        }
+    }
+}
+
+// Copies of the job, progress and capture events, one JSON object per line,
+// for supervisors and dashboards of a headless instance
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventLogConfig {
+    // JSON-lines file the events are appended to
+    pub path: Option<String>,
+    // Unix socket the app listens on; every connected client gets the lines
+    pub socket: Option<String>,
+}
+
+// How the processes the app runs (FFmpeg, ffprobe, aria2c, Whisper) are
+// started and stopped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessConfig {
+    // Environment variables FFmpeg gets besides PATH, HOME, locale, temp
+    // directory and certificate settings; every other one is withheld
+    pub pass_env: Vec<String>,
+    // Run each FFmpeg job in a scratch directory of its own, removed when
+    // the job ends, instead of the app's working directory
+    pub job_working_dir: bool,
+    // Seconds a stopped process gets to exit on its own before it and
+    // everything it started are killed; 0 kills at once
+    pub kill_grace_secs: u64,
+}
+
+impl Default for ProcessConfig {
+    fn default() -> Self {
+        Self {
+            pass_env: Vec::new(),
+            job_working_dir: false,
+            kill_grace_secs: 2,
+        }
+    }
+}
+
+// Application configuration stored in ~/.m3u8-mcp/config.json.
+// Missing fields fall back to defaults so older config files keep loading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub ffmpeg_path: Option<String>,
+    pub output_dir: String,
+    // Language of user-facing status and error messages
+    pub locale: Locale,
+    pub mcp: McpConfig,
+    pub transcription: TranscriptionConfig,
+    // Re-probe saved URLs in the background when the app starts
+    pub warm_probe_cache_on_start: bool,
+    // Offer a report of crashes since the last launch when the app starts
+    pub show_crash_reports: bool,
+    // Count tool calls and downloads in the local database (never sent anywhere)
+    pub collect_usage_stats: bool,
+    pub link_check: LinkCheckConfig,
+    pub snapshots: SnapshotConfig,
+    pub downloader: DownloaderConfig,
+    pub thumbnails: ThumbnailConfig,
+    pub encryption: EncryptionConfig,
+    pub retention: RetentionConfig,
+    pub canonical_urls: CanonicalUrlConfig,
+    // HTTP proxy for playlist fetches, segment downloads and FFmpeg
+    pub proxy: Option<String>,
+    pub proxy_credentials: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+    pub active_profile: Option<String>,
+    pub remote: RemoteConfig,
+    pub updates: UpdateConfig,
+    pub event_log: EventLogConfig,
+    // Offline mode: serve playlists, keys and segments from files under this
+    // directory (<dir>/<host>/<path>) instead of the network
+    pub offline_fixtures: Option<String>,
+    pub processes: ProcessConfig,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            ffmpeg_path: None,
+            output_dir: FFmpegConfig::default().default_output_dir.to_string_lossy().to_string(),
+            locale: Locale::default(),
+            mcp: McpConfig::default(),
+            transcription: TranscriptionConfig::default(),
+            warm_probe_cache_on_start: false,
+            show_crash_reports: true,
+            collect_usage_stats: false,
+            link_check: LinkCheckConfig::default(),
+            snapshots: SnapshotConfig::default(),
+            downloader: DownloaderConfig::default(),
+            thumbnails: ThumbnailConfig::default(),
+            encryption: EncryptionConfig::default(),
+            retention: RetentionConfig::default(),
+            canonical_urls: CanonicalUrlConfig::default(),
+            proxy: None,
+            proxy_credentials: None,
+            profiles: BTreeMap::new(),
+            active_profile: None,
+            remote: RemoteConfig::default(),
+            updates: UpdateConfig::default(),
+            event_log: EventLogConfig::default(),
+            offline_fixtures: None,
+            processes: ProcessConfig::default(),
+        }
+    }
+}
+
+impl AppConfig {
+    // This configuration with the active profile's overrides applied
+    pub fn effective(&self) -> AppConfig {
+        let mut config = self.clone();
+        let Some(profile) = self.active_profile.as_ref().and_then(|name| self.profiles.get(name)) else {
+            return config;
+        };
+        if let Some(output_dir) = &profile.output_dir {
+            config.output_dir = output_dir.clone();
+        }
+        if profile.proxy.is_some() {
+            config.proxy = profile.proxy.clone();
+            config.proxy_credentials = profile.proxy_credentials.clone();
+        }
+        if let Some(tools) = &profile.enabled_tools {
+            config.mcp.enabled_tools = tools.clone();
+        }
+        config
+    }
+
+    // FFmpeg settings derived from this configuration
+    pub fn ffmpeg_config(&self) -> FFmpegConfig {
+        FFmpegConfig {
+            ffmpeg_path: self.ffmpeg_path.as_deref()
+                .map(|path| crate::paths::expand_path(path).to_string_lossy().to_string()),
+            default_output_dir: crate::paths::expand_path(&self.output_dir),
+            process: self.processes.clone(),
+            ..FFmpegConfig::default()
+        }
+    }
+}
+
+// ~/.m3u8-mcp, where config, history and the cache database live
+pub fn app_dir() -> Result<PathBuf, AppError> {
+    let home_dir = dirs::home_dir()
+        .ok_or_else(|| AppError::not_found("Failed to get home directory"))?;
+    Ok(home_dir.join(".m3u8-mcp"))
+}
+
+pub fn load_config() -> Result<AppConfig, AppError> {
+    let config_path = app_dir()?.join("config.json");
+
+    if !config_path.exists() {
+        return Ok(AppConfig::default());
+    }
+
+    let config_str = fs::read_to_string(config_path)
+        .map_err(|e| AppError::io(format!("Failed to read configuration: {}", e)))?;
+
+    serde_json::from_str(&config_str)
+        .map_err(|e| AppError::internal(format!("Failed to parse configuration: {}", e)))
+}
+
+pub fn save_config(config: &AppConfig) -> Result<(), AppError> {
+    let config_dir = app_dir()?;
+
+    fs::create_dir_all(&config_dir)
+        .map_err(|e| AppError::io(format!("Failed to create config directory: {}", e)))?;
+
+    let config_str = serde_json::to_string_pretty(config)
+        .map_err(|e| AppError::internal(format!("Failed to serialize configuration: {}", e)))?;
+
+    fs::write(config_dir.join("config.json"), config_str)
+        .map_err(|e| AppError::io(format!("Failed to save configuration: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy { max_delay_secs: 15.0, ..RetryPolicy::default() };
+        let delays: Vec<f64> = (1..=4).map(|attempt| policy.delay(attempt).as_secs_f64()).collect();
+        assert_eq!(delays, vec![5.0, 10.0, 15.0, 15.0]);
+
+        let network = AppError::new(ErrorKind::Network, "connection reset");
+        let parse = AppError::new(ErrorKind::Parse, "not a playlist");
+        assert!(policy.should_retry(1, &network));
+        assert!(policy.should_retry(2, &network));
+        assert!(!policy.should_retry(3, &network));
+        assert!(!policy.should_retry(1, &parse));
+        assert!(!policy.should_retry(1, &network.with_retryable(false)));
+    }
+}
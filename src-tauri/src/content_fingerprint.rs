@@ -0,0 +1,239 @@
+use serde::Serialize;
+
+use crate::database::{Database, StreamFingerprint};
+use crate::error::AppError;
+use crate::ffmpeg_wrapper::FFmpegService;
+use crate::m3u8_parser::{M3u8Parser, ParsedPlaylist};
+use crate::media_analysis::{self, FramePosition};
+
+// Perceptual fingerprints of what a stream shows, to tell that two URLs
+// serve the same content before downloading both. Frames are sampled at
+// fixed fractions of the stream and reduced to a 64-bit difference hash
+// (dHash): the frame shrunk to 9x8 grey pixels, one bit per pair of
+// horizontal neighbours saying whether the left one is brighter. The hash
+// survives re-encoding, scaling and a different CDN, so renditions of the
+// same video come out a few bits apart while unrelated frames differ in
+// about half of them. duplicates.rs, by contrast, only recognises the same
+// playlist behind a different URL.
+
+const HASH_WIDTH: usize = 9;
+const HASH_HEIGHT: usize = 8;
+
+pub const DEFAULT_SAMPLES: usize = 5;
+pub const MAX_SAMPLES: usize = 20;
+
+// Frames whose hashes differ in at most this many bits show the same picture
+const MATCH_BITS: u32 = 10;
+// Share of the compared frames that must match for the same content
+const DUPLICATE_SHARE: f64 = 0.6;
+// Longest and shortest VOD may differ by this share and still be the same
+const DURATION_TOLERANCE: f64 = 0.02;
+// Frames whose pixels span fewer grey levels are black or a flat colour,
+// which fades and slates of unrelated streams share
+const MIN_CONTRAST: u8 = 8;
+
+// Other stored fingerprints listed with a new one
+const MAX_SIMILAR: usize = 20;
+
+fn dhash(pixels: &[u8]) -> Option<u64> {
+    if pixels.len() != HASH_WIDTH * HASH_HEIGHT {
+        return None;
+    }
+    let darkest = pixels.iter().min()?;
+    let brightest = pixels.iter().max()?;
+    if brightest - darkest < MIN_CONTRAST {
+        return None;
+    }
+    let mut hash = 0u64;
+    for row in pixels.chunks(HASH_WIDTH) {
+        for pair in row.windows(2) {
+            hash = (hash << 1) | u64::from(pair[0] > pair[1]);
+        }
+    }
+    Some(hash)
+}
+
+fn distance(a: &str, b: &str) -> Option<u32> {
+    let a = u64::from_str_radix(a, 16).ok()?;
+    let b = u64::from_str_radix(b, 16).ok()?;
+    Some((a ^ b).count_ones())
+}
+
+// Where to take `samples` frames: evenly through a VOD, in the middle of
+// each stretch; across the window of a live playlist by segment, since
+// FFmpeg cannot seek in one
+fn sample_positions(durations: &[f64], live: bool, samples: usize) -> Vec<FramePosition> {
+    if live {
+        let samples = samples.min(durations.len());
+        return (0..samples)
+            .map(|i| {
+                let index = i * durations.len() / samples;
+                FramePosition::LiveSegment { index, offset: durations[index] / 2.0 }
+            })
+            .collect();
+    }
+    let total: f64 = durations.iter().sum();
+    (0..samples)
+        .map(|i| FramePosition::Time(total * (i as f64 + 0.5) / samples as f64))
+        .collect()
+}
+
+// The segment durations of the media playlist to sample, and whether it is
+// live. For a master playlist that is its lowest-bandwidth variant, the
+// cheapest to decode, which shows the same pictures as the others.
+async fn media_playlist(parser: &M3u8Parser, url: &str) -> Result<(String, Vec<f64>, bool), AppError> {
+    let content = parser.fetch_playlist(url).await?;
+    let (content, media_url) = match parser.parse_content(&content, url)? {
+        ParsedPlaylist::Media { .. } => (content, url.to_string()),
+        ParsedPlaylist::Master { variants, .. } => {
+            let variant = variants.iter().min_by_key(|variant| variant.bandwidth)
+                .ok_or_else(|| AppError::invalid_input("Master playlist has no variants"))?;
+            (parser.fetch_playlist(&variant.uri).await?, variant.uri.clone())
+        }
+    };
+    let ParsedPlaylist::Media { segments, .. } = parser.parse_content(&content, &media_url)? else {
+        return Err(AppError::invalid_input("Variant playlist is itself a master playlist"));
+    };
+    if segments.is_empty() {
+        return Err(AppError::invalid_input("Playlist has no segments"));
+    }
+    let durations = segments.iter().map(|segment| f64::from(segment.duration)).collect();
+    Ok((media_url, durations, !content.contains("#EXT-X-ENDLIST")))
+}
+
+// How a stored fingerprint compares with another
+#[derive(Debug, Clone, Serialize)]
+pub struct Similarity {
+    pub url: String,
+    // Frames at the same position that both fingerprints have, and those
+    // of them showing the same picture
+    pub compared_frames: usize,
+    pub matching_frames: usize,
+    // Differing bits per compared frame, 0 (identical) to 64
+    pub mean_distance: f64,
+    // Whether the two URLs most likely serve the same content
+    pub duplicate: bool,
+    pub fingerprinted_at: String,
+}
+
+fn compare(a: &StreamFingerprint, b: &StreamFingerprint) -> Option<Similarity> {
+    // Samples sit at the same fractions of both streams only with the same
+    // number of them
+    if a.hashes.len() != b.hashes.len() {
+        return None;
+    }
+    let distances: Vec<u32> = a.hashes.iter().zip(&b.hashes)
+        .filter_map(|(a, b)| distance(a.as_deref()?, b.as_deref()?))
+        .collect();
+    if distances.is_empty() {
+        return None;
+    }
+    let matching_frames = distances.iter().filter(|&&bits| bits <= MATCH_BITS).count();
+    let same_length = a.live || b.live
+        || (a.duration - b.duration).abs() <= a.duration.max(b.duration) * DURATION_TOLERANCE;
+    Some(Similarity {
+        url: b.url.clone(),
+        compared_frames: distances.len(),
+        matching_frames,
+        mean_distance: distances.iter().sum::<u32>() as f64 / distances.len() as f64,
+        duplicate: same_length && matching_frames as f64 >= distances.len() as f64 * DUPLICATE_SHARE,
+        fingerprinted_at: b.fingerprinted_at.clone(),
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FingerprintReport {
+    #[serde(flatten)]
+    pub fingerprint: StreamFingerprint,
+    // Stored fingerprints of other URLs with frames to compare, closest first
+    pub similar: Vec<Similarity>,
+}
+
+// Fingerprint the stream at `url` from `samples` frames, store it, and
+// compare it with the fingerprints stored before
+pub async fn fingerprint(
+    db: &Database,
+    ffmpeg: &FFmpegService,
+    parser: &M3u8Parser,
+    url: &str,
+    samples: usize,
+) -> Result<FingerprintReport, AppError> {
+    let (media_url, durations, live) = media_playlist(parser, url).await?;
+    let mut hashes = Vec::new();
+    for position in sample_positions(&durations, live, samples.clamp(1, MAX_SAMPLES)) {
+        let pixels = media_analysis::grab_gray_pixels(ffmpeg, &media_url, position, HASH_WIDTH, HASH_HEIGHT).await?;
+        hashes.push(pixels.as_deref().and_then(dhash).map(|hash| format!("{:016x}", hash)));
+    }
+    if hashes.iter().all(Option::is_none) {
+        return Err(AppError::not_found("No sampled frame shows a picture to fingerprint"));
+    }
+
+    let duration = durations.iter().sum();
+    db.save_fingerprint(url, duration, live, &hashes)?;
+    let fingerprint = StreamFingerprint {
+        url: url.to_string(),
+        duration,
+        live,
+        hashes,
+        fingerprinted_at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+
+    let key = crate::canonical_url::key(url);
+    let mut similar: Vec<Similarity> = db.list_fingerprints()?
+        .iter()
+        .filter(|stored| stored.url != key)
+        .filter_map(|stored| compare(&fingerprint, stored))
+        .collect();
+    similar.sort_by(|a, b| b.duplicate.cmp(&a.duplicate).then(a.mean_distance.total_cmp(&b.mean_distance)));
+    similar.truncate(MAX_SIMILAR);
+    Ok(FingerprintReport { fingerprint, similar })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(url: &str, duration: f64, hashes: &[Option<&str>]) -> StreamFingerprint {
+        StreamFingerprint {
+            url: url.to_string(),
+            duration,
+            live: false,
+            hashes: hashes.iter().map(|hash| hash.map(str::to_string)).collect(),
+            fingerprinted_at: "2024-05-01 00:00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_compare_fingerprints() {
+        let gradient: Vec<u8> = (0..72).map(|i| (i % 9) as u8 * 20).collect();
+        assert_eq!(dhash(&gradient), Some(0));
+        let falling: Vec<u8> = (0..72).map(|i| 200 - (i % 9) as u8 * 20).collect();
+        assert_eq!(dhash(&falling), Some(u64::MAX));
+        assert_eq!(dhash(&[16; 72]), None);
+        assert_eq!(dhash(&[0; 10]), None);
+
+        let a = stored("a", 600.0, &[Some("ffff0000ffff0000"), Some("0123456789abcdef"), None]);
+        // Re-encoded: a couple of bits off
+        let b = stored("b", 603.0, &[Some("ffff0000ffff0003"), Some("0123456789abcdee"), Some("00000000ffffffff")]);
+        let similarity = compare(&a, &b).unwrap();
+        assert_eq!((similarity.compared_frames, similarity.matching_frames), (2, 2));
+        assert_eq!(similarity.mean_distance, 1.5);
+        assert!(similarity.duplicate);
+
+        // Same pictures, but a different length
+        assert!(!compare(&a, &stored("c", 900.0, &[Some("ffff0000ffff0000"), Some("0123456789abcdef"), None])).unwrap().duplicate);
+        let other = stored("d", 600.0, &[Some("0000ffff0000ffff"), Some("0123456789abcdef"), None]);
+        assert!(!compare(&a, &other).unwrap().duplicate);
+        assert!(compare(&a, &stored("e", 600.0, &[Some("ffff0000ffff0000")])).is_none());
+
+        let positions = sample_positions(&[6.0; 10], false, 4);
+        assert_eq!(positions[0], FramePosition::Time(7.5));
+        assert_eq!(positions[3], FramePosition::Time(52.5));
+        let live = sample_positions(&[4.0; 3], true, 5);
+        assert_eq!(live, vec![
+            FramePosition::LiveSegment { index: 0, offset: 2.0 },
+            FramePosition::LiveSegment { index: 1, offset: 2.0 },
+            FramePosition::LiveSegment { index: 2, offset: 2.0 },
+        ]);
+    }
+}
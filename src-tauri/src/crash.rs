@@ -0,0 +1,201 @@
+use chrono::{SecondsFormat, Utc};
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::fs;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::config;
+use crate::error::AppError;
+
+// Panics recorded in ~/.m3u8-mcp/crashes, one JSON file per crash named by
+// when it happened, so they are not lost with the stderr of a background
+// task and can be shown on the next launch
+
+// Crash records kept; older ones are deleted as new ones come in
+const KEEP: usize = 20;
+
+// File holding the id of the newest crash the user has already seen
+const SEEN_FILE: &str = "seen";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashRecord {
+    pub id: String,
+    pub time: String,
+    pub version: String,
+    pub thread: String,
+    // Background task that panicked, when it ran under `guard`
+    pub task: Option<String>,
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+}
+
+thread_local! {
+    // Task `guard` is polling on this thread
+    static CURRENT_TASK: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+fn crashes_dir() -> Result<PathBuf, AppError> {
+    Ok(config::app_dir()?.join("crashes"))
+}
+
+// `text` with URL credentials and query strings (signed tokens, API keys)
+// replaced by *** and the home directory shortened to ~
+pub fn redact(text: &str) -> String {
+    let mut redacted = text
+        .split(' ')
+        .map(|word| {
+            if !word.contains("://") {
+                return word.to_string();
+            }
+            let word = crate::ffmpeg_wrapper::redact_credentials(word);
+            match word.split_once('?') {
+                Some((url, _)) => format!("{}?***", url),
+                None => word,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    if let Some(home) = dirs::home_dir().map(|home| home.to_string_lossy().to_string()).filter(|home| home.len() > 1) {
+        redacted = redacted.replace(&home, "~");
+    }
+    redacted
+}
+
+fn write(record: &CrashRecord) -> Result<(), AppError> {
+    let dir = crashes_dir()?;
+    fs::create_dir_all(&dir)?;
+    let json = serde_json::to_string_pretty(record).map_err(|e| AppError::internal(e.to_string()))?;
+    fs::write(dir.join(format!("{}.json", record.id)), json)?;
+
+    let mut records = list();
+    if records.len() > KEEP {
+        for old in records.split_off(KEEP) {
+            let _ = fs::remove_file(dir.join(format!("{}.json", old.id)));
+        }
+    }
+    Ok(())
+}
+
+// Record every panic, then report it as Rust would. Call once, before
+// anything is spawned.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Box<dyn Any>".to_string());
+        let now = Utc::now();
+        let record = CrashRecord {
+            id: now.format("%Y%m%dT%H%M%S%3f").to_string(),
+            time: now.to_rfc3339_opts(SecondsFormat::Secs, true),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            thread: std::thread::current().name().unwrap_or("unnamed").to_string(),
+            task: CURRENT_TASK.with(|task| task.get()).map(str::to_string),
+            message: redact(&message),
+            location: info.location().map(|location| format!("{}:{}", location.file(), location.line())),
+            backtrace: redact(&std::backtrace::Backtrace::force_capture().to_string()),
+        };
+        if let Err(e) = write(&record) {
+            eprintln!("Failed to record crash: {}", e);
+        }
+        default_hook(info);
+    }));
+}
+
+// A future that names its task for the panic hook while it is polled
+struct Named<F> {
+    task: &'static str,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Named<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let previous = CURRENT_TASK.with(|task| task.replace(Some(self.task)));
+        let poll = self.future.as_mut().poll(cx);
+        CURRENT_TASK.with(|task| task.set(previous));
+        poll
+    }
+}
+
+// Run a background task, catching a panic in it so the caller can put its
+// state right. The panic is recorded under `task`; None is returned for it.
+pub async fn guard<F: Future>(task: &'static str, future: F) -> Option<F::Output> {
+    match AssertUnwindSafe(Named { task, future: Box::pin(future) }).catch_unwind().await {
+        Ok(output) => Some(output),
+        Err(_) => {
+            eprintln!("Background task {} panicked; see {}", task, crashes_dir().map(|d| d.display().to_string()).unwrap_or_default());
+            None
+        }
+    }
+}
+
+// Recorded crashes, newest first
+pub fn list() -> Vec<CrashRecord> {
+    let Ok(entries) = crashes_dir().and_then(|dir| Ok(fs::read_dir(dir)?)) else {
+        return Vec::new();
+    };
+    let mut records: Vec<CrashRecord> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|extension| extension == "json"))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect();
+    records.sort_by(|a, b| b.id.cmp(&a.id));
+    records
+}
+
+// Crashes since the user last dismissed the report
+pub fn unseen() -> Vec<CrashRecord> {
+    let seen = crashes_dir()
+        .and_then(|dir| Ok(fs::read_to_string(dir.join(SEEN_FILE))?))
+        .unwrap_or_default();
+    list().into_iter().filter(|record| record.id.as_str() > seen.trim()).collect()
+}
+
+// Mark every crash recorded so far as seen
+pub fn mark_seen() -> Result<(), AppError> {
+    let Some(newest) = list().into_iter().next() else {
+        return Ok(());
+    };
+    let dir = crashes_dir()?;
+    fs::write(dir.join(SEEN_FILE), newest.id)?;
+    Ok(())
+}
+
+// Delete every crash record
+pub fn clear() -> usize {
+    let Ok(dir) = crashes_dir() else {
+        return 0;
+    };
+    let count = list()
+        .iter()
+        .filter(|record| fs::remove_file(dir.join(format!("{}.json", record.id))).is_ok())
+        .count();
+    let _ = fs::remove_file(dir.join(SEEN_FILE));
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(
+            redact("Failed to fetch https://user:pw@cdn.example.com/live/index.m3u8?token=abc: timed out"),
+            "Failed to fetch https://***@cdn.example.com/live/index.m3u8?*** timed out"
+        );
+        assert_eq!(redact("index out of bounds: the len is 3"), "index out of bounds: the len is 3");
+    }
+}
@@ -0,0 +1,166 @@
+use std::fs;
+use tokio::sync::RwLock;
+
+use crate::config;
+use crate::error::AppError;
+
+// The URL each client is working on. MCP sessions keep theirs in their
+// Session, the UI has its own here, and whoever sets one last also sets the
+// shared URL, which clients without a URL of their own (or without a
+// session) fall back to.
+
+// URLs kept to go back to, per client
+const HISTORY_LIMIT: usize = 50;
+
+// File in the app directory holding the pinned URL
+const PINNED_FILE: &str = "pinned_url";
+
+lazy_static::lazy_static! {
+    static ref UI_URL: RwLock<UrlHistory> = RwLock::new(UrlHistory::default());
+    static ref SHARED_URL: RwLock<Option<String>> = RwLock::new(None);
+}
+
+// A client's current URL with the ones it had before and, after going back,
+// the ones it can go forward to again
+#[derive(Debug, Clone, Default)]
+pub struct UrlHistory {
+    back: Vec<String>,
+    current: Option<String>,
+    forward: Vec<String>,
+}
+
+impl UrlHistory {
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    // Make `url` current. A URL that extends or shortens the current one is
+    // the same URL being typed, so it replaces it rather than adding a step.
+    pub fn set(&mut self, url: Option<String>) {
+        if url == self.current {
+            return;
+        }
+        let editing = match (&self.current, &url) {
+            (Some(current), Some(url)) => url.starts_with(current.as_str()) || current.starts_with(url.as_str()),
+            _ => false,
+        };
+        if let Some(previous) = self.current.take().filter(|_| !editing) {
+            self.back.push(previous);
+            if self.back.len() > HISTORY_LIMIT {
+                self.back.remove(0);
+            }
+        }
+        self.forward.clear();
+        self.current = url;
+    }
+
+    // Return to the URL before the current one. None, with nothing changed,
+    // when there is none.
+    pub fn back(&mut self) -> Option<String> {
+        let url = self.back.pop()?;
+        if let Some(current) = self.current.replace(url.clone()) {
+            self.forward.push(current);
+        }
+        Some(url)
+    }
+
+    // Undo a `back`
+    pub fn forward(&mut self) -> Option<String> {
+        let url = self.forward.pop()?;
+        if let Some(current) = self.current.replace(url.clone()) {
+            self.back.push(current);
+        }
+        Some(url)
+    }
+}
+
+// A client's URL: `own` when it has one, else the shared one. The flag
+// tells whether it came from the shared fallback.
+pub async fn resolve(own: Option<String>) -> Option<(String, bool)> {
+    match own {
+        Some(url) => Some((url, false)),
+        None => SHARED_URL.read().await.clone().map(|url| (url, true)),
+    }
+}
+
+pub async fn set_shared(url: String) {
+    *SHARED_URL.write().await = Some(url);
+}
+
+// Set or, with None, clear the UI's URL. Clearing it also takes back the
+// shared URL if that is still the UI's; one another client set stays.
+pub async fn set_ui(url: Option<String>) {
+    let mut history = UI_URL.write().await;
+    let previous = history.current.clone();
+    history.set(url.clone());
+    let mut shared = SHARED_URL.write().await;
+    match url {
+        Some(url) => *shared = Some(url),
+        None if *shared == previous => *shared = None,
+        None => {}
+    }
+}
+
+// The UI's URL, falling back to the shared one
+pub async fn ui() -> Option<String> {
+    resolve(UI_URL.read().await.current.clone()).await.map(|(url, _)| url)
+}
+
+// Step the UI back or forward through its URLs; the one stepped to becomes
+// the shared URL as if set again
+pub async fn ui_step(forward: bool) -> Option<String> {
+    let mut history = UI_URL.write().await;
+    let url = if forward { history.forward() } else { history.back() }?;
+    set_shared(url.clone()).await;
+    Some(url)
+}
+
+// The pinned URL, which is kept across restarts so a reference stream is
+// at hand while the current URL moves between others
+pub fn pinned() -> Option<String> {
+    let path = config::app_dir().ok()?.join(PINNED_FILE);
+    let url = fs::read_to_string(path).ok()?;
+    Some(url.trim().to_string()).filter(|url| !url.is_empty())
+}
+
+pub fn pin(url: &str) -> Result<(), AppError> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Err(AppError::invalid_input("No URL to pin"));
+    }
+    let dir = config::app_dir()?;
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(PINNED_FILE), url)?;
+    Ok(())
+}
+
+// Remove the pinned URL, returning it
+pub fn unpin() -> Result<Option<String>, AppError> {
+    let url = pinned();
+    if url.is_some() {
+        fs::remove_file(config::app_dir()?.join(PINNED_FILE))?;
+    }
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_history() {
+        let mut history = UrlHistory::default();
+        history.set(Some("https://a.example.com/".to_string()));
+        history.set(Some("https://a.example.com/index.m3u8".to_string()));
+        history.set(Some("https://b.example.com/index.m3u8".to_string()));
+        assert_eq!(history.back().as_deref(), Some("https://a.example.com/index.m3u8"));
+        assert_eq!(history.back(), None);
+        assert_eq!(history.forward().as_deref(), Some("https://b.example.com/index.m3u8"));
+        assert_eq!(history.forward(), None);
+
+        history.back();
+        history.set(Some("https://c.example.com/index.m3u8".to_string()));
+        assert_eq!(history.forward(), None);
+        assert_eq!(history.current(), Some("https://c.example.com/index.m3u8"));
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+const MPD_NAMESPACE: &str = "urn:mpeg:dash:schema:mpd:2011";
+
+// Start tags of an XML document as (name, attributes), in document order.
+// Enough for the MPDs FFmpeg writes; comments and declarations are skipped.
+fn start_tags(xml: &str) -> Vec<(String, HashMap<String, String>)> {
+    let mut tags = Vec::new();
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let Some(close) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..close];
+        rest = &rest[close + 1..];
+        if tag.starts_with(['/', '?', '!']) {
+            continue;
+        }
+        let tag = tag.trim_end_matches('/');
+        let (name, mut attrs) = match tag.find(char::is_whitespace) {
+            Some(pos) => (&tag[..pos], &tag[pos..]),
+            None => (tag, ""),
+        };
+        let mut attributes = HashMap::new();
+        while let Some(eq) = attrs.find('=') {
+            let key = attrs[..eq].trim().to_string();
+            let value = attrs[eq + 1..].trim_start();
+            let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+                break;
+            };
+            let Some(end) = value[1..].find(quote) else {
+                break;
+            };
+            attributes.insert(key, value[1..end + 1].to_string());
+            attrs = &value[end + 2..];
+        }
+        tags.push((name.to_string(), attributes));
+    }
+    tags
+}
+
+// File name from a SegmentTemplate pattern ($RepresentationID$, $Number$ and
+// $Number%05d$ style identifiers)
+pub fn expand_template(template: &str, representation_id: &str, number: u64) -> String {
+    let mut out = template.replace("$RepresentationID$", representation_id);
+    while let Some(start) = out.find("$Number") {
+        let Some(len) = out[start + 1..].find('$') else {
+            break;
+        };
+        let spec = &out[start + 7..start + 1 + len];
+        let width: usize = spec
+            .strip_prefix('%')
+            .and_then(|s| s.strip_suffix('d'))
+            .map(|s| s.trim_start_matches('0').parse().unwrap_or(0))
+            .unwrap_or(0);
+        out.replace_range(start..start + len + 2, &format!("{:0width$}", number, width = width));
+    }
+    out
+}
+
+// Problems found in a generated MPD; empty when it looks playable. Init and
+// first media segments referenced by SegmentTemplate must exist in `dir`.
+pub fn validate_mpd(mpd: &str, dir: &Path) -> Vec<String> {
+    let mut issues = Vec::new();
+    let tags = start_tags(mpd);
+    let count = |name: &str| tags.iter().filter(|(n, _)| n == name).count();
+
+    match tags.iter().find(|(name, _)| name == "MPD") {
+        None => issues.push("No MPD element".to_string()),
+        Some((_, mpd)) => {
+            if mpd.get("xmlns").map(String::as_str) != Some(MPD_NAMESPACE) {
+                issues.push(format!("MPD is not in the {} namespace", MPD_NAMESPACE));
+            }
+            if !mpd.contains_key("profiles") {
+                issues.push("MPD has no profiles attribute".to_string());
+            }
+            let is_static = mpd.get("type").map_or(true, |t| t == "static");
+            if is_static && !mpd.contains_key("mediaPresentationDuration") {
+                issues.push("Static MPD has no mediaPresentationDuration".to_string());
+            }
+        }
+    }
+    for name in ["Period", "AdaptationSet", "Representation"] {
+        if count(name) == 0 {
+            issues.push(format!("MPD has no {}", name));
+        }
+    }
+
+    let mut representation: Option<&str> = None;
+    for (name, attributes) in &tags {
+        match name.as_str() {
+            "Representation" => {
+                let id = attributes.get("id").map(String::as_str);
+                if id.is_none() {
+                    issues.push("Representation without id".to_string());
+                }
+                if attributes.get("bandwidth").and_then(|b| b.parse::<u64>().ok()).unwrap_or(0) == 0 {
+                    issues.push(format!("Representation {} has no bandwidth", id.unwrap_or("?")));
+                }
+                representation = id;
+            }
+            "SegmentTemplate" => {
+                let id = representation.unwrap_or("");
+                let start_number = attributes.get("startNumber").and_then(|n| n.parse().ok()).unwrap_or(1);
+                let files = [
+                    attributes.get("initialization").map(|t| expand_template(t, id, start_number)),
+                    attributes.get("media").map(|t| expand_template(t, id, start_number)),
+                ];
+                for file in files.into_iter().flatten() {
+                    if !dir.join(&file).exists() {
+                        issues.push(format!("Segment {} referenced by the MPD is missing", file));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_mpd() {
+        assert_eq!(expand_template("chunk-$RepresentationID$-$Number%05d$.m4s", "0", 1), "chunk-0-00001.m4s");
+        assert_eq!(expand_template("init-$RepresentationID$.m4s", "1", 1), "init-1.m4s");
+
+        let dir = std::env::temp_dir().join(format!("dash-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("init-0.m4s"), b"").unwrap();
+        std::fs::write(dir.join("chunk-0-00001.m4s"), b"").unwrap();
+        let mpd = r#"<?xml version="1.0" encoding="utf-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" profiles="urn:mpeg:dash:profile:isoff-live:2011" type="static" mediaPresentationDuration="PT10.0S">
+    <Period id="0" start="PT0.0S">
+        <AdaptationSet id="0" contentType="video">
+            <Representation id="0" mimeType="video/mp4" codecs="avc1.64001f" bandwidth="1000000" width="1280" height="720">
+                <SegmentTemplate timescale="1000" initialization="init-$RepresentationID$.m4s" media="chunk-$RepresentationID$-$Number%05d$.m4s" startNumber="1"/>
+            </Representation>
+        </AdaptationSet>
+        <AdaptationSet id="1" contentType="audio">
+            <Representation id="1" mimeType="audio/mp4" bandwidth="0">
+                <SegmentTemplate initialization="init-$RepresentationID$.m4s" media="chunk-$RepresentationID$-$Number%05d$.m4s" startNumber="1"/>
+            </Representation>
+        </AdaptationSet>
+    </Period>
+</MPD>"#;
+
+        let issues = validate_mpd(mpd, &dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(issues, vec![
+            "Representation 1 has no bandwidth".to_string(),
+            "Segment init-1.m4s referenced by the MPD is missing".to_string(),
+            "Segment chunk-1-00001.m4s referenced by the MPD is missing".to_string(),
+        ]);
+        assert!(validate_mpd("<html></html>", Path::new(".")).contains(&"No MPD element".to_string()));
+    }
+}
@@ -1,9 +1,11 @@
 use chrono::{DateTime, Utc};
 use rusqlite::{params, Connection, Result, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
+use crate::error::AppError;
+
 pub struct Database {
     conn: Mutex<Connection>,
 }
@@ -32,9 +34,82 @@ pub struct DownloadedStream {
     pub format: Option<String>,
     pub resolution: Option<String>,
     pub bitrate: Option<i32>,
+    // Title of the page the stream was found on
+    pub title: Option<String>,
+    pub project_id: Option<i32>,
+    // Wall-clock time the download took, in seconds
+    pub elapsed_seconds: Option<f64>,
+    // Whether the output was probed successfully after downloading
+    pub verified: Option<bool>,
     pub downloaded_at: DateTime<Utc>,
 }
 
+// A finished download to record
+pub struct NewDownload<'a> {
+    pub url: &'a str,
+    pub output_path: &'a std::path::Path,
+    pub title: Option<&'a str>,
+    pub project_id: Option<i32>,
+    pub elapsed_seconds: Option<f64>,
+    pub duration: Option<f64>,
+    pub verified: Option<bool>,
+    // Fingerprint of the playlist, for spotting the same media under another URL
+    pub content_hash: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Project {
+    pub id: i32,
+    pub name: String,
+    // Where downloads for this project go when no output path is given
+    pub output_dir: Option<String>,
+    // Default download options, free-form JSON owned by the client
+    pub options: serde_json::Value,
+    pub download_count: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+const DOWNLOAD_COLUMNS: &str =
+    "id, url, output_path, file_size, duration, format, resolution, bitrate, title, project_id, \
+     elapsed_seconds, verified, downloaded_at";
+
+fn download_from_row(row: &rusqlite::Row) -> Result<DownloadedStream> {
+    Ok(DownloadedStream {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        output_path: row.get(2)?,
+        file_size: row.get(3)?,
+        duration: row.get(4)?,
+        format: row.get(5)?,
+        resolution: row.get(6)?,
+        bitrate: row.get(7)?,
+        title: row.get(8)?,
+        project_id: row.get(9)?,
+        elapsed_seconds: row.get(10)?,
+        verified: row.get(11)?,
+        downloaded_at: row.get(12)?,
+    })
+}
+
+const PROJECT_QUERY: &str =
+    "SELECT p.id, p.name, p.output_dir, p.options, p.created_at,
+            (SELECT COUNT(*) FROM downloaded_streams d WHERE d.project_id = p.id)
+     FROM projects p";
+
+fn project_from_row(row: &rusqlite::Row) -> Result<Project> {
+    let options: Option<String> = row.get(3)?;
+    Ok(Project {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        output_dir: row.get(2)?,
+        options: options
+            .and_then(|o| serde_json::from_str(&o).ok())
+            .unwrap_or(serde_json::Value::Null),
+        created_at: row.get(4)?,
+        download_count: row.get(5)?,
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProbeResult {
     pub id: i32,
@@ -50,19 +125,261 @@ pub struct ProbeResult {
     pub probed_at: DateTime<Utc>,
 }
 
+// Result of the last liveness check of a saved stream URL
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LinkStatus {
+    pub url: String,
+    pub alive: bool,
+    pub error: Option<String>,
+    // Status of the last playlist request, when the server answered
+    pub http_status: Option<u16>,
+    // The playlist is gone (403/404/410) rather than temporarily unreachable
+    pub dead: bool,
+    pub dead_since: Option<DateTime<Utc>>,
+    // A copy of the playlist from the last successful check is kept
+    pub has_snapshot: bool,
+    pub checked_at: DateTime<Utc>,
+}
+
+const LINK_STATUS_COLUMNS: &str =
+    "url, alive, error, http_status, dead, dead_since, last_playlist IS NOT NULL, checked_at";
+
+fn link_status_from_row(row: &rusqlite::Row) -> Result<LinkStatus> {
+    Ok(LinkStatus {
+        url: row.get(0)?,
+        alive: row.get(1)?,
+        error: row.get(2)?,
+        http_status: row.get(3)?,
+        dead: row.get(4)?,
+        dead_since: row.get(5)?,
+        has_snapshot: row.get(6)?,
+        checked_at: row.get(7)?,
+    })
+}
+
+// Raw playlist text captured when a URL was parsed or downloaded. Variant
+// playlists of a master are stored as children of its snapshot.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlaylistSnapshot {
+    pub id: i64,
+    pub parent_id: Option<i64>,
+    pub url: String,
+    // "parse" or "download"
+    pub reason: String,
+    // Set when the text is stored as a file instead of in the database
+    pub file_path: Option<String>,
+    pub size: i64,
+    pub captured_at: DateTime<Utc>,
+}
+
+const SNAPSHOT_COLUMNS: &str = "id, parent_id, url, reason, file_path, size, captured_at";
+
+fn snapshot_from_row(row: &rusqlite::Row) -> Result<PlaylistSnapshot> {
+    Ok(PlaylistSnapshot {
+        id: row.get(0)?,
+        parent_id: row.get(1)?,
+        url: row.get(2)?,
+        reason: row.get(3)?,
+        file_path: row.get(4)?,
+        size: row.get(5)?,
+        captured_at: row.get(6)?,
+    })
+}
+
+// Key a connection for SQLCipher. `key` is a hex-encoded 256-bit raw key.
+fn apply_key(conn: &Connection, key: &str) -> Result<()> {
+    conn.execute_batch(&format!("PRAGMA key = \"x'{}'\";", key))
+}
+
+// A wrongly keyed (or plaintext-as-encrypted) database fails on first read
+fn is_readable(conn: &Connection) -> bool {
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0)).is_ok()
+}
+
+fn file_error(message: String) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CANTOPEN), Some(message))
+}
+
+// Rewrite the database file with a different key; None means plaintext
+fn rekey_file(path: &Path, from: Option<&str>, to: Option<&str>) -> Result<()> {
+    let temp = path.with_extension("db.rekey");
+    let _ = std::fs::remove_file(&temp);
+    {
+        let conn = Connection::open(path)?;
+        if let Some(key) = from {
+            apply_key(&conn, key)?;
+        }
+        let target_key = to.map(|key| format!("x'{}'", key)).unwrap_or_default();
+        conn.execute(
+            "ATTACH DATABASE ?1 AS target KEY ?2",
+            params![temp.to_string_lossy(), target_key],
+        )?;
+        conn.query_row("SELECT sqlcipher_export('target')", [], |_| Ok(()))?;
+        conn.execute_batch("DETACH DATABASE target;")?;
+    }
+    std::fs::rename(&temp, path)
+        .map_err(|e| file_error(format!("Failed to replace database file: {}", e)))
+}
+
+// A file produced by a job (download, generated playlist, report),
+// served to remote clients by job id
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Artifact {
+    pub id: i64,
+    pub job_id: String,
+    // "download", "manifest", "playlist", "report", "transcript"
+    pub kind: String,
+    pub path: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// A journaled download; `spec` is the journal::JobSpec it was started with
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JournalEntry {
+    pub job_id: String,
+    pub spec: serde_json::Value,
+    // "running", "completed", "failed", "cancelled" or "interrupted"
+    pub status: String,
+    pub error: Option<String>,
+    pub output_path: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    // Audio and subtitle renditions picked by language
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renditions: Option<serde_json::Value>,
+}
+
+const JOURNAL_COLUMNS: &str = "job_id, spec, status, error, output_path, created_at, updated_at, renditions";
+
+fn journal_from_row(row: &rusqlite::Row) -> Result<JournalEntry> {
+    let spec: String = row.get(1)?;
+    Ok(JournalEntry {
+        job_id: row.get(0)?,
+        spec: serde_json::from_str(&spec).unwrap_or(serde_json::Value::Null),
+        status: row.get(2)?,
+        error: row.get(3)?,
+        output_path: row.get(4)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+        renditions: row.get::<_, Option<String>>(7)?.and_then(|json| serde_json::from_str(&json).ok()),
+    })
+}
+
+// One progress sample of a download
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProgressSample {
+    pub bytes: i64,
+    // Average since the previous sample of the job
+    pub bytes_per_second: f64,
+    pub recorded_at: DateTime<Utc>,
+}
+
+// Transfer speeds seen from one CDN host
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HostSpeed {
+    pub host: String,
+    pub jobs: i64,
+    pub samples: i64,
+    pub average_bytes_per_second: f64,
+    pub peak_bytes_per_second: f64,
+}
+
+// Calls of one MCP tool, from the opt-in usage statistics
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolUsage {
+    pub tool: String,
+    pub calls: i64,
+    pub failures: i64,
+}
+
+// Downloads finished on one day (YYYY-MM-DD, UTC)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DailyDownloads {
+    pub day: String,
+    pub downloads: i64,
+    pub failures: i64,
+    pub bytes: i64,
+}
+
+// FFmpeg command line run for a job, program first
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JobCommand {
+    pub job_id: String,
+    pub command: Vec<String>,
+    // The command quoted for a POSIX shell
+    pub shell: String,
+    pub created_at: String,
+}
+
+// Perceptual hashes of frames sampled from a stream, for telling that two
+// URLs serve the same content
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StreamFingerprint {
+    pub url: String,
+    // Seconds of media the samples were spread over
+    pub duration: f64,
+    pub live: bool,
+    // 64-bit difference hash of each sampled frame as hex, in stream order;
+    // None for frames that could not be read or show nothing
+    pub hashes: Vec<Option<String>>,
+    pub fingerprinted_at: String,
+}
+
+// What a soft delete removed, kept in the trash until it is restored or its
+// retention period passes
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrashEntry {
+    pub id: i64,
+    // What was cleared: "cache" or "history"
+    pub kind: String,
+    // Rows (or history entries) it holds
+    pub items: usize,
+    pub deleted_at: String,
+}
+
+// What the downloader has learned about a host
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HostStats {
+    pub host: String,
+    pub requests: i64,
+    pub errors: i64,
+    pub bytes: i64,
+    // Time spent in requests, summed over parallel connections
+    pub fetch_seconds: f64,
+    // Current connection limit (fractional while it grows)
+    pub concurrency: f64,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
 impl Database {
     pub fn new(db_path: PathBuf) -> Result<Self> {
+        Self::open(db_path, None, None)
+    }
+    
+    // Open the database, encrypted with `key` when given. A file still in
+    // the form `previous_key` describes (None = plaintext) is converted first,
+    // so encryption can be switched on and off.
+    pub fn open(db_path: PathBuf, key: Option<&str>, previous_key: Option<&str>) -> Result<Self> {
         // Create directory if it doesn't exist
         if let Some(parent) = db_path.parent() {
-            std::fs::create_dir_all(parent).map_err(|e| {
-                rusqlite::Error::SqliteFailure(
-                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CANTOPEN),
-                    Some(format!("Failed to create database directory: {}", e)),
-                )
-            })?;
+            std::fs::create_dir_all(parent)
+                .map_err(|e| file_error(format!("Failed to create database directory: {}", e)))?;
+        }
+        
+        let mut conn = Connection::open(&db_path)?;
+        if let Some(key) = key {
+            apply_key(&conn, key)?;
+        }
+        if !is_readable(&conn) && key != previous_key {
+            drop(conn);
+            println!("Converting database {} to {}", db_path.display(), if key.is_some() { "encrypted" } else { "plaintext" });
+            rekey_file(&db_path, previous_key, key)?;
+            conn = Connection::open(&db_path)?;
+            if let Some(key) = key {
+                apply_key(&conn, key)?;
+            }
         }
         
-        let conn = Connection::open(db_path)?;
         let db = Database {
             conn: Mutex::new(conn),
         };
@@ -107,6 +424,25 @@ impl Database {
             [],
         )?;
         
+        // Projects group downloads under a name and default output directory
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS projects (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT UNIQUE NOT NULL,
+                output_dir TEXT,
+                options TEXT,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        
+        // Columns added after the initial schema
+        add_column_if_missing(&conn, "downloaded_streams", "title", "TEXT")?;
+        add_column_if_missing(&conn, "downloaded_streams", "project_id", "INTEGER REFERENCES projects(id)")?;
+        add_column_if_missing(&conn, "downloaded_streams", "elapsed_seconds", "REAL")?;
+        add_column_if_missing(&conn, "downloaded_streams", "verified", "INTEGER")?;
+        add_column_if_missing(&conn, "downloaded_streams", "content_hash", "TEXT")?;
+        
         // FFmpeg probe results cache
         conn.execute(
             "CREATE TABLE IF NOT EXISTS probe_cache (
@@ -125,6 +461,158 @@ impl Database {
             [],
         )?;
         
+        // Content fingerprints by canonical URL; `hashes` is a JSON array
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS stream_fingerprints (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url TEXT UNIQUE NOT NULL,
+                duration REAL NOT NULL,
+                live INTEGER NOT NULL,
+                hashes TEXT NOT NULL,
+                fingerprinted_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        
+        // Liveness of saved URLs, refreshed by the probe cache warm-up and
+        // the periodic link check
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS link_status (
+                url TEXT PRIMARY KEY,
+                alive INTEGER NOT NULL,
+                error TEXT,
+                checked_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        add_column_if_missing(&conn, "link_status", "http_status", "INTEGER")?;
+        add_column_if_missing(&conn, "link_status", "dead", "INTEGER NOT NULL DEFAULT 0")?;
+        add_column_if_missing(&conn, "link_status", "dead_since", "TEXT")?;
+        add_column_if_missing(&conn, "link_status", "last_playlist", "TEXT")?;
+        
+        // Playlist text archived at parse/download time
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS playlist_snapshots (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                parent_id INTEGER REFERENCES playlist_snapshots(id) ON DELETE CASCADE,
+                url TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                content TEXT,
+                file_path TEXT,
+                size INTEGER NOT NULL,
+                captured_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        
+        // Files produced by jobs, looked up by job id
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS artifacts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                job_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                path TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        
+        // Downloads with the options they were started with, so interrupted
+        // ones can be restarted after the app restarts
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS job_journal (
+                job_id TEXT PRIMARY KEY,
+                spec TEXT NOT NULL,
+                status TEXT NOT NULL,
+                error TEXT,
+                output_path TEXT,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        add_column_if_missing(&conn, "job_journal", "renditions", "TEXT")?;
+        
+        // Periodic progress of downloads, for transfer graphs
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS job_progress (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                job_id TEXT NOT NULL,
+                host TEXT NOT NULL,
+                bytes INTEGER NOT NULL,
+                bytes_per_second REAL NOT NULL,
+                recorded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        
+        // Learned per-host connection limits and counters
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS host_stats (
+                host TEXT PRIMARY KEY,
+                requests INTEGER NOT NULL DEFAULT 0,
+                errors INTEGER NOT NULL DEFAULT 0,
+                bytes INTEGER NOT NULL DEFAULT 0,
+                fetch_seconds REAL NOT NULL DEFAULT 0,
+                concurrency REAL NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        
+        // Opt-in usage statistics, counted per day; nothing else about a call
+        // or download is kept
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS usage_tools (
+                day TEXT NOT NULL,
+                tool TEXT NOT NULL,
+                calls INTEGER NOT NULL DEFAULT 0,
+                failures INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (day, tool)
+            )",
+            [],
+        )?;
+        
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS usage_downloads (
+                day TEXT PRIMARY KEY,
+                downloads INTEGER NOT NULL DEFAULT 0,
+                failures INTEGER NOT NULL DEFAULT 0,
+                bytes INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+        
+        // FFmpeg command lines of jobs, one row per FFmpeg run
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS job_commands (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                job_id TEXT NOT NULL,
+                command TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_job_commands_job_id 
+             ON job_commands(job_id)",
+            [],
+        )?;
+        
+        // Soft-deleted rows and history entries; `data` is JSON, with the
+        // rows of each cleared table under "tables"
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS trash (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                items INTEGER NOT NULL,
+                data TEXT NOT NULL,
+                deleted_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        
         // Create indexes for better query performance
         conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_playlists_url 
@@ -132,6 +620,18 @@ impl Database {
             [],
         )?;
         
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_artifacts_job_id 
+             ON artifacts(job_id)",
+            [],
+        )?;
+        
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_progress_job_id 
+             ON job_progress(job_id, recorded_at)",
+            [],
+        )?;
+        
         conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_playlists_cached_at 
              ON cached_playlists(cached_at)",
@@ -150,6 +650,12 @@ impl Database {
             [],
         )?;
         
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_snapshots_url 
+             ON playlist_snapshots(url, captured_at)",
+            [],
+        )?;
+        
         conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_probe_url 
              ON probe_cache(url)",
@@ -165,14 +671,30 @@ impl Database {
         Ok(())
     }
     
-    // Cache a parsed m3u8 playlist
+    // Cheap query used by readiness checks
+    pub fn ping(&self) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT 1", [], |_| Ok(()))
+    }
+    
+    // Problems PRAGMA integrity_check finds; empty when the database is sound
+    pub fn integrity_check(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let problems = rows.collect::<Result<Vec<_>>>()?;
+        Ok(problems.into_iter().filter(|problem| problem != "ok").collect())
+    }
+    
+    // Cache a parsed m3u8 playlist under the canonical form of its URL
     pub fn cache_playlist(&self, url: &str, playlist_type: &str, data: &str) -> Result<()> {
+        let key = crate::canonical_url::key(url);
         let conn = self.conn.lock().unwrap();
         
         conn.execute(
             "INSERT OR REPLACE INTO cached_playlists (url, playlist_type, data, cached_at) 
              VALUES (?1, ?2, ?3, datetime('now'))",
-            params![url, playlist_type, data],
+            params![key, playlist_type, data],
         )?;
         
         Ok(())
@@ -180,11 +702,12 @@ impl Database {
     
     // Get cached playlist
     pub fn get_cached_playlist(&self, url: &str) -> Result<Option<String>> {
+        let key = crate::canonical_url::key(url);
         let conn = self.conn.lock().unwrap();
         
         let result = conn.query_row(
             "SELECT data FROM cached_playlists WHERE url = ?1",
-            params![url],
+            params![key],
             |row| row.get(0),
         ).optional()?;
         
@@ -192,144 +715,871 @@ impl Database {
     }
     
     // Save download record
-    pub fn save_download(&self, url: &str, output_path: &str, file_size: Option<i64>) -> Result<()> {
+    pub fn save_download(&self, download: &NewDownload, file_size: Option<i64>) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         
         conn.execute(
-            "INSERT INTO downloaded_streams (url, output_path, file_size, downloaded_at) 
-             VALUES (?1, ?2, ?3, datetime('now'))",
-            params![url, output_path, file_size],
+            "INSERT INTO downloaded_streams 
+                (url, output_path, file_size, duration, title, project_id, elapsed_seconds, verified, content_hash, downloaded_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, datetime('now'))",
+            params![
+                download.url,
+                download.output_path.to_string_lossy(),
+                file_size,
+                download.duration,
+                download.title,
+                download.project_id,
+                download.elapsed_seconds,
+                download.verified,
+                download.content_hash
+            ],
         )?;
         
         Ok(())
     }
     
+    // Downloads of `url`, or of a playlist with the same fingerprint, newest first
+    pub fn find_downloads(&self, url: &str, content_hash: Option<&str>) -> Result<Vec<DownloadedStream>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM downloaded_streams 
+             WHERE url = ?1 OR (?2 IS NOT NULL AND content_hash = ?2)
+             ORDER BY downloaded_at DESC",
+            DOWNLOAD_COLUMNS
+        ))?;
+        
+        let downloads = stmt.query_map(params![url, content_hash], download_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(downloads)
+    }
+    
     // Get download history
     pub fn get_download_history(&self, limit: i32) -> Result<Vec<DownloadedStream>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, url, output_path, file_size, duration, format, resolution, bitrate, downloaded_at 
-             FROM downloaded_streams 
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM downloaded_streams 
              ORDER BY downloaded_at DESC 
-             LIMIT ?1"
-        )?;
+             LIMIT ?1",
+            DOWNLOAD_COLUMNS
+        ))?;
         
-        let downloads = stmt.query_map(params![limit], |row| {
-            Ok(DownloadedStream {
-                id: row.get(0)?,
-                url: row.get(1)?,
-                output_path: row.get(2)?,
-                file_size: row.get(3)?,
-                duration: row.get(4)?,
-                format: row.get(5)?,
-                resolution: row.get(6)?,
-                bitrate: row.get(7)?,
-                downloaded_at: row.get(8)?,
-            })
-        })?
-        .collect::<Result<Vec<_>>>()?;
+        let downloads = stmt.query_map(params![limit], download_from_row)?
+            .collect::<Result<Vec<_>>>()?;
         
         Ok(downloads)
     }
     
-    // Cache probe result
-    pub fn cache_probe_result(&self, url: &str, format_name: &str, streams_info: &str) -> Result<()> {
+    // Create a project and return its ID
+    pub fn create_project(&self, name: &str, output_dir: Option<&str>, options: Option<&str>) -> Result<i32> {
         let conn = self.conn.lock().unwrap();
         
         conn.execute(
-            "INSERT OR REPLACE INTO probe_cache (url, format_name, streams_info, probed_at) 
+            "INSERT INTO projects (name, output_dir, options, created_at) 
              VALUES (?1, ?2, ?3, datetime('now'))",
-            params![url, format_name, streams_info],
+            params![name, output_dir, options],
         )?;
         
-        Ok(())
+        Ok(conn.last_insert_rowid() as i32)
     }
     
-    // Get cached probe result
-    pub fn get_cached_probe(&self, url: &str) -> Result<Option<String>> {
+    pub fn list_projects(&self) -> Result<Vec<Project>> {
         let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("{} ORDER BY p.name", PROJECT_QUERY))?;
         
-        let result = conn.query_row(
-            "SELECT streams_info FROM probe_cache WHERE url = ?1",
-            params![url],
-            |row| row.get(0),
-        ).optional()?;
+        let projects = stmt.query_map([], project_from_row)?
+            .collect::<Result<Vec<_>>>()?;
         
-        Ok(result)
+        Ok(projects)
     }
     
-    // Search cached playlists
-    pub fn search_cached_playlists(&self, query: &str) -> Result<Vec<CachedPlaylist>> {
+    pub fn get_project(&self, id: i32) -> Result<Option<Project>> {
         let conn = self.conn.lock().unwrap();
-        let search_pattern = format!("%{}%", query);
         
-        let mut stmt = conn.prepare(
-            "SELECT id, url, playlist_type, version, target_duration, media_sequence, 
-                    segments_count, total_duration, data, cached_at 
-             FROM cached_playlists 
-             WHERE url LIKE ?1 OR data LIKE ?1 
-             ORDER BY cached_at DESC 
-             LIMIT 100"
+        conn.query_row(
+            &format!("{} WHERE p.id = ?1", PROJECT_QUERY),
+            params![id],
+            project_from_row,
+        ).optional()
+    }
+    
+    // Update a project; returns false if it does not exist
+    pub fn update_project(&self, id: i32, name: &str, output_dir: Option<&str>, options: Option<&str>) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        
+        let changed = conn.execute(
+            "UPDATE projects SET name = ?2, output_dir = ?3, options = ?4 WHERE id = ?1",
+            params![id, name, output_dir, options],
         )?;
         
-        let playlists = stmt.query_map(params![search_pattern], |row| {
-            Ok(CachedPlaylist {
-                id: row.get(0)?,
-                url: row.get(1)?,
-                playlist_type: row.get(2)?,
-                version: row.get(3)?,
-                target_duration: row.get(4)?,
-                media_sequence: row.get(5)?,
-                segments_count: row.get(6)?,
-                total_duration: row.get(7)?,
-                data: row.get(8)?,
-                cached_at: row.get(9)?,
-            })
-        })?
-        .collect::<Result<Vec<_>>>()?;
+        Ok(changed > 0)
+    }
+    
+    // Delete a project. Its downloads are kept and become ungrouped.
+    pub fn delete_project(&self, id: i32) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
         
-        Ok(playlists)
+        conn.execute(
+            "UPDATE downloaded_streams SET project_id = NULL WHERE project_id = ?1",
+            params![id],
+        )?;
+        let changed = conn.execute("DELETE FROM projects WHERE id = ?1", params![id])?;
+        
+        Ok(changed > 0)
     }
     
-    // Clear all cache
-    pub fn clear_all_cache(&self) -> Result<()> {
+    pub fn get_project_downloads(&self, project_id: i32) -> Result<Vec<DownloadedStream>> {
         let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM cached_playlists", [])?;
-        conn.execute("DELETE FROM downloaded_streams", [])?;
-        conn.execute("DELETE FROM probe_cache", [])?;
-        Ok(())
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM downloaded_streams 
+             WHERE project_id = ?1 
+             ORDER BY downloaded_at DESC",
+            DOWNLOAD_COLUMNS
+        ))?;
+        
+        let downloads = stmt.query_map(params![project_id], download_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(downloads)
     }
     
-    // Clear old cache entries
-    pub fn clear_old_cache(&self, days: i32) -> Result<()> {
+    pub fn get_download(&self, id: i32) -> Result<Option<DownloadedStream>> {
         let conn = self.conn.lock().unwrap();
-        let query = format!("DELETE FROM cached_playlists WHERE cached_at < datetime('now', '-{} days')", days);
-        conn.execute(&query, [])?;
         
-        let query = format!("DELETE FROM probe_cache WHERE probed_at < datetime('now', '-{} days')", days);
-        conn.execute(&query, [])?;
-        Ok(())
+        conn.query_row(
+            &format!("SELECT {} FROM downloaded_streams WHERE id = ?1", DOWNLOAD_COLUMNS),
+            params![id],
+            download_from_row,
+        ).optional()
     }
     
-    // Get cache statistics
-    pub fn get_cache_stats(&self) -> Result<serde_json::Value> {
+    // Download records in a date range ("YYYY-MM-DD HH:MM:SS", UTC), oldest first
+    pub fn list_downloads_between(&self, from: Option<&str>, to: Option<&str>) -> Result<Vec<DownloadedStream>> {
         let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM downloaded_streams 
+             WHERE (?1 IS NULL OR datetime(downloaded_at) >= datetime(?1)) 
+               AND (?2 IS NULL OR datetime(downloaded_at) <= datetime(?2)) 
+             ORDER BY downloaded_at ASC",
+            DOWNLOAD_COLUMNS
+        ))?;
         
-        let playlist_count: i32 = conn.query_row(
-            "SELECT COUNT(*) FROM cached_playlists",
-            [],
-            |row| row.get(0),
-        )?;
+        let downloads = stmt.query_map(params![from, to], download_from_row)?
+            .collect::<Result<Vec<_>>>()?;
         
-        let download_count: i32 = conn.query_row(
-            "SELECT COUNT(*) FROM downloaded_streams",
-            [],
-            |row| row.get(0),
-        )?;
+        Ok(downloads)
+    }
+    
+    // All download records, oldest first
+    pub fn list_all_downloads(&self) -> Result<Vec<DownloadedStream>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM downloaded_streams ORDER BY downloaded_at ASC",
+            DOWNLOAD_COLUMNS
+        ))?;
         
-        let probe_count: i32 = conn.query_row(
-            "SELECT COUNT(*) FROM probe_cache",
-            [],
+        let downloads = stmt.query_map([], download_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(downloads)
+    }
+    
+    pub fn delete_download_record(&self, id: i32) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let changed = conn.execute("DELETE FROM downloaded_streams WHERE id = ?1", params![id])?;
+        Ok(changed > 0)
+    }
+    
+    // Cache probe result under the canonical form of the URL
+    pub fn cache_probe_result(&self, url: &str, format_name: &str, streams_info: &str) -> Result<()> {
+        let key = crate::canonical_url::key(url);
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "INSERT OR REPLACE INTO probe_cache (url, format_name, streams_info, probed_at) 
+             VALUES (?1, ?2, ?3, datetime('now'))",
+            params![key, format_name, streams_info],
+        )?;
+        
+        Ok(())
+    }
+    
+    // Get cached probe result
+    pub fn get_cached_probe(&self, url: &str) -> Result<Option<String>> {
+        let key = crate::canonical_url::key(url);
+        let conn = self.conn.lock().unwrap();
+        
+        let result = conn.query_row(
+            "SELECT streams_info FROM probe_cache WHERE url = ?1",
+            params![key],
+            |row| row.get(0),
+        ).optional()?;
+        
+        Ok(result)
+    }
+    
+    // Store the fingerprint of a stream under the canonical form of its URL,
+    // replacing an earlier one
+    pub fn save_fingerprint(&self, url: &str, duration: f64, live: bool, hashes: &[Option<String>]) -> Result<()> {
+        let key = crate::canonical_url::key(url);
+        let conn = self.conn.lock().unwrap();
+        let hashes = serde_json::to_string(hashes)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        conn.execute(
+            "INSERT OR REPLACE INTO stream_fingerprints (url, duration, live, hashes, fingerprinted_at) 
+             VALUES (?1, ?2, ?3, ?4, datetime('now'))",
+            params![key, duration, live, hashes],
+        )?;
+        Ok(())
+    }
+    
+    // Every stored fingerprint, newest first
+    pub fn list_fingerprints(&self) -> Result<Vec<StreamFingerprint>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT url, duration, live, hashes, fingerprinted_at FROM stream_fingerprints 
+             ORDER BY fingerprinted_at DESC, id DESC",
+        )?;
+        let fingerprints = stmt.query_map([], |row| {
+            let hashes: String = row.get(3)?;
+            Ok(StreamFingerprint {
+                url: row.get(0)?,
+                duration: row.get(1)?,
+                live: row.get(2)?,
+                hashes: serde_json::from_str(&hashes).unwrap_or_default(),
+                fingerprinted_at: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+        Ok(fingerprints)
+    }
+    
+    // Record whether a saved URL could be probed
+    pub fn set_link_status(&self, url: &str, alive: bool, error: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "INSERT INTO link_status (url, alive, error, checked_at) 
+             VALUES (?1, ?2, ?3, datetime('now')) 
+             ON CONFLICT(url) DO UPDATE SET 
+                alive = excluded.alive, error = excluded.error, checked_at = excluded.checked_at",
+            params![url, alive, error],
+        )?;
+        
+        Ok(())
+    }
+    
+    // Record a playlist request for a saved URL. `dead_since` is kept while
+    // the link stays dead, and an existing snapshot is kept when `playlist`
+    // is None.
+    pub fn record_link_check(
+        &self,
+        url: &str,
+        http_status: Option<u16>,
+        error: Option<&str>,
+        dead: bool,
+        playlist: Option<&str>,
+    ) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "INSERT INTO link_status (url, alive, error, http_status, dead, dead_since, last_playlist, checked_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, CASE WHEN ?5 THEN datetime('now') END, ?6, datetime('now')) 
+             ON CONFLICT(url) DO UPDATE SET 
+                alive = excluded.alive, 
+                error = excluded.error, 
+                http_status = excluded.http_status, 
+                dead = excluded.dead, 
+                dead_since = CASE WHEN excluded.dead THEN COALESCE(link_status.dead_since, excluded.dead_since) END, 
+                last_playlist = COALESCE(excluded.last_playlist, link_status.last_playlist), 
+                checked_at = excluded.checked_at",
+            params![url, error.is_none(), error, http_status, dead, playlist],
+        )?;
+        
+        Ok(())
+    }
+    
+    pub fn get_link_statuses(&self) -> Result<Vec<LinkStatus>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM link_status ORDER BY checked_at DESC",
+            LINK_STATUS_COLUMNS
+        ))?;
+        
+        let statuses = stmt.query_map([], link_status_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(statuses)
+    }
+    
+    // Playlist text saved at the last successful check of a URL
+    pub fn get_link_snapshot(&self, url: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        
+        let snapshot = conn.query_row(
+            "SELECT last_playlist FROM link_status WHERE url = ?1",
+            params![url],
+            |row| row.get(0),
+        ).optional()?;
+        
+        Ok(snapshot.flatten())
+    }
+    
+    // Store a playlist snapshot; `content` is None when it was written to `file_path`
+    pub fn save_snapshot(
+        &self,
+        parent_id: Option<i64>,
+        url: &str,
+        reason: &str,
+        content: Option<&str>,
+        file_path: Option<&str>,
+        size: usize,
+    ) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "INSERT INTO playlist_snapshots (parent_id, url, reason, content, file_path, size, captured_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now'))",
+            params![parent_id, url, reason, content, file_path, size as i64],
+        )?;
+        
+        Ok(conn.last_insert_rowid())
+    }
+    
+    // Top-level snapshots, newest first, optionally for one URL
+    pub fn list_snapshots(&self, url: Option<&str>, limit: i32) -> Result<Vec<PlaylistSnapshot>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM playlist_snapshots 
+             WHERE parent_id IS NULL AND (?1 IS NULL OR url = ?1) 
+             ORDER BY captured_at DESC, id DESC LIMIT ?2",
+            SNAPSHOT_COLUMNS
+        ))?;
+        
+        let snapshots = stmt.query_map(params![url, limit], snapshot_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(snapshots)
+    }
+    
+    // A snapshot with its stored text (None when kept as a file)
+    pub fn get_snapshot(&self, id: i64) -> Result<Option<(PlaylistSnapshot, Option<String>)>> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.query_row(
+            &format!("SELECT {}, content FROM playlist_snapshots WHERE id = ?1", SNAPSHOT_COLUMNS),
+            params![id],
+            |row| Ok((snapshot_from_row(row)?, row.get(7)?)),
+        ).optional()
+    }
+    
+    pub fn get_snapshot_children(&self, parent_id: i64) -> Result<Vec<PlaylistSnapshot>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM playlist_snapshots WHERE parent_id = ?1 ORDER BY id",
+            SNAPSHOT_COLUMNS
+        ))?;
+        
+        let snapshots = stmt.query_map(params![parent_id], snapshot_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(snapshots)
+    }
+    
+    pub fn save_artifact(&self, job_id: &str, kind: &str, path: &str) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "INSERT INTO artifacts (job_id, kind, path, created_at) VALUES (?1, ?2, ?3, datetime('now'))",
+            params![job_id, kind, path],
+        )?;
+        
+        Ok(conn.last_insert_rowid())
+    }
+    
+    // Artifacts of a job in the order they were produced
+    pub fn get_artifacts(&self, job_id: &str) -> Result<Vec<Artifact>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, job_id, kind, path, created_at FROM artifacts WHERE job_id = ?1 ORDER BY id",
+        )?;
+        
+        let artifacts = stmt.query_map(params![job_id], |row| {
+            Ok(Artifact {
+                id: row.get(0)?,
+                job_id: row.get(1)?,
+                kind: row.get(2)?,
+                path: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+        
+        Ok(artifacts)
+    }
+    
+    // Journal a job as running; a resumed job keeps its id and creation time
+    pub fn journal_job(&self, job_id: &str, spec: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "INSERT INTO job_journal (job_id, spec, status, created_at, updated_at) 
+             VALUES (?1, ?2, 'running', datetime('now'), datetime('now')) 
+             ON CONFLICT(job_id) DO UPDATE SET spec = ?2, status = 'running', error = NULL, updated_at = datetime('now')",
+            params![job_id, spec],
+        )?;
+        
+        Ok(())
+    }
+    
+    pub fn set_job_status(&self, job_id: &str, status: &str, error: Option<&str>, output_path: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "UPDATE job_journal SET status = ?2, error = ?3, output_path = COALESCE(?4, output_path), updated_at = datetime('now') 
+             WHERE job_id = ?1",
+            params![job_id, status, error, output_path],
+        )?;
+        
+        Ok(())
+    }
+    
+    pub fn set_job_renditions(&self, job_id: &str, renditions: &serde_json::Value) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "UPDATE job_journal SET renditions = ?2 WHERE job_id = ?1",
+            params![job_id, renditions.to_string()],
+        )?;
+        
+        Ok(())
+    }
+    
+    // Jobs still marked running belong to a previous run of the app
+    pub fn mark_interrupted_jobs(&self) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE job_journal SET status = 'interrupted', updated_at = datetime('now') WHERE status = 'running'",
+            [],
+        )
+    }
+    
+    // Interrupted and failed jobs, oldest first
+    pub fn get_resumable_jobs(&self) -> Result<Vec<JournalEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM job_journal WHERE status IN ('interrupted', 'failed') ORDER BY created_at, job_id",
+            JOURNAL_COLUMNS
+        ))?;
+        
+        let entries = stmt.query_map([], journal_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(entries)
+    }
+    
+    pub fn save_progress_sample(&self, job_id: &str, host: &str, bytes: u64, bytes_per_second: f64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "INSERT INTO job_progress (job_id, host, bytes, bytes_per_second, recorded_at) 
+             VALUES (?1, ?2, ?3, ?4, datetime('now'))",
+            params![job_id, host, bytes as i64, bytes_per_second],
+        )?;
+        
+        Ok(())
+    }
+    
+    // Samples of a job in the order they were taken
+    pub fn get_progress_samples(&self, job_id: &str) -> Result<Vec<ProgressSample>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT bytes, bytes_per_second, recorded_at FROM job_progress WHERE job_id = ?1 ORDER BY recorded_at, id",
+        )?;
+        
+        let samples = stmt.query_map(params![job_id], |row| {
+            Ok(ProgressSample {
+                bytes: row.get(0)?,
+                bytes_per_second: row.get(1)?,
+                recorded_at: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+        
+        Ok(samples)
+    }
+    
+    // Average and peak speed per host, fastest first
+    pub fn get_host_speeds(&self) -> Result<Vec<HostSpeed>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT host, COUNT(DISTINCT job_id), COUNT(*), AVG(bytes_per_second), MAX(bytes_per_second) 
+             FROM job_progress GROUP BY host ORDER BY AVG(bytes_per_second) DESC",
+        )?;
+        
+        let speeds = stmt.query_map([], |row| {
+            Ok(HostSpeed {
+                host: row.get(0)?,
+                jobs: row.get(1)?,
+                samples: row.get(2)?,
+                average_bytes_per_second: row.get(3)?,
+                peak_bytes_per_second: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+        
+        Ok(speeds)
+    }
+    
+    pub fn save_job_command(&self, job_id: &str, command: &[String]) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let command = serde_json::to_string(command).unwrap_or_default();
+        
+        conn.execute(
+            "INSERT INTO job_commands (job_id, command, created_at) VALUES (?1, ?2, datetime('now'))",
+            params![job_id, command],
+        )?;
+        
+        Ok(())
+    }
+    
+    // Commands of a job in the order they ran
+    pub fn get_job_commands(&self, job_id: &str) -> Result<Vec<JobCommand>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT job_id, command, created_at FROM job_commands WHERE job_id = ?1 ORDER BY id",
+        )?;
+        
+        let commands = stmt.query_map(params![job_id], |row| {
+            let command: Vec<String> = serde_json::from_str(&row.get::<_, String>(1)?).unwrap_or_default();
+            Ok(JobCommand {
+                job_id: row.get(0)?,
+                shell: crate::ffmpeg_wrapper::shell_quote(&command),
+                command,
+                created_at: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+        
+        Ok(commands)
+    }
+    
+    pub fn save_host_stats(&self, stats: &HostStats) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "INSERT OR REPLACE INTO host_stats (host, requests, errors, bytes, fetch_seconds, concurrency, updated_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now'))",
+            params![stats.host, stats.requests, stats.errors, stats.bytes, stats.fetch_seconds, stats.concurrency],
+        )?;
+        
+        Ok(())
+    }
+    
+    pub fn get_host_stats(&self, host: Option<&str>) -> Result<Vec<HostStats>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT host, requests, errors, bytes, fetch_seconds, concurrency, updated_at FROM host_stats 
+             WHERE ?1 IS NULL OR host = ?1 ORDER BY host",
+        )?;
+        
+        let stats = stmt.query_map(params![host], |row| {
+            Ok(HostStats {
+                host: row.get(0)?,
+                requests: row.get(1)?,
+                errors: row.get(2)?,
+                bytes: row.get(3)?,
+                fetch_seconds: row.get(4)?,
+                concurrency: row.get(5)?,
+                updated_at: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+        
+        Ok(stats)
+    }
+    
+    // Count a tool call on today's date
+    pub fn record_tool_usage(&self, tool: &str, ok: bool) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "INSERT INTO usage_tools (day, tool, calls, failures) VALUES (date('now'), ?1, 1, ?2)
+             ON CONFLICT (day, tool) DO UPDATE SET calls = calls + 1, failures = failures + excluded.failures",
+            params![tool, !ok as i64],
+        )?;
+        
+        Ok(())
+    }
+    
+    // Count a finished download, and the bytes it wrote, on today's date
+    pub fn record_download_usage(&self, ok: bool, bytes: u64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        
+        conn.execute(
+            "INSERT INTO usage_downloads (day, downloads, failures, bytes) VALUES (date('now'), 1, ?1, ?2)
+             ON CONFLICT (day) DO UPDATE SET downloads = downloads + 1, failures = failures + excluded.failures,
+             bytes = bytes + excluded.bytes",
+            params![!ok as i64, bytes as i64],
+        )?;
+        
+        Ok(())
+    }
+    
+    // Tool calls per tool since `since` (a YYYY-MM-DD day, or all time),
+    // most used first
+    pub fn get_tool_usage(&self, since: Option<&str>) -> Result<Vec<ToolUsage>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT tool, SUM(calls), SUM(failures) FROM usage_tools 
+             WHERE ?1 IS NULL OR day >= ?1 GROUP BY tool ORDER BY SUM(calls) DESC, tool",
+        )?;
+        
+        let usage = stmt.query_map(params![since], |row| {
+            Ok(ToolUsage {
+                tool: row.get(0)?,
+                calls: row.get(1)?,
+                failures: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+        
+        Ok(usage)
+    }
+    
+    // Downloads per day since `since`, oldest first
+    pub fn get_download_usage(&self, since: Option<&str>) -> Result<Vec<DailyDownloads>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT day, downloads, failures, bytes FROM usage_downloads 
+             WHERE ?1 IS NULL OR day >= ?1 ORDER BY day",
+        )?;
+        
+        let days = stmt.query_map(params![since], |row| {
+            Ok(DailyDownloads {
+                day: row.get(0)?,
+                downloads: row.get(1)?,
+                failures: row.get(2)?,
+                bytes: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+        
+        Ok(days)
+    }
+    
+    pub fn clear_usage(&self) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.execute("DELETE FROM usage_tools", [])? + conn.execute("DELETE FROM usage_downloads", [])?)
+    }
+    
+    // Search cached playlists
+    pub fn search_cached_playlists(&self, query: &str) -> Result<Vec<CachedPlaylist>> {
+        let conn = self.conn.lock().unwrap();
+        let search_pattern = format!("%{}%", query);
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, url, playlist_type, version, target_duration, media_sequence, 
+                    segments_count, total_duration, data, cached_at 
+             FROM cached_playlists 
+             WHERE url LIKE ?1 OR data LIKE ?1 
+             ORDER BY cached_at DESC 
+             LIMIT 100"
+        )?;
+        
+        let playlists = stmt.query_map(params![search_pattern], |row| {
+            Ok(CachedPlaylist {
+                id: row.get(0)?,
+                url: row.get(1)?,
+                playlist_type: row.get(2)?,
+                version: row.get(3)?,
+                target_duration: row.get(4)?,
+                media_sequence: row.get(5)?,
+                segments_count: row.get(6)?,
+                total_duration: row.get(7)?,
+                data: row.get(8)?,
+                cached_at: row.get(9)?,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+        
+        Ok(playlists)
+    }
+    
+    // Clear all cache, download records included, into the trash
+    pub fn clear_all_cache(&self) -> Result<TrashEntry> {
+        self.trash_tables("cache", &["cached_playlists", "downloaded_streams", "probe_cache", "link_status", "stream_fingerprints"])
+    }
+    
+    // Clear cached playlists, probe results, link checks and fingerprints
+    // into the trash but keep the download records
+    pub fn clear_playlist_cache(&self) -> Result<TrashEntry> {
+        self.trash_tables("cache", &["cached_playlists", "probe_cache", "link_status", "stream_fingerprints"])
+    }
+    
+    // Move every row of `tables` into one trash entry of `kind`
+    pub fn trash_tables(&self, kind: &str, tables: &[&str]) -> Result<TrashEntry> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut dump = serde_json::Map::new();
+        let mut items = 0;
+        for table in tables {
+            let rows = table_rows(&tx, table)?;
+            items += rows.len();
+            dump.insert(table.to_string(), serde_json::Value::Array(rows));
+            tx.execute(&format!("DELETE FROM {}", table), [])?;
+        }
+        let entry = insert_trash(&tx, kind, &serde_json::json!({ "tables": dump }), items)?;
+        tx.commit()?;
+        Ok(entry)
+    }
+    
+    // Put data kept outside the database, such as the URL history, in the trash
+    pub fn trash_data(&self, kind: &str, data: &serde_json::Value, items: usize) -> Result<TrashEntry> {
+        let conn = self.conn.lock().unwrap();
+        insert_trash(&conn, kind, data, items)
+    }
+    
+    // Trash entries, newest first
+    pub fn list_trash(&self) -> Result<Vec<TrashEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, kind, items, deleted_at FROM trash ORDER BY id DESC")?;
+        let entries = stmt.query_map([], trash_entry)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+    
+    // Take an entry out of the trash, putting the rows of its tables back.
+    // Rows whose key is in use again keep the newer row. Returns the entry
+    // and its data, for the caller to restore what was kept elsewhere; None
+    // when there is no such entry.
+    pub fn restore_trash(&self, id: i64) -> Result<Option<(TrashEntry, serde_json::Value)>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let found = tx.query_row(
+            "SELECT id, kind, items, deleted_at, data FROM trash WHERE id = ?1",
+            params![id],
+            |row| Ok((trash_entry(row)?, row.get::<_, String>(4)?)),
+        ).optional()?;
+        let Some((entry, data)) = found else {
+            return Ok(None);
+        };
+        let data: serde_json::Value = serde_json::from_str(&data)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        if let Some(tables) = data.get("tables").and_then(|v| v.as_object()) {
+            for (table, rows) in tables {
+                for row in rows.as_array().into_iter().flatten().filter_map(|row| row.as_object()) {
+                    insert_row(&tx, table, row)?;
+                }
+            }
+        }
+        tx.execute("DELETE FROM trash WHERE id = ?1", params![id])?;
+        tx.commit()?;
+        Ok(Some((entry, data)))
+    }
+    
+    // Delete trash entries older than `days`, or all of them; returns the
+    // number removed
+    pub fn purge_trash(&self, days: Option<u32>) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        match days {
+            Some(days) => conn.execute(
+                "DELETE FROM trash WHERE deleted_at < datetime('now', ?1)",
+                params![format!("-{} days", days)],
+            ),
+            None => conn.execute("DELETE FROM trash", []),
+        }
+    }
+    
+    // Clear cache entries older than `days`; returns the number removed
+    pub fn clear_old_cache(&self, days: u32) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let modifier = format!("-{} days", days);
+        let playlists = conn.execute(
+            "DELETE FROM cached_playlists WHERE cached_at < datetime('now', ?1)",
+            params![modifier],
+        )?;
+        let probes = conn.execute(
+            "DELETE FROM probe_cache WHERE probed_at < datetime('now', ?1)",
+            params![modifier],
+        )?;
+        let fingerprints = conn.execute(
+            "DELETE FROM stream_fingerprints WHERE fingerprinted_at < datetime('now', ?1)",
+            params![modifier],
+        )?;
+        Ok(playlists + probes + fingerprints)
+    }
+    
+    // Remove playlist snapshots and link check records older than `days`.
+    // Returns the rows removed and the snapshot files the caller should delete.
+    pub fn clear_old_audit(&self, days: u32) -> Result<(usize, Vec<String>)> {
+        let conn = self.conn.lock().unwrap();
+        let modifier = format!("-{} days", days);
+        
+        let mut stmt = conn.prepare(
+            "SELECT file_path FROM playlist_snapshots 
+             WHERE file_path IS NOT NULL AND captured_at < datetime('now', ?1)",
+        )?;
+        let files = stmt.query_map(params![modifier], |row| row.get(0))?
+            .collect::<Result<Vec<String>>>()?;
+        
+        let snapshots = conn.execute(
+            "DELETE FROM playlist_snapshots WHERE captured_at < datetime('now', ?1)",
+            params![modifier],
+        )?;
+        let statuses = conn.execute(
+            "DELETE FROM link_status WHERE checked_at < datetime('now', ?1)",
+            params![modifier],
+        )?;
+        let progress = conn.execute(
+            "DELETE FROM job_progress WHERE recorded_at < datetime('now', ?1)",
+            params![modifier],
+        )?;
+        Ok((snapshots + statuses + progress, files))
+    }
+    
+    // Delete everything that records what the user looked at: cached
+    // playlists, probe results, link checks and snapshots. Download records
+    // are kept. Returns the rows removed and the snapshot files to delete.
+    pub fn clear_personal_data(&self) -> Result<(usize, Vec<String>)> {
+        let conn = self.conn.lock().unwrap();
+        
+        let mut stmt = conn.prepare("SELECT file_path FROM playlist_snapshots WHERE file_path IS NOT NULL")?;
+        let files = stmt.query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>>>()?;
+        
+        let mut removed = 0;
+        for table in ["cached_playlists", "probe_cache", "stream_fingerprints", "link_status", "playlist_snapshots", "job_journal", "job_progress", "host_stats", "usage_tools", "usage_downloads"] {
+            removed += conn.execute(&format!("DELETE FROM {}", table), [])?;
+        }
+        Ok((removed, files))
+    }
+    
+    // Get cache statistics
+    pub fn get_cache_stats(&self) -> Result<serde_json::Value> {
+        let conn = self.conn.lock().unwrap();
+        
+        let playlist_count: i32 = conn.query_row(
+            "SELECT COUNT(*) FROM cached_playlists",
+            [],
+            |row| row.get(0),
+        )?;
+        
+        let download_count: i32 = conn.query_row(
+            "SELECT COUNT(*) FROM downloaded_streams",
+            [],
+            |row| row.get(0),
+        )?;
+        
+        let probe_count: i32 = conn.query_row(
+            "SELECT COUNT(*) FROM probe_cache",
+            [],
+            |row| row.get(0),
+        )?;
+        
+        let fingerprint_count: i32 = conn.query_row(
+            "SELECT COUNT(*) FROM stream_fingerprints",
+            [],
             |row| row.get(0),
         )?;
         
@@ -355,6 +1605,7 @@ impl Database {
             "cached_playlists": playlist_count,
             "downloaded_streams": download_count,
             "probe_results": probe_count,
+            "fingerprints": fingerprint_count,
             "total_download_size": total_size.unwrap_or(0),
             "latest_download": latest_download,
             "latest_cache": latest_cache,
@@ -362,6 +1613,89 @@ impl Database {
     }
 }
 
+fn trash_entry(row: &rusqlite::Row) -> Result<TrashEntry> {
+    Ok(TrashEntry {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        items: row.get::<_, i64>(2)? as usize,
+        deleted_at: row.get(3)?,
+    })
+}
+
+fn insert_trash(conn: &Connection, kind: &str, data: &serde_json::Value, items: usize) -> Result<TrashEntry> {
+    conn.execute(
+        "INSERT INTO trash (kind, items, data) VALUES (?1, ?2, ?3)",
+        params![kind, items as i64, data.to_string()],
+    )?;
+    conn.query_row(
+        "SELECT id, kind, items, deleted_at FROM trash WHERE id = ?1",
+        params![conn.last_insert_rowid()],
+        trash_entry,
+    )
+}
+
+// Every row of `table` as a JSON object by column name; blobs become arrays
+// of bytes
+fn table_rows(conn: &Connection, table: &str) -> Result<Vec<serde_json::Value>> {
+    use rusqlite::types::ValueRef;
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {}", table))?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let rows = stmt.query_map([], |row| {
+        let mut object = serde_json::Map::new();
+        for (index, column) in columns.iter().enumerate() {
+            let value = match row.get_ref(index)? {
+                ValueRef::Null => serde_json::Value::Null,
+                ValueRef::Integer(v) => serde_json::json!(v),
+                ValueRef::Real(v) => serde_json::json!(v),
+                ValueRef::Text(v) => serde_json::json!(String::from_utf8_lossy(v)),
+                ValueRef::Blob(v) => serde_json::json!(v),
+            };
+            object.insert(column.clone(), value);
+        }
+        Ok(serde_json::Value::Object(object))
+    })?;
+    rows.collect()
+}
+
+// Insert a row dumped by `table_rows`, unless its key is taken
+fn insert_row(conn: &Connection, table: &str, row: &serde_json::Map<String, serde_json::Value>) -> Result<()> {
+    use rusqlite::types::Value;
+    let columns: Vec<&str> = row.keys().map(String::as_str).collect();
+    let values: Vec<Value> = row.values().map(|value| match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(v) => Value::Integer(*v as i64),
+        serde_json::Value::Number(v) => match v.as_i64() {
+            Some(v) => Value::Integer(v),
+            None => Value::Real(v.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(v) => Value::Text(v.clone()),
+        serde_json::Value::Array(bytes) => Value::Blob(bytes.iter().filter_map(|b| b.as_u64()).map(|b| b as u8).collect()),
+        serde_json::Value::Object(_) => Value::Text(value.to_string()),
+    }).collect();
+    let placeholders = vec!["?"; columns.len()].join(", ");
+    conn.execute(
+        &format!("INSERT OR IGNORE INTO {} ({}) VALUES ({})", table, columns.join(", "), placeholders),
+        rusqlite::params_from_iter(values),
+    )?;
+    Ok(())
+}
+
+// Add a column to an existing table; CREATE TABLE IF NOT EXISTS leaves
+// databases created by older versions without it
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>>>()?
+        .iter()
+        .any(|name| name == column);
+    
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(())
+}
+
 // Global database instance for use in async contexts
 lazy_static::lazy_static! {
     pub static ref GLOBAL_DB: tokio::sync::RwLock<Option<std::sync::Arc<Database>>> = 
@@ -374,4 +1708,63 @@ pub async fn init_global_db(db_path: PathBuf) -> Result<()> {
     let mut global = GLOBAL_DB.write().await;
     *global = Some(std::sync::Arc::new(db));
     Ok(())
-}
\ No newline at end of file
+}
+
+// Record a finished download in the global database, if it is initialized
+pub async fn record_download(download: NewDownload<'_>) {
+    let db = GLOBAL_DB.read().await.clone();
+    if let Some(db) = db {
+        let file_size = std::fs::metadata(download.output_path).ok().map(|m| m.len() as i64);
+        if let Err(e) = db.save_download(&download, file_size) {
+            eprintln!("Failed to record download: {}", e);
+        }
+    }
+}
+
+// Record the command line of a job's FFmpeg run, if the database is initialized
+pub async fn record_job_command(job_id: &str, command: &[String]) {
+    let db = GLOBAL_DB.read().await.clone();
+    if let Some(db) = db {
+        if let Err(e) = db.save_job_command(job_id, command) {
+            eprintln!("Failed to record the command of job {}: {}", job_id, e);
+        }
+    }
+}
+
+// Point a running download's journal entry at the .part file it writes, so
+// an interrupted job shows where its partial output is
+pub async fn record_partial_output(job_id: &str, part: &Path) {
+    let db = GLOBAL_DB.read().await.clone();
+    if let Some(db) = db {
+        if let Err(e) = db.set_job_status(job_id, "running", None, Some(&part.to_string_lossy())) {
+            eprintln!("Failed to journal the output of job {}: {}", job_id, e);
+        }
+    }
+}
+
+// The global database, or an error if it has not been initialized yet
+pub async fn global_db() -> std::result::Result<std::sync::Arc<Database>, AppError> {
+    GLOBAL_DB.read().await.clone()
+        .ok_or_else(|| AppError::not_initialized(crate::i18n::t(crate::i18n::Msg::DatabaseNotInitialized)))
+}
+
+// Look up a project in the global database
+pub async fn find_project(id: i32) -> std::result::Result<Project, AppError> {
+    let db = global_db().await?;
+    db.get_project(id)?
+        .ok_or_else(|| AppError::not_found(format!("Project {} not found", id)))
+}
+
+// Output directory of a project, created if missing
+pub async fn project_output_dir(id: i32) -> std::result::Result<Option<PathBuf>, AppError> {
+    let project = find_project(id).await?;
+    match project.output_dir {
+        Some(dir) => {
+            let dir = crate::paths::expand_path(&dir);
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| AppError::io(format!("Failed to create project directory: {}", e)))?;
+            Ok(Some(dir))
+        }
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,175 @@
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::m3u8_parser::{self, M3u8Error, M3u8Parser, ParsedPlaylist, Rendition, Variant};
+
+// Media playlists fetched at once
+const CONCURRENCY: usize = 8;
+
+// What a media playlist holds, without its segment list
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaOverview {
+    pub url: String,
+    pub target_duration: Option<u64>,
+    pub media_sequence: u64,
+    pub segments: usize,
+    // Seconds, the sum of the EXTINF durations
+    pub duration: f64,
+    pub discontinuities: usize,
+    pub encrypted: bool,
+    // No EXT-X-ENDLIST yet
+    pub live: bool,
+}
+
+// A variant or rendition with its media playlist, or why that could not be read
+#[derive(Debug, Clone, Serialize)]
+pub struct DeepEntry<T> {
+    #[serde(flatten)]
+    pub entry: T,
+    pub playlist: Option<MediaOverview>,
+    pub error: Option<AppError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeepMaster {
+    pub url: String,
+    pub version: Option<u8>,
+    pub variants: Vec<DeepEntry<Variant>>,
+    // Renditions with a playlist of their own; closed captions and audio
+    // muxed into the variants have none and are left out
+    pub renditions: Vec<DeepEntry<Rendition>>,
+    // Media playlists that could not be fetched or parsed
+    pub failed: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeepPlaylist {
+    Master(DeepMaster),
+    Media(MediaOverview),
+}
+
+pub fn overview(content: &str, url: &str) -> Result<MediaOverview, M3u8Error> {
+    let ParsedPlaylist::Media { target_duration, segments, .. } = m3u8_parser::parse_playlist(content, url)? else {
+        return Err(M3u8Error::ParseError("Expected a media playlist, found a master playlist".to_string()));
+    };
+    Ok(MediaOverview {
+        url: url.to_string(),
+        target_duration,
+        media_sequence: m3u8_parser::media_sequence(content),
+        segments: segments.len(),
+        duration: segments.iter().map(|segment| segment.duration as f64).sum(),
+        discontinuities: segments.iter().filter(|segment| segment.discontinuity).count(),
+        encrypted: m3u8_parser::segment_keys(content, url).iter().any(Option::is_some),
+        live: !content.lines().any(|line| line.trim() == "#EXT-X-ENDLIST"),
+    })
+}
+
+async fn fetch_overview(parser: &M3u8Parser, url: &str) -> Result<MediaOverview, AppError> {
+    let content = parser.fetch_playlist(url).await?;
+    Ok(overview(&content, url)?)
+}
+
+fn entry<T>(entry: T, result: Result<MediaOverview, AppError>) -> DeepEntry<T> {
+    match result {
+        Ok(playlist) => DeepEntry { entry, playlist: Some(playlist), error: None },
+        Err(error) => DeepEntry { entry, playlist: None, error: Some(error) },
+    }
+}
+
+// Parse `url` and, for a master playlist, every variant and rendition
+// playlist it lists, fetched concurrently
+pub async fn parse(parser: &M3u8Parser, url: &str) -> Result<DeepPlaylist, AppError> {
+    let content = parser.fetch_playlist(url).await?;
+    let (version, variants) = match parser.parse_content(&content, url)? {
+        ParsedPlaylist::Master { version, variants } => (version, variants),
+        ParsedPlaylist::Media { .. } => return Ok(DeepPlaylist::Media(overview(&content, url)?)),
+    };
+    let renditions: Vec<Rendition> = m3u8_parser::parse_renditions(&content, url)
+        .into_iter()
+        .filter(|rendition| rendition.uri.is_some())
+        .collect();
+
+    let urls: Vec<String> = variants
+        .iter()
+        .map(|variant| variant.uri.clone())
+        .chain(renditions.iter().filter_map(|rendition| rendition.uri.clone()))
+        .collect();
+    let mut results = futures::stream::iter(urls)
+        .map(|url| async move { fetch_overview(parser, &url).await })
+        .buffered(CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter();
+
+    let variants: Vec<_> = variants
+        .into_iter()
+        .zip(results.by_ref())
+        .map(|(variant, result)| entry(variant, result))
+        .collect();
+    let renditions: Vec<_> = renditions
+        .into_iter()
+        .zip(results)
+        .map(|(rendition, result)| entry(rendition, result))
+        .collect();
+    let failed = variants.iter().filter(|v| v.error.is_some()).count()
+        + renditions.iter().filter(|r| r.error.is_some()).count();
+
+    Ok(DeepPlaylist::Master(DeepMaster {
+        url: url.to_string(),
+        version,
+        variants,
+        renditions,
+        failed,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overview() {
+        let content = "#EXTM3U\n\
+            #EXT-X-TARGETDURATION:6\n\
+            #EXT-X-MEDIA-SEQUENCE:100\n\
+            #EXTINF:6.0,\n\
+            seg100.ts\n\
+            #EXT-X-DISCONTINUITY\n\
+            #EXTINF:4.5,\n\
+            seg101.ts\n\
+            #EXT-X-ENDLIST\n";
+        let overview = overview(content, "https://example.com/720p.m3u8").unwrap();
+        assert_eq!(overview.segments, 2);
+        assert_eq!(overview.duration, 10.5);
+        assert_eq!(overview.media_sequence, 100);
+        assert_eq!(overview.discontinuities, 1);
+        assert!(!overview.encrypted);
+        assert!(!overview.live);
+
+        assert!(super::overview("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\na.m3u8\n", "https://example.com/").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parse_offline() {
+        let root = std::env::temp_dir().join(format!("m3u8-fixtures-{}", uuid::Uuid::new_v4().simple()));
+        let dir = root.join("cdn.example.com").join("show");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("master.m3u8"), "#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000\n360p.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2400000\n720p.m3u8\n").unwrap();
+        std::fs::write(dir.join("360p.m3u8"), "#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXTINF:6.0,\na.ts\n#EXT-X-ENDLIST\n").unwrap();
+
+        let parser = M3u8Parser::with_fetcher(std::sync::Arc::new(m3u8_parser::FixtureFetcher::new(&root)));
+        let result = parse(&parser, "https://cdn.example.com/show/master.m3u8?token=1").await;
+        std::fs::remove_dir_all(&root).ok();
+
+        let DeepPlaylist::Master(master) = result.unwrap() else {
+            panic!("expected a master playlist");
+        };
+        assert_eq!(master.variants[0].playlist.as_ref().unwrap().segments, 1);
+        assert!(master.variants[1].error.is_some());
+        assert_eq!(master.failed, 1);
+    }
+}
@@ -0,0 +1,223 @@
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::config::AppConfig;
+use crate::error::{AppError, ErrorKind};
+use crate::m3u8_parser;
+
+// Bytes read to tell what a URL is; enough for the head of a playlist, the
+// magic of a media file and the part of a page that links its player
+const SNIFF_BYTES: usize = 256 * 1024;
+
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+// Stream URLs listed for a page at most
+const MAX_CANDIDATES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamKind {
+    HlsMaster,
+    HlsMedia,
+    Dash,
+    MediaFile,
+    HtmlPage,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Detection {
+    pub url: String,
+    // Where redirects led
+    pub final_url: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub kind: StreamKind,
+    // What the content says about itself: variant or segment count, whether
+    // a playlist is live, the container of a media file
+    pub details: Vec<String>,
+    // Playlist and manifest URLs found in a page
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<String>,
+    // What to do with it next
+    pub suggestion: String,
+}
+
+// Container of a media file from its first bytes
+fn container(head: &[u8]) -> Option<&'static str> {
+    if head.len() > 188 && head[0] == 0x47 && head[188] == 0x47 {
+        Some("MPEG-TS")
+    } else if head.len() >= 8 && &head[4..8] == b"ftyp" {
+        Some("MP4")
+    } else if head.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        Some("Matroska/WebM")
+    } else if head.starts_with(b"ID3") || (head.len() >= 2 && head[0] == 0xFF && head[1] & 0xE0 == 0xE0) {
+        Some("MP3/AAC")
+    } else if head.starts_with(b"FLV") {
+        Some("FLV")
+    } else {
+        None
+    }
+}
+
+// Stream URLs (.m3u8, .mpd) written into a page's HTML or scripts, resolved
+// against the page URL
+pub fn stream_candidates(html: &str, page_url: &str) -> Vec<String> {
+    let lower = html.to_ascii_lowercase();
+    let is_delimiter = |c: char| c.is_whitespace() || "\"'<>()`,;{}[]|".contains(c);
+    let mut candidates: Vec<String> = Vec::new();
+    for extension in [".m3u8", ".mpd"] {
+        for (position, _) in lower.match_indices(extension) {
+            let end = position + extension.len();
+            if lower[end..].chars().next().is_some_and(|c| c.is_ascii_alphanumeric()) {
+                continue;
+            }
+            let start = html[..position].rfind(is_delimiter).map_or(0, |i| i + 1);
+            let stop = html[end..].find(is_delimiter).map_or(html.len(), |i| end + i);
+            let raw = html[start..stop].replace("\\/", "/").replace("&amp;", "&");
+            if raw.len() <= extension.len() || raw.starts_with("data:") {
+                continue;
+            }
+            let url = m3u8_parser::resolve_uri(&raw, page_url);
+            if !candidates.contains(&url) {
+                candidates.push(url);
+            }
+        }
+    }
+    candidates.truncate(MAX_CANDIDATES);
+    candidates
+}
+
+// What `head`, the first bytes of the response to `url`, is
+pub fn classify(url: &str, content_type: Option<&str>, head: &[u8]) -> (StreamKind, Vec<String>, Vec<String>) {
+    let text = String::from_utf8_lossy(head);
+    let trimmed = text.trim_start_matches('\u{feff}').trim_start();
+    let content_type = content_type.unwrap_or_default().to_ascii_lowercase();
+
+    if trimmed.starts_with("#EXTM3U") {
+        let lines: Vec<&str> = trimmed.lines().map(str::trim).collect();
+        let variants = lines.iter().filter(|line| line.starts_with("#EXT-X-STREAM-INF")).count();
+        if variants > 0 {
+            let renditions = lines.iter().filter(|line| line.starts_with("#EXT-X-MEDIA:")).count();
+            return (StreamKind::HlsMaster, vec![format!("{} variant(s), {} rendition(s)", variants, renditions)], Vec::new());
+        }
+        let segments = lines.iter().filter(|line| line.starts_with("#EXTINF")).count();
+        let mut details = vec![format!("{} segment(s) in the first {} KB", segments, head.len() / 1024)];
+        if lines.iter().any(|line| *line == "#EXT-X-ENDLIST") {
+            details.push("VOD (has EXT-X-ENDLIST)".to_string());
+        } else if head.len() < SNIFF_BYTES {
+            details.push("live (no EXT-X-ENDLIST)".to_string());
+        }
+        if lines.iter().any(|line| line.starts_with("#EXT-X-KEY") && !line.contains("METHOD=NONE")) {
+            details.push("encrypted".to_string());
+        }
+        return (StreamKind::HlsMedia, details, Vec::new());
+    }
+    let xml = trimmed.starts_with("<?xml") || trimmed.starts_with("<MPD") || content_type.contains("dash+xml");
+    if xml && trimmed.contains("<MPD") {
+        let live = trimmed.contains("type=\"dynamic\"");
+        return (StreamKind::Dash, vec![if live { "live (dynamic)" } else { "VOD (static)" }.to_string()], Vec::new());
+    }
+    if let Some(container) = container(head) {
+        return (StreamKind::MediaFile, vec![container.to_string()], Vec::new());
+    }
+    let lower = trimmed.chars().take(1024).collect::<String>().to_ascii_lowercase();
+    if content_type.starts_with("text/html") || lower.starts_with("<!doctype html") || lower.contains("<html") {
+        let mut details = Vec::new();
+        if let Some(title) = crate::page_title::extract_title(&text) {
+            details.push(format!("title: {}", title));
+        }
+        return (StreamKind::HtmlPage, details, stream_candidates(&text, url));
+    }
+    if content_type.starts_with("video/") || content_type.starts_with("audio/") {
+        return (StreamKind::MediaFile, vec![content_type], Vec::new());
+    }
+    (StreamKind::Unknown, Vec::new(), Vec::new())
+}
+
+fn suggestion(kind: StreamKind, candidates: &[String]) -> String {
+    match kind {
+        StreamKind::HlsMaster => "HLS master playlist: m3u8_parse with summary (or deep) to see the variants, then m3u8_download".to_string(),
+        StreamKind::HlsMedia => "HLS media playlist: m3u8_download, or m3u8_stats to check its segments".to_string(),
+        StreamKind::Dash => "DASH manifest: FFmpeg reads it, so m3u8_probe and m3u8_download with engine \"ffmpeg\" work; the native downloader and playlist tools are HLS only".to_string(),
+        StreamKind::MediaFile => "Media file: m3u8_probe to inspect it, m3u8_convert to package it as HLS".to_string(),
+        StreamKind::HtmlPage if !candidates.is_empty() => "Web page linking streams: run m3u8_detect_stream on the candidates".to_string(),
+        StreamKind::HtmlPage => "Web page without a stream URL in its HTML: the player probably builds it in script. Open the page in a browser and copy the .m3u8 request from the developer tools' network tab".to_string(),
+        StreamKind::Unknown => "Not a stream this app recognizes".to_string(),
+    }
+}
+
+// Fetch the start of `url` and tell what it is
+pub async fn detect(config: &AppConfig, url: &str) -> Result<Detection, AppError> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(AppError::invalid_input("URL must start with http:// or https://"));
+    }
+    let client = crate::profiles::http_client(config, TIMEOUT)?;
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| AppError::new(ErrorKind::Network, format!("Cannot reach {}: {}", url, e)).with_retryable(true))?;
+    let status = response.status().as_u16();
+    let final_url = response.url().to_string();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    if !response.status().is_success() {
+        return Err(AppError::new(ErrorKind::Network, format!("{} answered HTTP {}", url, status))
+            .with_retryable(response.status().is_server_error()));
+    }
+
+    let mut head = Vec::new();
+    while let Ok(Some(chunk)) = response.chunk().await {
+        head.extend_from_slice(&chunk);
+        if head.len() >= SNIFF_BYTES {
+            head.truncate(SNIFF_BYTES);
+            break;
+        }
+    }
+
+    let (kind, details, candidates) = classify(&final_url, content_type.as_deref(), &head);
+    Ok(Detection {
+        url: url.to_string(),
+        final_url,
+        status,
+        content_type,
+        kind,
+        details,
+        suggestion: suggestion(kind, &candidates),
+        candidates,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let url = "https://example.com/watch/1";
+        let master = b"#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=800000\n360p.m3u8\n";
+        assert_eq!(classify(url, None, master).0, StreamKind::HlsMaster);
+        let media = b"\xEF\xBB\xBF#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXTINF:6,\na.ts\n#EXT-X-ENDLIST\n";
+        let (kind, details, _) = classify(url, Some("application/vnd.apple.mpegurl"), media);
+        assert_eq!(kind, StreamKind::HlsMedia);
+        assert!(details.contains(&"VOD (has EXT-X-ENDLIST)".to_string()));
+        assert_eq!(classify(url, None, b"<?xml version=\"1.0\"?>\n<MPD type=\"dynamic\">").0, StreamKind::Dash);
+        assert_eq!(classify(url, None, b"\0\0\0\x20ftypisom").1, vec!["MP4".to_string()]);
+
+        let page = br#"<!DOCTYPE html><html><head><title>Live</title></head><body>
+            <video src="/hls/live.m3u8?token=a&amp;b=1"></video>
+            <script>var config = {"dash":"https:\/\/cdn.example.com\/vod\/manifest.mpd"};</script></body></html>"#;
+        let (kind, details, candidates) = classify(url, Some("text/html; charset=utf-8"), page);
+        assert_eq!(kind, StreamKind::HtmlPage);
+        assert_eq!(details, vec!["title: Live".to_string()]);
+        assert_eq!(candidates, vec![
+            "https://example.com/hls/live.m3u8?token=a&b=1".to_string(),
+            "https://cdn.example.com/vod/manifest.mpd".to_string(),
+        ]);
+        assert_eq!(classify(url, None, b"hello").0, StreamKind::Unknown);
+    }
+}
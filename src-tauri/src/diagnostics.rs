@@ -0,0 +1,216 @@
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::ffmpeg_wrapper::FFmpegService;
+
+// Public test stream fetched to check network access when no URL is given
+pub const TEST_PLAYLIST_URL: &str = "https://test-streams.mux.dev/x36xhzz/x36xhzz.m3u8";
+
+// Free space below which the output directory check warns
+const LOW_DISK_SPACE: u64 = 1024 * 1024 * 1024;
+
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Failed,
+}
+
+impl CheckStatus {
+    fn label(self) -> &'static str {
+        match self {
+            CheckStatus::Ok => "ok",
+            CheckStatus::Warning => "warning",
+            CheckStatus::Failed => "FAILED",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+// Results of run_diagnostics, with `text` ready to paste into a bug report.
+// Paths under the home directory and URL credentials are redacted.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsReport {
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    pub generated_at: String,
+    pub checks: Vec<Check>,
+    pub text: String,
+}
+
+fn check(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Check {
+    Check { name, status, detail: crate::crash::redact(&detail.into()) }
+}
+
+fn render(report: &DiagnosticsReport) -> String {
+    let mut text = format!(
+        "m3u8-mcp {} ({} {}) at {}\n",
+        report.version, report.os, report.arch, report.generated_at
+    );
+    for check in &report.checks {
+        text.push_str(&format!("[{}] {}: {}\n", check.status.label(), check.name, check.detail));
+    }
+    text
+}
+
+fn check_config() -> (Check, Option<config::AppConfig>) {
+    let app_config = match config::load_config() {
+        Ok(app_config) => app_config,
+        Err(e) => return (check("config", CheckStatus::Failed, e.message), None),
+    };
+    let mut problems = Vec::new();
+    if let Some(name) = &app_config.active_profile {
+        if !app_config.profiles.contains_key(name) {
+            problems.push(format!("active profile {} does not exist", name));
+        }
+    }
+    let effective = app_config.effective();
+    if let Err(e) = crate::profiles::proxy_url(&effective) {
+        problems.push(e.message);
+    }
+    if !crate::paths::expand_path(&effective.output_dir).is_absolute() {
+        problems.push(format!("output_dir {} is not an absolute path", effective.output_dir));
+    }
+    let result = if problems.is_empty() {
+        check("config", CheckStatus::Ok, format!("{} profile(s), active: {}", app_config.profiles.len(), app_config.active_profile.as_deref().unwrap_or("none")))
+    } else {
+        check("config", CheckStatus::Warning, problems.join("; "))
+    };
+    (result, Some(effective))
+}
+
+async fn check_database() -> Check {
+    let db = match crate::database::global_db().await {
+        Ok(db) => db,
+        Err(e) => return check("database", CheckStatus::Failed, e.message),
+    };
+    match db.integrity_check() {
+        Ok(problems) if problems.is_empty() => check("database", CheckStatus::Ok, "integrity_check: ok"),
+        Ok(problems) => check(
+            "database",
+            CheckStatus::Failed,
+            format!("integrity_check found {} problem(s): {}", problems.len(), problems.join("; ")),
+        ),
+        Err(e) => check("database", CheckStatus::Failed, format!("integrity_check failed: {}", e)),
+    }
+}
+
+fn check_disk_space(app_config: &config::AppConfig) -> Check {
+    let path = crate::paths::expand_path(&app_config.output_dir);
+    match crate::output_dir::describe(&path) {
+        Ok(info) => {
+            let detail = format!(
+                "{}: {:.1} GB free of {:.1} GB{}",
+                info.path,
+                info.free_bytes as f64 / 1e9,
+                info.total_bytes as f64 / 1e9,
+                if info.exists { "" } else { " (directory does not exist yet)" }
+            );
+            let status = if info.free_bytes < LOW_DISK_SPACE { CheckStatus::Warning } else { CheckStatus::Ok };
+            check("disk_space", status, detail)
+        }
+        Err(e) => check("disk_space", CheckStatus::Failed, e.message),
+    }
+}
+
+async fn check_network(app_config: &config::AppConfig, url: &str) -> Check {
+    let parser = crate::profiles::parser_for(app_config);
+    let started = Instant::now();
+    match tokio::time::timeout(NETWORK_TIMEOUT, parser.fetch_playlist(url)).await {
+        Ok(Ok(content)) if content.trim_start().starts_with("#EXTM3U") => check(
+            "network",
+            CheckStatus::Ok,
+            format!("fetched {} in {} ms", url, started.elapsed().as_millis()),
+        ),
+        Ok(Ok(_)) => check("network", CheckStatus::Warning, format!("{} did not answer with a playlist", url)),
+        Ok(Err(e)) => check("network", CheckStatus::Failed, format!("{}: {}", url, e)),
+        Err(_) => check("network", CheckStatus::Failed, format!("{}: no answer in {}s", url, NETWORK_TIMEOUT.as_secs())),
+    }
+}
+
+// The configured MCP port is fine when this app's server holds it or
+// nothing does
+async fn check_port(app_config: &config::AppConfig, running_port: Option<u16>) -> Check {
+    let mcp = &app_config.mcp;
+    if running_port.is_some() && running_port == Some(mcp.port) {
+        return check("port", CheckStatus::Ok, format!("MCP server running on {}:{}", mcp.bind_host, mcp.port));
+    }
+    if mcp.port == 0 {
+        return check("port", CheckStatus::Ok, "port 0: the OS picks a free port");
+    }
+    match tokio::net::TcpListener::bind((mcp.bind_host.as_str(), mcp.port)).await {
+        Ok(_) => check("port", CheckStatus::Ok, format!("{}:{} is free", mcp.bind_host, mcp.port)),
+        Err(e) => check("port", CheckStatus::Warning, format!("{}:{} is not available: {}", mcp.bind_host, mcp.port, e)),
+    }
+}
+
+// Check everything the app depends on. `running_port` is where this app's
+// MCP server listens, if it runs; `test_url` replaces the test playlist.
+pub async fn run(ffmpeg: &FFmpegService, running_port: Option<u16>, test_url: Option<&str>) -> DiagnosticsReport {
+    let mut checks = Vec::new();
+    let (config_check, app_config) = check_config();
+    checks.push(config_check);
+    let app_config = app_config.unwrap_or_default();
+
+    checks.push(match ffmpeg.check_installation() {
+        Ok(version) => check("ffmpeg", CheckStatus::Ok, version),
+        Err(e) => check("ffmpeg", CheckStatus::Failed, e.to_string()),
+    });
+    checks.push(match ffmpeg.check_ffprobe() {
+        Ok(version) => check("ffprobe", CheckStatus::Ok, version),
+        Err(e) => check("ffprobe", CheckStatus::Failed, e.to_string()),
+    });
+    checks.push(check_database().await);
+    checks.push(check_disk_space(&app_config));
+    checks.push(check_network(&app_config, test_url.unwrap_or(TEST_PLAYLIST_URL)).await);
+    checks.push(check_port(&app_config, running_port).await);
+
+    let mut report = DiagnosticsReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        generated_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        checks,
+        text: String::new(),
+    };
+    report.text = render(&report);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let report = DiagnosticsReport {
+            version: "0.1.0".to_string(),
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            generated_at: "2024-05-01T10:00:00Z".to_string(),
+            checks: vec![
+                check("ffmpeg", CheckStatus::Ok, "ffmpeg version 6.1"),
+                check("network", CheckStatus::Failed, "https://user:pw@cdn.example.com/a.m3u8?token=x: timed out"),
+            ],
+            text: String::new(),
+        };
+        assert_eq!(
+            render(&report),
+            "m3u8-mcp 0.1.0 (linux x86_64) at 2024-05-01T10:00:00Z\n\
+             [ok] ffmpeg: ffmpeg version 6.1\n\
+             [FAILED] network: https://***@cdn.example.com/a.m3u8?*** timed out\n"
+        );
+    }
+}
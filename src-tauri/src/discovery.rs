@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+
+use crate::config;
+use crate::error::AppError;
+
+// MCP endpoint URL for a server listening on `port`
+pub fn endpoint_url(port: u16) -> String {
+    format!("http://localhost:{}/mcp", port)
+}
+
+// Write ~/.m3u8-mcp/server.json describing the running server, so clients
+// and scripts can find the endpoint without knowing the port in advance.
+pub fn write_discovery_file(port: u16, auth_token: &str) -> Result<(), AppError> {
+    let dir = config::app_dir()?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| AppError::io(format!("Failed to create config directory: {}", e)))?;
+
+    let info = json!({
+        "name": "m3u8-mcp",
+        "version": env!("CARGO_PKG_VERSION"),
+        "transport": "streamable-http",
+        "url": endpoint_url(port),
+        "port": port,
+        // Artifacts are served at <artifacts_url>/<job_id> with this bearer token
+        "artifacts_url": format!("http://localhost:{}/artifacts", port),
+        "token": auth_token,
+        "pid": std::process::id(),
+        "started_at": chrono::Utc::now().to_rfc3339(),
+    });
+
+    let content = serde_json::to_string_pretty(&info)
+        .map_err(|e| AppError::internal(format!("Failed to serialize discovery file: {}", e)))?;
+    fs::write(dir.join("server.json"), content)
+        .map_err(|e| AppError::io(format!("Failed to write discovery file: {}", e)))
+}
+
+pub fn remove_discovery_file() -> Result<(), AppError> {
+    let path = config::app_dir()?.join("server.json");
+    if path.exists() {
+        fs::remove_file(path)
+            .map_err(|e| AppError::io(format!("Failed to remove discovery file: {}", e)))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum McpClient {
+    ClaudeDesktop,
+    ClaudeCode,
+    Cursor,
+    Windsurf,
+    Vscode,
+    Codex,
+}
+
+impl McpClient {
+    pub const ALL: [McpClient; 6] = [
+        McpClient::ClaudeDesktop,
+        McpClient::ClaudeCode,
+        McpClient::Cursor,
+        McpClient::Windsurf,
+        McpClient::Vscode,
+        McpClient::Codex,
+    ];
+}
+
+// How the client talks to the server: directly over Streamable HTTP, or
+// through the mcp-remote stdio bridge for clients that only spawn commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionMode {
+    Http,
+    Stdio,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientConfig {
+    pub client: McpClient,
+    pub mode: ConnectionMode,
+    // "json", "toml" or "shell"
+    pub format: &'static str,
+    // Where the block goes
+    pub target: &'static str,
+    pub content: String,
+}
+
+fn bridge_args(url: &str) -> serde_json::Value {
+    json!(["-y", "mcp-remote", url])
+}
+
+fn pretty(value: serde_json::Value) -> String {
+    serde_json::to_string_pretty(&value).unwrap_or_default()
+}
+
+// Generate the configuration block for one client
+pub fn generate_client_config(client: McpClient, mode: ConnectionMode, port: u16) -> ClientConfig {
+    let url = endpoint_url(port);
+    let stdio_server = json!({
+        "command": "npx",
+        "args": bridge_args(&url)
+    });
+
+    // Claude Desktop only launches local commands, so it always uses the bridge
+    let mode = if client == McpClient::ClaudeDesktop { ConnectionMode::Stdio } else { mode };
+
+    let (format, target, content) = match (client, mode) {
+        (McpClient::ClaudeDesktop, _) => (
+            "json",
+            "claude_desktop_config.json",
+            pretty(json!({ "mcpServers": { "m3u8": stdio_server } })),
+        ),
+        (McpClient::ClaudeCode, ConnectionMode::Http) => (
+            "shell",
+            "terminal",
+            format!("claude mcp add --transport http m3u8 {}", url),
+        ),
+        (McpClient::ClaudeCode, ConnectionMode::Stdio) => (
+            "shell",
+            "terminal",
+            format!("claude mcp add m3u8 -- npx -y mcp-remote {}", url),
+        ),
+        (McpClient::Cursor, ConnectionMode::Http) => (
+            "json",
+            ".cursor/mcp.json",
+            pretty(json!({ "mcpServers": { "m3u8": { "url": url } } })),
+        ),
+        (McpClient::Cursor, ConnectionMode::Stdio) => (
+            "json",
+            ".cursor/mcp.json",
+            pretty(json!({ "mcpServers": { "m3u8": stdio_server } })),
+        ),
+        (McpClient::Windsurf, ConnectionMode::Http) => (
+            "json",
+            "~/.codeium/windsurf/mcp_config.json",
+            pretty(json!({ "mcpServers": { "m3u8": { "serverUrl": url } } })),
+        ),
+        (McpClient::Windsurf, ConnectionMode::Stdio) => (
+            "json",
+            "~/.codeium/windsurf/mcp_config.json",
+            pretty(json!({ "mcpServers": { "m3u8": stdio_server } })),
+        ),
+        (McpClient::Vscode, ConnectionMode::Http) => (
+            "json",
+            ".vscode/mcp.json",
+            pretty(json!({ "servers": { "m3u8": { "type": "http", "url": url } } })),
+        ),
+        (McpClient::Vscode, ConnectionMode::Stdio) => (
+            "json",
+            ".vscode/mcp.json",
+            pretty(json!({
+                "servers": {
+                    "m3u8": {
+                        "type": "stdio",
+                        "command": "npx",
+                        "args": bridge_args(&url)
+                    }
+                }
+            })),
+        ),
+        (McpClient::Codex, ConnectionMode::Http) => (
+            "toml",
+            "~/.codex/config.toml",
+            format!("[mcp_servers.m3u8]\nurl = \"{}\"\n", url),
+        ),
+        (McpClient::Codex, ConnectionMode::Stdio) => (
+            "toml",
+            "~/.codex/config.toml",
+            format!("[mcp_servers.m3u8]\ncommand = \"npx\"\nargs = [\"-y\", \"mcp-remote\", \"{}\"]\n", url),
+        ),
+    };
+
+    ClientConfig {
+        client,
+        mode,
+        format,
+        target,
+        content,
+    }
+}
+
+// Configuration blocks for every supported client
+pub fn client_configs(port: u16, mode: ConnectionMode) -> Vec<ClientConfig> {
+    McpClient::ALL
+        .iter()
+        .map(|client| generate_client_config(*client, mode, port))
+        .collect()
+}
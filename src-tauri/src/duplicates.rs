@@ -0,0 +1,85 @@
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use crate::database::{self, DownloadedStream};
+use crate::error::AppError;
+use crate::m3u8_parser::M3u8Parser;
+
+// Identity of a playlist's media independent of the URL it came from: a hash
+// of its segment (or variant) URIs without query strings, which is where
+// signed URLs keep their tokens
+pub fn fingerprint(content: &str) -> Option<String> {
+    let uris: Vec<&str> = content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.split(['?', '#']).next().unwrap_or(line))
+        .collect();
+    if uris.is_empty() {
+        return None;
+    }
+    let mut hasher = Sha256::new();
+    for uri in uris {
+        hasher.update(uri.as_bytes());
+        hasher.update(b"\n");
+    }
+    Some(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// Fingerprint of the playlist at `url`; None when it cannot be fetched
+pub async fn playlist_fingerprint(parser: &M3u8Parser, url: &str) -> Option<String> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return None;
+    }
+    parser.fetch_playlist(url).await.ok().and_then(|content| fingerprint(&content))
+}
+
+// The latest earlier download of the same URL or the same media whose file
+// is still on disk
+pub async fn find_existing(parser: &M3u8Parser, url: &str) -> Result<Option<DownloadedStream>, AppError> {
+    let Some(db) = database::GLOBAL_DB.read().await.clone() else {
+        return Ok(None);
+    };
+    let fingerprint = playlist_fingerprint(parser, url).await;
+    Ok(db.find_downloads(url, fingerprint.as_deref())?
+        .into_iter()
+        .find(|download| Path::new(&download.output_path).exists()))
+}
+
+// Structured answer telling the caller the stream is already on disk
+pub fn already_downloaded(url: &str, existing: &DownloadedStream) -> Value {
+    json!({
+        "status": "already_downloaded",
+        "url": url,
+        "path": existing.output_path,
+        "download_id": existing.id,
+        "downloaded_at": existing.downloaded_at,
+        "file_size": existing.file_size,
+        "duration": existing.duration,
+        "same_url": existing.url == url,
+        "message": format!(
+            "Already downloaded at {}. Use that file, or call again with `force: true` to download it anyway.",
+            existing.output_path
+        )
+    })
+}
+
+// The same as a Conflict error for the app and the job API
+pub fn conflict(url: &str, existing: &DownloadedStream) -> AppError {
+    AppError::conflict(format!("Already downloaded at {}", existing.output_path))
+        .with_details(already_downloaded(url, existing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        let signed = "#EXTM3U\n#EXTINF:6,\nseg1.ts?token=abc\n#EXTINF:6,\nseg2.ts?token=abc\n#EXT-X-ENDLIST\n";
+        let resigned = "#EXTM3U\n#EXTINF:6.0,\nseg1.ts?token=xyz\n#EXTINF:6.0,\nseg2.ts?token=xyz\n#EXT-X-ENDLIST\n";
+        assert_eq!(fingerprint(signed), fingerprint(resigned));
+        assert_ne!(fingerprint(signed), fingerprint("#EXTM3U\n#EXTINF:6,\nseg3.ts\n"));
+        assert_eq!(fingerprint("#EXTM3U\n"), None);
+    }
+}
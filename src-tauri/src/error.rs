@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+use crate::ffmpeg_wrapper::FFmpegError;
+use crate::m3u8_parser::M3u8Error;
+
+// Error category shared by Tauri commands and MCP error data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    InvalidInput,
+    NotFound,
+    NotInitialized,
+    Conflict,
+    Network,
+    Parse,
+    MissingDependency,
+    Ffmpeg,
+    Database,
+    Io,
+    // A request ran out of time and was cancelled
+    Timeout,
+    Internal,
+}
+
+// Serializable error returned by every Tauri command.
+// The frontend receives it as { kind, message, details?, retryable }.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppError {
+    pub kind: ErrorKind,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+    pub retryable: bool,
+}
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            details: None,
+            retryable: matches!(kind, ErrorKind::Network),
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidInput, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    pub fn not_initialized(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotInitialized, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Conflict, message)
+    }
+
+    pub fn database(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Database, message)
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Io, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Internal, message)
+    }
+
+    // JSON-RPC error code used when this error is returned over MCP
+    pub fn jsonrpc_code(&self) -> i32 {
+        match self.kind {
+            ErrorKind::InvalidInput => -32602,
+            _ => -32603,
+        }
+    }
+
+    // Value placed in the JSON-RPC error `data` field
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<M3u8Error> for AppError {
+    fn from(e: M3u8Error) -> Self {
+        let kind = match e {
+            M3u8Error::NetworkError(_) => ErrorKind::Network,
+            M3u8Error::ParseError(_) => ErrorKind::Parse,
+            M3u8Error::InvalidUrl(_) => ErrorKind::InvalidInput,
+            M3u8Error::HttpStatus(_) => ErrorKind::Network,
+            // A segment or page passed where a playlist was expected
+            M3u8Error::TooLarge(_) => ErrorKind::InvalidInput,
+            M3u8Error::NotAPlaylist(_) => ErrorKind::Parse,
+            M3u8Error::TooManyRedirects => ErrorKind::Network,
+            _ => ErrorKind::Internal,
+        };
+        // Client errors such as 403 or 404 will not go away on retry, and
+        // neither will a redirect loop
+        let retryable = match e {
+            M3u8Error::HttpStatus(code) => code >= 500 || code == 429,
+            M3u8Error::TooManyRedirects => false,
+            _ => kind == ErrorKind::Network,
+        };
+        let error = Self::new(kind, e.to_string()).with_retryable(retryable);
+        match e {
+            M3u8Error::TooLarge(limit) => error.with_details(serde_json::json!({ "limit_bytes": limit })),
+            _ => error,
+        }
+    }
+}
+
+impl From<FFmpegError> for AppError {
+    fn from(e: FFmpegError) -> Self {
+        let kind = match e {
+            FFmpegError::NotInstalled => ErrorKind::MissingDependency,
+            FFmpegError::CommandFailed(_) => ErrorKind::Ffmpeg,
+            FFmpegError::InvalidInput(_) => ErrorKind::InvalidInput,
+            FFmpegError::OutputError(_) => ErrorKind::Io,
+            FFmpegError::Failed(ref diagnosis) => {
+                return Self::new(diagnosis.kind, e.to_string())
+                    .with_retryable(diagnosis.retryable)
+                    .with_details(serde_json::json!({
+                        "summary": diagnosis.summary,
+                        "hint": diagnosis.hint,
+                        "ffmpeg_output": diagnosis.line
+                    }));
+            }
+        };
+        Self::new(kind, e.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::database(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        Self::io(e.to_string())
+    }
+}
@@ -0,0 +1,165 @@
+use chrono::{SecondsFormat, Utc};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::config::EventLogConfig;
+use crate::ffmpeg_wrapper::{FFmpegService, ServiceEvent};
+
+// Every event the service emits, mirrored as JSON lines to a file and to
+// the clients of a Unix socket, so a supervisor of a headless instance can
+// follow jobs without Tauri or an HTTP client. A line looks like
+// {"time": "...", "event": "download-progress", "data": {...}}.
+
+// Time a socket client gets to take a line before it is dropped
+#[cfg(unix)]
+const CLIENT_WRITE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+// An event as one line of JSON, newline included
+pub fn line(event: &ServiceEvent) -> String {
+    let mut line = json!({
+        "time": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        "event": event.event,
+        "data": event.payload,
+    })
+    .to_string();
+    line.push('\n');
+    line
+}
+
+async fn open_file(path: &Path) -> std::io::Result<tokio::fs::File> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::OpenOptions::new().create(true).append(true).open(path).await
+}
+
+// Socket clients, added as they connect
+#[cfg(unix)]
+type Clients = Arc<tokio::sync::Mutex<Vec<tokio::net::UnixStream>>>;
+
+#[cfg(unix)]
+fn listen(path: &Path) -> std::io::Result<Clients> {
+    // A socket file left by a previous run would make bind fail
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)?;
+    let clients: Clients = Arc::default();
+    let accepted = clients.clone();
+    tokio::spawn(crate::crash::guard("event-socket", async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => accepted.lock().await.push(stream),
+                Err(e) => {
+                    eprintln!("Event socket stopped accepting clients: {}", e);
+                    break;
+                }
+            }
+        }
+    }));
+    Ok(clients)
+}
+
+// Write a line to every client, dropping the ones that are gone or too slow
+#[cfg(unix)]
+async fn broadcast(clients: &Clients, line: &str) {
+    let mut clients = clients.lock().await;
+    let mut connected = Vec::with_capacity(clients.len());
+    for mut client in clients.drain(..) {
+        let written = tokio::time::timeout(CLIENT_WRITE_TIMEOUT, client.write_all(line.as_bytes())).await;
+        if matches!(written, Ok(Ok(()))) {
+            connected.push(client);
+        }
+    }
+    *clients = connected;
+}
+
+// Mirror the service's events as configured; returns at once when neither a
+// file nor a socket is set, and otherwise runs for the lifetime of the service
+pub async fn run(ffmpeg: Arc<FFmpegService>, config: EventLogConfig) {
+    let path: Option<PathBuf> = config.path.as_deref().map(crate::paths::expand_path);
+    let mut file = match &path {
+        Some(path) => match open_file(path).await {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("Cannot open event log {}: {}", path.display(), e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    #[cfg(unix)]
+    let clients = match config.socket.as_deref().map(crate::paths::expand_path) {
+        Some(socket) => match listen(&socket) {
+            Ok(clients) => {
+                println!("Streaming events on {}", socket.display());
+                Some(clients)
+            }
+            Err(e) => {
+                eprintln!("Cannot listen on event socket {}: {}", socket.display(), e);
+                None
+            }
+        },
+        None => None,
+    };
+    #[cfg(not(unix))]
+    if config.socket.is_some() {
+        eprintln!("event_log.socket needs Unix sockets, which this platform does not have");
+    }
+
+    #[cfg(unix)]
+    let has_socket = clients.is_some();
+    #[cfg(not(unix))]
+    let has_socket = false;
+    if file.is_none() && !has_socket {
+        return;
+    }
+
+    let mut events = ffmpeg.subscribe();
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                eprintln!("Event log lagged, skipped {} events", skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        let line = line(&event);
+        if let Some(writer) = file.as_mut() {
+            let written = writer.write_all(line.as_bytes()).await;
+            if let Err(e) = written.and(writer.flush().await) {
+                eprintln!("Cannot write event log: {}", e);
+                file = None;
+            }
+        }
+        #[cfg(unix)]
+        if let Some(clients) = &clients {
+            broadcast(clients, &line).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        let event = ServiceEvent {
+            event: "download-progress".to_string(),
+            payload: json!({ "version": 1, "job_id": "job-1", "status": "starting", "message": "Starting" }),
+        };
+        let line = line(&event);
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["event"], "download-progress");
+        assert_eq!(value["data"]["job_id"], "job-1");
+        assert!(value["time"].as_str().unwrap().ends_with('Z'));
+    }
+}
@@ -0,0 +1,219 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::captures::Capture;
+use crate::error::AppError;
+use crate::native_download::RecordingGap;
+use crate::warmup::{LinkCheck, WarmupSummary};
+
+// Payloads of the events sent to the UI and to /api/events, mirrored by
+// src/events.ts. Every payload carries `version`: adding a field leaves it
+// as it is, while removing, renaming or changing the meaning of one raises
+// it, so an older frontend can tell the events it does not understand.
+pub const EVENT_VERSION: u32 = 1;
+
+pub const DOWNLOAD_PROGRESS: &str = "download-progress";
+pub const THUMBNAIL_PROGRESS: &str = "thumbnail-progress";
+pub const PROBE_WARMUP_PROGRESS: &str = "probe-warmup-progress";
+pub const CAPTURE_RECEIVED: &str = "capture-received";
+
+// What happened to a job, as its "status" and the fields that go with it
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobUpdate {
+    Starting,
+    Progress {
+        // FFmpeg's readout
+        #[serde(skip_serializing_if = "Option::is_none")]
+        time: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        size: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        speed: Option<String>,
+        // Native and aria2c downloads
+        #[serde(skip_serializing_if = "Option::is_none")]
+        segments_done: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        segments_total: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bytes: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fetcher: Option<&'static str>,
+    },
+    Retrying {
+        attempt: u32,
+        max_attempts: u32,
+        delay_seconds: f64,
+        error: AppError,
+    },
+    // A live segment left the playlist before it could be fetched;
+    // `position` is where it falls in the recording, in seconds
+    Gap {
+        sequence: u64,
+        position: f64,
+    },
+    UrlRefreshed {
+        url: String,
+    },
+    VariantSwitched {
+        from: String,
+        to: String,
+    },
+    Completed {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<PathBuf>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        gaps: Option<Vec<RecordingGap>>,
+    },
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<AppError>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        log_tail: Option<Vec<String>>,
+    },
+    Cancelled,
+}
+
+impl JobUpdate {
+    // Progress with only a message
+    pub fn progress() -> Self {
+        JobUpdate::Progress {
+            time: None,
+            size: None,
+            speed: None,
+            segments_done: None,
+            segments_total: None,
+            bytes: None,
+            fetcher: None,
+        }
+    }
+
+    // Progress of a segment download: `done` of `total` segments, `bytes` so far
+    pub fn segments(done: usize, total: usize, bytes: u64, fetcher: Option<&'static str>) -> Self {
+        JobUpdate::Progress {
+            time: None,
+            size: Some(format!("{}kB", bytes / 1024)),
+            speed: None,
+            segments_done: Some(done),
+            segments_total: Some(total),
+            bytes: Some(bytes),
+            fetcher,
+        }
+    }
+}
+
+// download-progress, and the convert-progress and normalize-progress
+// events of the other FFmpeg jobs
+#[derive(Debug, Clone, Serialize)]
+pub struct JobEvent {
+    pub version: u32,
+    pub job_id: String,
+    pub message: String,
+    #[serde(flatten)]
+    pub update: JobUpdate,
+}
+
+impl JobEvent {
+    pub fn new(job_id: &str, message: impl Into<String>, update: JobUpdate) -> Self {
+        Self { version: EVENT_VERSION, job_id: job_id.to_string(), message: message.into(), update }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ThumbnailUpdate {
+    Completed {
+        thumbnail: PathBuf,
+        preview: Option<PathBuf>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+// thumbnail-progress: a thumbnail of the download at `path` was made or failed
+#[derive(Debug, Clone, Serialize)]
+pub struct ThumbnailEvent {
+    pub version: u32,
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub update: ThumbnailUpdate,
+}
+
+impl ThumbnailEvent {
+    pub fn new(path: PathBuf, update: ThumbnailUpdate) -> Self {
+        Self { version: EVENT_VERSION, path, update }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WarmupUpdate {
+    Checking {
+        #[serde(flatten)]
+        check: LinkCheck,
+        done: usize,
+        total: usize,
+    },
+    Completed {
+        summary: WarmupSummary,
+    },
+}
+
+// probe-warmup-progress
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmupEvent {
+    pub version: u32,
+    #[serde(flatten)]
+    pub update: WarmupUpdate,
+}
+
+impl WarmupEvent {
+    pub fn new(update: WarmupUpdate) -> Self {
+        Self { version: EVENT_VERSION, update }
+    }
+}
+
+// capture-received: the browser extension sent a stream URL
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureEvent {
+    pub version: u32,
+    #[serde(flatten)]
+    pub capture: Capture,
+}
+
+impl CaptureEvent {
+    pub fn new(capture: Capture) -> Self {
+        Self { version: EVENT_VERSION, capture }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_job_event() {
+        let event = JobEvent::new("job-1", "Segments: 2/10", JobUpdate::segments(2, 10, 4096, Some("aria2c")));
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({
+                "version": EVENT_VERSION,
+                "status": "progress",
+                "job_id": "job-1",
+                "message": "Segments: 2/10",
+                "segments_done": 2,
+                "segments_total": 10,
+                "size": "4kB",
+                "bytes": 4096,
+                "fetcher": "aria2c"
+            })
+        );
+        let event = JobEvent::new("job-1", "Cancelled", JobUpdate::Cancelled);
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({ "version": EVENT_VERSION, "status": "cancelled", "job_id": "job-1", "message": "Cancelled" })
+        );
+    }
+}
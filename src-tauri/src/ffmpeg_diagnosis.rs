@@ -0,0 +1,199 @@
+use serde::Serialize;
+
+use crate::error::ErrorKind;
+
+// What a failed FFmpeg run's stderr says went wrong, in terms of the error
+// taxonomy, with a hint on what to do about it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnosis {
+    pub kind: ErrorKind,
+    pub retryable: bool,
+    pub summary: &'static str,
+    pub hint: &'static str,
+    // The stderr line that matched
+    pub line: String,
+}
+
+struct Pattern {
+    // Lowercase substrings, any of which identifies the failure
+    needles: &'static [&'static str],
+    kind: ErrorKind,
+    retryable: bool,
+    summary: &'static str,
+    hint: &'static str,
+}
+
+// Most specific first: a key that fails with 403 is reported as a key problem
+const PATTERNS: &[Pattern] = &[
+    Pattern {
+        needles: &["unable to open key file", "failed to open key", "error opening key"],
+        kind: ErrorKind::Network,
+        retryable: false,
+        summary: "Encryption key could not be loaded",
+        hint: "The AES key URL failed. Key servers often want the same cookies, Referer or token as the playlist; fetch a fresh playlist URL from the page.",
+    },
+    Pattern {
+        needles: &["403 forbidden"],
+        kind: ErrorKind::Network,
+        retryable: false,
+        summary: "Access denied (HTTP 403)",
+        hint: "The server refused the request. Signed URLs expire, so fetch a fresh playlist URL; some hosts also require the page's Referer, cookies or User-Agent.",
+    },
+    Pattern {
+        needles: &["401 unauthorized", "401 authorization required"],
+        kind: ErrorKind::Network,
+        retryable: false,
+        summary: "Authentication required (HTTP 401)",
+        hint: "The stream needs credentials or a session cookie from the site it is played on.",
+    },
+    Pattern {
+        needles: &["404 not found", "410 gone"],
+        kind: ErrorKind::NotFound,
+        retryable: false,
+        summary: "Not found (HTTP 404)",
+        hint: "The playlist or a segment no longer exists. Live windows move on and VOD links are taken down; check that the URL still plays in the browser.",
+    },
+    Pattern {
+        needles: &["5xx server error", "http error 50", "server returned 50", "429 too many requests"],
+        kind: ErrorKind::Network,
+        retryable: true,
+        summary: "Server error",
+        hint: "The server is overloaded or having a temporary problem; retrying later usually works.",
+    },
+    Pattern {
+        needles: &["4xx client error", "http error 4", "server returned 4"],
+        kind: ErrorKind::Network,
+        retryable: false,
+        summary: "Request rejected (HTTP 4xx)",
+        hint: "The server refused the request; check the URL and any headers the site requires.",
+    },
+    Pattern {
+        needles: &[
+            "failed to resolve hostname",
+            "name or service not known",
+            "temporary failure in name resolution",
+            "nodename nor servname",
+        ],
+        kind: ErrorKind::Network,
+        retryable: true,
+        summary: "Host name could not be resolved",
+        hint: "Check the host name in the URL and the DNS or proxy settings.",
+    },
+    Pattern {
+        needles: &[
+            "connection reset",
+            "connection refused",
+            "timed out",
+            "network is unreachable",
+            "broken pipe",
+        ],
+        kind: ErrorKind::Network,
+        retryable: true,
+        summary: "Connection failed",
+        hint: "The connection could not be made or dropped midway. Check the network and proxy settings; the download can be retried.",
+    },
+    Pattern {
+        needles: &["not on whitelist"],
+        kind: ErrorKind::InvalidInput,
+        retryable: false,
+        summary: "Protocol not allowed",
+        hint: "A local playlist refers to network URLs, which FFmpeg does not open by default. Download from the playlist's URL instead.",
+    },
+    Pattern {
+        needles: &["protocol not found"],
+        kind: ErrorKind::MissingDependency,
+        retryable: false,
+        summary: "Protocol not supported by FFmpeg",
+        hint: "This FFmpeg build cannot open the URL's protocol. Install a build with network and TLS support, such as one from ffmpeg.org.",
+    },
+    Pattern {
+        needles: &["invalid data found when processing input", "moov atom not found"],
+        kind: ErrorKind::Parse,
+        retryable: false,
+        summary: "FFmpeg could not read the input",
+        hint: "The URL did not return media FFmpeg understands. It may be an HTML or error page, DRM-protected, or encrypted with a key that could not be fetched.",
+    },
+    Pattern {
+        needles: &[
+            "unsupported codec",
+            "unknown encoder",
+            "encoder not found",
+            "decoder not found",
+            "could not find tag for codec",
+            "not currently supported in container",
+            "codec not supported",
+        ],
+        kind: ErrorKind::Ffmpeg,
+        retryable: false,
+        summary: "Unsupported codec",
+        hint: "FFmpeg cannot handle this codec with the chosen output. Try another container such as .mkv, or an FFmpeg build with the codec enabled.",
+    },
+    Pattern {
+        needles: &["no space left on device"],
+        kind: ErrorKind::Io,
+        retryable: false,
+        summary: "Disk full",
+        hint: "Free up space or choose another output directory.",
+    },
+    Pattern {
+        needles: &["permission denied", "read-only file system"],
+        kind: ErrorKind::Io,
+        retryable: false,
+        summary: "Permission denied",
+        hint: "FFmpeg cannot write the output; choose a directory you can write to.",
+    },
+    Pattern {
+        needles: &["no such file or directory"],
+        kind: ErrorKind::NotFound,
+        retryable: false,
+        summary: "File not found",
+        hint: "The input file or the output directory does not exist.",
+    },
+];
+
+// Match FFmpeg's stderr against known failures; None when nothing is recognised
+pub fn diagnose(stderr: &str) -> Option<Diagnosis> {
+    let lines: Vec<(&str, String)> = stderr.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| (line, line.to_lowercase()))
+        .collect();
+    PATTERNS.iter().find_map(|pattern| {
+        let (line, _) = lines.iter()
+            .find(|(_, lower)| pattern.needles.iter().any(|needle| lower.contains(needle)))?;
+        Some(Diagnosis {
+            kind: pattern.kind,
+            retryable: pattern.retryable,
+            summary: pattern.summary,
+            hint: pattern.hint,
+            line: line.to_string(),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnose() {
+        let stderr = "[hls @ 0x7f] Opening 'https://cdn.example.com/key.bin' for reading\n\
+            [https @ 0x7f] HTTP error 403 Forbidden\n\
+            [hls @ 0x7f] Unable to open key file https://cdn.example.com/key.bin\n";
+        let diagnosis = diagnose(stderr).unwrap();
+        assert_eq!(diagnosis.summary, "Encryption key could not be loaded");
+        assert_eq!(diagnosis.line, "[hls @ 0x7f] Unable to open key file https://cdn.example.com/key.bin");
+
+        let diagnosis = diagnose("[https @ 0x7f] HTTP error 403 Forbidden\nhttps://x/index.m3u8: Server returned 403 Forbidden (access denied)").unwrap();
+        assert_eq!(diagnosis.kind, ErrorKind::Network);
+        assert!(!diagnosis.retryable);
+
+        let diagnosis = diagnose("https://x/seg1.ts: Server returned 5XX Server Error reply").unwrap();
+        assert!(diagnosis.retryable);
+
+        assert_eq!(diagnose("[tcp @ 0x1] Connection to tcp://x:443 failed: Connection refused").unwrap().summary, "Connection failed");
+        assert_eq!(diagnose("page.html: Invalid data found when processing input").unwrap().kind, ErrorKind::Parse);
+        assert_eq!(diagnose("Could not find tag for codec pcm_s16le in stream #1").unwrap().summary, "Unsupported codec");
+        assert_eq!(diagnose("Conversion failed!"), None);
+    }
+}
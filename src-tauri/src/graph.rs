@@ -0,0 +1,295 @@
+use futures::StreamExt;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::m3u8_parser::{self, M3u8Error, M3u8Parser, ParsedPlaylist};
+
+// Requests in flight while checking the nodes
+const CONCURRENCY: usize = 8;
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Segments listed per media playlist unless the caller asks for more; live
+// playlists list hundreds and the first few show their layout
+pub const DEFAULT_MAX_SEGMENTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Master,
+    // Media playlist given directly rather than through a master playlist
+    Media,
+    Variant,
+    Rendition,
+    Segment,
+    Key,
+    Map,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Node {
+    pub id: usize,
+    pub kind: NodeKind,
+    pub url: String,
+    // Short description: resolution and bandwidth of a variant, language of
+    // a rendition, duration of a segment, method of a key
+    pub label: String,
+    // HTTP status of the reachability check; None when unchecked or when no
+    // answer came, which `error` then explains
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    // Segments of a media playlist left out of the graph
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub omitted_segments: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+}
+
+// A playlist and everything it references, as nodes and edges. A resource
+// referenced from several playlists (a key shared by all variants) is one
+// node with several edges.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlaylistGraph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    #[serde(skip)]
+    ids: HashMap<(String, &'static str), usize>,
+}
+
+impl PlaylistGraph {
+    // The node for `url`, added if it is new, linked from `from`
+    fn add(&mut self, from: Option<usize>, kind: NodeKind, url: &str, label: String) -> usize {
+        // Variant and rendition playlists may share a URI; they stay one node
+        let key_kind = match kind {
+            NodeKind::Variant | NodeKind::Rendition | NodeKind::Media => "playlist",
+            NodeKind::Master => "master",
+            _ => "resource",
+        };
+        let id = match self.ids.get(&(url.to_string(), key_kind)) {
+            Some(&id) => id,
+            None => {
+                let id = self.nodes.len();
+                self.nodes.push(Node { id, kind, url: url.to_string(), label, status: None, error: None, omitted_segments: None });
+                self.ids.insert((url.to_string(), key_kind), id);
+                id
+            }
+        };
+        if let Some(from) = from {
+            if !self.edges.iter().any(|edge| edge.from == from && edge.to == id) {
+                self.edges.push(Edge { from, to: id });
+            }
+        }
+        id
+    }
+
+    fn set_result(&mut self, id: usize, status: Option<u16>, error: Option<String>) {
+        self.nodes[id].status = status;
+        self.nodes[id].error = error;
+    }
+
+    // Add the playlists a master playlist lists; returns their node ids
+    fn add_master(&mut self, id: usize, content: &str, url: &str) -> Vec<usize> {
+        let mut playlists = Vec::new();
+        if let Ok(ParsedPlaylist::Master { variants, .. }) = m3u8_parser::parse_playlist(content, url) {
+            for variant in variants {
+                let label = match &variant.resolution {
+                    Some(resolution) => format!("{} {} kbps", resolution, variant.bandwidth / 1000),
+                    None => format!("{} kbps", variant.bandwidth / 1000),
+                };
+                playlists.push(self.add(Some(id), NodeKind::Variant, &variant.uri, label));
+            }
+        }
+        for rendition in m3u8_parser::parse_renditions(content, url) {
+            if let Some(uri) = &rendition.uri {
+                let label = match &rendition.language {
+                    Some(language) => format!("{} {} ({})", rendition.media_type, rendition.name, language),
+                    None => format!("{} {}", rendition.media_type, rendition.name),
+                };
+                playlists.push(self.add(Some(id), NodeKind::Rendition, uri, label));
+            }
+        }
+        playlists.sort_unstable();
+        playlists.dedup();
+        playlists
+    }
+
+    // Add the first `max_segments` segments of a media playlist with their
+    // keys and init segments
+    fn add_media(&mut self, id: usize, content: &str, url: &str, max_segments: usize) {
+        let Ok(ParsedPlaylist::Media { segments, .. }) = m3u8_parser::parse_playlist(content, url) else {
+            return;
+        };
+        let keys = m3u8_parser::segment_keys(content, url);
+        for map in map_uris(content, url) {
+            self.add(Some(id), NodeKind::Map, &map, "init segment".to_string());
+        }
+        for (index, segment) in segments.iter().enumerate().take(max_segments) {
+            let segment_id = self.add(Some(id), NodeKind::Segment, &segment.uri, format!("{:.3}s", segment.duration));
+            if let Some(key) = keys.get(index).cloned().flatten() {
+                if let Some(uri) = &key.uri {
+                    self.add(Some(segment_id), NodeKind::Key, uri, key.method.clone());
+                }
+            }
+        }
+        if segments.len() > max_segments {
+            self.nodes[id].omitted_segments = Some(segments.len() - max_segments);
+        }
+    }
+}
+
+// URIs of the EXT-X-MAP tags of a media playlist, resolved
+fn map_uris(content: &str, base_url: &str) -> Vec<String> {
+    let mut uris: Vec<String> = content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("#EXT-X-MAP:"))
+        .filter_map(|list| list.split_once("URI=\"").and_then(|(_, rest)| rest.split_once('"')))
+        .map(|(uri, _)| m3u8_parser::resolve_uri(uri, base_url))
+        .collect();
+    uris.dedup();
+    uris
+}
+
+fn fetch_result(result: &Result<String, M3u8Error>) -> (Option<u16>, Option<String>) {
+    match result {
+        Ok(_) => (Some(200), None),
+        Err(M3u8Error::HttpStatus(code)) => (Some(*code), None),
+        Err(e) => (None, Some(e.to_string())),
+    }
+}
+
+// Status of a segment, key or init segment. Servers that refuse HEAD are
+// asked for the first byte instead.
+async fn check(client: &reqwest::Client, url: &str) -> (Option<u16>, Option<String>) {
+    let status = match client.head(url).send().await {
+        Ok(response) if response.status().as_u16() != 405 => return (Some(response.status().as_u16()), None),
+        Ok(_) => client.get(url).header("Range", "bytes=0-0").send().await,
+        Err(e) => Err(e),
+    };
+    match status {
+        Ok(response) => (Some(response.status().as_u16()), None),
+        Err(e) => (None, Some(e.to_string())),
+    }
+}
+
+// Graph of `url` and everything it references, down to the first
+// `max_segments` segments of each media playlist. Playlists are always
+// fetched, which gives their status; with `check_resources` segments, keys
+// and init segments get a HEAD request each.
+pub async fn build(
+    config: &AppConfig,
+    parser: &M3u8Parser,
+    url: &str,
+    max_segments: usize,
+    check_resources: bool,
+) -> Result<PlaylistGraph, AppError> {
+    let content = parser.fetch_playlist(url).await?;
+    let mut graph = PlaylistGraph::default();
+    let playlists = match parser.parse_content(&content, url)? {
+        ParsedPlaylist::Master { .. } => {
+            let root = graph.add(None, NodeKind::Master, url, "master playlist".to_string());
+            graph.set_result(root, Some(200), None);
+            graph.add_master(root, &content, url)
+        }
+        ParsedPlaylist::Media { .. } => {
+            let root = graph.add(None, NodeKind::Media, url, "media playlist".to_string());
+            graph.set_result(root, Some(200), None);
+            graph.add_media(root, &content, url, max_segments);
+            Vec::new()
+        }
+    };
+
+    let fetched: Vec<(usize, Result<String, M3u8Error>)> = futures::stream::iter(playlists)
+        .map(|id| {
+            let url = graph.nodes[id].url.clone();
+            async move { (id, parser.fetch_playlist(&url).await) }
+        })
+        .buffered(CONCURRENCY)
+        .collect()
+        .await;
+    for (id, result) in fetched {
+        let (status, error) = fetch_result(&result);
+        graph.set_result(id, status, error);
+        if let Ok(content) = result {
+            let url = graph.nodes[id].url.clone();
+            graph.add_media(id, &content, &url, max_segments);
+        }
+    }
+
+    if check_resources {
+        let client = crate::profiles::http_client(config, CHECK_TIMEOUT)?;
+        let resources: Vec<(usize, String)> = graph
+            .nodes
+            .iter()
+            .filter(|node| matches!(node.kind, NodeKind::Segment | NodeKind::Key | NodeKind::Map))
+            .map(|node| (node.id, node.url.clone()))
+            .collect();
+        let checked: Vec<(usize, (Option<u16>, Option<String>))> = futures::stream::iter(resources)
+            .map(|(id, url)| {
+                let client = &client;
+                async move { (id, check(client, &url).await) }
+            })
+            .buffer_unordered(CONCURRENCY)
+            .collect()
+            .await;
+        for (id, (status, error)) in checked {
+            graph.set_result(id, status, error);
+        }
+    }
+
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph_structure() {
+        let base = "https://example.com/live/";
+        let master = "#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud\",NAME=\"English\",LANGUAGE=\"en\",URI=\"audio.m3u8\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2500000,RESOLUTION=1280x720,AUDIO=\"aud\"\n\
+            720p.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,AUDIO=\"aud\"\n\
+            360p.m3u8\n";
+        let media = "#EXTM3U\n\
+            #EXT-X-TARGETDURATION:6\n\
+            #EXT-X-MAP:URI=\"init.mp4\"\n\
+            #EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"\n\
+            #EXTINF:6.0,\n\
+            seg0.m4s\n\
+            #EXTINF:6.0,\n\
+            seg1.m4s\n\
+            #EXTINF:6.0,\n\
+            seg2.m4s\n";
+
+        let mut graph = PlaylistGraph::default();
+        let root = graph.add(None, NodeKind::Master, &format!("{}master.m3u8", base), String::new());
+        let playlists = graph.add_master(root, master, &format!("{}master.m3u8", base));
+        assert_eq!(playlists.len(), 3);
+        assert_eq!(graph.nodes[playlists[0]].label, "1280x720 2500 kbps");
+        assert_eq!(graph.nodes[playlists[2]].kind, NodeKind::Rendition);
+
+        for &id in &playlists[..2] {
+            let url = graph.nodes[id].url.clone();
+            graph.add_media(id, media, &url, 2);
+        }
+        // Both variants share init segment and key but list segments of their own
+        let keys = graph.nodes.iter().filter(|node| node.kind == NodeKind::Key).count();
+        let maps = graph.nodes.iter().filter(|node| node.kind == NodeKind::Map).collect::<Vec<_>>();
+        assert_eq!(keys, 1);
+        assert_eq!(maps.len(), 1);
+        assert_eq!(maps[0].url, "https://example.com/live/init.mp4");
+        assert_eq!(graph.nodes.iter().filter(|node| node.kind == NodeKind::Segment).count(), 2);
+        assert_eq!(graph.nodes[playlists[0]].omitted_segments, Some(1));
+        assert_eq!(graph.edges.iter().filter(|edge| edge.to == maps[0].id).count(), 2);
+    }
+}
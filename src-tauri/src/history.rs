@@ -0,0 +1,218 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::canonical_url::canonicalize;
+use crate::config::{self, CanonicalUrlConfig};
+use crate::database::Database;
+use crate::error::AppError;
+
+// Number of URLs kept in the history
+const MAX_ENTRIES: usize = 20;
+
+fn history_path() -> Result<PathBuf, AppError> {
+    Ok(config::app_dir()?.join("url_history.json"))
+}
+
+fn write_history(history: &[Value]) -> Result<(), AppError> {
+    let config_dir = config::app_dir()?;
+    fs::create_dir_all(&config_dir)
+        .map_err(|e| AppError::io(format!("Failed to create config directory: {}", e)))?;
+    
+    let json_str = serde_json::to_string_pretty(history)
+        .map_err(|e| AppError::internal(format!("Failed to serialize history: {}", e)))?;
+    
+    fs::write(history_path()?, json_str)
+        .map_err(|e| AppError::io(format!("Failed to save history: {}", e)))
+}
+
+fn entry_url(item: &Value) -> Option<&str> {
+    item.get("url").and_then(|v| v.as_str())
+}
+
+// History entries ({ url, timestamp, title? }), most recent first
+pub fn load() -> Result<Vec<Value>, AppError> {
+    let history_path = history_path()?;
+    
+    if !history_path.exists() {
+        return Ok(Vec::new());
+    }
+    
+    let content = fs::read_to_string(history_path)
+        .map_err(|e| AppError::io(format!("Failed to read history: {}", e)))?;
+    
+    serde_json::from_str(&content)
+        .map_err(|e| AppError::internal(format!("Failed to parse history: {}", e)))
+}
+
+// URLs in the history, most recent first
+pub fn urls() -> Result<Vec<String>, AppError> {
+    Ok(load()?.iter().filter_map(entry_url).map(|s| s.to_string()).collect())
+}
+
+// Saved stream URLs: the history plus recorded downloads, without
+// duplicates by canonical URL
+pub fn saved_urls(db: &Database) -> Result<Vec<String>, AppError> {
+    let rules = canonical_rules();
+    let mut urls = urls()?;
+    let mut keys: Vec<String> = urls.iter().map(|url| canonicalize(url, &rules)).collect();
+    for download in db.list_all_downloads()? {
+        let key = canonicalize(&download.url, &rules);
+        if !keys.contains(&key) {
+            keys.push(key);
+            urls.push(download.url);
+        }
+    }
+    Ok(urls)
+}
+
+fn canonical_rules() -> CanonicalUrlConfig {
+    config::load_config().map(|c| c.canonical_urls).unwrap_or_default()
+}
+
+// Whether a history entry is for the same stream as the canonical `key`
+fn is_entry_for(item: &Value, key: &str, rules: &CanonicalUrlConfig) -> bool {
+    entry_url(item).is_some_and(|url| canonicalize(url, rules) == key)
+}
+
+// Move a URL to the top of the history. An entry for the same canonical
+// URL is replaced, so the latest URL as given is the one kept for fetching.
+pub fn add(url: &str) -> Result<(), AppError> {
+    // A corrupt history file is replaced rather than blocking new entries
+    let mut history = load().unwrap_or_default();
+    
+    // Check if URL already exists and remove it
+    let rules = canonical_rules();
+    let key = canonicalize(url, &rules);
+    history.retain(|item| !is_entry_for(item, &key, &rules));
+    
+    // Add new entry at the beginning
+    history.insert(0, json!({
+        "url": url,
+        "timestamp": chrono::Local::now().to_rfc3339(),
+    }));
+    history.truncate(MAX_ENTRIES);
+    
+    write_history(&history)
+}
+
+// Attach the stream's own title to its history entry
+pub fn set_title(url: &str, title: &str) -> Result<(), AppError> {
+    let mut history = load()?;
+    let rules = canonical_rules();
+    let key = canonicalize(url, &rules);
+    let Some(entry) = history.iter_mut().find(|item| is_entry_for(item, &key, &rules)) else {
+        return Ok(());
+    };
+    entry["title"] = json!(title);
+    write_history(&history)
+}
+
+// Drop the given URLs from the history; returns how many were removed
+pub fn remove(urls: &[String]) -> Result<usize, AppError> {
+    let mut history = load()?;
+    let before = history.len();
+    let rules = canonical_rules();
+    let keys: Vec<String> = urls.iter().map(|url| canonicalize(url, &rules)).collect();
+    history.retain(|item| !keys.iter().any(|key| is_entry_for(item, key, &rules)));
+    
+    let removed = before - history.len();
+    if removed > 0 {
+        write_history(&history)?;
+    }
+    Ok(removed)
+}
+
+// Entries recorded before `cutoff`; entries without a readable timestamp
+// are kept
+fn is_older_than(item: &Value, cutoff: chrono::DateTime<chrono::Utc>) -> bool {
+    timestamp(item).is_some_and(|t| t < cutoff)
+}
+
+// Drop entries older than `days`; returns how many were removed
+pub fn prune(days: u32) -> Result<usize, AppError> {
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(days as i64);
+    let mut history = load()?;
+    let before = history.len();
+    history.retain(|item| !is_older_than(item, cutoff));
+    
+    let removed = before - history.len();
+    if removed > 0 {
+        write_history(&history)?;
+    }
+    Ok(removed)
+}
+
+fn timestamp(item: &Value) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+    item.get("timestamp")
+        .and_then(|v| v.as_str())
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+}
+
+// `current` with the `restored` entries for streams it lacks, most recent
+// first and cut to the history's size
+fn merge(mut current: Vec<Value>, restored: &[Value], rules: &CanonicalUrlConfig) -> Vec<Value> {
+    for item in restored {
+        let Some(url) = entry_url(item) else {
+            continue;
+        };
+        let key = canonicalize(url, rules);
+        if !current.iter().any(|existing| is_entry_for(existing, &key, rules)) {
+            current.push(item.clone());
+        }
+    }
+    // Entries without a readable timestamp go last
+    current.sort_by_key(|item| std::cmp::Reverse(timestamp(item)));
+    current.truncate(MAX_ENTRIES);
+    current
+}
+
+// Put cleared entries back; entries added since for the same streams are
+// kept. Returns the number of entries afterwards.
+pub fn restore(entries: &[Value]) -> Result<usize, AppError> {
+    let history = merge(load().unwrap_or_default(), entries, &canonical_rules());
+    write_history(&history)?;
+    Ok(history.len())
+}
+
+pub fn clear() -> Result<(), AppError> {
+    if history_path()?.exists() {
+        fs::write(history_path()?, "[]")
+            .map_err(|e| AppError::io(format!("Failed to clear history: {}", e)))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_older_than() {
+        let cutoff = chrono::DateTime::parse_from_rfc3339("2024-05-01T00:00:00Z").unwrap().with_timezone(&chrono::Utc);
+        assert!(is_older_than(&json!({ "url": "a", "timestamp": "2024-04-30T23:00:00+00:00" }), cutoff));
+        // 2024-05-01T08:00:00+09:00 is 23:00 UTC the day before
+        assert!(is_older_than(&json!({ "url": "b", "timestamp": "2024-05-01T08:00:00+09:00" }), cutoff));
+        assert!(!is_older_than(&json!({ "url": "c", "timestamp": "2024-05-02T00:00:00+00:00" }), cutoff));
+        assert!(!is_older_than(&json!({ "url": "d" }), cutoff));
+    }
+
+    #[test]
+    fn test_merge() {
+        let rules = CanonicalUrlConfig::default();
+        let current = vec![json!({ "url": "https://a.example.com/new.m3u8", "timestamp": "2024-05-03T00:00:00+00:00" })];
+        let restored = vec![
+            json!({ "url": "https://a.example.com/new.m3u8", "timestamp": "2024-05-01T00:00:00+00:00" }),
+            json!({ "url": "https://b.example.com/old.m3u8", "timestamp": "2024-05-02T00:00:00+00:00" }),
+            json!({ "url": "https://c.example.com/undated.m3u8" }),
+        ];
+        let merged = merge(current, &restored, &rules);
+        let urls: Vec<&str> = merged.iter().filter_map(entry_url).collect();
+        assert_eq!(urls, vec![
+            "https://a.example.com/new.m3u8",
+            "https://b.example.com/old.m3u8",
+            "https://c.example.com/undated.m3u8",
+        ]);
+        assert_eq!(merged[0]["timestamp"], "2024-05-03T00:00:00+00:00");
+    }
+}
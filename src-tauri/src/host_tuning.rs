@@ -0,0 +1,197 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::Notify;
+
+use crate::config::DownloaderConfig;
+use crate::database::{self, HostStats};
+use crate::error::AppError;
+
+// Connection limits are shared by every download from the same host
+lazy_static::lazy_static! {
+    static ref LIMITERS: Mutex<HashMap<String, Arc<HostLimiter>>> = Mutex::new(HashMap::new());
+}
+
+// How a request ended, as far as tuning is concerned
+#[derive(Debug)]
+pub enum Outcome {
+    Success(u64),
+    // Timeouts, connection errors, 429 and 5xx: the host is overloaded
+    Congestion,
+    // Errors that say nothing about load, such as 404
+    Failure,
+}
+
+// Additive increase, multiplicative decrease: one more connection per
+// window's worth of successful requests, half as many after congestion
+pub fn next_window(window: f64, outcome: &Outcome, max: f64) -> f64 {
+    match outcome {
+        Outcome::Success(_) => (window + 1.0 / window.max(1.0)).min(max),
+        Outcome::Congestion => (window / 2.0).max(1.0),
+        Outcome::Failure => window,
+    }
+}
+
+struct LimiterState {
+    in_flight: usize,
+    // Counters and window, including what was loaded from the database
+    stats: HostStats,
+}
+
+pub struct HostLimiter {
+    state: Mutex<LimiterState>,
+    released: Notify,
+}
+
+// A request slot; returned when dropped
+pub struct HostPermit {
+    limiter: Arc<HostLimiter>,
+    started: Instant,
+    adaptive: bool,
+    max: f64,
+}
+
+impl HostLimiter {
+    // Wait for a free connection. Without `adaptive` the host is not limited,
+    // only observed.
+    pub async fn acquire(self: &Arc<Self>, config: &DownloaderConfig) -> HostPermit {
+        let adaptive = config.adaptive_concurrency;
+        loop {
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+            {
+                let mut state = self.state.lock().unwrap();
+                if !adaptive || (state.in_flight as f64) < state.stats.concurrency.floor().max(1.0) {
+                    state.in_flight += 1;
+                    return HostPermit {
+                        limiter: self.clone(),
+                        started: Instant::now(),
+                        adaptive,
+                        max: config.max_concurrency.max(1) as f64,
+                    };
+                }
+            }
+            released.await;
+        }
+    }
+
+    fn snapshot(&self) -> HostStats {
+        self.state.lock().unwrap().stats.clone()
+    }
+}
+
+impl HostPermit {
+    pub fn finish(self, outcome: Outcome) {
+        let mut state = self.limiter.state.lock().unwrap();
+        let stats = &mut state.stats;
+        stats.requests += 1;
+        stats.fetch_seconds += self.started.elapsed().as_secs_f64();
+        match outcome {
+            Outcome::Success(bytes) => stats.bytes += bytes as i64,
+            _ => stats.errors += 1,
+        }
+        if self.adaptive {
+            stats.concurrency = next_window(stats.concurrency, &outcome, self.max);
+        }
+    }
+}
+
+impl Drop for HostPermit {
+    fn drop(&mut self) {
+        self.limiter.state.lock().unwrap().in_flight -= 1;
+        self.limiter.released.notify_waiters();
+    }
+}
+
+// The limiter of a host, starting from its learned limit, or from
+// `downloader.concurrency` for hosts not seen before
+pub async fn limiter(host: &str, config: &DownloaderConfig) -> Arc<HostLimiter> {
+    if let Some(limiter) = LIMITERS.lock().unwrap().get(host) {
+        return limiter.clone();
+    }
+    let learned = match database::GLOBAL_DB.read().await.clone() {
+        Some(db) => db.get_host_stats(Some(host)).ok().and_then(|stats| stats.into_iter().next()),
+        None => None,
+    };
+    let stats = learned.unwrap_or_else(|| HostStats {
+        host: host.to_string(),
+        concurrency: config.concurrency.max(1) as f64,
+        ..HostStats::default()
+    });
+    LIMITERS.lock().unwrap()
+        .entry(host.to_string())
+        .or_insert_with(|| Arc::new(HostLimiter {
+            state: Mutex::new(LimiterState { in_flight: 0, stats }),
+            released: Notify::new(),
+        }))
+        .clone()
+}
+
+// Persist what has been learned so far
+pub async fn save() {
+    let snapshots: Vec<HostStats> = LIMITERS.lock().unwrap()
+        .values()
+        .map(|limiter| limiter.snapshot())
+        .collect();
+    let Some(db) = database::GLOBAL_DB.read().await.clone() else {
+        return;
+    };
+    for stats in snapshots {
+        if let Err(e) = db.save_host_stats(&stats) {
+            eprintln!("Failed to save statistics for {}: {}", stats.host, e);
+        }
+    }
+}
+
+// Per-host statistics with derived rates
+#[derive(Debug, Serialize)]
+pub struct HostReport {
+    #[serde(flatten)]
+    pub stats: HostStats,
+    pub error_rate: f64,
+    // Average throughput of a single connection
+    pub bytes_per_second: f64,
+}
+
+// Saved statistics, with the live values of hosts in use
+pub async fn report() -> Result<Vec<HostReport>, AppError> {
+    let mut stats: HashMap<String, HostStats> = database::global_db().await?
+        .get_host_stats(None)?
+        .into_iter()
+        .map(|stats| (stats.host.clone(), stats))
+        .collect();
+    for (host, limiter) in LIMITERS.lock().unwrap().iter() {
+        stats.insert(host.clone(), limiter.snapshot());
+    }
+    let mut reports: Vec<HostReport> = stats.into_values()
+        .map(|stats| HostReport {
+            error_rate: if stats.requests > 0 { stats.errors as f64 / stats.requests as f64 } else { 0.0 },
+            bytes_per_second: if stats.fetch_seconds > 0.0 { stats.bytes as f64 / stats.fetch_seconds } else { 0.0 },
+            stats,
+        })
+        .collect();
+    reports.sort_by(|a, b| a.stats.host.cmp(&b.stats.host));
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_window() {
+        // Four successes at a window of 4 add one connection
+        let mut window = 4.0;
+        for _ in 0..4 {
+            window = next_window(window, &Outcome::Success(1000), 16.0);
+        }
+        assert!((window - 4.9).abs() < 0.1);
+
+        assert_eq!(next_window(window, &Outcome::Failure, 16.0), window);
+        assert!((next_window(window, &Outcome::Congestion, 16.0) - window / 2.0).abs() < 1e-9);
+        assert_eq!(next_window(1.5, &Outcome::Congestion, 16.0), 1.0);
+        assert_eq!(next_window(16.0, &Outcome::Success(1), 16.0), 16.0);
+    }
+}
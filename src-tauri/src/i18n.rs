@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::sync::RwLock;
+
+// Locale for user-facing messages; matches the frontend's language codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Ja,
+}
+
+static LOCALE: RwLock<Locale> = RwLock::new(Locale::En);
+
+pub fn set_locale(locale: Locale) {
+    *LOCALE.write().unwrap() = locale;
+}
+
+pub fn current_locale() -> Locale {
+    *LOCALE.read().unwrap()
+}
+
+// User-facing status and error messages sent in events, command results and
+// MCP text content. Log lines stay in English.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    DownloadInitializing,
+    DownloadStarting,
+    DownloadCancelled,
+    DownloadCancelledByUser,
+    DownloadCompleted,
+    DownloadFailed,
+    DownloadRetrying,
+    DownloadedTo,
+    NoDownloadInProgress,
+    FfmpegNotInstalled,
+    ServerStarted,
+    ServerStartedLan,
+    ServerStopped,
+    ServerNotRunning,
+    DatabaseInitialized,
+    DatabaseNotInitialized,
+    CacheCleared,
+    ProjectDeleted,
+}
+
+// Message template for a locale; `{}` placeholders are filled in order
+pub fn template(locale: Locale, msg: Msg) -> &'static str {
+    match (locale, msg) {
+        (Locale::En, Msg::DownloadInitializing) => "Initializing download...",
+        (Locale::Ja, Msg::DownloadInitializing) => "ダウンロードを準備しています...",
+        (Locale::En, Msg::DownloadStarting) => "Starting download...",
+        (Locale::Ja, Msg::DownloadStarting) => "ダウンロードを開始しています...",
+        (Locale::En, Msg::DownloadCancelled) => "Download cancelled",
+        (Locale::Ja, Msg::DownloadCancelled) => "ダウンロードをキャンセルしました",
+        (Locale::En, Msg::DownloadCancelledByUser) => "Download cancelled by user",
+        (Locale::Ja, Msg::DownloadCancelledByUser) => "ユーザーがダウンロードをキャンセルしました",
+        (Locale::En, Msg::DownloadCompleted) => "Download completed: {}",
+        (Locale::Ja, Msg::DownloadCompleted) => "ダウンロードが完了しました: {}",
+        (Locale::En, Msg::DownloadFailed) => "FFmpeg download failed: {}",
+        (Locale::Ja, Msg::DownloadFailed) => "FFmpeg によるダウンロードに失敗しました: {}",
+        (Locale::En, Msg::DownloadRetrying) => "Attempt {} of {} failed, retrying in {}s: {}",
+        (Locale::Ja, Msg::DownloadRetrying) => "{} / {} 回目の試行に失敗しました。{} 秒後に再試行します: {}",
+        (Locale::En, Msg::DownloadedTo) => "Downloaded to: {}",
+        (Locale::Ja, Msg::DownloadedTo) => "保存先: {}",
+        (Locale::En, Msg::NoDownloadInProgress) => "No download in progress",
+        (Locale::Ja, Msg::NoDownloadInProgress) => "実行中のダウンロードはありません",
+        (Locale::En, Msg::FfmpegNotInstalled) => "FFmpeg is not installed or not in PATH",
+        (Locale::Ja, Msg::FfmpegNotInstalled) => "FFmpeg がインストールされていないか、PATH にありません",
+        (Locale::En, Msg::ServerStarted) => "MCP Server started on port {}",
+        (Locale::Ja, Msg::ServerStarted) => "MCP サーバーをポート {} で起動しました",
+        (Locale::En, Msg::ServerStartedLan) => "MCP Server started on {}:{}. Warning: the server has no authentication and is reachable from other machines on the network.",
+        (Locale::Ja, Msg::ServerStartedLan) => "MCP サーバーを {}:{} で起動しました。警告: サーバーには認証がなく、ネットワーク上の他のマシンからアクセスできます。",
+        (Locale::En, Msg::ServerStopped) => "MCP Server stopped",
+        (Locale::Ja, Msg::ServerStopped) => "MCP サーバーを停止しました",
+        (Locale::En, Msg::ServerNotRunning) => "Server is not running",
+        (Locale::Ja, Msg::ServerNotRunning) => "サーバーは起動していません",
+        (Locale::En, Msg::DatabaseInitialized) => "Database initialized at: {}",
+        (Locale::Ja, Msg::DatabaseInitialized) => "データベースを初期化しました: {}",
+        (Locale::En, Msg::DatabaseNotInitialized) => "Database not initialized",
+        (Locale::Ja, Msg::DatabaseNotInitialized) => "データベースが初期化されていません",
+        (Locale::En, Msg::CacheCleared) => "Cache cleared successfully",
+        (Locale::Ja, Msg::CacheCleared) => "キャッシュを削除しました",
+        (Locale::En, Msg::ProjectDeleted) => "Project {} deleted",
+        (Locale::Ja, Msg::ProjectDeleted) => "プロジェクト {} を削除しました",
+    }
+}
+
+// Fill `{}` placeholders in order; extra placeholders are left empty
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+    if let Some(first) = parts.next() {
+        out.push_str(first);
+    }
+    for part in parts {
+        if let Some(arg) = args.next() {
+            out.push_str(&arg.to_string());
+        }
+        out.push_str(part);
+    }
+    out
+}
+
+// Message in the current locale
+pub fn t(msg: Msg) -> String {
+    template(current_locale(), msg).to_string()
+}
+
+// Message in the current locale with `{}` placeholders filled
+pub fn tf(msg: Msg, args: &[&dyn Display]) -> String {
+    fill(template(current_locale(), msg), args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(fill(template(Locale::En, Msg::ServerStarted), &[&37650]), "MCP Server started on port 37650");
+        assert_eq!(fill(template(Locale::Ja, Msg::ProjectDeleted), &[&3]), "プロジェクト 3 を削除しました");
+        assert_eq!(fill("{}:{}", &[&"localhost"]), "localhost:");
+    }
+}
@@ -0,0 +1,286 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::chapters::seconds_between;
+use crate::config::{self, InterstitialMode};
+use crate::error::{AppError, ErrorKind};
+use crate::ffmpeg_wrapper::FFmpegService;
+use crate::m3u8_parser::{self, Interstitial, M3u8Parser, ParsedPlaylist};
+
+const ASSET_LIST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// An interstitial with its place on the timeline of the primary content
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledInterstitial {
+    #[serde(flatten)]
+    pub interstitial: Interstitial,
+    // Seconds from the start of the primary content: 0 for pre-rolls, None
+    // for post-rolls and when the playlist has no EXT-X-PROGRAM-DATE-TIME
+    pub position: Option<f64>,
+}
+
+// A run of interstitial assets to splice into the primary content
+#[derive(Debug, Clone, PartialEq)]
+pub struct Break {
+    pub position: f64,
+    // Seconds of primary content the break replaces
+    pub resume_offset: f64,
+    // Asset files in play order, each with the point to stop at when
+    // X-PLAYOUT-LIMIT cuts it short
+    pub assets: Vec<(PathBuf, Option<f64>)>,
+}
+
+fn position(interstitial: &Interstitial, program_date_time: Option<&str>) -> Option<f64> {
+    if interstitial.is_pre_roll() {
+        return Some(0.0);
+    }
+    if interstitial.is_post_roll() {
+        return None;
+    }
+    seconds_between(program_date_time?, &interstitial.start_date).map(|seconds| seconds.max(0.0))
+}
+
+// Interstitials of a stream, in playlist order. For a master playlist the
+// first variant's media playlist is read, since date ranges live there.
+pub async fn list(parser: &M3u8Parser, url: &str) -> Result<Vec<ScheduledInterstitial>, AppError> {
+    let mut media_url = url.to_string();
+    let mut content = parser.fetch_playlist(url).await?;
+    if let ParsedPlaylist::Master { variants, .. } = parser.parse_content(&content, url)? {
+        let variant = variants.first()
+            .ok_or_else(|| AppError::invalid_input("Master playlist has no variants"))?;
+        media_url = variant.uri.clone();
+        content = parser.fetch_playlist(&media_url).await?;
+    }
+
+    let program_date_time = m3u8_parser::first_program_date_time(&content);
+    Ok(m3u8_parser::parse_interstitials(&content, &media_url)
+        .into_iter()
+        .map(|interstitial| ScheduledInterstitial {
+            position: position(&interstitial, program_date_time.as_deref()),
+            interstitial,
+        })
+        .collect())
+}
+
+// Asset URIs of an X-ASSET-LIST document ({"ASSETS": [{"URI": ..., "DURATION": ...}]})
+pub fn asset_list_uris(list: &serde_json::Value, list_url: &str) -> Vec<String> {
+    list.get("ASSETS")
+        .and_then(|assets| assets.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|asset| asset.get("URI").and_then(|uri| uri.as_str()))
+        .map(|uri| m3u8_parser::resolve_uri(uri, list_url))
+        .collect()
+}
+
+async fn asset_uris(interstitial: &Interstitial) -> Result<Vec<String>, AppError> {
+    if let Some(uri) = &interstitial.asset_uri {
+        return Ok(vec![uri.clone()]);
+    }
+    let Some(list_url) = &interstitial.asset_list else {
+        return Ok(Vec::new());
+    };
+    let app_config = config::load_config().unwrap_or_default().effective();
+    let client = crate::profiles::http_client(&app_config, ASSET_LIST_TIMEOUT)?;
+    let fetch_error = |e: reqwest::Error| AppError::new(ErrorKind::Network, format!("Failed to fetch {}: {}", list_url, e));
+    let list: serde_json::Value = client.get(list_url).send().await
+        .and_then(|response| response.error_for_status())
+        .map_err(fetch_error)?
+        .json()
+        .await
+        .map_err(fetch_error)?;
+    Ok(asset_list_uris(&list, list_url))
+}
+
+// File of an interstitial's asset next to `output`:
+// <name>.interstitial-<id>.<ext>, numbered when there are several assets
+pub fn asset_path(output: &Path, id: &str, index: usize, count: usize) -> PathBuf {
+    let stem = output.file_stem().and_then(|s| s.to_str()).unwrap_or("download");
+    let extension = output.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+    let name = if count > 1 {
+        format!("{}.interstitial-{}-{}.{}", stem, id, index + 1, extension)
+    } else {
+        format!("{}.interstitial-{}.{}", stem, id, extension)
+    };
+    output.with_file_name(crate::paths::sanitize_filename(&name))
+}
+
+fn concat_entry(path: &Path, inpoint: Option<f64>, outpoint: Option<f64>) -> String {
+    let mut entry = format!("file '{}'\n", path.to_string_lossy().replace('\'', "'\\''"));
+    if let Some(inpoint) = inpoint.filter(|seconds| *seconds > 0.0) {
+        entry.push_str(&format!("inpoint {:.3}\n", inpoint));
+    }
+    if let Some(outpoint) = outpoint {
+        entry.push_str(&format!("outpoint {:.3}\n", outpoint));
+    }
+    entry
+}
+
+// FFmpeg concat list playing `primary` (`duration` seconds long) with the
+// breaks spliced in: the primary content is cut at each break and resumes
+// the break's resume offset later
+pub fn concat_list(primary: &Path, duration: f64, breaks: &[Break]) -> String {
+    let mut breaks: Vec<&Break> = breaks.iter().collect();
+    breaks.sort_by(|a, b| a.position.total_cmp(&b.position));
+
+    let mut list = String::from("ffconcat version 1.0\n");
+    let mut cursor = 0.0;
+    for brk in breaks {
+        let position = brk.position.min(duration);
+        if position > cursor {
+            list.push_str(&concat_entry(primary, Some(cursor), Some(position)));
+        }
+        for (asset, outpoint) in &brk.assets {
+            list.push_str(&concat_entry(asset, None, *outpoint));
+        }
+        cursor = f64::max(cursor, position + brk.resume_offset);
+    }
+    if cursor < duration {
+        list.push_str(&concat_entry(primary, Some(cursor), None));
+    }
+    list
+}
+
+// Replace `output` with a copy that has the breaks spliced in. Streams are
+// copied, so the assets need the codecs of the primary content, and cuts
+// land on the keyframes nearest to them.
+pub async fn splice(ffmpeg: &FFmpegService, output: &Path, breaks: &[Break]) -> Result<(), AppError> {
+    let duration = ffmpeg.probe_duration(output).await?;
+    let list_path = std::env::temp_dir().join(format!("m3u8-mcp-{}.ffconcat", uuid::Uuid::new_v4()));
+    std::fs::write(&list_path, concat_list(output, duration, breaks))?;
+    let extension = output.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+    let temp_output = output.with_extension(format!("interstitials.{}", extension));
+
+    let args: Vec<String> = vec![
+        "-y".to_string(),
+        "-f".to_string(),
+        "concat".to_string(),
+        "-safe".to_string(),
+        "0".to_string(),
+        "-i".to_string(),
+        list_path.to_string_lossy().to_string(),
+        "-map".to_string(),
+        "0".to_string(),
+        "-c".to_string(),
+        "copy".to_string(),
+        temp_output.to_string_lossy().to_string(),
+    ];
+    let result = ffmpeg.run_ffmpeg(&args).await;
+    let _ = std::fs::remove_file(&list_path);
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temp_output);
+        return Err(e.into());
+    }
+    std::fs::rename(&temp_output, output)?;
+    Ok(())
+}
+
+// Handle the interstitials of the stream downloaded to `output` as `mode`
+// says. Returns the asset files left next to the output: all of them when
+// kept separately, and those that could not be placed when included.
+pub async fn process(
+    parser: &M3u8Parser,
+    ffmpeg: &FFmpegService,
+    url: &str,
+    output: &Path,
+    mode: InterstitialMode,
+) -> Result<Vec<PathBuf>, AppError> {
+    if mode == InterstitialMode::Skip {
+        return Ok(Vec::new());
+    }
+
+    let mut kept = Vec::new();
+    let mut breaks = Vec::new();
+    for scheduled in list(parser, url).await? {
+        let interstitial = &scheduled.interstitial;
+        let uris = asset_uris(interstitial).await?;
+        let mut assets = Vec::with_capacity(uris.len());
+        for (index, uri) in uris.iter().enumerate() {
+            let path = asset_path(output, &interstitial.id, index, uris.len());
+            ffmpeg.download_extra(uri, &path).await?;
+            let duration = ffmpeg.probe_duration(&path).await.unwrap_or(0.0);
+            assets.push((path, duration));
+        }
+
+        let position = match scheduled.position {
+            Some(position) => Some(position),
+            None if interstitial.is_post_roll() => Some(f64::INFINITY),
+            None => None,
+        };
+        match position {
+            Some(position) if mode == InterstitialMode::Include && !assets.is_empty() => {
+                let mut remaining = interstitial.playout_limit.unwrap_or(f64::INFINITY);
+                let mut played = 0.0;
+                let mut spliced = Vec::new();
+                for (path, duration) in assets {
+                    if remaining <= 0.0 {
+                        let _ = std::fs::remove_file(&path);
+                        continue;
+                    }
+                    spliced.push((path, (duration > remaining).then_some(remaining)));
+                    played += duration.min(remaining);
+                    remaining -= duration;
+                }
+                breaks.push(Break {
+                    position,
+                    resume_offset: interstitial.resume_offset.unwrap_or(played),
+                    assets: spliced,
+                });
+            }
+            _ => kept.extend(assets.into_iter().map(|(path, _)| path)),
+        }
+    }
+
+    if breaks.is_empty() {
+        return Ok(kept);
+    }
+    let spliced = splice(ffmpeg, output, &breaks).await;
+    for (path, _) in breaks.into_iter().flat_map(|brk| brk.assets) {
+        match &spliced {
+            Ok(()) => {
+                let _ = std::fs::remove_file(&path);
+            }
+            Err(_) => kept.push(path),
+        }
+    }
+    if let Err(e) = spliced {
+        eprintln!("Failed to splice interstitials into {}: {}", output.display(), e);
+    }
+    Ok(kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concat_list() {
+        let primary = Path::new("/videos/show.mp4");
+        let breaks = [
+            Break {
+                position: 30.0,
+                resume_offset: 15.0,
+                assets: vec![(asset_path(primary, "ad1", 0, 2), None), (asset_path(primary, "ad1", 1, 2), Some(5.0))],
+            },
+            Break {
+                position: 0.0,
+                resume_offset: 0.0,
+                assets: vec![(asset_path(primary, "pre", 0, 1), None)],
+            },
+        ];
+        assert_eq!(
+            concat_list(primary, 60.0, &breaks),
+            "ffconcat version 1.0\n\
+             file '/videos/show.interstitial-pre.mp4'\n\
+             file '/videos/show.mp4'\noutpoint 30.000\n\
+             file '/videos/show.interstitial-ad1-1.mp4'\n\
+             file '/videos/show.interstitial-ad1-2.mp4'\noutpoint 5.000\n\
+             file '/videos/show.mp4'\ninpoint 45.000\n"
+        );
+
+        let list = serde_json::json!({ "ASSETS": [{ "URI": "ad.m3u8", "DURATION": 10.0 }, { "DURATION": 5.0 }] });
+        assert_eq!(asset_list_uris(&list, "https://ads.example.com/list.json"), vec!["https://ads.example.com/ad.m3u8"]);
+    }
+}
@@ -0,0 +1,120 @@
+use chrono::{SecondsFormat, Utc};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::config;
+use crate::error::AppError;
+
+// Per-job log files in ~/.m3u8-mcp/logs: FFmpeg's stderr as it ran, or the
+// native downloader's and the job runner's own messages
+
+// Lines of the log included in failure events
+pub const TAIL_LINES: usize = 20;
+
+// Job ids are UUIDs; anything else is refused so it cannot name another file
+fn valid_job_id(job_id: &str) -> bool {
+    !job_id.is_empty() && job_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub fn log_path(job_id: &str) -> Result<PathBuf, AppError> {
+    if !valid_job_id(job_id) {
+        return Err(AppError::invalid_input(format!("Invalid job id: {}", job_id)));
+    }
+    Ok(config::app_dir()?.join("logs").join(format!("{}.log", job_id)))
+}
+
+// An open job log. Failing to write it never fails the job.
+pub struct JobLog {
+    file: Option<File>,
+}
+
+impl JobLog {
+    pub fn open(job_id: &str) -> Self {
+        let file = log_path(job_id).and_then(|path| {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            Ok(OpenOptions::new().create(true).append(true).open(path)?)
+        });
+        match file {
+            Ok(file) => Self { file: Some(file) },
+            Err(e) => {
+                eprintln!("Failed to open the log of job {}: {}", job_id, e);
+                Self { file: None }
+            }
+        }
+    }
+
+    pub fn line(&mut self, line: &str) {
+        if let Some(file) = &mut self.file {
+            if writeln!(file, "{}", line).is_err() {
+                self.file = None;
+            }
+        }
+    }
+}
+
+// Append a timestamped message to a job's log
+pub fn note(job_id: &str, message: &str) {
+    let time = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    JobLog::open(job_id).line(&format!("[{}] {}", time, message));
+}
+
+pub fn read(job_id: &str) -> Result<String, AppError> {
+    let path = log_path(job_id)?;
+    if !path.exists() {
+        return Err(AppError::not_found(format!("No log for job {}", job_id)));
+    }
+    Ok(fs::read_to_string(path)?)
+}
+
+// The last `count` non-empty lines of `text`
+pub fn last_lines(text: &str, count: usize) -> Vec<String> {
+    let mut lines: Vec<String> = text.lines()
+        .rev()
+        .filter(|line| !line.trim().is_empty())
+        .take(count)
+        .map(str::to_string)
+        .collect();
+    lines.reverse();
+    lines
+}
+
+// End of a job's log for failure events; empty when there is none
+pub fn tail(job_id: &str) -> Vec<String> {
+    read(job_id).map(|text| last_lines(&text, TAIL_LINES)).unwrap_or_default()
+}
+
+// Jobs with a log, most recently written first
+pub fn recent_jobs(limit: usize) -> Vec<String> {
+    let Ok(entries) = config::app_dir().and_then(|dir| Ok(fs::read_dir(dir.join("logs"))?)) else {
+        return Vec::new();
+    };
+    let mut logs: Vec<(std::time::SystemTime, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let job_id = path.file_name()?.to_str()?.strip_suffix(".log")?.to_string();
+            let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+            Some((modified, job_id))
+        })
+        .collect();
+    logs.sort_by(|a, b| b.0.cmp(&a.0));
+    logs.into_iter().take(limit).map(|(_, job_id)| job_id).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_lines() {
+        let text = "one\ntwo\n\nthree\nfour\n";
+        assert_eq!(last_lines(text, 2), vec!["three", "four"]);
+        assert_eq!(last_lines(text, 10), vec!["one", "two", "three", "four"]);
+        assert!(valid_job_id("0f8fad5b-d9cb-469f-a165-70867728950e"));
+        assert!(!valid_job_id("../config"));
+        assert!(!valid_job_id(""));
+    }
+}
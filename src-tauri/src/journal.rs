@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::config::{self, AudioTracks, DownloadEngine, InterstitialMode, RetryPolicy};
+use crate::database::{self, Database, JournalEntry};
+use crate::error::AppError;
+use crate::events::{self, JobEvent, JobUpdate};
+use crate::ffmpeg_wrapper::FFmpegService;
+use crate::i18n::{self, Msg};
+use crate::job_log;
+use crate::m3u8_parser::M3u8Parser;
+
+// Everything needed to start a download again
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobSpec {
+    pub url: String,
+    // File or directory, already resolved from the project if there was one
+    pub output_path: Option<PathBuf>,
+    pub page_url: Option<String>,
+    pub project_id: Option<i32>,
+    pub engine: Option<DownloadEngine>,
+    pub mirrors: Vec<String>,
+    // Overrides downloader.retry from the config
+    pub retry: Option<RetryPolicy>,
+    // Overrides downloader.audio from the config
+    pub audio: Option<AudioTracks>,
+    // Overrides downloader.interstitials from the config
+    pub interstitials: Option<InterstitialMode>,
+}
+
+async fn set_status(job_id: &str, status: &str, error: Option<&str>, output_path: Option<&str>) {
+    let db = database::GLOBAL_DB.read().await.clone();
+    if let Some(db) = db {
+        if let Err(e) = db.set_job_status(job_id, status, error, output_path) {
+            eprintln!("Failed to update job journal: {}", e);
+        }
+    }
+}
+
+fn is_cancelled(error: &AppError) -> bool {
+    error.message.contains(&i18n::t(Msg::DownloadCancelled))
+}
+
+// Run a journaled download and record the result like any other download.
+// Failures the retry policy covers are retried after a growing delay.
+// Without a database the download still runs, it just cannot be resumed.
+pub async fn run(
+    parser: Arc<M3u8Parser>,
+    ffmpeg: &Arc<FFmpegService>,
+    job_id: &str,
+    spec: &JobSpec,
+) -> Result<PathBuf, AppError> {
+    let db = database::GLOBAL_DB.read().await.clone();
+    if let Some(db) = db {
+        let journaled = serde_json::to_string(spec)
+            .map_err(|e| AppError::internal(e.to_string()))
+            .and_then(|json| db.journal_job(job_id, &json).map_err(AppError::from));
+        if let Err(e) = journaled {
+            eprintln!("Failed to journal job {}: {}", job_id, e);
+        }
+    }
+
+    let downloader = config::load_config().map(|c| c.downloader).unwrap_or_default();
+
+    // The manifest as it looked when the download started
+    let content = match parser.fetch_playlist(&spec.url).await {
+        Ok(content) => Some(content),
+        Err(e) => {
+            eprintln!("Failed to fetch {} before downloading: {}", spec.url, e);
+            None
+        }
+    };
+
+    // Name the file after the hosting page when we know it, else after the
+    // title in the manifest's session data. The first segment's ID3 tags
+    // are only read when the config asks for it, so the download need not
+    // wait for them.
+    let title = match spec.page_url.as_deref() {
+        Some(page_url) => crate::page_title::fetch_page_title(page_url).await,
+        None => None,
+    };
+    let title = match (title, &content) {
+        (Some(title), _) => Some(title),
+        (None, Some(content)) => {
+            crate::stream_info::content_title(&parser, &spec.url, content, downloader.id3_title).await
+        }
+        (None, None) => None,
+    };
+
+    // Archive the manifest as fetched above
+    let fetched = content.map(|content| HashMap::from([(spec.url.clone(), content)])).unwrap_or_default();
+    crate::snapshots::spawn_capture(parser.clone(), &spec.url, "download", fetched);
+
+    // Downloads without an output path are sorted into subfolders
+    let output_path = match &spec.output_path {
+        Some(path) => Some(path.clone()),
+        None => {
+            let rules = config::load_config().map(|c| c.downloader.organize).unwrap_or_default();
+            let base = ffmpeg.config().default_output_dir;
+            crate::organize::output_dir(&rules, &base, &spec.url, spec.project_id)
+                .await
+                .unwrap_or_else(|e| {
+                    eprintln!("Saving job {} without organizing: {}", job_id, e);
+                    None
+                })
+        }
+    };
+
+    let fingerprint = crate::duplicates::playlist_fingerprint(&parser, &spec.url).await;
+
+    // Audio and subtitle renditions by the preferred languages. The audio
+    // choice decides which track FFmpeg keeps unless the job or the config
+    // already names languages; the native engine only fetches the variant's
+    // own media, whose audio carries no language to select by.
+    let renditions = crate::renditions::select(&parser, &spec.url, &downloader.preferred_languages)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Choosing renditions for job {} failed: {}", job_id, e);
+            None
+        });
+    let mut download_spec = spec.clone();
+    if let Some(choices) = &renditions {
+        let summary = serde_json::to_value(choices).unwrap_or_default();
+        job_log::note(job_id, &format!("Renditions: {}", summary));
+        if let Some(db) = database::GLOBAL_DB.read().await.clone() {
+            if let Err(e) = db.set_job_renditions(job_id, &summary) {
+                eprintln!("Failed to record renditions of job {}: {}", job_id, e);
+            }
+        }
+        let default_engine = if spec.mirrors.is_empty() { downloader.engine } else { DownloadEngine::Native };
+        let by_ffmpeg = spec.engine.unwrap_or(default_engine) == DownloadEngine::Ffmpeg;
+        let mut audio = spec.audio.clone().unwrap_or_else(|| downloader.audio.clone());
+        if let Some(language) = choices.audio.as_ref().and_then(|choice| choice.language.clone()) {
+            if by_ffmpeg && audio.languages.is_empty() {
+                audio.languages = vec![language];
+                download_spec.audio = Some(audio);
+            }
+        }
+    }
+
+    let policy = match &spec.retry {
+        Some(policy) => policy.clone(),
+        None => config::load_config().map(|c| c.downloader.retry).unwrap_or_default(),
+    };
+    let started = std::time::Instant::now();
+    let mut attempt = 1;
+    let result = loop {
+        job_log::note(job_id, &format!("Attempt {} of {}: downloading {}", attempt, policy.max_attempts, spec.url));
+        let result = crate::native_download::run_download(
+            &parser,
+            ffmpeg,
+            job_id,
+            &download_spec,
+            output_path.as_deref(),
+            title.as_deref(),
+        ).await;
+        let error = match &result {
+            Err(e) if !is_cancelled(e) && policy.should_retry(attempt, e) => e,
+            _ => break result,
+        };
+
+        let delay = policy.delay(attempt);
+        let message = format!(
+            "Attempt {}/{} failed ({:?}): {}; retrying in {:.1}s",
+            attempt, policy.max_attempts, error.kind, error, delay.as_secs_f64()
+        );
+        println!("Job {} {}", job_id, message);
+        job_log::note(job_id, &message);
+        ffmpeg.emit(events::DOWNLOAD_PROGRESS, JobEvent::new(
+            job_id,
+            i18n::tf(Msg::DownloadRetrying, &[&attempt, &policy.max_attempts, &delay.as_secs(), &error]),
+            JobUpdate::Retrying {
+                attempt,
+                max_attempts: policy.max_attempts,
+                delay_seconds: delay.as_secs_f64(),
+                error: error.clone(),
+            },
+        ));
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    };
+
+    // Interstitial content goes into the output before it is measured and
+    // recorded; a failure leaves the primary download as it is
+    if let Ok(path) = &result {
+        let mode = spec.interstitials.unwrap_or(downloader.interstitials);
+        match crate::interstitials::process(&parser, ffmpeg, &spec.url, path, mode).await {
+            Ok(files) => {
+                for file in files {
+                    job_log::note(job_id, &format!("Interstitial: {}", file.display()));
+                    crate::artifacts::record(job_id, "interstitial", &file).await;
+                }
+            }
+            Err(e) => job_log::note(job_id, &format!("Failed to process interstitials: {}", e)),
+        }
+    }
+
+    match &result {
+        Ok(path) => {
+            let duration = ffmpeg.probe_duration(path).await.ok();
+            database::record_download(database::NewDownload {
+                url: &spec.url,
+                output_path: path,
+                title: title.as_deref(),
+                project_id: spec.project_id,
+                elapsed_seconds: Some(started.elapsed().as_secs_f64()),
+                duration,
+                verified: Some(duration.is_some_and(|d| d > 0.0)),
+                content_hash: fingerprint.as_deref(),
+            }).await;
+            crate::artifacts::record_download(job_id, path).await;
+            crate::transcription::spawn_auto_transcribe(ffmpeg.clone(), path.clone());
+            if let Some(subtitles) = renditions.as_ref().and_then(|choices| choices.subtitles.as_ref()) {
+                match crate::renditions::save_subtitles(&parser, subtitles, path).await {
+                    Ok(file) => {
+                        job_log::note(job_id, &format!("Subtitles: {}", file.display()));
+                        crate::artifacts::record(job_id, "subtitles", &file).await;
+                    }
+                    Err(e) => job_log::note(job_id, &format!("Failed to save subtitles: {}", e)),
+                }
+            }
+            for gap in crate::native_download::recording_gaps(path) {
+                job_log::note(job_id, &format!(
+                    "Permanent gap at {:.1}s{}: {} segment(s) from sequence {} ({:.1}s missing)",
+                    gap.position,
+                    gap.program_date_time.map(|time| format!(" ({})", time)).unwrap_or_default(),
+                    gap.segments,
+                    gap.sequence,
+                    gap.duration
+                ));
+            }
+            crate::usage::record_download(Some(path)).await;
+            job_log::note(job_id, &format!("Completed: {}", path.display()));
+            set_status(job_id, "completed", None, Some(&path.to_string_lossy())).await;
+        }
+        Err(e) => {
+            // Cancelled jobs were stopped on purpose and are not offered for resuming
+            let status = if is_cancelled(e) { "cancelled" } else { "failed" };
+            if !is_cancelled(e) {
+                crate::usage::record_download(None).await;
+            }
+            job_log::note(job_id, &format!("Job {} ({:?}): {}", status, e.kind, e));
+            set_status(job_id, status, Some(&e.message), None).await;
+        }
+    }
+    result
+}
+
+// Run a journaled download in the background, reporting it through
+// "download-progress" events
+pub fn spawn(parser: Arc<M3u8Parser>, ffmpeg: Arc<FFmpegService>, job_id: String, spec: JobSpec) {
+    tokio::spawn(async move {
+        ffmpeg.emit(events::DOWNLOAD_PROGRESS, JobEvent::new(&job_id, i18n::t(Msg::DownloadInitializing), JobUpdate::Starting));
+        let result = match crate::crash::guard("download", run(parser, &ffmpeg, &job_id, &spec)).await {
+            Some(result) => result,
+            None => {
+                // The panic skipped run's own bookkeeping
+                ffmpeg.finish_task(&job_id).await;
+                let error = AppError::internal("The download task crashed; details are in the crash log");
+                job_log::note(&job_id, &error.message);
+                set_status(&job_id, "failed", Some(&error.message), None).await;
+                Err(error)
+            }
+        };
+        match result {
+            Ok(path) => ffmpeg.emit(events::DOWNLOAD_PROGRESS, JobEvent::new(
+                &job_id,
+                i18n::tf(Msg::DownloadCompleted, &[&path.display()]),
+                JobUpdate::Completed {
+                    gaps: Some(crate::native_download::recording_gaps(&path)),
+                    path: Some(path),
+                },
+            )),
+            Err(e) => {
+                eprintln!("Download {} failed: {}", job_id, e);
+                ffmpeg.emit(events::DOWNLOAD_PROGRESS, JobEvent::new(
+                    &job_id,
+                    i18n::tf(Msg::DownloadFailed, &[&e]),
+                    JobUpdate::Error { log_tail: Some(job_log::tail(&job_id)), error: Some(e) },
+                ));
+            }
+        }
+    });
+}
+
+// Called once at startup: whatever was running when the app stopped is now
+// resumable
+pub fn mark_interrupted(db: &Database) {
+    match db.mark_interrupted_jobs() {
+        Ok(0) => {}
+        Ok(count) => println!("{} interrupted download(s) can be resumed", count),
+        Err(e) => eprintln!("Failed to check the job journal: {}", e),
+    }
+}
+
+pub async fn resumable() -> Result<Vec<JournalEntry>, AppError> {
+    database::global_db().await?
+        .get_resumable_jobs()
+        .map_err(AppError::from)
+}
+
+// Restart resumable jobs under their original ids: all of them, or those in
+// `job_ids`. Returns the ids that were started.
+pub async fn resume(
+    parser: Arc<M3u8Parser>,
+    ffmpeg: Arc<FFmpegService>,
+    job_ids: Option<&[String]>,
+) -> Result<Vec<String>, AppError> {
+    let mut started = Vec::new();
+    for entry in resumable().await? {
+        if job_ids.is_some_and(|ids| !ids.contains(&entry.job_id)) {
+            continue;
+        }
+        if ffmpeg.is_job_active(&entry.job_id).await {
+            continue;
+        }
+        let spec: JobSpec = match serde_json::from_value(entry.spec) {
+            Ok(spec) => spec,
+            Err(e) => {
+                eprintln!("Skipping job {} with an unreadable specification: {}", entry.job_id, e);
+                continue;
+            }
+        };
+        // Claim the job before it starts so a second resume skips it
+        set_status(&entry.job_id, "running", None, None).await;
+        spawn(parser.clone(), ffmpeg.clone(), entry.job_id.clone(), spec);
+        started.push(entry.job_id);
+    }
+    Ok(started)
+}
@@ -0,0 +1,82 @@
+use crate::error::AppError;
+
+// Entries are stored under this service name in the OS keychain (macOS
+// Keychain, Windows Credential Manager, Secret Service on Linux)
+const SERVICE: &str = "m3u8-mcp";
+const DATABASE_KEY: &str = "cache-database-key";
+// Keychains cannot be listed, so the names of stored credentials are kept
+// in an entry of their own
+const CREDENTIAL_INDEX: &str = "credential-index";
+
+fn entry(name: &str) -> Result<keyring::Entry, AppError> {
+    keyring::Entry::new(SERVICE, name)
+        .map_err(|e| AppError::internal(format!("Failed to open keychain entry {}: {}", name, e)))
+}
+
+// Read a secret such as a captured cookie or token
+pub fn get_secret(name: &str) -> Result<Option<String>, AppError> {
+    match entry(name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(AppError::internal(format!("Failed to read {} from the keychain: {}", name, e))),
+    }
+}
+
+fn credential_names() -> Result<Vec<String>, AppError> {
+    Ok(get_secret(CREDENTIAL_INDEX)?
+        .and_then(|index| serde_json::from_str(&index).ok())
+        .unwrap_or_default())
+}
+
+fn store(name: &str, value: &str) -> Result<(), AppError> {
+    entry(name)?.set_password(value)
+        .map_err(|e| AppError::internal(format!("Failed to store {} in the keychain: {}", name, e)))
+}
+
+pub fn set_secret(name: &str, value: &str) -> Result<(), AppError> {
+    store(name, value)?;
+    if name == DATABASE_KEY {
+        return Ok(());
+    }
+    let mut names = credential_names()?;
+    if !names.iter().any(|n| n == name) {
+        names.push(name.to_string());
+        store(CREDENTIAL_INDEX, &serde_json::to_string(&names).unwrap_or_default())?;
+    }
+    Ok(())
+}
+
+fn delete(name: &str) -> Result<(), AppError> {
+    match entry(name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(AppError::internal(format!("Failed to delete {} from the keychain: {}", name, e))),
+    }
+}
+
+// Delete every stored credential (not the database key); returns how many
+pub fn clear_credentials() -> Result<usize, AppError> {
+    let names = credential_names()?;
+    for name in &names {
+        delete(name)?;
+    }
+    delete(CREDENTIAL_INDEX)?;
+    Ok(names.len())
+}
+
+// The hex-encoded 256-bit key of the cache database. With `create`, a key
+// is generated and stored on first use.
+pub fn database_key(create: bool) -> Result<Option<String>, AppError> {
+    if let Some(key) = get_secret(DATABASE_KEY)? {
+        return Ok(Some(key));
+    }
+    if !create {
+        return Ok(None);
+    }
+
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| AppError::internal(format!("Failed to generate database key: {}", e)))?;
+    let key: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    set_secret(DATABASE_KEY, &key)?;
+    Ok(Some(key))
+}
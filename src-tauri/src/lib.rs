@@ -1,17 +1,85 @@
 mod mcp_server;
-mod m3u8_parser;
 mod ffmpeg_wrapper;
 mod database;
+mod error;
+mod config;
+mod discovery;
+mod analysis;
+mod compatibility;
+mod media_analysis;
+mod page_title;
+mod i18n;
+mod paths;
+mod cleanup;
+mod reports;
+mod transcription;
+mod chapters;
+mod warmup;
+mod history;
+mod link_check;
+mod snapshots;
+mod native_download;
+mod keystore;
+mod retention;
+mod profiles;
+mod artifacts;
+mod journal;
+mod progress;
+mod host_tuning;
+mod master;
+mod repair;
+mod dash;
+mod video_metadata;
+mod thumbnails;
+mod job_log;
+mod ffmpeg_diagnosis;
+mod variant_select;
+mod output_dir;
+mod organize;
+mod duplicates;
+mod stream_info;
+mod vod_export;
+mod audio_tracks;
+mod renditions;
+mod interstitials;
+mod canonical_url;
+mod cancellation;
+mod tool_queue;
+mod tool_output;
+mod result_store;
+mod segment_export;
+mod aria2c;
+mod events;
+mod updates;
+mod crash;
+mod diagnostics;
+mod usage;
+mod current_url;
+mod graph;
+mod deep_parse;
+mod segment_archive;
+mod abr_sim;
+mod detect;
+mod captures;
+mod event_log;
+mod process;
+mod completions;
+mod trash;
+mod roots;
+mod content_fingerprint;
+mod catalog;
 
+use m3u8_mcp_parser as m3u8_parser;
+
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::path::PathBuf;
 use tauri::{State, Emitter};
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::Mutex;
+use error::AppError;
+use events::{JobEvent, JobUpdate, WarmupEvent, WarmupUpdate};
+use i18n::Msg;
 
-// Global state for current m3u8 URL
-lazy_static::lazy_static! {
-    pub static ref CURRENT_M3U8_URL: Arc<RwLock<Option<String>>> = Arc::new(RwLock::new(None));
-}
 
 // Server state for Tauri
 struct ServerHandle {
@@ -25,14 +93,20 @@ struct DatabaseHandle {
     db: Arc<Mutex<Option<Arc<database::Database>>>>,
 }
 
-// m3u8 parser state for Tauri
+// m3u8 parser state for Tauri. Replaced when the profile changes.
 struct M3u8ParserHandle {
-    parser: Arc<m3u8_parser::M3u8Parser>,
+    parser: std::sync::RwLock<Arc<m3u8_parser::M3u8Parser>>,
 }
 
-// FFmpeg state for Tauri  
+impl M3u8ParserHandle {
+    fn parser(&self) -> Arc<m3u8_parser::M3u8Parser> {
+        self.parser.read().unwrap().clone()
+    }
+}
+
+// FFmpeg state for Tauri
 struct FFmpegHandle {
-    wrapper: Arc<Mutex<ffmpeg_wrapper::FFmpegWrapper>>,
+    service: Arc<ffmpeg_wrapper::FFmpegService>,
 }
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
@@ -43,25 +117,72 @@ fn greet(name: &str) -> String {
 
 // m3u8 URL management commands
 #[tauri::command]
-async fn set_current_m3u8_url(url: String) -> Result<(), String> {
-    let mut url_state = CURRENT_M3U8_URL.write().await;
+async fn set_current_m3u8_url(url: String) -> Result<(), AppError> {
     // Set to None if the URL is empty, otherwise Some(url)
-    *url_state = if url.trim().is_empty() {
+    let url = if url.trim().is_empty() {
         None
     } else {
         // Save to history if not empty
-        if let Err(e) = save_url_to_history(&url).await {
+        if let Err(e) = history::add(&url) {
             eprintln!("Failed to save URL to history: {}", e);
+        } else {
+            spawn_history_title(url.clone());
         }
         Some(url)
     };
+    // The UI is a session of its own; MCP clients keep their URLs
+    current_url::set_ui(url).await;
     Ok(())
 }
 
+// Label the history entry with the stream's title once it is known
+fn spawn_history_title(url: String) {
+    tauri::async_runtime::spawn(async move {
+        let parser = profiles::parser();
+        if let Some(title) = stream_info::title(&parser, &url).await {
+            if let Err(e) = history::set_title(&url, &title) {
+                eprintln!("Failed to save stream title to history: {}", e);
+            }
+        }
+    });
+}
+
 #[tauri::command]
-async fn get_current_m3u8_url() -> Result<Option<String>, String> {
-    let url_state = CURRENT_M3U8_URL.read().await;
-    Ok(url_state.clone())
+async fn get_current_m3u8_url() -> Result<Option<String>, AppError> {
+    Ok(current_url::ui().await)
+}
+
+// Step the UI back or forward through the URLs it had; None when there is
+// nothing that way
+#[tauri::command]
+async fn current_url_back() -> Result<Option<String>, AppError> {
+    Ok(current_url::ui_step(false).await)
+}
+
+#[tauri::command]
+async fn current_url_forward() -> Result<Option<String>, AppError> {
+    Ok(current_url::ui_step(true).await)
+}
+
+#[tauri::command]
+async fn get_pinned_url() -> Result<Option<String>, AppError> {
+    Ok(current_url::pinned())
+}
+
+// Pin `url`, or the current URL without one
+#[tauri::command]
+async fn pin_url(url: Option<String>) -> Result<String, AppError> {
+    let url = match url {
+        Some(url) => url,
+        None => current_url::ui().await.unwrap_or_default(),
+    };
+    current_url::pin(&url)?;
+    Ok(url.trim().to_string())
+}
+
+#[tauri::command]
+async fn unpin_url() -> Result<Option<String>, AppError> {
+    current_url::unpin()
 }
 
 // m3u8 parsing commands
@@ -69,11 +190,121 @@ async fn get_current_m3u8_url() -> Result<Option<String>, String> {
 async fn parse_m3u8_url(
     parser_state: State<'_, M3u8ParserHandle>,
     url: String
-) -> Result<m3u8_parser::ParsedPlaylist, String> {
-    parser_state.parser
-        .parse_url(&url)
+) -> Result<m3u8_parser::ParsedPlaylist, AppError> {
+    let (text, playlist) = parser_state.parser()
+        .fetch_and_parse(&url)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)?;
+    snapshots::spawn_capture(parser_state.parser(), &url, "parse", HashMap::from([(url.clone(), text)]));
+    Ok(playlist)
+}
+
+// Parse a master playlist together with all its variant and rendition
+// playlists
+#[tauri::command]
+async fn parse_m3u8_deep(
+    parser_state: State<'_, M3u8ParserHandle>,
+    url: String
+) -> Result<deep_parse::DeepPlaylist, AppError> {
+    deep_parse::parse(&parser_state.parser(), &url).await
+}
+
+#[tauri::command]
+async fn get_stream_info(
+    parser_state: State<'_, M3u8ParserHandle>,
+    url: String
+) -> Result<stream_info::StreamInfo, AppError> {
+    stream_info::probe(&parser_state.parser(), &url).await
+}
+
+#[tauri::command]
+async fn get_interstitials(
+    parser_state: State<'_, M3u8ParserHandle>,
+    url: String
+) -> Result<Vec<interstitials::ScheduledInterstitial>, AppError> {
+    interstitials::list(&parser_state.parser(), &url).await
+}
+
+// Variants, renditions, segments, keys and init segments of a playlist as
+// nodes and edges, each with the HTTP status it answered with
+#[tauri::command]
+async fn get_playlist_graph(
+    parser_state: State<'_, M3u8ParserHandle>,
+    url: String,
+    max_segments: Option<usize>,
+    check: Option<bool>
+) -> Result<graph::PlaylistGraph, AppError> {
+    let config = config::load_config()?.effective();
+    graph::build(
+        &config,
+        &parser_state.parser(),
+        &url,
+        max_segments.unwrap_or(graph::DEFAULT_MAX_SEGMENTS),
+        check.unwrap_or(true),
+    ).await
+}
+
+// Simulated ABR playback of a master playlist under a bandwidth profile
+// (or a constant `kbps`)
+#[tauri::command]
+async fn simulate_abr(
+    parser_state: State<'_, M3u8ParserHandle>,
+    url: String,
+    profile: Option<Vec<abr_sim::BandwidthStep>>,
+    kbps: Option<f64>,
+    options: Option<abr_sim::SimOptions>
+) -> Result<abr_sim::Simulation, AppError> {
+    let profile = abr_sim::profile(profile, kbps)?;
+    abr_sim::run(&parser_state.parser(), &url, &profile, &options.unwrap_or_default()).await
+}
+
+// Whether a URL is an HLS master or media playlist, a DASH manifest, a media
+// file or a web page, with the tool to use next
+#[tauri::command]
+async fn detect_stream(url: String) -> Result<detect::Detection, AppError> {
+    let config = config::load_config()?.effective();
+    detect::detect(&config, &url).await
+}
+
+// Sample frames of a stream into a fingerprint and list stored streams
+// showing the same content
+#[tauri::command]
+async fn fingerprint_stream(
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    parser_state: State<'_, M3u8ParserHandle>,
+    url: String,
+    samples: Option<usize>
+) -> Result<content_fingerprint::FingerprintReport, AppError> {
+    let db = database::global_db().await?;
+    let samples = samples.unwrap_or(content_fingerprint::DEFAULT_SAMPLES);
+    content_fingerprint::fingerprint(&db, &ffmpeg_state.service, &parser_state.parser(), &url, samples).await
+}
+
+// Write the tool catalog and the OpenAPI document of the artifact routes
+// into `output_dir`, or the configured output directory
+#[tauri::command]
+async fn export_tool_catalog(output_dir: Option<String>) -> Result<catalog::ExportedCatalog, AppError> {
+    let dir = match output_dir {
+        Some(dir) => paths::expand_path(&dir),
+        None => paths::expand_path(&config::load_config()?.output_dir),
+    };
+    catalog::export(&dir)
+}
+
+// Stream URLs sent by the companion browser extension, newest first
+#[tauri::command]
+async fn list_captures() -> Vec<captures::Capture> {
+    captures::list()
+}
+
+#[tauri::command]
+async fn dismiss_capture(id: String) -> Result<captures::Capture, AppError> {
+    captures::dismiss(&id)
+}
+
+#[tauri::command]
+async fn clear_captures() -> usize {
+    captures::clear()
 }
 
 #[tauri::command]
@@ -81,330 +312,678 @@ async fn extract_m3u8_segments(
     parser_state: State<'_, M3u8ParserHandle>,
     url: String,
     base_url: Option<String>
-) -> Result<Vec<String>, String> {
-    parser_state.parser
+) -> Result<Vec<String>, AppError> {
+    parser_state.parser()
         .extract_segments(&url, base_url.as_deref())
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
 // FFmpeg commands
 #[tauri::command]
 async fn check_ffmpeg_installation(
-    ffmpeg_state: State<'_, Arc<Mutex<FFmpegHandle>>>
-) -> Result<String, String> {
-    let handle = ffmpeg_state.lock().await;
-    let wrapper = handle.wrapper.lock().await;
-    wrapper.check_installation()
-        .map_err(|e| e.to_string())
+    ffmpeg_state: State<'_, FFmpegHandle>
+) -> Result<String, AppError> {
+    ffmpeg_state.service.check_installation()
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn list_jobs(
+    ffmpeg_state: State<'_, FFmpegHandle>
+) -> Result<Vec<ffmpeg_wrapper::JobInfo>, AppError> {
+    Ok(ffmpeg_state.service.list_jobs().await)
+}
+
+// FFmpeg command lines a job ran, for reproducing it in a terminal
+#[tauri::command]
+async fn get_job_commands(job_id: String) -> Result<Vec<database::JobCommand>, AppError> {
+    database::global_db().await?
+        .get_job_commands(&job_id)
+        .map_err(AppError::from)
+}
+
+// The FFmpeg command download_m3u8_stream would run
+#[tauri::command]
+async fn preview_download_command(
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    url: String,
+    output_path: Option<String>,
+    audio: Option<config::AudioTracks>
+) -> Result<ffmpeg_wrapper::CommandPreview, AppError> {
+    let output_path = output_path.map(|path| paths::expand_path(&path));
+    let audio = audio.unwrap_or_else(|| config::load_config().map(|c| c.downloader.audio).unwrap_or_default());
+    let command = ffmpeg_state.service.preview_download(&url, output_path.as_deref(), None, &audio)?;
+    Ok(command.into())
+}
+
+// The FFmpeg command convert_to_hls would run
+#[tauri::command]
+async fn preview_convert_command(
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    input_path: String,
+    output_dir: String,
+    segment_duration: u32,
+    options: Option<ffmpeg_wrapper::PackagingOptions>
+) -> Result<ffmpeg_wrapper::CommandPreview, AppError> {
+    let command = ffmpeg_state.service.preview_convert(
+        &paths::expand_path(&input_path),
+        &paths::expand_path(&output_dir),
+        segment_duration,
+        &options.unwrap_or_default()
+    );
+    Ok(command.into())
+}
+
+// Full log of a job: FFmpeg's stderr or the native downloader's messages
+#[tauri::command]
+async fn get_job_log(job_id: String) -> Result<String, AppError> {
+    job_log::read(&job_id)
 }
 
 #[tauri::command]
 async fn cancel_download(
-    _app: tauri::AppHandle,
-    ffmpeg_state: State<'_, Arc<Mutex<FFmpegHandle>>>
-) -> Result<String, String> {
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    job_id: Option<String>
+) -> Result<String, AppError> {
     println!("cancel_download command called");
-    let handle = ffmpeg_state.lock().await;
-    let wrapper = handle.wrapper.lock().await;
     
-    println!("Calling FFmpegWrapper::cancel_download");
-    wrapper.cancel_download()
+    println!("Calling FFmpegService::cancel_download");
+    ffmpeg_state.service.cancel_download(job_id.as_deref())
         .await
         .map_err(|e| {
-            let error_msg = e.to_string();
-            eprintln!("Cancel failed: {}", error_msg);
-            error_msg
+            eprintln!("Cancel failed: {}", e);
+            AppError::from(e)
         })?;
     
     println!("Download cancelled successfully");
-    Ok("Download cancelled".to_string())
+    Ok(i18n::t(Msg::DownloadCancelled))
 }
 
 #[tauri::command]
 async fn download_m3u8_stream(
-    app: tauri::AppHandle,
-    ffmpeg_state: State<'_, Arc<Mutex<FFmpegHandle>>>,
+    parser_state: State<'_, M3u8ParserHandle>,
+    ffmpeg_state: State<'_, FFmpegHandle>,
     url: String,
-    output_path: Option<String>
-) -> Result<String, String> {
+    output_path: Option<String>,
+    page_url: Option<String>,
+    project_id: Option<i32>,
+    engine: Option<config::DownloadEngine>,
+    mirrors: Option<Vec<String>>,
+    retry: Option<config::RetryPolicy>,
+    audio: Option<config::AudioTracks>,
+    interstitials: Option<config::InterstitialMode>,
+    force: Option<bool>
+) -> Result<String, AppError> {
     println!("Download requested for URL: {}", url);
+    // A stream already on disk is not fetched again unless forced
+    if !force.unwrap_or(false) {
+        if let Some(existing) = duplicates::find_existing(&parser_state.parser(), &url).await? {
+            return Err(duplicates::conflict(&url, &existing));
+        }
+    }
+    let job_id = ffmpeg_wrapper::new_job_id();
     
     // Emit start event
-    app.emit("download-progress", serde_json::json!({
-        "status": "starting",
-        "message": "Initializing download..."
-    })).ok();
-    
-    let handle = ffmpeg_state.lock().await;
-    let mut wrapper = handle.wrapper.lock().await;
+    ffmpeg_state.service.emit(events::DOWNLOAD_PROGRESS, JobEvent::new(&job_id, i18n::t(Msg::DownloadInitializing), JobUpdate::Starting));
     
     let output = if let Some(path) = output_path {
         println!("Using provided output path: {}", path);
-        Some(PathBuf::from(path))
+        Some(paths::expand_path(&path))
+    } else if let Some(id) = project_id {
+        // Downloads without a path go to the project's directory
+        database::project_output_dir(id).await?
     } else {
         println!("No output path provided, will generate default");
         None
     };
     
-    // Set the app handle for event emission
-    wrapper.set_app_handle(Some(app.clone()));
+    let spec = journal::JobSpec {
+        url,
+        output_path: output,
+        page_url,
+        project_id,
+        engine,
+        mirrors: mirrors.unwrap_or_default(),
+        retry,
+        audio,
+        interstitials,
+    };
     
-    println!("Starting FFmpeg download...");
-    let result_path = wrapper
-        .download_stream(&url, output.as_deref())
+    println!("Starting download...");
+    let result_path = journal::run(parser_state.parser(), &ffmpeg_state.service, &job_id, &spec)
         .await
-        .map_err(|e| {
-            let error_msg = format!("FFmpeg download failed: {}", e);
+        .map_err(|error| {
+            let error_msg = i18n::tf(Msg::DownloadFailed, &[&error]);
             eprintln!("{}", error_msg);
             // Emit error event
-            app.emit("download-progress", serde_json::json!({
-                "status": "error",
-                "message": error_msg.clone()
-            })).ok();
-            error_msg
+            ffmpeg_state.service.emit(events::DOWNLOAD_PROGRESS, JobEvent::new(
+                &job_id,
+                error_msg.clone(),
+                JobUpdate::Error { error: Some(error.clone()), log_tail: Some(job_log::tail(&job_id)) },
+            ));
+            AppError { message: error_msg, ..error }
         })?;
     
     let path_str = result_path.to_string_lossy().to_string();
     println!("Download completed successfully: {}", path_str);
     
     // Emit completion event
-    app.emit("download-progress", serde_json::json!({
-        "status": "completed",
-        "message": format!("Download completed: {}", path_str)
-    })).ok();
+    ffmpeg_state.service.emit(events::DOWNLOAD_PROGRESS, JobEvent::new(
+        &job_id,
+        i18n::tf(Msg::DownloadCompleted, &[&path_str]),
+        JobUpdate::Completed { path: Some(result_path), gaps: None },
+    ));
     
     Ok(path_str)
 }
 
+// Downloads interrupted by a restart, or failed, that can be started again
+#[tauri::command]
+async fn list_resumable_jobs() -> Result<Vec<database::JournalEntry>, AppError> {
+    journal::resumable().await
+}
+
+// Start resumable jobs again in the background; all of them without job_ids
+#[tauri::command]
+async fn resume_jobs(
+    parser_state: State<'_, M3u8ParserHandle>,
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    job_ids: Option<Vec<String>>
+) -> Result<Vec<String>, AppError> {
+    journal::resume(parser_state.parser(), ffmpeg_state.service.clone(), job_ids.as_deref()).await
+}
+
+// Recorded transfer history of a download, reduced to at most `points` samples
+#[tauri::command]
+async fn get_job_progress(job_id: String, points: Option<usize>) -> Result<progress::JobProgress, AppError> {
+    progress::job_progress(&job_id, points.unwrap_or(200)).await
+}
+
+// Average and peak download speed per CDN host
+#[tauri::command]
+async fn get_host_speeds() -> Result<Vec<database::HostSpeed>, AppError> {
+    progress::host_speeds().await
+}
+
+// Requests, errors, throughput and learned connection limit per host
+#[tauri::command]
+async fn get_host_stats() -> Result<Vec<host_tuning::HostReport>, AppError> {
+    host_tuning::report().await
+}
+
 #[tauri::command]
 async fn convert_to_hls(
-    ffmpeg_state: State<'_, Arc<Mutex<FFmpegHandle>>>,
+    ffmpeg_state: State<'_, FFmpegHandle>,
     input_path: String,
     output_dir: String,
-    segment_duration: u32
-) -> Result<String, String> {
-    let handle = ffmpeg_state.lock().await;
-    let wrapper = handle.wrapper.lock().await;
-    
-    let result_path = wrapper
+    segment_duration: u32,
+    options: Option<ffmpeg_wrapper::PackagingOptions>
+) -> Result<String, AppError> {
+    let job_id = ffmpeg_wrapper::new_job_id();
+    let options = options.unwrap_or_default();
+    let output = ffmpeg_state.service
         .convert_to_hls(
-            &PathBuf::from(input_path),
-            &PathBuf::from(output_dir),
-            segment_duration
+            &job_id,
+            &paths::expand_path(&input_path),
+            &paths::expand_path(&output_dir),
+            segment_duration,
+            &options
         )
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(AppError::from)?;
+    artifacts::record_packaged(&job_id, &output, options.encrypt && options.serve_key).await;
     
+    // The HLS playlist, or the MPD for DASH-only output
+    let result_path = output.playlist.or(output.mpd).unwrap_or_default();
     Ok(result_path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
 async fn probe_stream(
-    ffmpeg_state: State<'_, Arc<Mutex<FFmpegHandle>>>,
+    ffmpeg_state: State<'_, FFmpegHandle>,
     url: String
-) -> Result<String, String> {
-    let handle = ffmpeg_state.lock().await;
-    let wrapper = handle.wrapper.lock().await;
-    
-    wrapper.probe_stream(&url)
+) -> Result<String, AppError> {
+    ffmpeg_state.service.probe_stream(&url)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(AppError::from)
 }
 
-// URL history management
-async fn save_url_to_history(url: &str) -> Result<(), String> {
-    use std::fs;
-    use serde_json::json;
-    
-    let home_dir = dirs::home_dir()
-        .ok_or("Failed to get home directory")?;
-    let config_dir = home_dir.join(".m3u8-mcp");
-    
-    fs::create_dir_all(&config_dir)
-        .map_err(|e| format!("Failed to create config directory: {}", e))?;
-    
-    let history_path = config_dir.join("url_history.json");
-    
-    // Read existing history
-    let mut history: Vec<serde_json::Value> = if history_path.exists() {
-        let content = fs::read_to_string(&history_path)
-            .unwrap_or_else(|_| "[]".to_string());
-        serde_json::from_str(&content).unwrap_or_else(|_| Vec::new())
-    } else {
-        Vec::new()
-    };
-    
-    // Create new entry
-    let entry = json!({
-        "url": url,
-        "timestamp": chrono::Local::now().to_rfc3339(),
-    });
-    
-    // Check if URL already exists and remove it
-    history.retain(|item| {
-        item.get("url")
-            .and_then(|v| v.as_str())
-            .map(|u| u != url)
-            .unwrap_or(true)
-    });
-    
-    // Add new entry at the beginning
-    history.insert(0, entry);
-    
-    // Keep only last 20 URLs
-    history.truncate(20);
-    
-    // Save to file
-    let json_str = serde_json::to_string_pretty(&history)
-        .map_err(|e| format!("Failed to serialize history: {}", e))?;
-    
-    fs::write(history_path, json_str)
-        .map_err(|e| format!("Failed to save history: {}", e))?;
-    
-    Ok(())
+// Video metadata per variant, with VIDEO-RANGE mismatches flagged
+#[tauri::command]
+async fn probe_video(
+    parser_state: State<'_, M3u8ParserHandle>,
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    url: String
+) -> Result<Vec<video_metadata::VariantVideo>, AppError> {
+    video_metadata::probe_variants(&parser_state.parser(), &ffmpeg_state.service, &url).await
 }
 
+// Queue thumbnails (and previews) for recorded downloads, or all of them
 #[tauri::command]
-async fn get_url_history() -> Result<Vec<serde_json::Value>, String> {
-    use std::fs;
-    
-    let home_dir = dirs::home_dir()
-        .ok_or("Failed to get home directory")?;
-    let history_path = home_dir.join(".m3u8-mcp").join("url_history.json");
-    
-    if !history_path.exists() {
-        return Ok(Vec::new());
-    }
-    
-    let content = fs::read_to_string(history_path)
-        .map_err(|e| format!("Failed to read history: {}", e))?;
-    
-    serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse history: {}", e))
+async fn generate_thumbnails(
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    download_ids: Option<Vec<i32>>,
+    force: Option<bool>
+) -> Result<usize, AppError> {
+    thumbnails::enqueue_downloads(&ffmpeg_state.service, download_ids.as_deref(), force.unwrap_or(false)).await
 }
 
 #[tauri::command]
-async fn get_last_used_url() -> Result<Option<String>, String> {
-    let history = get_url_history().await?;
-    
-    if history.is_empty() {
-        return Ok(None);
-    }
-    
-    Ok(history[0].get("url")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string()))
+async fn get_thumbnail_queue() -> Result<thumbnails::QueueStatus, AppError> {
+    Ok(thumbnails::status())
 }
 
+// URL history management
 #[tauri::command]
-async fn clear_url_history() -> Result<(), String> {
-    use std::fs;
-    
-    let home_dir = dirs::home_dir()
-        .ok_or("Failed to get home directory")?;
-    let history_path = home_dir.join(".m3u8-mcp").join("url_history.json");
-    
-    if history_path.exists() {
-        fs::write(history_path, "[]")
-            .map_err(|e| format!("Failed to clear history: {}", e))?;
+async fn get_url_history() -> Result<Vec<serde_json::Value>, AppError> {
+    history::load()
+}
+
+#[tauri::command]
+async fn get_last_used_url() -> Result<Option<String>, AppError> {
+    Ok(history::urls()?.into_iter().next())
+}
+
+#[tauri::command]
+async fn clear_url_history(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>
+) -> Result<Option<database::TrashEntry>, AppError> {
+    match current_db(&db_state).await {
+        Ok(db) => trash::clear_history(&db),
+        // Without a database there is no trash to keep the entries in
+        Err(_) => history::clear().map(|_| None),
     }
-    
-    Ok(())
 }
 
 // Configuration management
 #[tauri::command]
 async fn save_m3u8_config(
+    ffmpeg_state: State<'_, FFmpegHandle>,
     ffmpeg_path: Option<String>,
     output_dir: String
-) -> Result<(), String> {
-    use std::fs;
-    
-    let home_dir = dirs::home_dir()
-        .ok_or("Failed to get home directory")?;
-    let config_dir = home_dir.join(".m3u8-mcp");
+) -> Result<(), AppError> {
+    let mut app_config = config::load_config()?;
+    app_config.ffmpeg_path = ffmpeg_path;
+    app_config.output_dir = output_dir;
+    config::save_config(&app_config)?;
     
-    fs::create_dir_all(&config_dir)
-        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    // Apply immediately so the next job uses the new settings
+    ffmpeg_state.service.set_config(profiles::ffmpeg_config(&app_config));
     
-    let config_path = config_dir.join("config.json");
-    let config = serde_json::json!({
-        "ffmpeg_path": ffmpeg_path,
-        "output_dir": output_dir
+    Ok(())
+}
+
+#[tauri::command]
+async fn load_m3u8_config() -> Result<config::AppConfig, AppError> {
+    config::load_config()
+}
+
+// Validate a directory, make it the default output directory and apply it
+fn store_output_dir(
+    ffmpeg_state: &State<'_, FFmpegHandle>,
+    path: &std::path::Path
+) -> Result<output_dir::OutputDirInfo, AppError> {
+    let info = output_dir::prepare(path)?;
+    let mut app_config = config::load_config()?;
+    app_config.output_dir = info.path.clone();
+    config::save_config(&app_config)?;
+    ffmpeg_state.service.set_config(profiles::ffmpeg_config(&app_config));
+    Ok(info)
+}
+
+// Pick the default output directory with the system folder dialog; None when
+// the dialog is cancelled
+#[tauri::command]
+async fn choose_output_dir(
+    app: tauri::AppHandle,
+    ffmpeg_state: State<'_, FFmpegHandle>
+) -> Result<Option<output_dir::OutputDirInfo>, AppError> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let current = paths::expand_path(&config::load_config()?.output_dir);
+    let mut dialog = app.dialog().file().set_title("Choose the output directory");
+    if current.is_dir() {
+        dialog = dialog.set_directory(&current);
+    }
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    dialog.pick_folder(move |folder| {
+        let _ = tx.send(folder);
     });
-    
-    fs::write(config_path, config.to_string())
-        .map_err(|e| format!("Failed to save configuration: {}", e))?;
-    
+    let Some(folder) = rx.await.map_err(|_| AppError::internal("The folder dialog closed unexpectedly"))? else {
+        return Ok(None);
+    };
+    let path = folder.into_path()
+        .map_err(|e| AppError::invalid_input(format!("Unsupported folder: {}", e)))?;
+    store_output_dir(&ffmpeg_state, &path).map(Some)
+}
+
+// Same as choose_output_dir for a path typed into the UI
+#[tauri::command]
+async fn set_output_dir(
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    path: String
+) -> Result<output_dir::OutputDirInfo, AppError> {
+    store_output_dir(&ffmpeg_state, &paths::expand_path(&path))
+}
+
+#[tauri::command]
+async fn get_output_dir_info() -> Result<output_dir::OutputDirInfo, AppError> {
+    output_dir::describe(&paths::expand_path(&config::load_config()?.output_dir))
+}
+
+// Push the active profile's settings into the running services
+async fn apply_profile(
+    app_config: &config::AppConfig,
+    server_state: &State<'_, Arc<Mutex<ServerHandle>>>,
+    parser_state: &State<'_, M3u8ParserHandle>,
+    ffmpeg_state: &State<'_, FFmpegHandle>,
+) {
+    let effective = app_config.effective();
+    ffmpeg_state.service.set_config(profiles::ffmpeg_config(app_config));
+    *parser_state.parser.write().unwrap() = Arc::new(profiles::parser_for(&effective));
+    
+    let server_handle = server_state.lock().await;
+    if let Some(state) = server_handle.state.lock().await.as_ref() {
+        *state.enabled_tools.write().await = effective.mcp.enabled_tools;
+    }
+}
+
+#[tauri::command]
+async fn list_profiles() -> Result<serde_json::Value, AppError> {
+    let app_config = config::load_config()?;
+    Ok(serde_json::json!({
+        "active": app_config.active_profile,
+        "profiles": app_config.profiles
+    }))
+}
+
+#[tauri::command]
+async fn save_profile(
+    server_state: State<'_, Arc<Mutex<ServerHandle>>>,
+    parser_state: State<'_, M3u8ParserHandle>,
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    name: String,
+    profile: config::Profile
+) -> Result<(), AppError> {
+    let app_config = profiles::save(&name, profile)?;
+    if app_config.active_profile.as_deref() == Some(name.trim()) {
+        apply_profile(&app_config, &server_state, &parser_state, &ffmpeg_state).await;
+    }
     Ok(())
 }
 
 #[tauri::command]
-async fn load_m3u8_config() -> Result<serde_json::Value, String> {
-    use std::fs;
-    
-    let home_dir = dirs::home_dir()
-        .ok_or("Failed to get home directory")?;
-    let config_dir = home_dir.join(".m3u8-mcp");
-    let config_path = config_dir.join("config.json");
-    
-    if !config_path.exists() {
-        return Ok(serde_json::json!({
-            "ffmpeg_path": null,
-            "output_dir": home_dir.join("Downloads").join("m3u8-mcp").to_string_lossy()
-        }));
+async fn delete_profile(
+    server_state: State<'_, Arc<Mutex<ServerHandle>>>,
+    parser_state: State<'_, M3u8ParserHandle>,
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    name: String
+) -> Result<(), AppError> {
+    let app_config = profiles::delete(&name)?;
+    apply_profile(&app_config, &server_state, &parser_state, &ffmpeg_state).await;
+    Ok(())
+}
+
+// Switch to a profile (or the base settings with None) without a restart
+#[tauri::command]
+async fn switch_profile(
+    server_state: State<'_, Arc<Mutex<ServerHandle>>>,
+    parser_state: State<'_, M3u8ParserHandle>,
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    name: Option<String>
+) -> Result<config::AppConfig, AppError> {
+    let app_config = profiles::switch(name.as_deref())?;
+    apply_profile(&app_config, &server_state, &parser_state, &ffmpeg_state).await;
+    Ok(app_config.effective())
+}
+
+#[tauri::command]
+async fn set_mcp_auto_start(enabled: bool) -> Result<(), AppError> {
+    let mut app_config = config::load_config()?;
+    app_config.mcp.auto_start = enabled;
+    config::save_config(&app_config)
+}
+
+// Check FFmpeg, the database, configuration, disk space, network access and
+// the MCP port, for pasting into bug reports
+#[tauri::command]
+async fn run_diagnostics(
+    server_state: State<'_, Arc<Mutex<ServerHandle>>>,
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    test_url: Option<String>,
+) -> Result<diagnostics::DiagnosticsReport, AppError> {
+    let running_port = *server_state.lock().await.port.lock().await;
+    Ok(diagnostics::run(&ffmpeg_state.service, running_port, test_url.as_deref()).await)
+}
+
+// Recordings in the segment archive and the space it takes
+#[tauri::command]
+async fn get_segment_archive() -> Result<segment_archive::ArchiveSummary, AppError> {
+    let config = config::load_config()?.effective();
+    Ok(segment_archive::summary(&segment_archive::dir(&config)))
+}
+
+// Put a window of `url`'s stream together from the archive into `output`,
+// which a relative path puts under the output directory
+#[tauri::command]
+async fn reconstruct_from_archive(
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    url: String,
+    window: Option<segment_archive::Window>,
+    output: String,
+) -> Result<segment_archive::Reconstruction, AppError> {
+    let config = config::load_config()?.effective();
+    let output = paths::resolve_output_path(&paths::expand_path(&output), &paths::expand_path(&config.output_dir));
+    segment_archive::reconstruct(
+        &ffmpeg_state.service,
+        &segment_archive::dir(&config),
+        &url,
+        &window.unwrap_or_default(),
+        &output,
+    ).await
+}
+
+// Delete an archived recording; returns the number of segments freed
+#[tauri::command]
+async fn delete_archived_recording(id: String) -> Result<usize, AppError> {
+    let config = config::load_config()?.effective();
+    let dir = segment_archive::dir(&config);
+    tokio::task::spawn_blocking(move || segment_archive::delete(&dir, &id))
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?
+}
+
+// Usage statistics of the last `days` days, or of all time
+#[tauri::command]
+async fn get_usage_stats(days: Option<u32>) -> Result<usage::UsageStats, AppError> {
+    usage::stats(days).await
+}
+
+// Write the usage statistics to a JSON file; the only way they leave the database
+#[tauri::command]
+async fn export_usage_report(path: String, days: Option<u32>) -> Result<usage::UsageStats, AppError> {
+    usage::export_report(&paths::expand_path(&path), days).await
+}
+
+#[tauri::command]
+async fn clear_usage_stats() -> Result<usize, AppError> {
+    Ok(database::global_db().await?.clear_usage()?)
+}
+
+// Recorded crashes, newest first. With `unseen` only those since the report
+// was last dismissed, and none when show_crash_reports is off.
+#[tauri::command]
+async fn get_crash_reports(unseen: Option<bool>) -> Result<Vec<crash::CrashRecord>, AppError> {
+    if !unseen.unwrap_or(false) {
+        return Ok(crash::list());
+    }
+    if !config::load_config()?.show_crash_reports {
+        return Ok(Vec::new());
+    }
+    Ok(crash::unseen())
+}
+
+#[tauri::command]
+async fn dismiss_crash_reports() -> Result<(), AppError> {
+    crash::mark_seen()
+}
+
+// Compare this version with the newest release of the configured channel
+#[tauri::command]
+async fn check_for_updates() -> Result<updates::UpdateCheck, AppError> {
+    let config = config::load_config()?.effective();
+    updates::check(&config).await
+}
+
+// Point the UI at a headless instance, or back at this one. The connection
+// is checked before it is saved.
+#[tauri::command]
+async fn set_remote_connection(
+    enabled: bool,
+    base_url: String,
+    token: Option<String>
+) -> Result<config::RemoteConfig, AppError> {
+    let base_url = base_url.trim().trim_end_matches('/').to_string();
+    let token = token.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    if enabled {
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            return Err(AppError::invalid_input("Remote URL must start with http:// or https://"));
+        }
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .map_err(|e| AppError::internal(format!("Failed to create HTTP client: {}", e)))?;
+        let response = client.get(format!("{}/api/jobs", base_url))
+            .bearer_auth(token.as_deref().unwrap_or_default())
+            .send()
+            .await
+            .map_err(|e| AppError::new(error::ErrorKind::Network, format!("Cannot reach {}: {}", base_url, e)).with_retryable(true))?;
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(AppError::invalid_input("The remote instance rejected the token"));
+        }
+        if !response.status().is_success() {
+            return Err(AppError::new(error::ErrorKind::Network, format!("{} answered {}", base_url, response.status())));
+        }
     }
     
-    let config_str = fs::read_to_string(config_path)
-        .map_err(|e| format!("Failed to read configuration: {}", e))?;
-    
-    serde_json::from_str(&config_str)
-        .map_err(|e| format!("Failed to parse configuration: {}", e))
+    let mut app_config = config::load_config()?;
+    app_config.remote = config::RemoteConfig { enabled, base_url, token };
+    config::save_config(&app_config)?;
+    Ok(app_config.remote)
 }
 
-// MCP Server commands (unchanged)
+// Set the language of status and error messages and persist it
+#[tauri::command]
+async fn set_locale(locale: i18n::Locale) -> Result<(), AppError> {
+    let mut app_config = config::load_config()?;
+    app_config.locale = locale;
+    config::save_config(&app_config)?;
+    i18n::set_locale(locale);
+    Ok(())
+}
+
+// MCP Server commands
 #[tauri::command]
 async fn start_mcp_server(
     state: State<'_, Arc<Mutex<ServerHandle>>>, 
+    ffmpeg_state: State<'_, FFmpegHandle>,
     port: u16,
-    enabled_tools: Vec<String>
-) -> Result<String, String> {
-    // Validate port number (port 0 is not allowed for explicit binding)
-    if port == 0 {
-        return Err("Port number must be greater than 0".to_string());
+    enabled_tools: Vec<String>,
+    bind_host: Option<String>,
+    allow_lan: Option<bool>
+) -> Result<String, AppError> {
+    let mut app_config = config::load_config()?;
+    let bind_host = bind_host.unwrap_or_else(|| app_config.mcp.bind_host.clone());
+    let allow_lan = allow_lan.unwrap_or(app_config.mcp.allow_lan);
+    
+    let server_handle = state.lock().await;
+    let message = launch_mcp_server(
+        &server_handle,
+        ffmpeg_state.service.clone(),
+        &bind_host,
+        allow_lan,
+        port,
+        enabled_tools.clone()
+    ).await?;
+    
+    // Remember the bind settings and tools so the server can be restored on next launch
+    app_config.mcp.bind_host = bind_host;
+    app_config.mcp.allow_lan = allow_lan;
+    app_config.mcp.port = port;
+    app_config.mcp.enabled_tools = enabled_tools;
+    if let Err(e) = config::save_config(&app_config) {
+        eprintln!("Failed to persist MCP server settings: {}", e);
     }
     
-    if port < 1024 {
-        return Err("Port number must be 1024 or higher (lower ports require root privileges)".to_string());
+    Ok(message)
+}
+
+// Start the MCP server in a background task and record it in the handle
+async fn launch_mcp_server(
+    server_handle: &ServerHandle,
+    ffmpeg: Arc<ffmpeg_wrapper::FFmpegService>,
+    bind_host: &str,
+    allow_lan: bool,
+    port: u16,
+    enabled_tools: Vec<String>
+) -> Result<String, AppError> {
+    // Anything other than loopback exposes the server to the network
+    let lan_exposed = !config::is_loopback_host(bind_host);
+    if lan_exposed && !allow_lan {
+        return Err(AppError::invalid_input(format!(
+            "Binding to {} exposes the MCP server to other machines; enable allow_lan to confirm",
+            bind_host
+        )));
     }
     
-    let server_handle = state.lock().await;
+    // Port 0 asks the OS for a free port; explicit ports must be unprivileged
+    if port != 0 && port < 1024 {
+        return Err(AppError::invalid_input("Port number must be 1024 or higher (lower ports require root privileges)"));
+    }
     
     // Check if server is already running
     let state_lock = server_handle.state.lock().await;
     if let Some(ref current_state) = *state_lock {
         if *current_state.running.lock().await {
-            return Err("Server is already running".to_string());
+            return Err(AppError::conflict("Server is already running"));
         }
     }
     drop(state_lock);
     
-    // First, check if the port is available by trying to connect to it
-    // If we can connect, it means something is already listening on that port
-    let addr = format!("127.0.0.1:{}", port);
-    match tokio::net::TcpStream::connect(&addr).await {
-        Ok(_) => {
-            // Port is already in use
-            return Err(format!("Port {} is already in use", port));
-        }
-        Err(_) => {
-            // Port is free (connection failed means nothing is listening)
+    if port != 0 {
+        // First, check if the port is available by trying to connect to it
+        // If we can connect, it means something is already listening on that port
+        let addr = format!("127.0.0.1:{}", port);
+        match tokio::net::TcpStream::connect(&addr).await {
+            Ok(_) => {
+                // Port is already in use
+                return Err(AppError::conflict(format!("Port {} is already in use", port)));
+            }
+            Err(_) => {
+                // Port is free (connection failed means nothing is listening)
+            }
         }
     }
     
-    // Create new server state with specified port and enabled tools
-    let new_state = Arc::new(mcp_server::McpServerState::new_with_tools(port, enabled_tools));
+    // Bind now so bind errors are reported directly and the actual port is known
+    let listener = mcp_server::bind_listener(bind_host, port)
+        .await
+        .map_err(|e| AppError::conflict(format!("Failed to bind {}:{}: {}", bind_host, port, e)))?;
+    let actual_port = listener.local_addr()
+        .map_err(AppError::from)?
+        .port();
+    
+    // Create new server state with the bound port and enabled tools
+    let auth_token = artifacts::auth_token()?;
+    let new_state = Arc::new(mcp_server::McpServerState::new_with_tools(
+        bind_host.to_string(),
+        actual_port,
+        enabled_tools,
+        ffmpeg,
+        auth_token,
+    ));
     
     // Update the stored state
     let mut state_lock = server_handle.state.lock().await;
@@ -413,19 +992,23 @@ async fn start_mcp_server(
     
     // Update the port
     let mut port_lock = server_handle.port.lock().await;
-    *port_lock = Some(port);
+    *port_lock = Some(actual_port);
     drop(port_lock);
     
     // Clone state for the spawn task
     let task_state = new_state.clone();
-    let task_port = port;
     
     // Start server in background task
     let handle = tokio::spawn(async move {
-        if let Err(e) = mcp_server::start_mcp_server(task_state.clone()).await {
-            eprintln!("MCP Server error: {}", e);
-            // Mark server as not running on error
-            *task_state.running.lock().await = false;
+        match crash::guard("mcp-server", mcp_server::start_mcp_server(task_state.clone(), listener)).await {
+            Some(Ok(())) => {}
+            Some(Err(e)) => {
+                eprintln!("MCP Server error: {}", e);
+                // Mark server as not running on error
+                *task_state.running.lock().await = false;
+            }
+            // A crashed server is not running either
+            None => *task_state.running.lock().await = false,
         }
     });
     
@@ -447,26 +1030,38 @@ async fn start_mcp_server(
         *port_lock = None;
         drop(port_lock);
         
-        return Err(format!("Failed to start MCP Server on port {}. An unexpected error occurred.", task_port));
+        return Err(AppError::internal(format!("Failed to start MCP Server on port {}. An unexpected error occurred.", actual_port)));
+    }
+    
+    // Publish the endpoint so MCP clients and scripts can find it
+    if config::load_config().map(|c| c.mcp.publish_discovery).unwrap_or(true) {
+        if let Err(e) = discovery::write_discovery_file(actual_port, &new_state.auth_token) {
+            eprintln!("Failed to write discovery file: {}", e);
+        }
+    }
+    
+    if lan_exposed {
+        eprintln!("Warning: MCP Server is listening on {} and is reachable from the network", bind_host);
+        return Ok(i18n::tf(Msg::ServerStartedLan, &[&bind_host, &actual_port]));
     }
     
-    Ok(format!("MCP Server started on port {}", port))
+    Ok(i18n::tf(Msg::ServerStarted, &[&actual_port]))
 }
 
 #[tauri::command]
-async fn stop_mcp_server(state: State<'_, Arc<Mutex<ServerHandle>>>) -> Result<String, String> {
+async fn stop_mcp_server(state: State<'_, Arc<Mutex<ServerHandle>>>) -> Result<String, AppError> {
     let server_handle = state.lock().await;
     
     // Check if server is running
     let state_lock = server_handle.state.lock().await;
     if let Some(ref current_state) = *state_lock {
         if !*current_state.running.lock().await {
-            return Err("Server is not running".to_string());
+            return Err(AppError::conflict(i18n::t(Msg::ServerNotRunning)));
         }
         // Set running to false
         *current_state.running.lock().await = false;
     } else {
-        return Err("Server is not running".to_string());
+        return Err(AppError::conflict(i18n::t(Msg::ServerNotRunning)));
     }
     drop(state_lock);
     
@@ -481,20 +1076,24 @@ async fn stop_mcp_server(state: State<'_, Arc<Mutex<ServerHandle>>>) -> Result<S
         h.abort();
     }
     
-    Ok("MCP Server stopped".to_string())
+    if let Err(e) = discovery::remove_discovery_file() {
+        eprintln!("Failed to remove discovery file: {}", e);
+    }
+    
+    Ok(i18n::t(Msg::ServerStopped))
 }
 
 #[tauri::command]
-async fn get_mcp_server_status(state: State<'_, Arc<Mutex<ServerHandle>>>) -> Result<serde_json::Value, String> {
+async fn get_mcp_server_status(state: State<'_, Arc<Mutex<ServerHandle>>>) -> Result<serde_json::Value, AppError> {
     use serde_json::json;
     
     let server_handle = state.lock().await;
     
     let state_lock = server_handle.state.lock().await;
-    let running = if let Some(ref current_state) = *state_lock {
-        *current_state.running.lock().await
+    let (running, host) = if let Some(ref current_state) = *state_lock {
+        (*current_state.running.lock().await, Some(current_state.host.clone()))
     } else {
-        false
+        (false, None)
     };
     drop(state_lock);
     
@@ -504,12 +1103,39 @@ async fn get_mcp_server_status(state: State<'_, Arc<Mutex<ServerHandle>>>) -> Re
     
     Ok(json!({
         "running": running,
+        "host": host,
         "port": port
     }))
 }
 
+// Ready-to-paste MCP client configuration for the running server
 #[tauri::command]
-async fn check_port_availability(port: u16) -> Result<bool, String> {
+async fn get_mcp_client_configs(
+    state: State<'_, Arc<Mutex<ServerHandle>>>,
+    mode: Option<discovery::ConnectionMode>
+) -> Result<Vec<discovery::ClientConfig>, AppError> {
+    let port = running_server_port(&state).await?;
+    Ok(discovery::client_configs(port, mode.unwrap_or(discovery::ConnectionMode::Http)))
+}
+
+#[tauri::command]
+async fn generate_mcp_client_config(
+    state: State<'_, Arc<Mutex<ServerHandle>>>,
+    client: discovery::McpClient,
+    mode: Option<discovery::ConnectionMode>
+) -> Result<discovery::ClientConfig, AppError> {
+    let port = running_server_port(&state).await?;
+    Ok(discovery::generate_client_config(client, mode.unwrap_or(discovery::ConnectionMode::Http), port))
+}
+
+async fn running_server_port(state: &State<'_, Arc<Mutex<ServerHandle>>>) -> Result<u16, AppError> {
+    let server_handle = state.lock().await;
+    let port = *server_handle.port.lock().await;
+    port.ok_or_else(|| AppError::conflict(i18n::t(Msg::ServerNotRunning)))
+}
+
+#[tauri::command]
+async fn check_port_availability(port: u16) -> Result<bool, AppError> {
     // Validate port number
     if port == 0 {
         return Ok(false); // Invalid port
@@ -529,56 +1155,509 @@ async fn check_port_availability(port: u16) -> Result<bool, String> {
     }
 }
 
+// Start a background warm-up of the probe cache; returns the number of URLs queued
+async fn spawn_probe_warmup(
+    app: tauri::AppHandle,
+    db: Arc<database::Database>,
+    ffmpeg: Arc<ffmpeg_wrapper::FFmpegService>,
+    concurrency: usize
+) -> Result<usize, AppError> {
+    if warmup::is_running() {
+        return Err(AppError::conflict("A probe cache warm-up is already running"));
+    }
+    let urls = history::saved_urls(&db)?;
+    let queued = urls.len();
+    
+    tauri::async_runtime::spawn(async move {
+        let progress_app = app.clone();
+        let result = warmup::warm_probe_cache(db, ffmpeg, urls, concurrency, move |check, done, total| {
+            progress_app.emit(events::PROBE_WARMUP_PROGRESS, WarmupEvent::new(WarmupUpdate::Checking {
+                check: check.clone(),
+                done,
+                total,
+            })).ok();
+        }).await;
+        
+        match result {
+            Ok(summary) => {
+                println!("Probe cache warm-up finished: {} alive, {} dead", summary.alive, summary.dead);
+                app.emit(events::PROBE_WARMUP_PROGRESS, WarmupEvent::new(WarmupUpdate::Completed { summary })).ok();
+            }
+            Err(e) => eprintln!("Probe cache warm-up failed: {}", e),
+        }
+    });
+    
+    Ok(queued)
+}
+
+// Re-probe every saved URL with bounded concurrency, refreshing the probe
+// cache and flagging dead links. Progress arrives as probe-warmup-progress events.
+#[tauri::command]
+async fn warm_probe_cache(
+    app: tauri::AppHandle,
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    concurrency: Option<usize>
+) -> Result<usize, AppError> {
+    let db = current_db(&db_state).await?;
+    spawn_probe_warmup(
+        app,
+        db,
+        ffmpeg_state.service.clone(),
+        concurrency.unwrap_or(warmup::DEFAULT_CONCURRENCY)
+    ).await
+}
+
+// Last liveness check of each saved URL
+#[tauri::command]
+async fn get_link_statuses(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>
+) -> Result<Vec<database::LinkStatus>, AppError> {
+    let db = current_db(&db_state).await?;
+    Ok(db.get_link_statuses()?)
+}
+
+// Fetch every saved playlist now and mark 403/404/410 responses as dead
+#[tauri::command]
+async fn check_links(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    parser_state: State<'_, M3u8ParserHandle>
+) -> Result<link_check::LinkCheckSummary, AppError> {
+    let db = current_db(&db_state).await?;
+    let config = config::load_config()?;
+    link_check::check_links(&db, &parser_state.parser(), &config.link_check).await
+}
+
+// Playlist text kept from the last successful check of a URL
+#[tauri::command]
+async fn get_link_snapshot(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    url: String
+) -> Result<Option<String>, AppError> {
+    let db = current_db(&db_state).await?;
+    Ok(db.get_link_snapshot(&url)?)
+}
+
+// Archived playlist snapshots, newest first
+#[tauri::command]
+async fn list_playlist_snapshots(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    url: Option<String>,
+    limit: Option<i32>
+) -> Result<Vec<database::PlaylistSnapshot>, AppError> {
+    let db = current_db(&db_state).await?;
+    Ok(db.list_snapshots(url.as_deref(), limit.unwrap_or(100))?)
+}
+
+// A snapshot's playlist text and its variant playlists
+#[tauri::command]
+async fn get_playlist_snapshot(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    id: i64
+) -> Result<snapshots::SnapshotContent, AppError> {
+    let db = current_db(&db_state).await?;
+    snapshots::load(&db, id)
+}
+
 // Database commands
 #[tauri::command]
-async fn init_database(db_state: State<'_, Arc<Mutex<DatabaseHandle>>>) -> Result<String, String> {
+async fn init_database(db_state: State<'_, Arc<Mutex<DatabaseHandle>>>) -> Result<String, AppError> {
     let db_handle = db_state.lock().await;
     
-    // Get app data directory using home directory
-    let home_dir = dirs::home_dir()
-        .ok_or("Failed to get home directory")?;
-    let db_dir = home_dir.join(".m3u8-mcp");
-    let db_path = db_dir.join("cache.db");
-    
-    // Create and initialize database
-    match database::Database::new(db_path.clone()) {
-        Ok(database) => {
-            *db_handle.db.lock().await = Some(Arc::new(database));
-            Ok(format!("Database initialized at: {}", db_path.display()))
-        }
-        Err(e) => Err(format!("Failed to initialize database: {}", e))
-    }
+    let (database, db_path) = open_database()?;
+    *db_handle.db.lock().await = Some(database.clone());
+    *database::GLOBAL_DB.write().await = Some(database);
+    
+    Ok(i18n::tf(Msg::DatabaseInitialized, &[&db_path.display()]))
+}
+
+// Open (or create) the cache database in the app directory, encrypted with
+// the keychain-held key when encryption is enabled
+fn open_database() -> Result<(Arc<database::Database>, PathBuf), AppError> {
+    let db_path = config::app_dir()?.join("cache.db");
+    let encrypt = config::load_config()?.encryption.encrypt_database;
+    let (key, previous_key) = if encrypt {
+        (keystore::database_key(true)?, None)
+    } else {
+        (None, keystore::database_key(false)?)
+    };
+    
+    database::Database::open(db_path.clone(), key.as_deref(), previous_key.as_deref())
+        .map(|database| (Arc::new(database), db_path))
+        .map_err(|e| AppError::database(format!("Failed to initialize database: {}", e)))
 }
 
 #[tauri::command]
-async fn get_cache_stats(db_state: State<'_, Arc<Mutex<DatabaseHandle>>>) -> Result<serde_json::Value, String> {
+async fn get_cache_stats(db_state: State<'_, Arc<Mutex<DatabaseHandle>>>) -> Result<serde_json::Value, AppError> {
     let db_handle = db_state.lock().await;
     let db_lock = db_handle.db.lock().await;
     
     if let Some(ref db) = *db_lock {
         db.get_cache_stats()
-            .map_err(|e| format!("Failed to get cache stats: {}", e))
+            .map_err(|e| AppError::database(format!("Failed to get cache stats: {}", e)))
     } else {
-        Err("Database not initialized".to_string())
+        Err(AppError::not_initialized(i18n::t(Msg::DatabaseNotInitialized)))
     }
 }
 
 #[tauri::command]
-async fn clear_cache(db_state: State<'_, Arc<Mutex<DatabaseHandle>>>) -> Result<String, String> {
+async fn clear_cache(db_state: State<'_, Arc<Mutex<DatabaseHandle>>>) -> Result<String, AppError> {
     let db_handle = db_state.lock().await;
     let db_lock = db_handle.db.lock().await;
     
     if let Some(ref db) = *db_lock {
-        db.clear_all_cache()
-            .map_err(|e| format!("Failed to clear cache: {}", e))?;
-        Ok("Cache cleared successfully".to_string())
+        trash::clear_cache(db, true)
+            .map_err(|e| AppError::database(format!("Failed to clear cache: {}", e)))?;
+        Ok(i18n::t(Msg::CacheCleared))
     } else {
-        Err("Database not initialized".to_string())
+        Err(AppError::not_initialized(i18n::t(Msg::DatabaseNotInitialized)))
+    }
+}
+
+// Database behind the handle, or an error if it was never opened
+async fn current_db(db_state: &State<'_, Arc<Mutex<DatabaseHandle>>>) -> Result<Arc<database::Database>, AppError> {
+    let db_handle = db_state.lock().await;
+    let db_lock = db_handle.db.lock().await;
+    db_lock.clone().ok_or_else(|| AppError::not_initialized(i18n::t(Msg::DatabaseNotInitialized)))
+}
+
+// What cleared caches and histories can be restored
+#[tauri::command]
+async fn list_trash(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>
+) -> Result<Vec<database::TrashEntry>, AppError> {
+    let db = current_db(&db_state).await?;
+    Ok(db.list_trash()?)
+}
+
+// Undo a cache or history clear
+#[tauri::command]
+async fn restore_trash(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    id: i64
+) -> Result<trash::Restored, AppError> {
+    let db = current_db(&db_state).await?;
+    trash::restore(&db, id)
+}
+
+// Apply the configured retention periods now
+#[tauri::command]
+async fn apply_retention_policy(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>
+) -> Result<retention::RetentionReport, AppError> {
+    let db = current_db(&db_state).await?;
+    let config = config::load_config()?;
+    retention::apply(&db, &config.retention)
+}
+
+// Clear history, caches, snapshots and credentials in one action
+#[tauri::command]
+async fn wipe_personal_data(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>
+) -> Result<retention::RetentionReport, AppError> {
+    let db = current_db(&db_state).await?;
+    retention::wipe_personal_data(&db)
+}
+
+// Project commands
+#[tauri::command]
+async fn create_project(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    name: String,
+    output_dir: Option<String>,
+    options: Option<serde_json::Value>
+) -> Result<database::Project, AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::invalid_input("Project name must not be empty"));
+    }
+    let db = current_db(&db_state).await?;
+    let options = options.map(|o| o.to_string());
+    
+    let id = db.create_project(name.trim(), output_dir.as_deref(), options.as_deref())
+        .map_err(|e| AppError::conflict(format!("Failed to create project: {}", e)))?;
+    db.get_project(id)?
+        .ok_or_else(|| AppError::internal("Project disappeared after creation"))
+}
+
+#[tauri::command]
+async fn list_projects(db_state: State<'_, Arc<Mutex<DatabaseHandle>>>) -> Result<Vec<database::Project>, AppError> {
+    let db = current_db(&db_state).await?;
+    db.list_projects()
+        .map_err(|e| AppError::database(format!("Failed to list projects: {}", e)))
+}
+
+#[tauri::command]
+async fn update_project(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    id: i32,
+    name: String,
+    output_dir: Option<String>,
+    options: Option<serde_json::Value>
+) -> Result<database::Project, AppError> {
+    let db = current_db(&db_state).await?;
+    let options = options.map(|o| o.to_string());
+    
+    if !db.update_project(id, name.trim(), output_dir.as_deref(), options.as_deref())? {
+        return Err(AppError::not_found(format!("Project {} not found", id)));
+    }
+    db.get_project(id)?
+        .ok_or_else(|| AppError::not_found(format!("Project {} not found", id)))
+}
+
+#[tauri::command]
+async fn delete_project(db_state: State<'_, Arc<Mutex<DatabaseHandle>>>, id: i32) -> Result<String, AppError> {
+    let db = current_db(&db_state).await?;
+    if !db.delete_project(id)? {
+        return Err(AppError::not_found(format!("Project {} not found", id)));
+    }
+    Ok(i18n::tf(Msg::ProjectDeleted, &[&id]))
+}
+
+#[tauri::command]
+async fn get_project_downloads(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    project_id: i32
+) -> Result<Vec<database::DownloadedStream>, AppError> {
+    let db = current_db(&db_state).await?;
+    Ok(db.get_project_downloads(project_id)?)
+}
+
+// Export a project and its downloads as JSON, optionally writing it to a file
+#[tauri::command]
+async fn export_project(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    project_id: i32,
+    output_path: Option<String>
+) -> Result<serde_json::Value, AppError> {
+    let db = current_db(&db_state).await?;
+    let project = db.get_project(project_id)?
+        .ok_or_else(|| AppError::not_found(format!("Project {} not found", project_id)))?;
+    let downloads = db.get_project_downloads(project_id)?;
+    
+    let export = serde_json::json!({
+        "project": project,
+        "downloads": downloads,
+        "exported_at": chrono::Utc::now().to_rfc3339()
+    });
+    
+    if let Some(path) = output_path {
+        let content = serde_json::to_string_pretty(&export)
+            .map_err(|e| AppError::internal(format!("Failed to serialize export: {}", e)))?;
+        std::fs::write(paths::expand_path(&path), content)
+            .map_err(|e| AppError::io(format!("Failed to write export: {}", e)))?;
+    }
+    
+    Ok(export)
+}
+
+// Downloaded file commands
+#[tauri::command]
+async fn get_download_history(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    limit: Option<i32>
+) -> Result<Vec<database::DownloadedStream>, AppError> {
+    let db = current_db(&db_state).await?;
+    Ok(db.get_download_history(limit.unwrap_or(100))?)
+}
+
+// Delete a downloaded file (to the OS trash unless permanent) and its record
+#[tauri::command]
+async fn delete_download(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    id: i32,
+    permanent: Option<bool>
+) -> Result<database::DownloadedStream, AppError> {
+    let db = current_db(&db_state).await?;
+    cleanup::delete_download(&db, id, permanent.unwrap_or(false))
+}
+
+#[tauri::command]
+async fn cleanup_downloads(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    older_than_days: Option<u32>,
+    max_total_bytes: Option<u64>,
+    permanent: Option<bool>,
+    dry_run: Option<bool>
+) -> Result<cleanup::CleanupReport, AppError> {
+    let db = current_db(&db_state).await?;
+    cleanup::cleanup_downloads(
+        &db,
+        older_than_days,
+        max_total_bytes,
+        permanent.unwrap_or(false),
+        dry_run.unwrap_or(false)
+    )
+}
+
+// Transcribe a recorded download with the configured whisper binary or endpoint
+#[tauri::command]
+async fn transcribe_download(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    id: i32
+) -> Result<String, AppError> {
+    let db = current_db(&db_state).await?;
+    let download = db.get_download(id)?
+        .ok_or_else(|| AppError::not_found(format!("Download {} not found", id)))?;
+    let config = config::load_config()?;
+    
+    let path = transcription::transcribe_file(
+        &ffmpeg_state.service,
+        &config.transcription,
+        std::path::Path::new(&download.output_path)
+    ).await?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+// Embed chapters in a recorded download, from the given list or from the
+// EXT-X-DATERANGE events of the stream it was recorded from
+#[tauri::command]
+async fn add_chapters(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    parser_state: State<'_, M3u8ParserHandle>,
+    ffmpeg_state: State<'_, FFmpegHandle>,
+    id: i32,
+    chapters: Option<Vec<chapters::Chapter>>
+) -> Result<Vec<chapters::Chapter>, AppError> {
+    let db = current_db(&db_state).await?;
+    let download = db.get_download(id)?
+        .ok_or_else(|| AppError::not_found(format!("Download {} not found", id)))?;
+    
+    chapters::add_chapters(
+        &parser_state.parser(),
+        &ffmpeg_state.service,
+        std::path::Path::new(&download.output_path),
+        Some(&download.url),
+        chapters
+    ).await
+}
+
+// Summary of downloads in a date range as JSON or CSV, optionally written to a file
+#[tauri::command]
+async fn generate_download_report(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>,
+    from: Option<String>,
+    to: Option<String>,
+    format: Option<reports::ReportFormat>,
+    output_path: Option<String>
+) -> Result<String, AppError> {
+    let db = current_db(&db_state).await?;
+    let report = reports::generate_report(&db, from.as_deref(), to.as_deref(), format.unwrap_or_default())?;
+    
+    if let Some(path) = output_path {
+        std::fs::write(paths::expand_path(&path), &report)
+            .map_err(|e| AppError::io(format!("Failed to write report: {}", e)))?;
     }
+    
+    Ok(report)
+}
+
+// Remove records of downloads whose files were deleted outside the app
+#[tauri::command]
+async fn reconcile_downloads(
+    db_state: State<'_, Arc<Mutex<DatabaseHandle>>>
+) -> Result<Vec<database::DownloadedStream>, AppError> {
+    let db = current_db(&db_state).await?;
+    cleanup::reconcile_downloads(&db)
+}
+
+// Apply the retention policy every `period`, re-reading it each time
+async fn run_maintenance(db: Arc<database::Database>, period: std::time::Duration) {
+    let mut ticker = tokio::time::interval(period);
+    loop {
+        ticker.tick().await;
+        let retention_config = config::load_config()
+            .map(|c| c.retention)
+            .unwrap_or_default();
+        match retention::apply(&db, &retention_config) {
+            Ok(report) => println!(
+                "Maintenance: removed {} history, {} cache and {} audit entries",
+                report.history_entries, report.cache_entries, report.audit_entries
+            ),
+            Err(e) => eprintln!("Maintenance failed: {}", e),
+        }
+    }
+}
+
+// `--export-catalog [dir]`: write the catalog files without starting
+// anything, for scripts generating client bindings. Returns the exit code.
+pub fn export_catalog(dir: Option<&str>) -> i32 {
+    let dir = paths::expand_path(dir.unwrap_or("."));
+    match catalog::export(&dir) {
+        Ok(exported) => {
+            println!("{} tools written to {}", exported.tool_count, exported.tools);
+            println!("OpenAPI document written to {}", exported.openapi);
+            0
+        }
+        Err(e) => {
+            eprintln!("Failed to export the tool catalog: {}", e);
+            1
+        }
+    }
+}
+
+// Run the engine without a window: the MCP server and its job API on the
+// configured mcp.bind_host/mcp.port, until Ctrl+C. Desktop UIs connect to it
+// in remote mode, so downloads run on an always-on server or NAS.
+pub fn run_headless() {
+    crash::install_panic_hook();
+    let app_config = config::load_config().unwrap_or_else(|e| {
+        eprintln!("Failed to load configuration, using defaults: {}", e);
+        config::AppConfig::default()
+    });
+    i18n::set_locale(app_config.locale);
+    
+    let database = match open_database() {
+        Ok((database, db_path)) => {
+            println!("Database initialized at: {}", db_path.display());
+            journal::mark_interrupted(&database);
+            *database::GLOBAL_DB.blocking_write() = Some(database.clone());
+            Some(database)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            None
+        }
+    };
+    
+    let ffmpeg = Arc::new(ffmpeg_wrapper::FFmpegService::new(profiles::ffmpeg_config(&app_config)));
+    let server_handle = ServerHandle {
+        state: Arc::new(Mutex::new(None)),
+        handle: Arc::new(Mutex::new(None)),
+        port: Arc::new(Mutex::new(None)),
+    };
+    let mcp = app_config.effective().mcp;
+    
+    tauri::async_runtime::block_on(async move {
+        tokio::spawn(crash::guard("progress", progress::record(ffmpeg.clone())));
+        tokio::spawn(crash::guard("event-log", event_log::run(ffmpeg.clone(), app_config.event_log.clone())));
+        match launch_mcp_server(&server_handle, ffmpeg, &mcp.bind_host, mcp.allow_lan, mcp.port, mcp.enabled_tools).await {
+            Ok(message) => println!("{}", message),
+            Err(e) => {
+                eprintln!("Failed to start MCP server: {}", e);
+                std::process::exit(1);
+            }
+        }
+        println!("Job API ready; connect with the token in mcp.auth_token of {}", config::app_dir().map(|d| d.join("config.json").display().to_string()).unwrap_or_default());
+        
+        if let (Some(period), Some(db)) = (retention::interval(&app_config.retention), database) {
+            tokio::spawn(crash::guard("maintenance", run_maintenance(db, period)));
+        }
+        
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            eprintln!("Failed to wait for Ctrl+C: {}", e);
+        }
+        println!("Shutting down");
+        if let Err(e) = discovery::remove_discovery_file() {
+            eprintln!("Failed to remove discovery file: {}", e);
+        }
+    });
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    crash::install_panic_hook();
+    
     // Initialize MCP server state
     let server_handle = Arc::new(Mutex::new(ServerHandle {
         state: Arc::new(Mutex::new(None)),
@@ -586,57 +1665,213 @@ pub fn run() {
         port: Arc::new(Mutex::new(None)),
     }));
     
-    // Initialize database state
+    // Restore persisted configuration
+    let app_config = config::load_config().unwrap_or_else(|e| {
+        eprintln!("Failed to load configuration, using defaults: {}", e);
+        config::AppConfig::default()
+    });
+    i18n::set_locale(app_config.locale);
+    
+    // Initialize the database up front so both Tauri commands and MCP resources can use it
+    let database = match open_database() {
+        Ok((database, db_path)) => {
+            println!("Database initialized at: {}", db_path.display());
+            journal::mark_interrupted(&database);
+            *database::GLOBAL_DB.blocking_write() = Some(database.clone());
+            Some(database)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            None
+        }
+    };
+    let warmup_db = database.clone();
     let database_handle = Arc::new(Mutex::new(DatabaseHandle {
-        db: Arc::new(Mutex::new(None)),
+        db: Arc::new(Mutex::new(database)),
     }));
     
     // Initialize m3u8 parser
     let parser_handle = M3u8ParserHandle {
-        parser: Arc::new(m3u8_parser::M3u8Parser::new()),
+        parser: std::sync::RwLock::new(Arc::new(profiles::parser_for(&app_config.effective()))),
     };
     
-    // Initialize FFmpeg wrapper with default config
-    let ffmpeg_config = ffmpeg_wrapper::FFmpegConfig::default();
-    let ffmpeg_handle = Arc::new(Mutex::new(FFmpegHandle {
-        wrapper: Arc::new(Mutex::new(ffmpeg_wrapper::FFmpegWrapper::new(ffmpeg_config))),
-    }));
+    // Initialize FFmpeg service with the saved config
+    let ffmpeg_config = profiles::ffmpeg_config(&app_config);
+    let ffmpeg_service = Arc::new(ffmpeg_wrapper::FFmpegService::new(ffmpeg_config));
+    let ffmpeg_handle = FFmpegHandle {
+        service: ffmpeg_service.clone(),
+    };
     
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
-        .manage(server_handle)
+        .manage(server_handle.clone())
         .manage(database_handle)
         .manage(parser_handle)
         .manage(ffmpeg_handle)
+        .setup(move |app| {
+            // Let the FFmpeg service emit progress events to the UI
+            ffmpeg_service.set_app_handle(app.handle().clone());
+            tauri::async_runtime::spawn(crash::guard("progress", progress::record(ffmpeg_service.clone())));
+            tauri::async_runtime::spawn(crash::guard("event-log", event_log::run(ffmpeg_service.clone(), app_config.event_log.clone())));
+            
+            // Bring the MCP server back up on its last port if requested
+            if app_config.mcp.auto_start {
+                let mcp = app_config.effective().mcp;
+                let ffmpeg = ffmpeg_service.clone();
+                tauri::async_runtime::spawn(async move {
+                    let server_handle = server_handle.lock().await;
+                    match launch_mcp_server(&server_handle, ffmpeg, &mcp.bind_host, mcp.allow_lan, mcp.port, mcp.enabled_tools).await {
+                        Ok(message) => println!("{}", message),
+                        Err(e) => eprintln!("Failed to auto-start MCP server: {}", e),
+                    }
+                });
+            }
+            
+            // Validate saved URLs every `link_check.interval_hours`
+            if let (Some(period), Some(db)) = (link_check::interval(&app_config.link_check), warmup_db.clone()) {
+                tauri::async_runtime::spawn(async move {
+                    let mut ticker = tokio::time::interval(period);
+                    loop {
+                        ticker.tick().await;
+                        // Pick up configuration changes between runs
+                        let link_config = config::load_config()
+                            .map(|c| c.link_check)
+                            .unwrap_or_default();
+                        let parser = profiles::parser();
+                        match link_check::check_links(&db, &parser, &link_config).await {
+                            Ok(summary) => println!(
+                                "Link check: {} alive, {} dead, {} unreachable",
+                                summary.alive, summary.dead, summary.unreachable
+                            ),
+                            Err(e) => eprintln!("Link check failed: {}", e),
+                        }
+                    }
+                });
+            }
+            
+            // Prune old personal data every `retention.maintenance_interval_hours`
+            if let (Some(period), Some(db)) = (retention::interval(&app_config.retention), warmup_db.clone()) {
+                tauri::async_runtime::spawn(crash::guard("maintenance", run_maintenance(db, period)));
+            }
+            
+            if app_config.warm_probe_cache_on_start {
+                if let Some(db) = warmup_db {
+                    let app_handle = app.handle().clone();
+                    let ffmpeg = ffmpeg_service.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = spawn_probe_warmup(app_handle, db, ffmpeg, warmup::DEFAULT_CONCURRENCY).await {
+                            eprintln!("Failed to start probe cache warm-up: {}", e);
+                        }
+                    });
+                }
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             // MCP Server
             start_mcp_server,
             stop_mcp_server,
             get_mcp_server_status,
+            set_mcp_auto_start,
+            set_locale,
+            set_remote_connection,
+            check_for_updates,
+            get_crash_reports,
+            run_diagnostics,
+            get_usage_stats,
+            export_usage_report,
+            clear_usage_stats,
+            dismiss_crash_reports,
+            get_mcp_client_configs,
+            generate_mcp_client_config,
             check_port_availability,
             // m3u8 URL management
             set_current_m3u8_url,
+            get_stream_info,
+            get_interstitials,
+            get_playlist_graph,
+            simulate_abr,
+            detect_stream,
+            fingerprint_stream,
+            export_tool_catalog,
+            list_captures,
+            dismiss_capture,
+            clear_captures,
+            get_segment_archive,
+            reconstruct_from_archive,
+            delete_archived_recording,
             get_current_m3u8_url,
+            current_url_back,
+            current_url_forward,
+            get_pinned_url,
+            pin_url,
+            unpin_url,
             get_last_used_url,
             get_url_history,
             clear_url_history,
             // m3u8 operations
             parse_m3u8_url,
+            parse_m3u8_deep,
             extract_m3u8_segments,
             check_ffmpeg_installation,
             download_m3u8_stream,
             cancel_download,
+            list_jobs,
+            list_resumable_jobs,
+            resume_jobs,
+            get_job_progress,
+            get_job_log,
+            get_job_commands,
+            preview_download_command,
+            preview_convert_command,
+            get_host_speeds,
+            get_host_stats,
             convert_to_hls,
             probe_stream,
+            probe_video,
+            generate_thumbnails,
+            get_thumbnail_queue,
             // Configuration
             save_m3u8_config,
             load_m3u8_config,
+            choose_output_dir,
+            set_output_dir,
+            get_output_dir_info,
             // Database
             init_database,
             get_cache_stats,
-            clear_cache
+            clear_cache,
+            // Projects
+            create_project,
+            list_projects,
+            update_project,
+            delete_project,
+            get_project_downloads,
+            export_project,
+            // Downloaded files
+            get_download_history,
+            delete_download,
+            cleanup_downloads,
+            reconcile_downloads,
+            generate_download_report,
+            transcribe_download,
+            add_chapters,
+            warm_probe_cache,
+            get_link_statuses,
+            check_links,
+            get_link_snapshot,
+            list_playlist_snapshots,
+            get_playlist_snapshot,
+            apply_retention_policy,
+            wipe_personal_data,
+            list_trash,
+            restore_trash,
+            list_profiles,
+            save_profile,
+            delete_profile,
+            switch_profile
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,97 @@
+use futures::StreamExt;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::config::LinkCheckConfig;
+use crate::database::Database;
+use crate::error::AppError;
+use crate::history;
+use crate::m3u8_parser::{M3u8Error, M3u8Parser};
+
+const CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LinkCheckSummary {
+    pub checked: usize,
+    pub alive: usize,
+    pub dead: usize,
+    // Failed for another reason (timeout, DNS, 5xx) and may come back
+    pub unreachable: usize,
+    // Dead URLs removed from the history
+    pub archived: usize,
+}
+
+// Status codes meaning the playlist is gone rather than temporarily unavailable
+pub fn is_dead_status(code: u16) -> bool {
+    matches!(code, 403 | 404 | 410)
+}
+
+// Interval of the periodic check, if enabled
+pub fn interval(config: &LinkCheckConfig) -> Option<Duration> {
+    (config.interval_hours > 0).then(|| Duration::from_secs(config.interval_hours as u64 * 3600))
+}
+
+// Fetch each saved playlist and record whether it is alive, dead or
+// unreachable. Dead URLs are dropped from the history when `archive_dead` is set.
+pub async fn check_links(
+    db: &Database,
+    parser: &M3u8Parser,
+    config: &LinkCheckConfig,
+) -> Result<LinkCheckSummary, AppError> {
+    let urls = history::saved_urls(db)?;
+    let mut summary = LinkCheckSummary::default();
+    let mut dead_urls = Vec::new();
+
+    let mut results = futures::stream::iter(urls)
+        .map(|url| async move {
+            let result = parser.fetch_playlist(&url).await;
+            (url, result)
+        })
+        .buffer_unordered(CONCURRENCY);
+
+    while let Some((url, result)) = results.next().await {
+        summary.checked += 1;
+        let recorded = match result {
+            Ok(playlist) => {
+                summary.alive += 1;
+                let snapshot = config.keep_snapshots.then_some(playlist.as_str());
+                db.record_link_check(&url, Some(200), None, false, snapshot)
+            }
+            Err(M3u8Error::HttpStatus(code)) if is_dead_status(code) => {
+                summary.dead += 1;
+                dead_urls.push(url.clone());
+                db.record_link_check(&url, Some(code), Some(&format!("HTTP error: {}", code)), true, None)
+            }
+            Err(e) => {
+                summary.unreachable += 1;
+                let code = match e {
+                    M3u8Error::HttpStatus(code) => Some(code),
+                    _ => None,
+                };
+                db.record_link_check(&url, code, Some(&e.to_string()), false, None)
+            }
+        };
+        if let Err(e) = recorded {
+            eprintln!("Failed to record link status for {}: {}", url, e);
+        }
+    }
+
+    if config.archive_dead && !dead_urls.is_empty() {
+        summary.archived = history::remove(&dead_urls)?;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_dead_status() {
+        assert!(is_dead_status(404));
+        assert!(is_dead_status(403));
+        assert!(!is_dead_status(503));
+        assert!(!is_dead_status(429));
+    }
+}
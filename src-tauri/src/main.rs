@@ -2,5 +2,15 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
-    m3u8_mcp_lib::run()
+    let args: Vec<String> = std::env::args().collect();
+    // `--export-catalog [dir]` writes the tool catalog and exits
+    if let Some(index) = args.iter().position(|arg| arg == "--export-catalog") {
+        std::process::exit(m3u8_mcp_lib::export_catalog(args.get(index + 1).map(String::as_str)));
+    }
+    // `--headless` runs the engine as a service for remote UIs
+    if args.iter().any(|arg| arg == "--headless") {
+        m3u8_mcp_lib::run_headless()
+    } else {
+        m3u8_mcp_lib::run()
+    }
 }
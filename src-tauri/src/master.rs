@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use crate::error::AppError;
+use crate::m3u8_parser::{self, MasterPlaylist};
+
+// URI of a variant or rendition as written in a master playlist at `dir`.
+// Local files inside `dir` become relative paths with forward slashes so the
+// folder can be moved or served as is; URLs and other paths are kept.
+pub fn playlist_uri(uri: &str, dir: &Path) -> String {
+    if uri.contains("://") {
+        return uri.to_string();
+    }
+    match Path::new(uri).strip_prefix(dir) {
+        Ok(relative) if Path::new(uri).is_absolute() => relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        _ => uri.replace('\\', "/"),
+    }
+}
+
+// Write a master playlist to `output` and return its text
+pub fn generate_master(output: &Path, mut playlist: MasterPlaylist) -> Result<String, AppError> {
+    let dir = output.parent().unwrap_or(Path::new(""));
+    for variant in &mut playlist.variants {
+        variant.uri = playlist_uri(&variant.uri, dir);
+    }
+    for rendition in &mut playlist.renditions {
+        rendition.uri = rendition.uri.as_deref().map(|uri| playlist_uri(uri, dir));
+    }
+
+    let text = m3u8_parser::write_master_playlist(&playlist)
+        .map_err(|e| AppError::invalid_input(e.to_string()))?;
+    if !dir.as_os_str().is_empty() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(output, &text)?;
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playlist_uri() {
+        let dir = Path::new("/srv/hls");
+        assert_eq!(playlist_uri("/srv/hls/720p/index.m3u8", dir), "720p/index.m3u8");
+        assert_eq!(playlist_uri("https://cdn.example.com/720p.m3u8", dir), "https://cdn.example.com/720p.m3u8");
+        assert_eq!(playlist_uri("audio/en.m3u8", dir), "audio/en.m3u8");
+        assert_eq!(playlist_uri("/other/720p.m3u8", dir), "/other/720p.m3u8");
+    }
+}
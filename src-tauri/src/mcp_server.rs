@@ -1,9 +1,9 @@
 use axum::{
-    body::Bytes,
-    extract::State,
+    body::{Body, Bytes},
+    extract::{ConnectInfo, Path, Query, State},
     http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response, sse::{Event, Sse}},
-    routing::post,
+    routing::{get, post},
     Json, Router,
 };
 use futures::stream::{self};
@@ -15,14 +15,22 @@ use serde_json::{json, Value};
 use std::{
     collections::{HashMap, HashSet},
     convert::Infallible,
+    net::SocketAddr,
     sync::Arc,
     time::{Duration, SystemTime},
 };
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
 use tower_http::cors::CorsLayer;
 
+use crate::error::AppError;
+use crate::ffmpeg_wrapper::{self, FFmpegService};
+use crate::tool_queue::ToolQueue;
+
 // MCP Protocol Version
-const MCP_PROTOCOL_VERSION: &str = "2025-03-26";
+pub const MCP_PROTOCOL_VERSION: &str = "2025-03-26";
+
+// Header carrying the session id given out by initialize
+const SESSION_HEADER: &str = "mcp-session-id";
 
 // Session data structure
 #[derive(Debug, Clone)]
@@ -34,6 +42,18 @@ pub struct Session {
     pub last_event_id: u64,
     pub tools: Vec<Tool>,
     pub resources: Vec<Resource>,
+    // Set by m3u8_set_url, so clients do not overwrite each other's
+    pub urls: crate::current_url::UrlHistory,
+    // Whether the client answers roots/list, per its initialize capabilities
+    pub roots_capable: bool,
+    // Workspace roots from the client's last roots/list answer; relative
+    // output paths go under the first
+    pub roots: Vec<std::path::PathBuf>,
+    // Set until roots/list is answered, and again by
+    // notifications/roots/list_changed
+    pub roots_stale: bool,
+    // Messages for the client's GET /mcp event stream, while one is open
+    pub outgoing: Option<mpsc::UnboundedSender<Value>>,
 }
 
 // Tool definition - matches MCP schema
@@ -86,17 +106,32 @@ pub struct JsonRpcError {
 // Server state
 pub struct McpServerState {
     pub sessions: Arc<RwLock<HashMap<String, Session>>>,
+    pub host: String,
     pub port: u16,
     pub running: Arc<Mutex<bool>>,
     pub enabled_tools: Arc<RwLock<Vec<String>>>,
+    pub ffmpeg: Arc<FFmpegService>,
+    // Required to download artifacts
+    pub auth_token: String,
+    pub tool_queue: Arc<ToolQueue>,
+    // Requests sent to clients over their event stream, by id, waiting for
+    // the answer
+    pub pending: Arc<Mutex<HashMap<String, oneshot::Sender<Value>>>>,
+}
+
+fn tool_queue() -> Arc<ToolQueue> {
+    let config = crate::config::load_config().map(|c| c.mcp).unwrap_or_default();
+    Arc::new(ToolQueue::new(config.workers, config.max_queued))
 }
 
 impl McpServerState {
-    pub fn new(port: u16) -> Self {
+    pub fn new(host: String, port: u16, ffmpeg: Arc<FFmpegService>) -> Self {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            host,
             port,
             running: Arc::new(Mutex::new(false)),
+            ffmpeg,
             enabled_tools: Arc::new(RwLock::new(vec![
                 "m3u8_parse".to_string(),
                 "m3u8_download".to_string(),
@@ -104,17 +139,68 @@ impl McpServerState {
                 "m3u8_probe".to_string(),
                 "m3u8_extract_segments".to_string(),
             ])),
+            auth_token: uuid::Uuid::new_v4().simple().to_string(),
+            tool_queue: tool_queue(),
+            pending: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    pub fn new_with_tools(port: u16, tools: Vec<String>) -> Self {
+    pub fn new_with_tools(
+        host: String,
+        port: u16,
+        tools: Vec<String>,
+        ffmpeg: Arc<FFmpegService>,
+        auth_token: String,
+    ) -> Self {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            host,
             port,
             running: Arc::new(Mutex::new(false)),
             enabled_tools: Arc::new(RwLock::new(tools)),
+            ffmpeg,
+            auth_token,
+            tool_queue: tool_queue(),
+            pending: Arc::new(Mutex::new(HashMap::new())),
         }
     }
+
+    // Link to a job's artifacts on this server
+    fn artifacts_url(&self, job_id: &str) -> String {
+        crate::artifacts::artifacts_url(&self.host, self.port, job_id)
+    }
+
+    fn key_url(&self, job_id: &str) -> String {
+        crate::artifacts::key_url(&self.host, self.port, job_id)
+    }
+}
+
+// Build a plain JSON-RPC error response
+fn error_response(request_id: Option<Value>, code: i32, message: impl Into<String>) -> JsonRpcResponse {
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: request_id,
+        result: None,
+        error: Some(JsonRpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }),
+    }
+}
+
+// Build an error response that carries the typed AppError in the data field
+fn app_error_response(request_id: Option<Value>, context: &str, error: AppError) -> JsonRpcResponse {
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: request_id,
+        result: None,
+        error: Some(JsonRpcError {
+            code: error.jsonrpc_code(),
+            message: format!("{}: {}", context, error),
+            data: Some(error.to_value()),
+        }),
+    }
 }
 
 // Generate session ID
@@ -122,36 +208,436 @@ fn generate_session_id() -> String {
     uuid::Uuid::new_v4().to_string()
 }
 
-// Start MCP server
-pub async fn start_mcp_server(state: Arc<McpServerState>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let addr = format!("0.0.0.0:{}", state.port);
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    
-    println!("MCP Server starting on {}", addr);
+// Bind the MCP listener. Port 0 lets the OS pick a free port; read the
+// actual port back from the listener's local address.
+pub async fn bind_listener(host: &str, port: u16) -> std::io::Result<tokio::net::TcpListener> {
+    tokio::net::TcpListener::bind((host, port)).await
+}
+
+// Start MCP server on an already bound listener
+pub async fn start_mcp_server(
+    state: Arc<McpServerState>,
+    listener: tokio::net::TcpListener,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    println!("MCP Server starting on {}", listener.local_addr()?);
     *state.running.lock().await = true;
     
     let app = Router::new()
-        .route("/mcp", post(handle_sse_endpoint))
+        .route("/mcp", post(handle_sse_endpoint).get(handle_mcp_stream))
         .route("/sse", post(handle_sse_endpoint))  // Keep for backward compatibility
+        .route("/healthz", get(handle_healthz))
+        .route("/readyz", get(handle_readyz))
+        .route("/artifacts/:job_id", get(handle_artifact_list))
+        .route("/artifacts/:job_id/:index", get(handle_artifact_file))
+        .route("/keys/:job_id", get(handle_key))
+        .route("/api/jobs", get(handle_api_jobs))
+        .route("/api/jobs/:job_id", axum::routing::delete(handle_api_cancel))
+        .route("/api/parse", post(handle_api_parse))
+        .route("/api/downloads", post(handle_api_download))
+        .route("/api/events", get(handle_api_events))
+        .route("/api/captures", post(handle_api_capture))
         .layer(CorsLayer::permissive())
         .with_state(state.clone());
     
-    axum::serve(listener, app)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
     
     Ok(())
 }
 
+// Liveness probe: the HTTP server is up and answering
+async fn handle_healthz(State(state): State<Arc<McpServerState>>) -> Response {
+    let sessions = state.sessions.read().await.len();
+    Json(json!({
+        "status": "ok",
+        "running": *state.running.lock().await,
+        "host": state.host,
+        "port": state.port,
+        "version": env!("CARGO_PKG_VERSION"),
+        "sessions": sessions,
+        "tool_queue": state.tool_queue.status()
+    })).into_response()
+}
+
+// Readiness probe: the database and FFmpeg are usable, so tool calls can succeed
+async fn handle_readyz(State(state): State<Arc<McpServerState>>) -> Response {
+    let database = {
+        let db_guard = crate::database::GLOBAL_DB.read().await;
+        match *db_guard {
+            Some(ref db) => match db.ping() {
+                Ok(()) => json!({ "ok": true }),
+                Err(e) => json!({ "ok": false, "error": e.to_string() }),
+            },
+            None => json!({ "ok": false, "error": "Database not initialized" }),
+        }
+    };
+
+    // FFmpeg check spawns a process, keep it off the async workers
+    let ffmpeg_service = state.ffmpeg.clone();
+    let ffmpeg = match tokio::task::spawn_blocking(move || ffmpeg_service.check_installation()).await {
+        Ok(Ok(version)) => json!({ "ok": true, "version": version }),
+        Ok(Err(e)) => json!({ "ok": false, "error": e.to_string() }),
+        Err(e) => json!({ "ok": false, "error": e.to_string() }),
+    };
+
+    let ready = database["ok"] == json!(true) && ffmpeg["ok"] == json!(true);
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+    (status, Json(json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "running": *state.running.lock().await,
+        "database": database,
+        "ffmpeg": ffmpeg
+    }))).into_response()
+}
+
+// Accept "Authorization: Bearer <token>" or, for players and browsers that
+// cannot set headers, "?token=<token>"
+fn is_authorized(state: &McpServerState, headers: &HeaderMap, query: &HashMap<String, String>) -> bool {
+    let given = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or(query.get("token").map(|t| t.as_str()));
+    given.is_some_and(|token| crate::artifacts::token_matches(&state.auth_token, token.trim()))
+}
+
+fn unauthorized() -> Response {
+    (StatusCode::UNAUTHORIZED, Json(json!({ "error": "Missing or invalid token" }))).into_response()
+}
+
+// Artifacts of a job that still exist on disk, in the order they were produced
+async fn job_artifacts(job_id: &str) -> Result<Vec<crate::database::Artifact>, Response> {
+    let db = crate::database::global_db().await
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, Json(e.to_value())).into_response())?;
+    let artifacts = db.get_artifacts(job_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(AppError::from(e).to_value())).into_response())?;
+    if artifacts.is_empty() {
+        return Err((StatusCode::NOT_FOUND, Json(json!({ "error": format!("No artifacts for job {}", job_id) }))).into_response());
+    }
+    Ok(artifacts)
+}
+
+// List a job's artifacts with their download URLs
+async fn handle_artifact_list(
+    State(state): State<Arc<McpServerState>>,
+    Path(job_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    if !is_authorized(&state, &headers, &query) {
+        return unauthorized();
+    }
+    let artifacts = match job_artifacts(&job_id).await {
+        Ok(artifacts) => artifacts,
+        Err(response) => return response,
+    };
+
+    let base = state.artifacts_url(&job_id);
+    let entries: Vec<Value> = artifacts.iter().enumerate().map(|(index, artifact)| {
+        let path = std::path::Path::new(&artifact.path);
+        json!({
+            "index": index,
+            "kind": artifact.kind,
+            "name": path.file_name().map(|n| n.to_string_lossy().to_string()),
+            "size": std::fs::metadata(path).ok().map(|m| m.len()),
+            "content_type": crate::artifacts::content_type(path),
+            "created_at": artifact.created_at.to_rfc3339(),
+            "url": format!("{}/{}", base, index)
+        })
+    }).collect();
+    Json(json!({ "job_id": job_id, "artifacts": entries })).into_response()
+}
+
+// AES-128 key of a conversion run with serve_key
+async fn handle_key(
+    State(state): State<Arc<McpServerState>>,
+    Path(job_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    use axum::http::header;
+
+    if !is_authorized(&state, &headers, &query) {
+        return unauthorized();
+    }
+    let artifacts = match job_artifacts(&job_id).await {
+        Ok(artifacts) => artifacts,
+        Err(response) => return response,
+    };
+    let Some(key) = artifacts.iter().find(|artifact| artifact.kind == "key") else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": format!("Job {} has no served key", job_id) }))).into_response();
+    };
+    match tokio::fs::read(&key.path).await {
+        Ok(bytes) => (
+            [(header::CONTENT_TYPE, "application/octet-stream"), (header::CACHE_CONTROL, "no-store")],
+            bytes,
+        ).into_response(),
+        Err(e) => (StatusCode::GONE, Json(json!({ "error": format!("{} is no longer available: {}", key.path, e) }))).into_response(),
+    }
+}
+
+// Stream one artifact, honouring a single Range request so players can seek
+async fn handle_artifact_file(
+    State(state): State<Arc<McpServerState>>,
+    Path((job_id, index)): Path<(String, usize)>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    use axum::http::header;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    if !is_authorized(&state, &headers, &query) {
+        return unauthorized();
+    }
+    let artifacts = match job_artifacts(&job_id).await {
+        Ok(artifacts) => artifacts,
+        Err(response) => return response,
+    };
+    let Some(artifact) = artifacts.get(index) else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": format!("Job {} has no artifact {}", job_id, index) }))).into_response();
+    };
+
+    let path = std::path::PathBuf::from(&artifact.path);
+    let mut file = match tokio::fs::File::open(&path).await {
+        Ok(file) => file,
+        Err(e) => {
+            return (StatusCode::GONE, Json(json!({ "error": format!("{} is no longer available: {}", path.display(), e) }))).into_response();
+        }
+    };
+    let len = match file.metadata().await {
+        Ok(metadata) => metadata.len(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response(),
+    };
+    let name = path.file_name().map(|n| n.to_string_lossy().replace('"', "")).unwrap_or_default();
+
+    let range = headers.get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| crate::artifacts::parse_range(v, len));
+    let (status, start, end) = match range {
+        Some((start, end)) => (StatusCode::PARTIAL_CONTENT, start, end),
+        None => (StatusCode::OK, 0, len.saturating_sub(1)),
+    };
+    if start > 0 {
+        if let Err(e) = file.seek(std::io::SeekFrom::Start(start)).await {
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response();
+        }
+    }
+    let length = if len == 0 { 0 } else { end - start + 1 };
+    let body = Body::from_stream(tokio_util::io::ReaderStream::new(file.take(length)));
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, crate::artifacts::content_type(&path))
+        .header(header::CONTENT_LENGTH, length)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", name));
+    if status == StatusCode::PARTIAL_CONTENT {
+        response = response.header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len));
+    }
+    response.body(body).unwrap_or_else(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response()
+    })
+}
+
+// Job API: lets a UI on another machine drive this instance. Every route
+// takes the same token as the artifact routes.
+
+#[derive(Debug, Deserialize)]
+struct ParseRequest {
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DownloadRequest {
+    url: String,
+    // A path on this machine; defaults to the configured output directory
+    output_path: Option<String>,
+    page_url: Option<String>,
+    project_id: Option<i32>,
+    engine: Option<crate::config::DownloadEngine>,
+    #[serde(default)]
+    mirrors: Vec<String>,
+    retry: Option<crate::config::RetryPolicy>,
+    audio: Option<crate::config::AudioTracks>,
+    interstitials: Option<crate::config::InterstitialMode>,
+    // Download even if the stream is already on disk
+    #[serde(default)]
+    force: bool,
+}
+
+// AppError as a JSON body, so remote clients see the same shape as Tauri commands
+fn api_error(error: AppError) -> Response {
+    use crate::error::ErrorKind;
+    let status = match error.kind {
+        ErrorKind::InvalidInput => StatusCode::BAD_REQUEST,
+        ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        ErrorKind::Conflict => StatusCode::CONFLICT,
+        ErrorKind::NotInitialized | ErrorKind::MissingDependency => StatusCode::SERVICE_UNAVAILABLE,
+        ErrorKind::Network | ErrorKind::Parse => StatusCode::BAD_GATEWAY,
+        ErrorKind::Timeout => StatusCode::GATEWAY_TIMEOUT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(error.to_value())).into_response()
+}
+
+async fn handle_api_jobs(
+    State(state): State<Arc<McpServerState>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    if !is_authorized(&state, &headers, &query) {
+        return unauthorized();
+    }
+    Json(state.ffmpeg.list_jobs().await).into_response()
+}
+
+async fn handle_api_parse(
+    State(state): State<Arc<McpServerState>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(request): Json<ParseRequest>,
+) -> Response {
+    if !is_authorized(&state, &headers, &query) {
+        return unauthorized();
+    }
+    let parser = Arc::new(crate::profiles::parser());
+    match parser.fetch_and_parse(&request.url).await {
+        Ok((text, playlist)) => {
+            crate::snapshots::spawn_capture(parser, &request.url, "parse", HashMap::from([(request.url.clone(), text)]));
+            Json(playlist).into_response()
+        }
+        Err(e) => api_error(e.into()),
+    }
+}
+
+// Start a download in the background. Progress, completion and errors are
+// reported as "download-progress" events on /api/events.
+async fn handle_api_download(
+    State(state): State<Arc<McpServerState>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(request): Json<DownloadRequest>,
+) -> Response {
+    if !is_authorized(&state, &headers, &query) {
+        return unauthorized();
+    }
+    let parser = Arc::new(crate::profiles::parser());
+    if !request.force {
+        match crate::duplicates::find_existing(&parser, &request.url).await {
+            Ok(Some(existing)) => return api_error(crate::duplicates::conflict(&request.url, &existing)),
+            Ok(None) => {}
+            Err(e) => eprintln!("Duplicate check failed for {}: {}", request.url, e),
+        }
+    }
+    let output = match (request.output_path.as_deref(), request.project_id) {
+        (Some(path), _) => Some(crate::paths::expand_path(path)),
+        (None, Some(id)) => match crate::database::project_output_dir(id).await {
+            Ok(dir) => dir,
+            Err(e) => return api_error(e),
+        },
+        (None, None) => None,
+    };
+
+    let job_id = ffmpeg_wrapper::new_job_id();
+    let spec = crate::journal::JobSpec {
+        url: request.url,
+        output_path: output,
+        page_url: request.page_url,
+        project_id: request.project_id,
+        engine: request.engine,
+        mirrors: request.mirrors,
+        retry: request.retry,
+        audio: request.audio,
+        interstitials: request.interstitials,
+    };
+    crate::journal::spawn(parser, state.ffmpeg.clone(), job_id.clone(), spec);
+
+    (StatusCode::ACCEPTED, Json(json!({
+        "job_id": job_id,
+        "artifacts_url": state.artifacts_url(&job_id)
+    }))).into_response()
+}
+
+async fn handle_api_cancel(
+    State(state): State<Arc<McpServerState>>,
+    Path(job_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    if !is_authorized(&state, &headers, &query) {
+        return unauthorized();
+    }
+    match state.ffmpeg.cancel_download(Some(&job_id)).await {
+        Ok(()) => Json(json!({ "job_id": job_id, "message": crate::i18n::t(crate::i18n::Msg::DownloadCancelled) })).into_response(),
+        Err(e) => api_error(e.into()),
+    }
+}
+
+// A stream URL from the companion browser extension, queued as a pending
+// capture for the UI and the m3u8://captures resource. Only taken from this
+// machine, even when the server listens on the LAN for the job API.
+async fn handle_api_capture(
+    State(state): State<Arc<McpServerState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    Json(request): Json<crate::captures::CaptureRequest>,
+) -> Response {
+    if !peer.ip().is_loopback() {
+        return (StatusCode::FORBIDDEN, Json(json!({ "error": "Captures are only accepted from localhost" }))).into_response();
+    }
+    if !is_authorized(&state, &headers, &query) {
+        return unauthorized();
+    }
+    match crate::captures::add(request) {
+        Ok(capture) => {
+            state.ffmpeg.emit(crate::events::CAPTURE_RECEIVED, crate::events::CaptureEvent::new(capture.clone()));
+            (StatusCode::ACCEPTED, Json(json!({ "id": capture.id }))).into_response()
+        }
+        Err(e) => api_error(e),
+    }
+}
+
+// Server-sent events mirroring what the desktop UI receives from Tauri.
+// EventSource cannot set headers, so browsers pass ?token=.
+async fn handle_api_events(
+    State(state): State<Arc<McpServerState>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    use tokio::sync::broadcast::error::RecvError;
+
+    if !is_authorized(&state, &headers, &query) {
+        return unauthorized();
+    }
+    let events = stream::unfold(state.ffmpeg.subscribe(), |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(message) => {
+                    let event = Event::default().event(message.event).data(message.payload.to_string());
+                    return Some((Ok::<_, Infallible>(event), receiver));
+                }
+                Err(RecvError::Lagged(skipped)) => eprintln!("Event stream lagged, skipped {} events", skipped),
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(events)
+        .keep_alive(axum::response::sse::KeepAlive::default())
+        .into_response()
+}
+
 // SSE endpoint handler - handles the MCP protocol over SSE
 async fn handle_sse_endpoint(
     State(state): State<Arc<McpServerState>>,
     headers: HeaderMap,
     body: Bytes,
 ) -> Response {
-    // Parse the incoming JSON-RPC request
-    let request: JsonRpcRequest = match serde_json::from_slice(&body) {
-        Ok(req) => req,
+    // Parse the incoming JSON-RPC message
+    let message: Value = match serde_json::from_slice(&body) {
+        Ok(message) => message,
         Err(e) => {
             let error_response = JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
@@ -167,26 +653,137 @@ async fn handle_sse_endpoint(
         }
     };
 
+    // A client's answer to a request sent over its event stream
+    if message.get("method").is_none() && (message.get("result").is_some() || message.get("error").is_some()) {
+        deliver_answer(&state, message).await;
+        return StatusCode::ACCEPTED.into_response();
+    }
+    let request: JsonRpcRequest = match serde_json::from_value(message) {
+        Ok(request) => request,
+        Err(e) => return Json(error_response(None, -32600, format!("Invalid request: {}", e))).into_response(),
+    };
+
+    let session_id = headers.get(SESSION_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+
+    // Notifications are handled, but never answered
+    if request.id.is_none() {
+        handle_jsonrpc_request(state, request, session_id).await;
+        return StatusCode::ACCEPTED.into_response();
+    }
+
     // Handle the request
-    let response = handle_jsonrpc_request(state, request).await;
+    let response = handle_jsonrpc_request(state, request, session_id).await;
+    
+    // Clients send the id of the session initialize opened back in the header
+    let new_session = response.result.as_ref()
+        .and_then(|result| result.get("sessionId"))
+        .and_then(|v| v.as_str())
+        .and_then(|id| axum::http::HeaderValue::from_str(id).ok());
     
     // Return as JSON response for now
     // Full SSE implementation would stream responses
-    Json(response).into_response()
+    let mut response = Json(response).into_response();
+    if let Some(id) = new_session {
+        response.headers_mut().insert(SESSION_HEADER, id);
+    }
+    response
+}
+
+// Event stream of a session (GET /mcp) for what the server sends the client
+// on its own: requests such as roots/list. Opening another one replaces it.
+async fn handle_mcp_stream(
+    State(state): State<Arc<McpServerState>>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(session_id) = headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) else {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Send the Mcp-Session-Id header initialize returned" }))).into_response();
+    };
+    let (sender, receiver) = mpsc::unbounded_channel::<Value>();
+    match state.sessions.write().await.get_mut(session_id) {
+        Some(session) => session.outgoing = Some(sender),
+        None => return (StatusCode::NOT_FOUND, Json(json!({ "error": "No such session" }))).into_response(),
+    }
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        let message = receiver.recv().await?;
+        let event = Event::default().event("message").data(message.to_string());
+        Some((Ok::<_, Infallible>(event), receiver))
+    });
+    Sse::new(events)
+        .keep_alive(axum::response::sse::KeepAlive::default())
+        .into_response()
+}
+
+// Hand a client's answer to the request waiting for it
+async fn deliver_answer(state: &McpServerState, answer: Value) {
+    let Some(id) = answer.get("id").and_then(|id| id.as_str()) else {
+        return;
+    };
+    if let Some(waiting) = state.pending.lock().await.remove(id) {
+        let _ = waiting.send(answer);
+    }
+}
+
+// Send `method` to a session's client over its event stream and wait for
+// the result; None without an open stream, on an error or after `timeout`
+async fn request_client(state: &McpServerState, session_id: &str, method: &str, timeout: Duration) -> Option<Value> {
+    let outgoing = state.sessions.read().await.get(session_id)?.outgoing.clone()?;
+    let id = format!("{}-{}", method, uuid::Uuid::new_v4().simple());
+    let (sender, receiver) = oneshot::channel();
+    state.pending.lock().await.insert(id.clone(), sender);
+    let answer = match outgoing.send(json!({ "jsonrpc": "2.0", "id": id, "method": method })) {
+        Ok(()) => tokio::time::timeout(timeout, receiver).await.ok().and_then(Result::ok),
+        Err(_) => None,
+    };
+    state.pending.lock().await.remove(&id);
+    let answer = answer?;
+    if let Some(error) = answer.get("error") {
+        eprintln!("Client answered {} with an error: {}", method, error);
+        return None;
+    }
+    answer.get("result").cloned()
+}
+
+// First workspace root of a session. Once the session has an event stream,
+// the client is asked for its roots once, and again only after it says they
+// changed. If it does not answer within `timeout`, the roots it listed last
+// still apply, and later calls do not wait for it again.
+async fn session_root(state: &McpServerState, session_id: &str, timeout: Duration) -> Option<std::path::PathBuf> {
+    let stale = state.sessions.read().await.get(session_id).is_some_and(|session| {
+        session.roots_capable && session.roots_stale && session.outgoing.is_some()
+    });
+    if stale {
+        let listed = request_client(state, session_id, "roots/list", timeout).await;
+        let roots = listed.as_ref().and_then(crate::roots::parse_roots);
+        if roots.is_none() {
+            eprintln!("Session {} did not list its roots", session_id);
+        }
+        if let Some(session) = state.sessions.write().await.get_mut(session_id) {
+            if let Some(roots) = roots {
+                session.roots = roots;
+            }
+            session.roots_stale = false;
+        }
+    }
+    state.sessions.read().await.get(session_id)?.roots.first().cloned()
 }
 
 // Handle JSON-RPC request
 async fn handle_jsonrpc_request(
     state: Arc<McpServerState>,
     request: JsonRpcRequest,
+    session_id: Option<String>,
 ) -> JsonRpcResponse {
     match request.method.as_str() {
         "initialize" => handle_initialize(state, request.id, request.params).await,
-        "initialized" => handle_initialized(state, request.id).await,
+        "initialized" | "notifications/initialized" => handle_initialized(state, request.id).await,
+        "notifications/roots/list_changed" => handle_roots_changed(state, request.id, session_id).await,
         "tools/list" => handle_tools_list(state, request.id).await,
-        "tools/call" => handle_tools_call(state, request.id, request.params).await,
+        "tools/call" => handle_tools_call(state, request.id, request.params, session_id).await,
         "resources/list" => handle_resources_list(state, request.id).await,
         "resources/read" => handle_resources_read(state, request.id, request.params).await,
+        "completion/complete" => handle_completion(state, request.id, request.params).await,
         "ping" => handle_ping(request.id).await,
         _ => JsonRpcResponse {
             jsonrpc: "2.0".to_string(),
@@ -205,12 +802,15 @@ async fn handle_jsonrpc_request(
 async fn handle_initialize(
     state: Arc<McpServerState>,
     request_id: Option<Value>,
-    _params: Option<Value>,
+    params: Option<Value>,
 ) -> JsonRpcResponse {
     let session_id = generate_session_id();
     let enabled_tools = state.enabled_tools.read().await;
     let tools = get_available_tools(&enabled_tools);
     let resources = get_available_resources();
+    let roots_capable = params.as_ref()
+        .and_then(|params| params.pointer("/capabilities/roots"))
+        .is_some();
     
     let session = Session {
         id: session_id.clone(),
@@ -220,6 +820,11 @@ async fn handle_initialize(
         last_event_id: 0,
         tools: tools.clone(),
         resources: resources.clone(),
+        urls: Default::default(),
+        roots_capable,
+        roots: Vec::new(),
+        roots_stale: roots_capable,
+        outgoing: None,
     };
     
     let mut sessions = state.sessions.write().await;
@@ -233,11 +838,12 @@ async fn handle_initialize(
             "capabilities": {
                 "tools": {},
                 "resources": {},
+                "completions": {},
                 "logging": {}
             },
             "serverInfo": {
                 "name": "m3u8-mcp",
-                "version": "0.1.0"
+                "version": env!("CARGO_PKG_VERSION")
             },
             "sessionId": session_id
         })),
@@ -264,16 +870,43 @@ async fn handle_initialized(
     }
 }
 
+// Handle notifications/roots/list_changed: the session's roots are listed
+// again before its next tool call
+async fn handle_roots_changed(
+    state: Arc<McpServerState>,
+    request_id: Option<Value>,
+    session_id: Option<String>,
+) -> JsonRpcResponse {
+    let mut sessions = state.sessions.write().await;
+    if let Some(session) = session_id.as_ref().and_then(|id| sessions.get_mut(id)) {
+        session.roots_stale = true;
+    }
+    
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: request_id,
+        result: Some(json!({})),
+        error: None,
+    }
+}
+
 // Get available tools based on enabled list
 fn get_available_tools(enabled_tools: &[String]) -> Vec<Tool> {
     let enabled_tools_set: HashSet<_> = enabled_tools.iter().cloned().collect();
     
-    // Define all available tools
-    let all_tools = vec![
+    // Filter tools based on enabled list
+    all_tools().into_iter()
+        .filter(|tool| enabled_tools_set.contains(&tool.name))
+        .collect()
+}
+
+// Every tool the server defines, enabled or not
+pub fn all_tools() -> Vec<Tool> {
+    vec![
         // m3u8 URL management
         Tool {
             name: "m3u8_set_url".to_string(),
-            description: Some("Set the current m3u8 URL in the UI".to_string()),
+            description: Some("Set the current m3u8 URL for this session".to_string()),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -287,12 +920,46 @@ fn get_available_tools(enabled_tools: &[String]) -> Vec<Tool> {
         },
         Tool {
             name: "m3u8_get_url".to_string(),
-            description: Some("Get the current m3u8 URL from the UI".to_string()),
+            description: Some("Get the current m3u8 URL of this session, or the shared one, and the pinned URL".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {}
+            }),
+        },
+        Tool {
+            name: "m3u8_url_back".to_string(),
+            description: Some("Go back to the URL this session had before the current one".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {}
+            }),
+        },
+        Tool {
+            name: "m3u8_url_forward".to_string(),
+            description: Some("Go forward again to the URL m3u8_url_back left".to_string()),
             input_schema: json!({
                 "type": "object",
                 "properties": {}
             }),
         },
+        Tool {
+            name: "m3u8_pin_url".to_string(),
+            description: Some("Pin a URL, kept across restarts, to return to while the current URL changes".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "URL to pin; defaults to the current URL"
+                    },
+                    "unpin": {
+                        "type": "boolean",
+                        "description": "Remove the pinned URL instead",
+                        "default": false
+                    }
+                }
+            }),
+        },
         // m3u8 parsing and analysis
         Tool {
             name: "m3u8_parse".to_string(),
@@ -307,6 +974,40 @@ fn get_available_tools(enabled_tools: &[String]) -> Vec<Tool> {
                     "content": {
                         "type": "string",
                         "description": "Raw m3u8 content (if URL not provided)"
+                    },
+                    "summary": {
+                        "type": "boolean",
+                        "description": "Return a compact digest (playlist type, variant table, segment count, total duration, encryption, notable tags) instead of the full playlist",
+                        "default": false
+                    },
+                    "deep": {
+                        "type": "boolean",
+                        "description": "For a master playlist, also fetch every variant and rendition playlist concurrently and return each one's segment count, duration, media sequence and live/encrypted state, or the error it failed with (URL only)",
+                        "default": false
+                    },
+                    "tolerant": {
+                        "type": "boolean",
+                        "description": "Parse a malformed playlist as far as possible (missing #EXTM3U, #EXTINF without a URI, bad durations) and also return the issues worked around",
+                        "default": false
+                    },
+                    "include_stream_info": {
+                        "type": "boolean",
+                        "description": "Also read the ID3 title tags at the start of the first segment, which takes fetching a variant playlist and part of a segment (URL only)",
+                        "default": false
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "description": "Index of the first segment or variant to return",
+                        "default": 0
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Most segment or variants to return; large results are cut short anyway and point to the full result"
+                    },
+                    "store_result": {
+                        "type": "boolean",
+                        "description": "Store the full result and return its m3u8://results/ URI instead of the result",
+                        "default": false
                     }
                 }
             }),
@@ -323,20 +1024,60 @@ fn get_available_tools(enabled_tools: &[String]) -> Vec<Tool> {
                     },
                     "output_path": {
                         "type": "string",
-                        "description": "Output file path (required)"
+                        "description": "Output file path (required). An existing directory gets a file named after the page title or URL"
+                    },
+                    "page_url": {
+                        "type": "string",
+                        "description": "URL of the page hosting the stream; its og:title or <title> names the file and the download record"
+                    },
+                    "project_id": {
+                        "type": "number",
+                        "description": "Project to file the download under; its output directory is used when output_path is omitted"
                     },
                     "format": {
                         "type": "string",
                         "description": "Output format (mp4, mkv, ts)",
                         "default": "mp4"
+                    },
+                    "engine": {
+                        "type": "string",
+                        "enum": ["ffmpeg", "native"],
+                        "description": "Downloader to use (defaults to downloader.engine in config). The native downloader fetches segments itself and writes <output>.manifest.json with each segment's URL, size, SHA-256 and key/IV"
+                    },
+                    "mirrors": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Alternate base URLs serving the same files as the playlist's directory. Segment fetches are spread across them, falling back to the others on errors (native engine)"
+                    },
+                    "variant": {
+                        "type": ["string", "number"],
+                        "description": "Variant of a master playlist to download: its index, a height such as \"720p\", its URI, or \"auto\" for the highest bandwidth. Without it a master playlist returns the list of variants to choose from (unless mcp.variant_selection is \"auto\")"
+                    },
+                    "retry": {
+                        "type": "object",
+                        "description": "Retry policy for this job, overriding downloader.retry: max_attempts, initial_delay_secs, backoff_multiplier, max_delay_secs, retry_on (error kinds such as \"network\" or \"ffmpeg\")"
+                    },
+                    "audio": {
+                        "type": "object",
+                        "description": "Audio tracks to keep, overriding downloader.audio: languages (codes as in EXT-X-MEDIA LANGUAGE, preferred first; empty keeps every track) and split (also write each track to its own file)"
+                    },
+                    "interstitials": {
+                        "type": "string",
+                        "enum": ["skip", "separate", "include"],
+                        "description": "What to do with HLS interstitials (ads and promos scheduled by EXT-X-DATERANGE), overriding downloader.interstitials: skip them (primary content only), download their assets into separate files next to the output, or include them in the output where players would show them"
+                    },
+                    "force": {
+                        "type": "boolean",
+                        "description": "Download even if the same URL or the same media (matched by playlist fingerprint) was downloaded before and its file still exists. Without it such a request returns an already_downloaded answer with the existing path",
+                        "default": false
                     }
                 },
-                "required": ["url", "output_path"]
+                "required": ["url"]
             }),
         },
         Tool {
             name: "m3u8_convert".to_string(),
-            description: Some("Convert video to HLS format".to_string()),
+            description: Some("Convert video to HLS and/or DASH".to_string()),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -357,6 +1098,32 @@ fn get_available_tools(enabled_tools: &[String]) -> Vec<Tool> {
                         "type": "string",
                         "description": "Playlist type (vod or event)",
                         "default": "vod"
+                    },
+                    "manifests": {
+                        "type": "string",
+                        "enum": ["hls", "dash", "both"],
+                        "description": "Write HLS playlists, a DASH MPD, or both sharing the same fMP4 segments",
+                        "default": "hls"
+                    },
+                    "hls_segment_type": {
+                        "type": "string",
+                        "enum": ["mpegts", "fmp4"],
+                        "description": "Segment container: MPEG-TS, or fragmented MP4 (CMAF) with an init segment",
+                        "default": "mpegts"
+                    },
+                    "encrypt": {
+                        "type": "boolean",
+                        "description": "Encrypt segments with AES-128 using a generated key, written as playlist.key",
+                        "default": false
+                    },
+                    "key_uri": {
+                        "type": "string",
+                        "description": "URI of the key in EXT-X-KEY, e.g. where it will be served (default: playlist.key next to the playlist, or the served key's URL with serve_key)"
+                    },
+                    "serve_key": {
+                        "type": "boolean",
+                        "description": "Serve the key from this server at /keys/<job_id>, behind the artifact token",
+                        "default": false
                     }
                 },
                 "required": ["input_path", "output_dir"]
@@ -371,6 +1138,16 @@ fn get_available_tools(enabled_tools: &[String]) -> Vec<Tool> {
                     "url": {
                         "type": "string",
                         "description": "URL of the m3u8 stream"
+                    },
+                    "structured": {
+                        "type": "boolean",
+                        "description": "Return video metadata (frame rate, field order, bit depth, color primaries, HDR transfer) per variant, flagging VIDEO-RANGE mismatches, instead of raw ffprobe output",
+                        "default": false
+                    },
+                    "store_result": {
+                        "type": "boolean",
+                        "description": "Store the full result and return its m3u8://results/ URI instead of the result",
+                        "default": false
                     }
                 },
                 "required": ["url"]
@@ -389,16 +1166,681 @@ fn get_available_tools(enabled_tools: &[String]) -> Vec<Tool> {
                     "base_url": {
                         "type": "string",
                         "description": "Base URL for relative segment URLs"
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "description": "Index of the first segment to return",
+                        "default": 0
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Most segments to return; large results are cut short anyway and point to the full result"
+                    },
+                    "store_result": {
+                        "type": "boolean",
+                        "description": "Store the full result and return its m3u8://results/ URI instead of the result",
+                        "default": false
+                    },
+                    "export_path": {
+                        "type": "string",
+                        "description": "Write the segment list to this file instead of returning it; relative paths go under the output directory"
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["list", "csv", "aria2c"],
+                        "description": "Format of the exported file: one URL per line (wget -i), CSV with durations and byte ranges, or aria2c -i input",
+                        "default": "list"
                     }
                 }
             }),
         },
-    ];
-    
-    // Filter tools based on enabled list
-    all_tools.into_iter()
-        .filter(|tool| enabled_tools_set.contains(&tool.name))
-        .collect()
+        Tool {
+            name: "m3u8_stats".to_string(),
+            description: Some("Compute statistics and anomalies for a media playlist: segment duration min/max/avg/variance, short and long segments, discontinuities, EXT-X-GAP segments and an estimated bitrate profile".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "URL of the m3u8 playlist (a master playlist uses its first variant)"
+                    },
+                    "sample_sizes": {
+                        "type": "boolean",
+                        "description": "Issue HEAD requests for a sample of segments to estimate the bitrate profile",
+                        "default": false
+                    },
+                    "max_samples": {
+                        "type": "number",
+                        "description": "Maximum number of segments to sample",
+                        "default": 20
+                    }
+                },
+                "required": ["url"]
+            }),
+        },
+        Tool {
+            name: "m3u8_compatibility".to_string(),
+            description: Some("Report which variants of a playlist play on common target devices (Safari macOS/iOS, Chrome, Firefox, Android, Chromecast, smart TVs) based on their CODECS and a built-in compatibility table".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "URL of the m3u8 playlist"
+                    },
+                    "targets": {
+                        "type": "array",
+                        "items": {
+                            "type": "string",
+                            "enum": ["safari_macos", "safari_ios_14", "safari_ios_17", "chrome_desktop", "firefox_desktop", "android_legacy", "android", "chromecast", "smart_tv"]
+                        },
+                        "description": "Devices to check (default: all)"
+                    },
+                    "probe": {
+                        "type": "boolean",
+                        "description": "Probe streams with ffprobe when the playlist has no CODECS attribute",
+                        "default": false
+                    }
+                },
+                "required": ["url"]
+            }),
+        },
+        Tool {
+            name: "m3u8_loudness".to_string(),
+            description: Some("Measure integrated loudness (LUFS), true peak and loudness range (LRA) of a stream or downloaded file with FFmpeg's ebur128 and volumedetect filters, optionally writing a loudness-normalized copy".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "input": {
+                        "type": "string",
+                        "description": "Stream URL or path to a downloaded file"
+                    },
+                    "normalize": {
+                        "type": "boolean",
+                        "description": "Also write a normalized copy with FFmpeg's loudnorm filter",
+                        "default": false
+                    },
+                    "output_path": {
+                        "type": "string",
+                        "description": "Output file for the normalized copy (required when normalize is true)"
+                    },
+                    "target_i": {
+                        "type": "number",
+                        "description": "Target integrated loudness in LUFS",
+                        "default": -24
+                    },
+                    "target_tp": {
+                        "type": "number",
+                        "description": "Maximum true peak in dBTP",
+                        "default": -2
+                    },
+                    "target_lra": {
+                        "type": "number",
+                        "description": "Target loudness range in LU",
+                        "default": 7
+                    }
+                },
+                "required": ["input"]
+            }),
+        },
+        Tool {
+            name: "m3u8_scenes".to_string(),
+            description: Some("Detect scene changes in a stream or downloaded video with FFmpeg and return scene start timestamps, optionally exporting a thumbnail or clip per scene".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "input": {
+                        "type": "string",
+                        "description": "Stream URL or path to a downloaded file"
+                    },
+                    "threshold": {
+                        "type": "number",
+                        "description": "Scene change score between 0 and 1; lower values detect more cuts",
+                        "default": 0.4
+                    },
+                    "max_scenes": {
+                        "type": "number",
+                        "description": "Maximum number of scenes to return",
+                        "default": 100
+                    },
+                    "export": {
+                        "type": "string",
+                        "enum": ["none", "thumbnails", "clips"],
+                        "description": "Export a JPEG thumbnail or a stream-copied clip per scene",
+                        "default": "none"
+                    },
+                    "output_dir": {
+                        "type": "string",
+                        "description": "Directory for exported files (required when export is not none)"
+                    }
+                },
+                "required": ["input"]
+            }),
+        },
+        Tool {
+            name: "m3u8_grab_frame".to_string(),
+            description: Some("Grab a single frame from a stream or downloaded file at a timestamp and return it as an image, to visually check a stream before downloading it".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "input": {
+                        "type": "string",
+                        "description": "Stream URL or path to a downloaded file"
+                    },
+                    "timestamp": {
+                        "type": "number",
+                        "description": "Position in seconds (default: where a player would start: the stream's EXT-X-START, a few segments from the live edge of a live stream, or 0)"
+                    },
+                    "max_width": {
+                        "type": "number",
+                        "description": "Downscale frames wider than this many pixels",
+                        "default": 1280
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["jpeg", "png"],
+                        "default": "jpeg"
+                    }
+                },
+                "required": ["input"]
+            }),
+        },
+        Tool {
+            name: "m3u8_fingerprint".to_string(),
+            description: Some("Fingerprint what a stream shows from a few sampled frames and compare it with streams fingerprinted before, to tell that two URLs serve the same content before downloading both".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "URL of a master or media playlist"
+                    },
+                    "samples": {
+                        "type": "number",
+                        "description": "Frames to sample, spread over the stream (1-20). Only fingerprints with the same number are compared",
+                        "default": 5
+                    },
+                    "compare_with": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Other playlist URLs to fingerprint first, so the result compares against them"
+                    }
+                },
+                "required": ["url"]
+            }),
+        },
+        Tool {
+            name: "m3u8_report".to_string(),
+            description: Some("Summarize downloads in a date range as JSON or CSV: URL, duration, size, time taken, average speed and verification status".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "from": {
+                        "type": "string",
+                        "description": "Start date (YYYY-MM-DD or RFC 3339)"
+                    },
+                    "to": {
+                        "type": "string",
+                        "description": "End date, inclusive (YYYY-MM-DD or RFC 3339)"
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["json", "csv"],
+                        "default": "json"
+                    }
+                }
+            }),
+        },
+        Tool {
+            name: "m3u8_transcribe".to_string(),
+            description: Some("Transcribe a downloaded recording with the configured Whisper binary or HTTP endpoint and store the transcript next to the file".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "download_id": {
+                        "type": "number",
+                        "description": "ID of a recorded download"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "Path to a downloaded file, instead of download_id"
+                    }
+                }
+            }),
+        },
+        Tool {
+            name: "m3u8_chapters".to_string(),
+            description: Some("Embed chapter markers in a downloaded MP4/MKV, from the stream's EXT-X-DATERANGE events or from a given list (for example topics found in a transcript)".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "download_id": {
+                        "type": "number",
+                        "description": "ID of a recorded download; its stream URL is used for EXT-X-DATERANGE"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "Path to a downloaded file, instead of download_id"
+                    },
+                    "url": {
+                        "type": "string",
+                        "description": "Stream URL to read EXT-X-DATERANGE events from, when using path"
+                    },
+                    "chapters": {
+                        "type": "array",
+                        "description": "Chapters to embed instead of the stream's date ranges",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "start": { "type": "number", "description": "Start in seconds" },
+                                "end": { "type": "number", "description": "End in seconds (defaults to the next chapter)" },
+                                "title": { "type": "string" }
+                            },
+                            "required": ["start", "title"]
+                        }
+                    }
+                }
+            }),
+        },
+        Tool {
+            name: "m3u8_cache_stats".to_string(),
+            description: Some("Show counts and sizes of cached playlists, probe results, stream fingerprints and download records".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {}
+            }),
+        },
+        Tool {
+            name: "m3u8_cache_list".to_string(),
+            description: Some("List cached playlists, newest first".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "Only playlists whose URL or content contains this text"
+                    },
+                    "limit": {
+                        "type": "number",
+                        "description": "Maximum results",
+                        "default": 100
+                    }
+                }
+            }),
+        },
+        Tool {
+            name: "m3u8_cache_clear".to_string(),
+            description: Some("Clear cached playlists, probe results, link checks and stream fingerprints. What is cleared goes to the trash and can be restored with m3u8_trash_restore".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "include_downloads": {
+                        "type": "boolean",
+                        "description": "Also delete the download records (files on disk are kept)",
+                        "default": false
+                    }
+                }
+            }),
+        },
+        Tool {
+            name: "m3u8_cache_prune".to_string(),
+            description: Some("Remove cached playlists and probe results older than a number of days".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "days": {
+                        "type": "number",
+                        "description": "Remove entries cached more than this many days ago"
+                    }
+                },
+                "required": ["days"]
+            }),
+        },
+        Tool {
+            name: "m3u8_trash_list".to_string(),
+            description: Some("List cleared caches and URL histories that can still be restored, newest first".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {}
+            }),
+        },
+        Tool {
+            name: "m3u8_trash_restore".to_string(),
+            description: Some("Undo a cache or URL history clear by putting a trash entry back".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "id": {
+                        "type": "number",
+                        "description": "Trash entry to restore, from m3u8_trash_list or the result of the clear"
+                    }
+                },
+                "required": ["id"]
+            }),
+        },
+        Tool {
+            name: "m3u8_resume_jobs".to_string(),
+            description: Some("List downloads interrupted by a restart or failed, and start them again with their original options".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "job_ids": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Jobs to resume (default: all resumable jobs)"
+                    },
+                    "list_only": {
+                        "type": "boolean",
+                        "description": "Only list resumable jobs",
+                        "default": false
+                    }
+                }
+            }),
+        },
+        Tool {
+            name: "m3u8_generate_master".to_string(),
+            description: Some("Write a master playlist from variant playlist URLs or paths and their attributes, with audio, subtitle and closed caption groups".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "output_path": {
+                        "type": "string",
+                        "description": "Where to write the master playlist"
+                    },
+                    "variants": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "uri": { "type": "string" },
+                                "bandwidth": { "type": "number" },
+                                "average_bandwidth": { "type": "number" },
+                                "resolution": { "type": "string" },
+                                "codecs": { "type": "string" },
+                                "frame_rate": { "type": "number" },
+                                "audio": { "type": "string" },
+                                "subtitles": { "type": "string" },
+                                "closed_captions": { "type": "string" }
+                            },
+                            "required": ["uri", "bandwidth"]
+                        },
+                        "description": "Variant streams; audio, subtitles and closed_captions name rendition groups"
+                    },
+                    "renditions": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "type": { "type": "string", "enum": ["AUDIO", "SUBTITLES", "CLOSED-CAPTIONS"] },
+                                "group_id": { "type": "string" },
+                                "name": { "type": "string" },
+                                "language": { "type": "string" },
+                                "uri": { "type": "string" },
+                                "default": { "type": "boolean" },
+                                "autoselect": { "type": "boolean" },
+                                "channels": { "type": "string" },
+                                "instream_id": { "type": "string" }
+                            },
+                            "required": ["type", "group_id", "name"]
+                        },
+                        "description": "Alternative renditions (EXT-X-MEDIA)"
+                    },
+                    "independent_segments": {
+                        "type": "boolean",
+                        "description": "Add EXT-X-INDEPENDENT-SEGMENTS",
+                        "default": false
+                    },
+                    "version": {
+                        "type": "number",
+                        "description": "EXT-X-VERSION (default: the lowest version the tags need)"
+                    }
+                },
+                "required": ["output_path", "variants"]
+            }),
+        },
+        Tool {
+            name: "m3u8_repair".to_string(),
+            description: Some("Fix a broken media playlist: recompute EXTINF from probed segment durations, fix TARGETDURATION, add a missing ENDLIST, renumber the media sequence and drop missing segments, writing a corrected copy".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "input": {
+                        "type": "string",
+                        "description": "URL or path of the media playlist"
+                    },
+                    "output_path": {
+                        "type": "string",
+                        "description": "Where to write the repaired playlist (default: <name>.repaired.m3u8 next to a local input)"
+                    },
+                    "probe_durations": {
+                        "type": "boolean",
+                        "description": "Replace EXTINF with the duration ffprobe measures",
+                        "default": true
+                    },
+                    "remove_missing": {
+                        "type": "boolean",
+                        "description": "Drop segments that answer 404/410 or whose file is gone",
+                        "default": true
+                    },
+                    "add_endlist": {
+                        "type": "boolean",
+                        "description": "End the playlist with EXT-X-ENDLIST",
+                        "default": true
+                    },
+                    "media_sequence": {
+                        "type": "number",
+                        "description": "New EXT-X-MEDIA-SEQUENCE (default: keep the original)"
+                    }
+                },
+                "required": ["input"]
+            }),
+        },
+        Tool {
+            name: "m3u8_interstitials".to_string(),
+            description: Some("List the HLS interstitials of a stream: EXT-X-DATERANGE tags of class com.apple.hls.interstitial with their asset URI or asset list, resume offset, playout limit, cue, restrictions and position in seconds from the start of the stream".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "URL of the master or media playlist"
+                    }
+                },
+                "required": ["url"]
+            }),
+        },
+        Tool {
+            name: "m3u8_graph".to_string(),
+            description: Some("Dependency graph of a playlist as JSON nodes and edges: master playlist -> variant and rendition playlists -> segments, encryption keys and init segments (EXT-X-MAP), each node with its HTTP status from a quick reachability check".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "URL of the master or media playlist"
+                    },
+                    "max_segments": {
+                        "type": "number",
+                        "description": "Segments listed per media playlist; the rest are counted in omitted_segments",
+                        "default": 5
+                    },
+                    "check": {
+                        "type": "boolean",
+                        "description": "Check segments, keys and init segments with HEAD requests; playlists are always fetched",
+                        "default": true
+                    }
+                },
+                "required": ["url"]
+            }),
+        },
+        Tool {
+            name: "m3u8_export_vod".to_string(),
+            description: Some("Make a live recording playable as VOD: writes a playlist with EXT-X-ENDLIST and the media sequence renumbered from 0. Native .ts recordings get byte ranges of the file itself; other recordings are repackaged into <name>_vod/".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path of the recorded file"
+                    }
+                },
+                "required": ["path"]
+            }),
+        },
+        Tool {
+            name: "m3u8_abr_simulate".to_string(),
+            description: Some("Simulate an ABR player on a master playlist under a bandwidth profile (e.g. 3 Mbps with dips): which variant it picks for each segment, its buffer, startup delay, rebuffering and switches. Segment sizes are estimated from AVERAGE-BANDWIDTH (or BANDWIDTH), to validate a packaged ladder".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "URL of the master playlist"
+                    },
+                    "kbps": {
+                        "type": "number",
+                        "description": "Constant bandwidth in kbps, when no profile is given"
+                    },
+                    "profile": {
+                        "type": "array",
+                        "description": "Bandwidth steps: each holds from its start, in seconds since playback was requested, until the next",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "from": { "type": "number" },
+                                "kbps": { "type": "number" }
+                            },
+                            "required": ["from", "kbps"]
+                        }
+                    },
+                    "duration": {
+                        "type": "number",
+                        "description": "Seconds of media to play; a short live window is repeated",
+                        "default": 300
+                    },
+                    "max_buffer": {
+                        "type": "number",
+                        "description": "Seconds the player buffers ahead before it pauses fetching",
+                        "default": 30
+                    },
+                    "safety": {
+                        "type": "number",
+                        "description": "Share of the estimated bandwidth a variant may use",
+                        "default": 0.8
+                    }
+                },
+                "required": ["url"]
+            }),
+        },
+        Tool {
+            name: "m3u8_detect_stream".to_string(),
+            description: Some("Tell what any URL is before working on it: HLS master playlist, HLS media playlist, DASH manifest, media file or web page. Reads the start of the response and suggests the tool to use next; for a web page it lists the .m3u8 and .mpd URLs found in its HTML".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "Any http(s) URL: a playlist, manifest, media file or the page of a player"
+                    }
+                },
+                "required": ["url"]
+            }),
+        },
+        Tool {
+            name: "m3u8_archive_list".to_string(),
+            description: Some("List the recordings in the segment archive (downloader.archive) with their stream, sequence range, duration and wall-clock span, and the bytes stored against the bytes recorded".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {}
+            }),
+        },
+        Tool {
+            name: "m3u8_archive_reconstruct".to_string(),
+            description: Some("Put a window of a stream together from the segment archive, drawing on every recording of it, into one file. Reports sequence numbers no recording has".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "Media playlist URL the stream was recorded from"
+                    },
+                    "output": {
+                        "type": "string",
+                        "description": "File to write; .ts is written as is, other formats are remuxed. A relative path goes under the output directory"
+                    },
+                    "from_sequence": {
+                        "type": "number",
+                        "description": "First media sequence number (default: the earliest recorded)"
+                    },
+                    "to_sequence": {
+                        "type": "number",
+                        "description": "Last media sequence number (default: the latest recorded)"
+                    },
+                    "start_time": {
+                        "type": "string",
+                        "description": "Wall-clock start, RFC 3339; needs EXT-X-PROGRAM-DATE-TIME in the recordings"
+                    },
+                    "end_time": {
+                        "type": "string",
+                        "description": "Wall-clock end, RFC 3339"
+                    }
+                },
+                "required": ["url", "output"]
+            }),
+        },
+        Tool {
+            name: "m3u8_archive_delete".to_string(),
+            description: Some("Delete a recording from the segment archive, and the segments no other recording uses".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "id": {
+                        "type": "string",
+                        "description": "Recording id from m3u8_archive_list (the job id of the download)"
+                    }
+                },
+                "required": ["id"]
+            }),
+        },
+        Tool {
+            name: "m3u8_ffmpeg_command".to_string(),
+            description: Some("Show the FFmpeg command line a job ran, or the one a download or conversion would run, to reproduce it in a terminal".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "job_id": {
+                        "type": "string",
+                        "description": "Job whose FFmpeg commands to return"
+                    },
+                    "operation": {
+                        "type": "string",
+                        "enum": ["download", "convert"],
+                        "description": "Operation to preview when no job_id is given"
+                    },
+                    "url": {
+                        "type": "string",
+                        "description": "Stream URL (download)"
+                    },
+                    "output_path": {
+                        "type": "string",
+                        "description": "Output file or directory (download)"
+                    },
+                    "input_path": {
+                        "type": "string",
+                        "description": "Input video file (convert)"
+                    },
+                    "output_dir": {
+                        "type": "string",
+                        "description": "Output directory (convert)"
+                    },
+                    "segment_duration": {
+                        "type": "number",
+                        "description": "Segment duration in seconds (convert, default: 10)"
+                    }
+                }
+            }),
+        },
+    ]
 }
 
 // Get available resources
@@ -416,49 +1858,421 @@ fn get_available_resources() -> Vec<Resource> {
             description: Some("Statistics about cached m3u8 data".to_string()),
             mime_type: Some("application/json".to_string()),
         },
+        Resource {
+            uri: "m3u8://status".to_string(),
+            name: "Server Status".to_string(),
+            description: Some("Tool calls running and waiting for a worker, with recent wait times, and active jobs".to_string()),
+            mime_type: Some("application/json".to_string()),
+        },
+        Resource {
+            uri: "m3u8://captures".to_string(),
+            name: "Browser Captures".to_string(),
+            description: Some("Stream URLs sent by the companion browser extension, with the page title and the request headers they need".to_string()),
+            mime_type: Some("application/json".to_string()),
+        },
+        Resource {
+            uri: "m3u8://history".to_string(),
+            name: "URL History".to_string(),
+            description: Some("Recently used stream URLs with the result of their last liveness check".to_string()),
+            mime_type: Some("application/json".to_string()),
+        },
     ]
 }
 
-// Handle tools/list request
-async fn handle_tools_list(
+// One resource per download that has a transcript next to it
+async fn transcript_resources() -> Vec<Resource> {
+    let db = match crate::database::global_db().await {
+        Ok(db) => db,
+        Err(_) => return Vec::new(),
+    };
+    let downloads = db.list_all_downloads().unwrap_or_default();
+    
+    downloads.into_iter()
+        .filter(|d| crate::transcription::transcript_path(std::path::Path::new(&d.output_path)).exists())
+        .map(|d| Resource {
+            uri: format!("m3u8://transcripts/{}", d.id),
+            name: format!("Transcript: {}", d.title.as_deref().unwrap_or(&d.output_path)),
+            description: Some(format!("Transcript of {}", d.url)),
+            mime_type: Some("text/plain".to_string()),
+        })
+        .collect()
+}
+
+// Logs of the most recent jobs
+fn job_log_resources() -> Vec<Resource> {
+    crate::job_log::recent_jobs(50)
+        .into_iter()
+        .map(|job_id| Resource {
+            uri: format!("m3u8://jobs/{}/log", job_id),
+            name: format!("Job log: {}", job_id),
+            description: Some("FFmpeg output or downloader messages of the job".to_string()),
+            mime_type: Some("text/plain".to_string()),
+        })
+        .collect()
+}
+
+// The most recent stored tool results
+fn result_resources() -> Vec<Resource> {
+    crate::result_store::list()
+        .into_iter()
+        .take(50)
+        .map(|result| Resource {
+            uri: result.uri(),
+            name: format!("Result: {}", result.id),
+            description: Some("Full result of a tool call, stored instead of returned inline".to_string()),
+            mime_type: Some(result.mime_type.to_string()),
+        })
+        .collect()
+}
+
+// Handle tools/list request
+async fn handle_tools_list(
+    state: Arc<McpServerState>,
+    request_id: Option<Value>,
+) -> JsonRpcResponse {
+    let enabled_tools = state.enabled_tools.read().await;
+    let tools = get_available_tools(&enabled_tools);
+    
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: request_id,
+        result: Some(json!({
+            "tools": tools
+        })),
+        error: None,
+    }
+}
+
+// Handle resources/list request
+async fn handle_resources_list(
+    _state: Arc<McpServerState>,
+    request_id: Option<Value>,
+) -> JsonRpcResponse {
+    let mut resources = get_available_resources();
+    resources.extend(transcript_resources().await);
+    resources.extend(job_log_resources());
+    resources.extend(result_resources());
+    
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: request_id,
+        result: Some(json!({
+            "resources": resources
+        })),
+        error: None,
+    }
+}
+
+// Handle resources/read request
+async fn handle_resources_read(
+    state: Arc<McpServerState>,
+    request_id: Option<Value>,
+    params: Option<Value>,
+) -> JsonRpcResponse {
+    let params = match params {
+        Some(p) => p,
+        None => {
+            return JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request_id,
+                result: None,
+                error: Some(JsonRpcError {
+                    code: -32602,
+                    message: "Invalid params".to_string(),
+                    data: None,
+                }),
+            };
+        }
+    };
+    
+    let uri = match params.get("uri").and_then(|v| v.as_str()) {
+        Some(u) => u,
+        None => {
+            return JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request_id,
+                result: None,
+                error: Some(JsonRpcError {
+                    code: -32602,
+                    message: "Missing required parameter: uri".to_string(),
+                    data: None,
+                }),
+            };
+        }
+    };
+    
+    let result = match uri {
+        "m3u8://config" => {
+            json!({
+                "contents": [{
+                    "uri": uri,
+                    "mimeType": "application/json",
+                    "text": json!({
+                        "ffmpeg_path": "ffmpeg",
+                        "output_dir": "~/Downloads/m3u8-mcp",
+                        "cache_enabled": true
+                    }).to_string()
+                }]
+            })
+        }
+        "m3u8://cache/stats" => {
+            // Get cache stats from database
+            let db_guard = crate::database::GLOBAL_DB.read().await;
+            if let Some(ref db) = *db_guard {
+                match db.get_cache_stats() {
+                    Ok(stats) => {
+                        json!({
+                            "contents": [{
+                                "uri": uri,
+                                "mimeType": "application/json",
+                                "text": stats.to_string()
+                            }]
+                        })
+                    }
+                    Err(e) => {
+                        return app_error_response(request_id, "Failed to get cache stats", e.into());
+                    }
+                }
+            } else {
+                json!({
+                    "contents": [{
+                        "uri": uri,
+                        "mimeType": "application/json",
+                        "text": json!({
+                            "error": "Database not initialized"
+                        }).to_string()
+                    }]
+                })
+            }
+        }
+        "m3u8://status" => {
+            json!({
+                "contents": [{
+                    "uri": uri,
+                    "mimeType": "application/json",
+                    "text": json!({
+                        "sessions": state.sessions.read().await.len(),
+                        "tool_queue": state.tool_queue.status(),
+                        "jobs": state.ffmpeg.list_jobs().await.len()
+                    }).to_string()
+                }]
+            })
+        }
+        "m3u8://history" => {
+            let history = match crate::history::load() {
+                Ok(history) => history,
+                Err(e) => return app_error_response(request_id, "Failed to read history", e),
+            };
+            let statuses = match crate::database::global_db().await {
+                Ok(db) => db.get_link_statuses().unwrap_or_default(),
+                Err(_) => Vec::new(),
+            };
+            
+            // History entries, then dead links that were archived out of it
+            let mut entries: Vec<Value> = history.into_iter()
+                .map(|mut item| {
+                    let url = item.get("url").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                    item["status"] = json!(statuses.iter().find(|s| s.url == url));
+                    item
+                })
+                .collect();
+            let archived: Vec<Value> = statuses.iter()
+                .filter(|s| s.dead && !entries.iter().any(|e| e["url"] == json!(s.url)))
+                .map(|s| json!({ "url": s.url, "archived": true, "status": s }))
+                .collect();
+            entries.extend(archived);
+            
+            json!({
+                "contents": [{
+                    "uri": uri,
+                    "mimeType": "application/json",
+                    "text": serde_json::to_string_pretty(&entries).unwrap_or_default()
+                }]
+            })
+        }
+        "m3u8://captures" => {
+            json!({
+                "contents": [{
+                    "uri": uri,
+                    "mimeType": "application/json",
+                    "text": serde_json::to_string_pretty(&crate::captures::list()).unwrap_or_default()
+                }]
+            })
+        }
+        _ if uri.starts_with("m3u8://transcripts/") => {
+            let id: i32 = match uri["m3u8://transcripts/".len()..].parse() {
+                Ok(id) => id,
+                Err(_) => return error_response(request_id, -32602, format!("Invalid transcript URI: {}", uri)),
+            };
+            let download = match crate::database::global_db().await.and_then(|db| db.get_download(id).map_err(AppError::from)) {
+                Ok(Some(download)) => download,
+                Ok(None) => return error_response(request_id, -32602, format!("Download {} not found", id)),
+                Err(e) => return app_error_response(request_id, "Failed to read transcript", e),
+            };
+            match crate::transcription::read_transcript(std::path::Path::new(&download.output_path)) {
+                Some(text) => json!({
+                    "contents": [{
+                        "uri": uri,
+                        "mimeType": "text/plain",
+                        "text": text
+                    }]
+                }),
+                None => return error_response(request_id, -32602, format!("Download {} has no transcript", id)),
+            }
+        }
+        _ if uri.starts_with(crate::result_store::URI_PREFIX) => {
+            match crate::result_store::read(&uri[crate::result_store::URI_PREFIX.len()..]) {
+                Ok((text, mime_type)) => json!({
+                    "contents": [{
+                        "uri": uri,
+                        "mimeType": mime_type,
+                        "text": text
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to read result", e),
+            }
+        }
+        _ if uri.starts_with("m3u8://jobs/") && uri.ends_with("/log") => {
+            let job_id = &uri["m3u8://jobs/".len()..uri.len() - "/log".len()];
+            match crate::job_log::read(job_id) {
+                Ok(text) => json!({
+                    "contents": [{
+                        "uri": uri,
+                        "mimeType": "text/plain",
+                        "text": text
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to read job log", e),
+            }
+        }
+        _ => {
+            return JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request_id,
+                result: None,
+                error: Some(JsonRpcError {
+                    code: -32602,
+                    message: format!("Unknown resource URI: {}", uri),
+                    data: None,
+                }),
+            };
+        }
+    };
+    
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id: request_id,
+        result: Some(result),
+        error: None,
+    }
+}
+
+// Handle completion/complete. Tool arguments are completed through
+// "ref/tool" references (see crate::completions); prompt and resource
+// references get no suggestions, as the server has no prompts or resource
+// templates.
+async fn handle_completion(
     state: Arc<McpServerState>,
     request_id: Option<Value>,
+    params: Option<Value>,
 ) -> JsonRpcResponse {
-    let enabled_tools = state.enabled_tools.read().await;
-    let tools = get_available_tools(&enabled_tools);
-    
+    let params = params.unwrap_or_default();
+    let name = params.pointer("/argument/name").and_then(|v| v.as_str());
+    let value = params.pointer("/argument/value").and_then(|v| v.as_str());
+    let (Some(name), Some(value)) = (name, value) else {
+        return error_response(request_id, -32602, "Missing required parameter: argument");
+    };
+    let completion = match params.pointer("/ref/type").and_then(|v| v.as_str()) {
+        Some("ref/tool") => {
+            let tool = params.pointer("/ref/name").and_then(|v| v.as_str()).unwrap_or_default();
+            let has_argument = {
+                let enabled_tools = state.enabled_tools.read().await;
+                get_available_tools(&enabled_tools)
+                    .iter()
+                    .any(|t| t.name == tool && t.input_schema.pointer(&format!("/properties/{}", name)).is_some())
+            };
+            if !has_argument {
+                return error_response(request_id, -32602, format!("Tool {} has no argument {}", tool, name));
+            }
+            let context = params.pointer("/context/arguments")
+                .and_then(|v| v.as_object())
+                .cloned()
+                .unwrap_or_default();
+            crate::completions::complete(name, value, &context).await
+        }
+        Some("ref/prompt") | Some("ref/resource") => crate::completions::Completion::default(),
+        _ => return error_response(request_id, -32602, "ref must be a ref/tool, ref/prompt or ref/resource reference"),
+    };
+
     JsonRpcResponse {
         jsonrpc: "2.0".to_string(),
         id: request_id,
-        result: Some(json!({
-            "tools": tools
-        })),
+        result: Some(json!({ "completion": completion })),
         error: None,
     }
 }
 
-// Handle resources/list request
-async fn handle_resources_list(
-    _state: Arc<McpServerState>,
-    request_id: Option<Value>,
-) -> JsonRpcResponse {
-    let resources = get_available_resources();
-    
+// Handle ping request
+async fn handle_ping(request_id: Option<Value>) -> JsonRpcResponse {
     JsonRpcResponse {
         jsonrpc: "2.0".to_string(),
         id: request_id,
-        result: Some(json!({
-            "resources": resources
-        })),
+        result: Some(json!({})),
         error: None,
     }
 }
 
-// Handle resources/read request
-async fn handle_resources_read(
-    _state: Arc<McpServerState>,
+// Handle tools/call request within the tool's time limit. A call that runs
+// out of time is cancelled, which stops its FFmpeg processes, and answers
+// with a timeout error. Heavy tools first wait for a worker of the pool;
+// the time limit starts once they have one.
+async fn handle_tools_call(
+    state: Arc<McpServerState>,
+    request_id: Option<Value>,
+    params: Option<Value>,
+    session_id: Option<String>,
+) -> JsonRpcResponse {
+    let tool_name = params.as_ref()
+        .and_then(|p| p.get("name"))
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+    // tools/list only shows the enabled tools; a client holding an older
+    // list, from before a profile switch, must not reach the others
+    if !state.enabled_tools.read().await.contains(&tool_name) {
+        return error_response(request_id, -32602, format!("Tool {} is not enabled", tool_name));
+    }
+    let limit = crate::config::load_config().unwrap_or_default().mcp.tool_timeout(&tool_name);
+    let queue = state.tool_queue.clone();
+    let call = {
+        let request_id = request_id.clone();
+        let tool_name = tool_name.clone();
+        async move {
+            match crate::cancellation::with_timeout(limit, &tool_name, run_tool_call(state, request_id.clone(), params, session_id)).await {
+                Ok(response) => response,
+                Err(e) => app_error_response(request_id, "Tool call cancelled", e),
+            }
+        }
+    };
+    let response = if !crate::tool_queue::is_pooled(&tool_name) {
+        call.await
+    } else {
+        match queue.run(&tool_name, call).await {
+            Ok(response) => response,
+            Err(e) => app_error_response(request_id, "Tool call not run", e),
+        }
+    };
+    let ok = response.error.is_none()
+        && !response.result.as_ref().is_some_and(|result| result["isError"].as_bool() == Some(true));
+    crate::usage::record_tool_call(&tool_name, ok).await;
+    response
+}
+
+async fn run_tool_call(
+    state: Arc<McpServerState>,
     request_id: Option<Value>,
     params: Option<Value>,
+    session_id: Option<String>,
 ) -> JsonRpcResponse {
     let params = match params {
         Some(p) => p,
@@ -476,8 +2290,8 @@ async fn handle_resources_read(
         }
     };
     
-    let uri = match params.get("uri").and_then(|v| v.as_str()) {
-        Some(u) => u,
+    let tool_name = match params.get("name").and_then(|v| v.as_str()) {
+        Some(n) => n,
         None => {
             return JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
@@ -485,141 +2299,488 @@ async fn handle_resources_read(
                 result: None,
                 error: Some(JsonRpcError {
                     code: -32602,
-                    message: "Missing required parameter: uri".to_string(),
+                    message: "Missing required parameter: name".to_string(),
                     data: None,
                 }),
             };
         }
     };
     
-    let result = match uri {
-        "m3u8://config" => {
+    let mut arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+    // "~/Videos" and "$HOME/Videos" mean the same to agents as in a shell
+    crate::paths::expand_path_arguments(&mut arguments);
+    // Relative outputs go next to the project an editor-based client works in
+    let root = match &session_id {
+        Some(id) => session_root(&state, id, crate::roots::LIST_TIMEOUT).await,
+        None => None,
+    };
+    if let Some(root) = root {
+        if let Err(e) = crate::roots::resolve_output_arguments(&mut arguments, &root) {
+            return app_error_response(request_id, "Invalid output path", e);
+        }
+    }
+    
+    // Execute tool based on name
+    let result = match tool_name {
+        "m3u8_set_url" => {
+            let url = match arguments.get("url").and_then(|v| v.as_str()) {
+                Some(u) => u,
+                None => {
+                    return JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: request_id,
+                        result: None,
+                        error: Some(JsonRpcError {
+                            code: -32602,
+                            message: "Missing required parameter: url".to_string(),
+                            data: None,
+                        }),
+                    };
+                }
+            };
+            
+            // The session's own URL, and the shared one for clients without
+            let mut sessions = state.sessions.write().await;
+            if let Some(session) = session_id.as_ref().and_then(|id| sessions.get_mut(id)) {
+                session.urls.set(Some(url.to_string()));
+            }
+            drop(sessions);
+            crate::current_url::set_shared(url.to_string()).await;
+            
             json!({
-                "contents": [{
-                    "uri": uri,
-                    "mimeType": "application/json",
-                    "text": json!({
-                        "ffmpeg_path": "ffmpeg",
-                        "output_dir": "~/Downloads/m3u8-mcp",
-                        "cache_enabled": true
-                    }).to_string()
+                "content": [{
+                    "type": "text",
+                    "text": format!("URL set to: {}", url)
                 }]
             })
         }
-        "m3u8://cache/stats" => {
-            // Get cache stats from database
-            let db_guard = crate::database::GLOBAL_DB.read().await;
-            if let Some(ref db) = *db_guard {
-                match db.get_cache_stats() {
-                    Ok(stats) => {
+        "m3u8_url_back" | "m3u8_url_forward" => {
+            let forward = tool_name == "m3u8_url_forward";
+            let mut sessions = state.sessions.write().await;
+            let Some(session) = session_id.as_ref().and_then(|id| sessions.get_mut(id)) else {
+                return app_error_response(
+                    request_id,
+                    "No URL history",
+                    AppError::invalid_input("URL history is kept per session; send the Mcp-Session-Id header initialize returned"),
+                );
+            };
+            let url = if forward { session.urls.forward() } else { session.urls.back() };
+            drop(sessions);
+            let text = match url {
+                Some(url) => {
+                    crate::current_url::set_shared(url.clone()).await;
+                    format!("URL set to: {}", url)
+                }
+                None if forward => "No URL to go forward to".to_string(),
+                None => "No earlier URL to go back to".to_string(),
+            };
+            
+            json!({
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }]
+            })
+        }
+        "m3u8_pin_url" => {
+            let text = if arguments.get("unpin").and_then(|v| v.as_bool()).unwrap_or(false) {
+                match crate::current_url::unpin() {
+                    Ok(Some(url)) => format!("Unpinned: {}", url),
+                    Ok(None) => "No URL was pinned".to_string(),
+                    Err(e) => return app_error_response(request_id, "Failed to unpin URL", e),
+                }
+            } else {
+                let url = match arguments.get("url").and_then(|v| v.as_str()) {
+                    Some(url) => Some(url.to_string()),
+                    None => {
+                        let own = match &session_id {
+                            Some(id) => state.sessions.read().await.get(id).and_then(|s| s.urls.current().map(str::to_string)),
+                            None => None,
+                        };
+                        crate::current_url::resolve(own).await.map(|(url, _)| url)
+                    }
+                };
+                match crate::current_url::pin(url.as_deref().unwrap_or_default()) {
+                    Ok(()) => format!("Pinned: {}", url.unwrap_or_default().trim()),
+                    Err(e) => return app_error_response(request_id, "Failed to pin URL", e),
+                }
+            };
+            
+            json!({
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }]
+            })
+        }
+        "m3u8_get_url" => {
+            let own = match &session_id {
+                Some(id) => state.sessions.read().await.get(id).and_then(|s| s.urls.current().map(str::to_string)),
+                None => None,
+            };
+            let text = match crate::current_url::resolve(own).await {
+                Some((url, false)) => format!("Current URL: {}", url),
+                Some((url, true)) => format!("Current URL: {} (shared; this session has not set one)", url),
+                None => "Current URL: No URL set".to_string(),
+            };
+            let text = match crate::current_url::pinned() {
+                Some(pinned) => format!("{}\nPinned URL: {}", text, pinned),
+                None => text,
+            };
+            
+            json!({
+                "content": [{
+                    "type": "text",
+                    "text": text
+                }]
+            })
+        }
+        "m3u8_parse" => {
+            let url = arguments.get("url").and_then(|v| v.as_str());
+            let content = arguments.get("content").and_then(|v| v.as_str());
+            
+            if url.is_none() && content.is_none() {
+                return JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id: request_id,
+                    result: None,
+                    error: Some(JsonRpcError {
+                        code: -32602,
+                        message: "Either 'url' or 'content' parameter is required".to_string(),
+                        data: None,
+                    }),
+                };
+            }
+            
+            // A digest instead of the full structure, which costs clients far fewer tokens
+            if arguments.get("summary").and_then(|v| v.as_bool()).unwrap_or(false) {
+                let (text, base_url) = match (url, content) {
+                    (Some(url), _) => match crate::profiles::parser().fetch_playlist(url).await {
+                        Ok(text) => (text, url),
+                        Err(e) => return app_error_response(request_id, "Failed to fetch m3u8", e.into()),
+                    },
+                    (None, content) => (content.unwrap_or_default().to_string(), ""),
+                };
+                match crate::m3u8_parser::summarize_playlist(&text, base_url) {
+                    Ok(summary) => json!({
+                        "content": [{
+                            "type": "text",
+                            "text": serde_json::to_string(&summary).unwrap_or_default()
+                        }]
+                    }),
+                    Err(e) => return app_error_response(request_id, "Failed to parse m3u8", e.into()),
+                }
+            } else if arguments.get("tolerant").and_then(|v| v.as_bool()).unwrap_or(false) {
+                let (text, base_url) = match (url, content) {
+                    (Some(url), _) => match crate::profiles::parser().fetch_playlist(url).await {
+                        Ok(text) => (text, url),
+                        Err(e) => return app_error_response(request_id, "Failed to fetch m3u8", e.into()),
+                    },
+                    (None, content) => (content.unwrap_or_default().to_string(), ""),
+                };
+                match crate::m3u8_parser::parse_playlist_tolerant(&text, base_url) {
+                    Ok((playlist, issues)) => {
+                        let field = match playlist {
+                            crate::m3u8_parser::ParsedPlaylist::Master { .. } => "variants",
+                            crate::m3u8_parser::ParsedPlaylist::Media { .. } => "segments",
+                        };
+                        let mut value = json!(playlist);
+                        value["issues"] = json!(issues);
                         json!({
-                            "contents": [{
-                                "uri": uri,
-                                "mimeType": "application/json",
-                                "text": stats.to_string()
+                            "content": [{
+                                "type": "text",
+                                "text": crate::tool_output::render(value, Some(field), &arguments)
                             }]
                         })
                     }
-                    Err(e) => {
+                    Err(e) => return app_error_response(request_id, "Failed to parse m3u8", e.into()),
+                }
+            } else if let Some(url) = url.filter(|_| arguments.get("deep").and_then(|v| v.as_bool()).unwrap_or(false)) {
+                match crate::deep_parse::parse(&crate::profiles::parser(), url).await {
+                    Ok(tree) => json!({
+                        "content": [{
+                            "type": "text",
+                            "text": crate::tool_output::render(json!(tree), Some("variants"), &arguments)
+                        }]
+                    }),
+                    Err(e) => return app_error_response(request_id, "Failed to parse m3u8", e),
+                }
+            } else if let Some(url) = url {
+                let parser = Arc::new(crate::profiles::parser());
+                match parser.fetch_and_parse(url).await {
+                    Ok((text, playlist)) => {
+                        crate::snapshots::spawn_capture(parser.clone(), url, "parse", HashMap::from([(url.to_string(), text.clone())]));
+                        let field = match playlist {
+                            crate::m3u8_parser::ParsedPlaylist::Master { .. } => "variants",
+                            crate::m3u8_parser::ParsedPlaylist::Media { .. } => "segments",
+                        };
+                        let mut content = vec![json!({
+                            "type": "text",
+                            "text": crate::tool_output::render(json!(playlist), Some(field), &arguments)
+                        })];
+                        // Title and program info the stream carries, when it has any;
+                        // its first segment is only read when asked for
+                        let read_segment = arguments.get("include_stream_info").and_then(|v| v.as_bool()).unwrap_or(false);
+                        match crate::stream_info::probe_content(&parser, url, &text, read_segment).await {
+                            Ok(info) if info.title.is_some() || !info.session_data.is_empty() || !info.id3.is_empty() => {
+                                content.push(json!({
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&json!({ "stream_info": info })).unwrap_or_default()
+                                }));
+                            }
+                            Ok(_) => {}
+                            Err(e) => eprintln!("Failed to read stream metadata of {}: {}", url, e),
+                        }
+                        json!({ "content": content })
+                    }
+                    Err(e) => {
+                        return app_error_response(request_id, "Failed to parse m3u8", e.into());
+                    }
+                }
+            } else {
+                // Parse from content
+                json!({
+                    "content": [{
+                        "type": "text",
+                        "text": "Parsing from content not yet implemented"
+                    }]
+                })
+            }
+        }
+        "m3u8_download" => {
+            let url = match arguments.get("url").and_then(|v| v.as_str()) {
+                Some(u) => u,
+                None => {
+                    return JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: request_id,
+                        result: None,
+                        error: Some(JsonRpcError {
+                            code: -32602,
+                            message: "Missing required parameter: url".to_string(),
+                            data: None,
+                        }),
+                    };
+                }
+            };
+            
+            let project_id = arguments.get("project_id").and_then(|v| v.as_i64()).map(|id| id as i32);
+            let output_path = match arguments.get("output_path").and_then(|v| v.as_str()) {
+                Some(p) => std::path::PathBuf::from(p),
+                None => {
+                    let project_dir = match project_id {
+                        Some(id) => match crate::database::project_output_dir(id).await {
+                            Ok(dir) => dir,
+                            Err(e) => return app_error_response(request_id, "Failed to resolve project", e),
+                        },
+                        None => None,
+                    };
+                    match project_dir {
+                        Some(dir) => dir,
+                        None => return error_response(
+                            request_id,
+                            -32602,
+                            "Missing required parameter: output_path (or a project_id with an output directory)",
+                        ),
+                    }
+                }
+            };
+            
+            let engine = match arguments.get("engine") {
+                Some(value) => match serde_json::from_value::<crate::config::DownloadEngine>(value.clone()) {
+                    Ok(engine) => Some(engine),
+                    Err(_) => return error_response(request_id, -32602, "engine must be \"ffmpeg\" or \"native\""),
+                },
+                None => None,
+            };
+            
+            let mirrors: Vec<String> = arguments.get("mirrors")
+                .and_then(|v| v.as_array())
+                .map(|items| items.iter().filter_map(|m| m.as_str().map(String::from)).collect())
+                .unwrap_or_default();
+            
+            let retry = match arguments.get("retry") {
+                Some(value) => match serde_json::from_value::<crate::config::RetryPolicy>(value.clone()) {
+                    Ok(retry) => Some(retry),
+                    Err(e) => return error_response(request_id, -32602, format!("Invalid retry policy: {}", e)),
+                },
+                None => None,
+            };
+            
+            let audio = match arguments.get("audio") {
+                Some(value) => match serde_json::from_value::<crate::config::AudioTracks>(value.clone()) {
+                    Ok(audio) => Some(audio),
+                    Err(e) => return error_response(request_id, -32602, format!("Invalid audio options: {}", e)),
+                },
+                None => None,
+            };
+            
+            let interstitials = match arguments.get("interstitials") {
+                Some(value) => match serde_json::from_value::<crate::config::InterstitialMode>(value.clone()) {
+                    Ok(mode) => Some(mode),
+                    Err(_) => return error_response(request_id, -32602, "interstitials must be \"skip\", \"separate\" or \"include\""),
+                },
+                None => None,
+            };
+            
+            // A master playlist is only downloaded once a variant is chosen,
+            // unless the caller or the config leaves the choice to the downloader
+            let parser = Arc::new(crate::profiles::parser());
+            let choice = match arguments.get("variant").map(crate::variant_select::parse_choice) {
+                Some(Ok(choice)) => Some(choice),
+                Some(Err(e)) => return app_error_response(request_id, "Invalid variant", e),
+                None => None,
+            };
+            let policy = crate::config::load_config().map(|c| c.mcp.variant_selection).unwrap_or_default();
+            let mut url = url.to_string();
+            let needs_variant = match &choice {
+                Some(choice) => *choice != crate::variant_select::VariantChoice::Auto,
+                None => policy == crate::config::VariantSelection::Ask,
+            };
+            if needs_variant {
+                let variants = match crate::variant_select::master_variants(&parser, &url).await {
+                    Ok(variants) => variants,
+                    Err(e) => return app_error_response(request_id, "Failed to read the playlist", e),
+                };
+                if let Some(variants) = variants {
+                    let Some(choice) = &choice else {
+                        let request = crate::variant_select::selection_request(&url, &variants);
+                        return JsonRpcResponse {
+                            jsonrpc: "2.0".to_string(),
+                            id: request_id,
+                            result: Some(json!({
+                                "content": [{
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&request).unwrap_or_default()
+                                }],
+                                "isError": false
+                            })),
+                            error: None,
+                        };
+                    };
+                    match crate::variant_select::select(&variants, choice) {
+                        Ok(Some(variant)) => url = variant.uri.clone(),
+                        Ok(None) => {}
+                        Err(e) => return app_error_response(request_id, "Invalid variant", e),
+                    }
+                }
+            }
+            
+            // A stream already on disk is not fetched again unless forced
+            if !arguments.get("force").and_then(|v| v.as_bool()).unwrap_or(false) {
+                match crate::duplicates::find_existing(&parser, &url).await {
+                    Ok(Some(existing)) => {
+                        let answer = crate::duplicates::already_downloaded(&url, &existing);
                         return JsonRpcResponse {
                             jsonrpc: "2.0".to_string(),
                             id: request_id,
-                            result: None,
-                            error: Some(JsonRpcError {
-                                code: -32603,
-                                message: format!("Failed to get cache stats: {}", e),
-                                data: None,
-                            }),
+                            result: Some(json!({
+                                "content": [{
+                                    "type": "text",
+                                    "text": serde_json::to_string_pretty(&answer).unwrap_or_default()
+                                }],
+                                "isError": false
+                            })),
+                            error: None,
                         };
                     }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Duplicate check failed for {}: {}", url, e),
                 }
-            } else {
-                json!({
-                    "contents": [{
-                        "uri": uri,
-                        "mimeType": "application/json",
-                        "text": json!({
-                            "error": "Database not initialized"
-                        }).to_string()
-                    }]
-                })
             }
-        }
-        _ => {
-            return JsonRpcResponse {
-                jsonrpc: "2.0".to_string(),
-                id: request_id,
-                result: None,
-                error: Some(JsonRpcError {
-                    code: -32602,
-                    message: format!("Unknown resource URI: {}", uri),
-                    data: None,
-                }),
+            
+            let spec = crate::journal::JobSpec {
+                url,
+                output_path: Some(output_path),
+                page_url: arguments.get("page_url").and_then(|v| v.as_str()).map(String::from),
+                project_id,
+                engine,
+                mirrors,
+                retry,
+                audio,
+                interstitials,
             };
+            
+            // Use the shared FFmpeg service so the job shows up in the UI
+            let job_id = ffmpeg_wrapper::new_job_id();
+            match crate::journal::run(parser, &state.ffmpeg, &job_id, &spec).await {
+                Ok(path) => {
+                    let mut content = vec![json!({
+                        "type": "text",
+                        "text": crate::i18n::tf(crate::i18n::Msg::DownloadedTo, &[&path.display()])
+                    }), json!({
+                        "type": "text",
+                        "text": format!("Job {}: fetch the file from {} (Authorization: Bearer <token>)", job_id, state.artifacts_url(&job_id))
+                    })];
+                    // Live recordings that lost segments say where
+                    let gaps = crate::native_download::recording_gaps(&path);
+                    if !gaps.is_empty() {
+                        content.push(json!({
+                            "type": "text",
+                            "text": serde_json::to_string_pretty(&json!({ "gaps": gaps })).unwrap_or_default()
+                        }));
+                    }
+                    json!({ "content": content })
+                }
+                Err(e) => {
+                    return app_error_response(request_id, "Failed to download m3u8", e);
+                }
+            }
         }
-    };
-    
-    JsonRpcResponse {
-        jsonrpc: "2.0".to_string(),
-        id: request_id,
-        result: Some(result),
-        error: None,
-    }
-}
-
-// Handle ping request
-async fn handle_ping(request_id: Option<Value>) -> JsonRpcResponse {
-    JsonRpcResponse {
-        jsonrpc: "2.0".to_string(),
-        id: request_id,
-        result: Some(json!({})),
-        error: None,
-    }
-}
-
-// Handle tools/call request
-async fn handle_tools_call(
-    _state: Arc<McpServerState>,
-    request_id: Option<Value>,
-    params: Option<Value>,
-) -> JsonRpcResponse {
-    let params = match params {
-        Some(p) => p,
-        None => {
-            return JsonRpcResponse {
-                jsonrpc: "2.0".to_string(),
-                id: request_id,
-                result: None,
-                error: Some(JsonRpcError {
-                    code: -32602,
-                    message: "Invalid params".to_string(),
-                    data: None,
-                }),
+        "m3u8_convert" => {
+            let (input_path, output_dir) = match (
+                arguments.get("input_path").and_then(|v| v.as_str()),
+                arguments.get("output_dir").and_then(|v| v.as_str()),
+            ) {
+                (Some(i), Some(o)) => (i, o),
+                _ => return error_response(request_id, -32602, "Missing required parameters: input_path and output_dir"),
             };
-        }
-    };
-    
-    let tool_name = match params.get("name").and_then(|v| v.as_str()) {
-        Some(n) => n,
-        None => {
-            return JsonRpcResponse {
-                jsonrpc: "2.0".to_string(),
-                id: request_id,
-                result: None,
-                error: Some(JsonRpcError {
-                    code: -32602,
-                    message: "Missing required parameter: name".to_string(),
-                    data: None,
-                }),
+            let segment_duration = arguments.get("segment_duration").and_then(|v| v.as_u64()).unwrap_or(10) as u32;
+            let mut options: crate::ffmpeg_wrapper::PackagingOptions = match serde_json::from_value(arguments.clone()) {
+                Ok(o) => o,
+                Err(e) => return error_response(request_id, -32602, format!("Invalid options: {}", e)),
             };
+            options.playlist_type.get_or_insert_with(|| "vod".to_string());
+            
+            let job_id = crate::ffmpeg_wrapper::new_job_id();
+            let serve_key = options.encrypt && options.serve_key;
+            if serve_key {
+                options.key_uri.get_or_insert_with(|| state.key_url(&job_id));
+            }
+            match state.ffmpeg.convert_to_hls(
+                &job_id,
+                std::path::Path::new(input_path),
+                std::path::Path::new(output_dir),
+                segment_duration,
+                &options,
+            ).await {
+                Ok(output) => {
+                    crate::artifacts::record_packaged(&job_id, &output, serve_key).await;
+                    let mut lines = Vec::new();
+                    if let Some(playlist) = &output.playlist {
+                        lines.push(format!("HLS playlist: {}", playlist.display()));
+                        if options.encrypt {
+                            lines.push(format!("AES-128 key: {}", playlist.with_file_name("playlist.key").display()));
+                        }
+                        if serve_key {
+                            lines.push(format!("Key served at {} (Authorization: Bearer <token>)", state.key_url(&job_id)));
+                        }
+                    }
+                    if let Some(mpd) = &output.mpd {
+                        lines.push(format!("DASH manifest: {}", mpd.display()));
+                    }
+                    let text = lines.join("\n");
+                    json!({
+                        "content": [{
+                            "type": "text",
+                            "text": text
+                        }]
+                    })
+                }
+                Err(e) => return app_error_response(request_id, "Failed to convert", e.into()),
+            }
         }
-    };
-    
-    let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
-    
-    // Execute tool based on name
-    let result = match tool_name {
-        "m3u8_set_url" => {
+        "m3u8_probe" => {
             let url = match arguments.get("url").and_then(|v| v.as_str()) {
                 Some(u) => u,
                 None => {
@@ -636,223 +2797,795 @@ async fn handle_tools_call(
                 }
             };
             
-            // Store the URL in a global state
-            let mut url_state = crate::CURRENT_M3U8_URL.write().await;
-            *url_state = Some(url.to_string());
+            if arguments.get("structured").and_then(|v| v.as_bool()).unwrap_or(false) {
+                let parser = crate::profiles::parser();
+                match crate::video_metadata::probe_variants(&parser, &state.ffmpeg, url).await {
+                    Ok(variants) => json!({
+                        "content": [{
+                            "type": "text",
+                            "text": crate::tool_output::render(json!(variants), None, &arguments)
+                        }]
+                    }),
+                    Err(e) => return app_error_response(request_id, "Failed to probe stream", e),
+                }
+            } else {
+                // Use FFmpeg service to probe
+                match state.ffmpeg.probe_stream(url).await {
+                    Ok(info) => json!({
+                        "content": [{
+                            "type": "text",
+                            "text": crate::tool_output::render_text(info, true, &arguments)
+                        }]
+                    }),
+                    Err(e) => {
+                        return app_error_response(request_id, "Failed to probe stream", e.into());
+                    }
+                }
+            }
+        }
+        "m3u8_extract_segments" => {
+            let url = match arguments.get("url").and_then(|v| v.as_str()) {
+                Some(u) => u,
+                None => {
+                    return JsonRpcResponse {
+                        jsonrpc: "2.0".to_string(),
+                        id: request_id,
+                        result: None,
+                        error: Some(JsonRpcError {
+                            code: -32602,
+                            message: "Missing 'url' parameter".to_string(),
+                            data: None,
+                        }),
+                    };
+                }
+            };
+            
+            let base_url = arguments.get("base_url").and_then(|v| v.as_str());
+            
+            // Use m3u8 parser to extract segments
+            let parser = crate::profiles::parser();
+            
+            // Written to a file for external downloaders
+            if let Some(export_path) = arguments.get("export_path").and_then(|v| v.as_str()) {
+                use crate::segment_export::SegmentListFormat;
+                let format: SegmentListFormat = match arguments.get("format") {
+                    Some(v) => match serde_json::from_value(v.clone()) {
+                        Ok(f) => f,
+                        Err(e) => return error_response(request_id, -32602, format!("Invalid format: {}", e)),
+                    },
+                    None => SegmentListFormat::List,
+                };
+                let output_dir = crate::paths::expand_path(&crate::config::load_config().unwrap_or_default().output_dir);
+                let path = crate::paths::resolve_output_path(std::path::Path::new(export_path), &output_dir);
+                match crate::segment_export::export(&parser, url, base_url, format, &path).await {
+                    Ok(count) => json!({
+                        "content": [{
+                            "type": "text",
+                            "text": serde_json::to_string_pretty(&json!({
+                                "path": path,
+                                "format": format,
+                                "segments": count
+                            })).unwrap_or_default()
+                        }]
+                    }),
+                    Err(e) => return app_error_response(request_id, "Failed to export segments", e),
+                }
+            } else {
+                match parser.extract_segments(url, base_url).await {
+                    Ok(segments) => json!({
+                        "content": [{
+                            "type": "text",
+                            "text": crate::tool_output::render(json!({ "segments": segments }), Some("segments"), &arguments)
+                        }]
+                    }),
+                    Err(e) => {
+                        return app_error_response(request_id, "Failed to extract segments", e.into());
+                    }
+                }
+            }
+        }
+        "m3u8_stats" => {
+            let url = match arguments.get("url").and_then(|v| v.as_str()) {
+                Some(u) => u,
+                None => return error_response(request_id, -32602, "Missing required parameter: url"),
+            };
+            let sample_sizes = arguments.get("sample_sizes").and_then(|v| v.as_bool()).unwrap_or(false);
+            let max_samples = arguments.get("max_samples").and_then(|v| v.as_u64()).unwrap_or(20) as usize;
+            
+            let parser = crate::profiles::parser();
+            match crate::analysis::playlist_stats(&parser, url, sample_sizes, max_samples).await {
+                Ok(report) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&report).unwrap_or_else(|_| "Failed to serialize".to_string())
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to compute playlist stats", e.into()),
+            }
+        }
+        "m3u8_compatibility" => {
+            let url = match arguments.get("url").and_then(|v| v.as_str()) {
+                Some(u) => u,
+                None => return error_response(request_id, -32602, "Missing required parameter: url"),
+            };
+            let targets: Vec<crate::compatibility::Target> = match arguments.get("targets") {
+                Some(v) => match serde_json::from_value(v.clone()) {
+                    Ok(t) => t,
+                    Err(e) => return error_response(request_id, -32602, format!("Invalid targets: {}", e)),
+                },
+                None => crate::compatibility::Target::ALL.to_vec(),
+            };
+            let probe = arguments.get("probe").and_then(|v| v.as_bool()).unwrap_or(false);
+            
+            let parser = crate::profiles::parser();
+            match crate::compatibility::compatibility_report(&parser, &state.ffmpeg, url, &targets, probe).await {
+                Ok(report) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&report).unwrap_or_else(|_| "Failed to serialize".to_string())
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to build compatibility report", e),
+            }
+        }
+        "m3u8_loudness" => {
+            let input = match arguments.get("input").and_then(|v| v.as_str()) {
+                Some(i) => i,
+                None => return error_response(request_id, -32602, "Missing required parameter: input"),
+            };
+            let normalize = arguments.get("normalize").and_then(|v| v.as_bool()).unwrap_or(false);
+            let output_path = arguments.get("output_path").and_then(|v| v.as_str());
+            if normalize && output_path.is_none() {
+                return error_response(request_id, -32602, "output_path is required when normalize is true");
+            }
+            
+            let loudness = match crate::media_analysis::analyze_loudness(&state.ffmpeg, input).await {
+                Ok(report) => report,
+                Err(e) => return app_error_response(request_id, "Failed to analyze loudness", e),
+            };
+            
+            let mut report = json!({
+                "input": input,
+                "loudness": loudness
+            });
+            
+            if let (true, Some(output_path)) = (normalize, output_path) {
+                let defaults = crate::media_analysis::LoudnessTarget::default();
+                let target = crate::media_analysis::LoudnessTarget {
+                    integrated: arguments.get("target_i").and_then(|v| v.as_f64()).unwrap_or(defaults.integrated),
+                    true_peak: arguments.get("target_tp").and_then(|v| v.as_f64()).unwrap_or(defaults.true_peak),
+                    lra: arguments.get("target_lra").and_then(|v| v.as_f64()).unwrap_or(defaults.lra),
+                };
+                let filter = crate::media_analysis::loudnorm_filter(&loudness, target);
+                let job_id = ffmpeg_wrapper::new_job_id();
+                
+                match state.ffmpeg.normalize_loudness(&job_id, input, std::path::Path::new(output_path), &filter).await {
+                    Ok(path) => {
+                        report["normalized_output"] = json!(path.to_string_lossy());
+                        report["filter"] = json!(filter);
+                    }
+                    Err(e) => return app_error_response(request_id, "Failed to normalize loudness", e.into()),
+                }
+            }
             
             json!({
                 "content": [{
                     "type": "text",
-                    "text": format!("URL set to: {}", url)
+                    "text": serde_json::to_string_pretty(&report).unwrap_or_else(|_| "Failed to serialize".to_string())
                 }]
             })
         }
-        "m3u8_get_url" => {
-            let url_state = crate::CURRENT_M3U8_URL.read().await;
-            let url = url_state.as_ref().map(|s| s.as_str()).unwrap_or("No URL set");
+        "m3u8_scenes" => {
+            use crate::media_analysis::SceneExport;
+            
+            let input = match arguments.get("input").and_then(|v| v.as_str()) {
+                Some(i) => i,
+                None => return error_response(request_id, -32602, "Missing required parameter: input"),
+            };
+            let threshold = arguments.get("threshold").and_then(|v| v.as_f64()).unwrap_or(0.4);
+            let max_scenes = arguments.get("max_scenes").and_then(|v| v.as_u64()).unwrap_or(100) as usize;
+            let export: SceneExport = match arguments.get("export") {
+                Some(v) => match serde_json::from_value(v.clone()) {
+                    Ok(e) => e,
+                    Err(e) => return error_response(request_id, -32602, format!("Invalid export: {}", e)),
+                },
+                None => SceneExport::None,
+            };
+            let output_dir = arguments.get("output_dir").and_then(|v| v.as_str());
+            if export != SceneExport::None && output_dir.is_none() {
+                return error_response(request_id, -32602, "output_dir is required when exporting scenes");
+            }
+            
+            let mut scenes = match crate::media_analysis::detect_scenes(&state.ffmpeg, input, threshold, max_scenes).await {
+                Ok(scenes) => scenes,
+                Err(e) => return app_error_response(request_id, "Failed to detect scenes", e),
+            };
+            if let Some(dir) = output_dir {
+                if let Err(e) = crate::media_analysis::export_scenes(&state.ffmpeg, input, &mut scenes, export, std::path::Path::new(dir)).await {
+                    return app_error_response(request_id, "Failed to export scenes", e);
+                }
+            }
             
+            let report = json!({
+                "input": input,
+                "threshold": threshold,
+                "scene_count": scenes.len(),
+                "scenes": scenes
+            });
             json!({
                 "content": [{
                     "type": "text",
-                    "text": format!("Current URL: {}", url)
+                    "text": serde_json::to_string_pretty(&report).unwrap_or_else(|_| "Failed to serialize".to_string())
                 }]
             })
         }
-        "m3u8_parse" => {
-            let url = arguments.get("url").and_then(|v| v.as_str());
-            let content = arguments.get("content").and_then(|v| v.as_str());
+        "m3u8_grab_frame" => {
+            use base64::Engine;
+            use crate::media_analysis::{FrameFormat, FramePosition};
             
-            if url.is_none() && content.is_none() {
-                return JsonRpcResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: request_id,
-                    result: None,
-                    error: Some(JsonRpcError {
-                        code: -32602,
-                        message: "Either 'url' or 'content' parameter is required".to_string(),
-                        data: None,
-                    }),
-                };
+            let input = match arguments.get("input").and_then(|v| v.as_str()) {
+                Some(i) => i,
+                None => return error_response(request_id, -32602, "Missing required parameter: input"),
+            };
+            let max_width = arguments.get("max_width").and_then(|v| v.as_u64()).unwrap_or(1280) as u32;
+            let format: FrameFormat = match arguments.get("format") {
+                Some(v) => match serde_json::from_value(v.clone()) {
+                    Ok(f) => f,
+                    Err(e) => return error_response(request_id, -32602, format!("Invalid format: {}", e)),
+                },
+                None => FrameFormat::Jpeg,
+            };
+            let max_width = if max_width > 0 { Some(max_width) } else { None };
+            // Without a timestamp, a stream is shown where a player would
+            // start it; inputs that are not playlists, such as MP4 URLs, at 0
+            let position = match arguments.get("timestamp").and_then(|v| v.as_f64()) {
+                Some(timestamp) => FramePosition::Time(timestamp),
+                None if input.starts_with("http://") || input.starts_with("https://") => {
+                    crate::media_analysis::playback_start(&crate::profiles::parser(), input)
+                        .await
+                        .unwrap_or(FramePosition::Time(0.0))
+                }
+                None => FramePosition::Time(0.0),
+            };
+            
+            match crate::media_analysis::grab_frame(&state.ffmpeg, input, position, max_width, format).await {
+                Ok(image) => json!({
+                    "content": [
+                        {
+                            "type": "image",
+                            "data": base64::engine::general_purpose::STANDARD.encode(&image),
+                            "mimeType": format.mime_type()
+                        },
+                        {
+                            "type": "text",
+                            "text": format!("Frame at {} from {}", position, input)
+                        }
+                    ]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to grab frame", e),
+            }
+        }
+        "m3u8_fingerprint" => {
+            let url = match arguments.get("url").and_then(|v| v.as_str()) {
+                Some(u) => u,
+                None => return error_response(request_id, -32602, "Missing required parameter: url"),
+            };
+            let samples = arguments.get("samples").and_then(|v| v.as_u64())
+                .map_or(crate::content_fingerprint::DEFAULT_SAMPLES, |n| n as usize);
+            let others: Vec<&str> = arguments.get("compare_with")
+                .and_then(|v| v.as_array())
+                .map(|items| items.iter().filter_map(|v| v.as_str()).collect())
+                .unwrap_or_default();
+            let db = match crate::database::global_db().await {
+                Ok(db) => db,
+                Err(e) => return app_error_response(request_id, "Failed to fingerprint stream", e),
+            };
+            let parser = crate::profiles::parser();
+            
+            for other in others {
+                if let Err(e) = crate::content_fingerprint::fingerprint(&db, &state.ffmpeg, &parser, other, samples).await {
+                    return app_error_response(request_id, &format!("Failed to fingerprint {}", other), e);
+                }
+            }
+            match crate::content_fingerprint::fingerprint(&db, &state.ffmpeg, &parser, url, samples).await {
+                Ok(report) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&report).unwrap_or_default()
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to fingerprint stream", e),
+            }
+        }
+        "m3u8_report" => {
+            use crate::reports::ReportFormat;
+            
+            let from = arguments.get("from").and_then(|v| v.as_str());
+            let to = arguments.get("to").and_then(|v| v.as_str());
+            let format: ReportFormat = match arguments.get("format") {
+                Some(v) => match serde_json::from_value(v.clone()) {
+                    Ok(f) => f,
+                    Err(e) => return error_response(request_id, -32602, format!("Invalid format: {}", e)),
+                },
+                None => ReportFormat::Json,
+            };
+            
+            let db = match crate::database::global_db().await {
+                Ok(db) => db,
+                Err(e) => return app_error_response(request_id, "Failed to generate report", e),
+            };
+            
+            match crate::reports::generate_report(&db, from, to, format) {
+                Ok(report) => {
+                    // Keep a copy so remote clients can download it as a file
+                    let job_id = ffmpeg_wrapper::new_job_id();
+                    let extension = if format == ReportFormat::Csv { "csv" } else { "json" };
+                    let mut content = vec![json!({
+                        "type": "text",
+                        "text": report
+                    })];
+                    match crate::artifacts::save_text(&job_id, extension, &report) {
+                        Ok(path) => {
+                            crate::artifacts::record(&job_id, "report", &path).await;
+                            content.push(json!({
+                                "type": "text",
+                                "text": format!("Job {}: report file at {}", job_id, state.artifacts_url(&job_id))
+                            }));
+                        }
+                        Err(e) => eprintln!("Failed to save report: {}", e),
+                    }
+                    json!({ "content": content })
+                }
+                Err(e) => return app_error_response(request_id, "Failed to generate report", e),
+            }
+        }
+        "m3u8_transcribe" => {
+            let download_id = arguments.get("download_id").and_then(|v| v.as_i64()).map(|v| v as i32);
+            let media = match (download_id, arguments.get("path").and_then(|v| v.as_str())) {
+                (Some(id), _) => {
+                    match crate::database::global_db().await.and_then(|db| db.get_download(id).map_err(AppError::from)) {
+                        Ok(Some(download)) => std::path::PathBuf::from(download.output_path),
+                        Ok(None) => return error_response(request_id, -32602, format!("Download {} not found", id)),
+                        Err(e) => return app_error_response(request_id, "Failed to transcribe", e),
+                    }
+                }
+                (None, Some(path)) => std::path::PathBuf::from(path),
+                (None, None) => return error_response(request_id, -32602, "Missing required parameter: download_id or path"),
+            };
+            let config = match crate::config::load_config() {
+                Ok(config) => config.transcription,
+                Err(e) => return app_error_response(request_id, "Failed to transcribe", e),
+            };
+            
+            match crate::transcription::transcribe_file(&state.ffmpeg, &config, &media).await {
+                Ok(path) => {
+                    let text = std::fs::read_to_string(&path).unwrap_or_default();
+                    let job_id = ffmpeg_wrapper::new_job_id();
+                    crate::artifacts::record(&job_id, "transcript", &path).await;
+                    let mut content = vec![json!({
+                        "type": "text",
+                        "text": text
+                    }), json!({
+                        "type": "text",
+                        "text": format!("Job {}: transcript file at {}", job_id, state.artifacts_url(&job_id))
+                    })];
+                    if let Some(id) = download_id {
+                        content.push(json!({
+                            "type": "text",
+                            "text": format!("Transcript saved to {} (resource m3u8://transcripts/{})", path.display(), id)
+                        }));
+                    }
+                    json!({ "content": content })
+                }
+                Err(e) => return app_error_response(request_id, "Failed to transcribe", e),
             }
+        }
+        "m3u8_chapters" => {
+            let chapters: Option<Vec<crate::chapters::Chapter>> = match arguments.get("chapters") {
+                Some(v) => match serde_json::from_value(v.clone()) {
+                    Ok(c) => Some(c),
+                    Err(e) => return error_response(request_id, -32602, format!("Invalid chapters: {}", e)),
+                },
+                None => None,
+            };
+            let download_id = arguments.get("download_id").and_then(|v| v.as_i64()).map(|v| v as i32);
+            let (media, stream_url) = match (download_id, arguments.get("path").and_then(|v| v.as_str())) {
+                (Some(id), _) => {
+                    match crate::database::global_db().await.and_then(|db| db.get_download(id).map_err(AppError::from)) {
+                        Ok(Some(download)) => (std::path::PathBuf::from(download.output_path), Some(download.url)),
+                        Ok(None) => return error_response(request_id, -32602, format!("Download {} not found", id)),
+                        Err(e) => return app_error_response(request_id, "Failed to add chapters", e),
+                    }
+                }
+                (None, Some(path)) => (
+                    std::path::PathBuf::from(path),
+                    arguments.get("url").and_then(|v| v.as_str()).map(|s| s.to_string()),
+                ),
+                (None, None) => return error_response(request_id, -32602, "Missing required parameter: download_id or path"),
+            };
             
-            // Parse m3u8 using the parser module
-            if let Some(url) = url {
-                let parser = crate::m3u8_parser::M3u8Parser::new();
-                match parser.parse_url(url).await {
-                    Ok(playlist) => json!({
+            let parser = crate::profiles::parser();
+            match crate::chapters::add_chapters(&parser, &state.ffmpeg, &media, stream_url.as_deref(), chapters).await {
+                Ok(chapters) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&json!({
+                            "path": media,
+                            "chapters": chapters
+                        })).unwrap_or_default()
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to add chapters", e),
+            }
+        }
+        "m3u8_cache_stats" => {
+            match crate::database::global_db().await.and_then(|db| db.get_cache_stats().map_err(AppError::from)) {
+                Ok(stats) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&stats).unwrap_or_default()
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to get cache stats", e),
+            }
+        }
+        "m3u8_cache_list" => {
+            let query = arguments.get("query").and_then(|v| v.as_str()).unwrap_or("");
+            let limit = arguments.get("limit").and_then(|v| v.as_u64()).unwrap_or(100) as usize;
+            match crate::database::global_db().await.and_then(|db| db.search_cached_playlists(query).map_err(AppError::from)) {
+                Ok(playlists) => {
+                    let entries: Vec<Value> = playlists.iter().take(limit).map(|p| json!({
+                        "url": p.url,
+                        "playlist_type": p.playlist_type,
+                        "segments_count": p.segments_count,
+                        "total_duration": p.total_duration,
+                        "cached_at": p.cached_at.to_rfc3339()
+                    })).collect();
+                    json!({
                         "content": [{
                             "type": "text",
-                            "text": serde_json::to_string_pretty(&playlist).unwrap_or_else(|_| "Failed to serialize".to_string())
+                            "text": serde_json::to_string_pretty(&entries).unwrap_or_default()
                         }]
-                    }),
-                    Err(e) => {
-                        return JsonRpcResponse {
-                            jsonrpc: "2.0".to_string(),
-                            id: request_id,
-                            result: None,
-                            error: Some(JsonRpcError {
-                                code: -32603,
-                                message: format!("Failed to parse m3u8: {}", e),
-                                data: None,
-                            }),
-                        };
-                    }
+                    })
                 }
-            } else {
-                // Parse from content
-                json!({
+                Err(e) => return app_error_response(request_id, "Failed to list cache", e),
+            }
+        }
+        "m3u8_cache_clear" => {
+            let include_downloads = arguments.get("include_downloads").and_then(|v| v.as_bool()).unwrap_or(false);
+            let result = crate::database::global_db().await
+                .and_then(|db| crate::trash::clear_cache(&db, include_downloads));
+            match result {
+                Ok(entry) => json!({
                     "content": [{
                         "type": "text",
-                        "text": "Parsing from content not yet implemented"
+                        "text": format!(
+                            "Cleared {} cache entries. Restore them with m3u8_trash_restore (id {})",
+                            entry.items, entry.id
+                        )
                     }]
-                })
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to clear cache", e),
             }
         }
-        "m3u8_download" => {
-            let url = match arguments.get("url").and_then(|v| v.as_str()) {
-                Some(u) => u,
-                None => {
-                    return JsonRpcResponse {
-                        jsonrpc: "2.0".to_string(),
-                        id: request_id,
-                        result: None,
-                        error: Some(JsonRpcError {
-                            code: -32602,
-                            message: "Missing required parameter: url".to_string(),
-                            data: None,
-                        }),
-                    };
-                }
+        "m3u8_trash_list" => {
+            match crate::database::global_db().await.and_then(|db| db.list_trash().map_err(AppError::from)) {
+                Ok(entries) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&entries).unwrap_or_default()
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to list trash", e),
+            }
+        }
+        "m3u8_trash_restore" => {
+            let id = match arguments.get("id").and_then(|v| v.as_i64()) {
+                Some(id) => id,
+                None => return error_response(request_id, -32602, "Missing required parameter: id"),
             };
-            
+            match crate::database::global_db().await.and_then(|db| crate::trash::restore(&db, id)) {
+                Ok(restored) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&restored).unwrap_or_default()
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to restore trash entry", e),
+            }
+        }
+        "m3u8_cache_prune" => {
+            let days = match arguments.get("days").and_then(|v| v.as_u64()) {
+                Some(days) => days as u32,
+                None => return error_response(request_id, -32602, "Missing required parameter: days"),
+            };
+            match crate::database::global_db().await.and_then(|db| db.clear_old_cache(days).map_err(AppError::from)) {
+                Ok(removed) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": format!("Removed {} cache entries older than {} days", removed, days)
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to prune cache", e),
+            }
+        }
+        "m3u8_resume_jobs" => {
+            if arguments.get("list_only").and_then(|v| v.as_bool()).unwrap_or(false) {
+                match crate::journal::resumable().await {
+                    Ok(entries) => json!({
+                        "content": [{
+                            "type": "text",
+                            "text": serde_json::to_string_pretty(&entries).unwrap_or_default()
+                        }]
+                    }),
+                    Err(e) => return app_error_response(request_id, "Failed to read job journal", e),
+                }
+            } else {
+                let job_ids: Option<Vec<String>> = arguments.get("job_ids")
+                    .and_then(|v| v.as_array())
+                    .map(|items| items.iter().filter_map(|id| id.as_str().map(String::from)).collect());
+                let parser = Arc::new(crate::profiles::parser());
+                match crate::journal::resume(parser, state.ffmpeg.clone(), job_ids.as_deref()).await {
+                    Ok(started) => json!({
+                        "content": [{
+                            "type": "text",
+                            "text": if started.is_empty() {
+                                "No resumable jobs".to_string()
+                            } else {
+                                format!("Resumed {} job(s): {}", started.len(), started.join(", "))
+                            }
+                        }]
+                    }),
+                    Err(e) => return app_error_response(request_id, "Failed to resume jobs", e),
+                }
+            }
+        }
+        "m3u8_generate_master" => {
             let output_path = match arguments.get("output_path").and_then(|v| v.as_str()) {
                 Some(p) => p,
-                None => {
-                    return JsonRpcResponse {
-                        jsonrpc: "2.0".to_string(),
-                        id: request_id,
-                        result: None,
-                        error: Some(JsonRpcError {
-                            code: -32602,
-                            message: "Missing required parameter: output_path".to_string(),
-                            data: None,
-                        }),
-                    };
-                }
+                None => return error_response(request_id, -32602, "Missing required parameter: output_path"),
+            };
+            let playlist: crate::m3u8_parser::MasterPlaylist = match serde_json::from_value(arguments.clone()) {
+                Ok(p) => p,
+                Err(e) => return error_response(request_id, -32602, format!("Invalid playlist: {}", e)),
             };
             
-            // Use FFmpeg wrapper to download
-            let config = crate::ffmpeg_wrapper::FFmpegConfig::default();
-            let wrapper = crate::ffmpeg_wrapper::FFmpegWrapper::new(config);
-            
-            let output = Some(std::path::Path::new(output_path));
-            
-            match wrapper.download_stream(url, output).await {
-                Ok(path) => json!({
+            match crate::master::generate_master(std::path::Path::new(output_path), playlist) {
+                Ok(text) => json!({
                     "content": [{
                         "type": "text",
-                        "text": format!("Downloaded to: {}", path.display())
+                        "text": format!("Master playlist written to {}\n\n{}", output_path, text)
                     }]
                 }),
-                Err(e) => {
-                    return JsonRpcResponse {
-                        jsonrpc: "2.0".to_string(),
-                        id: request_id,
-                        result: None,
-                        error: Some(JsonRpcError {
-                            code: -32603,
-                            message: format!("Failed to download m3u8: {}", e),
-                            data: None,
-                        }),
-                    };
-                }
+                Err(e) => return app_error_response(request_id, "Failed to generate master playlist", e),
             }
         }
-        "m3u8_probe" => {
-            let url = match arguments.get("url").and_then(|v| v.as_str()) {
-                Some(u) => u,
-                None => {
-                    return JsonRpcResponse {
-                        jsonrpc: "2.0".to_string(),
-                        id: request_id,
-                        result: None,
-                        error: Some(JsonRpcError {
-                            code: -32602,
-                            message: "Missing required parameter: url".to_string(),
-                            data: None,
-                        }),
-                    };
-                }
+        "m3u8_repair" => {
+            let input = match arguments.get("input").and_then(|v| v.as_str()) {
+                Some(i) => i,
+                None => return error_response(request_id, -32602, "Missing required parameter: input"),
+            };
+            let output_path = arguments.get("output_path").and_then(|v| v.as_str()).map(std::path::PathBuf::from);
+            let options: crate::repair::RepairOptions = match serde_json::from_value(arguments.clone()) {
+                Ok(o) => o,
+                Err(e) => return error_response(request_id, -32602, format!("Invalid options: {}", e)),
             };
             
-            // Use FFmpeg wrapper to probe
-            let config = crate::ffmpeg_wrapper::FFmpegConfig::default();
-            let wrapper = crate::ffmpeg_wrapper::FFmpegWrapper::new(config);
-            
-            match wrapper.probe_stream(url).await {
-                Ok(info) => json!({
+            let parser = crate::profiles::parser();
+            match crate::repair::repair(&parser, &state.ffmpeg, input, output_path.as_deref(), &options).await {
+                Ok(report) => json!({
                     "content": [{
                         "type": "text",
-                        "text": info
+                        "text": serde_json::to_string_pretty(&report).unwrap_or_default()
                     }]
                 }),
-                Err(e) => {
-                    return JsonRpcResponse {
-                        jsonrpc: "2.0".to_string(),
-                        id: request_id,
-                        result: None,
-                        error: Some(JsonRpcError {
-                            code: -32603,
-                            message: format!("Failed to probe stream: {}", e),
-                            data: None,
-                        }),
-                    };
-                }
+                Err(e) => return app_error_response(request_id, "Failed to repair playlist", e),
             }
         }
-        "m3u8_extract_segments" => {
-            let url = match arguments.get("url").and_then(|v| v.as_str()) {
-                Some(u) => u,
-                None => {
-                    return JsonRpcResponse {
-                        jsonrpc: "2.0".to_string(),
-                        id: request_id,
-                        result: None,
-                        error: Some(JsonRpcError {
-                            code: -32602,
-                            message: "Missing 'url' parameter".to_string(),
-                            data: None,
-                        }),
-                    };
-                }
+        "m3u8_interstitials" => {
+            let Some(url) = arguments.get("url").and_then(|v| v.as_str()) else {
+                return error_response(request_id, -32602, "Missing required parameter: url");
             };
-            
-            let base_url = arguments.get("base_url").and_then(|v| v.as_str());
-            
-            // Use m3u8 parser to extract segments
-            let parser = crate::m3u8_parser::M3u8Parser::new();
-            
-            match parser.extract_segments(url, base_url).await {
-                Ok(segments) => json!({
+            match crate::interstitials::list(&crate::profiles::parser(), url).await {
+                Ok(interstitials) => json!({
                     "content": [{
                         "type": "text",
-                        "text": serde_json::to_string_pretty(&segments).unwrap_or_else(|_| "[]".to_string())
+                        "text": serde_json::to_string_pretty(&interstitials).unwrap_or_default()
                     }]
                 }),
-                Err(e) => {
-                    return JsonRpcResponse {
-                        jsonrpc: "2.0".to_string(),
-                        id: request_id,
-                        result: None,
-                        error: Some(JsonRpcError {
-                            code: -32603,
-                            message: format!("Failed to extract segments: {}", e),
-                            data: None,
-                        }),
-                    };
-                }
+                Err(e) => return app_error_response(request_id, "Failed to read interstitials", e),
+            }
+        }
+        "m3u8_graph" => {
+            let Some(url) = arguments.get("url").and_then(|v| v.as_str()) else {
+                return error_response(request_id, -32602, "Missing required parameter: url");
+            };
+            let max_segments = arguments.get("max_segments").and_then(|v| v.as_u64())
+                .map(|n| n as usize)
+                .unwrap_or(crate::graph::DEFAULT_MAX_SEGMENTS);
+            let check = arguments.get("check").and_then(|v| v.as_bool()).unwrap_or(true);
+            let config = crate::config::load_config().unwrap_or_default().effective();
+            let parser = crate::profiles::parser_for(&config);
+            match crate::graph::build(&config, &parser, url, max_segments, check).await {
+                Ok(graph) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string(&graph).unwrap_or_default()
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to build playlist graph", e),
+            }
+        }
+        "m3u8_abr_simulate" => {
+            let Some(url) = arguments.get("url").and_then(|v| v.as_str()) else {
+                return error_response(request_id, -32602, "Missing required parameter: url");
+            };
+            let steps = match arguments.get("profile").map(|v| serde_json::from_value::<Vec<crate::abr_sim::BandwidthStep>>(v.clone())) {
+                Some(Ok(steps)) => Some(steps),
+                Some(Err(e)) => return error_response(request_id, -32602, format!("Invalid profile: {}", e)),
+                None => None,
+            };
+            let options: crate::abr_sim::SimOptions = match serde_json::from_value(arguments.clone()) {
+                Ok(options) => options,
+                Err(e) => return error_response(request_id, -32602, format!("Invalid options: {}", e)),
+            };
+            let profile = match crate::abr_sim::profile(steps, arguments.get("kbps").and_then(|v| v.as_f64())) {
+                Ok(profile) => profile,
+                Err(e) => return app_error_response(request_id, "Invalid bandwidth profile", e),
+            };
+            match crate::abr_sim::run(&crate::profiles::parser(), url, &profile, &options).await {
+                Ok(simulation) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": crate::tool_output::render(json!(simulation), Some("timeline"), &arguments)
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to simulate playback", e),
+            }
+        }
+        "m3u8_detect_stream" => {
+            let Some(url) = arguments.get("url").and_then(|v| v.as_str()) else {
+                return error_response(request_id, -32602, "Missing required parameter: url");
+            };
+            let config = crate::config::load_config().unwrap_or_default().effective();
+            match crate::detect::detect(&config, url).await {
+                Ok(detection) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&detection).unwrap_or_default()
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to detect stream type", e),
+            }
+        }
+        "m3u8_archive_list" => {
+            let config = crate::config::load_config().unwrap_or_default().effective();
+            let summary = crate::segment_archive::summary(&crate::segment_archive::dir(&config));
+            json!({
+                "content": [{
+                    "type": "text",
+                    "text": serde_json::to_string_pretty(&summary).unwrap_or_default()
+                }]
+            })
+        }
+        "m3u8_archive_reconstruct" => {
+            let (Some(url), Some(output)) = (
+                arguments.get("url").and_then(|v| v.as_str()),
+                arguments.get("output").and_then(|v| v.as_str()),
+            ) else {
+                return error_response(request_id, -32602, "Missing required parameters: url, output");
+            };
+            let window: crate::segment_archive::Window = match serde_json::from_value(arguments.clone()) {
+                Ok(window) => window,
+                Err(e) => return error_response(request_id, -32602, format!("Invalid window: {}", e)),
+            };
+            let config = crate::config::load_config().unwrap_or_default().effective();
+            let output = crate::paths::resolve_output_path(
+                &crate::paths::expand_path(output),
+                &crate::paths::expand_path(&config.output_dir),
+            );
+            match crate::segment_archive::reconstruct(&state.ffmpeg, &crate::segment_archive::dir(&config), url, &window, &output).await {
+                Ok(reconstruction) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&reconstruction).unwrap_or_default()
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to reconstruct from the archive", e),
+            }
+        }
+        "m3u8_archive_delete" => {
+            let Some(id) = arguments.get("id").and_then(|v| v.as_str()) else {
+                return error_response(request_id, -32602, "Missing required parameter: id");
+            };
+            let config = crate::config::load_config().unwrap_or_default().effective();
+            let (dir, recording) = (crate::segment_archive::dir(&config), id.to_string());
+            // Walks the archive on disk, keep it off the async workers
+            let deleted = tokio::task::spawn_blocking(move || crate::segment_archive::delete(&dir, &recording))
+                .await
+                .unwrap_or_else(|e| Err(AppError::internal(e.to_string())));
+            match deleted {
+                Ok(removed) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": format!("Deleted recording {}; {} segment(s) no longer used were removed", id, removed)
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to delete the recording", e),
+            }
+        }
+        "m3u8_export_vod" => {
+            let Some(path) = arguments.get("path").and_then(|v| v.as_str()) else {
+                return error_response(request_id, -32602, "Missing required parameter: path");
+            };
+            let job_id = crate::ffmpeg_wrapper::new_job_id();
+            match crate::vod_export::export(&state.ffmpeg, &job_id, std::path::Path::new(path)).await {
+                Ok(export) => json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&export).unwrap_or_default()
+                    }]
+                }),
+                Err(e) => return app_error_response(request_id, "Failed to export recording", e),
             }
         }
+        "m3u8_ffmpeg_command" => {
+            let commands: Vec<crate::ffmpeg_wrapper::CommandPreview> = if let Some(job_id) = arguments.get("job_id").and_then(|v| v.as_str()) {
+                match crate::database::global_db().await.and_then(|db| db.get_job_commands(job_id).map_err(AppError::from)) {
+                    Ok(commands) if commands.is_empty() => return error_response(request_id, -32602, format!("No FFmpeg command recorded for job {}", job_id)),
+                    Ok(commands) => commands.into_iter().map(|c| c.command.into()).collect(),
+                    Err(e) => return app_error_response(request_id, "Failed to read job commands", e),
+                }
+            } else {
+                match arguments.get("operation").and_then(|v| v.as_str()) {
+                    Some("download") => {
+                        let Some(url) = arguments.get("url").and_then(|v| v.as_str()) else {
+                            return error_response(request_id, -32602, "Missing required parameter: url");
+                        };
+                        let output_path = arguments.get("output_path").and_then(|v| v.as_str()).map(std::path::Path::new);
+                        let audio = arguments.get("audio")
+                            .and_then(|value| serde_json::from_value(value.clone()).ok())
+                            .unwrap_or_else(|| crate::config::load_config().map(|c| c.downloader.audio).unwrap_or_default());
+                        match state.ffmpeg.preview_download(url, output_path, None, &audio) {
+                            Ok(command) => vec![command.into()],
+                            Err(e) => return app_error_response(request_id, "Failed to build the command", e.into()),
+                        }
+                    }
+                    Some("convert") => {
+                        let (input_path, output_dir) = match (
+                            arguments.get("input_path").and_then(|v| v.as_str()),
+                            arguments.get("output_dir").and_then(|v| v.as_str()),
+                        ) {
+                            (Some(i), Some(o)) => (i, o),
+                            _ => return error_response(request_id, -32602, "Missing required parameters: input_path and output_dir"),
+                        };
+                        let segment_duration = arguments.get("segment_duration").and_then(|v| v.as_u64()).unwrap_or(10) as u32;
+                        let mut options: crate::ffmpeg_wrapper::PackagingOptions = match serde_json::from_value(arguments.clone()) {
+                            Ok(o) => o,
+                            Err(e) => return error_response(request_id, -32602, format!("Invalid options: {}", e)),
+                        };
+                        options.playlist_type.get_or_insert_with(|| "vod".to_string());
+                        vec![state.ffmpeg.preview_convert(
+                            std::path::Path::new(input_path),
+                            std::path::Path::new(output_dir),
+                            segment_duration,
+                            &options,
+                        ).into()]
+                    }
+                    _ => return error_response(request_id, -32602, "Give a job_id, or an operation: \"download\" or \"convert\""),
+                }
+            };
+            
+            json!({
+                "content": [{
+                    "type": "text",
+                    "text": commands.iter().map(|c| c.shell.as_str()).collect::<Vec<_>>().join("\n")
+                }, {
+                    "type": "text",
+                    "text": serde_json::to_string_pretty(&commands).unwrap_or_default()
+                }]
+            })
+        }
         _ => {
             return JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
@@ -876,4 +3609,116 @@ async fn handle_tools_call(
         })),
         error: None,
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AppConfig, Profile};
+    use crate::ffmpeg_wrapper::FFmpegConfig;
+
+    #[tokio::test]
+    async fn test_tools_call_after_profile_switch() {
+        let mut config = AppConfig::default();
+        config.profiles.insert("review".to_string(), Profile {
+            enabled_tools: Some(vec!["m3u8_parse".to_string()]),
+            ..Profile::default()
+        });
+        let state = Arc::new(McpServerState::new_with_tools(
+            "localhost".to_string(),
+            0,
+            config.effective().mcp.enabled_tools,
+            Arc::new(FFmpegService::new(FFmpegConfig::default())),
+            String::new(),
+        ));
+        assert!(state.enabled_tools.read().await.contains(&"m3u8_download".to_string()));
+
+        // What apply_profile does when the profile changes
+        config.active_profile = Some("review".to_string());
+        *state.enabled_tools.write().await = config.effective().mcp.enabled_tools;
+
+        let params = json!({ "name": "m3u8_download", "arguments": { "url": "https://example.com/a.m3u8" } });
+        let response = handle_tools_call(state, Some(json!(1)), Some(params), None).await;
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32602);
+        assert_eq!(error.message, "Tool m3u8_download is not enabled");
+    }
+
+    async fn post_message(state: &Arc<McpServerState>, session_id: Option<&str>, message: Value) -> Response {
+        let mut headers = HeaderMap::new();
+        if let Some(id) = session_id {
+            headers.insert(SESSION_HEADER, id.parse().unwrap());
+        }
+        handle_sse_endpoint(State(state.clone()), headers, Bytes::from(message.to_string())).await
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_roots_list_over_event_stream() {
+        let state = Arc::new(McpServerState::new_with_tools(
+            "localhost".to_string(),
+            0,
+            Vec::new(),
+            Arc::new(FFmpegService::new(FFmpegConfig::default())),
+            String::new(),
+        ));
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": { "capabilities": { "roots": { "listChanged": true } } }
+        });
+        let response = post_message(&state, None, initialize).await;
+        let session_id = response.headers()[SESSION_HEADER].to_str().unwrap().to_string();
+
+        // No stream open yet: nothing to ask, so no root
+        assert_eq!(session_root(&state, &session_id, crate::roots::LIST_TIMEOUT).await, None);
+
+        // What GET /mcp sets up, with this test as the client
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        state.sessions.write().await.get_mut(&session_id).unwrap().outgoing = Some(sender);
+        let client = {
+            let state = state.clone();
+            let session_id = session_id.clone();
+            tokio::spawn(async move {
+                let request = receiver.recv().await.unwrap();
+                assert_eq!(request["method"], "roots/list");
+                let answer = json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "result": { "roots": [{ "uri": "file:///home/me/project" }] }
+                });
+                let response = post_message(&state, Some(&session_id), answer).await;
+                assert_eq!(response.status(), StatusCode::ACCEPTED);
+                receiver
+            })
+        };
+        assert_eq!(session_root(&state, &session_id, crate::roots::LIST_TIMEOUT).await, Some(std::path::PathBuf::from("/home/me/project")));
+        let mut receiver = client.await.unwrap();
+
+        // Known roots are not asked for again until the client says they changed
+        assert_eq!(session_root(&state, &session_id, crate::roots::LIST_TIMEOUT).await, Some(std::path::PathBuf::from("/home/me/project")));
+        assert!(receiver.try_recv().is_err());
+
+        let changed = json!({ "jsonrpc": "2.0", "method": "notifications/roots/list_changed" });
+        let response = post_message(&state, Some(&session_id), changed).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.is_empty());
+        assert!(state.sessions.read().await[&session_id].roots_stale);
+
+        // A client that never answers is waited for once; the roots it listed
+        // last still apply, and the next call does not wait again
+        let timeout = Duration::from_millis(200);
+        let started = std::time::Instant::now();
+        assert_eq!(session_root(&state, &session_id, timeout).await, Some(std::path::PathBuf::from("/home/me/project")));
+        assert!(started.elapsed() >= timeout);
+        assert_eq!(receiver.try_recv().unwrap()["method"], "roots/list");
+        assert!(!state.sessions.read().await[&session_id].roots_stale);
+
+        let started = std::time::Instant::now();
+        assert_eq!(session_root(&state, &session_id, timeout).await, Some(std::path::PathBuf::from("/home/me/project")));
+        assert!(started.elapsed() < timeout);
+        assert!(receiver.try_recv().is_err());
+        assert!(state.pending.lock().await.is_empty());
+    }
+}
@@ -0,0 +1,505 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::error::AppError;
+use crate::ffmpeg_wrapper::FFmpegService;
+use crate::m3u8_parser::{self, M3u8Parser, ParsedPlaylist, PlaybackStart};
+
+// Loudness measurements from FFmpeg's ebur128 and volumedetect filters
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LoudnessReport {
+    // Integrated loudness, LUFS
+    pub integrated: Option<f64>,
+    pub integrated_threshold: Option<f64>,
+    // Loudness range, LU
+    pub lra: Option<f64>,
+    pub lra_low: Option<f64>,
+    pub lra_high: Option<f64>,
+    // True peak, dBTP
+    pub true_peak: Option<f64>,
+    pub mean_volume: Option<f64>,
+    pub max_volume: Option<f64>,
+}
+
+// Loudness normalization targets, matching loudnorm's defaults
+#[derive(Debug, Clone, Copy)]
+pub struct LoudnessTarget {
+    pub integrated: f64,
+    pub true_peak: f64,
+    pub lra: f64,
+}
+
+impl Default for LoudnessTarget {
+    fn default() -> Self {
+        Self {
+            integrated: -24.0,
+            true_peak: -2.0,
+            lra: 7.0,
+        }
+    }
+}
+
+// Number in a "Key: -23.0 LUFS" line
+fn value_after(line: &str, key: &str) -> Option<f64> {
+    line.trim()
+        .strip_prefix(key)?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+// Parse the ebur128 summary and volumedetect lines from FFmpeg stderr
+pub fn parse_loudness(stderr: &str) -> LoudnessReport {
+    let mut report = LoudnessReport::default();
+    let mut in_summary = false;
+    let mut section = "";
+
+    for line in stderr.lines() {
+        if line.contains("mean_volume:") {
+            report.mean_volume = line.split("mean_volume:").nth(1).and_then(|v| value_after(v, ""));
+            continue;
+        }
+        if line.contains("max_volume:") {
+            report.max_volume = line.split("max_volume:").nth(1).and_then(|v| value_after(v, ""));
+            continue;
+        }
+        if line.contains("Parsed_ebur128") && line.trim_end().ends_with("Summary:") {
+            in_summary = true;
+            continue;
+        }
+        if !in_summary {
+            continue;
+        }
+
+        let trimmed = line.trim();
+        match trimmed {
+            "Integrated loudness:" | "Loudness range:" | "True peak:" => {
+                section = trimmed;
+                continue;
+            }
+            _ => {}
+        }
+
+        match section {
+            "Integrated loudness:" => {
+                if let Some(v) = value_after(trimmed, "I:") {
+                    report.integrated = Some(v);
+                } else if let Some(v) = value_after(trimmed, "Threshold:") {
+                    report.integrated_threshold = Some(v);
+                }
+            }
+            "Loudness range:" => {
+                if let Some(v) = value_after(trimmed, "LRA low:") {
+                    report.lra_low = Some(v);
+                } else if let Some(v) = value_after(trimmed, "LRA high:") {
+                    report.lra_high = Some(v);
+                } else if let Some(v) = value_after(trimmed, "LRA:") {
+                    report.lra = Some(v);
+                }
+            }
+            "True peak:" => {
+                if let Some(v) = value_after(trimmed, "Peak:") {
+                    report.true_peak = Some(v);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    report
+}
+
+// Inputs are either stream URLs or local files
+fn check_input(input: &str) -> Result<(), AppError> {
+    if input.starts_with("http://") || input.starts_with("https://") || Path::new(input).exists() {
+        Ok(())
+    } else {
+        Err(AppError::invalid_input(format!("Input is neither a URL nor an existing file: {}", input)))
+    }
+}
+
+// Measure integrated loudness, loudness range and true peak of `input`
+pub async fn analyze_loudness(ffmpeg: &FFmpegService, input: &str) -> Result<LoudnessReport, AppError> {
+    check_input(input)?;
+
+    let args: Vec<String> = ["-vn", "-af", "ebur128=peak=true:framelog=verbose,volumedetect", "-f", "null", "-"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let stderr = ffmpeg.run_analysis(input, &args).await?;
+
+    let report = parse_loudness(&stderr);
+    if report.integrated.is_none() {
+        return Err(AppError::not_found("No audio loudness measured; the input may have no audio stream"));
+    }
+    Ok(report)
+}
+
+// Linear loudnorm filter using measurements from a previous analysis pass
+pub fn loudnorm_filter(measured: &LoudnessReport, target: LoudnessTarget) -> String {
+    let mut filter = format!(
+        "loudnorm=I={}:TP={}:LRA={}",
+        target.integrated, target.true_peak, target.lra
+    );
+    if let (Some(i), Some(tp), Some(lra), Some(thresh)) = (
+        measured.integrated,
+        measured.true_peak,
+        measured.lra,
+        measured.integrated_threshold,
+    ) {
+        filter.push_str(&format!(
+            ":measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:linear=true",
+            i, tp, lra, thresh
+        ));
+    }
+    filter
+}
+
+// A scene starting at `start` seconds. Scene 0 always starts at 0.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Scene {
+    pub index: usize,
+    pub start: f64,
+    // Scene-change score from the select filter, 0..1
+    pub score: Option<f64>,
+    // Exported thumbnail or clip
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SceneExport {
+    None,
+    Thumbnails,
+    Clips,
+}
+
+// Parse (pts_time, scene_score) pairs printed by metadata=print
+pub fn parse_scene_changes(stderr: &str) -> Vec<(f64, Option<f64>)> {
+    let mut changes: Vec<(f64, Option<f64>)> = Vec::new();
+
+    for line in stderr.lines() {
+        if let Some(time) = line.split("pts_time:").nth(1) {
+            if let Some(time) = time.split_whitespace().next().and_then(|t| t.parse().ok()) {
+                changes.push((time, None));
+            }
+        } else if let Some(score) = line.split("lavfi.scene_score=").nth(1) {
+            if let Some(last) = changes.last_mut() {
+                last.1 = score.trim().parse().ok();
+            }
+        }
+    }
+
+    changes
+}
+
+// Detect scene changes in the video of `input`. `threshold` is the select
+// filter's scene score (0..1); lower values find more cuts.
+pub async fn detect_scenes(
+    ffmpeg: &FFmpegService,
+    input: &str,
+    threshold: f64,
+    max_scenes: usize,
+) -> Result<Vec<Scene>, AppError> {
+    check_input(input)?;
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(AppError::invalid_input("threshold must be between 0 and 1"));
+    }
+
+    let filter = format!("select='gt(scene,{})',metadata=print:key=lavfi.scene_score", threshold);
+    let args: Vec<String> = ["-an", "-vf", filter.as_str(), "-f", "null", "-"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let stderr = ffmpeg.run_analysis(input, &args).await?;
+
+    let mut scenes = vec![Scene {
+        index: 0,
+        start: 0.0,
+        score: None,
+        file: None,
+    }];
+    for (start, score) in parse_scene_changes(&stderr) {
+        if scenes.len() >= max_scenes {
+            break;
+        }
+        scenes.push(Scene {
+            index: scenes.len(),
+            start,
+            score,
+            file: None,
+        });
+    }
+    Ok(scenes)
+}
+
+// Export a thumbnail or a stream-copied clip per scene into `output_dir`.
+// Clips are cut with -c copy, so they start on the nearest keyframe.
+pub async fn export_scenes(
+    ffmpeg: &FFmpegService,
+    input: &str,
+    scenes: &mut [Scene],
+    export: SceneExport,
+    output_dir: &Path,
+) -> Result<(), AppError> {
+    if export == SceneExport::None {
+        return Ok(());
+    }
+    std::fs::create_dir_all(output_dir)
+        .map_err(|e| AppError::io(format!("Failed to create output directory: {}", e)))?;
+
+    let ends: Vec<Option<f64>> = scenes.iter().skip(1).map(|s| Some(s.start)).chain([None]).collect();
+
+    for (scene, end) in scenes.iter_mut().zip(ends) {
+        let mut args = vec!["-ss".to_string(), format!("{:.3}", scene.start), "-i".to_string(), input.to_string()];
+        let path = match export {
+            SceneExport::Thumbnails => {
+                args.extend(["-frames:v", "1", "-q:v", "2"].iter().map(|s| s.to_string()));
+                output_dir.join(format!("scene_{:03}.jpg", scene.index))
+            }
+            _ => {
+                if let Some(end) = end {
+                    args.push("-t".to_string());
+                    args.push(format!("{:.3}", end - scene.start));
+                }
+                args.extend(["-c", "copy", "-avoid_negative_ts", "make_zero"].iter().map(|s| s.to_string()));
+                output_dir.join(format!("scene_{:03}.mp4", scene.index))
+            }
+        };
+        args.push("-y".to_string());
+        args.push(path.to_string_lossy().to_string());
+
+        ffmpeg.run_ffmpeg(&args).await?;
+        scene.file = Some(path.to_string_lossy().to_string());
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameFormat {
+    Jpeg,
+    Png,
+}
+
+impl FrameFormat {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            FrameFormat::Jpeg => "image/jpeg",
+            FrameFormat::Png => "image/png",
+        }
+    }
+}
+
+// Where in the input to grab a frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FramePosition {
+    // Seconds from the start of the input
+    Time(f64),
+    // A segment of a live playlist and seconds into it. FFmpeg starts
+    // reading a live playlist near its end, so the segment is picked with
+    // -live_start_index rather than by seeking.
+    LiveSegment { index: usize, offset: f64 },
+}
+
+impl FramePosition {
+    fn input_args(self) -> Vec<String> {
+        let (index, offset) = match self {
+            FramePosition::Time(timestamp) => (None, timestamp),
+            FramePosition::LiveSegment { index, offset } => (Some(index), offset),
+        };
+        let mut args = Vec::new();
+        if let Some(index) = index {
+            args.push("-live_start_index".to_string());
+            args.push(index.to_string());
+        }
+        args.push("-ss".to_string());
+        args.push(format!("{:.3}", offset));
+        args
+    }
+}
+
+impl std::fmt::Display for FramePosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FramePosition::Time(timestamp) => write!(f, "{:.3}s", timestamp),
+            FramePosition::LiveSegment { index, offset } => {
+                write!(f, "{:.3}s into segment {} of the live playlist", offset, index)
+            }
+        }
+    }
+}
+
+// Where a player would start playing the stream at `url`: the EXT-X-START
+// of its media playlist, or else of its master playlist, or a few segments
+// from the live edge. A master playlist uses its first variant.
+pub async fn playback_start(parser: &M3u8Parser, url: &str) -> Result<FramePosition, AppError> {
+    let content = parser.fetch_playlist(url).await?;
+    let (content, media_url, inherited) = match parser.parse_content(&content, url)? {
+        ParsedPlaylist::Media { .. } => (content, url.to_string(), None),
+        ParsedPlaylist::Master { variants, .. } => {
+            let variant = variants.first()
+                .ok_or_else(|| AppError::invalid_input("Master playlist has no variants"))?;
+            let inherited = m3u8_parser::parse_start(&content);
+            (parser.fetch_playlist(&variant.uri).await?, variant.uri.clone(), inherited)
+        }
+    };
+    let ParsedPlaylist::Media { target_duration, segments, .. } = parser.parse_content(&content, &media_url)? else {
+        return Err(AppError::invalid_input("Variant playlist is itself a master playlist"));
+    };
+    let live = !content.contains("#EXT-X-ENDLIST");
+    let tag = m3u8_parser::parse_start(&content).or(inherited);
+    let start = m3u8_parser::playback_start(&segments, target_duration, tag.as_ref(), live)
+        .ok_or_else(|| AppError::invalid_input("Playlist has no segments"))?;
+    Ok(frame_position(&start, live))
+}
+
+fn frame_position(start: &PlaybackStart, live: bool) -> FramePosition {
+    if live {
+        FramePosition::LiveSegment { index: start.segment, offset: start.segment_offset }
+    } else {
+        FramePosition::Time(start.position)
+    }
+}
+
+// Grab a single frame at `position`. -ss before -i seeks to the nearest
+// keyframe first and then decodes forward, which is fast on remote streams
+// and still frame-accurate.
+pub async fn grab_frame(
+    ffmpeg: &FFmpegService,
+    input: &str,
+    position: FramePosition,
+    max_width: Option<u32>,
+    format: FrameFormat,
+) -> Result<Vec<u8>, AppError> {
+    check_input(input)?;
+    if let FramePosition::Time(timestamp) = position {
+        if timestamp < 0.0 {
+            return Err(AppError::invalid_input("timestamp must not be negative"));
+        }
+    }
+
+    let mut args = vec!["-loglevel".to_string(), "error".to_string()];
+    args.extend(position.input_args());
+    args.extend([
+        "-i".to_string(),
+        input.to_string(),
+        "-an".to_string(),
+        "-frames:v".to_string(),
+        "1".to_string(),
+    ]);
+    if let Some(width) = max_width {
+        args.push("-vf".to_string());
+        args.push(format!("scale='min({},iw)':-2", width));
+    }
+    let codec = match format {
+        FrameFormat::Jpeg => "mjpeg",
+        FrameFormat::Png => "png",
+    };
+    args.extend(["-q:v", "3", "-c:v", codec, "-f", "image2pipe", "-"].iter().map(|s| s.to_string()));
+
+    let image = ffmpeg.run_ffmpeg_stdout(&args).await?;
+    if image.is_empty() {
+        return Err(AppError::not_found(format!(
+            "No frame at {}; the position may be past the end of the stream",
+            position
+        )));
+    }
+    Ok(image)
+}
+
+// The frame at `position` scaled to `width` x `height` 8-bit grey pixels,
+// row by row; None when there is no frame there. Averaging the area keeps
+// the result the same across resolutions and bitrates of one picture.
+pub async fn grab_gray_pixels(
+    ffmpeg: &FFmpegService,
+    input: &str,
+    position: FramePosition,
+    width: usize,
+    height: usize,
+) -> Result<Option<Vec<u8>>, AppError> {
+    check_input(input)?;
+    let mut args = vec!["-loglevel".to_string(), "error".to_string()];
+    args.extend(position.input_args());
+    args.extend([
+        "-i".to_string(),
+        input.to_string(),
+        "-an".to_string(),
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-vf".to_string(),
+        format!("scale={}:{}:flags=area,format=gray", width, height),
+        "-f".to_string(),
+        "rawvideo".to_string(),
+        "-".to_string(),
+    ]);
+    let pixels = ffmpeg.run_ffmpeg_stdout(&args).await?;
+    Ok((pixels.len() == width * height).then_some(pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_position() {
+        let start = PlaybackStart {
+            position: 19.5,
+            segment: 3,
+            segment_offset: 1.5,
+            live_edge_offset: 10.5,
+            from_start_tag: true,
+        };
+        assert_eq!(frame_position(&start, false).input_args(), vec!["-ss", "19.500"]);
+        assert_eq!(
+            frame_position(&start, true).input_args(),
+            vec!["-live_start_index", "3", "-ss", "1.500"]
+        );
+    }
+
+    #[test]
+    fn test_parse_loudness() {
+        let stderr = "\
+[Parsed_volumedetect_1 @ 0x1] mean_volume: -20.3 dB
+[Parsed_volumedetect_1 @ 0x1] max_volume: -1.0 dB
+[Parsed_ebur128_0 @ 0x2] Summary:
+
+  Integrated loudness:
+    I:         -23.0 LUFS
+    Threshold: -33.5 LUFS
+
+  Loudness range:
+    LRA:         5.2 LU
+    Threshold:  -43.7 LUFS
+    LRA low:    -26.8 LUFS
+    LRA high:   -21.6 LUFS
+
+  True peak:
+    Peak:       -1.2 dBFS
+";
+        let report = parse_loudness(stderr);
+        assert_eq!(report.integrated, Some(-23.0));
+        assert_eq!(report.integrated_threshold, Some(-33.5));
+        assert_eq!(report.lra, Some(5.2));
+        assert_eq!(report.lra_low, Some(-26.8));
+        assert_eq!(report.lra_high, Some(-21.6));
+        assert_eq!(report.true_peak, Some(-1.2));
+        assert_eq!(report.mean_volume, Some(-20.3));
+        assert_eq!(report.max_volume, Some(-1.0));
+    }
+
+    #[test]
+    fn test_parse_scene_changes() {
+        let stderr = "\
+[Parsed_metadata_1 @ 0x1] frame:0    pts:250     pts_time:10.01
+[Parsed_metadata_1 @ 0x1] lavfi.scene_score=0.532
+[Parsed_metadata_1 @ 0x1] frame:1    pts:900     pts_time:36
+[Parsed_metadata_1 @ 0x1] lavfi.scene_score=0.81
+";
+        let changes = parse_scene_changes(stderr);
+        assert_eq!(changes, vec![(10.01, Some(0.532)), (36.0, Some(0.81))]);
+    }
+}
@@ -0,0 +1,1146 @@
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::{self, DownloadEngine, DownloaderConfig};
+use crate::error::{AppError, ErrorKind};
+use crate::events::{self, JobEvent, JobUpdate};
+use crate::ffmpeg_wrapper::{self, FFmpegService, JobKind};
+use crate::host_tuning::{self, Outcome};
+use crate::i18n::{self, Msg};
+use crate::journal::JobSpec;
+use crate::m3u8_parser::{self, HttpFetcher, M3u8Error, M3u8Parser, ParsedPlaylist, Segment, SegmentKey};
+use crate::paths;
+
+type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+
+const SEGMENT_TIMEOUT: Duration = Duration::from_secs(60);
+const MISSING_ATTEMPTS: u32 = 3;
+// Reloads of a live playlist to re-fetch a failed segment, and the wait
+// before each
+const GAP_ATTEMPTS: u32 = 5;
+const GAP_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+// Key and IV used to decrypt a segment. The key itself is not stored, only
+// its hash, so a manifest can be shared without leaking the key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestKey {
+    pub method: String,
+    pub uri: Option<String>,
+    // IV in hex, either from the playlist or derived from the sequence number
+    pub iv: Option<String>,
+    pub key_sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestSegment {
+    pub index: usize,
+    // Media sequence number
+    pub sequence: u64,
+    pub url: String,
+    // Requested range as "length@offset"
+    pub byte_range: Option<String>,
+    pub duration: f32,
+    // Size and SHA-256 of the bytes as fetched, before decryption
+    pub size: u64,
+    pub sha256: String,
+    // Bytes written to the transport stream, after decryption
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub written: Option<u64>,
+    pub key: Option<ManifestKey>,
+    // Mirror the bytes came from, when not the playlist's own host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fetched_from: Option<String>,
+}
+
+// Where the download moved to a lower variant because segments were missing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantSwitch {
+    // Index of the first segment taken from `to`
+    pub segment: usize,
+    pub from: String,
+    pub to: String,
+    pub reason: String,
+}
+
+// Consecutive segments missing from the output: segments of a live recording
+// that could not be fetched before they left the playlist window, and
+// segments the playlist marks with EXT-X-GAP
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingGap {
+    // Media sequence number of the first missing segment
+    pub sequence: u64,
+    pub segments: usize,
+    // Where the gap falls in the recording, in seconds
+    pub position: f64,
+    // Length of the missing media in seconds
+    pub duration: f64,
+    // Wall-clock start from EXT-X-PROGRAM-DATE-TIME, RFC 3339
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program_date_time: Option<String>,
+    pub error: String,
+}
+
+// Sidecar written next to a native download: <output>.manifest.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityManifest {
+    pub version: u32,
+    pub playlist_url: String,
+    pub media_playlist_url: String,
+    pub output: String,
+    pub created_at: String,
+    pub total_bytes: u64,
+    // The media playlist had no EXT-X-ENDLIST: a recording of a live window
+    #[serde(default)]
+    pub live: bool,
+    pub segments: Vec<ManifestSegment>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variant_switches: Vec<VariantSwitch>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gaps: Vec<RecordingGap>,
+}
+
+// Directory aria2c fetches the segments of `output` into
+fn aria2c_dir(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_os_string();
+    name.push(".aria2c");
+    PathBuf::from(name)
+}
+
+pub fn manifest_path(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_os_string();
+    name.push(".manifest.json");
+    PathBuf::from(name)
+}
+
+// Gaps recorded in the manifest of `output`; none without a manifest
+pub fn recording_gaps(output: &Path) -> Vec<RecordingGap> {
+    std::fs::read_to_string(manifest_path(output))
+        .ok()
+        .and_then(|json| serde_json::from_str::<IntegrityManifest>(&json).ok())
+        .map(|manifest| manifest.gaps)
+        .unwrap_or_default()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// IV of a segment: the playlist's IV attribute, or else the media sequence
+// number as a 128-bit big-endian integer (RFC 8216, section 5.2)
+pub fn segment_iv(key: &SegmentKey, sequence: u64) -> Result<[u8; 16], AppError> {
+    let Some(iv) = key.iv.as_deref() else {
+        return Ok((sequence as u128).to_be_bytes());
+    };
+    let digits = iv.trim_start_matches("0x").trim_start_matches("0X");
+    let value = u128::from_str_radix(digits, 16)
+        .ok()
+        .filter(|_| digits.len() == 32)
+        .ok_or_else(|| AppError::new(ErrorKind::Parse, format!("Invalid IV: {}", iv)))?;
+    Ok(value.to_be_bytes())
+}
+
+// Why a segment's decrypted bytes are not media
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DecryptFault {
+    WrongKey,
+    WrongIv,
+}
+
+const TS_PACKET: usize = 188;
+
+// MPEG-TS: a sync byte at the start of every 188-byte packet
+fn is_ts(data: &[u8]) -> bool {
+    !data.is_empty() && data.len().is_multiple_of(TS_PACKET) && data.iter().step_by(TS_PACKET).all(|b| *b == 0x47)
+}
+
+// fMP4: a chain of boxes with printable types that ends exactly at the end
+// of the data
+fn is_fmp4(data: &[u8]) -> bool {
+    let mut at = 0;
+    while at < data.len() {
+        let Some(header) = data.get(at..at + 8) else {
+            return false;
+        };
+        if !header[4..].iter().all(|b| b.is_ascii_alphanumeric() || *b == b' ') {
+            return false;
+        }
+        let size = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+            0 => (data.len() - at) as u64,
+            1 => match data.get(at + 8..at + 16) {
+                Some(large) => u64::from_be_bytes(large.try_into().unwrap_or_default()),
+                None => return false,
+            },
+            size => size as u64,
+        };
+        if size < 8 || size > (data.len() - at) as u64 {
+            return false;
+        }
+        at += size as usize;
+    }
+    at > 0
+}
+
+// Packed audio (ID3-tagged, ADTS or MPEG audio, AC-3) and WebVTT
+fn is_packed(data: &[u8]) -> bool {
+    data.starts_with(b"ID3")
+        || data.starts_with(b"WEBVTT")
+        || data.starts_with(b"\xEF\xBB\xBFWEBVTT")
+        || data.starts_with(&[0x0B, 0x77])
+        || (data.len() >= 2 && data[0] == 0xFF && data[1] & 0xE0 == 0xE0)
+}
+
+// Check that decrypted segment bytes are media. A wrong IV garbles only the
+// first 16-byte block in CBC mode, so media that is intact after it points
+// at the IV; anything else at the key.
+fn check_decrypted(data: &[u8]) -> Result<(), DecryptFault> {
+    if is_ts(data) || is_fmp4(data) || is_packed(data) {
+        return Ok(());
+    }
+    let ts_after_first_block = data.len() > TS_PACKET && is_ts(&data[TS_PACKET..]);
+    // The first fMP4 box is longer than the garbled block
+    let fmp4_after_first_block = (16..data.len().min(256)).any(|at| is_fmp4(&data[at..]));
+    if ts_after_first_block || fmp4_after_first_block {
+        Err(DecryptFault::WrongIv)
+    } else {
+        Err(DecryptFault::WrongKey)
+    }
+}
+
+fn decrypt_error(segment: &Segment, sequence: u64, key: &SegmentKey, fault: DecryptFault) -> AppError {
+    let message = match fault {
+        DecryptFault::WrongKey => format!(
+            "Segment {} did not decrypt to media; the key is probably wrong, or the segment is not encrypted",
+            segment.uri
+        ),
+        DecryptFault::WrongIv if key.iv.is_none() => format!(
+            "Segment {} decrypted with a corrupt start; the IV is probably wrong (the playlist gives none, so the media sequence number {} was used)",
+            segment.uri, sequence
+        ),
+        DecryptFault::WrongIv => format!(
+            "Segment {} decrypted with a corrupt start; the IV is probably wrong",
+            segment.uri
+        ),
+    };
+    let cause = match fault {
+        DecryptFault::WrongKey => "wrong_key",
+        DecryptFault::WrongIv => "wrong_iv",
+    };
+    AppError::new(ErrorKind::Parse, message).with_details(serde_json::json!({
+        "segment": segment.uri,
+        "sequence": sequence,
+        "key_uri": key.uri,
+        "likely_cause": cause,
+    }))
+}
+
+// Absolute (offset, length) of each segment's byte range. A range without an
+// offset continues where the previous range of the same resource ended.
+fn byte_ranges(segments: &[Segment]) -> Vec<Option<(u64, u64)>> {
+    let mut next_offset: HashMap<&str, u64> = HashMap::new();
+    segments
+        .iter()
+        .map(|segment| {
+            let range = segment.byte_range.as_deref()?;
+            let length = m3u8_parser::byte_range_length(range).filter(|l| *l > 0)?;
+            let offset = match range.split_once('@') {
+                Some((_, offset)) => offset.trim().parse().ok()?,
+                None => next_offset.get(segment.uri.as_str()).copied().unwrap_or(0),
+            };
+            next_offset.insert(segment.uri.as_str(), offset + length);
+            Some((offset, length))
+        })
+        .collect()
+}
+
+async fn fetch_bytes(fetcher: &dyn HttpFetcher, url: &str, range: Option<(u64, u64)>) -> Result<Vec<u8>, AppError> {
+    fetcher.get(url, range).await.map_err(|e| match e {
+        M3u8Error::HttpStatus(status) => AppError::new(ErrorKind::Network, format!("Failed to fetch {}: HTTP {}", url, status))
+            .with_details(serde_json::json!({ "status": status }))
+            .with_retryable(status >= 500),
+        e => AppError::new(ErrorKind::Network, format!("Failed to fetch {}: {}", url, e)).with_retryable(true),
+    })
+}
+
+// The server answered 404 or 410
+fn is_missing(error: &AppError) -> bool {
+    error.details.as_ref()
+        .and_then(|details| details.get("status"))
+        .and_then(|status| status.as_u64())
+        .is_some_and(|status| status == 404 || status == 410)
+}
+
+// The server refused the segment, as it does once the signed URLs of a
+// playlist have expired
+fn is_expired(error: &AppError) -> bool {
+    error.details.as_ref()
+        .and_then(|details| details.get("status"))
+        .and_then(|status| status.as_u64())
+        .is_some_and(|status| status == 401 || status == 403)
+}
+
+// The URI in `uris` naming the same file as `uri`; the queries, which carry
+// the tokens, may differ
+fn same_file(uri: &str, uris: &[String]) -> Option<String> {
+    let path = |uri: &str| uri.split(['?', '#']).next().unwrap_or(uri).to_string();
+    let wanted = path(uri);
+    uris.iter().find(|candidate| path(candidate) == wanted).cloned()
+}
+
+// Directory of a URL, with the trailing slash
+fn base_of(url: &str) -> String {
+    let without_query = url.split(['?', '#']).next().unwrap_or(url);
+    match without_query.rfind('/') {
+        Some(pos) => without_query[..=pos].to_string(),
+        None => without_query.to_string(),
+    }
+}
+
+// URLs to try for a segment: its own URL plus the same path under each mirror
+// of `base`. The list is rotated by the segment index so that fetches are
+// spread across all sources; the others are fallbacks.
+pub fn segment_sources(segment_url: &str, index: usize, base: &str, mirrors: &[String]) -> Vec<String> {
+    let Some(rest) = segment_url.strip_prefix(base).filter(|_| !mirrors.is_empty()) else {
+        return vec![segment_url.to_string()];
+    };
+    let mut sources = vec![segment_url.to_string()];
+    sources.extend(mirrors.iter().map(|mirror| format!("{}/{}", mirror.trim_end_matches('/'), rest)));
+    let len = sources.len();
+    sources.rotate_left(index % len);
+    sources
+}
+
+// How a failed fetch affects the host's connection limit: only errors that
+// point at an overloaded server reduce it
+fn outcome_of(error: &AppError) -> Outcome {
+    let status = error.details.as_ref()
+        .and_then(|details| details.get("status"))
+        .and_then(|status| status.as_u64());
+    match status {
+        Some(429) => Outcome::Congestion,
+        Some(status) if status < 500 => Outcome::Failure,
+        _ => Outcome::Congestion,
+    }
+}
+
+// Fetch from the first source that answers; returns the bytes and the URL used.
+// Each attempt waits for a connection to its host.
+async fn fetch_any(
+    fetcher: &dyn HttpFetcher,
+    config: &DownloaderConfig,
+    sources: &[String],
+    range: Option<(u64, u64)>,
+) -> Result<(Vec<u8>, String), AppError> {
+    let mut last_error = None;
+    for source in sources {
+        let permit = host_tuning::limiter(&crate::progress::host_of(source), config).await
+            .acquire(config)
+            .await;
+        let fetched = fetch_bytes(fetcher, source, range).await;
+        match &fetched {
+            Ok(bytes) => permit.finish(Outcome::Success(bytes.len() as u64)),
+            Err(e) => permit.finish(outcome_of(e)),
+        }
+        match fetched {
+            Ok(bytes) => return Ok((bytes, source.clone())),
+            Err(e) => {
+                if sources.len() > 1 {
+                    eprintln!("{}; trying the next source", e);
+                }
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| AppError::internal("No source to fetch from")))
+}
+
+// A segment missing from every source is retried a few times before it
+// counts as gone, since CDNs can lag behind the playlist
+async fn fetch_segment(
+    fetcher: &dyn HttpFetcher,
+    config: &DownloaderConfig,
+    sources: &[String],
+    range: Option<(u64, u64)>,
+) -> Result<(Vec<u8>, String), AppError> {
+    let mut attempt = 1;
+    loop {
+        match fetch_any(fetcher, config, sources, range).await {
+            Err(e) if is_missing(&e) && attempt < MISSING_ATTEMPTS => {
+                attempt += 1;
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            fetched => return fetched,
+        }
+    }
+}
+
+// Fetch a failed segment of a live playlist again while it is still in the
+// window: reload the playlist, find the segment by its media sequence number
+// and retry it. Fails once the segment has slid out or every attempt failed.
+async fn refetch_live(
+    parser: &M3u8Parser,
+    fetcher: &dyn HttpFetcher,
+    config: &DownloaderConfig,
+    playlist_url: &str,
+    sequence: u64,
+    base: &str,
+    mirrors: &[String],
+) -> Result<(Vec<u8>, String), AppError> {
+    let mut last_error = None;
+    for _ in 0..GAP_ATTEMPTS {
+        tokio::time::sleep(GAP_RETRY_DELAY).await;
+        let (content, segments) = match load_media(parser, playlist_url).await {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                last_error = Some(e);
+                continue;
+            }
+        };
+        let position = sequence.checked_sub(m3u8_parser::media_sequence(&content))
+            .and_then(|position| usize::try_from(position).ok())
+            .filter(|position| *position < segments.len());
+        let Some(position) = position else {
+            return Err(AppError::new(ErrorKind::Network, format!("Segment {} left the live window", sequence)));
+        };
+        let sources = segment_sources(&segments[position].uri, position, base, mirrors);
+        match fetch_segment(fetcher, config, &sources, byte_ranges(&segments)[position]).await {
+            Ok(fetched) => return Ok(fetched),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| AppError::internal("No attempt to re-fetch was made")))
+}
+
+// Media playlist being downloaded. For master playlists, `fallbacks` lists
+// the lower-bandwidth variants, best first, to switch to when segments go
+// missing.
+struct MediaPlaylist {
+    url: String,
+    content: String,
+    segments: Vec<Segment>,
+    fallbacks: Vec<String>,
+}
+
+async fn load_media(parser: &M3u8Parser, url: &str) -> Result<(String, Vec<Segment>), AppError> {
+    let content = parser.fetch_playlist(url).await?;
+    match parser.parse_content(&content, url)? {
+        ParsedPlaylist::Media { segments, .. } => Ok((content, segments)),
+        ParsedPlaylist::Master { .. } => Err(AppError::invalid_input("Variant playlist is not a media playlist")),
+    }
+}
+
+// Media playlist to download: the URL itself, or the highest-bandwidth
+// variant of a master playlist
+async fn media_playlist(parser: &M3u8Parser, url: &str) -> Result<MediaPlaylist, AppError> {
+    let content = parser.fetch_playlist(url).await?;
+    match parser.parse_content(&content, url)? {
+        ParsedPlaylist::Media { segments, .. } => Ok(MediaPlaylist {
+            url: url.to_string(),
+            content,
+            segments,
+            fallbacks: Vec::new(),
+        }),
+        ParsedPlaylist::Master { mut variants, .. } => {
+            variants.sort_by(|a, b| b.bandwidth.cmp(&a.bandwidth));
+            let mut uris = variants.into_iter().map(|v| v.uri);
+            let uri = uris.next()
+                .ok_or_else(|| AppError::invalid_input("Master playlist has no variants"))?;
+            let (content, segments) = load_media(parser, &uri).await?;
+            Ok(MediaPlaylist { url: uri, content, segments, fallbacks: uris.collect() })
+        }
+    }
+}
+
+// Wall-clock start of each segment in milliseconds, from
+// EXT-X-PROGRAM-DATE-TIME carried forward by segment durations
+fn segment_times(content: &str, segments: &[Segment]) -> Vec<Option<i64>> {
+    let dates = m3u8_parser::segment_program_date_times(content);
+    let mut current: Option<i64> = None;
+    segments
+        .iter()
+        .enumerate()
+        .map(|(index, segment)| {
+            let date = dates.get(index).cloned().flatten()
+                .and_then(|date| chrono::DateTime::parse_from_rfc3339(&date).ok());
+            if let Some(date) = date {
+                current = Some(date.timestamp_millis());
+            }
+            let start = current;
+            current = current.map(|time| time + (segment.duration as f64 * 1000.0) as i64);
+            start
+        })
+        .collect()
+}
+
+// Position in another variant of the segment with `sequence` starting at
+// `time`: by program date-time when the variant has one, otherwise by media
+// sequence number, which variants of one stream share
+pub fn aligned_position(
+    times: &[Option<i64>],
+    segments: &[Segment],
+    first_sequence: u64,
+    sequence: u64,
+    time: Option<i64>,
+) -> Option<usize> {
+    if let Some(time) = time.filter(|_| times.iter().any(Option::is_some)) {
+        return times.iter().zip(segments).position(|(start, segment)| {
+            start.is_some_and(|start| start + (segment.duration as f64 * 1000.0) as i64 > time)
+        });
+    }
+    let position = usize::try_from(sequence.checked_sub(first_sequence)?).ok()?;
+    (position < segments.len()).then_some(position)
+}
+
+// Replace `playlist` with the next fallback variant that has the segment at
+// `index`; returns where to resume in it
+async fn fall_back(parser: &M3u8Parser, playlist: &mut MediaPlaylist, index: usize) -> Option<usize> {
+    let sequence = m3u8_parser::media_sequence(&playlist.content) + index as u64;
+    let time = segment_times(&playlist.content, &playlist.segments).get(index).copied().flatten();
+    while !playlist.fallbacks.is_empty() {
+        let url = playlist.fallbacks.remove(0);
+        let (content, segments) = match load_media(parser, &url).await {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                eprintln!("Skipping variant {}: {}", url, e);
+                continue;
+            }
+        };
+        let times = segment_times(&content, &segments);
+        let first_sequence = m3u8_parser::media_sequence(&content);
+        if let Some(position) = aligned_position(&times, &segments, first_sequence, sequence, time) {
+            playlist.url = url;
+            playlist.content = content;
+            playlist.segments = segments;
+            return Some(position);
+        }
+        eprintln!("Variant {} has no segment at sequence {}", url, sequence);
+    }
+    None
+}
+
+// Load `playlist` again through the playlist the download started from,
+// `url`, so that signed URLs come with fresh tokens: a master playlist is
+// asked for the current URL of the variant, a media playlist is reloaded.
+// Returns where the segment at `index` is in the reloaded playlist.
+async fn refresh(parser: &M3u8Parser, url: &str, playlist: &mut MediaPlaylist, index: usize) -> Result<usize, AppError> {
+    let sequence = m3u8_parser::media_sequence(&playlist.content) + index as u64;
+    let time = segment_times(&playlist.content, &playlist.segments).get(index).copied().flatten();
+    let content = parser.fetch_playlist(url).await?;
+    let media_url = match parser.parse_content(&content, url)? {
+        ParsedPlaylist::Master { variants, .. } => {
+            let uris: Vec<String> = variants.into_iter().map(|v| v.uri).collect();
+            playlist.fallbacks = playlist.fallbacks.iter().filter_map(|fallback| same_file(fallback, &uris)).collect();
+            same_file(&playlist.url, &uris).ok_or_else(|| {
+                AppError::new(ErrorKind::Network, format!("Variant {} is no longer in the master playlist", playlist.url))
+            })?
+        }
+        ParsedPlaylist::Media { .. } => url.to_string(),
+    };
+
+    let (content, segments) = load_media(parser, &media_url).await?;
+    let times = segment_times(&content, &segments);
+    let first_sequence = m3u8_parser::media_sequence(&content);
+    let position = aligned_position(&times, &segments, first_sequence, sequence, time)
+        .ok_or_else(|| AppError::new(ErrorKind::Network, format!("Segment {} is no longer in the playlist", sequence)))?;
+    playlist.url = media_url;
+    playlist.content = content;
+    playlist.segments = segments;
+    Ok(position)
+}
+
+// Fetch every distinct key that has not been fetched yet
+async fn fetch_keys(
+    fetcher: &dyn HttpFetcher,
+    keys: &[Option<SegmentKey>],
+    key_bytes: &mut HashMap<String, Vec<u8>>,
+) -> Result<(), AppError> {
+    for key in keys.iter().flatten() {
+        if key.method != "AES-128" {
+            return Err(AppError::invalid_input(format!("Unsupported encryption method: {}", key.method)));
+        }
+        let uri = key.uri.as_deref()
+            .ok_or_else(|| AppError::new(ErrorKind::Parse, "EXT-X-KEY has no URI"))?;
+        if !key_bytes.contains_key(uri) {
+            let bytes = fetch_bytes(fetcher, uri, None).await?;
+            if bytes.len() != 16 {
+                return Err(AppError::new(ErrorKind::Parse, format!("Key {} is {} bytes, expected 16", uri, bytes.len())));
+            }
+            key_bytes.insert(uri.to_string(), bytes);
+        }
+    }
+    Ok(())
+}
+
+// Report a segment missing from the output, as part of the previous gap when
+// it directly follows it
+fn record_gap(ffmpeg: &FFmpegService, job_id: &str, gaps: &mut Vec<RecordingGap>, gap: RecordingGap, message: String) {
+    eprintln!("{}", message);
+    crate::job_log::note(job_id, &message);
+    let update = JobUpdate::Gap { sequence: gap.sequence, position: gap.position };
+    ffmpeg.emit(events::DOWNLOAD_PROGRESS, JobEvent::new(job_id, message, update));
+    match gaps.last_mut() {
+        Some(last) if last.sequence + last.segments as u64 == gap.sequence => {
+            last.segments += 1;
+            last.duration += gap.duration;
+        }
+        _ => gaps.push(gap),
+    }
+}
+
+// Download a stream segment by segment into `output`, writing an integrity
+// manifest next to it. Non-.ts outputs are remuxed by FFmpeg (stream copy).
+// `mirrors` are alternate base URLs serving the same files as the directory
+// of `url`; segment fetches are distributed across them with failover. When
+// a segment of a master playlist's variant stays missing, the download
+// continues from the next lower variant at the same position.
+pub async fn download(
+    parser: &M3u8Parser,
+    ffmpeg: &FFmpegService,
+    config: &DownloaderConfig,
+    job_id: &str,
+    url: &str,
+    mirrors: &[String],
+    output: &Path,
+) -> Result<PathBuf, AppError> {
+    let mut playlist = media_playlist(parser, url).await?;
+    if playlist.segments.is_empty() {
+        return Err(AppError::invalid_input("Playlist has no segments"));
+    }
+    let media_url = playlist.url.clone();
+    let live = !playlist.content.contains("#EXT-X-ENDLIST");
+    let base = base_of(url);
+
+    let app_config = config::load_config().unwrap_or_default().effective();
+    let fetcher = crate::profiles::fetcher_for(&app_config, SEGMENT_TIMEOUT)?;
+    let mut key_bytes: HashMap<String, Vec<u8>> = HashMap::new();
+    fetch_keys(fetcher.as_ref(), &m3u8_parser::segment_keys(&playlist.content, &playlist.url), &mut key_bytes).await?;
+
+    // Segments are appended to a transport stream; other containers are
+    // produced from it afterwards
+    let is_ts = output.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("ts"));
+    let ts_path = if is_ts { paths::part_path(output) } else { output.with_extension("native.ts") };
+    let mut file = tokio::fs::File::create(&ts_path).await?;
+    crate::database::record_partial_output(job_id, &ts_path).await;
+
+    ffmpeg.register_task(job_id, JobKind::Download, url, output).await;
+    crate::job_log::note(job_id, &format!(
+        "Native download of {} ({} segments, {} source(s)) to {}",
+        media_url, playlist.segments.len(), mirrors.len() + 1, output.display()
+    ));
+    let mut archive = config.archive.enabled.then(|| {
+        crate::segment_archive::Recorder::new(crate::segment_archive::dir(&app_config), job_id, url, &media_url)
+    });
+    let mut records = Vec::with_capacity(playlist.segments.len());
+    let mut switches = Vec::new();
+    let mut gaps: Vec<RecordingGap> = Vec::new();
+    let mut total_bytes = 0u64;
+    // First segment of `playlist` still to fetch
+    let mut start = 0;
+    // Segments written when the playlist was last refreshed for expired
+    // URLs; refreshing again before any progress would not help
+    let mut refreshed_at = None;
+
+    // With adaptive concurrency the per-host limits decide how many fetches
+    // actually run; the stream only caps them
+    let parallel = if config.adaptive_concurrency {
+        config.max_concurrency.max(config.concurrency).max(1)
+    } else {
+        config.concurrency.max(1)
+    };
+
+    // aria2c fetches the segments up front when enabled; whatever it could
+    // not get is fetched below as usual. Byte ranges stay with the native
+    // fetcher, since aria2c cannot request them per entry, and so does
+    // offline mode, whose files aria2c cannot reach.
+    let aria2c = match crate::aria2c::Aria2c::from_config(config, &app_config, parallel) {
+        Ok(aria2c) => aria2c
+            .filter(|_| byte_ranges(&playlist.segments).iter().all(Option::is_none))
+            .filter(|_| app_config.offline_fixtures.is_none()),
+        Err(e) => {
+            crate::job_log::note(job_id, &format!("Not using aria2c: {}", e));
+            None
+        }
+    };
+    let mut prefetched: Vec<Option<PathBuf>> = Vec::new();
+    let mut result = Ok(());
+    if let Some(aria2c) = aria2c {
+        // Gap segments are not fetched at all
+        let indexes: Vec<usize> = (0..playlist.segments.len()).filter(|index| !playlist.segments[*index].gap).collect();
+        let entries: Vec<crate::aria2c::Entry> = indexes.iter()
+            .map(|&index| crate::aria2c::Entry {
+                sources: segment_sources(&playlist.segments[index].uri, index, &base, mirrors),
+                out: format!("{:06}.seg", index),
+            })
+            .collect();
+        match aria2c.fetch(ffmpeg, job_id, &entries, &aria2c_dir(output)).await {
+            Ok(files) => {
+                prefetched = vec![None; playlist.segments.len()];
+                for (index, file) in indexes.into_iter().zip(files) {
+                    prefetched[index] = file;
+                }
+            }
+            Err(e) => {
+                if ffmpeg.is_job_active(job_id).await && !crate::cancellation::is_cancelled() {
+                    crate::job_log::note(job_id, &format!("aria2c failed ({}); fetching segments natively", e));
+                } else {
+                    result = Err(e);
+                }
+            }
+        }
+    }
+
+    'playlists: while result.is_ok() {
+        let keys = m3u8_parser::segment_keys(&playlist.content, &playlist.url);
+        let first_sequence = m3u8_parser::media_sequence(&playlist.content);
+        let ranges = byte_ranges(&playlist.segments);
+        let times = segment_times(&playlist.content, &playlist.segments);
+        if let Err(e) = fetch_keys(fetcher.as_ref(), &keys, &mut key_bytes).await {
+            result = Err(e);
+            break;
+        }
+        let total = records.len() + playlist.segments.len() - start;
+
+        let mut fetches = futures::stream::iter(playlist.segments.iter().zip(ranges.clone()).enumerate().skip(start))
+            .map(|(index, (segment, range))| {
+                let fetcher = fetcher.as_ref();
+                let sources = segment_sources(&segment.uri, index, &base, mirrors);
+                let prefetched = prefetched.get(index).cloned().flatten();
+                async move {
+                    if segment.gap {
+                        return (index, Err(AppError::invalid_input("Gap segments are not fetched")));
+                    }
+                    // aria2c does not say which source it used
+                    if let Some(path) = prefetched {
+                        if let Ok(bytes) = tokio::fs::read(path).await {
+                            return (index, Ok((bytes, segment.uri.clone())));
+                        }
+                    }
+                    (index, fetch_segment(fetcher, config, &sources, range).await)
+                }
+            })
+            .buffered(parallel);
+
+        let mut missing = None;
+        let mut expired = None;
+        while let Some((index, fetched)) = fetches.next().await {
+            if !ffmpeg.is_job_active(job_id).await || crate::cancellation::is_cancelled() {
+                result = Err(AppError::new(ErrorKind::Ffmpeg, i18n::t(Msg::DownloadCancelled)));
+                break 'playlists;
+            }
+            let segment = &playlist.segments[index];
+            let sequence = first_sequence + index as u64;
+            let program_date_time = || {
+                times[index]
+                    .and_then(chrono::DateTime::from_timestamp_millis)
+                    .map(|time| time.to_rfc3339())
+            };
+            if segment.gap {
+                let position: f64 = records.iter().map(|r| r.duration as f64).sum();
+                let gap = RecordingGap {
+                    sequence,
+                    segments: 1,
+                    position,
+                    duration: segment.duration as f64,
+                    program_date_time: program_date_time(),
+                    error: "Marked with EXT-X-GAP in the playlist".to_string(),
+                };
+                let message = format!("Gap at {:.1}s: segment {} is marked as a gap", position, sequence);
+                record_gap(ffmpeg, job_id, &mut gaps, gap, message);
+                continue;
+            }
+            let (data, source) = match fetched {
+                Ok(fetched) => fetched,
+                Err(e) if is_missing(&e) && !playlist.fallbacks.is_empty() => {
+                    missing = Some((index, e));
+                    break;
+                }
+                Err(e) if is_expired(&e) && refreshed_at != Some(records.len()) => {
+                    expired = Some((index, e));
+                    break;
+                }
+                // A live recording goes on without a segment that cannot be
+                // had while it is in the window
+                Err(e) if live => {
+                    crate::job_log::note(job_id, &format!("Segment {} failed ({}); retrying while it is in the live window", sequence, e));
+                    match refetch_live(parser, fetcher.as_ref(), config, &playlist.url, sequence, &base, mirrors).await {
+                        Ok(fetched) => {
+                            crate::job_log::note(job_id, &format!("Recovered segment {}", sequence));
+                            fetched
+                        }
+                        Err(e) => {
+                            let position: f64 = records.iter().map(|r| r.duration as f64).sum();
+                            let message = format!("Gap at {:.1}s: segment {} could not be fetched: {}", position, sequence, e);
+                            let gap = RecordingGap {
+                                sequence,
+                                segments: 1,
+                                position,
+                                duration: segment.duration as f64,
+                                program_date_time: program_date_time(),
+                                error: e.message,
+                            };
+                            record_gap(ffmpeg, job_id, &mut gaps, gap, message);
+                            continue;
+                        }
+                    }
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break 'playlists;
+                }
+            };
+
+            let sha256 = hex(&Sha256::digest(&data));
+            let size = data.len() as u64;
+
+            let (data, key) = match keys.get(index).cloned().flatten() {
+                Some(key) => {
+                    let uri = key.uri.clone().unwrap_or_default();
+                    let secret = &key_bytes[&uri];
+                    let iv = match segment_iv(&key, sequence) {
+                        Ok(iv) => iv,
+                        Err(e) => {
+                            result = Err(e);
+                            break 'playlists;
+                        }
+                    };
+                    let decrypted = Aes128CbcDec::new_from_slices(secret, &iv)
+                        .ok()
+                        .and_then(|cipher| cipher.decrypt_padded_vec_mut::<Pkcs7>(&data).ok());
+                    // Bad padding almost always means a wrong key
+                    let checked = decrypted
+                        .ok_or(DecryptFault::WrongKey)
+                        .and_then(|decrypted| check_decrypted(&decrypted).map(|_| decrypted));
+                    let decrypted = match checked {
+                        Ok(decrypted) => decrypted,
+                        Err(fault) => {
+                            result = Err(decrypt_error(segment, sequence, &key, fault));
+                            break 'playlists;
+                        }
+                    };
+                    let record = ManifestKey {
+                        method: key.method,
+                        uri: key.uri,
+                        iv: Some(hex(&iv)),
+                        key_sha256: Some(hex(&Sha256::digest(secret))),
+                    };
+                    (decrypted, Some(record))
+                }
+                None => (data, None),
+            };
+
+            if let Err(e) = file.write_all(&data).await {
+                result = Err(e.into());
+                break 'playlists;
+            }
+            if let Some(recorder) = archive.as_mut() {
+                if let Err(e) = recorder.add(sequence, segment.duration, program_date_time(), &data).await {
+                    result = Err(e);
+                    break 'playlists;
+                }
+            }
+            total_bytes += size;
+            records.push(ManifestSegment {
+                index: records.len(),
+                sequence,
+                url: segment.uri.clone(),
+                byte_range: ranges[index].map(|(offset, length)| format!("{}@{}", length, offset)),
+                duration: segment.duration,
+                size,
+                sha256,
+                written: Some(data.len() as u64),
+                key,
+                fetched_from: Some(source).filter(|source| *source != segment.uri),
+            });
+
+            let done = records.len();
+            let message = format!("Segments: {}/{} | Size: {}kB", done, total, total_bytes / 1024);
+            ffmpeg.emit(events::DOWNLOAD_PROGRESS, JobEvent::new(job_id, message, JobUpdate::segments(done, total, total_bytes, None)));
+        }
+        drop(fetches);
+
+        if let Some((index, error)) = expired {
+            refreshed_at = Some(records.len());
+            crate::job_log::note(job_id, &format!("Segment {} was refused ({}); refreshing the playlist URL", index, error));
+            match refresh(parser, url, &mut playlist, index).await {
+                Ok(position) => {
+                    let message = format!("Playlist URL refreshed; continuing with {}", playlist.url);
+                    println!("{}", message);
+                    crate::job_log::note(job_id, &message);
+                    ffmpeg.emit(events::DOWNLOAD_PROGRESS, JobEvent::new(
+                        job_id,
+                        message,
+                        JobUpdate::UrlRefreshed { url: playlist.url.clone() },
+                    ));
+                    start = position;
+                    prefetched.clear();
+                    continue;
+                }
+                Err(e) => {
+                    crate::job_log::note(job_id, &format!("Failed to refresh the playlist URL: {}", e));
+                    result = Err(error);
+                    break;
+                }
+            }
+        }
+
+        let Some((index, error)) = missing else {
+            break;
+        };
+        let from = playlist.url.clone();
+        let Some(position) = fall_back(parser, &mut playlist, index).await else {
+            result = Err(error);
+            break;
+        };
+        let message = format!("Segment {} is missing from {}; continuing with {}", index, from, playlist.url);
+        eprintln!("{}", message);
+        crate::job_log::note(job_id, &message);
+        ffmpeg.emit(events::DOWNLOAD_PROGRESS, JobEvent::new(
+            job_id,
+            message,
+            JobUpdate::VariantSwitched { from: from.clone(), to: playlist.url.clone() },
+        ));
+        switches.push(VariantSwitch {
+            segment: records.len(),
+            from,
+            to: playlist.url.clone(),
+            reason: error.message,
+        });
+        start = position;
+        // Positions in the new variant are not those aria2c fetched
+        prefetched.clear();
+    }
+    ffmpeg.finish_task(job_id).await;
+    host_tuning::save().await;
+    let _ = std::fs::remove_dir_all(aria2c_dir(output));
+    // Archived segments stay usable even when the download failed
+    if let Some(recorder) = archive {
+        match recorder.finish() {
+            Ok(Some(path)) => crate::job_log::note(job_id, &format!("Archived segments: {}", path.display())),
+            Ok(None) => {}
+            Err(e) => crate::job_log::note(job_id, &format!("Failed to write the archived recording: {}", e)),
+        }
+    }
+
+    let result = match result {
+        Ok(()) => file.flush().await.map_err(AppError::from),
+        Err(e) => Err(e),
+    };
+    drop(file);
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&ts_path);
+        return Err(e);
+    }
+
+    if records.is_empty() {
+        let _ = std::fs::remove_file(&ts_path);
+        return Err(AppError::new(ErrorKind::Network, "No segment of the live window could be fetched"));
+    }
+    crate::job_log::note(job_id, &format!("Fetched {} segments, {} bytes", records.len(), total_bytes));
+    if !gaps.is_empty() {
+        let missing: usize = gaps.iter().map(|gap| gap.segments).sum();
+        crate::job_log::note(job_id, &format!("{} segment(s) permanently missing in {} gap(s)", missing, gaps.len()));
+    }
+
+    if is_ts {
+        paths::commit_part(&ts_path, output)?;
+    } else {
+        let mut args: Vec<String> = vec![
+            "-y".to_string(),
+            "-i".to_string(),
+            ts_path.to_string_lossy().to_string(),
+        ];
+        // Everything in the stream, unless only some languages are wanted
+        if config.audio.languages.is_empty() {
+            args.extend(["-map".to_string(), "0".to_string()]);
+        } else {
+            args.extend(["-map".to_string(), "0:v?".to_string()]);
+            args.extend(ffmpeg_wrapper::audio_map_args(&config.audio));
+        }
+        args.extend(["-c".to_string(), "copy".to_string()]);
+        args.extend(ffmpeg_wrapper::output_args(output));
+        let remuxed = ffmpeg.run_ffmpeg(&args).await;
+        let _ = std::fs::remove_file(&ts_path);
+        remuxed?;
+        ffmpeg_wrapper::finish_output(output)?;
+    }
+
+    if config.write_manifest {
+        let manifest = IntegrityManifest {
+            version: 1,
+            playlist_url: url.to_string(),
+            media_playlist_url: media_url,
+            output: output.to_string_lossy().to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            total_bytes,
+            live,
+            segments: records,
+            variant_switches: switches,
+            gaps,
+        };
+        let json = serde_json::to_string_pretty(&manifest)
+            .map_err(|e| AppError::internal(e.to_string()))?;
+        std::fs::write(manifest_path(output), json)?;
+
+        if live && config.export_vod {
+            if let Some(playlist) = crate::vod_export::write_byte_range_playlist(&manifest, output)? {
+                crate::job_log::note(job_id, &format!("VOD playlist: {}", playlist.display()));
+            }
+        }
+    }
+
+    Ok(output.to_path_buf())
+}
+
+// Download with the configured engine, or `engine` when given. Mirrors are
+// only used by the native engine, which they select unless `engine` is set.
+pub async fn run_download(
+    parser: &M3u8Parser,
+    ffmpeg: &FFmpegService,
+    job_id: &str,
+    spec: &JobSpec,
+    output_path: Option<&Path>,
+    title: Option<&str>,
+) -> Result<PathBuf, AppError> {
+    let mut config = config::load_config().map(|c| c.downloader).unwrap_or_default();
+    if let Some(audio) = &spec.audio {
+        config.audio = audio.clone();
+    }
+    let audio = &config.audio;
+    let (url, mirrors) = (spec.url.as_str(), spec.mirrors.as_slice());
+    let default_engine = if mirrors.is_empty() { config.engine } else { DownloadEngine::Native };
+    let output = match spec.engine.unwrap_or(default_engine) {
+        DownloadEngine::Ffmpeg => ffmpeg.download_stream(job_id, url, output_path, title, audio).await?,
+        DownloadEngine::Native => {
+            let output = ffmpeg.resolve_download_output(url, output_path, title)?;
+            download(parser, ffmpeg, &config, job_id, url, mirrors, &output).await?
+        }
+    };
+    if audio.split {
+        // The download itself is done; a failed extraction only loses the extra files
+        match crate::audio_tracks::split(ffmpeg, &output).await {
+            Ok(files) => {
+                for file in &files {
+                    crate::job_log::note(job_id, &format!("Audio track: {}", file.display()));
+                    crate::artifacts::record(job_id, "audio", file).await;
+                }
+            }
+            Err(e) => crate::job_log::note(job_id, &format!("Failed to split audio tracks: {}", e)),
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_iv() {
+        let mut key = SegmentKey {
+            method: "AES-128".to_string(),
+            uri: Some("https://example.com/key".to_string()),
+            iv: None,
+            key_format: None,
+        };
+        let iv = segment_iv(&key, 258).unwrap();
+        assert_eq!(hex(&iv), "00000000000000000000000000000102");
+
+        key.iv = Some("0x0102030405060708090A0B0C0D0E0F10".to_string());
+        assert_eq!(hex(&segment_iv(&key, 0).unwrap()), "0102030405060708090a0b0c0d0e0f10");
+        key.iv = Some("0x01".to_string());
+        assert!(segment_iv(&key, 0).is_err());
+
+        assert_eq!(
+            manifest_path(Path::new("/videos/live.mp4")),
+            PathBuf::from("/videos/live.mp4.manifest.json")
+        );
+    }
+
+    #[test]
+    fn test_check_decrypted() {
+        let mut ts = vec![0u8; TS_PACKET * 3];
+        for packet in ts.chunks_mut(TS_PACKET) {
+            packet[0] = 0x47;
+        }
+        assert_eq!(check_decrypted(&ts), Ok(()));
+
+        let mut fmp4 = Vec::new();
+        for (name, size) in [(b"styp", 24u32), (b"moof", 100), (b"mdat", 400)] {
+            fmp4.extend_from_slice(&size.to_be_bytes());
+            fmp4.extend_from_slice(name);
+            fmp4.resize(fmp4.len() + size as usize - 8, 0);
+        }
+        assert_eq!(check_decrypted(&fmp4), Ok(()));
+        assert_eq!(check_decrypted(b"ID3\x04\x00"), Ok(()));
+
+        // Only the first block garbled: the IV
+        ts[..16].fill(0xA5);
+        fmp4[..16].fill(0xA5);
+        assert_eq!(check_decrypted(&ts), Err(DecryptFault::WrongIv));
+        assert_eq!(check_decrypted(&fmp4), Err(DecryptFault::WrongIv));
+
+        // Garbled throughout: the key
+        let noise: Vec<u8> = (0..TS_PACKET * 3).map(|i| (i * 7 + 13) as u8).collect();
+        assert_eq!(check_decrypted(&noise), Err(DecryptFault::WrongKey));
+    }
+
+    #[test]
+    fn test_segment_sources() {
+        let base = base_of("https://a.example.com/live/index.m3u8?token=1");
+        assert_eq!(base, "https://a.example.com/live/");
+        let mirrors = vec!["https://b.example.com/live/".to_string(), "https://c.example.com/hls".to_string()];
+
+        let sources = segment_sources("https://a.example.com/live/seg1.ts", 1, &base, &mirrors);
+        assert_eq!(sources, vec![
+            "https://b.example.com/live/seg1.ts",
+            "https://c.example.com/hls/seg1.ts",
+            "https://a.example.com/live/seg1.ts",
+        ]);
+        assert_eq!(segment_sources("https://a.example.com/live/seg3.ts", 3, &base, &mirrors)[0], "https://a.example.com/live/seg3.ts");
+
+        // Segments outside the playlist's directory have no mirror
+        assert_eq!(segment_sources("https://other.example.com/seg.ts", 1, &base, &mirrors), vec!["https://other.example.com/seg.ts"]);
+
+        // A refreshed master playlist names the variant with a new token
+        let uris = vec![
+            "https://a.example.com/live/720p.m3u8?token=new".to_string(),
+            "https://a.example.com/live/360p.m3u8?token=new".to_string(),
+        ];
+        assert_eq!(same_file("https://a.example.com/live/360p.m3u8?token=old", &uris).as_deref(), Some(uris[1].as_str()));
+        assert_eq!(same_file("https://a.example.com/live/1080p.m3u8?token=old", &uris), None);
+    }
+
+    #[test]
+    fn test_aligned_position() {
+        let segment = |uri: &str| Segment {
+            uri: uri.to_string(),
+            duration: 6.0,
+            title: None,
+            byte_range: None,
+            discontinuity: false,
+            gap: false,
+            bitrate: None,
+        };
+        let segments = vec![segment("a.ts"), segment("b.ts"), segment("c.ts")];
+
+        // Without program date-times, by media sequence number
+        let content = "#EXTM3U\n#EXT-X-MEDIA-SEQUENCE:100\n#EXTINF:6,\na.ts\n#EXTINF:6,\nb.ts\n#EXTINF:6,\nc.ts\n";
+        let times = segment_times(content, &segments);
+        assert_eq!(aligned_position(&times, &segments, 100, 102, None), Some(2));
+        assert_eq!(aligned_position(&times, &segments, 100, 103, None), None);
+        assert_eq!(aligned_position(&times, &segments, 100, 99, Some(0)), None);
+
+        // With them, by the segment that covers the missing segment's start
+        let content = "#EXTM3U\n#EXT-X-MEDIA-SEQUENCE:5\n#EXT-X-PROGRAM-DATE-TIME:2024-05-01T10:00:00Z\n#EXTINF:6,\na.ts\n#EXTINF:6,\nb.ts\n#EXTINF:6,\nc.ts\n";
+        let times = segment_times(content, &segments);
+        let start = chrono::DateTime::parse_from_rfc3339("2024-05-01T10:00:00Z").unwrap().timestamp_millis();
+        assert_eq!(times[1], Some(start + 6000));
+        assert_eq!(aligned_position(&times, &segments, 5, 0, Some(start + 7000)), Some(1));
+        assert_eq!(aligned_position(&times, &segments, 5, 0, Some(start + 60000)), None);
+    }
+}
@@ -0,0 +1,76 @@
+use chrono::{DateTime, Local};
+use std::path::{Path, PathBuf};
+
+use crate::config::OrganizeRule;
+use crate::error::AppError;
+use crate::paths;
+
+// Host of a URL as a folder name, without "www." and the port
+fn host_folder(url: &str) -> Option<String> {
+    let host = url::Url::parse(url).ok()?.host_str()?.to_lowercase();
+    Some(host.strip_prefix("www.").unwrap_or(&host).to_string())
+}
+
+// Subfolder path the rules give a download, relative to the output directory.
+// Rules that do not apply (no project, unparsable URL) add nothing.
+pub fn subdirectory(rules: &[OrganizeRule], url: &str, project: Option<&str>, now: DateTime<Local>) -> PathBuf {
+    let mut dir = PathBuf::new();
+    for rule in rules {
+        let folder = match rule {
+            OrganizeRule::Date { format } => Some(now.format(format).to_string()),
+            OrganizeRule::Host => host_folder(url),
+            OrganizeRule::Project => project.map(str::to_string),
+        };
+        // "/" in a date format nests folders; each level is made safe on its own
+        for part in folder.iter().flat_map(|f| f.split(['/', '\\'])) {
+            if !part.trim().is_empty() {
+                dir.push(paths::sanitize_filename(part.trim()));
+            }
+        }
+    }
+    dir
+}
+
+// Directory a download without an output path goes to, created if missing;
+// None when no rule applies, leaving the output directory itself
+pub async fn output_dir(
+    rules: &[OrganizeRule],
+    base: &Path,
+    url: &str,
+    project_id: Option<i32>,
+) -> Result<Option<PathBuf>, AppError> {
+    let project = match project_id {
+        Some(id) if rules.contains(&OrganizeRule::Project) => Some(crate::database::find_project(id).await?.name),
+        _ => None,
+    };
+    let subdirectory = subdirectory(rules, url, project.as_deref(), Local::now());
+    if subdirectory.as_os_str().is_empty() {
+        return Ok(None);
+    }
+    let dir = base.join(subdirectory);
+    std::fs::create_dir_all(paths::platform_path(&dir))
+        .map_err(|e| AppError::io(format!("Failed to create {}: {}", dir.display(), e)))?;
+    Ok(Some(dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_subdirectory() {
+        let now = Local.with_ymd_and_hms(2025, 3, 9, 12, 0, 0).unwrap();
+        let rules = vec![
+            OrganizeRule::Date { format: "%Y/%m".to_string() },
+            OrganizeRule::Host,
+            OrganizeRule::Project,
+        ];
+        let url = "https://www.Example.com:8443/live/index.m3u8";
+        assert_eq!(
+            subdirectory(&rules, url, Some("Talks: 2025"), now),
+            PathBuf::from("2025").join("03").join("example.com").join("Talks__2025")
+        );
+        assert_eq!(subdirectory(&rules[1..], "not a url", None, now), PathBuf::new());
+    }
+}
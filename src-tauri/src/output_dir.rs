@@ -0,0 +1,61 @@
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use crate::error::AppError;
+
+// The default output directory as the UI shows it, with the space left on its volume
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputDirInfo {
+    pub path: String,
+    pub exists: bool,
+    // Whether validating it created the directory
+    pub created: bool,
+    pub free_bytes: u64,
+    pub total_bytes: u64,
+}
+
+// Closest directory that exists, so a missing output directory still reports
+// the volume it will be created on
+fn existing_ancestor(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|dir| dir.is_dir())
+}
+
+// Describe `path` without touching it
+pub fn describe(path: &Path) -> Result<OutputDirInfo, AppError> {
+    let volume = existing_ancestor(path)
+        .ok_or_else(|| AppError::not_found(format!("No part of {} exists", path.display())))?;
+    Ok(OutputDirInfo {
+        path: path.display().to_string(),
+        exists: path.is_dir(),
+        created: false,
+        free_bytes: fs2::available_space(volume)?,
+        total_bytes: fs2::total_space(volume)?,
+    })
+}
+
+// Check that `path` can hold downloads: an absolute directory, created if
+// missing, in which a file can be written
+pub fn prepare(path: &Path) -> Result<OutputDirInfo, AppError> {
+    if !path.is_absolute() {
+        return Err(AppError::invalid_input(format!(
+            "Output directory must be an absolute path: {}",
+            path.display()
+        )));
+    }
+    if path.exists() && !path.is_dir() {
+        return Err(AppError::invalid_input(format!("{} is not a directory", path.display())));
+    }
+
+    let created = !path.exists();
+    fs::create_dir_all(path)
+        .map_err(|e| AppError::io(format!("Cannot create {}: {}", path.display(), e)))?;
+
+    // Permissions alone do not tell (read-only mounts, ACLs), so write a file
+    let probe = path.join(format!(".m3u8-mcp-write-test-{}", uuid::Uuid::new_v4()));
+    fs::write(&probe, b"")
+        .map_err(|e| AppError::io(format!("{} is not writable: {}", path.display(), e)))?;
+    let _ = fs::remove_file(&probe);
+
+    Ok(OutputDirInfo { created, ..describe(path)? })
+}
@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+// Only the head of the page matters, so stop reading after this many bytes
+const MAX_PAGE_BYTES: usize = 512 * 1024;
+const MAX_TITLE_CHARS: usize = 120;
+
+// Decode the handful of HTML entities that show up in titles
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(pos) = rest.find('&') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        let end = match rest.find(';') {
+            Some(end) if end <= 10 => end,
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ if entity.starts_with("#x") || entity.starts_with("#X") => {
+                u32::from_str_radix(&entity[2..], 16).ok().and_then(char::from_u32)
+            }
+            _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// Value of `attr` in a tag like `<meta property="og:title" content="...">`
+fn attribute(tag: &str, attr: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search = 0;
+    while let Some(found) = lower[search..].find(attr) {
+        let start = search + found;
+        search = start + attr.len();
+
+        // Must be a whole attribute name followed by '='
+        let before_ok = start == 0 || lower.as_bytes()[start - 1].is_ascii_whitespace();
+        let after = lower[search..].trim_start();
+        if !before_ok || !after.starts_with('=') {
+            continue;
+        }
+
+        let value_start = tag.len() - after.len() + 1;
+        let value = tag[value_start..].trim_start();
+        let quote = value.chars().next()?;
+        let value = if quote == '"' || quote == '\'' {
+            let inner = &value[1..];
+            &inner[..inner.find(quote)?]
+        } else {
+            value.split(|c: char| c.is_whitespace() || c == '>').next()?
+        };
+        return Some(value.to_string());
+    }
+    None
+}
+
+fn clean_title(raw: &str) -> Option<String> {
+    let title: String = decode_entities(raw).split_whitespace().collect::<Vec<_>>().join(" ");
+    if title.is_empty() {
+        return None;
+    }
+    Some(title.chars().take(MAX_TITLE_CHARS).collect())
+}
+
+// Page title from og:title (or twitter:title), falling back to <title>
+pub fn extract_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+
+    let mut search = 0;
+    while let Some(found) = lower[search..].find("<meta") {
+        let start = search + found;
+        let end = match lower[start..].find('>') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let tag = &html[start..end];
+        search = end;
+
+        let key = attribute(tag, "property").or_else(|| attribute(tag, "name"));
+        if let Some(key) = key {
+            if key.eq_ignore_ascii_case("og:title") || key.eq_ignore_ascii_case("twitter:title") {
+                if let Some(title) = attribute(tag, "content").and_then(|c| clean_title(&c)) {
+                    return Some(title);
+                }
+            }
+        }
+    }
+
+    let open = lower.find("<title")?;
+    let content_start = open + lower[open..].find('>')? + 1;
+    let content_end = content_start + lower[content_start..].find("</title")?;
+    clean_title(&html[content_start..content_end])
+}
+
+// Fetch the hosting page of a stream and extract its title. Failures are
+// not errors: the caller falls back to a filename derived from the URL.
+pub async fn fetch_page_title(page_url: &str) -> Option<String> {
+    if !page_url.starts_with("http://") && !page_url.starts_with("https://") {
+        return None;
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .ok()?;
+    let mut response = client.get(page_url).send().await.ok()?;
+    if !response.status().is_success() {
+        eprintln!("Failed to fetch page title from {}: HTTP {}", page_url, response.status());
+        return None;
+    }
+
+    let mut body = Vec::new();
+    while let Ok(Some(chunk)) = response.chunk().await {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_PAGE_BYTES {
+            break;
+        }
+    }
+
+    extract_title(&String::from_utf8_lossy(&body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_title() {
+        let html = r#"<html><head>
+            <title>Fallback &amp; Title</title>
+            <meta property="og:title" content="Live: Concert &#x2014; Night 1">
+        </head></html>"#;
+        assert_eq!(extract_title(html), Some("Live: Concert \u{2014} Night 1".to_string()));
+
+        let html = "<html><head><TITLE>\n  My   Stream &quot;HD&quot; </TITLE></head></html>";
+        assert_eq!(extract_title(html), Some("My Stream \"HD\"".to_string()));
+
+        assert_eq!(extract_title("<html><body>no title</body></html>"), None);
+    }
+}
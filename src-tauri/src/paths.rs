@@ -0,0 +1,283 @@
+use std::path::{Path, PathBuf};
+
+// Paths at or above this length need the \\?\ prefix on Windows (MAX_PATH - 1)
+const WINDOWS_MAX_PATH: usize = 259;
+
+// Keep generated names well below the 255-byte limit of most file systems
+const MAX_FILENAME_BYTES: usize = 200;
+
+// Device names Windows reserves regardless of extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// Make a file name safe on every platform: only alphanumerics, '.', '-' and
+// '_', no reserved device names, no trailing dots and a bounded length.
+pub fn sanitize_filename(name: &str) -> String {
+    let mut safe: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+
+    // Windows strips trailing dots, which would change the name on disk
+    while safe.ends_with('.') {
+        safe.pop();
+    }
+
+    if safe.len() > MAX_FILENAME_BYTES {
+        // Truncate the stem and keep a short extension
+        let ext = safe
+            .rfind('.')
+            .map(|dot| safe[dot..].to_string())
+            .filter(|ext| ext.len() <= 10)
+            .unwrap_or_default();
+        let mut end = MAX_FILENAME_BYTES - ext.len();
+        while !safe.is_char_boundary(end) {
+            end -= 1;
+        }
+        safe = format!("{}{}", &safe[..end], ext);
+    }
+
+    let stem = safe.split('.').next().unwrap_or("");
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        safe.insert(stem.len(), '_');
+    }
+
+    if safe.trim_matches(|c| c == '_' || c == '.').is_empty() {
+        return "stream".to_string();
+    }
+    safe
+}
+
+// UNC path such as \\server\share\dir (network drives)
+pub fn is_unc(path: &str) -> bool {
+    (path.starts_with("\\\\") || path.starts_with("//"))
+        && !path.starts_with("\\\\?\\")
+        && !path.starts_with("\\\\.\\")
+}
+
+// Absolute Windows path with a drive letter, e.g. C:\dir or C:/dir
+fn has_drive(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && (bytes[2] == b'\\' || bytes[2] == b'/')
+}
+
+// Resolve "." and ".." segments; extended-length paths are not normalized by Windows
+fn normalize_segments(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split(['\\', '/']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("\\")
+}
+
+// Extended-length form (\\?\C:\... or \\?\UNC\server\share\...) of a long
+// absolute Windows path. Returns None for short, relative or already
+// prefixed paths.
+pub fn extended_length_path(path: &str) -> Option<String> {
+    if path.len() < WINDOWS_MAX_PATH || path.starts_with("\\\\?\\") {
+        return None;
+    }
+    if has_drive(path) {
+        let drive = &path[..2];
+        return Some(format!("\\\\?\\{}\\{}", drive, normalize_segments(&path[3..])));
+    }
+    if is_unc(path) {
+        return Some(format!("\\\\?\\UNC\\{}", normalize_segments(&path[2..])));
+    }
+    None
+}
+
+// Names of MCP tool arguments that hold local paths
+pub const PATH_ARGUMENTS: [&str; 7] = ["path", "input", "input_path", "output_path", "output_dir", "dir", "export_path"];
+
+fn is_var_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// Expand a leading ~ and $VAR, ${VAR} (and %VAR% on Windows) references.
+// Unset variables and ~user forms are left as written, and so are URLs.
+fn expand_with(input: &str, home: Option<&str>, var: impl Fn(&str) -> Option<String>, windows: bool) -> String {
+    let input = input.trim();
+    if input.contains("://") {
+        return input.to_string();
+    }
+
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    if let (Some(home), Some(after)) = (home, input.strip_prefix('~')) {
+        if after.is_empty() || after.starts_with('/') || after.starts_with('\\') {
+            out.push_str(home.trim_end_matches(['/', '\\']));
+            rest = after;
+        }
+    }
+
+    while let Some(pos) = rest.find(|c| c == '$' || (windows && c == '%')) {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, consumed) = if rest[pos..].starts_with('%') {
+            match after.find('%') {
+                Some(end) if end > 0 && after[..end].chars().all(is_var_char) => (&after[..end], end + 1),
+                _ => ("", 0),
+            }
+        } else if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) if end > 0 && braced[..end].chars().all(is_var_char) => (&braced[..end], end + 2),
+                _ => ("", 0),
+            }
+        } else {
+            let end = after.find(|c: char| !is_var_char(c)).unwrap_or(after.len());
+            (&after[..end], end)
+        };
+        match var(name).filter(|_| !name.is_empty()) {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[pos..pos + 1 + consumed]),
+        }
+        rest = &after[consumed..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// A path as typed by a user or agent, with ~ and environment variables expanded
+pub fn expand_path(input: &str) -> PathBuf {
+    let home = dirs::home_dir().map(|h| h.to_string_lossy().to_string());
+    PathBuf::from(expand_with(input, home.as_deref(), |name| std::env::var(name).ok(), cfg!(windows)))
+}
+
+// Expand the path arguments of an MCP tool call in place
+pub fn expand_path_arguments(arguments: &mut serde_json::Value) {
+    let Some(arguments) = arguments.as_object_mut() else {
+        return;
+    };
+    for name in PATH_ARGUMENTS {
+        if let Some(serde_json::Value::String(value)) = arguments.get_mut(name) {
+            *value = expand_path(value).to_string_lossy().to_string();
+        }
+    }
+}
+
+// Resolve a requested output path: relative paths without a drive or root
+// are placed under the configured output directory.
+pub fn resolve_output_path(path: &Path, output_dir: &Path) -> PathBuf {
+    if path.is_relative() && !path.has_root() && !is_unc(&path.to_string_lossy()) {
+        output_dir.join(path)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+// `path` made absolute against the working directory, unchanged if that
+// cannot be read
+pub fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+// <name>.part next to `path`; outputs are written there and renamed once
+// complete, so a file under its final name is never partial
+pub fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+// Move a completed .part file to its final name, replacing any file there.
+// An empty file means the writer failed without saying so and is removed.
+pub fn commit_part(part: &Path, output: &Path) -> std::io::Result<()> {
+    let part = platform_path(part);
+    if std::fs::metadata(&part)?.len() == 0 {
+        let _ = std::fs::remove_file(&part);
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "output is empty"));
+    }
+    std::fs::rename(part, platform_path(output))
+}
+
+// Path to hand to the file system and FFmpeg; on Windows, long paths get the
+// \\?\ prefix so they work without the LongPathsEnabled policy.
+pub fn platform_path(path: &Path) -> PathBuf {
+    if cfg!(windows) {
+        if let Some(extended) = extended_length_path(&path.to_string_lossy()) {
+            return PathBuf::from(extended);
+        }
+    }
+    path.to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("My Stream: Part 1?.mp4"), "My_Stream__Part_1_.mp4");
+        assert_eq!(sanitize_filename("con.mp4"), "con_.mp4");
+        assert_eq!(sanitize_filename("LPT1"), "LPT1_");
+        assert_eq!(sanitize_filename("video..."), "video");
+        assert_eq!(sanitize_filename("???"), "stream");
+        assert_eq!(sanitize_filename("ライブ配信.mp4"), "ライブ配信.mp4");
+
+        let long = format!("{}.mp4", "あ".repeat(100));
+        let safe = sanitize_filename(&long);
+        assert!(safe.len() <= MAX_FILENAME_BYTES);
+        assert!(safe.ends_with(".mp4"));
+    }
+
+    #[test]
+    fn test_extended_length_path() {
+        let dir = "a".repeat(260);
+        assert_eq!(
+            extended_length_path(&format!("C:\\Videos\\{}\\out.mp4", dir)),
+            Some(format!("\\\\?\\C:\\Videos\\{}\\out.mp4", dir))
+        );
+        assert_eq!(
+            extended_length_path(&format!("C:/Videos/./x/../{}/out.mp4", dir)),
+            Some(format!("\\\\?\\C:\\Videos\\{}\\out.mp4", dir))
+        );
+        assert_eq!(
+            extended_length_path(&format!("\\\\nas\\share\\{}\\out.mp4", dir)),
+            Some(format!("\\\\?\\UNC\\nas\\share\\{}\\out.mp4", dir))
+        );
+        assert_eq!(extended_length_path("C:\\Videos\\out.mp4"), None);
+        assert_eq!(extended_length_path(&format!("relative\\{}", dir)), None);
+    }
+
+    #[test]
+    fn test_expand_with() {
+        let var = |name: &str| match name {
+            "HOME" => Some("/home/user".to_string()),
+            "USERPROFILE" => Some("C:\\Users\\user".to_string()),
+            _ => None,
+        };
+        let expand = |input: &str| expand_with(input, Some("/home/user"), var, false);
+        assert_eq!(expand("~/Videos/out.mp4"), "/home/user/Videos/out.mp4");
+        assert_eq!(expand("~"), "/home/user");
+        assert_eq!(expand("$HOME/out.mp4"), "/home/user/out.mp4");
+        assert_eq!(expand("${HOME}_backup/out.mp4"), "/home/user_backup/out.mp4");
+        assert_eq!(expand("$UNSET/out.mp4"), "$UNSET/out.mp4");
+        assert_eq!(expand("~other/out.mp4"), "~other/out.mp4");
+        assert_eq!(expand("cost$"), "cost$");
+        assert_eq!(expand("https://example.com/$HOME"), "https://example.com/$HOME");
+        assert_eq!(expand("100%USERPROFILE%"), "100%USERPROFILE%");
+        assert_eq!(
+            expand_with("%USERPROFILE%\\Videos", None, var, true),
+            "C:\\Users\\user\\Videos"
+        );
+    }
+
+    #[test]
+    fn test_resolve_output_path() {
+        let base = Path::new("/home/user/Downloads/m3u8-mcp");
+        assert_eq!(resolve_output_path(Path::new("out.mp4"), base), base.join("out.mp4"));
+        assert_eq!(resolve_output_path(Path::new("/tmp/out.mp4"), base), PathBuf::from("/tmp/out.mp4"));
+        assert!(is_unc("\\\\nas\\share\\out.mp4"));
+        assert_eq!(part_path(Path::new("/tmp/out.mp4")), PathBuf::from("/tmp/out.mp4.part"));
+        assert!(!is_unc("\\\\?\\C:\\out.mp4"));
+    }
+}
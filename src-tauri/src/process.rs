@@ -0,0 +1,312 @@
+use std::ffi::OsString;
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::process::{Output, Stdio};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::config::ProcessConfig;
+
+// Job control for the processes the app starts (FFmpeg, ffprobe, aria2c,
+// Whisper). Each child leads a process group of its own on Unix and is put
+// in a job object on Windows, so stopping it reaches every process it
+// started. Stopping asks first (SIGTERM, or CTRL_BREAK on Windows), which
+// lets FFmpeg finish the file it is writing, and kills the whole tree once
+// the grace period has passed. FFmpeg children also get a sanitized
+// environment, so credentials and proxy variables of the app's own
+// environment do not leak into them.
+
+// Environment variables FFmpeg keeps: what it needs to find libraries,
+// certificates, fonts and a temp directory, and to print in the user's locale
+const PASSED_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+    "TZ",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "LD_LIBRARY_PATH",
+    "DYLD_LIBRARY_PATH",
+    "SSL_CERT_FILE",
+    "SSL_CERT_DIR",
+    "FONTCONFIG_FILE",
+    "FONTCONFIG_PATH",
+    "SYSTEMROOT",
+    "WINDIR",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PATHEXT",
+    "COMSPEC",
+];
+
+// CREATE_NEW_PROCESS_GROUP, so CTRL_BREAK can be sent to the child alone
+#[cfg(windows)]
+const NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+
+// The environment an FFmpeg child starts with: the passed variables that are set
+pub fn environment(config: &ProcessConfig) -> Vec<(String, OsString)> {
+    PASSED_ENV
+        .iter()
+        .copied()
+        .chain(config.pass_env.iter().map(String::as_str))
+        .filter_map(|name| std::env::var_os(name).map(|value| (name.to_string(), value)))
+        .collect()
+}
+
+// Give `command` a sanitized environment
+pub fn isolate(command: &mut tokio::process::Command, config: &ProcessConfig) {
+    command.env_clear().envs(environment(config));
+}
+
+// Time a stopped process tree gets to exit before it is killed
+pub fn grace(config: &ProcessConfig) -> Duration {
+    Duration::from_secs(config.kill_grace_secs)
+}
+
+// Scratch directory a job's FFmpeg runs in when job_working_dir is set, for
+// the files FFmpeg leaves in its working directory
+pub fn job_dir(job_id: &str) -> PathBuf {
+    std::env::temp_dir().join("m3u8-mcp-jobs").join(job_id)
+}
+
+#[cfg(windows)]
+mod job_object {
+    use std::os::windows::io::RawHandle;
+
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation, SetInformationJobObject,
+        TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    // A job object holding a child and every process it starts. Closing it
+    // kills them all, so they cannot outlive the app either.
+    pub struct JobObject(HANDLE);
+
+    // The handle is only passed to Win32 calls, which take it from any thread
+    unsafe impl Send for JobObject {}
+    unsafe impl Sync for JobObject {}
+
+    impl JobObject {
+        pub fn assign(process: RawHandle) -> Option<Self> {
+            unsafe {
+                let handle = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+                if handle.is_null() {
+                    return None;
+                }
+                let job = JobObject(handle);
+                let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+                info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+                let set = SetInformationJobObject(
+                    handle,
+                    JobObjectExtendedLimitInformation,
+                    &info as *const _ as *const std::ffi::c_void,
+                    std::mem::size_of_val(&info) as u32,
+                );
+                if set == 0 || AssignProcessToJobObject(handle, process as HANDLE) == 0 {
+                    return None;
+                }
+                Some(job)
+            }
+        }
+
+        pub fn terminate(&self) {
+            unsafe {
+                TerminateJobObject(self.0, 1);
+            }
+        }
+    }
+
+    impl Drop for JobObject {
+        fn drop(&mut self) {
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+}
+
+// A child started by `spawn`, with the group or job object holding the
+// processes it starts. Derefs to the tokio Child for its pipes and status.
+pub struct ControlledChild {
+    child: tokio::process::Child,
+    // Kept because Child::id is None once the child has been reaped, and the
+    // group may still have members then
+    pid: Option<u32>,
+    #[cfg(windows)]
+    job: Option<job_object::JobObject>,
+}
+
+// Spawn `command` under job control. The child is also killed if it is
+// dropped without being stopped, as when the request it runs for goes away.
+pub fn spawn(command: &mut tokio::process::Command) -> std::io::Result<ControlledChild> {
+    #[cfg(unix)]
+    command.process_group(0);
+    #[cfg(windows)]
+    command.creation_flags(NEW_PROCESS_GROUP);
+    command.kill_on_drop(true);
+
+    let child = command.spawn()?;
+    let pid = child.id();
+    // Processes the child starts before it is assigned stay outside the job
+    #[cfg(windows)]
+    let job = child.raw_handle().and_then(job_object::JobObject::assign);
+    Ok(ControlledChild {
+        child,
+        pid,
+        #[cfg(windows)]
+        job,
+    })
+}
+
+impl Deref for ControlledChild {
+    type Target = tokio::process::Child;
+
+    fn deref(&self) -> &Self::Target {
+        &self.child
+    }
+}
+
+impl DerefMut for ControlledChild {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.child
+    }
+}
+
+impl ControlledChild {
+    // Ask the tree to exit; false when the request could not be delivered.
+    // The child leads its group, so the group id is its pid.
+    #[cfg(unix)]
+    fn terminate_tree(&self) -> bool {
+        self.pid.is_some_and(|pid| unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGTERM) == 0 })
+    }
+
+    // Only reaches children that share the app's console
+    #[cfg(windows)]
+    fn terminate_tree(&self) -> bool {
+        use windows_sys::Win32::System::Console::{GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT};
+        self.pid.is_some_and(|pid| unsafe { GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) != 0 })
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn terminate_tree(&self) -> bool {
+        false
+    }
+
+    fn kill_tree(&self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.pid {
+            unsafe {
+                libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+            }
+        }
+        #[cfg(windows)]
+        if let Some(job) = &self.job {
+            job.terminate();
+        }
+    }
+
+    // Stop the child and everything it started: ask first, and kill what is
+    // left after `grace`. Returns once the child has been reaped.
+    pub async fn stop(&mut self, grace: Duration) -> std::io::Result<()> {
+        if self.child.try_wait()?.is_some() {
+            // Processes the child left behind
+            self.kill_tree();
+            return Ok(());
+        }
+        if !grace.is_zero()
+            && self.terminate_tree()
+            && tokio::time::timeout(grace, self.child.wait()).await.is_ok()
+        {
+            self.kill_tree();
+            return Ok(());
+        }
+        self.kill_tree();
+        self.child.kill().await
+    }
+}
+
+async fn read_all<R: AsyncRead + Unpin>(reader: Option<&mut R>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+    match reader {
+        Some(reader) => reader.read_to_end(buf).await.map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+// Like `command.output()` under job control: the tree is stopped when the
+// request it runs for is cancelled, and the result is then an Interrupted error
+pub async fn output_unless_cancelled(command: &mut tokio::process::Command, grace: Duration) -> std::io::Result<Output> {
+    command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = spawn(command)?;
+    let mut stdout = child.stdout.take();
+    let mut stderr = child.stderr.take();
+
+    let output = crate::cancellation::or_cancelled(async {
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let (read_out, read_err, status) = tokio::join!(
+            read_all(stdout.as_mut(), &mut out),
+            read_all(stderr.as_mut(), &mut err),
+            child.wait(),
+        );
+        read_out?;
+        read_err?;
+        Ok(Output { status: status?, stdout: out, stderr: err })
+    })
+    .await;
+
+    match output {
+        Some(output) => output,
+        None => {
+            let _ = child.stop(grace).await;
+            Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "cancelled"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_environment() {
+        std::env::set_var("M3U8_MCP_TEST_SECRET", "hunter2");
+        std::env::set_var("M3U8_MCP_TEST_PASSED", "yes");
+        let config = ProcessConfig {
+            pass_env: vec!["M3U8_MCP_TEST_PASSED".to_string()],
+            ..ProcessConfig::default()
+        };
+        let names: Vec<String> = environment(&config).into_iter().map(|(name, _)| name).collect();
+        assert!(names.contains(&"M3U8_MCP_TEST_PASSED".to_string()));
+        assert!(!names.contains(&"M3U8_MCP_TEST_SECRET".to_string()));
+        assert_eq!(names.contains(&"PATH".to_string()), std::env::var_os("PATH").is_some());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stop_reaches_grandchildren() {
+        let mut command = tokio::process::Command::new("sh");
+        // The shell ignores SIGTERM, so only the kill after the grace period stops it
+        command.args(["-c", "trap '' TERM; sleep 30 & echo $!; wait"]).stdout(Stdio::piped());
+        let mut child = spawn(&mut command).unwrap();
+        let mut stdout = child.stdout.take().unwrap();
+        let mut line = [0u8; 16];
+        let read = stdout.read(&mut line).await.unwrap();
+        let grandchild: i32 = String::from_utf8_lossy(&line[..read]).trim().parse().unwrap();
+
+        child.stop(Duration::from_millis(200)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        // Gone, or a zombie waiting for init to reap it
+        let state = std::process::Command::new("ps")
+            .args(["-o", "stat=", "-p", &grandchild.to_string()])
+            .output()
+            .unwrap();
+        let state = String::from_utf8_lossy(&state.stdout);
+        assert!(state.trim().is_empty() || state.trim().starts_with('Z'), "{}", state);
+    }
+}
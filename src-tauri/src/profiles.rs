@@ -0,0 +1,120 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::{self, AppConfig, Profile};
+use crate::error::AppError;
+use crate::ffmpeg_wrapper::FFmpegConfig;
+use crate::keystore;
+use crate::m3u8_parser::{FixtureFetcher, HttpFetcher, M3u8Parser, ReqwestFetcher};
+
+pub const USER_AGENT: &str = "m3u8-mcp/0.1.0";
+
+// Proxy URL with the credentials from the keychain filled in
+pub fn proxy_url(config: &AppConfig) -> Result<Option<String>, AppError> {
+    let Some(proxy) = &config.proxy else {
+        return Ok(None);
+    };
+    let Some(entry) = &config.proxy_credentials else {
+        return Ok(Some(proxy.clone()));
+    };
+
+    let credentials = keystore::get_secret(entry)?
+        .ok_or_else(|| AppError::not_found(format!("Proxy credentials {} are not in the keychain", entry)))?;
+    let (user, password) = credentials.split_once(':').unwrap_or((credentials.as_str(), ""));
+    let mut url = url::Url::parse(proxy)
+        .map_err(|e| AppError::invalid_input(format!("Invalid proxy URL {}: {}", proxy, e)))?;
+    if url.set_username(user).is_err() || url.set_password(Some(password)).is_err() {
+        return Err(AppError::invalid_input(format!("Proxy URL {} cannot carry credentials", proxy)));
+    }
+    Ok(Some(url.to_string()))
+}
+
+// HTTP client honouring the configured proxy, with the parser's redirect limit
+pub fn http_client(config: &AppConfig, timeout: Duration) -> Result<reqwest::Client, AppError> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(timeout)
+        .user_agent(USER_AGENT)
+        .redirect(reqwest::redirect::Policy::limited(crate::m3u8_parser::MAX_REDIRECTS));
+    if let Some(proxy) = proxy_url(config)? {
+        let proxy = reqwest::Proxy::all(&proxy)
+            .map_err(|e| AppError::invalid_input(format!("Invalid proxy: {}", e)))?;
+        builder = builder.proxy(proxy);
+    }
+    builder.build().map_err(|e| AppError::internal(e.to_string()))
+}
+
+// Where playlists, keys and segments come from: the fixture directory in
+// offline mode, else the network through the configured proxy
+pub fn fetcher_for(config: &AppConfig, timeout: Duration) -> Result<Arc<dyn HttpFetcher>, AppError> {
+    if let Some(dir) = &config.offline_fixtures {
+        return Ok(Arc::new(FixtureFetcher::new(crate::paths::expand_path(dir))));
+    }
+    Ok(Arc::new(ReqwestFetcher::new(http_client(config, timeout)?)))
+}
+
+// Playlist parser for the active profile. Falls back to a direct
+// connection if the proxy cannot be set up.
+pub fn parser_for(config: &AppConfig) -> M3u8Parser {
+    match fetcher_for(config, Duration::from_secs(30)) {
+        Ok(fetcher) => M3u8Parser::with_fetcher(fetcher),
+        Err(e) => {
+            eprintln!("Using a direct connection: {}", e);
+            M3u8Parser::new()
+        }
+    }
+}
+
+// Playlist parser for the current configuration, read on every call so
+// profile switches apply without a restart
+pub fn parser() -> M3u8Parser {
+    parser_for(&config::load_config().unwrap_or_default().effective())
+}
+
+// FFmpeg settings for the active profile
+pub fn ffmpeg_config(config: &AppConfig) -> FFmpegConfig {
+    let config = config.effective();
+    let http_proxy = proxy_url(&config).unwrap_or_else(|e| {
+        eprintln!("FFmpeg will not use the proxy: {}", e);
+        None
+    });
+    FFmpegConfig {
+        http_proxy,
+        ..config.ffmpeg_config()
+    }
+}
+
+// Make `name` the active profile, or go back to the base settings with None
+pub fn switch(name: Option<&str>) -> Result<AppConfig, AppError> {
+    let mut app_config = config::load_config()?;
+    if let Some(name) = name {
+        if !app_config.profiles.contains_key(name) {
+            return Err(AppError::not_found(format!("Profile {} not found", name)));
+        }
+    }
+    app_config.active_profile = name.map(|n| n.to_string());
+    config::save_config(&app_config)?;
+    Ok(app_config)
+}
+
+pub fn save(name: &str, profile: Profile) -> Result<AppConfig, AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::invalid_input("Profile name cannot be empty"));
+    }
+    let mut app_config = config::load_config()?;
+    app_config.profiles.insert(name.trim().to_string(), profile);
+    config::save_config(&app_config)?;
+    Ok(app_config)
+}
+
+// Delete a profile; deleting the active one switches back to the base settings
+pub fn delete(name: &str) -> Result<AppConfig, AppError> {
+    let mut app_config = config::load_config()?;
+    if app_config.profiles.remove(name).is_none() {
+        return Err(AppError::not_found(format!("Profile {} not found", name)));
+    }
+    if app_config.active_profile.as_deref() == Some(name) {
+        app_config.active_profile = None;
+    }
+    config::save_config(&app_config)?;
+    Ok(app_config)
+}
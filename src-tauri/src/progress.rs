@@ -0,0 +1,172 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::database::{self, HostSpeed, ProgressSample};
+use crate::error::AppError;
+use crate::ffmpeg_wrapper::FFmpegService;
+
+// Minimum time between two stored samples of a job
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+// Transfer history of one job, for graphs
+#[derive(Debug, Serialize)]
+pub struct JobProgress {
+    pub job_id: String,
+    pub samples: Vec<ProgressSample>,
+    pub average_bytes_per_second: Option<f64>,
+}
+
+// Bytes in a size as FFmpeg prints it ("2048kB", "1.5MiB", "512B")
+pub fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+    let (number, unit) = size.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let factor = match unit.trim().to_ascii_lowercase().as_str() {
+        "b" => 1.0,
+        "kb" | "kib" => 1024.0,
+        "mb" | "mib" => 1024.0 * 1024.0,
+        "gb" | "gib" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * factor) as u64)
+}
+
+pub fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// Reduce a series to at most `points` samples, averaging the speed over
+// each run of consecutive samples
+pub fn downsample(samples: Vec<ProgressSample>, points: usize) -> Vec<ProgressSample> {
+    if points == 0 || samples.len() <= points {
+        return samples;
+    }
+    let run_len = samples.len().div_ceil(points);
+    samples
+        .chunks(run_len)
+        .map(|run| {
+            let last = &run[run.len() - 1];
+            ProgressSample {
+                bytes: last.bytes,
+                bytes_per_second: run.iter().map(|s| s.bytes_per_second).sum::<f64>() / run.len() as f64,
+                recorded_at: last.recorded_at,
+            }
+        })
+        .collect()
+}
+
+struct JobSampler {
+    host: String,
+    last_at: Instant,
+    last_bytes: u64,
+}
+
+// Store download progress events as job_progress samples; runs for the
+// lifetime of the service
+pub async fn record(ffmpeg: Arc<FFmpegService>) {
+    let mut events = ffmpeg.subscribe();
+    let mut jobs: HashMap<String, JobSampler> = HashMap::new();
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+        if event.event != crate::events::DOWNLOAD_PROGRESS {
+            continue;
+        }
+        let payload = &event.payload;
+        let Some(job_id) = payload["job_id"].as_str() else {
+            continue;
+        };
+        match payload["status"].as_str() {
+            Some("progress") => {}
+            Some("completed") | Some("error") | Some("cancelled") => {
+                jobs.remove(job_id);
+                continue;
+            }
+            _ => continue,
+        }
+        let bytes = payload["bytes"].as_u64()
+            .or_else(|| payload["size"].as_str().and_then(parse_size));
+        let Some(bytes) = bytes else {
+            continue;
+        };
+
+        let now = Instant::now();
+        let Some(sampler) = jobs.get_mut(job_id) else {
+            let host = ffmpeg.list_jobs().await
+                .into_iter()
+                .find(|job| job.id == job_id)
+                .map(|job| host_of(&job.source))
+                .unwrap_or_else(|| "unknown".to_string());
+            jobs.insert(job_id.to_string(), JobSampler { host, last_at: now, last_bytes: bytes });
+            continue;
+        };
+        let elapsed = now.duration_since(sampler.last_at);
+        if elapsed < SAMPLE_INTERVAL {
+            continue;
+        }
+        let speed = bytes.saturating_sub(sampler.last_bytes) as f64 / elapsed.as_secs_f64();
+        sampler.last_at = now;
+        sampler.last_bytes = bytes;
+
+        let db = database::GLOBAL_DB.read().await.clone();
+        if let Some(db) = db {
+            if let Err(e) = db.save_progress_sample(job_id, &sampler.host, bytes, speed) {
+                eprintln!("Failed to record progress of {}: {}", job_id, e);
+            }
+        }
+    }
+}
+
+// Progress of a job, downsampled to at most `points` samples
+pub async fn job_progress(job_id: &str, points: usize) -> Result<JobProgress, AppError> {
+    let samples = database::global_db().await?.get_progress_samples(job_id)?;
+    let average_bytes_per_second = (!samples.is_empty())
+        .then(|| samples.iter().map(|s| s.bytes_per_second).sum::<f64>() / samples.len() as f64);
+    Ok(JobProgress {
+        job_id: job_id.to_string(),
+        samples: downsample(samples, points),
+        average_bytes_per_second,
+    })
+}
+
+pub async fn host_speeds() -> Result<Vec<HostSpeed>, AppError> {
+    Ok(database::global_db().await?.get_host_speeds()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size_and_downsample() {
+        assert_eq!(parse_size("2048kB"), Some(2048 * 1024));
+        assert_eq!(parse_size("1.5MiB"), Some(1572864));
+        assert_eq!(parse_size("512B"), Some(512));
+        assert_eq!(parse_size("N/A"), None);
+        assert_eq!(host_of("https://cdn.example.com/live/index.m3u8"), "cdn.example.com");
+
+        let samples: Vec<ProgressSample> = (0..10)
+            .map(|i| ProgressSample {
+                bytes: i * 100,
+                bytes_per_second: i as f64,
+                recorded_at: chrono::Utc::now(),
+            })
+            .collect();
+        let reduced = downsample(samples.clone(), 3);
+        assert_eq!(reduced.len(), 3);
+        assert_eq!(reduced[0].bytes, 300);
+        assert_eq!(reduced[0].bytes_per_second, 1.5);
+        assert_eq!(reduced[2].bytes, 900);
+        assert_eq!(downsample(samples, 20).len(), 10);
+    }
+}
@@ -0,0 +1,193 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config;
+use crate::error::{AppError, ErrorKind};
+use crate::m3u8_parser::{self, M3u8Parser, ParsedPlaylist, Rendition};
+
+const SUBTITLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// A rendition picked for a download, and why
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenditionChoice {
+    pub group_id: String,
+    pub name: String,
+    pub language: Option<String>,
+    pub uri: Option<String>,
+    // "preferred_language", "default" or "first"
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RenditionChoices {
+    pub audio: Option<RenditionChoice>,
+    pub subtitles: Option<RenditionChoice>,
+}
+
+fn primary_subtag(tag: &str) -> String {
+    tag.split(['-', '_']).next().unwrap_or(tag).to_ascii_lowercase()
+}
+
+// Rendition to play from one group: the first preferred language it has
+// (an exact tag before a tag with the same primary language, so "pt-BR"
+// beats "pt-PT"), else with `fallback` its DEFAULT, first AUTOSELECT or
+// first rendition
+pub fn choose(renditions: &[&Rendition], preferred: &[String], fallback: bool) -> Option<RenditionChoice> {
+    let by_language = preferred.iter().find_map(|wanted| {
+        let has_language = |matches: &dyn Fn(&str) -> bool| {
+            renditions.iter().find(|r| r.language.as_deref().is_some_and(matches))
+        };
+        has_language(&|language| language.eq_ignore_ascii_case(wanted))
+            .or_else(|| has_language(&|language| primary_subtag(language) == primary_subtag(wanted)))
+    });
+    let (rendition, reason) = match by_language {
+        Some(rendition) => (rendition, "preferred_language"),
+        None if !fallback => return None,
+        None => match renditions.iter().find(|r| r.default) {
+            Some(rendition) => (rendition, "default"),
+            None => (renditions.iter().find(|r| r.autoselect).or(renditions.first())?, "first"),
+        },
+    };
+    Some(RenditionChoice {
+        group_id: rendition.group_id.clone(),
+        name: rendition.name.clone(),
+        language: rendition.language.clone(),
+        uri: rendition.uri.clone(),
+        reason: reason.to_string(),
+    })
+}
+
+// Renditions of `media_type` in the group the highest-bandwidth variant (the
+// one downloaded) names with `attribute`; all of that type when it names none
+fn group<'a>(content: &str, url: &str, renditions: &'a [Rendition], media_type: &str, attribute: &str) -> Vec<&'a Rendition> {
+    let group_id = m3u8_parser::variant_attributes(content, url)
+        .into_iter()
+        .max_by_key(|attributes| {
+            attributes.iter()
+                .find(|(name, _)| name == "BANDWIDTH")
+                .and_then(|(_, value)| value.parse::<u64>().ok())
+                .unwrap_or(0)
+        })
+        .and_then(|attributes| attributes.into_iter().find(|(name, _)| name == attribute).map(|(_, value)| value));
+    renditions.iter()
+        .filter(|r| r.media_type == media_type)
+        .filter(|r| group_id.as_ref().is_none_or(|id| r.group_id == *id))
+        .collect()
+}
+
+// Audio and subtitle renditions of the master playlist at `url` for the
+// preferred languages. None without preferences, for media playlists and
+// for master playlists without EXT-X-MEDIA tags.
+pub async fn select(parser: &M3u8Parser, url: &str, preferred: &[String]) -> Result<Option<RenditionChoices>, AppError> {
+    if preferred.is_empty() {
+        return Ok(None);
+    }
+    let content = parser.fetch_playlist(url).await?;
+    if !matches!(parser.parse_content(&content, url)?, ParsedPlaylist::Master { .. }) {
+        return Ok(None);
+    }
+    let renditions = m3u8_parser::parse_renditions(&content, url);
+    if renditions.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(RenditionChoices {
+        audio: choose(&group(&content, url, &renditions, "AUDIO", "AUDIO"), preferred, true),
+        // Subtitles are only wanted in a language the user reads
+        subtitles: choose(&group(&content, url, &renditions, "SUBTITLES", "SUBTITLES"), preferred, false),
+    }))
+}
+
+// WebVTT segments as one file: the header of the first, then the cues of each
+pub fn merge_webvtt(parts: &[String]) -> String {
+    let mut merged = String::new();
+    for (index, part) in parts.iter().enumerate() {
+        let part = part.replace("\r\n", "\n");
+        let body = if index == 0 {
+            part.trim_end()
+        } else {
+            // Cues start after the header block (WEBVTT, X-TIMESTAMP-MAP)
+            match part.split_once("\n\n") {
+                Some((_, cues)) => cues.trim(),
+                None => continue,
+            }
+        };
+        if body.is_empty() {
+            continue;
+        }
+        if !merged.is_empty() {
+            merged.push_str("\n\n");
+        }
+        merged.push_str(body);
+    }
+    merged.push('\n');
+    merged
+}
+
+// Fetch the chosen subtitle rendition into <output name>.<language>.vtt
+pub async fn save_subtitles(parser: &M3u8Parser, choice: &RenditionChoice, output: &Path) -> Result<PathBuf, AppError> {
+    let uri = choice.uri.as_deref()
+        .ok_or_else(|| AppError::invalid_input(format!("Subtitle rendition {} has no URI", choice.name)))?;
+    let content = parser.fetch_playlist(uri).await?;
+    let ParsedPlaylist::Media { segments, .. } = parser.parse_content(&content, uri)? else {
+        return Err(AppError::invalid_input("Subtitle rendition is not a media playlist"));
+    };
+
+    let app_config = config::load_config().unwrap_or_default().effective();
+    let client = crate::profiles::http_client(&app_config, SUBTITLE_TIMEOUT)?;
+    let mut parts = Vec::with_capacity(segments.len());
+    for segment in &segments {
+        let fetch_error = |e: String| AppError::new(ErrorKind::Network, format!("Failed to fetch {}: {}", segment.uri, e));
+        let response = client.get(&segment.uri).send().await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| fetch_error(e.to_string()))?;
+        parts.push(response.text().await.map_err(|e| fetch_error(e.to_string()))?);
+    }
+
+    let stem = output.file_stem().and_then(|s| s.to_str()).unwrap_or("subtitles");
+    let label = choice.language.as_deref().unwrap_or(&choice.name);
+    let path = output.with_file_name(crate::paths::sanitize_filename(&format!("{}.{}.vtt", stem, label)));
+    std::fs::write(&path, merge_webvtt(&parts))
+        .map_err(|e| AppError::io(format!("Failed to write {}: {}", path.display(), e)))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rendition(language: &str, default: bool) -> Rendition {
+        Rendition {
+            media_type: "AUDIO".to_string(),
+            group_id: "aac".to_string(),
+            name: language.to_string(),
+            language: Some(language.to_string()),
+            default,
+            ..Rendition::default()
+        }
+    }
+
+    #[test]
+    fn test_choose() {
+        let renditions = [rendition("en", true), rendition("pt-PT", false), rendition("pt-BR", false), rendition("ja", false)];
+        let refs: Vec<&Rendition> = renditions.iter().collect();
+        let preferred = |langs: &[&str]| langs.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+
+        let choice = choose(&refs, &preferred(&["ja", "en"]), true).unwrap();
+        assert_eq!((choice.name.as_str(), choice.reason.as_str()), ("ja", "preferred_language"));
+        assert_eq!(choose(&refs, &preferred(&["pt-BR"]), true).unwrap().name, "pt-BR");
+        assert_eq!(choose(&refs, &preferred(&["pt"]), true).unwrap().name, "pt-PT");
+        assert_eq!(choose(&refs, &preferred(&["fr"]), true).unwrap().reason, "default");
+        assert_eq!(choose(&refs, &preferred(&["fr"]), false), None);
+
+        let parts = vec![
+            "WEBVTT\r\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\r\n\r\n00:00.000 --> 00:02.000\r\nHello\r\n".to_string(),
+            "WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n\n00:06.000 --> 00:08.000\nWorld\n".to_string(),
+            "WEBVTT\n".to_string(),
+        ];
+        assert_eq!(
+            merge_webvtt(&parts),
+            "WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n\n00:00.000 --> 00:02.000\nHello\n\n00:06.000 --> 00:08.000\nWorld\n"
+        );
+    }
+}
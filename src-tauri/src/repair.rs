@@ -0,0 +1,368 @@
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::ffmpeg_wrapper::FFmpegService;
+use crate::m3u8_parser::{self, M3u8Error, M3u8Parser};
+
+// Segments probed or checked at once
+const PROBE_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RepairOptions {
+    // Replace EXTINF with the duration ffprobe reports for each segment
+    pub probe_durations: bool,
+    // Drop segments that no longer exist (404/410, or missing local files)
+    pub remove_missing: bool,
+    pub add_endlist: bool,
+    // New EXT-X-MEDIA-SEQUENCE; the original is kept when absent
+    pub media_sequence: Option<u64>,
+}
+
+impl Default for RepairOptions {
+    fn default() -> Self {
+        Self {
+            probe_durations: true,
+            remove_missing: true,
+            add_endlist: true,
+            media_sequence: None,
+        }
+    }
+}
+
+// What is known about a segment before rewriting
+#[derive(Debug, Clone, Default)]
+pub struct SegmentCheck {
+    pub duration: Option<f64>,
+    pub missing: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RepairReport {
+    pub output_path: PathBuf,
+    pub segments: usize,
+    pub removed: Vec<String>,
+    // Segments whose EXTINF changed
+    pub retimed: usize,
+    // Segments that could not be probed and kept their EXTINF
+    pub unprobed: usize,
+    pub previous_target_duration: Option<u64>,
+    pub target_duration: u64,
+    pub media_sequence: u64,
+    pub endlist_added: bool,
+}
+
+// A segment's URI line and the tags written before it
+struct Entry {
+    tags: Vec<String>,
+    uri: String,
+}
+
+// Split a media playlist into segments and the tags after the last one
+fn entries(content: &str) -> (Vec<Entry>, Vec<String>) {
+    let mut entries = Vec::new();
+    let mut tags = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('#') {
+            tags.push(line.to_string());
+        } else {
+            entries.push(Entry { tags: std::mem::take(&mut tags), uri: line.to_string() });
+        }
+    }
+    (entries, tags)
+}
+
+fn tag_value<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    tag.strip_prefix(name)?.strip_prefix(':')
+}
+
+fn extinf_duration(tag: &str) -> Option<f64> {
+    tag_value(tag, "#EXTINF")?.split(',').next()?.trim().parse().ok()
+}
+
+// Tags that describe a single segment and go away with it
+fn is_segment_tag(tag: &str) -> bool {
+    ["#EXTINF:", "#EXT-X-BYTERANGE:", "#EXT-X-PROGRAM-DATE-TIME:", "#EXT-X-GAP", "#EXT-X-BITRATE:"]
+        .iter()
+        .any(|prefix| tag.starts_with(prefix))
+}
+
+// Tags rewritten in the header or at the end
+fn is_rewritten_tag(tag: &str) -> bool {
+    tag == "#EXTM3U"
+        || tag == "#EXT-X-ENDLIST"
+        || ["#EXT-X-VERSION:", "#EXT-X-TARGETDURATION:", "#EXT-X-MEDIA-SEQUENCE:"]
+            .iter()
+            .any(|prefix| tag.starts_with(prefix))
+}
+
+// Rewrite a media playlist with the results of `checks`, one per URI line.
+// `relocate` maps URIs when the playlist is written somewhere else than where
+// it was read from. A removed segment in the middle is replaced by an
+// EXT-X-DISCONTINUITY, since the timestamps jump.
+pub fn repair_playlist(
+    content: &str,
+    checks: &[SegmentCheck],
+    options: &RepairOptions,
+    relocate: &dyn Fn(&str) -> String,
+) -> (String, RepairReport) {
+    let (entries, trailer) = entries(content);
+    let header_tag = |name: &str| content.lines().find_map(|line| tag_value(line.trim(), name).map(str::trim));
+    let mut report = RepairReport {
+        previous_target_duration: header_tag("#EXT-X-TARGETDURATION").and_then(|v| v.parse().ok()),
+        media_sequence: options.media_sequence
+            .unwrap_or_else(|| m3u8_parser::media_sequence(content)),
+        ..RepairReport::default()
+    };
+    let version: Option<u8> = header_tag("#EXT-X-VERSION").and_then(|v| v.parse().ok());
+    let has_endlist = content.lines().any(|line| line.trim() == "#EXT-X-ENDLIST");
+
+    let mut body = Vec::new();
+    let mut carried: Vec<String> = Vec::new();
+    let mut gap = false;
+    let mut max_duration: f64 = 0.0;
+    // Where each resource's implicit byte ranges continue from
+    let mut next_offset: HashMap<&str, u64> = HashMap::new();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let check = checks.get(index).cloned().unwrap_or_default();
+        let range = entry.tags.iter().find_map(|tag| tag_value(tag, "#EXT-X-BYTERANGE"));
+        let explicit_range = range.map(|range| {
+            let length = m3u8_parser::byte_range_length(range).unwrap_or(0);
+            let offset = match range.split_once('@') {
+                Some((_, offset)) => offset.trim().parse().unwrap_or(0),
+                None => next_offset.get(entry.uri.as_str()).copied().unwrap_or(0),
+            };
+            next_offset.insert(entry.uri.as_str(), offset + length);
+            format!("#EXT-X-BYTERANGE:{}@{}", length, offset)
+        });
+
+        if check.missing && options.remove_missing {
+            report.removed.push(entry.uri.clone());
+            carried.extend(entry.tags.iter().filter(|tag| !is_segment_tag(tag) && !is_rewritten_tag(tag)).cloned());
+            gap = report.segments > 0;
+            continue;
+        }
+
+        let marked = carried.iter().chain(&entry.tags).any(|tag| tag == "#EXT-X-DISCONTINUITY");
+        body.append(&mut carried);
+        if gap && !marked {
+            body.push("#EXT-X-DISCONTINUITY".to_string());
+        }
+
+        let mut duration = None;
+        for tag in &entry.tags {
+            if is_rewritten_tag(tag) {
+                continue;
+            }
+            if let Some(value) = tag_value(tag, "#EXTINF") {
+                let original = extinf_duration(tag);
+                let title = value.split_once(',').map(|(_, title)| title).unwrap_or("");
+                match check.duration {
+                    Some(probed) => {
+                        if original.map_or(true, |d| (d - probed).abs() >= 0.001) {
+                            report.retimed += 1;
+                        }
+                        body.push(format!("#EXTINF:{:.3},{}", probed, title));
+                        duration = Some(probed);
+                    }
+                    None => {
+                        body.push(tag.clone());
+                        duration = original;
+                    }
+                }
+            } else if tag.starts_with("#EXT-X-BYTERANGE:") && !report.removed.is_empty() {
+                // The implicit offset pointed after the removed segment
+                body.push(explicit_range.clone().unwrap_or_else(|| tag.clone()));
+            } else {
+                body.push(tag.clone());
+            }
+        }
+        if !entry.tags.iter().any(|tag| tag.starts_with("#EXTINF:")) {
+            // A URI without EXTINF is not a segment to players; add it
+            if let Some(probed) = check.duration {
+                let at = body.len() - entry.tags.iter().filter(|tag| !is_rewritten_tag(tag)).count();
+                body.insert(at, format!("#EXTINF:{:.3},", probed));
+                report.retimed += 1;
+                duration = Some(probed);
+            }
+        }
+        if options.probe_durations && check.duration.is_none() {
+            report.unprobed += 1;
+        }
+        max_duration = max_duration.max(duration.unwrap_or(0.0));
+        body.push(relocate(&entry.uri));
+        report.segments += 1;
+        gap = false;
+    }
+    body.extend(carried);
+    body.extend(trailer.into_iter().filter(|tag| !is_rewritten_tag(tag)));
+
+    // Every EXTINF rounded to the nearest integer must fit the target duration
+    report.target_duration = (max_duration.round() as u64).max(1);
+    let mut out = vec!["#EXTM3U".to_string()];
+    // Decimal EXTINF values need version 3
+    let version = match version {
+        Some(v) if v < 3 && report.retimed > 0 => Some(3),
+        v => v,
+    };
+    if let Some(version) = version {
+        out.push(format!("#EXT-X-VERSION:{}", version));
+    }
+    out.push(format!("#EXT-X-TARGETDURATION:{}", report.target_duration));
+    out.push(format!("#EXT-X-MEDIA-SEQUENCE:{}", report.media_sequence));
+    out.extend(body.into_iter().map(|line| {
+        if line.starts_with('#') && line.contains("URI=\"") {
+            relocate_attribute(&line, relocate)
+        } else {
+            line
+        }
+    }));
+    if has_endlist || options.add_endlist {
+        out.push("#EXT-X-ENDLIST".to_string());
+        report.endlist_added = !has_endlist;
+    }
+
+    let mut text = out.join("\n");
+    text.push('\n');
+    (text, report)
+}
+
+// Apply `relocate` to the URI attribute of a tag such as EXT-X-KEY or EXT-X-MAP
+fn relocate_attribute(tag: &str, relocate: &dyn Fn(&str) -> String) -> String {
+    let Some(start) = tag.find("URI=\"").map(|pos| pos + 5) else {
+        return tag.to_string();
+    };
+    let Some(len) = tag[start..].find('"') else {
+        return tag.to_string();
+    };
+    format!("{}{}{}", &tag[..start], relocate(&tag[start..start + len]), &tag[start + len..])
+}
+
+fn is_url(uri: &str) -> bool {
+    uri.contains("://")
+}
+
+// Where a segment of the playlist at `source` lives
+fn resolve(uri: &str, source: &str) -> String {
+    if is_url(uri) || is_url(source) {
+        m3u8_parser::resolve_uri(uri, source)
+    } else {
+        let path = Path::new(uri);
+        if path.is_absolute() {
+            uri.to_string()
+        } else {
+            Path::new(source).parent().unwrap_or(Path::new("")).join(path).to_string_lossy().into_owned()
+        }
+    }
+}
+
+async fn check_segment(
+    parser: &M3u8Parser,
+    ffmpeg: &FFmpegService,
+    location: &str,
+    byte_range: bool,
+    options: &RepairOptions,
+) -> SegmentCheck {
+    let missing = if is_url(location) {
+        matches!(
+            parser.head_content_length(location).await,
+            Err(M3u8Error::HttpStatus(404)) | Err(M3u8Error::HttpStatus(410))
+        )
+    } else {
+        !Path::new(location).exists()
+    };
+    // ffprobe would measure the whole file of a byte-range segment
+    let duration = if options.probe_durations && !missing && !byte_range {
+        ffmpeg.probe_duration(Path::new(location)).await.ok().filter(|d| *d > 0.0)
+    } else {
+        None
+    };
+    SegmentCheck { duration, missing }
+}
+
+// Repair the media playlist at `input` (a URL or a local file) and write it to
+// `output`, by default `<name>.repaired.m3u8` next to a local input
+pub async fn repair(
+    parser: &M3u8Parser,
+    ffmpeg: &FFmpegService,
+    input: &str,
+    output: Option<&Path>,
+    options: &RepairOptions,
+) -> Result<RepairReport, AppError> {
+    let content = if is_url(input) {
+        parser.fetch_playlist(input).await?
+    } else {
+        tokio::fs::read_to_string(input).await?
+    };
+    if content.contains("#EXT-X-STREAM-INF:") {
+        return Err(AppError::invalid_input("Master playlists cannot be repaired; repair each variant playlist instead"));
+    }
+
+    let output = match output {
+        Some(output) => output.to_path_buf(),
+        None if !is_url(input) => Path::new(input).with_extension("repaired.m3u8"),
+        None => return Err(AppError::invalid_input("output_path is required for a playlist URL")),
+    };
+
+    let (entries, _) = entries(&content);
+    let checks: Vec<SegmentCheck> = futures::stream::iter(entries.iter())
+        .map(|entry| {
+            let location = resolve(&entry.uri, input);
+            let byte_range = entry.tags.iter().any(|tag| tag.starts_with("#EXT-X-BYTERANGE:"));
+            async move { check_segment(parser, ffmpeg, &location, byte_range, options).await }
+        })
+        .buffered(PROBE_CONCURRENCY)
+        .collect()
+        .await;
+
+    // Relative URIs stay valid only next to the original
+    let same_place = !is_url(input)
+        && Path::new(input).parent().map(Path::to_path_buf) == output.parent().map(Path::to_path_buf);
+    let relocate = |uri: &str| if same_place { uri.to_string() } else { resolve(uri, input) };
+
+    let (text, mut report) = repair_playlist(&content, &checks, options, &relocate);
+    tokio::fs::write(&output, text).await?;
+    report.output_path = output;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repair_playlist() {
+        let content = "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:10\n\
+            #EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"\n#EXTINF:4.0,first\na.ts\n#EXTINF:4.0,\nb.ts\n#EXTINF:4.0,\nc.ts\n";
+        let checks = vec![
+            SegmentCheck { duration: Some(6.4), missing: false },
+            SegmentCheck { duration: None, missing: true },
+            SegmentCheck { duration: None, missing: false },
+        ];
+        let options = RepairOptions { media_sequence: Some(0), ..RepairOptions::default() };
+        let (text, report) = repair_playlist(content, &checks, &options, &|uri: &str| uri.to_string());
+
+        assert_eq!(
+            text,
+            "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:6\n#EXT-X-MEDIA-SEQUENCE:0\n\
+             #EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"\n#EXTINF:6.400,first\na.ts\n\
+             #EXT-X-DISCONTINUITY\n#EXTINF:4.0,\nc.ts\n#EXT-X-ENDLIST\n"
+        );
+        assert_eq!(report.removed, vec!["b.ts"]);
+        assert_eq!(report.retimed, 1);
+        assert_eq!(report.previous_target_duration, Some(4));
+        assert!(report.endlist_added);
+
+        // Relocated playlists get absolute URIs, including the key's
+        let (text, _) = repair_playlist(content, &[], &RepairOptions::default(), &|uri: &str| format!("https://cdn.example.com/{}", uri));
+        assert!(text.contains("URI=\"https://cdn.example.com/key.bin\""));
+        assert!(text.contains("#EXT-X-MEDIA-SEQUENCE:10\n"));
+    }
+}
@@ -0,0 +1,155 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::database::{Database, DownloadedStream};
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+const CSV_HEADER: &str =
+    "downloaded_at,url,title,output_path,duration_seconds,size_bytes,elapsed_seconds,average_bytes_per_second,verified";
+
+// Parse a report bound given as YYYY-MM-DD or RFC 3339 into the format the
+// database stores ("YYYY-MM-DD HH:MM:SS", UTC). A bare end date covers the
+// whole day.
+pub fn parse_bound(value: &str, end_of_day: bool) -> Result<String, AppError> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let time = if end_of_day { "23:59:59" } else { "00:00:00" };
+        return Ok(format!("{} {}", date.format("%Y-%m-%d"), time));
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc).format("%Y-%m-%d %H:%M:%S").to_string())
+        .map_err(|_| AppError::invalid_input(format!("Invalid date '{}': expected YYYY-MM-DD or RFC 3339", value)))
+}
+
+// Average download speed in bytes per second
+fn average_speed(download: &DownloadedStream) -> Option<f64> {
+    match (download.file_size, download.elapsed_seconds) {
+        (Some(size), Some(elapsed)) if elapsed > 0.0 => Some(size as f64 / elapsed),
+        _ => None,
+    }
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+// Render download records as a JSON document with totals, or as CSV rows
+pub fn render_report(downloads: &[DownloadedStream], format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Json => {
+            let rows: Vec<serde_json::Value> = downloads
+                .iter()
+                .map(|d| {
+                    json!({
+                        "downloaded_at": d.downloaded_at.to_rfc3339(),
+                        "url": d.url,
+                        "title": d.title,
+                        "output_path": d.output_path,
+                        "duration_seconds": d.duration,
+                        "size_bytes": d.file_size,
+                        "elapsed_seconds": d.elapsed_seconds,
+                        "average_bytes_per_second": average_speed(d),
+                        "verified": d.verified
+                    })
+                })
+                .collect();
+            let total_bytes: i64 = downloads.iter().filter_map(|d| d.file_size).sum();
+            let total_duration: f64 = downloads.iter().filter_map(|d| d.duration).sum();
+            let total_elapsed: f64 = downloads.iter().filter_map(|d| d.elapsed_seconds).sum();
+            let report = json!({
+                "generated_at": Utc::now().to_rfc3339(),
+                "downloads": rows,
+                "totals": {
+                    "count": downloads.len(),
+                    "verified": downloads.iter().filter(|d| d.verified == Some(true)).count(),
+                    "size_bytes": total_bytes,
+                    "duration_seconds": total_duration,
+                    "elapsed_seconds": total_elapsed
+                }
+            });
+            serde_json::to_string_pretty(&report).unwrap_or_default()
+        }
+        ReportFormat::Csv => {
+            let mut out = String::from(CSV_HEADER);
+            out.push('\n');
+            for d in downloads {
+                let fields = [
+                    d.downloaded_at.to_rfc3339(),
+                    csv_field(&d.url),
+                    csv_field(d.title.as_deref().unwrap_or("")),
+                    csv_field(&d.output_path),
+                    csv_opt(d.duration),
+                    csv_opt(d.file_size),
+                    csv_opt(d.elapsed_seconds),
+                    csv_opt(average_speed(d).map(|s| s.round() as i64)),
+                    csv_opt(d.verified),
+                ];
+                out.push_str(&fields.join(","));
+                out.push('\n');
+            }
+            out
+        }
+    }
+}
+
+// Report of downloads recorded between `from` and `to` (inclusive)
+pub fn generate_report(
+    db: &Database,
+    from: Option<&str>,
+    to: Option<&str>,
+    format: ReportFormat,
+) -> Result<String, AppError> {
+    let from = from.map(|v| parse_bound(v, false)).transpose()?;
+    let to = to.map(|v| parse_bound(v, true)).transpose()?;
+    let downloads = db.list_downloads_between(from.as_deref(), to.as_deref())?;
+    Ok(render_report(&downloads, format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        assert_eq!(parse_bound("2024-05-01", true).unwrap(), "2024-05-01 23:59:59");
+        assert_eq!(parse_bound("2024-05-01T09:00:00+09:00", false).unwrap(), "2024-05-01 00:00:00");
+        assert!(parse_bound("May 1st", false).is_err());
+
+        let download = DownloadedStream {
+            id: 1,
+            url: "https://example.com/a,b.m3u8".to_string(),
+            output_path: "/tmp/out.mp4".to_string(),
+            file_size: Some(1000),
+            duration: Some(60.0),
+            format: None,
+            resolution: None,
+            bitrate: None,
+            title: Some("Say \"hi\"".to_string()),
+            project_id: None,
+            elapsed_seconds: Some(4.0),
+            verified: Some(true),
+            downloaded_at: DateTime::parse_from_rfc3339("2024-05-01T00:00:00Z").unwrap().with_timezone(&Utc),
+        };
+        let csv = render_report(&[download], ReportFormat::Csv);
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            "2024-05-01T00:00:00+00:00,\"https://example.com/a,b.m3u8\",\"Say \"\"hi\"\"\",/tmp/out.mp4,60,1000,4,250,true"
+        );
+    }
+}
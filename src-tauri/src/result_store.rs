@@ -0,0 +1,117 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::config;
+use crate::error::AppError;
+
+// Full tool results in ~/.m3u8-mcp/results, one file per result named by
+// its id, so clients can read them as m3u8://results/<id> resources long
+// after the call that produced them
+
+pub const URI_PREFIX: &str = "m3u8://results/";
+
+// Extensions of the stored files with the MIME type they are served as
+const KINDS: &[(&str, &str)] = &[("json", "application/json"), ("txt", "text/plain")];
+
+// Result ids are UUIDs without dashes; anything else is refused so it
+// cannot name another file
+fn valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn results_dir() -> Result<PathBuf, AppError> {
+    Ok(config::app_dir()?.join("results"))
+}
+
+// A stored result
+#[derive(Debug, Clone)]
+pub struct StoredResult {
+    pub id: String,
+    pub mime_type: &'static str,
+    pub path: PathBuf,
+    pub modified: SystemTime,
+}
+
+impl StoredResult {
+    pub fn uri(&self) -> String {
+        format!("{}{}", URI_PREFIX, self.id)
+    }
+}
+
+fn stored(path: PathBuf) -> Option<StoredResult> {
+    let extension = path.extension()?.to_str()?;
+    let (_, mime_type) = KINDS.iter().find(|(kind, _)| *kind == extension)?;
+    let id = path.file_stem()?.to_str()?.to_string();
+    let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+    valid_id(&id).then_some(StoredResult { id, mime_type, path, modified })
+}
+
+// Write `text` as a new result; JSON unless `json` is false. Returns its URI.
+pub fn store(text: &str, json: bool) -> Result<String, AppError> {
+    let dir = results_dir()?;
+    fs::create_dir_all(&dir)?;
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let extension = if json { "json" } else { "txt" };
+    fs::write(dir.join(format!("{}.{}", id, extension)), text)?;
+    Ok(format!("{}{}", URI_PREFIX, id))
+}
+
+pub fn find(id: &str) -> Result<StoredResult, AppError> {
+    if !valid_id(id) {
+        return Err(AppError::invalid_input(format!("Invalid result id: {}", id)));
+    }
+    let dir = results_dir()?;
+    KINDS
+        .iter()
+        .find_map(|(extension, _)| stored(dir.join(format!("{}.{}", id, extension))))
+        .ok_or_else(|| AppError::not_found(format!("Result {} not found or expired", id)))
+}
+
+pub fn read(id: &str) -> Result<(String, &'static str), AppError> {
+    let result = find(id)?;
+    Ok((fs::read_to_string(&result.path)?, result.mime_type))
+}
+
+// Stored results, most recent first
+pub fn list() -> Vec<StoredResult> {
+    let Ok(entries) = results_dir().and_then(|dir| Ok(fs::read_dir(dir)?)) else {
+        return Vec::new();
+    };
+    let mut results: Vec<StoredResult> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| stored(entry.path()))
+        .collect();
+    results.sort_by(|a, b| b.modified.cmp(&a.modified));
+    results
+}
+
+// Delete results older than `days`, or all of them for None. Returns how
+// many were deleted.
+pub fn prune(days: Option<u32>) -> usize {
+    let cutoff = days.and_then(|days| SystemTime::now().checked_sub(Duration::from_secs(days as u64 * 86_400)));
+    list()
+        .into_iter()
+        .filter(|result| cutoff.is_none_or(|cutoff| result.modified < cutoff))
+        .filter(|result| match fs::remove_file(&result.path) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Failed to delete result {}: {}", result.id, e);
+                false
+            }
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_id() {
+        assert!(valid_id(&uuid::Uuid::new_v4().simple().to_string()));
+        assert!(!valid_id("../config"));
+        assert!(!valid_id("abc.json"));
+        assert!(!valid_id(""));
+    }
+}
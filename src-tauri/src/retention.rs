@@ -0,0 +1,92 @@
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::config::RetentionConfig;
+use crate::database::Database;
+use crate::error::AppError;
+use crate::history;
+use crate::keystore;
+use crate::result_store;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetentionReport {
+    pub history_entries: usize,
+    pub cache_entries: usize,
+    pub audit_entries: usize,
+    pub snapshot_files: usize,
+    pub result_files: usize,
+    pub trash_entries: usize,
+    pub crash_reports: usize,
+    pub credentials: usize,
+}
+
+pub fn interval(config: &RetentionConfig) -> Option<Duration> {
+    (config.maintenance_interval_hours > 0)
+        .then(|| Duration::from_secs(config.maintenance_interval_hours as u64 * 3600))
+}
+
+// Delete archived snapshot files; a file that is already gone counts as removed
+fn remove_files(files: &[String]) -> usize {
+    files
+        .iter()
+        .filter(|path| match std::fs::remove_file(path) {
+            Ok(()) => true,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+            Err(e) => {
+                eprintln!("Failed to delete snapshot {}: {}", path, e);
+                false
+            }
+        })
+        .count()
+}
+
+// Remove everything older than the configured retention periods
+pub fn apply(db: &Database, config: &RetentionConfig) -> Result<RetentionReport, AppError> {
+    let mut report = RetentionReport::default();
+
+    if let Some(days) = config.history_days {
+        report.history_entries = history::prune(days)?;
+    }
+    if let Some(days) = config.cache_days {
+        report.cache_entries = db.clear_old_cache(days)?;
+    }
+    if let Some(days) = config.audit_days {
+        let (removed, files) = db.clear_old_audit(days)?;
+        report.audit_entries = removed;
+        report.snapshot_files = remove_files(&files);
+    }
+    if let Some(days) = config.results_days {
+        report.result_files = result_store::prune(Some(days));
+    }
+    if let Some(days) = config.trash_days {
+        report.trash_entries = db.purge_trash(Some(days))?;
+    }
+
+    Ok(report)
+}
+
+// Clear the history and pinned URL, caches, link checks, snapshots, stored tool results,
+// crash reports, the trash and stored credentials in one go. Nothing is kept for restoring. Download records and downloaded files are left alone.
+pub fn wipe_personal_data(db: &Database) -> Result<RetentionReport, AppError> {
+    let history_entries = history::load().map(|h| h.len()).unwrap_or(0);
+    history::clear()?;
+    let history_entries = history_entries + usize::from(crate::current_url::unpin()?.is_some());
+
+    let (removed, files) = db.clear_personal_data()?;
+    let snapshot_files = remove_files(&files);
+    let result_files = result_store::prune(None);
+    let trash_entries = db.purge_trash(None)?;
+    let crash_reports = crate::crash::clear();
+    let credentials = keystore::clear_credentials()?;
+
+    Ok(RetentionReport {
+        history_entries,
+        cache_entries: removed,
+        audit_entries: 0,
+        snapshot_files,
+        result_files,
+        trash_entries,
+        crash_reports,
+        credentials,
+    })
+}
@@ -0,0 +1,117 @@
+use serde_json::Value;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use crate::error::AppError;
+use crate::paths;
+
+// Workspace roots of an MCP client. A client that declares the roots
+// capability in initialize answers roots/list, which the server sends over
+// the session's GET /mcp event stream before the session's first tool call,
+// and again after notifications/roots/list_changed. Relative output paths in
+// that session's tool calls then land under the first root instead of the
+// configured output directory, and may not climb out of it.
+
+// Tool arguments naming a file or directory to write
+pub const OUTPUT_ARGUMENTS: [&str; 4] = ["output_path", "output_dir", "output", "export_path"];
+
+// How long a tool call waits for the client to answer roots/list
+pub const LIST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Local directories of a roots/list result, in the order given; None when
+// the result has no roots at all. Roots other than file: URIs are skipped.
+pub fn parse_roots(result: &Value) -> Option<Vec<PathBuf>> {
+    let roots = result.get("roots")?.as_array()?;
+    Some(
+        roots
+            .iter()
+            .filter_map(|root| root.get("uri")?.as_str())
+            .filter_map(|uri| url::Url::parse(uri).ok())
+            .filter(|uri| uri.scheme() == "file")
+            .filter_map(|uri| uri.to_file_path().ok())
+            .collect(),
+    )
+}
+
+// `path` with its . and .. components resolved, without touching the disk
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+// Place relative output paths of a tool call under `root`; absolute paths
+// are kept. A relative path that leads out of the root is refused.
+pub fn resolve_output_arguments(arguments: &mut Value, root: &Path) -> Result<(), AppError> {
+    let Some(arguments) = arguments.as_object_mut() else {
+        return Ok(());
+    };
+    let root = normalize(root);
+    for name in OUTPUT_ARGUMENTS {
+        let Some(Value::String(value)) = arguments.get_mut(name) else {
+            continue;
+        };
+        let requested = PathBuf::from(value.as_str());
+        let resolved = paths::resolve_output_path(&requested, &root);
+        if resolved == requested {
+            continue;
+        }
+        let resolved = normalize(&resolved);
+        if !resolved.starts_with(&root) {
+            return Err(AppError::invalid_input(format!(
+                "{} {} leads out of the workspace root {}",
+                name,
+                value,
+                root.display()
+            )));
+        }
+        *value = resolved.to_string_lossy().to_string();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_roots() {
+        let result = json!({
+            "roots": [
+                { "uri": "https://example.com/repo" },
+                { "uri": "file:///home/me/my%20project", "name": "project" },
+                { "uri": "file:///home/me/other" }
+            ]
+        });
+        let roots = parse_roots(&result).unwrap();
+        assert_eq!(roots, vec![PathBuf::from("/home/me/my project"), PathBuf::from("/home/me/other")]);
+        assert_eq!(parse_roots(&json!({})), None);
+        assert_eq!(parse_roots(&json!({ "roots": [] })), Some(Vec::new()));
+
+        let mut arguments = json!({
+            "url": "https://example.com/a.m3u8",
+            "output_path": "media/./clips/../out.mp4",
+            "output_dir": "/tmp/frames"
+        });
+        resolve_output_arguments(&mut arguments, &roots[0]).unwrap();
+        assert_eq!(arguments["output_path"], "/home/me/my project/media/out.mp4");
+        assert_eq!(arguments["output_dir"], "/tmp/frames");
+        assert_eq!(arguments["url"], "https://example.com/a.m3u8");
+
+        for escape in ["../../x.mp4", "media/../../other/x.mp4", ".."] {
+            let mut arguments = json!({ "output": escape });
+            assert!(resolve_output_arguments(&mut arguments, &roots[0]).is_err(), "{}", escape);
+            assert_eq!(arguments["output"], escape);
+        }
+    }
+}
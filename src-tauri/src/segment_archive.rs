@@ -0,0 +1,568 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::{AppConfig, ArchiveConfig};
+use crate::error::{AppError, ErrorKind};
+use crate::ffmpeg_wrapper::{self, FFmpegService};
+use crate::paths;
+
+// Segments of native downloads kept by content: objects/<ab>/<sha256> holds
+// the decrypted bytes of a segment once, however many recordings contain
+// it, and recordings/<job id>.json maps each recording's media sequence
+// numbers to those hashes. Overlapping recordings of a live stream share
+// their common segments, and any recorded window can be put together again.
+
+const VERSION: u32 = 1;
+
+// A recording is only written when its recorder finishes, so while one is
+// under way nothing on disk says which objects it needs. Recorders register
+// here with the objects they refer to, and delete() keeps those.
+struct LiveRecording {
+    started: SystemTime,
+    objects: HashSet<String>,
+}
+
+lazy_static::lazy_static! {
+    static ref LIVE: Mutex<HashMap<u64, LiveRecording>> = Mutex::new(HashMap::new());
+}
+
+static NEXT_RECORDER: AtomicU64 = AtomicU64::new(0);
+
+// Bumped, under the LIVE lock, whenever a recorder starts or ends
+static LIVE_CHANGES: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedSegment {
+    pub sequence: u64,
+    pub duration: f32,
+    // Wall-clock start from EXT-X-PROGRAM-DATE-TIME, RFC 3339
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program_date_time: Option<String>,
+    // SHA-256 of the stored (decrypted) bytes, naming the object
+    pub sha256: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub version: u32,
+    // Job that recorded it
+    pub id: String,
+    // Canonical key of the media playlist, shared by recordings of the
+    // same stream
+    pub stream: String,
+    pub playlist_url: String,
+    pub media_playlist_url: String,
+    pub created_at: String,
+    pub segments: Vec<ArchivedSegment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingSummary {
+    pub id: String,
+    pub stream: String,
+    pub media_playlist_url: String,
+    pub created_at: String,
+    pub segments: usize,
+    pub first_sequence: Option<u64>,
+    pub last_sequence: Option<u64>,
+    pub duration: f64,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveSummary {
+    pub dir: String,
+    pub recordings: Vec<RecordingSummary>,
+    pub objects: usize,
+    // Bytes on disk
+    pub stored_bytes: u64,
+    // Bytes the recordings would take stored separately
+    pub recorded_bytes: u64,
+}
+
+// Part of a stream to put together: media sequence numbers and/or wall-clock
+// times (RFC 3339), all inclusive; None leaves that end open
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Window {
+    pub from_sequence: Option<u64>,
+    pub to_sequence: Option<u64>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Reconstruction {
+    pub output: PathBuf,
+    pub segments: usize,
+    pub duration: f64,
+    pub first_sequence: u64,
+    pub last_sequence: u64,
+    // Sequence numbers in the window that no recording has, as inclusive ranges
+    pub missing: Vec<(u64, u64)>,
+}
+
+// Archive directory: archive.dir, or `archive` under the output directory
+pub fn dir(config: &AppConfig) -> PathBuf {
+    archive_dir(&config.downloader.archive, &config.output_dir)
+}
+
+fn archive_dir(archive: &ArchiveConfig, output_dir: &str) -> PathBuf {
+    match &archive.dir {
+        Some(dir) => paths::expand_path(dir),
+        None => paths::expand_path(output_dir).join("archive"),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn object_path(dir: &Path, sha256: &str) -> PathBuf {
+    dir.join("objects").join(&sha256[..2]).join(sha256)
+}
+
+fn recordings_dir(dir: &Path) -> PathBuf {
+    dir.join("recordings")
+}
+
+// Store a segment's bytes under their hash unless an identical one is
+// stored already
+pub async fn store(dir: &Path, sha256: &str, data: &[u8]) -> Result<(), AppError> {
+    let path = object_path(dir, sha256);
+    if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    // Written aside first, so a half-written object never has a valid name
+    let part = paths::part_path(&path);
+    let mut file = tokio::fs::File::create(&part).await?;
+    file.write_all(data).await?;
+    file.flush().await?;
+    drop(file);
+    tokio::fs::rename(&part, &path).await?;
+    Ok(())
+}
+
+pub fn write_recording(dir: &Path, recording: &Recording) -> Result<PathBuf, AppError> {
+    let recordings = recordings_dir(dir);
+    fs::create_dir_all(&recordings)?;
+    let path = recordings.join(format!("{}.json", paths::sanitize_filename(&recording.id)));
+    let json = serde_json::to_string_pretty(recording).map_err(|e| AppError::internal(e.to_string()))?;
+    fs::write(&path, json)?;
+    Ok(path)
+}
+
+// Recordings in the archive, oldest first
+pub fn recordings(dir: &Path) -> Vec<Recording> {
+    let Ok(entries) = fs::read_dir(recordings_dir(dir)) else {
+        return Vec::new();
+    };
+    let mut recordings: Vec<Recording> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|extension| extension == "json"))
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect();
+    recordings.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    recordings
+}
+
+fn summarize(recording: &Recording) -> RecordingSummary {
+    RecordingSummary {
+        id: recording.id.clone(),
+        stream: recording.stream.clone(),
+        media_playlist_url: recording.media_playlist_url.clone(),
+        created_at: recording.created_at.clone(),
+        segments: recording.segments.len(),
+        first_sequence: recording.segments.first().map(|s| s.sequence),
+        last_sequence: recording.segments.last().map(|s| s.sequence),
+        duration: recording.segments.iter().map(|s| s.duration as f64).sum(),
+        start_time: recording.segments.iter().find_map(|s| s.program_date_time.clone()),
+        end_time: recording.segments.iter().rev().find_map(|s| s.program_date_time.clone()),
+    }
+}
+
+pub fn summary(dir: &Path) -> ArchiveSummary {
+    let recordings = recordings(dir);
+    let mut objects: HashSet<&str> = HashSet::new();
+    let mut stored_bytes = 0;
+    let mut recorded_bytes = 0;
+    for segment in recordings.iter().flat_map(|recording| &recording.segments) {
+        recorded_bytes += segment.size;
+        if objects.insert(&segment.sha256) {
+            stored_bytes += segment.size;
+        }
+    }
+    ArchiveSummary {
+        dir: dir.to_string_lossy().to_string(),
+        objects: objects.len(),
+        recordings: recordings.iter().map(summarize).collect(),
+        stored_bytes,
+        recorded_bytes,
+    }
+}
+
+fn timestamp(time: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(time).ok().map(|time| time.timestamp_millis())
+}
+
+// Segments of `stream` in `window` from all its recordings, one per
+// sequence number, in order
+pub fn select(recordings: &[Recording], stream: &str, window: &Window) -> Result<Vec<ArchivedSegment>, AppError> {
+    let bound = |time: &Option<String>| match time {
+        Some(time) => timestamp(time)
+            .map(Some)
+            .ok_or_else(|| AppError::invalid_input(format!("Not an RFC 3339 time: {}", time))),
+        None => Ok(None),
+    };
+    let start = bound(&window.start_time)?;
+    let end = bound(&window.end_time)?;
+
+    let mut segments = BTreeMap::new();
+    for segment in recordings.iter().filter(|r| r.stream == stream).flat_map(|r| &r.segments) {
+        if window.from_sequence.is_some_and(|from| segment.sequence < from)
+            || window.to_sequence.is_some_and(|to| segment.sequence > to)
+        {
+            continue;
+        }
+        if start.is_some() || end.is_some() {
+            // Times can only place segments that carry one
+            let Some(time) = segment.program_date_time.as_deref().and_then(timestamp) else {
+                continue;
+            };
+            if start.is_some_and(|start| time + (segment.duration * 1000.0) as i64 <= start)
+                || end.is_some_and(|end| time > end)
+            {
+                continue;
+            }
+        }
+        segments.entry(segment.sequence).or_insert_with(|| segment.clone());
+    }
+    Ok(segments.into_values().collect())
+}
+
+// Sequence numbers between the first and last segment that are not there
+fn missing(segments: &[ArchivedSegment]) -> Vec<(u64, u64)> {
+    segments
+        .windows(2)
+        .filter(|pair| pair[1].sequence > pair[0].sequence + 1)
+        .map(|pair| (pair[0].sequence + 1, pair[1].sequence - 1))
+        .collect()
+}
+
+// Put the segments of `url`'s stream in `window` together into `output`,
+// remuxing with FFmpeg unless it is a .ts file
+pub async fn reconstruct(
+    ffmpeg: &FFmpegService,
+    dir: &Path,
+    url: &str,
+    window: &Window,
+    output: &Path,
+) -> Result<Reconstruction, AppError> {
+    let recordings = recordings(dir);
+    let stream = crate::canonical_url::key(url);
+    if !recordings.iter().any(|recording| recording.stream == stream) {
+        return Err(AppError::not_found(format!("No archived recording of {}", url)));
+    }
+    let segments = select(&recordings, &stream, window)?;
+    let (Some(first), Some(last)) = (segments.first(), segments.last()) else {
+        return Err(AppError::not_found("No archived segment falls in the window"));
+    };
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let is_ts = output.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("ts"));
+    let ts_path = if is_ts { paths::part_path(output) } else { output.with_extension("archive.ts") };
+    let written = async {
+        let mut file = tokio::fs::File::create(&ts_path).await?;
+        for segment in &segments {
+            let data = tokio::fs::read(object_path(dir, &segment.sha256)).await.map_err(|e| {
+                AppError::new(ErrorKind::Io, format!("Archived segment {} is unreadable: {}", segment.sequence, e))
+            })?;
+            file.write_all(&data).await?;
+        }
+        file.flush().await?;
+        Ok::<(), AppError>(())
+    }
+    .await;
+    if let Err(e) = written {
+        let _ = fs::remove_file(&ts_path);
+        return Err(e);
+    }
+
+    if is_ts {
+        paths::commit_part(&ts_path, output)?;
+    } else {
+        let mut args: Vec<String> = vec![
+            "-y".to_string(),
+            "-i".to_string(),
+            ts_path.to_string_lossy().to_string(),
+            "-map".to_string(),
+            "0".to_string(),
+            "-c".to_string(),
+            "copy".to_string(),
+        ];
+        args.extend(ffmpeg_wrapper::output_args(output));
+        let remuxed = ffmpeg.run_ffmpeg(&args).await;
+        let _ = fs::remove_file(&ts_path);
+        remuxed?;
+        ffmpeg_wrapper::finish_output(output)?;
+    }
+
+    Ok(Reconstruction {
+        output: output.to_path_buf(),
+        first_sequence: first.sequence,
+        last_sequence: last.sequence,
+        duration: segments.iter().map(|s| s.duration as f64).sum(),
+        missing: missing(&segments),
+        segments: segments.len(),
+    })
+}
+
+// Objects delete() keeps: those of archived and live recordings, and
+// anything stored since the oldest live recording started. Holds while
+// LIVE_CHANGES is still `changes`.
+struct Keep {
+    changes: u64,
+    used: HashSet<String>,
+    oldest_live: Option<SystemTime>,
+}
+
+impl Keep {
+    fn read(dir: &Path) -> Self {
+        let (changes, oldest_live, mut used) = {
+            let live = LIVE.lock().unwrap_or_else(|e| e.into_inner());
+            (
+                LIVE_CHANGES.load(Ordering::SeqCst),
+                live.values().map(|recording| recording.started).min(),
+                live.values().flat_map(|recording| recording.objects.iter().cloned()).collect::<HashSet<_>>(),
+            )
+        };
+        // Read after LIVE: a recorder writes its recording before it leaves
+        used.extend(
+            recordings(dir)
+                .into_iter()
+                .flat_map(|recording| recording.segments)
+                .map(|segment| segment.sha256),
+        );
+        Keep { changes, used, oldest_live }
+    }
+
+    fn keeps(&self, name: &str, object: &fs::DirEntry) -> bool {
+        if self.used.contains(name) {
+            return true;
+        }
+        let Some(oldest_live) = self.oldest_live else {
+            return false;
+        };
+        let modified = object.metadata().and_then(|metadata| metadata.modified());
+        modified.map_or(true, |modified| modified >= oldest_live)
+    }
+}
+
+// Delete a recording and the objects no other recording uses; returns the
+// number of objects deleted. Objects of recordings under way are kept, and
+// so is anything stored since the oldest of them started. Walks the archive
+// on disk, so async callers run it with spawn_blocking.
+pub fn delete(dir: &Path, id: &str) -> Result<usize, AppError> {
+    let path = recordings_dir(dir).join(format!("{}.json", paths::sanitize_filename(id)));
+    if !path.exists() {
+        return Err(AppError::not_found(format!("No archived recording {}", id)));
+    }
+    fs::remove_file(&path)?;
+
+    let mut keep = Keep::read(dir);
+    let Ok(shards) = fs::read_dir(dir.join("objects")) else {
+        return Ok(0);
+    };
+    let mut removed = 0;
+    for shard in shards.filter_map(|entry| entry.ok()) {
+        let Ok(objects) = fs::read_dir(shard.path()) else {
+            continue;
+        };
+        for object in objects.filter_map(|entry| entry.ok()) {
+            let name = object.file_name().to_string_lossy().to_string();
+            // .part files are objects still being written
+            if name.ends_with(".part") {
+                continue;
+            }
+            while !keep.keeps(&name, &object) {
+                // Checked again and removed under the lock: a recorder
+                // registers an object before it looks for it on disk, so it
+                // never skips storing one removed here
+                let live = LIVE.lock().unwrap_or_else(|e| e.into_inner());
+                if LIVE_CHANGES.load(Ordering::SeqCst) != keep.changes {
+                    drop(live);
+                    keep = Keep::read(dir);
+                    continue;
+                }
+                let registered = live.values().any(|recording| recording.objects.contains(&name));
+                if !registered && fs::remove_file(object.path()).is_ok() {
+                    removed += 1;
+                }
+                break;
+            }
+        }
+    }
+    Ok(removed)
+}
+
+// A recording under way: collects its segments for write_recording
+pub struct Recorder {
+    pub dir: PathBuf,
+    pub recording: Recording,
+    // Key in LIVE
+    live: u64,
+}
+
+impl Recorder {
+    pub fn new(dir: PathBuf, job_id: &str, playlist_url: &str, media_playlist_url: &str) -> Self {
+        let live = NEXT_RECORDER.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut live_recordings = LIVE.lock().unwrap_or_else(|e| e.into_inner());
+            live_recordings.insert(live, LiveRecording {
+                started: SystemTime::now(),
+                objects: HashSet::new(),
+            });
+            LIVE_CHANGES.fetch_add(1, Ordering::SeqCst);
+        }
+        Recorder {
+            dir,
+            live,
+            recording: Recording {
+                version: VERSION,
+                id: job_id.to_string(),
+                stream: crate::canonical_url::key(media_playlist_url),
+                playlist_url: playlist_url.to_string(),
+                media_playlist_url: media_playlist_url.to_string(),
+                created_at: chrono::Utc::now().to_rfc3339(),
+                segments: Vec::new(),
+            },
+        }
+    }
+
+    pub async fn add(&mut self, sequence: u64, duration: f32, program_date_time: Option<String>, data: &[u8]) -> Result<(), AppError> {
+        let sha256 = hex(&Sha256::digest(data));
+        if let Some(live) = LIVE.lock().unwrap_or_else(|e| e.into_inner()).get_mut(&self.live) {
+            live.objects.insert(sha256.clone());
+        }
+        store(&self.dir, &sha256, data).await?;
+        self.recording.segments.push(ArchivedSegment {
+            sequence,
+            duration,
+            program_date_time,
+            sha256,
+            size: data.len() as u64,
+        });
+        Ok(())
+    }
+
+    // Write the recording, if it has any segment
+    pub fn finish(self) -> Result<Option<PathBuf>, AppError> {
+        if self.recording.segments.is_empty() {
+            return Ok(None);
+        }
+        write_recording(&self.dir, &self.recording).map(Some)
+    }
+}
+
+// Once written (or abandoned), the recording no longer holds its objects
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let mut live_recordings = LIVE.lock().unwrap_or_else(|e| e.into_inner());
+        live_recordings.remove(&self.live);
+        LIVE_CHANGES.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording(id: &str, sequences: std::ops::RangeInclusive<u64>) -> Recording {
+        Recording {
+            version: VERSION,
+            id: id.to_string(),
+            stream: "https://example.com/live/720p.m3u8".to_string(),
+            playlist_url: String::new(),
+            media_playlist_url: String::new(),
+            created_at: String::new(),
+            segments: sequences
+                .map(|sequence| ArchivedSegment {
+                    sequence,
+                    duration: 6.0,
+                    program_date_time: Some(format!("2024-05-01T10:00:{:02}Z", sequence * 6)),
+                    sha256: format!("{:064x}", sequence),
+                    size: 100,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let recordings = [recording("a", 0..=3), recording("b", 2..=5), recording("c", 8..=9)];
+        let stream = "https://example.com/live/720p.m3u8";
+
+        let all = select(&recordings, stream, &Window::default()).unwrap();
+        assert_eq!(all.iter().map(|s| s.sequence).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5, 8, 9]);
+        assert_eq!(missing(&all), vec![(6, 7)]);
+
+        let window = Window { from_sequence: Some(3), to_sequence: Some(4), ..Window::default() };
+        assert_eq!(select(&recordings, stream, &window).unwrap().len(), 2);
+
+        // 10:00:13 falls in segment 2 (10:00:12-10:00:18)
+        let window = Window { start_time: Some("2024-05-01T10:00:13Z".to_string()), end_time: Some("2024-05-01T10:00:24Z".to_string()), ..Window::default() };
+        let segments = select(&recordings, stream, &window).unwrap();
+        assert_eq!(segments.iter().map(|s| s.sequence).collect::<Vec<_>>(), vec![2, 3, 4]);
+
+        assert!(select(&recordings, "https://example.com/other.m3u8", &Window::default()).unwrap().is_empty());
+        let window = Window { start_time: Some("yesterday".to_string()), ..Window::default() };
+        assert!(select(&recordings, stream, &window).is_err());
+
+        let summary = summarize(&recordings[1]);
+        assert_eq!((summary.first_sequence, summary.last_sequence, summary.duration), (Some(2), Some(5), 24.0));
+    }
+
+    #[tokio::test]
+    async fn test_delete_keeps_live_objects() {
+        let dir = std::env::temp_dir().join(format!("m3u8-archive-{}", uuid::Uuid::new_v4().simple()));
+        let url = "https://example.com/live/720p.m3u8";
+        let shared = hex(&Sha256::digest(b"shared"));
+
+        let mut old = Recorder::new(dir.clone(), "old", url, url);
+        old.add(0, 6.0, None, b"shared").await.unwrap();
+        old.finish().unwrap();
+
+        // A live recording reuses the stored object, and another is still
+        // being written
+        let mut live = Recorder::new(dir.clone(), "live", url, url);
+        live.add(0, 6.0, None, b"shared").await.unwrap();
+        let part = paths::part_path(&object_path(&dir, &hex(&Sha256::digest(b"partial"))));
+        fs::create_dir_all(part.parent().unwrap()).unwrap();
+        fs::write(&part, b"partial").unwrap();
+
+        assert_eq!(delete(&dir, "old").unwrap(), 0);
+        assert!(object_path(&dir, &shared).exists());
+
+        live.finish().unwrap();
+        assert_eq!(delete(&dir, "live").unwrap(), 1);
+        assert!(!object_path(&dir, &shared).exists());
+        assert!(part.exists());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::error::AppError;
+use crate::m3u8_parser::{M3u8Parser, ParsedPlaylist, Segment};
+use crate::reports::csv_field;
+
+// File formats for segment lists handed to other downloaders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SegmentListFormat {
+    // One URL per line, as wget -i and curl read them
+    #[default]
+    List,
+    // index,uri,duration,byte_range,discontinuity
+    Csv,
+    // aria2c -i input, naming each file by its position so they sort in order
+    Aria2c,
+}
+
+const CSV_HEADER: &str = "index,uri,duration,byte_range,discontinuity";
+
+// File name aria2c saves a segment under: its position, zero-padded, and
+// the extension of the URL path
+fn numbered_name(index: usize, uri: &str) -> String {
+    let path = uri.split(['?', '#']).next().unwrap_or(uri);
+    let name = path.rsplit('/').next().unwrap_or(path);
+    match name.rsplit_once('.') {
+        Some((_, extension)) if !extension.is_empty() && extension.len() <= 5 => {
+            format!("segment-{:05}.{}", index, extension)
+        }
+        _ => format!("segment-{:05}.ts", index),
+    }
+}
+
+pub fn render(segments: &[Segment], format: SegmentListFormat) -> String {
+    let mut out = String::new();
+    match format {
+        SegmentListFormat::List => {
+            for segment in segments {
+                out.push_str(&segment.uri);
+                out.push('\n');
+            }
+        }
+        SegmentListFormat::Csv => {
+            out.push_str(CSV_HEADER);
+            out.push('\n');
+            for (index, segment) in segments.iter().enumerate() {
+                out.push_str(&format!(
+                    "{},{},{},{},{}\n",
+                    index,
+                    csv_field(&segment.uri),
+                    segment.duration,
+                    csv_field(segment.byte_range.as_deref().unwrap_or("")),
+                    segment.discontinuity
+                ));
+            }
+        }
+        SegmentListFormat::Aria2c => {
+            for (index, segment) in segments.iter().enumerate() {
+                out.push_str(&format!("{}\n  out={}\n", segment.uri, numbered_name(index, &segment.uri)));
+            }
+        }
+    }
+    out
+}
+
+// Segments of a playlist with relative URIs resolved against `base_url`
+// (the playlist URL when None). A master playlist uses its first variant,
+// like m3u8_extract_segments.
+pub async fn fetch_segments(parser: &M3u8Parser, url: &str, base_url: Option<&str>) -> Result<Vec<Segment>, AppError> {
+    let content = parser.fetch_playlist(url).await?;
+    match parser.parse_content(&content, base_url.unwrap_or(url))? {
+        ParsedPlaylist::Media { segments, .. } => Ok(segments),
+        ParsedPlaylist::Master { variants, .. } => {
+            let variant = variants.first()
+                .ok_or_else(|| AppError::invalid_input("Master playlist has no variants"))?;
+            let content = parser.fetch_playlist(&variant.uri).await?;
+            match parser.parse_content(&content, &variant.uri)? {
+                ParsedPlaylist::Media { segments, .. } => Ok(segments),
+                ParsedPlaylist::Master { .. } => {
+                    Err(AppError::invalid_input("Variant playlist is itself a master playlist"))
+                }
+            }
+        }
+    }
+}
+
+// Write the segment list of `url` to `path` in `format`. Returns the number
+// of segments written.
+pub async fn export(
+    parser: &M3u8Parser,
+    url: &str,
+    base_url: Option<&str>,
+    format: SegmentListFormat,
+    path: &Path,
+) -> Result<usize, AppError> {
+    let segments = fetch_segments(parser, url, base_url).await?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, render(&segments, format))
+        .map_err(|e| AppError::io(format!("Failed to write {}: {}", path.display(), e)))?;
+    Ok(segments.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(uri: &str, duration: f32, byte_range: Option<&str>) -> Segment {
+        Segment {
+            uri: uri.to_string(),
+            duration,
+            title: None,
+            byte_range: byte_range.map(str::to_string),
+            discontinuity: false,
+            gap: false,
+            bitrate: None,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let segments = [
+            segment("https://cdn.example.com/v/seg0.ts?token=a", 6.0, None),
+            segment("https://cdn.example.com/v/main.mp4", 4.5, Some("1000@0")),
+        ];
+        assert_eq!(
+            render(&segments, SegmentListFormat::List),
+            "https://cdn.example.com/v/seg0.ts?token=a\nhttps://cdn.example.com/v/main.mp4\n"
+        );
+        assert_eq!(
+            render(&segments, SegmentListFormat::Csv),
+            "index,uri,duration,byte_range,discontinuity\n\
+             0,https://cdn.example.com/v/seg0.ts?token=a,6,,false\n\
+             1,https://cdn.example.com/v/main.mp4,4.5,1000@0,false\n"
+        );
+        assert_eq!(
+            render(&segments, SegmentListFormat::Aria2c),
+            "https://cdn.example.com/v/seg0.ts?token=a\n  out=segment-00000.ts\n\
+             https://cdn.example.com/v/main.mp4\n  out=segment-00001.mp4\n"
+        );
+    }
+}
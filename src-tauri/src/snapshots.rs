@@ -0,0 +1,147 @@
+use futures::StreamExt;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::config::{self, SnapshotConfig, SnapshotStorage};
+use crate::database::{Database, PlaylistSnapshot};
+use crate::error::AppError;
+use crate::m3u8_parser::{M3u8Parser, ParsedPlaylist};
+
+// Variant playlists fetched at once for a master snapshot
+const VARIANT_CONCURRENCY: usize = 4;
+
+// A snapshot with its playlist text and, for a master, its variants
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotContent {
+    #[serde(flatten)]
+    pub snapshot: PlaylistSnapshot,
+    pub content: String,
+    pub variants: Vec<SnapshotContent>,
+}
+
+fn snapshot_dir(config: &SnapshotConfig) -> Result<PathBuf, AppError> {
+    match &config.dir {
+        Some(dir) => Ok(crate::paths::expand_path(dir)),
+        None => Ok(config::app_dir()?.join("snapshots")),
+    }
+}
+
+// Store one playlist in the database or as a file, per the configuration
+fn store(
+    db: &Database,
+    config: &SnapshotConfig,
+    parent_id: Option<i64>,
+    url: &str,
+    reason: &str,
+    content: &str,
+) -> Result<i64, AppError> {
+    if config.storage == SnapshotStorage::Database {
+        return Ok(db.save_snapshot(parent_id, url, reason, Some(content), None, content.len())?);
+    }
+
+    let dir = snapshot_dir(config)?
+        .join(chrono::Utc::now().format("%Y%m%d").to_string());
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| AppError::io(format!("Failed to create snapshot directory: {}", e)))?;
+    let path = dir.join(format!("{}.m3u8", uuid::Uuid::new_v4()));
+    std::fs::write(&path, content)
+        .map_err(|e| AppError::io(format!("Failed to write snapshot: {}", e)))?;
+
+    Ok(db.save_snapshot(parent_id, url, reason, None, Some(&path.to_string_lossy()), content.len())?)
+}
+
+// Archive a playlist and, for a master playlist, each variant playlist.
+// Playlists in `fetched` (text by URL) are archived as the caller fetched
+// them; only the others are fetched here. Returns the id of the top-level
+// snapshot.
+pub async fn capture(
+    db: &Database,
+    parser: &M3u8Parser,
+    config: &SnapshotConfig,
+    url: &str,
+    reason: &str,
+    mut fetched: HashMap<String, String>,
+) -> Result<i64, AppError> {
+    let content = match fetched.remove(url) {
+        Some(content) => content,
+        None => parser.fetch_playlist(url).await?,
+    };
+    let id = store(db, config, None, url, reason, &content)?;
+
+    if let Ok(ParsedPlaylist::Master { variants, .. }) = parser.parse_content(&content, url) {
+        let mut fetches = futures::stream::iter(variants)
+            .map(|variant| {
+                let known = fetched.remove(&variant.uri);
+                async move {
+                    let result = match known {
+                        Some(text) => Ok(text),
+                        None => parser.fetch_playlist(&variant.uri).await,
+                    };
+                    (variant.uri, result)
+                }
+            })
+            .buffered(VARIANT_CONCURRENCY);
+
+        while let Some((variant_url, result)) = fetches.next().await {
+            match result {
+                Ok(text) => {
+                    store(db, config, Some(id), &variant_url, reason, &text)?;
+                }
+                Err(e) => eprintln!("Failed to snapshot variant {}: {}", variant_url, e),
+            }
+        }
+    }
+
+    Ok(id)
+}
+
+// Archive `url` in the background when snapshots are enabled, reusing the
+// playlists in `fetched`
+pub fn spawn_capture(parser: Arc<M3u8Parser>, url: &str, reason: &'static str, fetched: HashMap<String, String>) {
+    let config = match config::load_config() {
+        Ok(config) if config.snapshots.enabled => config.snapshots,
+        _ => return,
+    };
+    let url = url.to_string();
+
+    tokio::spawn(async move {
+        let db = match crate::database::global_db().await {
+            Ok(db) => db,
+            Err(_) => return,
+        };
+        if let Err(e) = capture(&db, &parser, &config, &url, reason, fetched).await {
+            eprintln!("Failed to snapshot {}: {}", url, e);
+        }
+    });
+}
+
+fn read_content(snapshot: &PlaylistSnapshot, stored: Option<String>) -> Result<String, AppError> {
+    match (stored, &snapshot.file_path) {
+        (Some(content), _) => Ok(content),
+        (None, Some(path)) => std::fs::read_to_string(path)
+            .map_err(|e| AppError::io(format!("Failed to read snapshot {}: {}", path, e))),
+        (None, None) => Ok(String::new()),
+    }
+}
+
+// A snapshot with its text and variant playlists
+pub fn load(db: &Database, id: i64) -> Result<SnapshotContent, AppError> {
+    let (snapshot, stored) = db.get_snapshot(id)?
+        .ok_or_else(|| AppError::not_found(format!("Snapshot {} not found", id)))?;
+    let content = read_content(&snapshot, stored)?;
+
+    let mut variants = Vec::new();
+    for child in db.get_snapshot_children(id)? {
+        let (child, stored) = db.get_snapshot(child.id)?
+            .ok_or_else(|| AppError::not_found(format!("Snapshot {} not found", child.id)))?;
+        variants.push(SnapshotContent {
+            content: read_content(&child, stored)?,
+            snapshot: child,
+            variants: Vec::new(),
+        });
+    }
+
+    Ok(SnapshotContent { snapshot, content, variants })
+}
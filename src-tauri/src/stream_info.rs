@@ -0,0 +1,115 @@
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::error::AppError;
+use crate::m3u8_parser::{self, Id3Frame, M3u8Parser, ParsedPlaylist, SessionData};
+
+// Enough of the first segment for the metadata tags at its start
+const PROBE_BYTES: u64 = 256 * 1024;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+// What a stream says about itself: #EXT-X-SESSION-DATA of its master playlist
+// and the ID3 text frames of its first segment
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StreamInfo {
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub session_data: Vec<SessionData>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub id3: Vec<Id3Frame>,
+}
+
+// ID3 frames at the start of the first segment of a media playlist.
+// AES-128 segments are skipped since their tags are encrypted too.
+async fn first_segment_id3(parser: &M3u8Parser, content: &str, media_url: &str) -> Vec<Id3Frame> {
+    let first = match parser.parse_content(content, media_url) {
+        Ok(ParsedPlaylist::Media { segments, .. }) => segments.into_iter().next(),
+        _ => None,
+    };
+    let encrypted = m3u8_parser::segment_keys(content, media_url)
+        .first()
+        .is_some_and(|key| key.as_ref().is_some_and(|k| k.method == "AES-128"));
+    let Some(segment) = first.filter(|_| !encrypted) else {
+        return Vec::new();
+    };
+    let offset = segment.byte_range.as_deref()
+        .and_then(|range| range.split_once('@'))
+        .and_then(|(_, offset)| offset.trim().parse().ok())
+        .unwrap_or(0);
+    let fetcher = parser.fetcher();
+    match tokio::time::timeout(PROBE_TIMEOUT, fetcher.get(&segment.uri, Some((offset, PROBE_BYTES)))).await {
+        Ok(Ok(mut data)) => {
+            // Servers that ignore Range send the whole segment
+            data.truncate(PROBE_BYTES as usize);
+            m3u8_parser::id3_frames(&data)
+        }
+        Ok(Err(e)) => {
+            eprintln!("Skipping ID3 metadata of {}: {}", media_url, e);
+            Vec::new()
+        }
+        Err(_) => {
+            eprintln!("Skipping ID3 metadata of {}: timed out", media_url);
+            Vec::new()
+        }
+    }
+}
+
+// Metadata of the stream at `url`, whose playlist text is `content`. The
+// session data comes from that text alone. With `read_segment` the ID3
+// tags are read too, which takes the start of the first segment and, for a
+// master playlist, the playlist of its lowest-bandwidth variant, whose
+// segments are the cheapest to fetch.
+pub async fn probe_content(parser: &M3u8Parser, url: &str, content: &str, read_segment: bool) -> Result<StreamInfo, AppError> {
+    let mut info = StreamInfo::default();
+    let playlist = parser.parse_content(content, url)?;
+    if matches!(playlist, ParsedPlaylist::Master { .. }) {
+        info.session_data = m3u8_parser::parse_session_data(content);
+    }
+    if read_segment {
+        match playlist {
+            ParsedPlaylist::Master { variants, .. } => {
+                if let Some(variant) = variants.iter().min_by_key(|v| v.bandwidth) {
+                    let media = parser.fetch_playlist(&variant.uri).await?;
+                    info.id3 = first_segment_id3(parser, &media, &variant.uri).await;
+                }
+            }
+            ParsedPlaylist::Media { .. } => info.id3 = first_segment_id3(parser, content, url).await,
+        }
+    }
+    info.title = m3u8_parser::stream_title(&info.session_data, &info.id3);
+    Ok(info)
+}
+
+// Metadata of the stream at `url`, with the ID3 tags of its first segment
+pub async fn probe(parser: &M3u8Parser, url: &str) -> Result<StreamInfo, AppError> {
+    let content = parser.fetch_playlist(url).await?;
+    probe_content(parser, url, &content, true).await
+}
+
+// Title of the stream at `url`, whose playlist text is `content`, from its
+// session data. With `read_segment` the ID3 tags of the first segment are
+// read when the session data names no title. Failures just mean no title.
+pub async fn content_title(parser: &M3u8Parser, url: &str, content: &str, read_segment: bool) -> Option<String> {
+    let mut probed = probe_content(parser, url, content, false).await;
+    if read_segment && matches!(&probed, Ok(info) if info.title.is_none()) {
+        probed = probe_content(parser, url, content, true).await;
+    }
+    match probed {
+        Ok(info) => info.title,
+        Err(e) => {
+            eprintln!("Failed to read stream metadata of {}: {}", url, e);
+            None
+        }
+    }
+}
+
+// Title of the stream at `url`, if it names one; failures just mean no title
+pub async fn title(parser: &M3u8Parser, url: &str) -> Option<String> {
+    match parser.fetch_playlist(url).await {
+        Ok(content) => content_title(parser, url, &content, true).await,
+        Err(e) => {
+            eprintln!("Failed to read stream metadata of {}: {}", url, e);
+            None
+        }
+    }
+}
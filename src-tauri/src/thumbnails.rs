@@ -0,0 +1,221 @@
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+use crate::config::{self, ThumbnailConfig};
+use crate::database;
+use crate::error::AppError;
+use crate::events::{self, ThumbnailEvent, ThumbnailUpdate};
+use crate::ffmpeg_wrapper::FFmpegService;
+
+// Thumbnails and previews of recorded downloads are made by a fixed number
+// of workers reading from one queue, so asking for a whole history does not
+// start one FFmpeg process per file
+
+struct Request {
+    media: PathBuf,
+    // Known duration, to pick a frame away from the intro
+    duration: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueueStatus {
+    pub workers: usize,
+    pub queued: usize,
+    pub running: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub last_error: Option<String>,
+}
+
+struct Queue {
+    sender: mpsc::UnboundedSender<Request>,
+    status: Mutex<QueueStatus>,
+    // Files queued or in progress, so repeated requests are not duplicated
+    pending: Mutex<HashSet<PathBuf>>,
+}
+
+static QUEUE: OnceCell<Arc<Queue>> = OnceCell::new();
+
+pub fn thumbnail_path(media: &Path) -> PathBuf {
+    media.with_extension("thumb.jpg")
+}
+
+pub fn preview_path(media: &Path) -> PathBuf {
+    media.with_extension("preview.mp4")
+}
+
+// One FFmpeg run writes both the thumbnail and, if enabled, the preview clip
+pub fn thumbnail_args(media: &Path, duration: Option<f64>, config: &ThumbnailConfig) -> Vec<String> {
+    let mut args = vec!["-loglevel".to_string(), "error".to_string(), "-y".to_string()];
+    if config.hardware_decode {
+        args.extend(["-hwaccel".to_string(), "auto".to_string()]);
+    }
+    // A tenth of the way in, where intros and black frames are usually over
+    let position = duration.map(|d| (d * 0.1).min(60.0)).unwrap_or(0.0);
+    let scale = format!("scale='min({},iw)':-2", config.width);
+    args.extend([
+        "-ss".to_string(),
+        format!("{:.3}", position),
+        "-i".to_string(),
+        media.to_string_lossy().to_string(),
+        "-map".to_string(),
+        "0:v:0".to_string(),
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-vf".to_string(),
+        scale.clone(),
+        "-q:v".to_string(),
+        "4".to_string(),
+        thumbnail_path(media).to_string_lossy().to_string(),
+    ]);
+    if config.preview_seconds > 0 {
+        args.extend([
+            "-map".to_string(),
+            "0:v:0".to_string(),
+            "-t".to_string(),
+            config.preview_seconds.to_string(),
+            "-vf".to_string(),
+            scale,
+            "-an".to_string(),
+            "-c:v".to_string(),
+            "libx264".to_string(),
+            "-preset".to_string(),
+            "veryfast".to_string(),
+            "-movflags".to_string(),
+            "+faststart".to_string(),
+            preview_path(media).to_string_lossy().to_string(),
+        ]);
+    }
+    args
+}
+
+fn queue(ffmpeg: &Arc<FFmpegService>) -> Arc<Queue> {
+    QUEUE.get_or_init(|| {
+        // The worker count is read once; changes apply after a restart
+        let workers = config::load_config().unwrap_or_default().thumbnails.workers.max(1);
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let queue = Arc::new(Queue {
+            sender,
+            status: Mutex::new(QueueStatus { workers, ..QueueStatus::default() }),
+            pending: Mutex::new(HashSet::new()),
+        });
+        let receiver = Arc::new(tokio::sync::Mutex::new(receiver));
+        for _ in 0..workers {
+            tauri::async_runtime::spawn(crate::crash::guard("thumbnails", work(queue.clone(), receiver.clone(), ffmpeg.clone())));
+        }
+        queue
+    }).clone()
+}
+
+async fn work(
+    queue: Arc<Queue>,
+    receiver: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<Request>>>,
+    ffmpeg: Arc<FFmpegService>,
+) {
+    loop {
+        let Some(request) = receiver.lock().await.recv().await else {
+            break;
+        };
+        {
+            let mut status = queue.status.lock().unwrap();
+            status.queued -= 1;
+            status.running += 1;
+        }
+
+        let config = config::load_config().unwrap_or_default().thumbnails;
+        let args = thumbnail_args(&request.media, request.duration, &config);
+        let result = ffmpeg.run_ffmpeg(&args).await;
+
+        queue.pending.lock().unwrap().remove(&request.media);
+        let mut status = queue.status.lock().unwrap();
+        status.running -= 1;
+        match result {
+            Ok(_) => {
+                status.completed += 1;
+                ffmpeg.emit(events::THUMBNAIL_PROGRESS, ThumbnailEvent::new(
+                    request.media.clone(),
+                    ThumbnailUpdate::Completed {
+                        thumbnail: thumbnail_path(&request.media),
+                        preview: (config.preview_seconds > 0).then(|| preview_path(&request.media)),
+                    },
+                ));
+            }
+            Err(e) => {
+                eprintln!("Failed to make a thumbnail of {}: {}", request.media.display(), e);
+                status.failed += 1;
+                status.last_error = Some(e.to_string());
+                ffmpeg.emit(events::THUMBNAIL_PROGRESS, ThumbnailEvent::new(
+                    request.media.clone(),
+                    ThumbnailUpdate::Error { message: e.to_string() },
+                ));
+            }
+        }
+    }
+}
+
+// Queue files for thumbnails; returns how many were added. Files already
+// queued, missing, or (without `force`) with a thumbnail are skipped.
+pub fn enqueue(ffmpeg: &Arc<FFmpegService>, files: Vec<(PathBuf, Option<f64>)>, force: bool) -> usize {
+    let queue = queue(ffmpeg);
+    let mut added = 0;
+    for (media, duration) in files {
+        if !media.exists() || (!force && thumbnail_path(&media).exists()) {
+            continue;
+        }
+        if !queue.pending.lock().unwrap().insert(media.clone()) {
+            continue;
+        }
+        queue.status.lock().unwrap().queued += 1;
+        if queue.sender.send(Request { media, duration }).is_ok() {
+            added += 1;
+        }
+    }
+    added
+}
+
+// Queue the recorded downloads in `download_ids`, or the whole history
+pub async fn enqueue_downloads(
+    ffmpeg: &Arc<FFmpegService>,
+    download_ids: Option<&[i32]>,
+    force: bool,
+) -> Result<usize, AppError> {
+    let downloads = database::global_db().await?.list_all_downloads()?;
+    let files = downloads
+        .into_iter()
+        .filter(|d| download_ids.map_or(true, |ids| ids.contains(&d.id)))
+        .map(|d| (PathBuf::from(d.output_path), d.duration))
+        .collect();
+    Ok(enqueue(ffmpeg, files, force))
+}
+
+pub fn status() -> QueueStatus {
+    QUEUE.get()
+        .map(|queue| queue.status.lock().unwrap().clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_args() {
+        let media = Path::new("/videos/show.mp4");
+        assert_eq!(thumbnail_path(media), Path::new("/videos/show.thumb.jpg"));
+
+        let config = ThumbnailConfig { preview_seconds: 0, ..ThumbnailConfig::default() };
+        let args = thumbnail_args(media, Some(120.0), &config);
+        assert_eq!(args[args.iter().position(|a| a == "-ss").unwrap() + 1], "12.000");
+        assert!(!args.contains(&"-hwaccel".to_string()));
+        assert_eq!(args.last().map(String::as_str), Some("/videos/show.thumb.jpg"));
+
+        let config = ThumbnailConfig { hardware_decode: true, preview_seconds: 5, ..ThumbnailConfig::default() };
+        let args = thumbnail_args(media, None, &config);
+        assert!(args.contains(&"-hwaccel".to_string()));
+        assert_eq!(args.last().map(String::as_str), Some("/videos/show.preview.mp4"));
+    }
+}
@@ -0,0 +1,154 @@
+use serde_json::{json, Value};
+
+use crate::config;
+use crate::result_store;
+
+// Keep `text` as a result resource; None (after logging why) when it
+// cannot be written
+fn store(text: &str, json: bool) -> Option<String> {
+    result_store::store(text, json)
+        .map_err(|e| eprintln!("Failed to store tool result: {}", e))
+        .ok()
+}
+
+fn max_chars() -> usize {
+    config::load_config().map(|c| c.mcp.max_result_chars).unwrap_or_default()
+}
+
+// Page of `items` from `offset`: at most `limit` items, and no more than
+// fit in `max_chars` characters of JSON (0 for no cap). At least one item
+// is returned when any are left. The flag tells whether the cap cut the
+// page short.
+fn page(items: &[Value], offset: usize, limit: Option<usize>, max_chars: usize) -> (Vec<Value>, bool) {
+    let wanted = &items[offset.min(items.len())..];
+    let wanted = &wanted[..limit.unwrap_or(wanted.len()).min(wanted.len())];
+    let mut used = 0;
+    let mut page = Vec::new();
+    for item in wanted {
+        let size = item.to_string().len() + 1;
+        if max_chars > 0 && !page.is_empty() && used + size > max_chars {
+            return (page, true);
+        }
+        used += size;
+        page.push(item.clone());
+    }
+    (page, false)
+}
+
+// `object` with its `field` array replaced by the page the call's `offset`
+// and `limit` arguments ask for, and a "page" entry saying where it sits in
+// the whole. When the size cap cut the page short the whole array is kept
+// as a result resource named in "page.full_result".
+fn paged(mut object: Value, field: &str, arguments: &Value) -> Value {
+    let Some(items) = object.get_mut(field).and_then(|v| v.as_array_mut()).map(std::mem::take) else {
+        return object;
+    };
+    let offset = arguments.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+    let limit = arguments.get("limit").and_then(|v| v.as_u64()).map(|limit| limit as usize);
+    let (page, truncated) = page(&items, offset, limit, max_chars());
+
+    let end = offset.min(items.len()) + page.len();
+    let mut info = json!({
+        "total": items.len(),
+        "offset": offset,
+        "returned": page.len(),
+        "next_offset": (end < items.len()).then_some(end),
+        "truncated": truncated
+    });
+    if truncated {
+        if let Some(uri) = store(&serde_json::to_string_pretty(&items).unwrap_or_default(), true) {
+            info["full_result"] = json!(uri);
+        }
+    }
+    object[field] = Value::Array(page);
+    object["page"] = info;
+    object
+}
+
+// `text` cut at a line boundary to the size cap, ending with a note of what
+// was left out and the result resource holding all of it
+fn truncate(text: String, json: bool) -> String {
+    let max_chars = max_chars();
+    if max_chars == 0 || text.len() <= max_chars {
+        return text;
+    }
+    let mut end = max_chars;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let end = text[..end].rfind('\n').unwrap_or(end);
+    let shown = text[..end].to_string();
+    let lines = text[end..].lines().filter(|line| !line.trim().is_empty()).count();
+    let note = format!("... truncated: {} of {} characters shown, {} more lines", end, text.len(), lines);
+    match store(&text, json) {
+        Some(uri) => format!("{}\n{}. Full result: {}", shown, note, uri),
+        None => format!("{}\n{}", shown, note),
+    }
+}
+
+fn wants_store(arguments: &Value) -> bool {
+    arguments.get("store_result").and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+// Short answer naming the stored result instead of the result itself
+fn stored_reference(uri: String, text: &str, items: Option<usize>) -> String {
+    let mut reference = json!({ "result": uri, "bytes": text.len() });
+    if let Some(items) = items {
+        reference["items"] = json!(items);
+    }
+    serde_json::to_string_pretty(&reference).unwrap_or_default()
+}
+
+// Text of a tool's JSON answer. With the call's store_result argument the
+// whole of `value` is stored and only its URI returned; otherwise the
+// `field` array is paged, or without one the text is capped.
+pub fn render(value: Value, field: Option<&str>, arguments: &Value) -> String {
+    if wants_store(arguments) {
+        let text = serde_json::to_string_pretty(&value).unwrap_or_default();
+        let items = field.and_then(|field| value.get(field)).and_then(|v| v.as_array()).map(Vec::len);
+        if let Some(uri) = store(&text, true) {
+            return stored_reference(uri, &text, items);
+        }
+    }
+    match field {
+        Some(field) => serde_json::to_string_pretty(&paged(value, field, arguments)).unwrap_or_default(),
+        None => truncate(serde_json::to_string_pretty(&value).unwrap_or_default(), true),
+    }
+}
+
+// Text of a tool's answer given as text (JSON when `json` is set), stored
+// or capped like render's
+pub fn render_text(text: String, json: bool, arguments: &Value) -> String {
+    if wants_store(arguments) {
+        if let Some(uri) = store(&text, json) {
+            return stored_reference(uri, &text, None);
+        }
+    }
+    truncate(text, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page() {
+        let items: Vec<Value> = (0..10).map(|i| json!(format!("https://cdn.example.com/seg{}.ts", i))).collect();
+        let (page_items, truncated) = page(&items, 2, Some(3), 0);
+        assert_eq!(page_items, items[2..5].to_vec());
+        assert!(!truncated);
+
+        // Each item is 33 characters of JSON plus a separator
+        let (page_items, truncated) = page(&items, 8, None, 80);
+        assert_eq!(page_items.len(), 2);
+        assert!(!truncated);
+        let (page_items, truncated) = page(&items, 0, None, 80);
+        assert_eq!(page_items.len(), 2);
+        assert!(truncated);
+
+        let (page_items, truncated) = page(&items, 0, None, 10);
+        assert_eq!(page_items.len(), 1);
+        assert!(truncated);
+        assert!(page(&items, 20, None, 0).0.is_empty());
+    }
+}
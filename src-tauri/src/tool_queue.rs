@@ -0,0 +1,180 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+use crate::error::AppError;
+
+// Waits kept for the average and maximum in the status
+const RECENT_WAITS: usize = 100;
+
+// Tools that run FFmpeg or fetch media, and so share the worker pool. The
+// others answer from memory, the database or a single playlist fetch and
+// run straight away.
+const POOLED_TOOLS: &[&str] = &[
+    "m3u8_download",
+    "m3u8_convert",
+    "m3u8_probe",
+    "m3u8_stats",
+    "m3u8_compatibility",
+    "m3u8_loudness",
+    "m3u8_scenes",
+    "m3u8_grab_frame",
+    "m3u8_fingerprint",
+    "m3u8_report",
+    "m3u8_transcribe",
+    "m3u8_chapters",
+    "m3u8_repair",
+    "m3u8_export_vod",
+];
+
+pub fn is_pooled(tool: &str) -> bool {
+    POOLED_TOOLS.contains(&tool)
+}
+
+#[derive(Default)]
+struct Stats {
+    // Calls by ticket, with their tool and since when they wait or run
+    waiting: HashMap<u64, (String, Instant)>,
+    running: HashMap<u64, (String, Instant)>,
+    completed: u64,
+    rejected: u64,
+    recent_waits: VecDeque<Duration>,
+}
+
+// A tool call as the status shows it
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedCall {
+    pub tool: String,
+    // Seconds spent waiting, or running
+    pub seconds: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueStatus {
+    pub workers: usize,
+    pub max_queued: usize,
+    pub running: Vec<QueuedCall>,
+    pub queued: Vec<QueuedCall>,
+    pub completed: u64,
+    pub rejected: u64,
+    // Over the last 100 calls that got a worker
+    pub average_wait_seconds: Option<f64>,
+    pub max_wait_seconds: Option<f64>,
+}
+
+// Removes a call from the statistics once it stops waiting or running,
+// including when the client goes away while it waits
+struct Ticket {
+    stats: Arc<Mutex<Stats>>,
+    id: u64,
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.waiting.remove(&self.id);
+        if stats.running.remove(&self.id).is_some() {
+            stats.completed += 1;
+        }
+    }
+}
+
+// Bounded pool that runs heavy tool calls off the request handlers, with
+// the calls waiting for a worker visible in the status
+pub struct ToolQueue {
+    workers: usize,
+    max_queued: usize,
+    permits: Arc<Semaphore>,
+    next_ticket: AtomicU64,
+    stats: Arc<Mutex<Stats>>,
+}
+
+impl ToolQueue {
+    pub fn new(workers: usize, max_queued: usize) -> Self {
+        let workers = workers.max(1);
+        Self {
+            workers,
+            max_queued,
+            permits: Arc::new(Semaphore::new(workers)),
+            next_ticket: AtomicU64::new(0),
+            stats: Arc::new(Mutex::new(Stats::default())),
+        }
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        let stats = self.stats.lock().unwrap();
+        let calls = |calls: &HashMap<u64, (String, Instant)>| {
+            let mut calls: Vec<QueuedCall> = calls
+                .values()
+                .map(|(tool, since)| QueuedCall { tool: tool.clone(), seconds: since.elapsed().as_secs_f64() })
+                .collect();
+            calls.sort_by(|a, b| b.seconds.total_cmp(&a.seconds));
+            calls
+        };
+        let waits = &stats.recent_waits;
+        QueueStatus {
+            workers: self.workers,
+            max_queued: self.max_queued,
+            running: calls(&stats.running),
+            queued: calls(&stats.waiting),
+            completed: stats.completed,
+            rejected: stats.rejected,
+            average_wait_seconds: (!waits.is_empty())
+                .then(|| waits.iter().map(Duration::as_secs_f64).sum::<f64>() / waits.len() as f64),
+            max_wait_seconds: waits.iter().max().map(Duration::as_secs_f64),
+        }
+    }
+
+    // Run `work` on its own task once a worker is free. Fails at once when
+    // max_queued calls are already waiting.
+    pub async fn run<F>(&self, tool: &str, work: F) -> Result<F::Output, AppError>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let ticket = Ticket {
+            stats: self.stats.clone(),
+            id: self.next_ticket.fetch_add(1, Ordering::Relaxed),
+        };
+        {
+            let mut stats = self.stats.lock().unwrap();
+            if stats.waiting.len() >= self.max_queued {
+                stats.rejected += 1;
+                return Err(AppError::conflict(format!(
+                    "Server busy: {} tool calls are waiting for a worker",
+                    stats.waiting.len()
+                ))
+                .with_retryable(true));
+            }
+            stats.waiting.insert(ticket.id, (tool.to_string(), Instant::now()));
+        }
+
+        let permit = self.permits.clone().acquire_owned().await
+            .map_err(|e| AppError::internal(format!("Worker pool closed: {}", e)))?;
+        {
+            let mut stats = self.stats.lock().unwrap();
+            if let Some((tool, since)) = stats.waiting.remove(&ticket.id) {
+                if stats.recent_waits.len() == RECENT_WAITS {
+                    stats.recent_waits.pop_front();
+                }
+                stats.recent_waits.push_back(since.elapsed());
+                stats.running.insert(ticket.id, (tool, Instant::now()));
+            }
+        }
+
+        // The worker keeps its slot until the call is done, even if the
+        // client stops waiting for the answer
+        tokio::spawn(async move {
+            let output = work.await;
+            drop(ticket);
+            drop(permit);
+            output
+        })
+        .await
+        .map_err(|e| AppError::internal(format!("Tool call failed: {}", e)))
+    }
+}
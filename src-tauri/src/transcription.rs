@@ -0,0 +1,186 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::TranscriptionConfig;
+use crate::error::{AppError, ErrorKind};
+use crate::ffmpeg_wrapper::FFmpegService;
+
+// Uploading and transcribing an hour of audio can take a while
+const ENDPOINT_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+// Transcript stored next to a download: video.mp4 -> video.transcript.txt
+pub fn transcript_path(media: &Path) -> PathBuf {
+    media.with_extension("transcript.txt")
+}
+
+// Read the transcript of a download, if one has been written
+pub fn read_transcript(media: &Path) -> Option<String> {
+    std::fs::read_to_string(transcript_path(media)).ok()
+}
+
+// Extract 16 kHz mono PCM, the input format whisper expects
+async fn extract_audio(ffmpeg: &FFmpegService, media: &Path, wav: &Path) -> Result<(), AppError> {
+    let args: Vec<String> = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        media.to_string_lossy().to_string(),
+        "-vn".to_string(),
+        "-ac".to_string(),
+        "1".to_string(),
+        "-ar".to_string(),
+        "16000".to_string(),
+        "-c:a".to_string(),
+        "pcm_s16le".to_string(),
+        wav.to_string_lossy().to_string(),
+    ];
+    ffmpeg.run_ffmpeg(&args).await?;
+    Ok(())
+}
+
+// Run the whisper.cpp CLI; it writes `<prefix>.txt` for -otxt -of <prefix>
+async fn transcribe_with_binary(
+    config: &TranscriptionConfig,
+    binary: &str,
+    wav: &Path,
+    output: &Path,
+    grace: std::time::Duration,
+) -> Result<(), AppError> {
+    let model = config.model_path.as_deref()
+        .ok_or_else(|| AppError::invalid_input("transcription.model_path is required with whisper_path"))?;
+    let prefix = output.with_extension("");
+
+    let mut command = tokio::process::Command::new(binary);
+    command
+        .arg("-m")
+        .arg(model)
+        .arg("-f")
+        .arg(wav)
+        .arg("-otxt")
+        .arg("-of")
+        .arg(&prefix);
+    if let Some(language) = &config.language {
+        command.arg("-l").arg(language);
+    }
+
+    let result = crate::process::output_unless_cancelled(&mut command, grace).await
+        .map_err(|e| AppError::internal(format!("Failed to run {}: {}", binary, e)))?;
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let last_line = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("");
+        return Err(AppError::internal(format!("Whisper failed ({}): {}", result.status, last_line)));
+    }
+    if !output.exists() {
+        return Err(AppError::internal(format!("Whisper did not write {}", output.display())));
+    }
+    Ok(())
+}
+
+// Upload the audio to an HTTP transcription endpoint and return its text
+async fn transcribe_with_endpoint(
+    config: &TranscriptionConfig,
+    endpoint: &str,
+    wav: &Path,
+) -> Result<String, AppError> {
+    let audio = tokio::fs::read(wav).await?;
+    let part = reqwest::multipart::Part::bytes(audio)
+        .file_name("audio.wav")
+        .mime_str("audio/wav")
+        .map_err(|e| AppError::internal(e.to_string()))?;
+    let mut form = reqwest::multipart::Form::new()
+        .part("file", part)
+        .text("response_format", "json");
+    if let Some(model) = &config.endpoint_model {
+        form = form.text("model", model.clone());
+    }
+    if let Some(language) = &config.language {
+        form = form.text("language", language.clone());
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(ENDPOINT_TIMEOUT)
+        .build()
+        .map_err(|e| AppError::internal(e.to_string()))?;
+    let response = client.post(endpoint).multipart(form).send().await
+        .map_err(|e| AppError::new(ErrorKind::Network, format!("Transcription request failed: {}", e)))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(AppError::new(ErrorKind::Network, format!("Transcription endpoint returned {}: {}", status, body.trim()))
+            .with_retryable(status.is_server_error()));
+    }
+
+    let body: serde_json::Value = response.json().await
+        .map_err(|e| AppError::internal(format!("Invalid transcription response: {}", e)))?;
+    body.get("text")
+        .and_then(|v| v.as_str())
+        .map(|text| text.trim().to_string())
+        .ok_or_else(|| AppError::internal("Transcription response has no \"text\" field"))
+}
+
+// Transcribe a downloaded file and store the transcript next to it.
+// Returns the path of the transcript.
+pub async fn transcribe_file(
+    ffmpeg: &FFmpegService,
+    config: &TranscriptionConfig,
+    media: &Path,
+) -> Result<PathBuf, AppError> {
+    if !media.exists() {
+        return Err(AppError::not_found(format!("File not found: {}", media.display())));
+    }
+    if config.whisper_path.is_none() && config.endpoint.is_none() {
+        return Err(AppError::invalid_input(
+            "Transcription is not configured: set transcription.whisper_path or transcription.endpoint",
+        ));
+    }
+
+    let output = transcript_path(media);
+    let wav = std::env::temp_dir().join(format!("m3u8-mcp-{}.wav", uuid::Uuid::new_v4()));
+    extract_audio(ffmpeg, media, &wav).await?;
+
+    // The local binary wins when both are configured
+    let result = if let Some(binary) = &config.whisper_path {
+        let grace = crate::process::grace(&ffmpeg.config().process);
+        transcribe_with_binary(config, binary, &wav, &output, grace).await
+    } else {
+        let endpoint = config.endpoint.as_deref().unwrap_or_default();
+        match transcribe_with_endpoint(config, endpoint, &wav).await {
+            Ok(text) => tokio::fs::write(&output, text).await.map_err(AppError::from),
+            Err(e) => Err(e),
+        }
+    };
+    let _ = tokio::fs::remove_file(&wav).await;
+
+    result.map(|_| output)
+}
+
+// Transcribe a finished download in the background when auto_transcribe is on
+pub fn spawn_auto_transcribe(ffmpeg: Arc<FFmpegService>, media: PathBuf) {
+    let config = match crate::config::load_config() {
+        Ok(config) if config.transcription.auto_transcribe => config.transcription,
+        _ => return,
+    };
+
+    tokio::spawn(async move {
+        println!("Transcribing {}", media.display());
+        match transcribe_file(&ffmpeg, &config, &media).await {
+            Ok(path) => println!("Transcript written to {}", path.display()),
+            Err(e) => eprintln!("Transcription of {} failed: {}", media.display(), e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcript_path() {
+        assert_eq!(
+            transcript_path(Path::new("/videos/live.mp4")),
+            PathBuf::from("/videos/live.transcript.txt")
+        );
+        assert_eq!(transcript_path(Path::new("recording")), PathBuf::from("recording.transcript.txt"));
+    }
+}
@@ -0,0 +1,83 @@
+use serde::Serialize;
+use serde_json::json;
+
+use crate::database::{Database, TrashEntry};
+use crate::error::AppError;
+use crate::history;
+
+// Clearing the cache or the URL history is a soft delete: what was cleared
+// goes to the trash table, where m3u8_trash_restore (or the app's restore
+// command) can bring it back, until retention.trash_days have passed. One
+// mistaken call from an agent should not wipe a library for good.
+
+pub const CACHE: &str = "cache";
+pub const HISTORY: &str = "history";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Restored {
+    #[serde(flatten)]
+    pub entry: TrashEntry,
+    // Entries in the URL history afterwards, for a restored history
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_entries: Option<usize>,
+}
+
+// Clear cached playlists, probe results, link checks and fingerprints, and with
+// `include_downloads` the download records, into the trash
+pub fn clear_cache(db: &Database, include_downloads: bool) -> Result<TrashEntry, AppError> {
+    let entry = if include_downloads {
+        db.clear_all_cache()?
+    } else {
+        db.clear_playlist_cache()?
+    };
+    Ok(entry)
+}
+
+// Clear the URL history into the trash; None when it was empty already
+pub fn clear_history(db: &Database) -> Result<Option<TrashEntry>, AppError> {
+    let entries = history::load().unwrap_or_default();
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    let entry = db.trash_data(HISTORY, &json!({ "history": entries }), entries.len())?;
+    history::clear()?;
+    Ok(Some(entry))
+}
+
+// Undo a clear
+pub fn restore(db: &Database, id: i64) -> Result<Restored, AppError> {
+    let (entry, data) = db.restore_trash(id)?
+        .ok_or_else(|| AppError::not_found(format!("No trash entry {}", id)))?;
+    let history_entries = match data.get("history").and_then(|v| v.as_array()) {
+        Some(entries) => Some(history::restore(entries)?),
+        None => None,
+    };
+    Ok(Restored { entry, history_entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clear_and_restore_cache() {
+        let path = std::env::temp_dir().join(format!("m3u8-trash-{}.db", uuid::Uuid::new_v4().simple()));
+        let db = Database::new(path.clone()).unwrap();
+        db.cache_playlist("https://example.com/a.m3u8", "media", "{}").unwrap();
+
+        let entry = clear_cache(&db, false).unwrap();
+        assert_eq!((entry.kind.as_str(), entry.items), (CACHE, 1));
+        assert!(db.get_cached_playlist("https://example.com/a.m3u8").unwrap().is_none());
+        assert_eq!(db.list_trash().unwrap().len(), 1);
+
+        let restored = restore(&db, entry.id).unwrap();
+        assert_eq!(restored.entry.items, 1);
+        assert!(restored.history_entries.is_none());
+        assert_eq!(db.get_cached_playlist("https://example.com/a.m3u8").unwrap().as_deref(), Some("{}"));
+        assert!(db.list_trash().unwrap().is_empty());
+        assert!(restore(&db, entry.id).is_err());
+
+        drop(db);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::time::Duration;
+
+use crate::config::{AppConfig, UpdateChannel};
+use crate::error::{AppError, ErrorKind};
+
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// A release as the GitHub releases API lists it
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub html_url: String,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub published_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateCheck {
+    pub current_version: String,
+    pub channel: UpdateChannel,
+    pub update_available: bool,
+    // Newest release of the channel, None when the feed lists none
+    pub latest_version: Option<String>,
+    pub release_url: Option<String>,
+    pub release_notes: Option<String>,
+    pub published_at: Option<String>,
+}
+
+// Order two versions like 1.2.0, v1.10.0 or 2.0.0-beta.2: numerically part
+// by part, and a pre-release before the release it leads up to. None when
+// either does not look like a version.
+pub fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    fn split(version: &str) -> Option<(Vec<u64>, Option<&str>)> {
+        let version = version.trim().trim_start_matches('v');
+        let version = version.split('+').next().unwrap_or(version);
+        let (release, pre) = match version.split_once('-') {
+            Some((release, pre)) => (release, Some(pre)),
+            None => (version, None),
+        };
+        let parts = release.split('.').map(|part| part.parse().ok()).collect::<Option<Vec<u64>>>()?;
+        (!parts.is_empty()).then_some((parts, pre))
+    }
+    fn compare_pre(a: &str, b: &str) -> Ordering {
+        let mut a_ids = a.split('.');
+        let mut b_ids = b.split('.');
+        loop {
+            match (a_ids.next(), b_ids.next()) {
+                (None, None) => return Ordering::Equal,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(a), Some(b)) => {
+                    let order = match (a.parse::<u64>(), b.parse::<u64>()) {
+                        (Ok(a), Ok(b)) => a.cmp(&b),
+                        (Ok(_), Err(_)) => Ordering::Less,
+                        (Err(_), Ok(_)) => Ordering::Greater,
+                        (Err(_), Err(_)) => a.cmp(b),
+                    };
+                    if order != Ordering::Equal {
+                        return order;
+                    }
+                }
+            }
+        }
+    }
+
+    let (a_parts, a_pre) = split(a)?;
+    let (b_parts, b_pre) = split(b)?;
+    let len = a_parts.len().max(b_parts.len());
+    for index in 0..len {
+        let order = a_parts.get(index).unwrap_or(&0).cmp(b_parts.get(index).unwrap_or(&0));
+        if order != Ordering::Equal {
+            return Some(order);
+        }
+    }
+    Some(match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => compare_pre(a, b),
+    })
+}
+
+// Newest published release the channel takes
+pub fn latest_release(releases: &[Release], channel: UpdateChannel) -> Option<&Release> {
+    releases
+        .iter()
+        .filter(|release| !release.draft)
+        .filter(|release| channel == UpdateChannel::Beta || !release.prerelease)
+        .filter(|release| compare_versions(&release.tag_name, "0").is_some())
+        .max_by(|a, b| compare_versions(&a.tag_name, &b.tag_name).unwrap_or(Ordering::Equal))
+}
+
+// Ask the release feed whether a newer version than this one is out
+pub async fn check(config: &AppConfig) -> Result<UpdateCheck, AppError> {
+    let feed_url = &config.updates.feed_url;
+    let client = crate::profiles::http_client(config, Duration::from_secs(15))?;
+    let response = client
+        .get(feed_url)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .map_err(|e| AppError::new(ErrorKind::Network, format!("Cannot reach {}: {}", feed_url, e)).with_retryable(true))?;
+    if !response.status().is_success() {
+        return Err(AppError::new(ErrorKind::Network, format!("{} answered {}", feed_url, response.status()))
+            .with_retryable(response.status().is_server_error()));
+    }
+    let releases: Vec<Release> = response
+        .json()
+        .await
+        .map_err(|e| AppError::new(ErrorKind::Parse, format!("Unexpected release feed from {}: {}", feed_url, e)))?;
+
+    let channel = config.updates.channel;
+    let latest = latest_release(&releases, channel);
+    let update_available = latest
+        .and_then(|release| compare_versions(&release.tag_name, CURRENT_VERSION))
+        .is_some_and(|order| order == Ordering::Greater);
+    Ok(UpdateCheck {
+        current_version: CURRENT_VERSION.to_string(),
+        channel,
+        update_available,
+        latest_version: latest.map(|release| release.tag_name.trim_start_matches('v').to_string()),
+        release_url: latest.map(|release| release.html_url.clone()).filter(|url| !url.is_empty()),
+        release_notes: latest.and_then(|release| release.body.clone()),
+        published_at: latest.and_then(|release| release.published_at.clone()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag_name: &str, prerelease: bool) -> Release {
+        Release {
+            tag_name: tag_name.to_string(),
+            html_url: String::new(),
+            body: None,
+            prerelease,
+            draft: false,
+            published_at: None,
+        }
+    }
+
+    #[test]
+    fn test_latest_release() {
+        assert_eq!(compare_versions("v0.10.0", "0.9.3"), Some(Ordering::Greater));
+        assert_eq!(compare_versions("1.0", "1.0.0"), Some(Ordering::Equal));
+        assert_eq!(compare_versions("1.0.0-beta.2", "1.0.0"), Some(Ordering::Less));
+        assert_eq!(compare_versions("1.0.0-beta.10", "1.0.0-beta.2"), Some(Ordering::Greater));
+        assert_eq!(compare_versions("nightly", "1.0.0"), None);
+
+        let releases = [release("v0.2.0", false), release("v0.3.0-beta.1", true), release("nightly", false)];
+        assert_eq!(latest_release(&releases, UpdateChannel::Stable).unwrap().tag_name, "v0.2.0");
+        assert_eq!(latest_release(&releases, UpdateChannel::Beta).unwrap().tag_name, "v0.3.0-beta.1");
+    }
+}
@@ -0,0 +1,139 @@
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::config;
+use crate::database::{self, DailyDownloads, ToolUsage};
+use crate::error::AppError;
+
+// Local usage statistics, collected only when collect_usage_stats is on.
+// They are counts per day in the database: which tools were called and how
+// often they failed, and how many downloads finished with how many bytes.
+// Nothing is sent anywhere; export_report writes them to a file the user
+// chooses.
+
+fn enabled() -> bool {
+    config::load_config().map(|c| c.collect_usage_stats).unwrap_or(false)
+}
+
+pub async fn record_tool_call(tool: &str, ok: bool) {
+    if tool.is_empty() || !enabled() {
+        return;
+    }
+    if let Ok(db) = database::global_db().await {
+        if let Err(e) = db.record_tool_usage(tool, ok) {
+            eprintln!("Failed to record tool usage: {}", e);
+        }
+    }
+}
+
+// A download that ended, with the size of its output when it succeeded
+pub async fn record_download(output: Option<&Path>) {
+    if !enabled() {
+        return;
+    }
+    let bytes = output.and_then(|path| std::fs::metadata(path).ok()).map_or(0, |m| m.len());
+    if let Ok(db) = database::global_db().await {
+        if let Err(e) = db.record_download_usage(output.is_some(), bytes) {
+            eprintln!("Failed to record download usage: {}", e);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolStats {
+    pub tool: String,
+    pub calls: i64,
+    pub failures: i64,
+    // Share of calls that succeeded, 0 to 1
+    pub success_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageStats {
+    pub enabled: bool,
+    // First day counted (YYYY-MM-DD), None for all time
+    pub since: Option<String>,
+    pub tool_calls: i64,
+    pub tools: Vec<ToolStats>,
+    pub downloads: i64,
+    pub download_failures: i64,
+    pub download_success_rate: f64,
+    pub bytes_downloaded: i64,
+    pub days: Vec<DailyDownloads>,
+}
+
+fn success_rate(total: i64, failures: i64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    (total - failures) as f64 / total as f64
+}
+
+fn summarize(since: Option<String>, enabled: bool, tools: Vec<ToolUsage>, days: Vec<DailyDownloads>) -> UsageStats {
+    let downloads = days.iter().map(|day| day.downloads).sum();
+    let download_failures = days.iter().map(|day| day.failures).sum();
+    UsageStats {
+        enabled,
+        since,
+        tool_calls: tools.iter().map(|tool| tool.calls).sum(),
+        tools: tools
+            .into_iter()
+            .map(|tool| ToolStats {
+                success_rate: success_rate(tool.calls, tool.failures),
+                tool: tool.tool,
+                calls: tool.calls,
+                failures: tool.failures,
+            })
+            .collect(),
+        downloads,
+        download_failures,
+        download_success_rate: success_rate(downloads, download_failures),
+        bytes_downloaded: days.iter().map(|day| day.bytes).sum(),
+        days,
+    }
+}
+
+// Statistics of the last `days` days, or of all time
+pub async fn stats(days: Option<u32>) -> Result<UsageStats, AppError> {
+    let since = days.map(|days| (Utc::now() - Duration::days(days.saturating_sub(1) as i64)).format("%Y-%m-%d").to_string());
+    let db = database::global_db().await?;
+    let tools = db.get_tool_usage(since.as_deref())?;
+    let downloads = db.get_download_usage(since.as_deref())?;
+    Ok(summarize(since, enabled(), tools, downloads))
+}
+
+// Write the statistics to `path` as JSON
+pub async fn export_report(path: &Path, days: Option<u32>) -> Result<UsageStats, AppError> {
+    let stats = stats(days).await?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string_pretty(&stats).map_err(|e| AppError::internal(e.to_string()))?;
+    std::fs::write(path, json).map_err(|e| AppError::io(format!("Failed to write {}: {}", path.display(), e)))?;
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        let tools = vec![
+            ToolUsage { tool: "m3u8_parse".to_string(), calls: 8, failures: 2 },
+            ToolUsage { tool: "m3u8_download".to_string(), calls: 2, failures: 0 },
+        ];
+        let days = vec![
+            DailyDownloads { day: "2024-05-01".to_string(), downloads: 3, failures: 1, bytes: 1000 },
+            DailyDownloads { day: "2024-05-02".to_string(), downloads: 1, failures: 0, bytes: 500 },
+        ];
+        let stats = summarize(None, true, tools, days);
+        assert_eq!(stats.tool_calls, 10);
+        assert_eq!(stats.tools[0].success_rate, 0.75);
+        assert_eq!(stats.downloads, 4);
+        assert_eq!(stats.download_success_rate, 0.75);
+        assert_eq!(stats.bytes_downloaded, 1500);
+        assert_eq!(summarize(None, false, Vec::new(), Vec::new()).download_success_rate, 0.0);
+    }
+}
@@ -0,0 +1,123 @@
+use serde_json::{json, Value};
+
+use crate::error::AppError;
+use crate::m3u8_parser::{M3u8Parser, ParsedPlaylist, Variant};
+
+// The `variant` argument of m3u8_download
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariantChoice {
+    Auto,
+    // Position in the master playlist, as listed
+    Index(usize),
+    // Height such as 720 (given as "720p")
+    Height(u64),
+    Uri(String),
+}
+
+pub fn parse_choice(value: &Value) -> Result<VariantChoice, AppError> {
+    if let Some(index) = value.as_u64() {
+        return Ok(VariantChoice::Index(index as usize));
+    }
+    let text = value.as_str()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| AppError::invalid_input("variant must be \"auto\", an index, a height like \"720p\" or a variant URI"))?;
+    if text.eq_ignore_ascii_case("auto") {
+        return Ok(VariantChoice::Auto);
+    }
+    if let Some(height) = text.strip_suffix(['p', 'P']).and_then(|h| h.parse().ok()) {
+        return Ok(VariantChoice::Height(height));
+    }
+    if let Ok(index) = text.parse() {
+        return Ok(VariantChoice::Index(index));
+    }
+    Ok(VariantChoice::Uri(text.to_string()))
+}
+
+fn height(variant: &Variant) -> Option<u64> {
+    variant.resolution.as_deref()?.split_once('x')?.1.parse().ok()
+}
+
+// The variant a choice names; None for Auto
+pub fn select<'a>(variants: &'a [Variant], choice: &VariantChoice) -> Result<Option<&'a Variant>, AppError> {
+    let found = match choice {
+        VariantChoice::Auto => return Ok(None),
+        VariantChoice::Index(index) => variants.get(*index),
+        // Several variants of the same height differ in bitrate; take the best
+        VariantChoice::Height(h) => variants.iter()
+            .filter(|v| height(v) == Some(*h))
+            .max_by_key(|v| v.bandwidth),
+        // Relative URIs as written in the playlist match too
+        VariantChoice::Uri(uri) => variants.iter()
+            .find(|v| v.uri == *uri || v.uri.ends_with(&format!("/{}", uri.trim_start_matches("./")))),
+    };
+    found.map(Some).ok_or_else(|| AppError::invalid_input(format!(
+        "No variant matches {:?}; the playlist has {} variants",
+        choice,
+        variants.len()
+    )))
+}
+
+// Structured answer asking the caller to pick a variant
+pub fn selection_request(url: &str, variants: &[Variant]) -> Value {
+    let listed: Vec<Value> = variants.iter()
+        .enumerate()
+        .map(|(index, v)| json!({
+            "index": index,
+            "uri": v.uri,
+            "bandwidth": v.bandwidth,
+            "resolution": v.resolution,
+            "codecs": v.codecs,
+            "frame_rate": v.frame_rate
+        }))
+        .collect();
+    json!({
+        "status": "variant_selection_required",
+        "url": url,
+        "message": "This is a master playlist. Call m3u8_download again with `variant` set to an index, a height such as \"720p\", a variant URI, or \"auto\" for the highest bandwidth.",
+        "variants": listed
+    })
+}
+
+// Variants of `url` when it is a master playlist, None for anything else
+pub async fn master_variants(parser: &M3u8Parser, url: &str) -> Result<Option<Vec<Variant>>, AppError> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Ok(None);
+    }
+    match parser.parse_url(url).await? {
+        ParsedPlaylist::Master { variants, .. } if !variants.is_empty() => Ok(Some(variants)),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variant(uri: &str, bandwidth: u64, resolution: &str) -> Variant {
+        Variant {
+            uri: uri.to_string(),
+            bandwidth,
+            resolution: Some(resolution.to_string()),
+            codecs: None,
+            frame_rate: None,
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let variants = vec![
+            variant("https://cdn.example.com/1080p.m3u8", 6_000_000, "1920x1080"),
+            variant("https://cdn.example.com/720p_hi.m3u8", 3_500_000, "1280x720"),
+            variant("https://cdn.example.com/720p.m3u8", 2_500_000, "1280x720"),
+        ];
+        let pick = |value: Value| select(&variants, &parse_choice(&value).unwrap()).unwrap().map(|v| v.uri.as_str());
+
+        assert_eq!(pick(json!("auto")), None);
+        assert_eq!(pick(json!(2)), Some("https://cdn.example.com/720p.m3u8"));
+        assert_eq!(pick(json!("720p")), Some("https://cdn.example.com/720p_hi.m3u8"));
+        assert_eq!(pick(json!("1080p.m3u8")), Some("https://cdn.example.com/1080p.m3u8"));
+        assert!(select(&variants, &VariantChoice::Index(3)).is_err());
+        assert!(parse_choice(&json!("")).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::AppError;
+use crate::ffmpeg_wrapper::FFmpegService;
+use crate::m3u8_parser::{self, M3u8Parser};
+
+// Video stream properties from ffprobe that the HLS attributes describe
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VideoMetadata {
+    pub codec: Option<String>,
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+    pub frame_rate: Option<f64>,
+    // "progressive", or "tt"/"bb"/"tb"/"bt" for interlaced video
+    pub field_order: Option<String>,
+    pub interlaced: bool,
+    pub pixel_format: Option<String>,
+    pub bit_depth: Option<u32>,
+    pub color_primaries: Option<String>,
+    pub color_transfer: Option<String>,
+    pub color_space: Option<String>,
+    // VIDEO-RANGE the stream should be labelled with: SDR, PQ or HLG
+    pub dynamic_range: &'static str,
+}
+
+// A probed variant, and whether its VIDEO-RANGE matches the stream
+#[derive(Debug, Serialize)]
+pub struct VariantVideo {
+    pub uri: String,
+    pub video_range: Option<String>,
+    pub video: Option<VideoMetadata>,
+    pub error: Option<String>,
+    pub mismatch: Option<String>,
+}
+
+// "30000/1001" -> 29.97
+fn parse_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let (num, den): (f64, f64) = (num.parse().ok()?, den.parse().ok()?);
+    (num > 0.0 && den > 0.0).then(|| (num / den * 1000.0).round() / 1000.0)
+}
+
+// Bits per sample of a pixel format: the digits after the last 'p', as in
+// yuv420p10le or p010le; 8 for formats like yuv420p or nv12
+fn pixel_format_depth(pix_fmt: &str) -> u32 {
+    let name = pix_fmt.trim_end_matches("le").trim_end_matches("be");
+    let digits = name.len() - name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (prefix, depth) = name.split_at(name.len() - digits);
+    match depth.parse() {
+        Ok(depth) if prefix.ends_with('p') && depth >= 8 => depth,
+        _ => 8,
+    }
+}
+
+// Bits per sample, from bits_per_raw_sample or the pixel format
+fn bit_depth(stream: &Value) -> Option<u32> {
+    if let Some(bits) = stream["bits_per_raw_sample"].as_str().and_then(|b| b.parse().ok()) {
+        return Some(bits);
+    }
+    stream["pix_fmt"].as_str().map(pixel_format_depth)
+}
+
+// Transfer characteristics as ffprobe names them
+pub fn dynamic_range(color_transfer: Option<&str>) -> &'static str {
+    match color_transfer {
+        Some("smpte2084") => "PQ",
+        Some("arib-std-b67") => "HLG",
+        _ => "SDR",
+    }
+}
+
+// The first video stream of ffprobe's -show_streams JSON output
+pub fn video_metadata_from_probe(probe_json: &str) -> Option<VideoMetadata> {
+    let value: Value = serde_json::from_str(probe_json).ok()?;
+    let stream = value["streams"].as_array()?
+        .iter()
+        .find(|s| s["codec_type"].as_str() == Some("video"))?;
+    let text = |key: &str| stream[key].as_str().filter(|s| !s.is_empty() && *s != "unknown").map(str::to_string);
+
+    let field_order = text("field_order");
+    let color_transfer = text("color_transfer");
+    Some(VideoMetadata {
+        codec: text("codec_name"),
+        width: stream["width"].as_u64(),
+        height: stream["height"].as_u64(),
+        frame_rate: stream["avg_frame_rate"].as_str().and_then(parse_rate)
+            .or_else(|| stream["r_frame_rate"].as_str().and_then(parse_rate)),
+        interlaced: field_order.as_deref().is_some_and(|f| f != "progressive"),
+        field_order,
+        pixel_format: text("pix_fmt"),
+        bit_depth: bit_depth(stream),
+        color_primaries: text("color_primaries"),
+        dynamic_range: dynamic_range(color_transfer.as_deref()),
+        color_transfer,
+        color_space: text("color_space"),
+    })
+}
+
+// A variant without VIDEO-RANGE is SDR (RFC 8216bis, section 4.4.6.2)
+pub fn range_mismatch(video_range: Option<&str>, video: &VideoMetadata) -> Option<String> {
+    let declared = video_range.unwrap_or("SDR");
+    (declared != video.dynamic_range).then(|| match video_range {
+        Some(range) => format!("VIDEO-RANGE is {} but the stream is {}", range, video.dynamic_range),
+        None => format!("VIDEO-RANGE is missing (SDR) but the stream is {}", video.dynamic_range),
+    })
+}
+
+async fn probe_video(ffmpeg: &FFmpegService, input: &str) -> Result<Option<VideoMetadata>, AppError> {
+    let output = ffmpeg.probe_stream(input).await?;
+    Ok(video_metadata_from_probe(&output))
+}
+
+// Probe a stream or file. For a master playlist every variant is probed and
+// compared with its VIDEO-RANGE; anything else yields a single entry.
+pub async fn probe_variants(
+    parser: &M3u8Parser,
+    ffmpeg: &FFmpegService,
+    input: &str,
+) -> Result<Vec<VariantVideo>, AppError> {
+    let content = if input.contains("://") {
+        parser.fetch_playlist(input).await.ok()
+    } else {
+        None
+    };
+    let Some(content) = content.filter(|c| c.contains("#EXT-X-STREAM-INF:")) else {
+        let video = probe_video(ffmpeg, input).await?;
+        return Ok(vec![VariantVideo {
+            uri: input.to_string(),
+            video_range: None,
+            video,
+            error: None,
+            mismatch: None,
+        }]);
+    };
+
+    let variants = match parser.parse_content(&content, input)? {
+        m3u8_parser::ParsedPlaylist::Master { variants, .. } => variants,
+        m3u8_parser::ParsedPlaylist::Media { .. } => Vec::new(),
+    };
+    let attributes = m3u8_parser::variant_attributes(&content, input);
+
+    let mut results = Vec::with_capacity(variants.len());
+    for (variant, attributes) in variants.iter().zip(attributes) {
+        let video_range = attributes.into_iter()
+            .find(|(name, _)| name == "VIDEO-RANGE")
+            .map(|(_, value)| value);
+        let (video, error) = match probe_video(ffmpeg, &variant.uri).await {
+            Ok(video) => (video, None),
+            Err(e) => (None, Some(e.message)),
+        };
+        let mismatch = video.as_ref().and_then(|video| range_mismatch(video_range.as_deref(), video));
+        results.push(VariantVideo {
+            uri: variant.uri.clone(),
+            video_range,
+            video,
+            error,
+            mismatch,
+        });
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_video_metadata_from_probe() {
+        let probe = r#"{"streams": [
+            {"codec_type": "audio", "codec_name": "aac"},
+            {"codec_type": "video", "codec_name": "hevc", "width": 3840, "height": 2160,
+             "pix_fmt": "yuv420p10le", "field_order": "progressive", "avg_frame_rate": "60000/1001",
+             "color_primaries": "bt2020", "color_transfer": "smpte2084", "color_space": "bt2020nc"}
+        ]}"#;
+        let video = video_metadata_from_probe(probe).unwrap();
+        assert_eq!(video.frame_rate, Some(59.94));
+        assert_eq!(video.bit_depth, Some(10));
+        assert!(!video.interlaced);
+        assert_eq!(video.dynamic_range, "PQ");
+        assert_eq!(range_mismatch(Some("PQ"), &video), None);
+        assert_eq!(
+            range_mismatch(None, &video).as_deref(),
+            Some("VIDEO-RANGE is missing (SDR) but the stream is PQ")
+        );
+
+        let interlaced = r#"{"streams": [{"codec_type": "video", "field_order": "tt", "pix_fmt": "yuv420p", "color_transfer": "bt709"}]}"#;
+        let video = video_metadata_from_probe(interlaced).unwrap();
+        assert!(video.interlaced);
+        assert_eq!(video.bit_depth, Some(8));
+        assert_eq!(video.dynamic_range, "SDR");
+
+        assert_eq!(pixel_format_depth("yuv420p"), 8);
+        assert_eq!(pixel_format_depth("yuv410p"), 8);
+        assert_eq!(pixel_format_depth("yuv420p10le"), 10);
+        assert_eq!(pixel_format_depth("yuv444p12be"), 12);
+        assert_eq!(pixel_format_depth("p010le"), 10);
+        assert_eq!(pixel_format_depth("nv12"), 8);
+        assert_eq!(parse_rate("30000/1001"), Some(29.97));
+        assert_eq!(parse_rate("0/0"), None);
+    }
+}
@@ -0,0 +1,170 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::ffmpeg_wrapper::{FFmpegService, PackagingOptions};
+use crate::native_download::{self, IntegrityManifest};
+
+// Segment length when a recording has to be repackaged
+const SEGMENT_SECONDS: u32 = 6;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VodExport {
+    pub playlist: PathBuf,
+    // "byte_ranges" (segments of the recording itself) or "repackaged"
+    pub method: String,
+    // Segments and duration of a byte-range playlist; FFmpeg decides them
+    // when repackaging
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segments: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+}
+
+fn is_ts(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("ts"))
+}
+
+// VOD playlist over a transport stream recorded by the native downloader:
+// each recorded segment becomes a byte range of `file_name`, numbered from 0,
+// with a discontinuity wherever the live window skipped segments or the
+// download switched variants. None when the manifest predates per-segment
+// output sizes.
+pub fn byte_range_playlist(manifest: &IntegrityManifest, file_name: &str) -> Option<String> {
+    if manifest.segments.is_empty() {
+        return None;
+    }
+    let max_duration = manifest.segments.iter().map(|s| s.duration as f64).fold(0.0, f64::max);
+    let mut lines = vec![
+        "#EXTM3U".to_string(),
+        // EXT-X-BYTERANGE needs version 4
+        "#EXT-X-VERSION:4".to_string(),
+        "#EXT-X-PLAYLIST-TYPE:VOD".to_string(),
+        format!("#EXT-X-TARGETDURATION:{}", (max_duration.round() as u64).max(1)),
+        "#EXT-X-MEDIA-SEQUENCE:0".to_string(),
+    ];
+    let mut offset = 0u64;
+    let mut previous: Option<u64> = None;
+    for (index, segment) in manifest.segments.iter().enumerate() {
+        let length = segment.written?;
+        let switched = manifest.variant_switches.iter().any(|switch| switch.segment == index);
+        if switched || previous.is_some_and(|sequence| segment.sequence != sequence + 1) {
+            lines.push("#EXT-X-DISCONTINUITY".to_string());
+        }
+        lines.push(format!("#EXTINF:{:.3},", segment.duration));
+        lines.push(format!("#EXT-X-BYTERANGE:{}@{}", length, offset));
+        lines.push(file_name.to_string());
+        offset += length;
+        previous = Some(segment.sequence);
+    }
+    lines.push("#EXT-X-ENDLIST".to_string());
+    Some(lines.join("\n") + "\n")
+}
+
+// Write <recording>.m3u8 next to a .ts recording; None when the recording
+// is in another container or its manifest lacks the sizes
+pub fn write_byte_range_playlist(manifest: &IntegrityManifest, recording: &Path) -> Result<Option<PathBuf>, AppError> {
+    let Some(file_name) = recording.file_name().and_then(|n| n.to_str()) else {
+        return Ok(None);
+    };
+    if !is_ts(recording) {
+        return Ok(None);
+    }
+    let Some(content) = byte_range_playlist(manifest, file_name) else {
+        return Ok(None);
+    };
+    let playlist = recording.with_extension("m3u8");
+    std::fs::write(&playlist, content)
+        .map_err(|e| AppError::io(format!("Failed to write {}: {}", playlist.display(), e)))?;
+    Ok(Some(playlist))
+}
+
+// Make a recording playable as VOD. Native .ts recordings get a byte-range
+// playlist without copying any media; anything else is repackaged by FFmpeg
+// into <name>_vod/ with a VOD playlist.
+pub async fn export(ffmpeg: &FFmpegService, job_id: &str, recording: &Path) -> Result<VodExport, AppError> {
+    if !recording.is_file() {
+        return Err(AppError::not_found(format!("Recording not found: {}", recording.display())));
+    }
+
+    let manifest = std::fs::read_to_string(native_download::manifest_path(recording))
+        .ok()
+        .and_then(|json| serde_json::from_str::<IntegrityManifest>(&json).ok());
+    if let Some(manifest) = &manifest {
+        if let Some(playlist) = write_byte_range_playlist(manifest, recording)? {
+            return Ok(VodExport {
+                playlist,
+                method: "byte_ranges".to_string(),
+                segments: Some(manifest.segments.len()),
+                duration: Some(manifest.segments.iter().map(|s| s.duration as f64).sum()),
+            });
+        }
+    }
+
+    let stem = recording.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+    let output_dir = recording.with_file_name(format!("{}_vod", stem));
+    let options = PackagingOptions {
+        playlist_type: Some("vod".to_string()),
+        ..Default::default()
+    };
+    let output = ffmpeg.convert_to_hls(job_id, recording, &output_dir, SEGMENT_SECONDS, &options).await?;
+    crate::artifacts::record_packaged(job_id, &output, false).await;
+    Ok(VodExport {
+        playlist: output.playlist.unwrap_or_else(|| output_dir.join("playlist.m3u8")),
+        method: "repackaged".to_string(),
+        segments: None,
+        duration: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::native_download::{ManifestSegment, VariantSwitch};
+
+    fn segment(index: usize, sequence: u64, written: u64) -> ManifestSegment {
+        ManifestSegment {
+            index,
+            sequence,
+            url: format!("https://example.com/live/{}.ts", sequence),
+            byte_range: None,
+            duration: 6.0,
+            size: written + 16,
+            sha256: String::new(),
+            written: Some(written),
+            key: None,
+            fetched_from: None,
+        }
+    }
+
+    #[test]
+    fn test_byte_range_playlist() {
+        let mut manifest = IntegrityManifest {
+            version: 1,
+            playlist_url: "https://example.com/live/index.m3u8".to_string(),
+            media_playlist_url: "https://example.com/live/index.m3u8".to_string(),
+            output: "/videos/live.ts".to_string(),
+            created_at: String::new(),
+            total_bytes: 0,
+            live: true,
+            segments: vec![segment(0, 1040, 188), segment(1, 1041, 376), segment(2, 1045, 188)],
+            variant_switches: Vec::new(),
+            gaps: Vec::new(),
+        };
+        let playlist = byte_range_playlist(&manifest, "live.ts").unwrap();
+        assert!(playlist.contains("#EXT-X-MEDIA-SEQUENCE:0\n"));
+        assert!(playlist.contains("#EXT-X-BYTERANGE:376@188\nlive.ts\n#EXT-X-DISCONTINUITY\n#EXTINF:6.000,\n#EXT-X-BYTERANGE:188@564\n"));
+        assert!(playlist.ends_with("#EXT-X-ENDLIST\n"));
+
+        manifest.variant_switches.push(VariantSwitch {
+            segment: 1,
+            from: "a".to_string(),
+            to: "b".to_string(),
+            reason: String::new(),
+        });
+        assert_eq!(byte_range_playlist(&manifest, "live.ts").unwrap().matches("#EXT-X-DISCONTINUITY").count(), 2);
+
+        manifest.segments[1].written = None;
+        assert_eq!(byte_range_playlist(&manifest, "live.ts"), None);
+    }
+}
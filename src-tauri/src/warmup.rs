@@ -0,0 +1,111 @@
+use futures::StreamExt;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::database::Database;
+use crate::error::AppError;
+use crate::ffmpeg_wrapper::FFmpegService;
+
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+// ffprobe can hang on servers that accept the connection but never answer
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Only one warm-up runs at a time
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+// Outcome of probing one saved URL
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkCheck {
+    pub url: String,
+    pub alive: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmupSummary {
+    pub total: usize,
+    pub alive: usize,
+    pub dead: usize,
+}
+
+// Clears RUNNING when the warm-up ends, even by a panic
+struct RunningFlag;
+
+impl Drop for RunningFlag {
+    fn drop(&mut self) {
+        RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+// Probe one URL, caching the result on success
+async fn check_url(db: &Database, ffmpeg: &FFmpegService, url: String) -> LinkCheck {
+    let error = match tokio::time::timeout(PROBE_TIMEOUT, ffmpeg.probe_stream(&url)).await {
+        Ok(Ok(info)) => {
+            let format_name = serde_json::from_str::<serde_json::Value>(&info)
+                .ok()
+                .and_then(|v| v["format"]["format_name"].as_str().map(|s| s.to_string()))
+                .unwrap_or_default();
+            if let Err(e) = db.cache_probe_result(&url, &format_name, &info) {
+                eprintln!("Failed to cache probe result for {}: {}", url, e);
+            }
+            None
+        }
+        Ok(Err(e)) => Some(e.to_string().trim().to_string()),
+        Err(_) => Some(format!("Probe timed out after {}s", PROBE_TIMEOUT.as_secs())),
+    };
+
+    if let Err(e) = db.set_link_status(&url, error.is_none(), error.as_deref()) {
+        eprintln!("Failed to record link status for {}: {}", url, e);
+    }
+    LinkCheck { url, alive: error.is_none(), error }
+}
+
+// Re-probe `urls` with at most `concurrency` ffprobe processes at once,
+// refreshing the probe cache and the liveness of each URL. `on_check` is
+// called as each probe finishes.
+pub async fn warm_probe_cache<F>(
+    db: Arc<Database>,
+    ffmpeg: Arc<FFmpegService>,
+    mut urls: Vec<String>,
+    concurrency: usize,
+    on_check: F,
+) -> Result<WarmupSummary, AppError>
+where
+    F: Fn(&LinkCheck, usize, usize),
+{
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(AppError::conflict("A probe cache warm-up is already running"));
+    }
+    let _running = RunningFlag;
+
+    urls.sort();
+    urls.dedup();
+    let total = urls.len();
+    let mut summary = WarmupSummary { total, alive: 0, dead: 0 };
+
+    let mut checks = futures::stream::iter(urls)
+        .map(|url| {
+            let db = db.clone();
+            let ffmpeg = ffmpeg.clone();
+            async move { check_url(&db, &ffmpeg, url).await }
+        })
+        .buffer_unordered(concurrency.max(1));
+
+    while let Some(check) = checks.next().await {
+        if check.alive {
+            summary.alive += 1;
+        } else {
+            summary.dead += 1;
+        }
+        on_check(&check, summary.alive + summary.dead, total);
+    }
+
+    Ok(summary)
+}
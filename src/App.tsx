@@ -1,10 +1,23 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { open } from "@tauri-apps/plugin-dialog";
+import { errorMessage } from "./appError";
+import { message, open } from "@tauri-apps/plugin-dialog";
 import { M3u8Form } from "./components/M3u8Form";
-import { t, Language } from "./i18n";
+import { t, tWithParams, Language } from "./i18n";
 import "./App.css";
 
+interface OutputDirInfo {
+  path: string;
+  exists: boolean;
+  created: boolean;
+  free_bytes: number;
+  total_bytes: number;
+}
+
+function formatGigabytes(bytes: number): string {
+  return `${(bytes / 1024 / 1024 / 1024).toFixed(1)} GB`;
+}
+
 function App() {
   const [mcpServerRunning, setMcpServerRunning] = useState(false);
   const [mcpServerMessage, setMcpServerMessage] = useState("");
@@ -16,6 +29,8 @@ function App() {
   
   // FFmpeg configuration
   const [ffmpegPath, setFfmpegPath] = useState<string>("ffmpeg");
+  const [outputDirInfo, setOutputDirInfo] = useState<OutputDirInfo | null>(null);
+  const [outputDirMessage, setOutputDirMessage] = useState<string>("");
   
   // Cache state
   const [databaseInitialized, setDatabaseInitialized] = useState(false);
@@ -74,17 +89,56 @@ function App() {
 
   async function loadM3u8Configuration() {
     try {
-      const config = await invoke<{ ffmpeg_path: string | null; output_dir: string }>(
+      const config = await invoke<{ ffmpeg_path: string | null; output_dir: string; locale?: Language }>(
         "load_m3u8_config",
       );
       if (config) {
         setFfmpegPath(config.ffmpeg_path || "ffmpeg");
+        if (config.locale) {
+          setLanguage(config.locale);
+        }
+        showCrashReports(config.locale ?? 'en');
       }
     } catch (error) {
       console.error("Failed to load m3u8 configuration:", error);
     }
   }
 
+  // Tell the user about crashes since the last launch, once
+  async function showCrashReports(lang: Language) {
+    try {
+      const crashes = await invoke<unknown[]>("get_crash_reports", { unseen: true });
+      if (crashes.length === 0) return;
+      await message(tWithParams(lang, 'crashReport.message', { count: crashes.length }), {
+        title: t(lang, 'crashReport.title'),
+        kind: 'warning',
+      });
+      await invoke("dismiss_crash_reports");
+    } catch (error) {
+      console.error("Failed to read crash reports:", error);
+    }
+  }
+
+
+  async function loadOutputDirInfo() {
+    try {
+      setOutputDirInfo(await invoke<OutputDirInfo>("get_output_dir_info"));
+    } catch (error) {
+      setOutputDirMessage(errorMessage(error, "Failed to read the output directory"));
+    }
+  }
+
+  async function chooseOutputDir() {
+    try {
+      const info = await invoke<OutputDirInfo | null>("choose_output_dir");
+      if (info) {
+        setOutputDirInfo(info);
+        setOutputDirMessage(info.created ? t(language, 'mcpServer.outputDirCreated') : "");
+      }
+    } catch (error) {
+      setOutputDirMessage(errorMessage(error, "Failed to set the output directory"));
+    }
+  }
 
   // async function _saveM3u8Configuration() {
   //   try {
@@ -118,13 +172,38 @@ function App() {
           enabledTools: [
             "m3u8_set_url",
             "m3u8_get_url",
+            "m3u8_url_back",
+            "m3u8_url_forward",
+            "m3u8_pin_url",
             "m3u8_parse",
             "m3u8_download",
             "m3u8_convert",
             "m3u8_probe",
             "m3u8_extract_segments",
+            "m3u8_stats",
+            "m3u8_compatibility",
+            "m3u8_loudness",
+            "m3u8_scenes",
+            "m3u8_grab_frame",
+            "m3u8_report",
+            "m3u8_transcribe",
+            "m3u8_chapters",
+            "m3u8_cache_stats",
+            "m3u8_resume_jobs",
+            "m3u8_generate_master",
+            "m3u8_repair",
+            "m3u8_ffmpeg_command",
+            "m3u8_export_vod",
+            "m3u8_interstitials",
+            "m3u8_graph",
+            "m3u8_abr_simulate",
+            "m3u8_detect_stream",
+            "m3u8_archive_list",
+            "m3u8_archive_reconstruct",
+            "m3u8_archive_delete",
             "m3u8_cache_list",
-            "m3u8_cache_clear"
+            "m3u8_cache_clear",
+            "m3u8_cache_prune"
           ]
         });
         setMcpServerRunning(true);
@@ -132,7 +211,7 @@ function App() {
         setMcpServerMessage("");
       }
     } catch (error) {
-      setMcpServerMessage(`Error: ${error}`);
+      setMcpServerMessage(`Error: ${errorMessage(error, "Unknown error")}`);
     }
   }
 
@@ -215,7 +294,12 @@ function App() {
   const [language, setLanguage] = useState<Language>('en');
 
   const toggleLanguage = () => {
-    setLanguage((prev) => prev === 'en' ? 'ja' : 'en');
+    const next: Language = language === 'en' ? 'ja' : 'en';
+    setLanguage(next);
+    // Backend status and error messages follow the UI language
+    invoke("set_locale", { locale: next }).catch((error) => {
+      console.error("Failed to save language:", error);
+    });
   };
 
   return (
@@ -242,6 +326,7 @@ function App() {
                   <button
                     onClick={() => {
                       setFfmpegConfigModalOpen(true);
+                      loadOutputDirInfo();
                       setMenuOpen(false);
                     }}
                     className="w-full text-left px-4 py-2 text-sm text-gray-700 dark:text-gray-300 hover:bg-gray-100 dark:hover:bg-gray-700 transition-colors"
@@ -333,6 +418,34 @@ function App() {
                     className="w-full px-3 py-2 rounded-md border border-gray-300 dark:border-gray-600 focus:ring-2 focus:ring-blue-500 focus:outline-none dark:bg-gray-700 dark:text-white"
                   />
                 </div>
+
+                <div>
+                  <label className="block text-sm font-medium text-gray-600 dark:text-gray-400 mb-1">
+                    {t(language, 'mcpServer.outputDir')}
+                  </label>
+                  <div className="flex gap-2">
+                    <input
+                      type="text"
+                      value={outputDirInfo?.path ?? ""}
+                      readOnly
+                      className="flex-1 px-3 py-2 rounded-md border border-gray-300 dark:border-gray-600 bg-gray-50 dark:bg-gray-700 dark:text-white"
+                    />
+                    <button
+                      onClick={chooseOutputDir}
+                      className="px-4 py-2 bg-blue-500 hover:bg-blue-600 text-white rounded-lg font-medium transition-colors"
+                    >
+                      {t(language, 'mcpServer.browse')}
+                    </button>
+                  </div>
+                  {outputDirInfo && (
+                    <p className="mt-1 text-xs text-gray-500 dark:text-gray-400">
+                      {formatGigabytes(outputDirInfo.free_bytes)} {t(language, 'mcpServer.freeSpace')} {formatGigabytes(outputDirInfo.total_bytes)}
+                    </p>
+                  )}
+                  {outputDirMessage && (
+                    <p className="mt-1 text-xs text-gray-600 dark:text-gray-300">{outputDirMessage}</p>
+                  )}
+                </div>
                 
                 <div className="flex justify-end gap-3 pt-4">
                   <button
@@ -0,0 +1,18 @@
+// Mirrors the AppError struct returned by every Tauri command (src-tauri/src/error.rs)
+export interface AppError {
+  kind: string;
+  message: string;
+  details?: unknown;
+  retryable: boolean;
+}
+
+export function isAppError(err: unknown): err is AppError {
+  return typeof err === 'object' && err !== null && 'kind' in err && 'message' in err;
+}
+
+export function errorMessage(err: unknown, fallback: string): string {
+  if (isAppError(err)) return err.message;
+  if (err instanceof Error) return err.message;
+  if (typeof err === 'string') return err;
+  return fallback;
+}
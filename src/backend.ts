@@ -0,0 +1,112 @@
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import type { AppError } from './appError';
+import { isSupported, type JobEvent } from './events';
+
+// Mirrors RemoteConfig in src-tauri/src/config.rs
+export interface RemoteConnection {
+  enabled: boolean;
+  base_url: string;
+  token?: string | null;
+}
+
+export type DownloadProgress = JobEvent;
+
+// Remote connection in use, or null for the in-process engine
+let remote: RemoteConnection | null = null;
+
+// Read the remote settings from the saved config
+export async function loadRemoteConnection(): Promise<RemoteConnection | null> {
+  const config = await invoke<{ remote?: RemoteConnection }>('load_m3u8_config');
+  remote = config.remote?.enabled ? config.remote : null;
+  return remote;
+}
+
+// Check and save a remote connection; disabling it switches back to the local engine
+export async function setRemoteConnection(connection: RemoteConnection): Promise<RemoteConnection> {
+  const saved = await invoke<RemoteConnection>('set_remote_connection', {
+    enabled: connection.enabled,
+    baseUrl: connection.base_url,
+    token: connection.token ?? null,
+  });
+  remote = saved.enabled ? saved : null;
+  return saved;
+}
+
+export function isRemote(): boolean {
+  return remote !== null;
+}
+
+// Call the job API of the remote instance; errors come back as AppError JSON
+async function request<T>(method: string, path: string, body?: unknown): Promise<T> {
+  const connection = remote!;
+  let response: Response;
+  try {
+    response = await fetch(`${connection.base_url}${path}`, {
+      method,
+      headers: {
+        Authorization: `Bearer ${connection.token ?? ''}`,
+        ...(body !== undefined ? { 'Content-Type': 'application/json' } : {}),
+      },
+      body: body !== undefined ? JSON.stringify(body) : undefined,
+    });
+  } catch (err) {
+    const error: AppError = {
+      kind: 'network',
+      message: `Cannot reach ${connection.base_url}: ${err}`,
+      retryable: true,
+    };
+    throw error;
+  }
+  const data = await response.json().catch(() => null);
+  if (!response.ok) {
+    throw data ?? { kind: 'network', message: `${connection.base_url} answered ${response.status}`, retryable: false };
+  }
+  return data as T;
+}
+
+export async function parseUrl<T>(url: string): Promise<T> {
+  if (remote) {
+    return request<T>('POST', '/api/parse', { url });
+  }
+  return invoke<T>('parse_m3u8_url', { url });
+}
+
+// Locally this resolves when the download finishes; remotely as soon as the
+// job is queued. Either way the outcome arrives as download-progress events.
+// Remote output paths are on the remote machine; omit them to use its output directory.
+// Fails with a "conflict" AppError when the stream is already on disk, unless forced
+export async function startDownload(url: string, outputPath?: string, force?: boolean): Promise<void> {
+  if (remote) {
+    await request<{ job_id: string }>('POST', '/api/downloads', { url, output_path: outputPath, force });
+    return;
+  }
+  await invoke<string>('download_m3u8_stream', { url, outputPath, force });
+}
+
+export async function cancelDownload(jobId?: string): Promise<void> {
+  if (remote) {
+    if (jobId) {
+      await request('DELETE', `/api/jobs/${encodeURIComponent(jobId)}`);
+    }
+    return;
+  }
+  await invoke('cancel_download', { jobId });
+}
+
+// Subscribe to download-progress from whichever engine is active, skipping
+// events of a newer version than this frontend knows
+export async function onDownloadProgress(handler: (progress: DownloadProgress) => void): Promise<() => void> {
+  const deliver = (progress: DownloadProgress) => {
+    if (isSupported(progress)) handler(progress);
+  };
+  if (remote) {
+    const token = encodeURIComponent(remote.token ?? '');
+    const source = new EventSource(`${remote.base_url}/api/events?token=${token}`);
+    source.addEventListener('download-progress', (event) => {
+      deliver(JSON.parse((event as MessageEvent).data));
+    });
+    return () => source.close();
+  }
+  return listen<DownloadProgress>('download-progress', (event) => deliver(event.payload));
+}
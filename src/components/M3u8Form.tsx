@@ -1,6 +1,9 @@
 import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { errorMessage, isAppError } from '../appError';
 import { listen } from '@tauri-apps/api/event';
+import { isRemote, loadRemoteConnection, onDownloadProgress, parseUrl, startDownload } from '../backend';
+import { isSupported, type Capture, type CaptureEvent, type WarmupEvent } from '../events';
 import { t, tWithParams, Language } from '../i18n';
 
 interface ParsedPlaylist {
@@ -36,12 +39,17 @@ export function M3u8Form({ language }: M3u8FormProps) {
   const [url, setUrl] = useState('');
   const [loading, setLoading] = useState(false);
   const [parsedData, setParsedData] = useState<ParsedPlaylist | null>(null);
+  const [streamTitle, setStreamTitle] = useState<string | null>(null);
   const [error, setError] = useState<string | null>(null);
   const [downloadStatus, setDownloadStatus] = useState<string | null>(null);
+  // Event status behind downloadStatus; messages are localized, so don't match on text
+  const [downloadStatusKind, setDownloadStatusKind] = useState<string | null>(null);
   const [downloadProgress, setDownloadProgress] = useState<string | null>(null);
   const [isDownloading, setIsDownloading] = useState(false);
-  const [urlHistory, setUrlHistory] = useState<Array<{url: string; timestamp: string}>>([]);
+  const [urlHistory, setUrlHistory] = useState<Array<{url: string; timestamp: string; title?: string}>>([]);
   const [showHistory, setShowHistory] = useState(false);
+  const [deadLinks, setDeadLinks] = useState<Record<string, string>>({});
+  const [captures, setCaptures] = useState<Capture[]>([]);
   const [extractedSegments, setExtractedSegments] = useState<string[] | null>(null);
   const [extractingSegments, setExtractingSegments] = useState(false);
   const [segmentDisplayCount, setSegmentDisplayCount] = useState(20);
@@ -52,54 +60,55 @@ export function M3u8Form({ language }: M3u8FormProps) {
 
   // Listen for download progress events
   useEffect(() => {
-    const unsubscribe = listen<{
-      status: string;
-      message: string;
-      time?: string;
-      size?: string;
-      speed?: string;
-    }>('download-progress', (event) => {
-      const { status, message, time, size, speed } = event.payload;
+    // Progress comes from the remote instance when one is configured
+    const unsubscribe = loadRemoteConnection()
+      .catch(() => null)
+      .then(() => onDownloadProgress((progress) => {
+        const { status, message } = progress;
       
-      if (status === 'progress') {
-        // Format progress message
-        let progressMsg = message;
-        if (time && size && speed) {
-          progressMsg = `Time: ${time} | Size: ${size} | Speed: ${speed}`;
-        }
-        setDownloadProgress(progressMsg);
-        setDownloadStatus(null); // Clear the initial status message
-        setIsDownloading(true);
-      } else if (status === 'completed') {
-        setDownloadStatus(message);
-        setDownloadProgress(null);
-        setLoading(false);
-        setIsDownloading(false);
-        // Clear success message after 5 seconds
-        setTimeout(() => {
-          setDownloadStatus(null);
-        }, 5000);
-      } else if (status === 'error') {
-        setError(message);
-        setDownloadProgress(null);
-        setDownloadStatus(null);
-        setLoading(false);
-        setIsDownloading(false);
-      } else if (status === 'cancelled') {
-        setDownloadStatus(message);
-        setDownloadProgress(null);
-        setLoading(false);
-        setIsDownloading(false);
-        // Clear message after 3 seconds
-        setTimeout(() => {
+        if (progress.status === 'progress') {
+          // Format progress message
+          const { time, size, speed } = progress;
+          let progressMsg = message;
+          if (time && size && speed) {
+            progressMsg = `Time: ${time} | Size: ${size} | Speed: ${speed}`;
+          }
+          setDownloadProgress(progressMsg);
+          setDownloadStatus(null); // Clear the initial status message
+          setIsDownloading(true);
+        } else if (status === 'completed') {
+          setDownloadStatus(message);
+          setDownloadStatusKind(status);
+          setDownloadProgress(null);
+          setLoading(false);
+          setIsDownloading(false);
+          // Clear success message after 5 seconds
+          setTimeout(() => {
+            setDownloadStatus(null);
+          }, 5000);
+        } else if (status === 'error') {
+          setError(message);
+          setDownloadProgress(null);
           setDownloadStatus(null);
-        }, 3000);
-      } else if (status === 'starting') {
-        setDownloadStatus(message);
-        setDownloadProgress(null);
-        setIsDownloading(true);
-      }
-    });
+          setLoading(false);
+          setIsDownloading(false);
+        } else if (status === 'cancelled') {
+          setDownloadStatus(message);
+          setDownloadStatusKind(status);
+          setDownloadProgress(null);
+          setLoading(false);
+          setIsDownloading(false);
+          // Clear message after 3 seconds
+          setTimeout(() => {
+            setDownloadStatus(null);
+          }, 3000);
+        } else if (status === 'starting') {
+          setDownloadStatus(message);
+          setDownloadStatusKind(status);
+          setDownloadProgress(null);
+          setIsDownloading(true);
+        }
+      }));
 
     return () => {
       unsubscribe.then(fn => fn());
@@ -125,7 +134,7 @@ export function M3u8Form({ language }: M3u8FormProps) {
   useEffect(() => {
     const loadHistory = async () => {
       try {
-        const history = await invoke<Array<{url: string; timestamp: string}>>('get_url_history');
+        const history = await invoke<Array<{url: string; timestamp: string; title?: string}>>('get_url_history');
         setUrlHistory(history);
       } catch (err) {
         console.error('Failed to load URL history:', err);
@@ -134,6 +143,79 @@ export function M3u8Form({ language }: M3u8FormProps) {
     loadHistory();
   }, [url]); // Reload history when URL changes
 
+  // Load liveness of saved URLs and refresh it as a warm-up reports back
+  useEffect(() => {
+    const loadLinkStatuses = async () => {
+      try {
+        const statuses = await invoke<Array<{url: string; alive: boolean; error: string | null}>>('get_link_statuses');
+        const dead: Record<string, string> = {};
+        statuses.filter(s => !s.alive).forEach(s => { dead[s.url] = s.error ?? ''; });
+        setDeadLinks(dead);
+      } catch (err) {
+        console.error('Failed to load link statuses:', err);
+      }
+    };
+    loadLinkStatuses();
+
+    const unsubscribe = listen<WarmupEvent>('probe-warmup-progress', (event) => {
+      const progress = event.payload;
+      if (!isSupported(progress) || progress.status !== 'checking') return;
+      const { url: checkedUrl, alive, error } = progress;
+      setDeadLinks(prev => {
+        const next = { ...prev };
+        if (alive) {
+          delete next[checkedUrl];
+        } else {
+          next[checkedUrl] = error ?? '';
+        }
+        return next;
+      });
+    });
+
+    return () => {
+      unsubscribe.then(fn => fn());
+    };
+  }, []);
+
+  // Load stream URLs sent by the browser extension and add new ones as they come in
+  useEffect(() => {
+    invoke<Capture[]>('list_captures')
+      .then(setCaptures)
+      .catch(err => console.error('Failed to load captures:', err));
+
+    const unsubscribe = listen<CaptureEvent>('capture-received', (event) => {
+      const capture = event.payload;
+      if (!isSupported(capture)) return;
+      setCaptures(prev => [capture, ...prev.filter(c => c.url !== capture.url)]);
+    });
+
+    return () => {
+      unsubscribe.then(fn => fn());
+    };
+  }, []);
+
+  const handleDismissCapture = async (id: string) => {
+    try {
+      await invoke('dismiss_capture', { id });
+    } catch (err) {
+      console.error('Failed to dismiss capture:', err);
+    }
+    setCaptures(prev => prev.filter(c => c.id !== id));
+  };
+
+  const handleUseCapture = (capture: Capture) => {
+    handleUrlChange(capture.url);
+    handleDismissCapture(capture.id);
+  };
+
+  const handleCheckLinks = async () => {
+    try {
+      await invoke('warm_probe_cache');
+    } catch (err) {
+      console.error('Failed to check links:', err);
+    }
+  };
+
   // Check for URL updates from MCP
   useEffect(() => {
     const checkUrl = async () => {
@@ -176,17 +258,41 @@ export function M3u8Form({ language }: M3u8FormProps) {
     setLoading(true);
     setError(null);
     setParsedData(null);
+    setStreamTitle(null);
 
     try {
-      const result = await invoke<ParsedPlaylist>('parse_m3u8_url', { url });
+      const result = await parseUrl<ParsedPlaylist>(url);
       setParsedData(result);
+      if (!isRemote()) {
+        // Metadata needs an extra fetch; the playlist is shown without waiting
+        invoke<{ title: string | null }>('get_stream_info', { url })
+          .then(info => setStreamTitle(info.title))
+          .catch(err => console.error('Failed to read stream metadata:', err));
+      }
     } catch (err) {
-      setError(err instanceof Error ? err.message : 'Failed to parse m3u8');
+      setError(errorMessage(err, 'Failed to parse m3u8'));
     } finally {
       setLoading(false);
     }
   };
 
+  // Ask before fetching a stream that is already on disk
+  const startDownloadUnlessDuplicate = async (outputPath?: string) => {
+    try {
+      await startDownload(url, outputPath);
+    } catch (err) {
+      if (!isAppError(err) || err.kind !== 'conflict') throw err;
+      const path = (err.details as { path?: string } | undefined)?.path ?? '';
+      if (!window.confirm(tWithParams(language, 'm3u8Form.alreadyDownloaded', { path }))) {
+        setLoading(false);
+        setDownloadStatus(null);
+        setDownloadStatusKind(null);
+        return;
+      }
+      await startDownload(url, outputPath, true);
+    }
+  };
+
   const handleDownload = async () => {
     if (!url.trim()) {
       setError(t(language, 'm3u8Form.noUrlError'));
@@ -199,6 +305,22 @@ export function M3u8Form({ language }: M3u8FormProps) {
       return;
     }
 
+    // A remote instance saves to its own output directory
+    if (isRemote()) {
+      setLoading(true);
+      setError(null);
+      setDownloadStatus(t(language, 'm3u8Form.initializingDownload'));
+      setDownloadStatusKind('starting');
+      setDownloadProgress(null);
+      try {
+        await startDownloadUnlessDuplicate();
+      } catch (err) {
+        setError(errorMessage(err, 'Failed to download stream'));
+        setLoading(false);
+      }
+      return;
+    }
+
     // Show folder selection dialog
     try {
       const { open } = await import('@tauri-apps/plugin-dialog');
@@ -216,6 +338,7 @@ export function M3u8Form({ language }: M3u8FormProps) {
       setLoading(true);
       setError(null);
       setDownloadStatus(t(language, 'm3u8Form.initializingDownload'));
+      setDownloadStatusKind('starting');
       setDownloadProgress(null);
 
       // Generate filename based on URL
@@ -228,15 +351,12 @@ export function M3u8Form({ language }: M3u8FormProps) {
       const fullPath = `${selectedPath}/${filename}`;
 
       // The actual progress will be handled by the event listener
-      await invoke<string>('download_m3u8_stream', { 
-        url,
-        outputPath: fullPath
-      });
+      await startDownloadUnlessDuplicate(fullPath);
       // Success is handled by the event listener
     } catch (err) {
       // Error is also handled by the event listener, but we keep this as fallback
       if (!error) {
-        setError(err instanceof Error ? err.message : 'Failed to download stream');
+        setError(errorMessage(err, 'Failed to download stream'));
       }
     }
   };
@@ -270,7 +390,7 @@ export function M3u8Form({ language }: M3u8FormProps) {
       });
       setExtractedSegments(segments);
     } catch (err) {
-      setError(err instanceof Error ? err.message : 'Failed to extract segments');
+      setError(errorMessage(err, 'Failed to extract segments'));
     } finally {
       setExtractingSegments(false);
     }
@@ -352,12 +472,20 @@ export function M3u8Form({ language }: M3u8FormProps) {
             <div className="absolute z-10 mt-1 left-0 right-0 bg-white dark:bg-gray-800 border border-gray-300 dark:border-gray-600 rounded-md shadow-lg max-h-60 overflow-auto">
               <div className="p-2 border-b border-gray-200 dark:border-gray-700 flex justify-between items-center">
                 <span className="text-sm font-medium text-gray-700 dark:text-gray-300">{t(language, 'm3u8Form.recentUrls')}</span>
-                <button
-                  onClick={handleClearHistory}
-                  className="px-2 py-1 text-xs bg-red-500 hover:bg-red-600 text-white rounded font-medium transition-colors"
-                >
-                  {t(language, 'm3u8Form.clearAll')}
-                </button>
+                <div className="flex gap-1">
+                  <button
+                    onClick={handleCheckLinks}
+                    className="px-2 py-1 text-xs bg-gray-500 hover:bg-gray-600 text-white rounded font-medium transition-colors"
+                  >
+                    {t(language, 'm3u8Form.checkLinks')}
+                  </button>
+                  <button
+                    onClick={handleClearHistory}
+                    className="px-2 py-1 text-xs bg-red-500 hover:bg-red-600 text-white rounded font-medium transition-colors"
+                  >
+                    {t(language, 'm3u8Form.clearAll')}
+                  </button>
+                </div>
               </div>
               {urlHistory.map((item, index) => (
                 <button
@@ -365,7 +493,20 @@ export function M3u8Form({ language }: M3u8FormProps) {
                   onClick={() => handleSelectHistoryUrl(item.url)}
                   className="w-full px-3 py-2 text-left hover:bg-gray-100 dark:hover:bg-gray-700 border-b border-gray-100 dark:border-gray-700 last:border-b-0"
                 >
-                  <div className="text-sm text-gray-900 dark:text-white truncate">{item.url}</div>
+                  {item.title && (
+                    <div className="text-sm font-medium text-gray-900 dark:text-white truncate">{item.title}</div>
+                  )}
+                  <div className="flex items-center gap-2">
+                    <div className={`${item.title ? 'text-xs text-gray-600 dark:text-gray-300' : 'text-sm text-gray-900 dark:text-white'} truncate`}>{item.url}</div>
+                    {item.url in deadLinks && (
+                      <span
+                        title={deadLinks[item.url]}
+                        className="shrink-0 px-1.5 py-0.5 text-xs bg-red-100 dark:bg-red-900/30 text-red-700 dark:text-red-400 rounded"
+                      >
+                        {t(language, 'm3u8Form.linkDead')}
+                      </span>
+                    )}
+                  </div>
                   <div className="text-xs text-gray-500 dark:text-gray-400">
                     {new Date(item.timestamp).toLocaleString()}
                   </div>
@@ -375,6 +516,39 @@ export function M3u8Form({ language }: M3u8FormProps) {
           )}
         </div>
 
+        {captures.length > 0 && (
+          <div className="border border-gray-300 dark:border-gray-600 rounded-md">
+            <div className="px-3 py-2 border-b border-gray-200 dark:border-gray-700 text-sm font-medium text-gray-700 dark:text-gray-300">
+              {t(language, 'm3u8Form.captures')}
+            </div>
+            {captures.map((capture) => (
+              <div
+                key={capture.id}
+                className="px-3 py-2 flex items-center gap-2 border-b border-gray-100 dark:border-gray-700 last:border-b-0"
+              >
+                <div className="min-w-0 flex-1">
+                  {capture.page_title && (
+                    <div className="text-sm font-medium text-gray-900 dark:text-white truncate">{capture.page_title}</div>
+                  )}
+                  <div className="text-xs text-gray-600 dark:text-gray-300 truncate">{capture.url}</div>
+                </div>
+                <button
+                  onClick={() => handleUseCapture(capture)}
+                  className="px-2 py-1 text-xs bg-blue-500 hover:bg-blue-600 text-white rounded font-medium transition-colors"
+                >
+                  {t(language, 'm3u8Form.useCapture')}
+                </button>
+                <button
+                  onClick={() => handleDismissCapture(capture.id)}
+                  className="px-2 py-1 text-xs bg-gray-500 hover:bg-gray-600 text-white rounded font-medium transition-colors"
+                >
+                  {t(language, 'm3u8Form.dismissCapture')}
+                </button>
+              </div>
+            ))}
+          </div>
+        )}
+
         <div className="flex gap-3">
           <button
             onClick={handleParse}
@@ -410,9 +584,9 @@ export function M3u8Form({ language }: M3u8FormProps) {
 
         {downloadStatus && (
           <div className={`p-3 rounded-md border ${
-            downloadStatus.includes('completed') 
+            downloadStatusKind === 'completed' 
               ? 'bg-green-100 dark:bg-green-900/30 border-green-400 dark:border-green-700 text-green-700 dark:text-green-400'
-              : downloadStatus.includes('cancelled')
+              : downloadStatusKind === 'cancelled'
               ? 'bg-yellow-100 dark:bg-yellow-900/30 border-yellow-400 dark:border-yellow-700 text-yellow-700 dark:text-yellow-400'
               : 'bg-blue-100 dark:bg-blue-900/30 border-blue-400 dark:border-blue-700 text-blue-700 dark:text-blue-400'
           }`}>
@@ -444,6 +618,13 @@ export function M3u8Form({ language }: M3u8FormProps) {
             </div>
             
             <div className="space-y-2 text-sm">
+              {streamTitle && (
+                <div>
+                  <span className="font-medium text-gray-600 dark:text-gray-400">{t(language, 'm3u8Form.streamTitle')}:</span>{' '}
+                  <span className="text-gray-900 dark:text-white">{streamTitle}</span>
+                </div>
+              )}
+
               {parsedData.version && (
                 <div>
                   <span className="font-medium text-gray-600 dark:text-gray-400">{t(language, 'm3u8Form.version')}:</span>{' '}
@@ -0,0 +1,73 @@
+import type { AppError } from './appError';
+
+// Mirrors the event payloads in src-tauri/src/events.rs. Fields may be added
+// to a version; removing, renaming or redefining one raises it.
+export const EVENT_VERSION = 1;
+
+// Events from an engine newer than this frontend are skipped rather than
+// misread. Payloads from before versioning have no version at all.
+export function isSupported(payload: { version?: number }): boolean {
+  return (payload.version ?? EVENT_VERSION) <= EVENT_VERSION;
+}
+
+// Mirrors RecordingGap in src-tauri/src/native_download.rs
+export interface RecordingGap {
+  sequence: number;
+  segments: number;
+  position: number;
+  duration: number;
+  program_date_time?: string;
+  error: string;
+}
+
+export type JobUpdate =
+  | { status: 'starting' }
+  | {
+      status: 'progress';
+      time?: string;
+      size?: string;
+      speed?: string;
+      segments_done?: number;
+      segments_total?: number;
+      bytes?: number;
+      fetcher?: string;
+    }
+  | { status: 'retrying'; attempt: number; max_attempts: number; delay_seconds: number; error: AppError }
+  | { status: 'gap'; sequence: number; position: number }
+  | { status: 'url_refreshed'; url: string }
+  | { status: 'variant_switched'; from: string; to: string }
+  | { status: 'completed'; path?: string; gaps?: RecordingGap[] }
+  | { status: 'error'; error?: AppError; log_tail?: string[] }
+  | { status: 'cancelled' };
+
+// download-progress, convert-progress and normalize-progress
+export type JobEvent = {
+  version: number;
+  job_id: string;
+  message: string;
+} & JobUpdate;
+
+// thumbnail-progress
+export type ThumbnailEvent = { version: number; path: string } & (
+  | { status: 'completed'; thumbnail: string; preview: string | null }
+  | { status: 'error'; message: string }
+);
+
+// probe-warmup-progress
+export type WarmupEvent = { version: number } & (
+  | { status: 'checking'; url: string; alive: boolean; error: string | null; done: number; total: number }
+  | { status: 'completed'; summary: { total: number; alive: number; dead: number } }
+);
+
+// Mirrors Capture in src-tauri/src/captures.rs
+export interface Capture {
+  id: string;
+  url: string;
+  headers: Record<string, string>;
+  page_title: string | null;
+  page_url: string | null;
+  received_at: string;
+}
+
+// capture-received
+export type CaptureEvent = { version: number } & Capture;
@@ -24,8 +24,14 @@ export const translations = {
       urlHistory: "URL History",
       recentUrls: "Recent URLs",
       clearAll: "Clear All",
+      checkLinks: "Check Links",
+      linkDead: "Offline",
+      captures: "From the browser",
+      useCapture: "Use",
+      dismissCapture: "Dismiss",
       noUrlError: "Please enter a valid m3u8 URL",
       parsedPlaylist: "Parsed Playlist",
+      streamTitle: "Title",
       version: "Version",
       targetDuration: "Target Duration",
       variants: "Variants",
@@ -41,6 +47,7 @@ export const translations = {
       downloadCompleted: "Download completed",
       downloadCancelled: "Download cancelled",
       initializingDownload: "Initializing download...",
+      alreadyDownloaded: "This stream was already downloaded to {path}. Download it again?",
       cancellingDownload: "Cancelling download...",
       extractSegmentsButton: "Extract Segments",
       extracting: "Extracting...",
@@ -61,6 +68,10 @@ export const translations = {
       title: "MCP Server Control",
       ffmpegConfig: "FFmpeg Configuration",
       ffmpegPath: "FFmpeg Path",
+      outputDir: "Output Directory",
+      browse: "Browse…",
+      freeSpace: "free of",
+      outputDirCreated: "Created the directory",
       connectWith: "Connect with:",
       claudeCode: "Claude Code",
       claudeDesktop: "Claude Desktop",
@@ -93,6 +104,12 @@ export const translations = {
       clearSuccess: "Cache cleared successfully",
       clearError: "Failed to clear cache",
     },
+    
+    // Crash report shown on launch
+    crashReport: {
+      title: "Crash Report",
+      message: "m3u8 MCP crashed {count} time(s) since it was last used. Details are in ~/.m3u8-mcp/crashes.",
+    },
   },
   ja: {
     // App title
@@ -119,8 +136,14 @@ export const translations = {
       urlHistory: "URL履歴",
       recentUrls: "最近のURL",
       clearAll: "すべてクリア",
+      checkLinks: "リンクを確認",
+      linkDead: "オフライン",
+      captures: "ブラウザから",
+      useCapture: "使用",
+      dismissCapture: "削除",
       noUrlError: "有効なm3u8 URLを入力してください",
       parsedPlaylist: "解析済みプレイリスト",
+      streamTitle: "タイトル",
       version: "バージョン",
       targetDuration: "ターゲット時間",
       variants: "バリアント",
@@ -136,6 +159,7 @@ export const translations = {
       downloadCompleted: "ダウンロード完了",
       downloadCancelled: "ダウンロードをキャンセルしました",
       initializingDownload: "ダウンロードを初期化しています...",
+      alreadyDownloaded: "このストリームは {path} にダウンロード済みです。もう一度ダウンロードしますか？",
       cancellingDownload: "ダウンロードをキャンセル中...",
       extractSegmentsButton: "セグメント抽出",
       extracting: "抽出中...",
@@ -156,6 +180,10 @@ export const translations = {
       title: "MCP サーバー制御",
       ffmpegConfig: "FFmpeg 設定",
       ffmpegPath: "FFmpeg パス",
+      outputDir: "出力フォルダ",
+      browse: "参照…",
+      freeSpace: "空き / 全体",
+      outputDirCreated: "フォルダを作成しました",
       connectWith: "接続方法:",
       claudeCode: "Claude Code",
       claudeDesktop: "Claude Desktop",
@@ -188,6 +216,12 @@ export const translations = {
       clearSuccess: "キャッシュを正常にクリアしました",
       clearError: "キャッシュのクリアに失敗しました",
     },
+    
+    // Crash report shown on launch
+    crashReport: {
+      title: "クラッシュレポート",
+      message: "前回の使用以降に m3u8 MCP が {count} 回クラッシュしました。詳細は ~/.m3u8-mcp/crashes にあります。",
+    },
   },
 } as const;
 